@@ -0,0 +1,24 @@
+from os import PathLike
+from typing import Any, Callable, Optional
+
+EventCallback = Callable[[dict[str, Any]], None]
+
+class MarsError(RuntimeError): ...
+
+class MarsConfig:
+    def __init__(self, **fields: Any) -> None: ...
+    @staticmethod
+    def from_file(path: str | PathLike[str]) -> MarsConfig: ...
+    def replace(self, **fields: Any) -> MarsConfig: ...
+    def to_dict(self) -> dict[str, Any]: ...
+
+class Mars:
+    def __init__(
+        self, config: Optional[MarsConfig] = None, model: Optional[str] = None
+    ) -> None: ...
+    def run(
+        self, query: str, on_event: Optional[EventCallback] = None
+    ) -> dict[str, Any]: ...
+    async def run_async(
+        self, query: str, on_event: Optional[EventCallback] = None
+    ) -> dict[str, Any]: ...
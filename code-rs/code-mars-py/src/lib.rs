@@ -0,0 +1,273 @@
+//! Python bindings for code-mars.
+//!
+//! Builds the `code_mars` extension module with maturin (`maturin develop`
+//! in this directory), so MARS experiments can be driven from Python
+//! notebooks while the runs themselves stay in Rust:
+//!
+//! - `MarsConfig(**fields)` and `MarsConfig.from_file(path)` build a
+//!   [`MarsConfig`]; keyword fields take the same names and values as the
+//!   config files, over the defaults. `config.replace(**fields)` returns a
+//!   modified copy, for parameter sweeps.
+//! - `Mars(config, model="openai/gpt-4o")` wraps a [`MarsCoordinator`].
+//!   Without `model`, the config's `provider_routing` picks the providers.
+//! - `mars.run(query, on_event=None)` blocks until the run is done, with the
+//!   GIL released; `await mars.run_async(query, on_event=None)` runs on a
+//!   shared Tokio runtime. Both return the [`MarsOutput`] as a dict.
+//!
+//! `on_event` is called with each [`MarsEventRecord`] as a dict, in order,
+//! while the run is in progress. Exceptions it raises are reported through
+//! `sys.unraisablehook` and do not stop the run. Runs on the same `Mars`
+//! take turns, so strategies learned in one run carry over to the next.
+
+use code_mars::{
+    MarsConfig, MarsCoordinator, MarsEventRecord, MarsOutput, MultiProviderRouter, PricingTable,
+    ProviderSpec, provider_from_spec,
+};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pythonize::{depythonize, pythonize};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+create_exception!(
+    code_mars,
+    MarsError,
+    PyRuntimeError,
+    "A MARS run or its configuration failed."
+);
+
+/// Events buffered before the run waits for the callback
+const EVENT_CAPACITY: usize = 100;
+
+/// Configuration for MARS runs
+#[pyclass(name = "MarsConfig", module = "code_mars", frozen)]
+#[derive(Clone)]
+struct PyMarsConfig {
+    inner: MarsConfig,
+}
+
+#[pymethods]
+impl PyMarsConfig {
+    /// The defaults with `fields` set, named as in config files
+    #[new]
+    #[pyo3(signature = (**fields))]
+    fn new(fields: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        Self {
+            inner: MarsConfig::default(),
+        }
+        .replace(fields)
+    }
+
+    /// Load a `.toml`, `.yaml` or `.json` config file over the defaults
+    #[staticmethod]
+    fn from_file(path: PathBuf) -> PyResult<Self> {
+        let inner = MarsConfig::from_file(path).map_err(to_py_err)?;
+        Ok(Self { inner })
+    }
+
+    /// A copy with `fields` set
+    #[pyo3(signature = (**fields))]
+    fn replace(&self, fields: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let Some(fields) = fields else {
+            return Ok(self.clone());
+        };
+        let fields: Map<String, Value> = depythonize(fields.as_any())?;
+        let inner = config_with_fields(&self.inner, fields).map_err(PyValueError::new_err)?;
+        Ok(Self { inner })
+    }
+
+    /// Every field as a dict
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.inner)?)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "MarsConfig(num_agents={}, max_iterations={}, enable_aggregation={})",
+            self.inner.num_agents, self.inner.max_iterations, self.inner.enable_aggregation
+        )
+    }
+}
+
+/// `config` with top-level `fields` replaced
+///
+/// Setting `num_agents` without `temperatures` resizes the temperatures to
+/// match, as [`MarsConfig::with_num_agents`] does.
+fn config_with_fields(
+    config: &MarsConfig,
+    fields: Map<String, Value>,
+) -> Result<MarsConfig, String> {
+    let resize_temperatures =
+        fields.contains_key("num_agents") && !fields.contains_key("temperatures");
+    let mut value = serde_json::to_value(config).map_err(|e| e.to_string())?;
+    if let Value::Object(current) = &mut value {
+        current.extend(fields);
+    }
+    let config: MarsConfig =
+        serde_json::from_value(value).map_err(|e| format!("Invalid MARS config: {e}"))?;
+    Ok(if resize_temperatures {
+        let num_agents = config.num_agents;
+        config.with_num_agents(num_agents)
+    } else {
+        config
+    })
+}
+
+/// Runs MARS on queries
+#[pyclass(name = "Mars", module = "code_mars", frozen)]
+struct PyMars {
+    coordinator: Arc<Mutex<MarsCoordinator>>,
+}
+
+#[pymethods]
+impl PyMars {
+    /// Run `config` (the defaults if `None`) on `model`, given as
+    /// `provider/model`, or else on the config's provider routing
+    #[new]
+    #[pyo3(signature = (config = None, model = None))]
+    fn new(config: Option<PyMarsConfig>, model: Option<&str>) -> PyResult<Self> {
+        let config = config.map(|config| config.inner).unwrap_or_default();
+        let provider = match (model, &config.provider_routing) {
+            (Some(model), _) => {
+                provider_from_spec(&ProviderSpec::from_model_id(model).map_err(to_py_err)?)
+            }
+            (None, Some(routing)) => {
+                let pricing = PricingTable::default().with_overrides(&config.model_pricing);
+                Arc::new(MultiProviderRouter::from_config(routing, &pricing).map_err(to_py_err)?)
+            }
+            (None, None) => {
+                return Err(PyValueError::new_err(
+                    "no provider: pass model=\"provider/model\" or set provider_routing",
+                ));
+            }
+        };
+        let coordinator = MarsCoordinator::with_provider(config, provider);
+        Ok(Self {
+            coordinator: Arc::new(Mutex::new(coordinator)),
+        })
+    }
+
+    /// Answer `query`, blocking until the run is done
+    #[pyo3(signature = (query, on_event = None))]
+    fn run(&self, py: Python<'_>, query: String, on_event: Option<PyObject>) -> PyResult<PyObject> {
+        let coordinator = Arc::clone(&self.coordinator);
+        let output = py.allow_threads(|| {
+            pyo3_async_runtimes::tokio::get_runtime().block_on(run(coordinator, query, on_event))
+        });
+        output_to_py(py, output)
+    }
+
+    /// Answer `query` without blocking the event loop
+    #[pyo3(signature = (query, on_event = None))]
+    fn run_async<'py>(
+        &self,
+        py: Python<'py>,
+        query: String,
+        on_event: Option<PyObject>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let coordinator = Arc::clone(&self.coordinator);
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let output = run(coordinator, query, on_event).await;
+            Python::with_gil(|py| output_to_py(py, output))
+        })
+    }
+}
+
+/// Run `query`, passing each event to `on_event` while the run is in progress
+async fn run(
+    coordinator: Arc<Mutex<MarsCoordinator>>,
+    query: String,
+    on_event: Option<PyObject>,
+) -> code_mars::Result<MarsOutput> {
+    let mut coordinator = coordinator.lock_owned().await;
+    let Some(callback) = on_event else {
+        return coordinator.run(&query).await;
+    };
+
+    let mut events = coordinator.subscribe_events(EVENT_CAPACITY);
+    let output = {
+        let run = coordinator.run(&query);
+        tokio::pin!(run);
+        loop {
+            tokio::select! {
+                output = &mut run => break output,
+                Some(record) = events.recv() => deliver(&callback, &record),
+            }
+        }
+    };
+    // All of a run's events are published before it returns
+    while let Ok(record) = events.try_recv() {
+        deliver(&callback, &record);
+    }
+    output
+}
+
+/// Call `callback` with `record` as a dict
+fn deliver(callback: &PyObject, record: &MarsEventRecord) {
+    Python::with_gil(|py| {
+        let called = pythonize(py, record)
+            .map_err(PyErr::from)
+            .and_then(|event| callback.call1(py, (event,)));
+        if let Err(e) = called {
+            e.write_unraisable(py, Some(callback.bind(py)));
+        }
+    });
+}
+
+fn output_to_py(py: Python<'_>, output: code_mars::Result<MarsOutput>) -> PyResult<PyObject> {
+    let output = output.map_err(to_py_err)?;
+    Ok(pythonize(py, &output)?.unbind())
+}
+
+fn to_py_err(error: code_mars::MarsError) -> PyErr {
+    MarsError::new_err(error.to_string())
+}
+
+/// The `code_mars` Python module
+#[pymodule]
+#[pyo3(name = "code_mars")]
+fn code_mars_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyMarsConfig>()?;
+    module.add_class::<PyMars>()?;
+    module.add("MarsError", module.py().get_type::<MarsError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fields(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(fields) => fields,
+            _ => Map::new(),
+        }
+    }
+
+    #[test]
+    fn test_fields_replace_config_values() {
+        let config = config_with_fields(
+            &MarsConfig::default(),
+            fields(json!({"num_agents": 5, "max_iterations": 1})),
+        )
+        .unwrap();
+        assert_eq!(config.num_agents, 5);
+        assert_eq!(config.temperatures.len(), 5);
+        assert_eq!(config.max_iterations, 1);
+
+        let config = config_with_fields(
+            &config,
+            fields(json!({"num_agents": 2, "temperatures": [0.1, 0.9]})),
+        )
+        .unwrap();
+        assert_eq!(config.temperatures, [0.1, 0.9]);
+
+        let err = config_with_fields(&config, fields(json!({"num_agents": "many"}))).unwrap_err();
+        assert!(err.starts_with("Invalid MARS config"));
+    }
+}
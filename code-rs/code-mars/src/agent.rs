@@ -1,8 +1,9 @@
 use crate::Result;
 /// Individual agents that explore solution paths with different temperatures.
+use crate::model_router::{CompletionResponse, ModelClientRouter};
 use crate::prompts;
-use crate::types::Solution;
-use futures::StreamExt;
+use crate::types::{ReasoningStep, Solution};
+use tracing::Instrument;
 use uuid::Uuid;
 
 /// An individual agent in the MARS system
@@ -12,6 +13,10 @@ pub struct Agent {
     pub id: String,
     /// Temperature setting for exploration (0.0 = deterministic, higher = more diverse)
     pub temperature: f32,
+    /// Strategies from the strategy network to include in prompts
+    pub strategies: Option<String>,
+    /// Prompt overrides for generation, improvement and strategy extraction
+    pub prompts: prompts::PromptSet,
 }
 
 impl Agent {
@@ -20,123 +25,53 @@ impl Agent {
         Self {
             id: format!("agent-{}", Uuid::new_v4()),
             temperature,
+            strategies: None,
+            prompts: prompts::PromptSet::default(),
         }
     }
 
+    /// Include strategies discovered by other agents in this agent's prompts
+    pub fn with_strategies(mut self, strategies: impl Into<String>) -> Self {
+        self.strategies = Some(strategies.into());
+        self
+    }
+
+    /// Use `prompts` in place of the built-in prompts
+    pub fn with_prompts(mut self, prompts: prompts::PromptSet) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
     /// Generate an initial solution given a query with ModelClient
     ///
-    /// This method calls the LLM with appropriate prompting to generate
-    /// a reasoning chain and answer to the given query.
+    /// Convenience wrapper around [`Agent::generate_solution`] for callers
+    /// holding a `code_core::ModelClient`.
     pub async fn generate_solution_with_client(
         &self,
         query: &str,
         use_thinking_tags: bool,
         client: &code_core::ModelClient,
     ) -> Result<Solution> {
-        // Build the system and user prompts
-        let system_prompt = if use_thinking_tags {
-            prompts::MARS_SYSTEM_PROMPT_WITH_THINKING.to_string()
-        } else {
-            prompts::MARS_SYSTEM_PROMPT.to_string()
-        };
-
-        let user_prompt = format!("{}\n\n{}", prompts::MARS_REASONING_PROMPT, query);
-
-        // Build prompt for ModelClient
-        let mut prompt = code_core::Prompt::default();
-        prompt.input = vec![code_core::ResponseItem::Message {
-            id: None,
-            role: "user".to_string(),
-            content: vec![code_core::ContentItem::InputText { text: user_prompt }],
-        }];
-        prompt.base_instructions_override = Some(system_prompt);
-        prompt.set_log_tag(&format!("mars_agent_{}", self.id));
-
-        // Stream the response from LLM
-        let mut stream = client.stream(&prompt).await?;
-        let mut full_response = String::new();
-        let mut token_count = 0;
-
-        while let Some(event) = stream.next().await {
-            match event? {
-                code_core::ResponseEvent::OutputTextDelta { delta, .. } => {
-                    full_response.push_str(&delta);
-                }
-                code_core::ResponseEvent::Completed { token_usage, .. } => {
-                    if let Some(usage) = token_usage {
-                        token_count = usage.total_tokens as usize;
-                    }
-                    break;
-                }
-                _ => {}
-            }
-        }
-
-        let (reasoning, answer) = self.parse_response(&full_response).await?;
-
-        let solution = Solution::new(
-            self.id.clone(),
-            reasoning,
-            answer,
-            self.temperature,
-            token_count,
-        );
-
-        Ok(solution)
+        let provider = ModelClientRouter::new(client.clone());
+        self.generate_solution(query, use_thinking_tags, &provider)
+            .await
     }
 
     /// Verify another agent's solution with ModelClient
     ///
-    /// This method evaluates if a solution is mathematically correct,
-    /// complete, and rigorous.
+    /// Convenience wrapper around [`Agent::verify_solution`].
     pub async fn verify_solution_with_client(
         &self,
         solution: &Solution,
         client: &code_core::ModelClient,
     ) -> Result<f32> {
-        let verification_prompt = format!(
-            "{}\n\nSolution to verify:\n{}\n\nAnswer: {}",
-            prompts::VERIFICATION_SYSTEM_PROMPT,
-            solution.reasoning,
-            solution.answer
-        );
-
-        // Build prompt for ModelClient
-        let mut prompt = code_core::Prompt::default();
-        prompt.input = vec![code_core::ResponseItem::Message {
-            id: None,
-            role: "user".to_string(),
-            content: vec![code_core::ContentItem::InputText {
-                text: verification_prompt,
-            }],
-        }];
-        prompt.set_log_tag(&format!("mars_verifier_{}", self.id));
-
-        // Stream the verification response
-        let mut stream = client.stream(&prompt).await?;
-        let mut verification_response = String::new();
-
-        while let Some(event) = stream.next().await {
-            match event? {
-                code_core::ResponseEvent::OutputTextDelta { delta, .. } => {
-                    verification_response.push_str(&delta);
-                }
-                code_core::ResponseEvent::Completed { .. } => {
-                    break;
-                }
-                _ => {}
-            }
-        }
-
-        // Parse verification score from response
-        let score = Self::extract_verification_score(&verification_response)?;
-        Ok(score)
+        let provider = ModelClientRouter::new(client.clone());
+        self.verify_solution(solution, &provider).await
     }
 
     /// Improve an existing solution based on feedback with ModelClient
     ///
-    /// This method takes unverified solutions and attempts to improve them
-    /// based on verification feedback.
+    /// Convenience wrapper around [`Agent::improve_solution`].
     pub async fn improve_solution_with_client(
         &self,
         solution: &Solution,
@@ -144,149 +79,175 @@ impl Agent {
         use_thinking_tags: bool,
         client: &code_core::ModelClient,
     ) -> Result<Solution> {
-        let system_prompt = if use_thinking_tags {
-            prompts::MARS_SYSTEM_PROMPT_WITH_THINKING.to_string()
-        } else {
-            prompts::MARS_SYSTEM_PROMPT.to_string()
-        };
-
-        let improvement_prompt = format!(
-            "{}\n\nOriginal solution:\nReasoning: {}\nAnswer: {}\n\nFeedback: {}\n\nPlease improve the solution:",
-            prompts::IMPROVEMENT_PROMPT,
-            solution.reasoning,
-            solution.answer,
-            feedback
-        );
-
-        // Build prompt for ModelClient
-        let mut prompt = code_core::Prompt::default();
-        prompt.input = vec![code_core::ResponseItem::Message {
-            id: None,
-            role: "user".to_string(),
-            content: vec![code_core::ContentItem::InputText {
-                text: improvement_prompt,
-            }],
-        }];
-        prompt.base_instructions_override = Some(system_prompt);
-        prompt.set_log_tag(&format!("mars_improve_{}", self.id));
-
-        // Stream the improved response
-        let mut stream = client.stream(&prompt).await?;
-        let mut improved_response = String::new();
-
-        while let Some(event) = stream.next().await {
-            match event? {
-                code_core::ResponseEvent::OutputTextDelta { delta, .. } => {
-                    improved_response.push_str(&delta);
-                }
-                code_core::ResponseEvent::Completed { .. } => {
-                    break;
-                }
-                _ => {}
-            }
-        }
-
-        let (new_reasoning, new_answer) = self.parse_response(&improved_response).await?;
-
-        let mut improved = Solution::new(
-            self.id.clone(),
-            new_reasoning,
-            new_answer,
-            self.temperature,
-            solution.token_count,
-        );
-
-        improved.phase = crate::types::GenerationPhase::Improved;
-
-        Ok(improved)
+        let provider = ModelClientRouter::new(client.clone());
+        self.improve_solution(solution, feedback, use_thinking_tags, &provider)
+            .await
     }
 
     /// Extract strategies from a solution with ModelClient
     ///
-    /// This identifies key techniques and approaches that worked well
-    /// so other agents can benefit from them.
+    /// Convenience wrapper around [`Agent::extract_strategies`].
     pub async fn extract_strategies_with_client(
         &self,
         solution: &Solution,
         client: &code_core::ModelClient,
     ) -> Result<Vec<String>> {
-        let extraction_prompt = format!(
-            "{}\n\nSolution:\n{}",
-            prompts::STRATEGY_EXTRACTION_PROMPT,
-            solution.reasoning
-        );
+        let provider = ModelClientRouter::new(client.clone());
+        self.extract_strategies(solution, &provider).await
+    }
 
-        // Build prompt for ModelClient
-        let mut prompt = code_core::Prompt::default();
-        prompt.input = vec![code_core::ResponseItem::Message {
-            id: None,
-            role: "user".to_string(),
-            content: vec![code_core::ContentItem::InputText {
-                text: extraction_prompt,
-            }],
-        }];
-        prompt.set_log_tag(&format!("mars_strategy_{}", self.id));
-
-        // Stream the strategy extraction response
-        let mut stream = client.stream(&prompt).await?;
-        let mut response = String::new();
-
-        while let Some(event) = stream.next().await {
-            match event? {
-                code_core::ResponseEvent::OutputTextDelta { delta, .. } => {
-                    response.push_str(&delta);
-                }
-                code_core::ResponseEvent::Completed { .. } => {
-                    break;
-                }
-                _ => {}
-            }
-        }
+    /// Generate an initial solution with any LLM provider
+    #[deprecated(note = "renamed to `Agent::generate_solution`")]
+    pub async fn generate_solution_with_provider(
+        &self,
+        query: &str,
+        use_thinking_tags: bool,
+        provider: &dyn crate::LLMProvider,
+    ) -> Result<Solution> {
+        self.generate_solution(query, use_thinking_tags, provider)
+            .await
+    }
 
-        // Parse strategies from response (numbered list format)
-        let strategies = Self::parse_strategies(&response);
-        Ok(strategies)
+    /// Verify another agent's solution with any LLM provider
+    #[deprecated(note = "renamed to `Agent::verify_solution`")]
+    pub async fn verify_solution_with_provider(
+        &self,
+        solution: &Solution,
+        provider: &dyn crate::LLMProvider,
+    ) -> Result<f32> {
+        self.verify_solution(solution, provider).await
+    }
+
+    /// Improve an existing solution based on feedback with any LLM provider
+    #[deprecated(note = "renamed to `Agent::improve_solution`")]
+    pub async fn improve_solution_with_provider(
+        &self,
+        solution: &Solution,
+        feedback: &str,
+        use_thinking_tags: bool,
+        provider: &dyn crate::LLMProvider,
+    ) -> Result<Solution> {
+        self.improve_solution(solution, feedback, use_thinking_tags, provider)
+            .await
+    }
+
+    /// Extract strategies from a solution with any LLM provider
+    #[deprecated(note = "renamed to `Agent::extract_strategies`")]
+    pub async fn extract_strategies_with_provider(
+        &self,
+        solution: &Solution,
+        provider: &dyn crate::LLMProvider,
+    ) -> Result<Vec<String>> {
+        self.extract_strategies(solution, provider).await
     }
 
     /// Generate an initial solution given a query with any LLM provider
     ///
-    /// This method works with any provider implementing the LLMProvider trait,
-    /// enabling multi-model agent generation.
-    pub async fn generate_solution_with_provider(
+    /// This method calls the LLM with appropriate prompting to generate
+    /// a reasoning chain and answer to the given query. It works with any
+    /// provider implementing the LLMProvider trait, enabling multi-model
+    /// agent generation.
+    pub async fn generate_solution(
         &self,
         query: &str,
         use_thinking_tags: bool,
         provider: &dyn crate::LLMProvider,
     ) -> Result<Solution> {
-        // Build the system and user prompts
-        let system_prompt = if use_thinking_tags {
-            prompts::MARS_SYSTEM_PROMPT_WITH_THINKING.to_string()
-        } else {
-            prompts::MARS_SYSTEM_PROMPT.to_string()
-        };
-
-        let user_prompt = format!("{}\n\n{}", prompts::MARS_REASONING_PROMPT, query);
+        let (system_prompt, user_prompt) = self.generation_prompts(query, use_thinking_tags);
 
         // Call provider
         let full_response = provider
-            .complete(&user_prompt, Some(&system_prompt))
+            .complete_with_temperature(&user_prompt, Some(&system_prompt), self.temperature)
+            .instrument(self.call_span("generate", None))
             .await?;
 
-        let (reasoning, answer) = self.parse_response(&full_response).await?;
+        self.solution_from_response(
+            &full_response,
+            system_prompt.len() + user_prompt.len(),
+            provider,
+        )
+        .await
+    }
 
-        let solution = Solution::new(
+    /// Generate `n` solutions to a query in one batched provider call
+    ///
+    /// Uses [`LLMProvider::complete_n`](crate::LLMProvider::complete_n), so
+    /// providers with an `n` parameter answer with a single request. Samples
+    /// that fail or cannot be parsed are dropped; an error is returned only
+    /// when none succeed.
+    pub async fn generate_solutions(
+        &self,
+        query: &str,
+        use_thinking_tags: bool,
+        provider: &dyn crate::LLMProvider,
+        n: usize,
+    ) -> Result<Vec<Solution>> {
+        let (system_prompt, user_prompt) = self.generation_prompts(query, use_thinking_tags);
+        let responses = provider
+            .complete_n(
+                &user_prompt,
+                Some(&system_prompt),
+                n,
+                Some(self.temperature),
+            )
+            .instrument(self.call_span("generate_n", None))
+            .await?;
+
+        let mut solutions = Vec::with_capacity(responses.len());
+        let mut last_error = None;
+        for response in &responses {
+            match self
+                .solution_from_response(response, system_prompt.len() + user_prompt.len(), provider)
+                .await
+            {
+                Ok(solution) => solutions.push(solution),
+                Err(e) => {
+                    tracing::warn!("Discarding unparsable sample: {e}");
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) if solutions.is_empty() => Err(e),
+            _ => Ok(solutions),
+        }
+    }
+
+    /// System and user prompts for generating a solution
+    fn generation_prompts(&self, query: &str, use_thinking_tags: bool) -> (String, String) {
+        let system_prompt = self.prompts.system_prompt(use_thinking_tags).to_string();
+        let user_prompt = self.with_strategy_context(self.prompts.reasoning_prompt(query));
+        (system_prompt, user_prompt)
+    }
+
+    /// Build a solution from a generation response
+    async fn solution_from_response(
+        &self,
+        response: &CompletionResponse,
+        prompt_len: usize,
+        provider: &dyn crate::LLMProvider,
+    ) -> Result<Solution> {
+        let (reasoning, answer) = self.parse_response(&response.text).await?;
+
+        let mut solution = Solution::new(
             self.id.clone(),
             reasoning,
             answer,
             self.temperature,
-            full_response.len() / 4, // Rough token estimate
+            0,
         );
+        solution.record_completion(response, prompt_len);
+        solution.steps = Self::parse_steps(&solution.reasoning);
+        solution.record_provider(provider, Some(self.temperature));
 
         Ok(solution)
     }
 
     /// Verify another agent's solution with any LLM provider
-    pub async fn verify_solution_with_provider(
+    ///
+    /// Evaluates if a solution is mathematically correct, complete, and
+    /// rigorous, returning a score between 0.0 and 1.0.
+    pub async fn verify_solution(
         &self,
         solution: &Solution,
         provider: &dyn crate::LLMProvider,
@@ -300,75 +261,102 @@ impl Agent {
 
         let verification_response = provider
             .complete(&verification_prompt, None)
+            .instrument(self.call_span("verify", Some(&solution.id)))
             .await?;
 
         // Parse verification score from response
-        let score = Self::extract_verification_score(&verification_response)?;
+        let score = Self::extract_verification_score(&verification_response.text)?;
         Ok(score)
     }
 
     /// Improve an existing solution based on feedback with any LLM provider
-    pub async fn improve_solution_with_provider(
+    ///
+    /// Takes an unverified solution and attempts to improve it based on
+    /// verification feedback.
+    pub async fn improve_solution(
         &self,
         solution: &Solution,
         feedback: &str,
         use_thinking_tags: bool,
         provider: &dyn crate::LLMProvider,
     ) -> Result<Solution> {
-        let system_prompt = if use_thinking_tags {
-            prompts::MARS_SYSTEM_PROMPT_WITH_THINKING.to_string()
-        } else {
-            prompts::MARS_SYSTEM_PROMPT.to_string()
-        };
+        let system_prompt = self.prompts.system_prompt(use_thinking_tags).to_string();
 
-        let improvement_prompt = format!(
-            "{}\n\nOriginal solution:\nReasoning: {}\nAnswer: {}\n\nFeedback: {}\n\nPlease improve the solution:",
-            prompts::IMPROVEMENT_PROMPT,
-            solution.reasoning,
-            solution.answer,
-            feedback
-        );
+        let improvement_prompt = self
+            .with_strategy_context(self.prompts.improvement_prompt(solution, feedback))
+            + "\n\nPlease improve the solution:";
 
         let improved_response = provider
             .complete(&improvement_prompt, Some(&system_prompt))
+            .instrument(self.call_span("improve", Some(&solution.id)))
             .await?;
 
-        let (new_reasoning, new_answer) = self.parse_response(&improved_response).await?;
+        let (new_reasoning, new_answer) = self.parse_response(&improved_response.text).await?;
 
         let mut improved = Solution::new(
             self.id.clone(),
             new_reasoning,
             new_answer,
             self.temperature,
-            solution.token_count,
+            0,
+        );
+        improved.record_completion(
+            &improved_response,
+            system_prompt.len() + improvement_prompt.len(),
         );
 
         improved.phase = crate::types::GenerationPhase::Improved;
+        improved.steps = Self::parse_steps(&improved.reasoning);
+        improved.record_provider(provider, None);
+        improved.parent_ids = vec![solution.id.clone()];
 
         Ok(improved)
     }
 
     /// Extract strategies from a solution with any LLM provider
-    pub async fn extract_strategies_with_provider(
+    ///
+    /// Identifies key techniques and approaches that worked well so other
+    /// agents can benefit from them.
+    pub async fn extract_strategies(
         &self,
         solution: &Solution,
         provider: &dyn crate::LLMProvider,
     ) -> Result<Vec<String>> {
-        let extraction_prompt = format!(
-            "{}\n\nSolution:\n{}",
-            prompts::STRATEGY_EXTRACTION_PROMPT,
-            solution.reasoning
-        );
+        let extraction_prompt = self.prompts.strategy_extraction_prompt(solution);
 
         let response = provider
             .complete(&extraction_prompt, None)
+            .instrument(self.call_span("extract_strategies", Some(&solution.id)))
             .await?;
 
         // Parse strategies from response (numbered list format)
-        let strategies = Self::parse_strategies(&response);
+        let strategies = Self::parse_strategies(&response.text);
         Ok(strategies)
     }
 
+    /// Span of one provider call this agent makes, about `solution_id` if given
+    fn call_span(&self, operation: &'static str, solution_id: Option<&str>) -> tracing::Span {
+        tracing::info_span!(
+            "agent_call",
+            operation,
+            agent_id = %self.id,
+            temperature = self.temperature,
+            solution_id
+        )
+    }
+
+    /// Append this agent's strategies, if any, to a prompt
+    fn with_strategy_context(&self, prompt: String) -> String {
+        match &self.strategies {
+            Some(strategies) => format!(
+                "{prompt}\n\n{}\n\n{}",
+                prompts::STRATEGY_GUIDANCE_PROMPT,
+                strategies.trim_end()
+            ),
+            None => prompt,
+        }
+    }
+
     /// Parse a response into reasoning and answer components
     async fn parse_response(&self, response: &str) -> Result<(String, String)> {
         // Extract reasoning from <think> tags if present
@@ -411,6 +399,85 @@ impl Agent {
         Ok(score)
     }
 
+    /// Parse structured reasoning steps from a reasoning chain
+    ///
+    /// Recognizes blocks of the form:
+    ///
+    /// ```text
+    /// Step 1: <claim>
+    /// Justification: <why the claim holds>
+    /// Result: <intermediate result>
+    /// ```
+    ///
+    /// Lines that follow a step header without a recognized label are treated
+    /// as a continuation of the justification. Returns an empty vector when the
+    /// reasoning is not structured as steps.
+    pub fn parse_steps(reasoning: &str) -> Vec<ReasoningStep> {
+        let mut steps: Vec<ReasoningStep> = Vec::new();
+
+        for line in reasoning.lines() {
+            let trimmed = line.trim().trim_start_matches(['*', '#', '-']).trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(claim) = Self::strip_step_header(trimmed) {
+                steps.push(ReasoningStep {
+                    index: steps.len() + 1,
+                    claim: claim.to_string(),
+                    justification: String::new(),
+                    intermediate_result: None,
+                });
+                continue;
+            }
+
+            let Some(step) = steps.last_mut() else {
+                continue;
+            };
+
+            if let Some(justification) = Self::strip_label(trimmed, "justification") {
+                step.justification = justification.to_string();
+            } else if let Some(result) = Self::strip_label(trimmed, "result") {
+                step.intermediate_result = Some(result.to_string());
+            } else if step.justification.is_empty() {
+                step.justification = trimmed.to_string();
+            } else {
+                step.justification.push('\n');
+                step.justification.push_str(trimmed);
+            }
+        }
+
+        steps
+    }
+
+    /// Strip a `Step N:` header, returning the claim that follows it
+    fn strip_step_header(line: &str) -> Option<&str> {
+        let rest = Self::strip_label_prefix(line, "step")?;
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return None;
+        }
+        let rest = rest[digits..].trim_start_matches(['*', ':', '.', ')']);
+        Some(rest.trim())
+    }
+
+    /// Strip a `Label:` prefix (case-insensitive), returning the remaining text
+    fn strip_label<'a>(line: &'a str, label: &str) -> Option<&'a str> {
+        let rest = Self::strip_label_prefix(line, label)?;
+        let rest = rest.trim_start_matches('*').strip_prefix(':')?;
+        Some(rest.trim_start_matches('*').trim())
+    }
+
+    /// Case-insensitively strip `label` and any following whitespace from `line`
+    fn strip_label_prefix<'a>(line: &'a str, label: &str) -> Option<&'a str> {
+        let head = line.get(..label.len())?;
+        if head.eq_ignore_ascii_case(label) {
+            Some(line[label.len()..].trim_start())
+        } else {
+            None
+        }
+    }
+
     /// Parse strategies from response
     fn parse_strategies(response: &str) -> Vec<String> {
         let mut strategies = Vec::new();
@@ -454,9 +521,68 @@ mod tests {
         assert!(!agent.id.is_empty());
     }
 
+    #[test]
+    fn test_strategy_context() {
+        let plain = Agent::new(0.7);
+        assert_eq!(
+            plain.with_strategy_context("Solve x".to_string()),
+            "Solve x"
+        );
+
+        let guided = Agent::new(0.7).with_strategies("1. Work backwards");
+        let prompt = guided.with_strategy_context("Solve x".to_string());
+        assert!(prompt.starts_with("Solve x\n\n"));
+        assert!(prompt.contains(prompts::STRATEGY_GUIDANCE_PROMPT));
+        assert!(prompt.ends_with("1. Work backwards"));
+    }
+
+    #[test]
+    fn test_custom_prompts() {
+        let default_agent = Agent::new(0.7);
+        let (system, user) = default_agent.generation_prompts("What is 2+2?", false);
+        assert_eq!(system, prompts::MARS_SYSTEM_PROMPT);
+        assert_eq!(
+            user,
+            format!("{}\n\nWhat is 2+2?", prompts::MARS_REASONING_PROMPT)
+        );
+
+        let agent = Agent::new(0.7).with_prompts(prompts::PromptSet {
+            system: Some("You are an editor.".to_string()),
+            reasoning: Some("Edit this text: {query}".to_string()),
+            ..Default::default()
+        });
+        let (system, user) = agent.generation_prompts("Hello wrold", true);
+        assert_eq!(system, "You are an editor.");
+        assert_eq!(user, "Edit this text: Hello wrold");
+    }
+
     #[tokio::test]
     async fn test_agent_default() {
         let agent = Agent::default();
         assert_eq!(agent.temperature, 0.5);
     }
+
+    #[test]
+    fn test_parse_steps() {
+        let reasoning = "Step 1: The sum is even\n\
+                         Justification: Both addends are even\n\
+                         Result: 2 + 4 = 6\n\
+                         Step 2: The answer is 6\n\
+                         It follows directly from step 1.";
+
+        let steps = Agent::parse_steps(reasoning);
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].index, 1);
+        assert_eq!(steps[0].claim, "The sum is even");
+        assert_eq!(steps[0].justification, "Both addends are even");
+        assert_eq!(steps[0].intermediate_result.as_deref(), Some("2 + 4 = 6"));
+        assert_eq!(steps[1].claim, "The answer is 6");
+        assert_eq!(steps[1].justification, "It follows directly from step 1.");
+        assert!(steps[1].intermediate_result.is_none());
+    }
+
+    #[test]
+    fn test_parse_steps_unstructured() {
+        assert!(Agent::parse_steps("Just some free-form reasoning.").is_empty());
+    }
 }
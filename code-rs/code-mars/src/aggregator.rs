@@ -1,12 +1,35 @@
 use crate::Result;
 /// RSA-inspired aggregation for refining solutions.
-use crate::types::{GenerationPhase, Solution};
+use crate::prompts::{AggregationPrompts, render_template};
+use crate::types::{GenerationPhase, ParentSelection, Solution};
+use rand::Rng;
 use rand::prelude::IndexedRandom;
 use std::collections::HashSet;
 
 /// Aggregator that combines multiple solutions to produce refined ones
 pub struct Aggregator;
 
+/// Parent-selection settings for RSA aggregation
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RsaSelectionConfig {
+    /// Strategy used to pick parents
+    pub strategy: ParentSelection,
+    /// How much similarity to an already-chosen parent lowers a candidate's
+    /// score (score-based strategies only)
+    pub diversity_penalty: f32,
+}
+
+impl RsaSelectionConfig {
+    /// Whether selection needs pairwise similarities between solutions
+    fn uses_similarity(self) -> bool {
+        match self.strategy {
+            ParentSelection::Random => false,
+            ParentSelection::DiversityAware => true,
+            _ => self.diversity_penalty > 0.0,
+        }
+    }
+}
+
 impl Aggregator {
     /// Run MOA (Mixture of Agents) aggregation using any LLM provider
     ///
@@ -33,6 +56,34 @@ impl Aggregator {
         Ok(vec![solution])
     }
 
+    /// Run multi-layer MOA aggregation
+    ///
+    /// Each entry of `layer_providers` is one proposer layer; later layers
+    /// refine the previous layer's outputs. `aggregator` critiques and
+    /// synthesizes the final layer.
+    pub async fn aggregate_layered_moa(
+        query: &str,
+        system_prompt: &str,
+        num_completions: usize,
+        fallback_enabled: bool,
+        layer_providers: &[&dyn crate::LLMProvider],
+        aggregator: &dyn crate::LLMProvider,
+        prompts: &AggregationPrompts,
+    ) -> Result<Vec<Solution>> {
+        let (solution, _metadata) = crate::moa::MoaAggregator::run_layered_moa(
+            query,
+            system_prompt,
+            num_completions,
+            fallback_enabled,
+            layer_providers,
+            aggregator,
+            prompts,
+        )
+        .await?;
+
+        Ok(vec![solution])
+    }
+
     /// Run MCTS aggregation using any LLM provider
     ///
     /// This process:
@@ -46,6 +97,34 @@ impl Aggregator {
         config: crate::mcts::MCTSConfig,
         provider: &dyn crate::LLMProvider,
     ) -> Result<Vec<Solution>> {
+        let (solutions, _tree) = Self::aggregate_mcts_with_tree(
+            query,
+            system_prompt,
+            config,
+            None,
+            None,
+            provider,
+            None,
+        )
+        .await?;
+        Ok(solutions)
+    }
+
+    /// Run MCTS aggregation and also return the search tree for inspection
+    ///
+    /// Simulated states are scored by `value_function`, or rated by
+    /// `provider` when it is `None`. `action_generator` replaces free-form
+    /// replies with a custom action space. With `tx`, node expansions and
+    /// finished simulations are reported as they happen.
+    pub async fn aggregate_mcts_with_tree(
+        query: &str,
+        system_prompt: &str,
+        config: crate::mcts::MCTSConfig,
+        value_function: Option<std::sync::Arc<dyn crate::mcts::ValueFunction>>,
+        action_generator: Option<std::sync::Arc<dyn crate::mcts::ActionGenerator>>,
+        provider: &dyn crate::LLMProvider,
+        tx: Option<&tokio::sync::mpsc::Sender<crate::types::MarsEvent>>,
+    ) -> Result<(Vec<Solution>, crate::mcts::MCTSTree)> {
         let initial_state = crate::mcts::DialogueState::new(
             system_prompt.to_string(),
             vec![],
@@ -53,6 +132,15 @@ impl Aggregator {
         );
 
         let mut mcts = crate::mcts::MCTS::new(config);
+        if let Some(value_function) = value_function {
+            mcts = mcts.with_value_function(value_function);
+        }
+        if let Some(action_generator) = action_generator {
+            mcts = mcts.with_action_generator(action_generator);
+        }
+        if let Some(tx) = tx {
+            mcts = mcts.with_events(tx.clone());
+        }
         let final_state = mcts.search(initial_state, provider).await?;
 
         // Extract final answer from conversation history
@@ -78,20 +166,98 @@ impl Aggregator {
             mcts.completion_tokens,
         );
 
-        Ok(vec![solution])
+        Ok((vec![solution], mcts.tree()))
+    }
+
+    /// Run genetic-algorithm aggregation using any LLM provider
+    ///
+    /// This process:
+    /// 1. Treats the solutions as the initial population
+    /// 2. Breeds children by LLM crossover of tournament-selected parents
+    /// 3. Mutates children with probability `mutation_rate`
+    /// 4. Uses verification score as fitness across generations
+    pub async fn aggregate_genetic(
+        query: &str,
+        solutions: &[Solution],
+        config: &crate::genetic::GeneticConfig,
+        provider: &dyn crate::LLMProvider,
+    ) -> Result<Vec<Solution>> {
+        crate::genetic::GeneticAggregator::evolve(query, solutions, config, provider).await
+    }
+
+    /// Run beam-search aggregation using any LLM provider
+    ///
+    /// This process:
+    /// 1. Scores the solutions and keeps the top `beam_width`
+    /// 2. Expands each beam entry with LLM continuation prompts
+    /// 3. Scores the continuations and prunes back to `beam_width`
+    /// 4. Repeats for `depth` rounds, emitting an event per expansion
+    pub async fn aggregate_beam_search(
+        query: &str,
+        solutions: &[Solution],
+        config: &crate::beam::BeamSearchConfig,
+        provider: &dyn crate::LLMProvider,
+        tx: &tokio::sync::mpsc::Sender<crate::types::MarsEvent>,
+    ) -> Result<Vec<Solution>> {
+        crate::beam::BeamSearchAggregator::search(query, solutions, config, provider, tx).await
+    }
+
+    /// Run best-of-N aggregation using any LLM provider
+    ///
+    /// This process:
+    /// 1. Samples `num_samples` fresh completions across the temperatures
+    /// 2. Scores each with the verifier or a reward prompt
+    /// 3. Keeps only the highest-scoring sample
+    pub async fn aggregate_best_of_n(
+        query: &str,
+        config: &crate::best_of_n::BestOfNConfig,
+        provider: &dyn crate::LLMProvider,
+    ) -> Result<Vec<Solution>> {
+        crate::best_of_n::BestOfNAggregator::select(query, config, provider).await
     }
 
-    /// Run RSA-inspired aggregation on solutions
+    /// Run plan-and-solve aggregation using any LLM provider
+    ///
+    /// This process:
+    /// 1. Picks the `num_sources` highest-scoring solutions
+    /// 2. Asks the LLM for a step-by-step plan combining their best ideas
+    /// 3. Executes the plan with a second call
+    /// 4. Returns the plan and the executed solution, linked by `parent_ids`
+    pub async fn aggregate_plan_and_solve(
+        query: &str,
+        solutions: &[Solution],
+        num_sources: usize,
+        system_prompt: &str,
+        provider: &dyn crate::LLMProvider,
+    ) -> Result<Vec<Solution>> {
+        crate::plan_solve::PlanAndSolveAggregator::run(
+            query,
+            solutions,
+            num_sources,
+            system_prompt,
+            provider,
+        )
+        .await
+    }
+
+    /// Run RSA-inspired aggregation on solutions using any LLM provider
     ///
     /// This process:
     /// 1. Maintains a population of N solutions
-    /// 2. Selects K solutions for refinement
-    /// 3. Repeats T times to iteratively improve
+    /// 2. Selects K solutions for refinement using the `selection` strategy
+    /// 3. Merges the selected parents via the LLM synthesis prompt, or the
+    ///    `rsa_merge` override in `prompts`
+    /// 4. Repeats T times to iteratively improve
+    #[allow(clippy::too_many_arguments)]
     pub async fn aggregate_rsa(
+        query: &str,
         solutions: &[Solution],
         population_size: usize,
         selection_size: usize,
         num_loops: usize,
+        selection: RsaSelectionConfig,
+        prompts: &AggregationPrompts,
+        provider: &dyn crate::LLMProvider,
     ) -> Result<Vec<Solution>> {
         let mut aggregated = Vec::new();
 
@@ -107,23 +273,230 @@ impl Aggregator {
             population.truncate(population_size);
         }
 
+        let mut scores = Vec::with_capacity(population.len());
+        for solution in &population {
+            scores.push(Self::selection_score(query, solution, selection.strategy, provider).await);
+        }
+
+        // Embeddings are only needed when similarity influences selection
+        let mut embeddings = if selection.uses_similarity() {
+            Self::embed_solutions(&population, provider).await
+        } else {
+            None
+        };
+
         // Perform aggregation loops
         for loop_idx in 0..num_loops {
-            let selected = Self::select_diverse_solutions(&population, selection_size)?;
+            let selected = Self::select_parents(
+                &population,
+                &scores,
+                embeddings.as_deref(),
+                selection_size,
+                selection,
+            );
 
             // Create aggregated solution from selected ones
             if !selected.is_empty() {
-                let aggregated_solution = Self::synthesize_solution(&selected, loop_idx)?;
+                let aggregated_solution =
+                    match Self::recombine(query, &selected, loop_idx, prompts, provider).await {
+                        Ok(solution) => solution,
+                        Err(e) => {
+                            tracing::warn!("RSA recombination failed, merging heuristically: {e}");
+                            Self::synthesize_solution(&selected, loop_idx)?
+                        }
+                    };
                 aggregated.push(aggregated_solution);
 
                 // Add back to population for next iteration
-                population.push(aggregated[aggregated.len() - 1].clone());
+                let latest = aggregated[aggregated.len() - 1].clone();
+                scores.push(
+                    Self::selection_score(query, &latest, selection.strategy, provider).await,
+                );
+                if let Some(mut vectors) = embeddings.take()
+                    && let Some(mut latest_vector) =
+                        Self::embed_solutions(std::slice::from_ref(&latest), provider).await
+                {
+                    vectors.append(&mut latest_vector);
+                    embeddings = Some(vectors);
+                }
+                population.push(latest);
             }
         }
 
         Ok(aggregated)
     }
 
+    /// Score used by score-based parent selection
+    ///
+    /// Only score-based strategies pay for verification; a failed verification
+    /// scores 0.0 rather than aborting aggregation.
+    async fn selection_score(
+        query: &str,
+        solution: &Solution,
+        selection: ParentSelection,
+        provider: &dyn crate::LLMProvider,
+    ) -> f32 {
+        match selection {
+            ParentSelection::TopK | ParentSelection::Tournament | ParentSelection::Roulette => {
+                verifier_score(query, solution, provider)
+                    .await
+                    .unwrap_or_else(|e| {
+                        tracing::warn!("Scoring RSA parent failed: {e}");
+                        0.0
+                    })
+            }
+            ParentSelection::Random | ParentSelection::DiversityAware => 0.0,
+        }
+    }
+
+    /// Embed solutions for similarity, or `None` if the provider cannot embed
+    async fn embed_solutions(
+        solutions: &[Solution],
+        provider: &dyn crate::LLMProvider,
+    ) -> Option<Vec<Vec<f32>>> {
+        let texts: Vec<String> = solutions
+            .iter()
+            .map(|s| format!("{}\n{}", s.reasoning, s.answer))
+            .collect();
+        match provider.embed(&texts).await {
+            Ok(vectors) if vectors.len() == texts.len() => Some(vectors),
+            Ok(vectors) => {
+                tracing::warn!(
+                    "Expected {} embeddings, got {}; using lexical similarity",
+                    texts.len(),
+                    vectors.len()
+                );
+                None
+            }
+            Err(e) => {
+                tracing::debug!("Embeddings unavailable, using lexical similarity: {e}");
+                None
+            }
+        }
+    }
+
+    /// Select parents with a score- or diversity-based strategy
+    ///
+    /// `scores` and `embeddings` are parallel to `population`; without
+    /// embeddings, similarity is lexical. Parents are distinct.
+    fn select_parents(
+        population: &[Solution],
+        scores: &[f32],
+        embeddings: Option<&[Vec<f32>]>,
+        num_to_select: usize,
+        selection: RsaSelectionConfig,
+    ) -> Vec<Solution> {
+        if selection.strategy == ParentSelection::Random {
+            return Self::select_diverse_solutions(population, num_to_select).unwrap_or_default();
+        }
+
+        let similarity = |a: usize, b: usize| match embeddings {
+            Some(vectors) => cosine_similarity(&vectors[a], &vectors[b]),
+            None => Self::lexical_similarity(&population[a], &population[b]),
+        };
+        // Score minus a penalty for resembling parents that are already chosen
+        let adjusted = |candidate: usize, chosen: &[usize]| {
+            let closest = chosen
+                .iter()
+                .map(|&parent| similarity(candidate, parent))
+                .fold(0.0, f32::max);
+            scores[candidate] - selection.diversity_penalty * closest
+        };
+
+        let num_to_select = num_to_select.min(population.len());
+        let mut rng = rand::rng();
+        let mut remaining: Vec<usize> = (0..population.len()).collect();
+        let mut chosen: Vec<usize> = Vec::with_capacity(num_to_select);
+
+        match selection.strategy {
+            ParentSelection::Random => {}
+            ParentSelection::TopK => {
+                while chosen.len() < num_to_select {
+                    let position = (0..remaining.len())
+                        .max_by(|&a, &b| {
+                            adjusted(remaining[a], &chosen)
+                                .total_cmp(&adjusted(remaining[b], &chosen))
+                        })
+                        .unwrap_or(0);
+                    chosen.push(remaining.swap_remove(position));
+                }
+            }
+            ParentSelection::Tournament => {
+                while chosen.len() < num_to_select {
+                    let a = rng.random_range(0..remaining.len());
+                    let b = rng.random_range(0..remaining.len());
+                    let winner =
+                        if adjusted(remaining[a], &chosen) >= adjusted(remaining[b], &chosen) {
+                            a
+                        } else {
+                            b
+                        };
+                    chosen.push(remaining.swap_remove(winner));
+                }
+            }
+            ParentSelection::Roulette => {
+                while chosen.len() < num_to_select {
+                    // Small floor so zero-scored parents can still be drawn
+                    let weights: Vec<f32> = remaining
+                        .iter()
+                        .map(|&i| adjusted(i, &chosen).max(0.0) + 0.01)
+                        .collect();
+                    let mut ticket = rng.random::<f32>() * weights.iter().sum::<f32>();
+                    let mut pick = remaining.len() - 1;
+                    for (position, weight) in weights.iter().enumerate() {
+                        if ticket < *weight {
+                            pick = position;
+                            break;
+                        }
+                        ticket -= weight;
+                    }
+                    chosen.push(remaining.swap_remove(pick));
+                }
+            }
+            ParentSelection::DiversityAware => {
+                if num_to_select > 0 {
+                    let first = rng.random_range(0..remaining.len());
+                    chosen.push(remaining.swap_remove(first));
+                }
+                while chosen.len() < num_to_select {
+                    // Farthest-point selection: maximize distance to the closest chosen parent
+                    let (position, _) = remaining
+                        .iter()
+                        .enumerate()
+                        .map(|(position, &candidate)| {
+                            let nearest = chosen
+                                .iter()
+                                .map(|&parent| 1.0 - similarity(candidate, parent))
+                                .fold(f32::INFINITY, f32::min);
+                            (position, nearest)
+                        })
+                        .max_by(|a, b| a.1.total_cmp(&b.1))
+                        .unwrap_or((0, 0.0));
+                    chosen.push(remaining.swap_remove(position));
+                }
+            }
+        }
+
+        chosen.into_iter().map(|i| population[i].clone()).collect()
+    }
+
+    /// Jaccard similarity of the word sets of two solutions (1.0 = identical)
+    pub(crate) fn lexical_similarity(a: &Solution, b: &Solution) -> f32 {
+        let words = |s: &Solution| -> HashSet<String> {
+            s.reasoning
+                .split_whitespace()
+                .chain(s.answer.split_whitespace())
+                .map(str::to_lowercase)
+                .collect()
+        };
+        let (a, b) = (words(a), words(b));
+        let union = a.union(&b).count();
+        if union == 0 {
+            return 1.0;
+        }
+        a.intersection(&b).count() as f32 / union as f32
+    }
+
     /// Select diverse solutions from the population
     ///
     /// This promotes diversity to explore different reasoning paths
@@ -145,7 +518,63 @@ impl Aggregator {
         Ok(selected)
     }
 
-    /// Synthesize a new solution from multiple selected solutions
+    /// Merge selected parent solutions into a new one via the LLM
+    async fn recombine(
+        query: &str,
+        parents: &[Solution],
+        iteration: usize,
+        prompts: &AggregationPrompts,
+        provider: &dyn crate::LLMProvider,
+    ) -> Result<Solution> {
+        let listing = parents
+            .iter()
+            .enumerate()
+            .map(|(idx, parent)| {
+                format!(
+                    "Solution {}:\nReasoning: {}\nAnswer: {}",
+                    idx + 1,
+                    parent.reasoning,
+                    parent.answer
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let template = match &prompts.rsa_merge {
+            Some(template) => template.clone(),
+            None => format!(
+                "Problem:\n{{query}}\n\n{}",
+                crate::prompts::AGGREGATION_PROMPT
+            ),
+        };
+        let prompt = render_template(&template, &[("query", query), ("solutions", &listing)]);
+
+        let response = provider
+            .complete(&prompt, Some(prompts.system_prompt()))
+            .await?;
+        if response.text.trim().is_empty() {
+            return Err(crate::MarsError::AggregationError(
+                "Empty response from RSA recombination".to_string(),
+            ));
+        }
+
+        let (reasoning, answer) = split_final_answer(&response.text);
+        let mut recombined = Solution::new(
+            format!("aggregator-iteration-{iteration}"),
+            reasoning,
+            answer,
+            0.5, // Use medium temperature for aggregated solution
+            0,
+        );
+        recombined.record_completion(&response, prompt.len());
+        recombined.phase = GenerationPhase::Aggregated;
+        recombined.steps = crate::agent::Agent::parse_steps(&recombined.reasoning);
+        recombined.record_provider(provider, None);
+        recombined.parent_ids = parents.iter().map(|parent| parent.id.clone()).collect();
+
+        Ok(recombined)
+    }
+
+    /// Synthesize a new solution from multiple selected solutions without a model
     fn synthesize_solution(solutions: &[Solution], iteration: usize) -> Result<Solution> {
         if solutions.is_empty() {
             return Err(crate::MarsError::AggregationError(
@@ -171,6 +600,7 @@ impl Aggregator {
         );
 
         aggregated.phase = GenerationPhase::Aggregated;
+        aggregated.parent_ids = solutions.iter().map(|s| s.id.clone()).collect();
 
         Ok(aggregated)
     }
@@ -254,6 +684,47 @@ impl Aggregator {
     }
 }
 
+/// Score a solution for search-based aggregation: its verification score if it
+/// has been verified, otherwise a fresh verification pass
+pub(crate) async fn verifier_score(
+    query: &str,
+    solution: &Solution,
+    provider: &dyn crate::LLMProvider,
+) -> Result<f32> {
+    if solution.verification_passes + solution.verification_failures > 0 {
+        return Ok(solution.verification_score);
+    }
+
+    let result =
+        crate::verifier::Verifier::verify_solution(query, solution, "aggregation-scorer", provider)
+            .await?;
+    Ok(result.score)
+}
+
+/// Cosine similarity of two embedding vectors (0.0 when either is zero)
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    dot / denominator
+}
+
+/// Split an LLM response on its final `ANSWER:` line into (reasoning, answer)
+///
+/// Responses without an `ANSWER:` line are used whole for both parts.
+pub(crate) fn split_final_answer(response: &str) -> (String, String) {
+    match response.rfind("ANSWER:") {
+        Some(pos) => (
+            response[..pos].trim().to_string(),
+            response[pos + 7..].trim().to_string(),
+        ),
+        None => (response.trim().to_string(), response.trim().to_string()),
+    }
+}
+
 /// Statistics about aggregation results
 #[derive(Debug, Clone)]
 pub struct AggregationStatistics {
@@ -272,6 +743,306 @@ pub struct AggregationStatistics {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+
+    /// Mock provider that merges any parents into the answer "42"
+    struct MockRecombiner;
+
+    #[async_trait]
+    impl crate::LLMProvider for MockRecombiner {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            assert!(prompt.contains("Solution 1:"));
+            Ok("Step 1: Both parents agree on the setup\nANSWER: 42".into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-recombiner"
+        }
+    }
+
+    /// Mock provider whose calls always fail
+    struct FailingProvider;
+
+    #[async_trait]
+    impl crate::LLMProvider for FailingProvider {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Err(crate::MarsError::AggregationError(
+                "unavailable".to_string(),
+            ))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-failing"
+        }
+    }
+
+    /// Mock provider that echoes the prompt as reasoning and the system prompt as answer
+    struct EchoProvider;
+
+    #[async_trait]
+    impl crate::LLMProvider for EchoProvider {
+        async fn complete(
+            &self,
+            prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Ok(format!("{prompt}\nANSWER: {}", system_prompt.unwrap_or_default()).into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-echo"
+        }
+    }
+
+    fn parent(answer: &str) -> Solution {
+        Solution::new(
+            "agent".to_string(),
+            "reasoning".to_string(),
+            answer.to_string(),
+            0.5,
+            100,
+        )
+    }
+
+    fn parents() -> Vec<Solution> {
+        vec![parent("41"), parent("43")]
+    }
+
+    #[tokio::test]
+    async fn test_rsa_recombines_with_llm() {
+        let aggregated = Aggregator::aggregate_rsa(
+            "q",
+            &parents(),
+            6,
+            2,
+            2,
+            RsaSelectionConfig::default(),
+            &AggregationPrompts::default(),
+            &MockRecombiner,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(aggregated.len(), 2);
+        for solution in &aggregated {
+            assert_eq!(solution.answer, "42");
+            assert_eq!(solution.steps.len(), 1);
+            assert_eq!(solution.model, "mock/mock-recombiner");
+            assert_eq!(solution.phase, GenerationPhase::Aggregated);
+            assert_eq!(solution.parent_ids.len(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rsa_falls_back_without_model() {
+        let aggregated = Aggregator::aggregate_rsa(
+            "q",
+            &parents(),
+            6,
+            2,
+            1,
+            RsaSelectionConfig::default(),
+            &AggregationPrompts::default(),
+            &FailingProvider,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(aggregated.len(), 1);
+        assert!(aggregated[0].reasoning.starts_with("Combined reasoning"));
+    }
+
+    #[tokio::test]
+    async fn test_rsa_uses_custom_merge_prompt() {
+        let prompts = AggregationPrompts {
+            system: Some("You are a careful editor.".to_string()),
+            rsa_merge: Some("Merge drafts of {query}:\n{solutions}".to_string()),
+            ..AggregationPrompts::default()
+        };
+        let aggregated = Aggregator::aggregate_rsa(
+            "a summary",
+            &parents(),
+            6,
+            2,
+            1,
+            RsaSelectionConfig::default(),
+            &prompts,
+            &EchoProvider,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(aggregated.len(), 1);
+        assert!(
+            aggregated[0]
+                .reasoning
+                .starts_with("Merge drafts of a summary:\nSolution 1:")
+        );
+        assert_eq!(aggregated[0].answer, "You are a careful editor.");
+    }
+
+    fn scored(reasoning: &str, score: f32) -> Solution {
+        let mut solution = Solution::new(
+            "agent".to_string(),
+            reasoning.to_string(),
+            "answer".to_string(),
+            0.5,
+            100,
+        );
+        solution.verification_score = score;
+        solution
+    }
+
+    fn strategy(strategy: ParentSelection) -> RsaSelectionConfig {
+        RsaSelectionConfig {
+            strategy,
+            ..RsaSelectionConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_select_parents_top_k() {
+        let population = vec![scored("a", 0.2), scored("b", 0.9), scored("c", 0.5)];
+        let scores: Vec<f32> = population.iter().map(|s| s.verification_score).collect();
+
+        let selected = Aggregator::select_parents(
+            &population,
+            &scores,
+            None,
+            2,
+            strategy(ParentSelection::TopK),
+        );
+        let reasoning: Vec<&str> = selected.iter().map(|s| s.reasoning.as_str()).collect();
+        assert_eq!(reasoning, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_select_parents_are_distinct() {
+        let population = vec![scored("a", 0.2), scored("b", 0.9), scored("c", 0.0)];
+        let scores: Vec<f32> = population.iter().map(|s| s.verification_score).collect();
+
+        for selection in [ParentSelection::Tournament, ParentSelection::Roulette] {
+            let selected =
+                Aggregator::select_parents(&population, &scores, None, 3, strategy(selection));
+            let ids: HashSet<&str> = selected.iter().map(|s| s.id.as_str()).collect();
+            assert_eq!(ids.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_select_parents_diversity_aware() {
+        let population = vec![
+            scored("add the two numbers", 0.0),
+            scored("add the two numbers together", 0.0),
+            scored("count on fingers instead", 0.0),
+        ];
+        let scores = vec![0.0; 3];
+
+        // Whichever parent is drawn first, the near-duplicate pair is never chosen together
+        for _ in 0..10 {
+            let selected = Aggregator::select_parents(
+                &population,
+                &scores,
+                None,
+                2,
+                strategy(ParentSelection::DiversityAware),
+            );
+            assert!(
+                selected
+                    .iter()
+                    .any(|s| s.reasoning == "count on fingers instead")
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_parents_diversity_uses_embeddings() {
+        // Lexically "a" and "b" differ, but their embeddings say they are the same
+        let population = vec![scored("a", 0.0), scored("b", 0.0), scored("c", 0.0)];
+        let embeddings = vec![vec![1.0, 0.0], vec![1.0, 0.0], vec![0.0, 1.0]];
+        let scores = vec![0.0; 3];
+
+        for _ in 0..10 {
+            let selected = Aggregator::select_parents(
+                &population,
+                &scores,
+                Some(&embeddings),
+                2,
+                strategy(ParentSelection::DiversityAware),
+            );
+            assert!(selected.iter().any(|s| s.reasoning == "c"));
+        }
+    }
+
+    #[test]
+    fn test_diversity_penalty_skips_near_duplicates() {
+        let population = vec![scored("a", 0.9), scored("b", 0.85), scored("c", 0.5)];
+        let embeddings = vec![vec![1.0, 0.0], vec![0.99, 0.1], vec![0.0, 1.0]];
+        let scores: Vec<f32> = population.iter().map(|s| s.verification_score).collect();
+        let selection = RsaSelectionConfig {
+            strategy: ParentSelection::TopK,
+            diversity_penalty: 1.0,
+        };
+
+        let selected =
+            Aggregator::select_parents(&population, &scores, Some(&embeddings), 2, selection);
+        let reasoning: Vec<&str> = selected.iter().map(|s| s.reasoning.as_str()).collect();
+        assert_eq!(reasoning, vec!["a", "c"]);
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_split_final_answer() {
+        let (reasoning, answer) = split_final_answer("work\nANSWER: 7");
+        assert_eq!(reasoning, "work");
+        assert_eq!(answer, "7");
+
+        let (reasoning, answer) = split_final_answer(" just 7 ");
+        assert_eq!(reasoning, "just 7");
+        assert_eq!(answer, "just 7");
+    }
 
     #[test]
     fn test_select_diverse_solutions() {
@@ -319,11 +1090,13 @@ mod tests {
             100,
         );
 
+        let parent_ids = vec![sol1.id.clone(), sol2.id.clone()];
         let solutions = vec![sol1, sol2];
         let synthesized = Aggregator::synthesize_solution(&solutions, 0).unwrap();
         assert!(!synthesized.reasoning.is_empty());
         assert!(!synthesized.answer.is_empty());
         assert_eq!(synthesized.phase, GenerationPhase::Aggregated);
+        assert_eq!(synthesized.parent_ids, parent_ids);
     }
 
     #[test]
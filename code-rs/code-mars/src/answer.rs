@@ -0,0 +1,293 @@
+//! Typed extraction of final answers.
+//!
+//! [`AnswerType::extract`] parses an answer into a [`TypedAnswer`]: numbers
+//! (including fractions and `\boxed{}` values), the last fenced code block
+//! with its language, or a multiple-choice letter. Extractors look for the
+//! strict format [`AnswerType::format_instruction`] asks for first, then fall
+//! back to looser conventions models commonly use.
+
+use crate::code_verifier::CodeVerifier;
+use crate::prompts::{CODE_ANSWER_PROMPT, MULTIPLE_CHOICE_ANSWER_PROMPT, NUMERIC_ANSWER_PROMPT};
+use crate::types::{AnswerType, TypedAnswer};
+use crate::{MarsError, Result};
+
+/// Marker of the line a strict-format answer is given on
+const ANSWER_MARKER: &str = "answer:";
+
+impl AnswerType {
+    /// Prompt instruction that asks for this answer format, if any
+    pub fn format_instruction(&self) -> Option<&'static str> {
+        match self {
+            Self::FreeText => None,
+            Self::Numeric => Some(NUMERIC_ANSWER_PROMPT),
+            Self::Code => Some(CODE_ANSWER_PROMPT),
+            Self::MultipleChoice => Some(MULTIPLE_CHOICE_ANSWER_PROMPT),
+        }
+    }
+
+    /// Parse `answer` as this type
+    ///
+    /// Fails with [`MarsError::AnswerExtractionError`] when no value of the
+    /// type can be found.
+    pub fn extract(&self, answer: &str) -> Result<TypedAnswer> {
+        let typed = match self {
+            Self::FreeText => Some(TypedAnswer::Text {
+                text: answer.trim().to_string(),
+            }),
+            Self::Numeric => extract_number(answer).map(|value| TypedAnswer::Numeric { value }),
+            Self::Code => CodeVerifier::extract_code_blocks(answer)
+                .pop()
+                .map(|block| TypedAnswer::Code {
+                    language: block.language,
+                    code: block.code,
+                }),
+            Self::MultipleChoice => {
+                extract_choice(answer).map(|letter| TypedAnswer::Choice { letter })
+            }
+        };
+        typed.ok_or_else(|| {
+            let preview: String = answer.trim().chars().take(100).collect();
+            MarsError::AnswerExtractionError(format!("no {self:?} answer in: {preview}"))
+        })
+    }
+}
+
+/// Text after the last `ANSWER:` marker, up to the end of its line
+pub(crate) fn marked_answer(text: &str) -> Option<&str> {
+    let lower = text.to_ascii_lowercase();
+    let start = lower.rfind(ANSWER_MARKER)? + ANSWER_MARKER.len();
+    let rest = &text[start..];
+    Some(rest.lines().next().unwrap_or_default().trim())
+}
+
+/// Contents of the last `\boxed{...}`, with nested braces
+pub(crate) fn boxed(text: &str) -> Option<&str> {
+    let start = text.rfind("\\boxed{")? + "\\boxed{".len();
+    let mut depth = 1;
+    for (offset, c) in text[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[start..start + offset]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The number a numeric answer gives
+///
+/// Prefers the `ANSWER:` line, then `\boxed{}`, then the last number in
+/// the text.
+fn extract_number(text: &str) -> Option<f64> {
+    marked_answer(text)
+        .and_then(|marked| numbers(marked).into_iter().next())
+        .or_else(|| boxed(text).and_then(|inner| numbers(inner).into_iter().next()))
+        .or_else(|| numbers(text).pop())
+}
+
+/// Numbers in `text`, in order
+///
+/// Reads signs, thousands separators, decimals, `a/b` fractions and LaTeX
+/// `\frac{a}{b}`.
+fn numbers(text: &str) -> Vec<f64> {
+    let text = text
+        .replace("\\dfrac", "\\frac")
+        .replace("\\tfrac", "\\frac");
+    let chars: Vec<char> = text.chars().collect();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i..].starts_with(&['\\', 'f', 'r', 'a', 'c', '{'])
+            && let Some((value, end)) = latex_fraction(&chars, i + "\\frac".len())
+        {
+            found.push(value);
+            i = end;
+            continue;
+        }
+        match number_at(&chars, i) {
+            Some((value, end)) => {
+                found.push(value);
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+    found
+}
+
+/// `\frac{a}{b}` starting at the `{` at `start`, and the index after it
+fn latex_fraction(chars: &[char], start: usize) -> Option<(f64, usize)> {
+    let (numerator, end) = braced_number(chars, start)?;
+    let (denominator, end) = braced_number(chars, end)?;
+    (denominator != 0.0).then_some((numerator / denominator, end))
+}
+
+/// A number alone in braces at `start`, and the index after the braces
+fn braced_number(chars: &[char], start: usize) -> Option<(f64, usize)> {
+    if chars.get(start) != Some(&'{') {
+        return None;
+    }
+    let (value, end) = number_at(chars, start + 1)?;
+    (chars.get(end) == Some(&'}')).then_some((value, end + 1))
+}
+
+/// The number starting at `start`, if one does, and the index after it
+fn number_at(chars: &[char], start: usize) -> Option<(f64, usize)> {
+    // Digits inside words such as `x2` are not numbers
+    if start > 0 && chars[start - 1].is_alphanumeric() {
+        return None;
+    }
+    let negative = chars.get(start) == Some(&'-');
+    let digits_start = if negative { start + 1 } else { start };
+    let first = *chars.get(digits_start)?;
+    let leading_point = first == '.'
+        && chars
+            .get(digits_start + 1)
+            .is_some_and(char::is_ascii_digit);
+    if !(first.is_ascii_digit() || leading_point) {
+        return None;
+    }
+
+    let mut end = digits_start;
+    let mut literal = String::new();
+    while let Some(&c) = chars.get(end) {
+        let next_is_digit = chars.get(end + 1).is_some_and(char::is_ascii_digit);
+        match c {
+            '0'..='9' => literal.push(c),
+            // Thousands separators and decimal points only between digits
+            ',' if next_is_digit && !literal.is_empty() => {}
+            '.' if next_is_digit && !literal.contains('.') => literal.push(c),
+            _ => break,
+        }
+        end += 1;
+    }
+    let mut value: f64 = literal.parse().ok()?;
+
+    if chars.get(end) == Some(&'/')
+        && let Some((denominator, after)) = number_at(chars, end + 1)
+        && denominator != 0.0
+    {
+        value /= denominator;
+        end = after;
+    }
+    Some((if negative { -value } else { value }, end))
+}
+
+/// The option letter a multiple-choice answer picks
+///
+/// Prefers the `ANSWER:` line, then "answer is X", then an answer that is
+/// only a letter, then the last parenthesized capital letter.
+fn extract_choice(text: &str) -> Option<char> {
+    if let Some(marked) = marked_answer(text) {
+        return choice_token(marked);
+    }
+    let lower = text.to_ascii_lowercase();
+    if let Some(pos) = lower.rfind("answer is")
+        && let Some(letter) = choice_token(&text[pos + "answer is".len()..])
+    {
+        return Some(letter);
+    }
+    if let Some(letter) = choice_token(text).filter(|_| text.split_whitespace().count() == 1) {
+        return Some(letter);
+    }
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .windows(3)
+        .rev()
+        .find(|w| w[0] == '(' && w[1].is_ascii_uppercase() && w[2] == ')')
+        .map(|w| w[1])
+}
+
+/// The option letter the first word of `text` names, e.g. `B`, `(b)` or `**C.**`
+fn choice_token(text: &str) -> Option<char> {
+    let word = text.split_whitespace().next()?;
+    let word = word.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+    let mut letters = word.chars();
+    match (letters.next(), letters.next()) {
+        (Some(letter), None) if letter.is_ascii_alphabetic() => Some(letter.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(text: &str) -> f64 {
+        match AnswerType::Numeric.extract(text).unwrap() {
+            TypedAnswer::Numeric { value } => value,
+            other => panic!("expected a number, got {other:?}"),
+        }
+    }
+
+    fn choice(text: &str) -> Option<char> {
+        match AnswerType::MultipleChoice.extract(text) {
+            Ok(TypedAnswer::Choice { letter }) => Some(letter),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_extracts_numbers() {
+        assert_eq!(number("Adding 2 and 2 gives 4.\nANSWER: 4"), 4.0);
+        assert_eq!(
+            number("Step 1 costs 3 apples.\nAnswer: -1,234.5 dollars"),
+            -1234.5
+        );
+        assert_eq!(
+            number("So the total is \\boxed{\\frac{3}{4}} overall, step 2"),
+            0.75
+        );
+        assert_eq!(number("Half of it: 1/2"), 0.5);
+        assert_eq!(number("The result is .5"), 0.5);
+        // The last number wins without a marker, ignoring numbers inside words
+        assert_eq!(number("From x2 and step 3 we get 12"), 12.0);
+        assert!(AnswerType::Numeric.extract("no idea").is_err());
+    }
+
+    #[test]
+    fn test_extracts_choices() {
+        assert_eq!(choice("B is tempting, but\nANSWER: (c)"), Some('C'));
+        assert_eq!(choice("The correct answer is **D**."), Some('D'));
+        assert_eq!(choice(" a. "), Some('A'));
+        assert_eq!(choice("Options (A) and (B) fail, so (C) it is"), Some('C'));
+        assert_eq!(choice("I am not sure"), None);
+        assert_eq!(choice("ANSWER: none of them"), None);
+    }
+
+    #[test]
+    fn test_extracts_last_code_block() {
+        let answer = "Draft:\n```rust\nfn f() {}\n```\nFinal:\n```Python\nprint(1)\n```\n";
+        assert_eq!(
+            AnswerType::Code.extract(answer).unwrap(),
+            TypedAnswer::Code {
+                language: "python".to_string(),
+                code: "print(1)\n".to_string(),
+            }
+        );
+        let err = AnswerType::Code.extract("print(1)").unwrap_err();
+        assert!(matches!(err, MarsError::AnswerExtractionError(_)));
+    }
+
+    #[test]
+    fn test_free_text_and_instructions() {
+        assert_eq!(
+            AnswerType::FreeText.extract("  Paris \n").unwrap(),
+            TypedAnswer::Text {
+                text: "Paris".to_string()
+            }
+        );
+        assert_eq!(AnswerType::FreeText.format_instruction(), None);
+        assert!(
+            AnswerType::MultipleChoice
+                .format_instruction()
+                .unwrap()
+                .contains("ANSWER: <letter>")
+        );
+    }
+}
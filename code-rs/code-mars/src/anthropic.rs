@@ -0,0 +1,404 @@
+//! Native Anthropic provider speaking the Messages API.
+//!
+//! Unlike OpenAI-compatible endpoints, the Messages API takes the system
+//! prompt as a top-level field, requires `max_tokens` on every request,
+//! authenticates with an `x-api-key` header and streams typed server-sent
+//! events (`content_block_delta`, `message_stop`, `error`, ...).
+//!
+//! Select it in the routing config with `ProviderSpec::new("anthropic", model)`.
+
+use crate::model_router::{
+    CompletionResponse, HttpOptions, HttpProviderBase, HttpTimeouts, LLMProvider, ModelStream,
+    StreamLine, http_json, invalid_response, reported_str, reported_usage, stream_sse_data,
+};
+use crate::tools::{ToolAwareResponse, ToolSpec, anthropic_tool_calls};
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use serde_json::{Map, Value, json};
+use std::time::Duration;
+
+/// Public Messages API root
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+
+/// Value of the required `anthropic-version` header
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Completion length used unless [`AnthropicProvider::with_max_tokens`] overrides it
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Request timeout used unless [`AnthropicProvider::with_timeout`] overrides it
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Claude models through the Anthropic Messages API
+pub struct AnthropicProvider {
+    model: String,
+    api_key: String,
+    /// API root, without a trailing slash
+    base_url: String,
+    max_tokens: u32,
+    base: HttpProviderBase,
+}
+
+impl AnthropicProvider {
+    /// Create a provider for `model` authenticating with `api_key`
+    pub fn new(model: String, api_key: String) -> Self {
+        Self {
+            model,
+            api_key,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            max_tokens: DEFAULT_MAX_TOKENS,
+            base: HttpProviderBase::new(DEFAULT_REQUEST_TIMEOUT),
+        }
+    }
+
+    /// Create a provider from a provider specification
+    pub fn from_spec(spec: &crate::provider_config::ProviderSpec) -> Self {
+        let provider = Self::new(spec.model.clone(), spec.api_key.clone())
+            .with_http_options(spec.http_options())
+            .with_extra_params(spec.extra_params.clone());
+        match &spec.base_url {
+            Some(base_url) => provider.with_base_url(base_url.clone()),
+            None => provider,
+        }
+    }
+
+    /// Send requests to a different API root
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Cap each completion at `max_tokens` (at least 1)
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens.max(1);
+        self
+    }
+
+    /// Give up on requests that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.base.set_timeout(timeout);
+        self
+    }
+
+    /// Apply request, connect and read timeouts (unset ones keep their defaults)
+    pub fn with_timeouts(mut self, timeouts: &HttpTimeouts) -> Self {
+        self.base.set_timeouts(timeouts);
+        self
+    }
+
+    /// Merge provider-specific parameters into every request body
+    pub fn with_extra_params(mut self, extra_params: Map<String, Value>) -> Self {
+        self.base.set_extra_params(extra_params);
+        self
+    }
+
+    /// Apply timeouts, a proxy and extra headers to every request
+    pub fn with_http_options(mut self, http: HttpOptions) -> Self {
+        self.base.set_http_options(http);
+        self
+    }
+
+    /// Messages API request body
+    fn request_body(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: Option<f32>,
+        stream: bool,
+    ) -> Value {
+        let mut body = json!({
+            "model": self.model,
+            "max_tokens": self.max_tokens,
+            "messages": [{ "role": "user", "content": prompt }],
+        });
+        if let Some(system) = system_prompt {
+            body["system"] = json!(system);
+        }
+        // The Messages API accepts temperatures from 0.0 to 1.0 only
+        if let Some(temperature) = temperature {
+            body["temperature"] = json!(temperature.clamp(0.0, 1.0));
+        }
+        if stream {
+            body["stream"] = json!(true);
+        }
+        body
+    }
+
+    /// POST a Messages API request
+    async fn send(&self, body: &Value) -> Result<reqwest::Response> {
+        let request = self
+            .base
+            .post_json(format!("{}/messages", self.base_url), body)?
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION);
+        self.base.send(request, &self.model_id()).await
+    }
+
+    /// Run a non-streaming request and join the text content blocks
+    async fn message(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: Option<f32>,
+    ) -> Result<CompletionResponse> {
+        let body = self.request_body(prompt, system_prompt, temperature, false);
+        let response = http_json(self.send(&body).await?, &self.model_id()).await?;
+        Ok(self.parse_message(&response)?.0)
+    }
+
+    /// Joined text of a Messages API response, with its content blocks
+    fn parse_message<'a>(&self, response: &'a Value) -> Result<(CompletionResponse, &'a [Value])> {
+        let blocks = response
+            .get("content")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid_response(&self.model_id(), "no content blocks"))?;
+
+        let (prompt_tokens, completion_tokens) =
+            reported_usage(response, "/usage/input_tokens", "/usage/output_tokens");
+        let message = CompletionResponse {
+            text: blocks
+                .iter()
+                .filter(|block| block.get("type").and_then(Value::as_str) == Some("text"))
+                .filter_map(|block| block.get("text").and_then(Value::as_str))
+                .collect(),
+            prompt_tokens,
+            completion_tokens,
+            finish_reason: reported_str(response, "/stop_reason"),
+            model: reported_str(response, "/model"),
+            latency_ms: None,
+        };
+        Ok((message, blocks))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for AnthropicProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        self.message(prompt, system_prompt, None).await
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        self.message(prompt, system_prompt, Some(temperature)).await
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let body = self.request_body(prompt, system_prompt, None, true);
+        let response = self.send(&body).await?;
+        let model_id = self.model_id();
+        let owner = model_id.clone();
+
+        // Every event repeats its name in the `type` field of its data
+        Ok(stream_sse_data(response, &model_id, move |data| {
+            let event: Value =
+                serde_json::from_str(data).map_err(|e| invalid_response(&owner, &e.to_string()))?;
+            match event.get("type").and_then(Value::as_str) {
+                Some("content_block_delta") => Ok(event
+                    .pointer("/delta/text")
+                    .and_then(Value::as_str)
+                    .map_or(StreamLine::Skip, |text| StreamLine::Chunk(text.to_string()))),
+                Some("message_stop") => Ok(StreamLine::End(None)),
+                Some("error") => Err(MarsError::ClientError(format!(
+                    "{owner} failed mid-stream: {}",
+                    event
+                        .pointer("/error/message")
+                        .and_then(Value::as_str)
+                        .unwrap_or("unknown error")
+                ))),
+                _ => Ok(StreamLine::Skip),
+            }
+        }))
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        let mut body = self.request_body(prompt, system_prompt, None, false);
+        if !tools.is_empty() {
+            body["tools"] = tools.iter().map(ToolSpec::to_anthropic).collect();
+        }
+        let response = http_json(self.send(&body).await?, &self.model_id()).await?;
+        let (message, blocks) = self.parse_message(&response)?;
+        Ok(ToolAwareResponse {
+            response: message,
+            tool_calls: anthropic_tool_calls(blocks),
+        })
+    }
+
+    fn provider_name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer) -> AnthropicProvider {
+        AnthropicProvider::new("claude-sonnet-4".to_string(), "test-key".to_string())
+            .with_base_url(format!("{}/v1", server.uri()))
+    }
+
+    #[tokio::test]
+    async fn test_messages_request_and_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("x-api-key", "test-key"))
+            .and(header("anthropic-version", ANTHROPIC_VERSION))
+            .and(body_partial_json(json!({
+                "model": "claude-sonnet-4",
+                "max_tokens": 256,
+                "system": "Be brief.",
+                "temperature": 1.0,
+                "messages": [{ "role": "user", "content": "What is 2+2?" }],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "claude-sonnet-4-20250514",
+                "content": [
+                    { "type": "thinking", "thinking": "simple" },
+                    { "type": "text", "text": "2 + 2 " },
+                    { "type": "text", "text": "= 4" },
+                ],
+                "stop_reason": "end_turn",
+                "usage": { "input_tokens": 12, "output_tokens": 7 },
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let answer = provider(&server)
+            .with_max_tokens(256)
+            .complete_with_temperature("What is 2+2?", Some("Be brief."), 1.3)
+            .await
+            .unwrap();
+        assert_eq!(answer.text, "2 + 2 = 4");
+        assert_eq!(answer.prompt_tokens, Some(12));
+        assert_eq!(answer.completion_tokens, Some(7));
+        assert_eq!(answer.finish_reason.as_deref(), Some("end_turn"));
+        assert_eq!(answer.model.as_deref(), Some("claude-sonnet-4-20250514"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_use_blocks_become_tool_calls() {
+        let tool = ToolSpec::new(
+            "run_tests",
+            "Run the test suite",
+            json!({ "type": "object", "properties": { "filter": { "type": "string" } } }),
+        );
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_partial_json(json!({
+                "tools": [{ "name": "run_tests", "input_schema": tool.parameters }],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "content": [
+                    { "type": "text", "text": "Running the parser tests." },
+                    {
+                        "type": "tool_use",
+                        "id": "toolu_01",
+                        "name": "run_tests",
+                        "input": { "filter": "parser" },
+                    },
+                ],
+                "stop_reason": "tool_use",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let answer = provider(&server)
+            .complete_with_tools("Check the parser", None, &[tool])
+            .await
+            .unwrap();
+        assert_eq!(answer.response.text, "Running the parser tests.");
+        assert_eq!(answer.response.finish_reason.as_deref(), Some("tool_use"));
+        let call = answer.tool_call("run_tests").unwrap();
+        assert_eq!(call.id, "toolu_01");
+        assert_eq!(call.arguments, json!({ "filter": "parser" }));
+    }
+
+    #[tokio::test]
+    async fn test_error_status_uses_api_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "type": "error",
+                "error": { "type": "invalid_request_error", "message": "max_tokens: required" },
+            })))
+            .mount(&server)
+            .await;
+
+        let err = provider(&server).complete("q", None).await.unwrap_err();
+        assert!(err.to_string().contains("max_tokens: required"));
+    }
+
+    #[tokio::test]
+    async fn test_streams_text_deltas() {
+        let events = [
+            "event: message_start",
+            r#"data: {"type":"message_start","message":{"content":[]}}"#,
+            "",
+            "event: content_block_delta",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"2 + 2"}}"#,
+            "",
+            "event: ping",
+            r#"data: {"type":"ping"}"#,
+            "",
+            "event: content_block_delta",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" = 4"}}"#,
+            "",
+            "event: message_stop",
+            r#"data: {"type":"message_stop"}"#,
+        ];
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(events.join("\n") + "\n\n"),
+            )
+            .mount(&server)
+            .await;
+
+        let mut stream = provider(&server).stream("q", None).await.unwrap();
+        assert_eq!(stream.next_chunk().await.unwrap().unwrap(), "2 + 2");
+        assert_eq!(stream.next_chunk().await.unwrap().unwrap(), " = 4");
+        assert!(stream.next_chunk().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_error_event() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+            ))
+            .mount(&server)
+            .await;
+
+        let mut stream = provider(&server).stream("q", None).await.unwrap();
+        let err = stream.next_chunk().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("Overloaded"));
+        assert!(stream.next_chunk().await.is_none());
+    }
+}
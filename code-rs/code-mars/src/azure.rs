@@ -0,0 +1,332 @@
+//! Azure OpenAI provider with deployment-based routing.
+//!
+//! Azure serves the OpenAI chat completions API under a per-resource endpoint,
+//! addressing models by deployment name rather than model name:
+//! `{endpoint}/openai/deployments/{deployment}/chat/completions?api-version=...`.
+//! Requests authenticate with an `api-key` header instead of a bearer token.
+//!
+//! Select it in the routing config with `ProviderSpec::new("azure", model)`,
+//! setting the endpoint with `with_base_url` and, when it differs from the
+//! model name, the deployment with `with_deployment`.
+
+use crate::Result;
+use crate::model_router::{
+    CompletionResponse, HttpOptions, HttpProviderBase, HttpTimeouts, LLMProvider, ModelStream,
+    chat_completion_body, chat_completion_choices, chat_completion_stream,
+    chat_completion_structured_body, chat_completion_text, chat_completion_tools_body,
+    complete_n_concurrently, fill_completions, http_json,
+};
+use crate::structured::ResponseFormat;
+use crate::tools::{ToolAwareResponse, ToolSpec, openai_tool_response};
+use async_trait::async_trait;
+use serde_json::{Map, Value, json};
+use std::time::Duration;
+
+/// API version used unless [`AzureOpenAIProvider::with_api_version`] overrides it
+const DEFAULT_API_VERSION: &str = "2024-10-21";
+
+/// Request timeout used unless [`AzureOpenAIProvider::with_timeout`] overrides it
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Whether a provider name selects Azure OpenAI
+pub(crate) fn is_azure(provider: &str) -> bool {
+    matches!(
+        provider.to_ascii_lowercase().as_str(),
+        "azure" | "azure_openai"
+    )
+}
+
+/// OpenAI models deployed on an Azure OpenAI resource
+pub struct AzureOpenAIProvider {
+    /// Resource endpoint, e.g. `https://contoso.openai.azure.com`
+    endpoint: String,
+    deployment: String,
+    /// Model name, for identification only; Azure routes by deployment
+    model: String,
+    api_key: String,
+    api_version: String,
+    base: HttpProviderBase,
+}
+
+impl AzureOpenAIProvider {
+    /// Create a provider for `deployment` on the resource at `endpoint`
+    pub fn new(endpoint: impl Into<String>, deployment: String, api_key: String) -> Self {
+        Self {
+            endpoint: Self::normalize_endpoint(endpoint.into()),
+            model: deployment.clone(),
+            deployment,
+            api_key,
+            api_version: DEFAULT_API_VERSION.to_string(),
+            base: HttpProviderBase::new(DEFAULT_REQUEST_TIMEOUT),
+        }
+    }
+
+    /// Create a provider from a provider specification
+    ///
+    /// The endpoint comes from `base_url`; the deployment defaults to the model name.
+    pub fn from_spec(spec: &crate::provider_config::ProviderSpec) -> Self {
+        let deployment = spec
+            .deployment
+            .clone()
+            .unwrap_or_else(|| spec.model.clone());
+        let mut provider = Self::new(
+            spec.base_url.clone().unwrap_or_default(),
+            deployment,
+            spec.api_key.clone(),
+        )
+        .with_http_options(spec.http_options())
+        .with_extra_params(spec.extra_params.clone());
+        provider.model = spec.model.clone();
+        match &spec.api_version {
+            Some(api_version) => provider.with_api_version(api_version.clone()),
+            None => provider,
+        }
+    }
+
+    /// Use a different `api-version` (defaults to `2024-10-21`)
+    pub fn with_api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.api_version = api_version.into();
+        self
+    }
+
+    /// Give up on requests that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.base.set_timeout(timeout);
+        self
+    }
+
+    /// Apply request, connect and read timeouts (unset ones keep their defaults)
+    pub fn with_timeouts(mut self, timeouts: &HttpTimeouts) -> Self {
+        self.base.set_timeouts(timeouts);
+        self
+    }
+
+    /// Merge provider-specific parameters into every request body
+    pub fn with_extra_params(mut self, extra_params: Map<String, Value>) -> Self {
+        self.base.set_extra_params(extra_params);
+        self
+    }
+
+    /// Apply timeouts, a proxy and extra headers to every request
+    pub fn with_http_options(mut self, http: HttpOptions) -> Self {
+        self.base.set_http_options(http);
+        self
+    }
+
+    /// Deployment requests are routed to
+    pub fn deployment(&self) -> &str {
+        &self.deployment
+    }
+
+    /// Chat completions URL of the deployment, without the query string
+    pub fn chat_completions_url(&self) -> String {
+        format!(
+            "{}/openai/deployments/{}/chat/completions",
+            self.endpoint, self.deployment
+        )
+    }
+
+    /// Accept the endpoint with or without a trailing `/` or `/openai`
+    fn normalize_endpoint(endpoint: String) -> String {
+        let endpoint = endpoint.trim_end_matches('/');
+        endpoint
+            .strip_suffix("/openai")
+            .unwrap_or(endpoint)
+            .to_string()
+    }
+
+    /// POST a chat completion request to the deployment
+    async fn send(&self, body: &Value) -> Result<reqwest::Response> {
+        let request = self
+            .base
+            .post_json(self.chat_completions_url(), body)?
+            .query(&[("api-version", self.api_version.as_str())])
+            .header("api-key", &self.api_key);
+        self.base.send(request, &self.model_id()).await
+    }
+
+    /// Run a non-streaming completion and return the message content
+    async fn chat(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: Option<f32>,
+    ) -> Result<CompletionResponse> {
+        let body = chat_completion_body(&self.model, prompt, system_prompt, temperature, false);
+        let response = http_json(self.send(&body).await?, &self.model_id()).await?;
+        chat_completion_text(&response, &self.model_id())
+    }
+}
+
+#[async_trait]
+impl LLMProvider for AzureOpenAIProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        self.chat(prompt, system_prompt, None).await
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        self.chat(prompt, system_prompt, Some(temperature)).await
+    }
+
+    async fn complete_n(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        n: usize,
+        temperature: Option<f32>,
+    ) -> Result<Vec<CompletionResponse>> {
+        if n <= 1 {
+            return complete_n_concurrently(self, prompt, system_prompt, n, temperature).await;
+        }
+        let mut body = chat_completion_body(&self.model, prompt, system_prompt, temperature, false);
+        body["n"] = json!(n);
+        let response = http_json(self.send(&body).await?, &self.model_id()).await?;
+        let choices = chat_completion_choices(&response, &self.model_id())?;
+        Ok(fill_completions(self, prompt, system_prompt, n, temperature, choices).await)
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let body = chat_completion_body(&self.model, prompt, system_prompt, None, true);
+        let response = self.send(&body).await?;
+        Ok(chat_completion_stream(response, &self.model_id()))
+    }
+
+    fn provider_name(&self) -> &str {
+        "azure"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        let body = chat_completion_tools_body(&self.model, prompt, system_prompt, tools);
+        let response = http_json(self.send(&body).await?, &self.model_id()).await?;
+        openai_tool_response(&response, &self.model_id())
+    }
+
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        let body = chat_completion_structured_body(&self.model, prompt, system_prompt, format);
+        let response = http_json(self.send(&body).await?, &self.model_id()).await?;
+        format.normalize(chat_completion_text(&response, &self.model_id())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider_config::ProviderSpec;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn spec(server: &MockServer) -> ProviderSpec {
+        ProviderSpec::new("azure", "gpt-4o")
+            .with_api_key("azure-key".to_string())
+            .with_base_url(format!("{}/openai/", server.uri()))
+            .with_deployment("gpt4o-prod".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_routes_to_deployment_with_api_key_header() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/openai/deployments/gpt4o-prod/chat/completions"))
+            .and(query_param("api-version", "2024-06-01"))
+            .and(header("api-key", "azure-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{
+                    "message": { "role": "assistant", "content": "4" },
+                    "finish_reason": "stop",
+                }],
+                "usage": { "prompt_tokens": 14, "completion_tokens": 1 },
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider =
+            AzureOpenAIProvider::from_spec(&spec(&server).with_api_version("2024-06-01".into()));
+        assert_eq!(provider.deployment(), "gpt4o-prod");
+        assert_eq!(provider.model_id(), "azure/gpt-4o");
+        let answer = provider.complete("What is 2+2?", None).await.unwrap();
+        assert_eq!(answer.text, "4");
+        assert_eq!(answer.prompt_tokens, Some(14));
+        assert_eq!(answer.completion_tokens, Some(1));
+        assert_eq!(answer.finish_reason.as_deref(), Some("stop"));
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[0].headers.get("authorization").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_content_filter_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(query_param("api-version", DEFAULT_API_VERSION))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{ "message": { "role": "assistant" }, "finish_reason": "content_filter" }],
+            })))
+            .mount(&server)
+            .await;
+
+        let err = AzureOpenAIProvider::from_spec(&spec(&server))
+            .complete("q", None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("content_filter"));
+    }
+
+    #[tokio::test]
+    async fn test_streams_deltas_after_filter_results() {
+        // Azure sends prompt filter results in a first event with no choices
+        let events = [
+            r#"data: {"choices":[],"prompt_filter_results":[{"prompt_index":0}]}"#,
+            r#"data: {"choices":[{"delta":{"content":"2 + 2"}}]}"#,
+            r#"data: {"choices":[{"delta":{"content":" = 4"}}]}"#,
+            "data: [DONE]",
+        ];
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/openai/deployments/gpt4o-prod/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(events.join("\n\n")))
+            .mount(&server)
+            .await;
+
+        let provider = AzureOpenAIProvider::from_spec(&spec(&server));
+        let mut stream = provider.stream("q", None).await.unwrap();
+        assert_eq!(stream.next_chunk().await.unwrap().unwrap(), "2 + 2");
+        assert_eq!(stream.next_chunk().await.unwrap().unwrap(), " = 4");
+        assert!(stream.next_chunk().await.is_none());
+    }
+
+    #[test]
+    fn test_deployment_defaults_to_model() {
+        let spec = ProviderSpec::new("Azure_OpenAI", "gpt-4o-mini")
+            .with_base_url("https://contoso.openai.azure.com/".to_string());
+        assert!(is_azure(&spec.provider));
+        assert_eq!(
+            AzureOpenAIProvider::from_spec(&spec).chat_completions_url(),
+            "https://contoso.openai.azure.com/openai/deployments/gpt-4o-mini/chat/completions"
+        );
+    }
+}
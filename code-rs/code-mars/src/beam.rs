@@ -0,0 +1,268 @@
+//! Beam-search aggregation.
+//!
+//! Keeps the `beam_width` best partial solutions by verifier score, asks the
+//! LLM to continue each of them, scores the continuations, and prunes back to
+//! the beam width. This repeats for `depth` rounds.
+//!
+//! Every expansion emits a [`MarsEvent::BeamExpanded`] so progress can be
+//! followed live.
+
+use crate::LLMProvider;
+use crate::Result;
+use crate::aggregator::{split_final_answer, verifier_score};
+use crate::prompts;
+use crate::types::{GenerationPhase, MarsEvent, Solution};
+use tokio::sync::mpsc;
+
+/// Configuration for beam-search aggregation
+#[derive(Clone, Debug)]
+pub struct BeamSearchConfig {
+    /// Number of partial solutions kept after each round (default: 3)
+    pub beam_width: usize,
+    /// Number of expansion rounds (default: 2)
+    pub depth: usize,
+    /// Continuations generated per beam entry (default: 2)
+    pub expansions_per_beam: usize,
+    /// Temperature used for continuations (default: 0.7)
+    pub continuation_temperature: f32,
+    /// System prompt for continuations (default: [`prompts::MARS_SYSTEM_PROMPT`])
+    pub system_prompt: String,
+}
+
+impl Default for BeamSearchConfig {
+    fn default() -> Self {
+        Self {
+            beam_width: 3,
+            depth: 2,
+            expansions_per_beam: 2,
+            continuation_temperature: 0.7,
+            system_prompt: prompts::MARS_SYSTEM_PROMPT.to_string(),
+        }
+    }
+}
+
+/// A partial solution in the beam with its verifier score
+#[derive(Clone, Debug)]
+struct BeamEntry {
+    solution: Solution,
+    score: f32,
+}
+
+/// Beam search over LLM continuations of partial solutions
+pub struct BeamSearchAggregator;
+
+impl BeamSearchAggregator {
+    /// Run beam search from the given solutions and return the final beam
+    ///
+    /// The returned solutions are sorted by score, best first. When no
+    /// expansion round runs, nothing is returned.
+    pub async fn search(
+        query: &str,
+        solutions: &[Solution],
+        config: &BeamSearchConfig,
+        provider: &dyn LLMProvider,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Result<Vec<Solution>> {
+        if solutions.is_empty() || config.depth == 0 {
+            return Ok(Vec::new());
+        }
+
+        let beam_width = config.beam_width.max(1);
+        let mut beam = Vec::with_capacity(solutions.len());
+        for solution in solutions {
+            let score = verifier_score(query, solution, provider).await?;
+            beam.push(BeamEntry {
+                solution: solution.clone(),
+                score,
+            });
+        }
+        Self::prune(&mut beam, beam_width);
+
+        let mut expanded = false;
+        for depth in 0..config.depth {
+            let mut candidates = Vec::with_capacity(beam.len() * config.expansions_per_beam);
+            for entry in &beam {
+                for _ in 0..config.expansions_per_beam {
+                    let child =
+                        Self::continue_solution(query, &entry.solution, depth, config, provider)
+                            .await?;
+                    let score = verifier_score(query, &child, provider).await?;
+
+                    let _result = tx
+                        .send(MarsEvent::BeamExpanded {
+                            depth,
+                            parent_id: entry.solution.id.clone(),
+                            solution_id: child.id.clone(),
+                            score,
+                        })
+                        .await;
+
+                    candidates.push(BeamEntry {
+                        solution: child,
+                        score,
+                    });
+                }
+            }
+
+            if candidates.is_empty() {
+                break;
+            }
+            Self::prune(&mut candidates, beam_width);
+            beam = candidates;
+            expanded = true;
+        }
+
+        if !expanded {
+            return Ok(Vec::new());
+        }
+        Ok(beam.into_iter().map(|entry| entry.solution).collect())
+    }
+
+    /// Keep the `width` highest-scoring entries, best first
+    fn prune(entries: &mut Vec<BeamEntry>, width: usize) {
+        entries.sort_by(|a, b| b.score.total_cmp(&a.score));
+        entries.truncate(width);
+    }
+
+    /// Ask the LLM to continue a partial solution by its next reasoning steps
+    async fn continue_solution(
+        query: &str,
+        partial: &Solution,
+        depth: usize,
+        config: &BeamSearchConfig,
+        provider: &dyn LLMProvider,
+    ) -> Result<Solution> {
+        let temperature = config.continuation_temperature;
+        let prompt = format!(
+            "{}\n\nProblem:\n{}\n\nPartial solution:\nReasoning: {}\nAnswer so far: {}",
+            prompts::BEAM_CONTINUATION_PROMPT,
+            query,
+            partial.reasoning,
+            partial.answer
+        );
+        let response = provider
+            .complete_with_temperature(&prompt, Some(&config.system_prompt), temperature)
+            .await?;
+
+        let (continuation, answer) = split_final_answer(&response.text);
+        let reasoning = format!("{}\n\n{}", partial.reasoning, continuation);
+        let mut child = Solution::new(
+            format!("beam-search-depth-{depth}"),
+            reasoning,
+            answer,
+            temperature,
+            0,
+        );
+        child.record_completion(&response, prompt.len());
+        child.phase = GenerationPhase::Aggregated;
+        child.steps = crate::agent::Agent::parse_steps(&child.reasoning);
+        child.record_provider(provider, Some(temperature));
+        child.parent_ids = vec![partial.id.clone()];
+
+        Ok(child)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+
+    /// Mock provider whose continuations always reach "4", which it approves
+    struct MockExpander;
+
+    #[async_trait]
+    impl LLMProvider for MockExpander {
+        async fn complete(
+            &self,
+            prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if system_prompt == Some(prompts::VERIFICATION_SYSTEM_PROMPT) {
+                let verdict = if prompt.contains("Answer: 4") {
+                    "RESULT: CORRECT\nSCORE: 0.9"
+                } else {
+                    "RESULT: INCORRECT\nSCORE: 0.1"
+                };
+                Ok(verdict.into())
+            } else {
+                Ok("Step 1: 2 + 2 = 4\nANSWER: 4".into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-expander"
+        }
+    }
+
+    fn solution(answer: &str) -> Solution {
+        Solution::new(
+            "agent".to_string(),
+            "Start".to_string(),
+            answer.to_string(),
+            0.5,
+            10,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_beam_search_expands_and_prunes() {
+        let config = BeamSearchConfig {
+            beam_width: 2,
+            depth: 2,
+            expansions_per_beam: 2,
+            ..BeamSearchConfig::default()
+        };
+        let initial = vec![solution("3"), solution("5"), solution("6")];
+        let (tx, mut rx) = mpsc::channel(100);
+
+        let beam =
+            BeamSearchAggregator::search("What is 2+2?", &initial, &config, &MockExpander, &tx)
+                .await
+                .unwrap();
+
+        assert_eq!(beam.len(), 2);
+        for solution in &beam {
+            assert_eq!(solution.answer, "4");
+            assert!(solution.reasoning.starts_with("Start"));
+            assert_eq!(solution.agent_id, "beam-search-depth-1");
+            assert_eq!(solution.phase, GenerationPhase::Aggregated);
+            assert_eq!(solution.parent_ids.len(), 1);
+        }
+
+        drop(tx);
+        let mut expansions = 0;
+        while let Some(event) = rx.recv().await {
+            if let MarsEvent::BeamExpanded { score, .. } = event {
+                assert!((score - 0.9).abs() < 1e-6);
+                expansions += 1;
+            }
+        }
+        // Two beam entries, two continuations each, over two rounds
+        assert_eq!(expansions, 8);
+    }
+
+    #[tokio::test]
+    async fn test_beam_search_without_depth() {
+        let config = BeamSearchConfig {
+            depth: 0,
+            ..BeamSearchConfig::default()
+        };
+        let (tx, _rx) = mpsc::channel(100);
+
+        let beam = BeamSearchAggregator::search("q", &[solution("4")], &config, &MockExpander, &tx)
+            .await
+            .unwrap();
+        assert!(beam.is_empty());
+    }
+}
@@ -0,0 +1,794 @@
+//! Benchmark harness: run MARS over a dataset and grade the answers.
+//!
+//! [`Dataset::load`] reads JSON Lines files in the shapes of the common
+//! reasoning benchmarks, detecting the format from the first record:
+//!
+//! - GSM8K: `question`, and `answer` ending in `#### <number>`
+//! - MATH: `problem`, and `solution` with a `\boxed{}` answer (or `answer`)
+//! - HumanEval: `task_id`, `prompt`, `test` and `entry_point`
+//! - Generic: `question` and `answer`, with an optional `id`
+//!
+//! A [`Benchmark`] answers each item with a fresh coordinator, so runs do not
+//! share strategies, and grades the answer with a [`Grader`]. Each format
+//! has a default grader ([`DatasetFormat::grader`]); implement the trait to
+//! grade differently. The [`BenchReport`] gives accuracy, cost and latency,
+//! along with every item's result.
+
+use crate::answer::{boxed, marked_answer};
+use crate::code_verifier::{CodeBlock, CodeVerifier, ExecutionLimits, ExecutionSandbox};
+use crate::config::MarsConfig;
+use crate::coordinator::{MarsCoordinator, RunOptions};
+use crate::model_router::LLMProvider;
+use crate::types::{AnswerType, TypedAnswer};
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Layout of a benchmark's JSON Lines records
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetFormat {
+    /// Grade-school math word problems with numeric answers
+    Gsm8k,
+    /// Competition math with LaTeX answers
+    Math,
+    /// Python functions checked by their unit tests
+    HumanEval,
+    /// Questions with reference answers, compared as text
+    Generic,
+}
+
+impl DatasetFormat {
+    /// The format `record` is in
+    pub fn detect(record: &Value) -> Self {
+        let has = |field: &str| record.get(field).is_some();
+        if has("entry_point") && has("test") {
+            Self::HumanEval
+        } else if has("problem") && (has("solution") || has("answer")) {
+            Self::Math
+        } else if record
+            .get("answer")
+            .and_then(Value::as_str)
+            .is_some_and(|answer| answer.contains("####"))
+        {
+            Self::Gsm8k
+        } else {
+            Self::Generic
+        }
+    }
+
+    /// The grader answers to this format's items are checked with; code runs
+    /// under `config`'s execution limits and sandbox
+    pub fn grader(self, config: &MarsConfig) -> Arc<dyn Grader> {
+        match self {
+            Self::Gsm8k => Arc::new(NumericGrader::default()),
+            Self::Math => Arc::new(MathGrader),
+            Self::HumanEval => Arc::new(CodeExecutionGrader::for_config(config)),
+            Self::Generic => Arc::new(ExactMatchGrader),
+        }
+    }
+
+    /// The item on line `line` of dataset `name`
+    fn item(self, name: &str, line: usize, record: &Value) -> Result<BenchItem> {
+        let field = |key: &str| {
+            record.get(key).and_then(Value::as_str).ok_or_else(|| {
+                MarsError::ParsingError(format!("{name} line {line}: missing string field {key}"))
+            })
+        };
+        let id = ["id", "task_id", "unique_id"]
+            .iter()
+            .find_map(|key| match record.get(*key)? {
+                Value::String(id) => Some(id.clone()),
+                Value::Number(id) => Some(id.to_string()),
+                _ => None,
+            })
+            .unwrap_or_else(|| format!("{name}/{line}"));
+
+        let item = match self {
+            Self::Gsm8k => {
+                let answer = field("answer")?;
+                let expected = answer.rsplit("####").next().unwrap_or(answer);
+                BenchItem::new(id, field("question")?, expected.trim().replace(',', ""))
+            }
+            Self::Math => {
+                let expected = match field("answer") {
+                    Ok(answer) => answer,
+                    Err(_) => boxed(field("solution")?).ok_or_else(|| {
+                        MarsError::ParsingError(format!(
+                            "{name} line {line}: solution has no \\boxed{{}} answer"
+                        ))
+                    })?,
+                };
+                BenchItem::new(id, field("problem")?, expected.trim())
+            }
+            Self::HumanEval => {
+                let prompt = field("prompt")?;
+                let question = format!(
+                    "Complete this Python function. Reply with the whole function, including \
+                     its signature and imports, in a ```python code block.\n\n```python\n{}\n```",
+                    prompt.trim_end()
+                );
+                let solution = format!("{prompt}{}", field("canonical_solution").unwrap_or(""));
+                BenchItem::new(id, question, solution).with_tests(CodeTests {
+                    language: "python".to_string(),
+                    prelude: prompt.to_string(),
+                    harness: format!("{}\n\ncheck({})\n", field("test")?, field("entry_point")?),
+                })
+            }
+            Self::Generic => BenchItem::new(id, field("question")?, field("answer")?.trim()),
+        };
+        Ok(item)
+    }
+}
+
+impl std::str::FromStr for DatasetFormat {
+    type Err = MarsError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().replace('-', "_").as_str() {
+            "gsm8k" => Ok(Self::Gsm8k),
+            "math" => Ok(Self::Math),
+            "humaneval" | "human_eval" => Ok(Self::HumanEval),
+            "generic" => Ok(Self::Generic),
+            _ => Err(MarsError::InvalidConfiguration(format!(
+                "unknown dataset format {name:?}; expected gsm8k, math, humaneval or generic"
+            ))),
+        }
+    }
+}
+
+/// Tests a code answer must pass
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CodeTests {
+    /// Language the program is run as
+    pub language: String,
+    /// Code placed before the answer, e.g. the function's stub and imports
+    pub prelude: String,
+    /// Code placed after the answer that fails when a test fails
+    pub harness: String,
+}
+
+/// One question of a dataset
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BenchItem {
+    /// ID from the record, or `<dataset>/<line>`
+    pub id: String,
+    /// The query MARS answers
+    pub question: String,
+    /// Reference answer
+    pub expected: String,
+    /// Tests run on code answers, for code datasets
+    #[serde(default)]
+    pub tests: Option<CodeTests>,
+}
+
+impl BenchItem {
+    /// An item answered by `expected`
+    pub fn new(
+        id: impl Into<String>,
+        question: impl Into<String>,
+        expected: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            question: question.into(),
+            expected: expected.into(),
+            tests: None,
+        }
+    }
+
+    /// Check answers by running `tests` on them
+    pub fn with_tests(mut self, tests: CodeTests) -> Self {
+        self.tests = Some(tests);
+        self
+    }
+}
+
+/// Items of a benchmark
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Dataset {
+    /// Name shown in reports, the file stem when loaded from a file
+    pub name: String,
+    /// Layout the items were read from
+    pub format: DatasetFormat,
+    /// Items in file order
+    pub items: Vec<BenchItem>,
+}
+
+impl Dataset {
+    /// Read the JSON Lines file at `path`, in `format` or else the format of
+    /// its first record
+    pub fn load(path: impl AsRef<Path>, format: Option<DatasetFormat>) -> Result<Self> {
+        let path = path.as_ref();
+        let jsonl = std::fs::read_to_string(path).map_err(|e| {
+            MarsError::StorageError(format!("Failed to read {}: {e}", path.display()))
+        })?;
+        let name = path
+            .file_stem()
+            .map_or_else(|| "dataset".into(), |stem| stem.to_string_lossy());
+        Self::parse(&name, &jsonl, format)
+    }
+
+    /// Parse the JSON Lines `jsonl` as dataset `name`
+    pub fn parse(name: &str, jsonl: &str, format: Option<DatasetFormat>) -> Result<Self> {
+        let mut records = Vec::new();
+        for (index, line) in jsonl.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: Value = serde_json::from_str(line)
+                .map_err(|e| MarsError::ParsingError(format!("{name} line {}: {e}", index + 1)))?;
+            records.push((index + 1, record));
+        }
+        let format = format
+            .or_else(|| {
+                records
+                    .first()
+                    .map(|(_, record)| DatasetFormat::detect(record))
+            })
+            .unwrap_or(DatasetFormat::Generic);
+        let items = records
+            .iter()
+            .map(|(line, record)| format.item(name, *line, record))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            name: name.to_string(),
+            format,
+            items,
+        })
+    }
+
+    /// Keep only the first `limit` items
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.items.truncate(limit);
+        self
+    }
+}
+
+/// Decides whether an answer to a benchmark item is correct
+#[async_trait]
+pub trait Grader: Send + Sync {
+    /// Whether `answer` correctly answers `item`
+    ///
+    /// An error means the answer could not be graded; it counts as wrong.
+    async fn grade(&self, item: &BenchItem, answer: &str) -> Result<bool>;
+}
+
+/// Compares the answer's `ANSWER:` line, or the whole answer, with the
+/// reference, ignoring case, spacing and a final period
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExactMatchGrader;
+
+#[async_trait]
+impl Grader for ExactMatchGrader {
+    async fn grade(&self, item: &BenchItem, answer: &str) -> Result<bool> {
+        let normalize = |text: &str| {
+            text.trim()
+                .trim_end_matches('.')
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase()
+        };
+        let answer = marked_answer(answer).unwrap_or(answer);
+        Ok(normalize(answer) == normalize(&item.expected))
+    }
+}
+
+/// Compares the number the answer gives with the reference number
+#[derive(Clone, Copy, Debug)]
+pub struct NumericGrader {
+    /// Largest difference accepted, relative to the reference (at least 1)
+    pub tolerance: f64,
+}
+
+impl Default for NumericGrader {
+    fn default() -> Self {
+        Self { tolerance: 1e-6 }
+    }
+}
+
+#[async_trait]
+impl Grader for NumericGrader {
+    async fn grade(&self, item: &BenchItem, answer: &str) -> Result<bool> {
+        let number = |text: &str| match AnswerType::Numeric.extract(text)? {
+            TypedAnswer::Numeric { value } => Ok(value),
+            other => Err(MarsError::AnswerExtractionError(format!(
+                "expected a number, got {other:?}"
+            ))),
+        };
+        let expected = number(&item.expected)?;
+        let Ok(given) = number(answer) else {
+            return Ok(false);
+        };
+        Ok((given - expected).abs() <= self.tolerance * expected.abs().max(1.0))
+    }
+}
+
+/// Compares the answer's last `\boxed{}` (or `ANSWER:` line) with the
+/// reference after normalizing LaTeX spacing and notation
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MathGrader;
+
+impl MathGrader {
+    /// `answer` without the notation that does not change its value
+    fn normalize(answer: &str) -> String {
+        let mut answer = answer
+            .replace("\\left", "")
+            .replace("\\right", "")
+            .replace("\\dfrac", "\\frac")
+            .replace("\\tfrac", "\\frac")
+            .replace("^\\circ", "")
+            .replace("^{\\circ}", "")
+            .replace("\\%", "")
+            .replace(['$', '%'], "");
+        for spacing in ["\\!", "\\,", "\\;", "\\ "] {
+            answer = answer.replace(spacing, "");
+        }
+        answer.retain(|c| !c.is_whitespace());
+        answer.trim_end_matches('.').to_string()
+    }
+}
+
+#[async_trait]
+impl Grader for MathGrader {
+    async fn grade(&self, item: &BenchItem, answer: &str) -> Result<bool> {
+        let given = boxed(answer)
+            .or_else(|| marked_answer(answer))
+            .unwrap_or(answer);
+        let (given, expected) = (Self::normalize(given), Self::normalize(&item.expected));
+        let number = |text: &str| text.replace(',', "").parse::<f64>().ok();
+        Ok(match (number(&given), number(&expected)) {
+            (Some(given), Some(expected)) => (given - expected).abs() <= 1e-6,
+            _ => given == expected,
+        })
+    }
+}
+
+/// Runs the answer's last code block in the tests' language (or untagged)
+/// between the item's test prelude and harness; the answer is correct when
+/// the program succeeds. An answer without fences is taken as bare code, and
+/// one whose blocks are all in other languages is wrong without being run.
+#[derive(Clone, Debug, Default)]
+pub struct CodeExecutionGrader {
+    /// Limits the test program runs under
+    pub limits: ExecutionLimits,
+    /// Isolation the test program runs under
+    pub sandbox: ExecutionSandbox,
+}
+
+impl CodeExecutionGrader {
+    /// Run programs under `config`'s execution limits and sandbox
+    pub fn for_config(config: &MarsConfig) -> Self {
+        Self {
+            limits: config.code_execution_limits.clone(),
+            sandbox: config.code_execution_sandbox.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Grader for CodeExecutionGrader {
+    async fn grade(&self, item: &BenchItem, answer: &str) -> Result<bool> {
+        let tests = item.tests.as_ref().ok_or_else(|| {
+            MarsError::InvalidConfiguration(format!("item {} has no tests to run", item.id))
+        })?;
+        let blocks = CodeVerifier::extract_code_blocks(answer);
+        let interpreter = CodeVerifier::interpreter_for(&tests.language);
+        let code = if blocks.is_empty() {
+            answer.to_string()
+        } else {
+            match blocks.into_iter().rev().find(|block| {
+                block.language.is_empty()
+                    || CodeVerifier::interpreter_for(&block.language) == interpreter
+            }) {
+                Some(block) => block.code,
+                None => return Ok(false),
+            }
+        };
+        let program = CodeBlock {
+            language: tests.language.clone(),
+            code: format!("{}\n{code}\n{}", tests.prelude, tests.harness),
+        };
+        let outcome = CodeVerifier::new(Vec::new(), self.limits.clone())
+            .with_sandbox(self.sandbox.clone())
+            .execute(&program, "")
+            .await?;
+        Ok(outcome.success)
+    }
+}
+
+/// How MARS did on one item
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ItemResult {
+    /// ID of the item
+    pub id: String,
+    /// Whether the grader accepted the answer
+    pub correct: bool,
+    /// The final answer, if the run completed
+    pub answer: Option<String>,
+    /// Reference answer of the item
+    pub expected: String,
+    /// Dollars the run spent
+    pub cost_usd: f64,
+    /// Wall-clock time of the run in milliseconds
+    pub latency_ms: u64,
+    /// Tokens the run used
+    pub total_tokens: usize,
+    /// Why the run or its grading failed
+    pub error: Option<String>,
+}
+
+/// Totals over a benchmark's items
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct BenchSummary {
+    /// Items run
+    pub items: usize,
+    /// Items answered correctly
+    pub correct: usize,
+    /// Items whose run or grading failed
+    pub errors: usize,
+    /// Fraction of items answered correctly
+    pub accuracy: f64,
+    /// Dollars spent on all items
+    pub total_cost_usd: f64,
+    /// Dollars spent per item, on average
+    pub mean_cost_usd: f64,
+    /// Median run time in milliseconds
+    pub p50_latency_ms: u64,
+    /// 95th percentile run time in milliseconds
+    pub p95_latency_ms: u64,
+    /// Tokens used on all items
+    pub total_tokens: usize,
+}
+
+impl BenchSummary {
+    /// Totals of `results`
+    pub fn of(results: &[ItemResult]) -> Self {
+        if results.is_empty() {
+            return Self::default();
+        }
+        let items = results.len();
+        let correct = results.iter().filter(|result| result.correct).count();
+        let total_cost_usd: f64 = results.iter().map(|result| result.cost_usd).sum();
+        let mut latencies: Vec<u64> = results.iter().map(|result| result.latency_ms).collect();
+        latencies.sort_unstable();
+        // Nearest-rank percentile
+        let percentile =
+            |p: f64| latencies[((p * items as f64).ceil() as usize).clamp(1, items) - 1];
+        Self {
+            items,
+            correct,
+            errors: results
+                .iter()
+                .filter(|result| result.error.is_some())
+                .count(),
+            accuracy: correct as f64 / items as f64,
+            total_cost_usd,
+            mean_cost_usd: total_cost_usd / items as f64,
+            p50_latency_ms: percentile(0.5),
+            p95_latency_ms: percentile(0.95),
+            total_tokens: results.iter().map(|result| result.total_tokens).sum(),
+        }
+    }
+}
+
+/// Results of a benchmark run
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BenchReport {
+    /// Name of the dataset
+    pub dataset: String,
+    /// Totals over the items
+    pub summary: BenchSummary,
+    /// Each item's result, in dataset order
+    pub items: Vec<ItemResult>,
+}
+
+impl BenchReport {
+    /// Report of `items`, run from `dataset`
+    pub fn new(dataset: impl Into<String>, items: Vec<ItemResult>) -> Self {
+        Self {
+            dataset: dataset.into(),
+            summary: BenchSummary::of(&items),
+            items,
+        }
+    }
+
+    /// A Markdown report: the totals, then the items that were missed
+    pub fn to_markdown(&self) -> String {
+        let summary = &self.summary;
+        let mut report = format!("# MARS Benchmark: {}\n\n", self.dataset);
+        report.push_str(&format!(
+            "- **Accuracy:** {:.1}% ({} of {})\n",
+            summary.accuracy * 100.0,
+            summary.correct,
+            summary.items
+        ));
+        report.push_str(&format!("- **Errors:** {}\n", summary.errors));
+        report.push_str(&format!(
+            "- **Cost:** ${:.4} (${:.4} per item)\n",
+            summary.total_cost_usd, summary.mean_cost_usd
+        ));
+        report.push_str(&format!(
+            "- **Latency:** p50 {:.1}s, p95 {:.1}s\n",
+            summary.p50_latency_ms as f64 / 1000.0,
+            summary.p95_latency_ms as f64 / 1000.0
+        ));
+        report.push_str(&format!("- **Tokens:** {}\n", summary.total_tokens));
+
+        let misses: Vec<&ItemResult> = self.items.iter().filter(|item| !item.correct).collect();
+        if !misses.is_empty() {
+            report.push_str("\n## Misses\n\n| Item | Answer | Expected |\n|---|---|---|\n");
+            for item in misses {
+                let answer = match (&item.answer, &item.error) {
+                    (_, Some(error)) => format!("error: {error}"),
+                    (Some(answer), None) => answer.clone(),
+                    (None, None) => String::new(),
+                };
+                report.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    item.id,
+                    table_cell(&answer),
+                    table_cell(&item.expected)
+                ));
+            }
+        }
+        report
+    }
+}
+
+/// Characters of an answer shown in a table cell
+const CELL_LEN: usize = 80;
+
+/// `text` on one line, shortened, with pipes escaped
+fn table_cell(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut cell: String = line.chars().take(CELL_LEN).collect();
+    if line.chars().count() > CELL_LEN {
+        cell.push('…');
+    }
+    cell.replace('|', "\\|")
+}
+
+/// Runs MARS on every item of a dataset and grades the answers
+pub struct Benchmark {
+    config: MarsConfig,
+    provider: Arc<dyn LLMProvider>,
+    grader: Arc<dyn Grader>,
+    concurrency: usize,
+}
+
+impl Benchmark {
+    /// Answer items with `config` on `provider`, graded by `grader`, one
+    /// item at a time
+    pub fn new(
+        config: MarsConfig,
+        provider: Arc<dyn LLMProvider>,
+        grader: Arc<dyn Grader>,
+    ) -> Self {
+        Self {
+            config,
+            provider,
+            grader,
+            concurrency: 1,
+        }
+    }
+
+    /// Run up to `concurrency` items at once
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Each item's result as it is graded, in dataset order
+    pub fn results<'a>(&'a self, dataset: &'a Dataset) -> impl Stream<Item = ItemResult> + 'a {
+        futures::stream::iter(&dataset.items)
+            .map(|item| self.run_item(item))
+            .buffered(self.concurrency)
+    }
+
+    /// Run every item of `dataset`
+    pub async fn run(&self, dataset: &Dataset) -> BenchReport {
+        let items = self.results(dataset).collect().await;
+        BenchReport::new(&dataset.name, items)
+    }
+
+    async fn run_item(&self, item: &BenchItem) -> ItemResult {
+        let started = Instant::now();
+        let mut coordinator =
+            MarsCoordinator::with_provider(self.config.clone(), Arc::clone(&self.provider));
+        let options = RunOptions::new().with_tag(item.id.clone());
+        let output = coordinator.run_with_options(&item.question, options).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let mut result = ItemResult {
+            id: item.id.clone(),
+            correct: false,
+            answer: None,
+            expected: item.expected.clone(),
+            cost_usd: coordinator.cost_summary().total_usd,
+            latency_ms,
+            total_tokens: 0,
+            error: None,
+        };
+        match output {
+            Ok(output) => {
+                match self.grader.grade(item, &output.answer).await {
+                    Ok(correct) => result.correct = correct,
+                    Err(e) => result.error = Some(format!("grading failed: {e}")),
+                }
+                result.answer = Some(output.answer.trim().to_string());
+                result.total_tokens = output.total_tokens;
+            }
+            Err(e) => result.error = Some(e.to_string()),
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::ModelPricing;
+    use crate::test_support::Four;
+
+    #[test]
+    fn test_datasets_load_in_detected_formats() {
+        let gsm8k = Dataset::parse(
+            "gsm8k",
+            r#"{"question": "How many?", "answer": "2 + 2 = 4\n#### 1,004"}
+
+{"question": "And now?", "answer": "7 in all\n#### 7"}"#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(gsm8k.format, DatasetFormat::Gsm8k);
+        assert_eq!(gsm8k.items.len(), 2);
+        assert_eq!(gsm8k.items[0].id, "gsm8k/1");
+        assert_eq!(gsm8k.items[0].expected, "1004");
+        assert_eq!(gsm8k.items[1].id, "gsm8k/3");
+
+        let math = Dataset::parse(
+            "math",
+            r#"{"problem": "Halve 1.", "solution": "It is $\\boxed{\\frac{1}{2}}$.", "unique_id": "algebra/1"}"#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(math.format, DatasetFormat::Math);
+        assert_eq!(math.items[0].id, "algebra/1");
+        assert_eq!(math.items[0].expected, "\\frac{1}{2}");
+
+        let humaneval = Dataset::parse(
+            "humaneval",
+            r#"{"task_id": "HumanEval/0", "prompt": "def double(x):\n    \"\"\"Twice x\"\"\"\n", "canonical_solution": "    return 2 * x\n", "test": "def check(f):\n    assert f(3) == 6", "entry_point": "double"}"#,
+            None,
+        )
+        .unwrap();
+        let item = &humaneval.items[0];
+        assert_eq!(humaneval.format, DatasetFormat::HumanEval);
+        assert_eq!(item.id, "HumanEval/0");
+        assert!(item.question.contains("```python\ndef double(x):"));
+        let tests = item.tests.as_ref().unwrap();
+        assert!(tests.harness.ends_with("check(double)\n"));
+
+        let generic = Dataset::parse(
+            "qa",
+            r#"{"id": 7, "question": "Capital of France?", "answer": "Paris"}"#,
+            None,
+        )
+        .unwrap()
+        .with_limit(0);
+        assert_eq!(generic.format, DatasetFormat::Generic);
+        assert!(generic.items.is_empty());
+
+        let err = Dataset::parse("qa", "{\"question\": \"q\"}\nnot json", None).unwrap_err();
+        assert!(err.to_string().contains("qa line 2"));
+        assert_eq!(
+            "human-eval".parse::<DatasetFormat>().unwrap(),
+            DatasetFormat::HumanEval
+        );
+    }
+
+    #[tokio::test]
+    async fn test_graders_normalize_answers() {
+        let item = BenchItem::new("1", "q", "1,004");
+        let numeric = NumericGrader::default();
+        assert!(
+            numeric
+                .grade(&item, "So the total is 1004 apples.")
+                .await
+                .unwrap()
+        );
+        assert!(!numeric.grade(&item, "ANSWER: 1005").await.unwrap());
+        assert!(!numeric.grade(&item, "I cannot tell").await.unwrap());
+
+        let item = BenchItem::new("2", "q", "\\frac{1}{2}");
+        assert!(
+            MathGrader
+                .grade(&item, "Thus $\\boxed{\\dfrac{1}{2}}$.")
+                .await
+                .unwrap()
+        );
+        assert!(
+            !MathGrader
+                .grade(&item, "\\boxed{\\frac{1}{3}}")
+                .await
+                .unwrap()
+        );
+        let item = BenchItem::new("3", "q", "90^\\circ");
+        assert!(MathGrader.grade(&item, "ANSWER: 90").await.unwrap());
+
+        let item = BenchItem::new("4", "q", "Paris");
+        assert!(
+            ExactMatchGrader
+                .grade(&item, "It is Paris.\nANSWER: paris.")
+                .await
+                .unwrap()
+        );
+        assert!(!ExactMatchGrader.grade(&item, "Lyon").await.unwrap());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_code_grader_runs_the_tests() {
+        let item = BenchItem::new("sh/1", "Write double", "").with_tests(CodeTests {
+            language: "sh".to_string(),
+            prelude: "set -e".to_string(),
+            harness: "test \"$(double 3)\" = 6".to_string(),
+        });
+        let grader = CodeExecutionGrader {
+            sandbox: ExecutionSandbox::Unsandboxed,
+            ..Default::default()
+        };
+        let right = "Here:\n```sh\ndouble() { echo $(($1 * 2)); }\n```";
+        assert!(grader.grade(&item, right).await.unwrap());
+        let wrong = "```sh\ndouble() { echo $(($1 + 2)); }\n```";
+        assert!(!grader.grade(&item, wrong).await.unwrap());
+        let with_output = format!("{right}\nwhich prints:\n```text\n6\n```");
+        assert!(grader.grade(&item, &with_output).await.unwrap());
+        let other_language = "```python\ndef double(x):\n    return x * 2\n```";
+        assert!(!grader.grade(&item, other_language).await.unwrap());
+
+        let untested = BenchItem::new("sh/2", "q", "");
+        assert!(grader.grade(&untested, right).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_reports_accuracy_cost_and_latency() {
+        let dataset = Dataset::parse(
+            "sums",
+            r#"{"question": "What is 2+2?", "answer": "4"}
+{"question": "What is 3+3?", "answer": "6"}"#,
+            None,
+        )
+        .unwrap();
+        let config = MarsConfig::default()
+            .with_max_iterations(1)
+            .with_model_pricing("four", ModelPricing::new(1.0, 1.0));
+        let report = Benchmark::new(config, Arc::new(Four), Arc::new(NumericGrader::default()))
+            .with_concurrency(2)
+            .run(&dataset)
+            .await;
+
+        assert_eq!(report.dataset, "sums");
+        assert_eq!(report.items[0].id, "sums/1");
+        assert!(report.items[0].correct);
+        assert!(!report.items[1].correct);
+        assert_eq!(report.summary.items, 2);
+        assert_eq!(report.summary.accuracy, 0.5);
+        assert_eq!(report.summary.errors, 0);
+        assert!(report.summary.total_cost_usd > 0.0);
+        assert!(report.summary.total_tokens > 0);
+        assert!(report.summary.p95_latency_ms >= report.summary.p50_latency_ms);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("**Accuracy:** 50.0% (1 of 2)"));
+        assert!(markdown.contains("| sums/2 |"));
+        assert!(!markdown.contains("| sums/1 |"));
+    }
+}
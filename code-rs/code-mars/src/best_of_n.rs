@@ -0,0 +1,292 @@
+//! Best-of-N aggregation.
+//!
+//! Samples N fresh completions for the query, scores each one, and keeps only
+//! the highest-scoring sample. This is the cheap baseline that RSA, MOA, and
+//! MCTS are usually compared against.
+//!
+//! Samples are scored either by the built-in verifier or by a single reward
+//! prompt that asks the model for a `SCORE:` line.
+
+use crate::LLMProvider;
+use crate::MarsError;
+use crate::Result;
+use crate::agent::Agent;
+use crate::aggregator::verifier_score;
+use crate::prompts;
+use crate::types::{BestOfNScorer, GenerationPhase, Solution};
+
+/// Configuration for best-of-N aggregation
+#[derive(Clone, Debug)]
+pub struct BestOfNConfig {
+    /// Number of completions to sample (default: 4)
+    pub num_samples: usize,
+    /// How samples are scored (default: verifier)
+    pub scorer: BestOfNScorer,
+    /// Sampling temperatures, cycled across samples (default: 0.3, 0.6, 1.0)
+    pub temperatures: Vec<f32>,
+    /// Whether samples use the thinking-tags system prompt (default: true)
+    pub use_thinking_tags: bool,
+    /// Prompt overrides for sampling (default: built-in prompts)
+    pub prompts: prompts::PromptSet,
+}
+
+impl Default for BestOfNConfig {
+    fn default() -> Self {
+        Self {
+            num_samples: 4,
+            scorer: BestOfNScorer::Verifier,
+            temperatures: vec![0.3, 0.6, 1.0],
+            use_thinking_tags: true,
+            prompts: prompts::PromptSet::default(),
+        }
+    }
+}
+
+/// Samples N completions and keeps the best one
+pub struct BestOfNAggregator;
+
+impl BestOfNAggregator {
+    /// Sample `num_samples` completions and return the highest-scoring one
+    ///
+    /// Failed samples are skipped; an error is returned only when every
+    /// sample fails. Nothing is returned when `num_samples` is zero.
+    pub async fn select(
+        query: &str,
+        config: &BestOfNConfig,
+        provider: &dyn LLMProvider,
+    ) -> Result<Vec<Solution>> {
+        let mut best: Option<(Solution, f32)> = None;
+        let mut last_error = None;
+
+        for (temperature, count) in Self::temperature_batches(config) {
+            let agent = Agent::new(temperature).with_prompts(config.prompts.clone());
+            let samples = match agent
+                .generate_solutions(query, config.use_thinking_tags, provider, count)
+                .await
+            {
+                Ok(samples) => samples,
+                Err(e) => {
+                    tracing::warn!("Best-of-N samples at temperature {temperature} failed: {e}");
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+
+            for sample in samples {
+                let score = Self::score(query, &sample, config.scorer, provider).await?;
+                if best
+                    .as_ref()
+                    .is_none_or(|(_, best_score)| score > *best_score)
+                {
+                    best = Some((sample, score));
+                }
+            }
+        }
+
+        match (best, last_error) {
+            (Some((mut solution, _score)), _) => {
+                solution.phase = GenerationPhase::Aggregated;
+                Ok(vec![solution])
+            }
+            (None, Some(e)) => Err(MarsError::AggregationError(format!(
+                "All best-of-N samples failed: {e}"
+            ))),
+            (None, None) => Ok(Vec::new()),
+        }
+    }
+
+    /// Number of samples drawn at each temperature, cycling through the
+    /// configured temperatures, so each is requested in one batch
+    fn temperature_batches(config: &BestOfNConfig) -> Vec<(f32, usize)> {
+        let mut batches: Vec<(f32, usize)> = Vec::new();
+        for index in 0..config.num_samples {
+            let temperature = config
+                .temperatures
+                .get(index % config.temperatures.len().max(1))
+                .copied()
+                .unwrap_or(0.7);
+            match batches.iter_mut().find(|(t, _)| *t == temperature) {
+                Some((_, count)) => *count += 1,
+                None => batches.push((temperature, 1)),
+            }
+        }
+        batches
+    }
+
+    /// Score a sample with the configured scorer
+    async fn score(
+        query: &str,
+        sample: &Solution,
+        scorer: BestOfNScorer,
+        provider: &dyn LLMProvider,
+    ) -> Result<f32> {
+        match scorer {
+            BestOfNScorer::Verifier => verifier_score(query, sample, provider).await,
+            BestOfNScorer::RewardPrompt => Self::reward_score(query, sample, provider).await,
+        }
+    }
+
+    /// Ask the model to rate a sample, reading its `SCORE:` line
+    ///
+    /// Responses without a parsable score count as 0.0.
+    async fn reward_score(
+        query: &str,
+        sample: &Solution,
+        provider: &dyn LLMProvider,
+    ) -> Result<f32> {
+        let prompt = format!(
+            "{}\n\nProblem:\n{}\n\nResponse:\nReasoning: {}\nAnswer: {}",
+            prompts::BEST_OF_N_REWARD_PROMPT,
+            query,
+            sample.reasoning,
+            sample.answer
+        );
+        let response = provider.complete(&prompt, None).await?;
+        Ok(Self::parse_reward(&response.text).unwrap_or(0.0))
+    }
+
+    /// Parse the last `SCORE: x` line of a reward response, clamped to 0.0..=1.0
+    fn parse_reward(response: &str) -> Option<f32> {
+        response.lines().rev().find_map(|line| {
+            let value = line.trim().strip_prefix("SCORE:")?;
+            let token = value.split_whitespace().next()?;
+            token
+                .trim_end_matches(|c: char| !c.is_ascii_digit())
+                .parse::<f32>()
+                .ok()
+                .map(|score| score.clamp(0.0, 1.0))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Mock provider that answers "3", "4", "5" in turn and only approves "4"
+    struct MockSampler {
+        calls: AtomicUsize,
+    }
+
+    impl MockSampler {
+        fn new() -> Self {
+            Self {
+                calls: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for MockSampler {
+        async fn complete(
+            &self,
+            prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if system_prompt == Some(prompts::VERIFICATION_SYSTEM_PROMPT) {
+                let verdict = if prompt.contains("Answer: 4") {
+                    "RESULT: CORRECT\nSCORE: 0.9"
+                } else {
+                    "RESULT: INCORRECT\nSCORE: 0.1"
+                };
+                Ok(verdict.into())
+            } else if prompt.starts_with(prompts::BEST_OF_N_REWARD_PROMPT) {
+                let score = if prompt.contains("Answer: 5") {
+                    "SCORE: 0.8"
+                } else {
+                    "SCORE: 0.2"
+                };
+                Ok(score.into())
+            } else {
+                let answer = 3 + self.calls.fetch_add(1, Ordering::SeqCst) % 3;
+                Ok(format!("Step 1: Add the numbers\n---\n{answer}").into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-sampler"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_best_of_n_keeps_verifier_best() {
+        let config = BestOfNConfig {
+            num_samples: 3,
+            use_thinking_tags: false,
+            ..BestOfNConfig::default()
+        };
+
+        let best = BestOfNAggregator::select("What is 2+2?", &config, &MockSampler::new())
+            .await
+            .unwrap();
+
+        assert_eq!(best.len(), 1);
+        assert_eq!(best[0].answer, "4");
+        assert_eq!(best[0].temperature, 0.6);
+        assert_eq!(best[0].phase, GenerationPhase::Aggregated);
+    }
+
+    #[tokio::test]
+    async fn test_best_of_n_with_reward_prompt() {
+        let config = BestOfNConfig {
+            num_samples: 3,
+            scorer: BestOfNScorer::RewardPrompt,
+            use_thinking_tags: false,
+            ..BestOfNConfig::default()
+        };
+
+        let best = BestOfNAggregator::select("What is 2+2?", &config, &MockSampler::new())
+            .await
+            .unwrap();
+
+        assert_eq!(best.len(), 1);
+        assert_eq!(best[0].answer, "5");
+    }
+
+    #[tokio::test]
+    async fn test_best_of_n_without_samples() {
+        let config = BestOfNConfig {
+            num_samples: 0,
+            ..BestOfNConfig::default()
+        };
+
+        let best = BestOfNAggregator::select("q", &config, &MockSampler::new())
+            .await
+            .unwrap();
+        assert!(best.is_empty());
+    }
+
+    #[test]
+    fn test_temperature_batches() {
+        let config = BestOfNConfig {
+            num_samples: 5,
+            ..BestOfNConfig::default()
+        };
+        assert_eq!(
+            BestOfNAggregator::temperature_batches(&config),
+            vec![(0.3, 2), (0.6, 2), (1.0, 1)]
+        );
+    }
+
+    #[test]
+    fn test_parse_reward() {
+        assert_eq!(
+            BestOfNAggregator::parse_reward("Looks good.\nSCORE: 0.75"),
+            Some(0.75)
+        );
+        assert_eq!(BestOfNAggregator::parse_reward("SCORE: 7."), Some(1.0));
+        assert_eq!(BestOfNAggregator::parse_reward("No score here"), None);
+    }
+}
@@ -0,0 +1,224 @@
+//! Query classification for picking a run's settings.
+//!
+//! With `MarsConfig::query_classification` enabled, the coordinator labels
+//! each query before exploring it: math and code queries run with the
+//! matching [`Preset`], short factual ones in lightweight mode, and anything
+//! else with the config as is. Labels come from keyword and length
+//! heuristics ([`classify`]) or from one provider call
+//! ([`classify_with_provider`]).
+
+use crate::LLMProvider;
+use crate::prompts::{QUERY_CLASSIFICATION_PROMPT, render_template};
+use crate::types::Preset;
+use crate::{MarsError, Result};
+
+/// Queries up to this many words without math or code signals are short-form
+pub const SHORT_FORM_MAX_WORDS: usize = 12;
+
+/// Words that mark a programming request
+const CODE_KEYWORDS: &[&str] = &[
+    "algorithm",
+    "bug",
+    "code",
+    "compile",
+    "compiler",
+    "debug",
+    "function",
+    "implement",
+    "java",
+    "javascript",
+    "program",
+    "python",
+    "refactor",
+    "regex",
+    "rust",
+    "script",
+    "sql",
+    "typescript",
+];
+
+/// Words that mark a mathematical problem
+const MATH_KEYWORDS: &[&str] = &[
+    "calculate",
+    "compute",
+    "derivative",
+    "divisible",
+    "equation",
+    "geometry",
+    "integer",
+    "integral",
+    "lemma",
+    "matrix",
+    "polynomial",
+    "prime",
+    "primes",
+    "probability",
+    "proof",
+    "prove",
+    "remainder",
+    "solve",
+    "theorem",
+    "triangle",
+];
+
+/// Kind of query, deciding which settings a run uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryClass {
+    /// Mathematical problem, proof or calculation
+    Math,
+    /// Writing, fixing or explaining code
+    Code,
+    /// Short factual question with a brief answer
+    ShortForm,
+    /// Anything else
+    General,
+}
+
+impl QueryClass {
+    /// Preset a run for this class uses, if any
+    pub fn preset(self) -> Option<Preset> {
+        match self {
+            QueryClass::Math => Some(Preset::Math),
+            QueryClass::Code => Some(Preset::Coding),
+            QueryClass::ShortForm | QueryClass::General => None,
+        }
+    }
+
+    /// Whether a run for this class uses lightweight mode
+    pub fn is_lightweight(self) -> bool {
+        self == QueryClass::ShortForm
+    }
+
+    /// The first category name in a classifier reply
+    fn parse(reply: &str) -> Option<Self> {
+        reply
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .find_map(|word| match word.to_ascii_lowercase().as_str() {
+                "math" => Some(QueryClass::Math),
+                "code" => Some(QueryClass::Code),
+                "short" => Some(QueryClass::ShortForm),
+                "general" => Some(QueryClass::General),
+                _ => None,
+            })
+    }
+}
+
+/// Classify `query` with keyword and length heuristics
+///
+/// Code signals win over math ones, and both over length.
+pub fn classify(query: &str) -> QueryClass {
+    let lowercase = query.to_lowercase();
+    let words: Vec<&str> = lowercase
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let has_keyword = |keywords: &[&str]| words.iter().any(|w| keywords.contains(w));
+
+    if query.contains("```") || has_keyword(CODE_KEYWORDS) {
+        return QueryClass::Code;
+    }
+    let arithmetic =
+        query.chars().any(|c| c.is_ascii_digit()) && query.chars().any(|c| "+*/^=<>".contains(c));
+    if arithmetic || query.contains("\\frac") || has_keyword(MATH_KEYWORDS) {
+        return QueryClass::Math;
+    }
+    if query.split_whitespace().count() <= SHORT_FORM_MAX_WORDS {
+        return QueryClass::ShortForm;
+    }
+    QueryClass::General
+}
+
+/// Classify `query` with one call to `provider`
+///
+/// Fails with [`MarsError::ParsingError`] when the reply names no category.
+pub async fn classify_with_provider(query: &str, provider: &dyn LLMProvider) -> Result<QueryClass> {
+    let prompt = render_template(QUERY_CLASSIFICATION_PROMPT, &[("query", query)]);
+    let reply = provider.complete(&prompt, None).await?.text;
+    QueryClass::parse(&reply).ok_or_else(|| {
+        MarsError::ParsingError(format!("Unrecognized query classification: {reply}"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+
+    /// Mock classifier that always gives the same reply
+    struct MockClassifier(&'static str);
+
+    #[async_trait]
+    impl LLMProvider for MockClassifier {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Ok(self.0.into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-classifier"
+        }
+    }
+
+    #[test]
+    fn test_heuristic_classification() {
+        assert_eq!(classify("What is 2+2?"), QueryClass::Math);
+        assert_eq!(
+            classify("Prove that there are infinitely many primes."),
+            QueryClass::Math
+        );
+        assert_eq!(
+            classify("Write a Python function that reverses a linked list"),
+            QueryClass::Code
+        );
+        assert_eq!(
+            classify("Why does this fail?\n```\nlet x: u8 = 256;\n```"),
+            QueryClass::Code
+        );
+        assert_eq!(
+            classify("What is the capital of France?"),
+            QueryClass::ShortForm
+        );
+        assert_eq!(
+            classify(
+                "Draft a friendly note to my neighbours explaining that the garden party \
+                 moves to Sunday because of the forecast, and invite them to bring chairs"
+            ),
+            QueryClass::General
+        );
+    }
+
+    #[test]
+    fn test_classes_map_to_settings() {
+        assert_eq!(QueryClass::Math.preset(), Some(Preset::Math));
+        assert_eq!(QueryClass::Code.preset(), Some(Preset::Coding));
+        assert_eq!(QueryClass::General.preset(), None);
+        assert!(QueryClass::ShortForm.is_lightweight());
+        assert!(!QueryClass::Math.is_lightweight());
+    }
+
+    #[tokio::test]
+    async fn test_provider_classification() {
+        let class = classify_with_provider("anything", &MockClassifier("Category: Code."))
+            .await
+            .unwrap();
+        assert_eq!(class, QueryClass::Code);
+
+        let err = classify_with_provider("anything", &MockClassifier("not sure"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MarsError::ParsingError(_)));
+    }
+}
@@ -0,0 +1,456 @@
+//! The `code-mars` command line, behind the `cli` feature.
+//!
+//! `code-mars run "question"` runs MARS on one question. The config comes
+//! from `--config` (any file [`MarsConfig::from_file`] reads) with flags
+//! layered on top, and the provider from the config's `provider_routing` or
+//! from `--model provider/model`. Phase progress streams to stderr from the
+//! run's events; the answer (or with `--json`, the whole [`MarsOutput`])
+//! goes to stdout, so the output can be piped.
+//!
+//! `code-mars bench data.jsonl` runs the same config over a benchmark
+//! dataset (see [`crate::bench`]), printing each item's result to stderr and
+//! the [`BenchReport`] to stdout.
+
+use crate::bench::{BenchReport, Benchmark, Dataset, DatasetFormat, ItemResult};
+use crate::config::MarsConfig;
+use crate::coordinator::MarsCoordinator;
+use crate::cost::PricingTable;
+use crate::model_router::{LLMProvider, provider_from_spec};
+use crate::multi_router::MultiProviderRouter;
+use crate::provider_config::ProviderSpec;
+use crate::types::{AggregationMethod, MarsEvent, MarsOutput};
+use crate::{MarsError, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::StreamExt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Multi-agent reasoning from the command line
+#[derive(Debug, Parser)]
+#[command(name = "code-mars", version)]
+pub struct Cli {
+    /// What to do
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// `code-mars` subcommands
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Answer a question with a MARS run
+    Run(RunArgs),
+    /// Run MARS over a benchmark dataset and grade the answers
+    Bench(BenchArgs),
+}
+
+/// Arguments of `code-mars run`
+#[derive(Debug, clap::Args)]
+pub struct RunArgs {
+    /// Question to answer
+    pub question: String,
+
+    /// Config and provider
+    #[command(flatten)]
+    pub mars: MarsArgs,
+
+    /// Print the whole output as JSON instead of the answer
+    #[arg(long)]
+    pub json: bool,
+
+    /// Print no progress
+    #[arg(long, short)]
+    pub quiet: bool,
+}
+
+/// Arguments of `code-mars bench`
+#[derive(Debug, clap::Args)]
+pub struct BenchArgs {
+    /// JSON Lines dataset file
+    pub dataset: PathBuf,
+
+    /// Dataset format: gsm8k, math, humaneval or generic
+    ///
+    /// Detected from the first record by default.
+    #[arg(long)]
+    pub format: Option<DatasetFormat>,
+
+    /// Run only the first N items
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    /// Items run at once
+    #[arg(long, default_value_t = 1)]
+    pub concurrency: usize,
+
+    /// Config and provider
+    #[command(flatten)]
+    pub mars: MarsArgs,
+
+    /// Print the whole report as JSON instead of a Markdown summary
+    #[arg(long)]
+    pub json: bool,
+
+    /// Print no per-item progress
+    #[arg(long, short)]
+    pub quiet: bool,
+}
+
+/// Config and provider flags shared by the subcommands
+#[derive(Debug, clap::Args)]
+pub struct MarsArgs {
+    /// Config file (.toml, .yaml or .json)
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Model to use as `provider/model`, overriding the config's routing
+    ///
+    /// The API key is read from `<PROVIDER>_API_KEY`, e.g. `OPENAI_API_KEY`.
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Number of exploring agents
+    #[arg(long)]
+    pub agents: Option<usize>,
+
+    /// Aggregate solutions with this method
+    #[arg(long, value_enum)]
+    pub aggregation: Option<Aggregation>,
+
+    /// Most improvement iterations
+    #[arg(long)]
+    pub max_iterations: Option<usize>,
+}
+
+/// Aggregation methods selectable with `--aggregation`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Aggregation {
+    /// RSA-inspired iterative refinement
+    Rsa,
+    /// Mixture of Agents
+    Moa,
+    /// Monte Carlo Tree Search
+    Mcts,
+    /// Genetic algorithm
+    Genetic,
+    /// Beam search
+    Beam,
+    /// Best-of-N sampling
+    BestOfN,
+    /// Plan-and-solve
+    PlanSolve,
+}
+
+impl From<Aggregation> for AggregationMethod {
+    fn from(aggregation: Aggregation) -> Self {
+        match aggregation {
+            Aggregation::Rsa => AggregationMethod::RSA,
+            Aggregation::Moa => AggregationMethod::MixtureOfAgents,
+            Aggregation::Mcts => AggregationMethod::MonteCarloTreeSearch,
+            Aggregation::Genetic => AggregationMethod::GeneticAlgorithm,
+            Aggregation::Beam => AggregationMethod::BeamSearch,
+            Aggregation::BestOfN => AggregationMethod::BestOfN,
+            Aggregation::PlanSolve => AggregationMethod::PlanAndSolve,
+        }
+    }
+}
+
+impl MarsArgs {
+    /// The config file, or the defaults, with the flags applied
+    pub fn config(&self) -> Result<MarsConfig> {
+        let mut config = match &self.config {
+            Some(path) => MarsConfig::from_file(path)?,
+            None => MarsConfig::default(),
+        };
+        if let Some(agents) = self.agents {
+            config = config.with_num_agents(agents);
+        }
+        if let Some(aggregation) = self.aggregation {
+            config = config
+                .with_aggregation(true)
+                .with_aggregation_method(aggregation.into());
+        }
+        if let Some(max_iterations) = self.max_iterations {
+            config = config.with_max_iterations(max_iterations);
+        }
+        Ok(config)
+    }
+
+    /// Provider for `--model`, or else for the config's routing
+    pub fn provider(&self, config: &MarsConfig) -> Result<Arc<dyn LLMProvider>> {
+        if let Some(model) = &self.model {
+            return Ok(provider_from_spec(&ProviderSpec::from_model_id(model)?));
+        }
+        match &config.provider_routing {
+            Some(routing) => {
+                let pricing = PricingTable::default().with_overrides(&config.model_pricing);
+                Ok(Arc::new(MultiProviderRouter::from_config(
+                    routing, &pricing,
+                )?))
+            }
+            None => Err(MarsError::InvalidConfiguration(
+                "no provider: pass --model provider/model or set provider_routing in --config"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+/// Run the command `cli` parsed
+pub async fn run_main(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Run(args) => run(args).await,
+        Command::Bench(args) => bench(args).await,
+    }
+}
+
+async fn run(args: RunArgs) -> Result<()> {
+    let config = args.mars.config()?;
+    let provider = args.mars.provider(&config)?;
+    let mut coordinator = MarsCoordinator::with_provider(config, provider);
+
+    let progress = (!args.quiet).then(|| {
+        let mut events = coordinator.subscribe_events(100);
+        tokio::spawn(async move {
+            while let Some(record) = events.recv().await {
+                if let Some(line) = progress_line(&record.event) {
+                    eprintln!("{line}");
+                }
+            }
+        })
+    });
+    let output = coordinator.run(&args.question).await;
+    // Closes the event stream, so the progress task finishes
+    drop(coordinator);
+    if let Some(progress) = progress {
+        let _result = progress.await;
+    }
+
+    let output = output?;
+    if args.json {
+        let json = serde_json::to_string_pretty(&output)
+            .map_err(|e| MarsError::ParsingError(format!("Failed to serialize output: {e}")))?;
+        println!("{json}");
+    } else {
+        println!("{}", output.answer.trim());
+        if !args.quiet {
+            eprintln!("{}", summary_line(&output));
+        }
+    }
+    Ok(())
+}
+
+async fn bench(args: BenchArgs) -> Result<()> {
+    let mut dataset = Dataset::load(&args.dataset, args.format)?;
+    if let Some(limit) = args.limit {
+        dataset = dataset.with_limit(limit);
+    }
+    let config = args.mars.config()?;
+    let provider = args.mars.provider(&config)?;
+    let benchmark = Benchmark::new(config.clone(), provider, dataset.format.grader(&config))
+        .with_concurrency(args.concurrency);
+
+    let total = dataset.items.len();
+    let mut results = Vec::with_capacity(total);
+    let mut stream = std::pin::pin!(benchmark.results(&dataset));
+    while let Some(result) = stream.next().await {
+        if !args.quiet {
+            eprintln!("[{}/{total}] {}", results.len() + 1, bench_line(&result));
+        }
+        results.push(result);
+    }
+
+    let report = BenchReport::new(&dataset.name, results);
+    if args.json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| MarsError::ParsingError(format!("Failed to serialize report: {e}")))?;
+        println!("{json}");
+    } else {
+        print!("{}", report.to_markdown());
+    }
+    Ok(())
+}
+
+/// One line for a graded benchmark item
+pub fn bench_line(result: &ItemResult) -> String {
+    let verdict = match (&result.error, result.correct) {
+        (Some(error), _) => format!("error: {error}"),
+        (None, true) => "correct".to_string(),
+        (None, false) => "wrong".to_string(),
+    };
+    format!(
+        "{} {verdict} ({:.1}s, ${:.4})",
+        result.id,
+        result.latency_ms as f64 / 1000.0,
+        result.cost_usd
+    )
+}
+
+/// One line of progress for `event`, if it marks any
+pub fn progress_line(event: &MarsEvent) -> Option<String> {
+    let line = match event {
+        MarsEvent::ExplorationStarted { num_agents } => {
+            format!("Exploring with {num_agents} agents")
+        }
+        MarsEvent::SolutionGenerated { agent_id, .. } => format!("  solution from {agent_id}"),
+        MarsEvent::AggregationStarted => "Aggregating solutions".to_string(),
+        MarsEvent::StrategyNetworkStarted => "Extracting strategies".to_string(),
+        MarsEvent::VerificationStarted => "Verifying solutions".to_string(),
+        MarsEvent::SolutionVerified {
+            solution_id,
+            is_correct,
+            score,
+        } => format!(
+            "  {} {} ({score:.2})",
+            short_id(solution_id),
+            if *is_correct { "passed" } else { "failed" }
+        ),
+        MarsEvent::ImprovementStarted { iteration } => {
+            format!("Improving (iteration {})", iteration + 1)
+        }
+        MarsEvent::SolutionImproved { solution_id } => {
+            format!("  improved {}", short_id(solution_id))
+        }
+        MarsEvent::ImprovementRolledBack { iteration, .. } => {
+            format!("  rolled back iteration {}", iteration + 1)
+        }
+        MarsEvent::BudgetTruncated { spent_usd } => {
+            format!("Budget reached at ${spent_usd:.4}; skipping to synthesis")
+        }
+        MarsEvent::SynthesisStarted => "Selecting the final answer".to_string(),
+        MarsEvent::Error { message } => format!("error: {message}"),
+        _ => return None,
+    };
+    Some(line)
+}
+
+/// How the answer was reached, in one line
+fn summary_line(output: &MarsOutput) -> String {
+    format!(
+        "{:?}, confidence {:.2}, {} tokens, ${:.4}",
+        output.selection_method, output.confidence, output.total_tokens, output.cost.total_usd
+    )
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> RunArgs {
+        let cli = Cli::try_parse_from(["code-mars", "run"].iter().chain(args)).unwrap();
+        match cli.command {
+            Command::Run(args) => args,
+            Command::Bench(_) => panic!("parsed as bench"),
+        }
+    }
+
+    #[test]
+    fn test_flags_override_the_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mars.toml");
+        std::fs::write(&path, "num_agents = 4\nmax_iterations = 3\n").unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let args = parse(&[
+            "What is 2+2?",
+            "--config",
+            &path,
+            "--agents",
+            "5",
+            "--aggregation",
+            "moa",
+            "--json",
+        ]);
+        assert_eq!(args.question, "What is 2+2?");
+        assert!(args.json);
+        let config = args.mars.config().unwrap();
+        assert_eq!(config.num_agents, 5);
+        assert_eq!(config.temperatures.len(), 5);
+        assert_eq!(config.max_iterations, 3);
+        assert!(config.enable_aggregation);
+        assert!(matches!(
+            config.aggregation_method,
+            AggregationMethod::MixtureOfAgents
+        ));
+    }
+
+    #[test]
+    fn test_provider_needs_model_or_routing() {
+        let args = parse(&["q"]);
+        let config = args.mars.config().unwrap();
+        assert!(args.mars.provider(&config).is_err());
+
+        let args = parse(&["q", "--model", "gpt-4o"]);
+        assert!(args.mars.provider(&config).is_err());
+        let args = parse(&["q", "--model", "ollama/llama3"]);
+        assert_eq!(args.mars.provider(&config).unwrap().model_name(), "llama3");
+    }
+
+    #[test]
+    fn test_bench_args() {
+        let cli = Cli::try_parse_from([
+            "code-mars",
+            "bench",
+            "gsm8k.jsonl",
+            "--format",
+            "gsm8k",
+            "--limit",
+            "50",
+            "--concurrency",
+            "4",
+            "--model",
+            "openai/gpt-4o-mini",
+            "--agents",
+            "2",
+        ])
+        .unwrap();
+        let Command::Bench(args) = cli.command else {
+            panic!("parsed as run");
+        };
+        assert_eq!(args.format, Some(DatasetFormat::Gsm8k));
+        assert_eq!(args.limit, Some(50));
+        assert_eq!(args.concurrency, 4);
+        assert_eq!(args.mars.config().unwrap().num_agents, 2);
+        assert!(
+            Cli::try_parse_from(["code-mars", "bench", "x.jsonl", "--format", "mmlu"]).is_err()
+        );
+
+        let result = ItemResult {
+            id: "gsm8k/3".to_string(),
+            correct: true,
+            answer: Some("18".to_string()),
+            expected: "18".to_string(),
+            cost_usd: 0.0123,
+            latency_ms: 4200,
+            total_tokens: 900,
+            error: None,
+        };
+        assert_eq!(bench_line(&result), "gsm8k/3 correct (4.2s, $0.0123)");
+    }
+
+    #[test]
+    fn test_progress_lines() {
+        assert_eq!(
+            progress_line(&MarsEvent::ExplorationStarted { num_agents: 3 }).as_deref(),
+            Some("Exploring with 3 agents")
+        );
+        assert_eq!(
+            progress_line(&MarsEvent::SolutionVerified {
+                solution_id: "0123456789abcdef".to_string(),
+                is_correct: true,
+                score: 0.9,
+            })
+            .as_deref(),
+            Some("  01234567 passed (0.90)")
+        );
+        assert!(
+            progress_line(&MarsEvent::StrategyExtracted {
+                strategy_id: "s".to_string()
+            })
+            .is_none()
+        );
+    }
+}
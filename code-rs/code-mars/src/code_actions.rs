@@ -0,0 +1,332 @@
+//! Code-edit action space for MCTS.
+//!
+//! Instead of free-form replies, each action is a typed code operation
+//! (`add_test`, `refactor`, `fix_bug`, `implement`) aimed at a region of the
+//! code, so MCTS searches over sequences of edits.
+//!
+//! States keep the current code in their last assistant message and the task
+//! in `current_query`. Applying an action appends the operation as a user
+//! message and the edited code as an assistant message, so the final state of
+//! a search ends with the best code found.
+
+use crate::LLMProvider;
+use crate::MarsError;
+use crate::Result;
+use crate::code_verifier::CodeVerifier;
+use crate::mcts::{ActionGenerator, DialogueState, Message};
+use crate::prompts;
+use async_trait::async_trait;
+use std::fmt;
+use std::str::FromStr;
+
+/// Kind of code-edit operation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodeEditKind {
+    /// Add a test covering the region
+    AddTest,
+    /// Restructure the region without changing behavior
+    Refactor,
+    /// Fix a bug in the region
+    FixBug,
+    /// Implement missing functionality in the region
+    Implement,
+}
+
+impl CodeEditKind {
+    /// Name used in prompts and action strings
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::AddTest => "add_test",
+            Self::Refactor => "refactor",
+            Self::FixBug => "fix_bug",
+            Self::Implement => "implement",
+        }
+    }
+}
+
+impl FromStr for CodeEditKind {
+    type Err = MarsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s
+            .trim()
+            .to_ascii_lowercase()
+            .replace([' ', '-'], "_")
+            .as_str()
+        {
+            "add_test" => Ok(Self::AddTest),
+            "refactor" => Ok(Self::Refactor),
+            "fix_bug" => Ok(Self::FixBug),
+            "implement" => Ok(Self::Implement),
+            other => Err(MarsError::ParsingError(format!(
+                "Unknown code edit kind: {other}"
+            ))),
+        }
+    }
+}
+
+/// A typed code-edit operation, encoded as `kind | region | description`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeAction {
+    /// What kind of edit to make
+    pub kind: CodeEditKind,
+    /// Function, type, or lines the edit touches (`None` for the whole file)
+    pub region: Option<String>,
+    /// What the edit should do
+    pub description: String,
+}
+
+impl fmt::Display for CodeAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} | {} | {}",
+            self.kind.as_str(),
+            self.region.as_deref().unwrap_or("-"),
+            self.description
+        )
+    }
+}
+
+impl FromStr for CodeAction {
+    type Err = MarsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.splitn(3, '|').map(str::trim);
+        let (Some(kind), Some(region), Some(description)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(MarsError::ParsingError(format!(
+                "Expected `kind | region | description`, got: {s}"
+            )));
+        };
+
+        let kind = kind
+            .trim_start_matches(|c: char| c == '-' || c == '*' || c.is_ascii_digit() || c == '.')
+            .parse()?;
+        let region = match region {
+            "" | "-" => None,
+            region => Some(region.to_string()),
+        };
+
+        Ok(Self {
+            kind,
+            region,
+            description: description.to_string(),
+        })
+    }
+}
+
+/// Action generator that proposes and applies typed code edits
+#[derive(Clone, Debug, Default)]
+pub struct CodeEditActions;
+
+impl CodeEditActions {
+    /// Root state for searching edits to `code` that accomplish `task`
+    pub fn initial_state(system_prompt: &str, task: &str, code: &str) -> DialogueState {
+        DialogueState::new(
+            system_prompt.to_string(),
+            vec![Message::new("assistant", code)],
+            task.to_string(),
+        )
+    }
+
+    /// Current code of a state (its last assistant message)
+    pub fn current_code(state: &DialogueState) -> &str {
+        state
+            .conversation_history
+            .iter()
+            .rev()
+            .find(|msg| msg.role == "assistant")
+            .map_or("", |msg| msg.content.as_str())
+    }
+
+    /// Edits applied so far, oldest first
+    pub fn applied_actions(state: &DialogueState) -> Vec<CodeAction> {
+        state
+            .conversation_history
+            .iter()
+            .filter(|msg| msg.role == "user")
+            .filter_map(|msg| msg.content.parse().ok())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ActionGenerator for CodeEditActions {
+    async fn generate(
+        &self,
+        state: &DialogueState,
+        num_actions: usize,
+        temperature: f32,
+        provider: &dyn LLMProvider,
+    ) -> Result<Vec<String>> {
+        let history = Self::applied_actions(state)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = format!(
+            "{}\n\nPropose up to {num_actions} edits.\n\nTask:\n{}\n\nEdits so far:\n{}\n\nCode:\n```\n{}\n```",
+            prompts::CODE_ACTION_PROPOSAL_PROMPT,
+            state.current_query,
+            if history.is_empty() {
+                "(none)"
+            } else {
+                &history
+            },
+            Self::current_code(state).trim_end()
+        );
+
+        let response = provider
+            .complete_with_temperature(&prompt, Some(&state.system_prompt), temperature)
+            .await?;
+        Ok(response
+            .text
+            .lines()
+            .filter_map(|line| line.parse::<CodeAction>().ok())
+            .take(num_actions)
+            .map(|action| action.to_string())
+            .collect())
+    }
+
+    async fn apply(
+        &self,
+        state: &DialogueState,
+        action: &str,
+        provider: &dyn LLMProvider,
+    ) -> Result<DialogueState> {
+        let action: CodeAction = action.parse()?;
+        let prompt = format!(
+            "{}\n\nTask:\n{}\n\nEdit:\n{action}\n\nCode:\n```\n{}\n```",
+            prompts::CODE_ACTION_APPLY_PROMPT,
+            state.current_query,
+            Self::current_code(state).trim_end()
+        );
+
+        let response = provider
+            .complete(&prompt, Some(&state.system_prompt))
+            .await?;
+        let code = CodeVerifier::extract_code_blocks(&response.text)
+            .pop()
+            .map_or_else(|| response.text.trim().to_string(), |block| block.code);
+
+        let mut history = state.conversation_history.clone();
+        history.push(Message::new("user", action.to_string()));
+        history.push(Message::new("assistant", code));
+        Ok(DialogueState::new(
+            state.system_prompt.clone(),
+            history,
+            state.current_query.clone(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::{MCTS, MCTSConfig};
+    use crate::model_router::{CompletionResponse, ModelStream};
+
+    /// Mock provider that proposes two edits and applies them by tagging the code
+    struct MockEditor;
+
+    #[async_trait]
+    impl LLMProvider for MockEditor {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if prompt.starts_with(prompts::CODE_ACTION_PROPOSAL_PROMPT) {
+                Ok("Here are some edits:\n\
+                    1. fix_bug | add | handle overflow\n\
+                    not an edit\n\
+                    add_test | - | cover negative numbers"
+                    .into())
+            } else if prompt.starts_with(prompts::CODE_ACTION_APPLY_PROMPT) {
+                let edit = if prompt.contains("fix_bug") {
+                    "fixed"
+                } else {
+                    "tested"
+                };
+                Ok(format!("```rust\n// {edit}\nfn add() {{}}\n```").into())
+            } else {
+                Ok("0.9".into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-editor"
+        }
+    }
+
+    #[test]
+    fn test_code_action_round_trip() {
+        let action: CodeAction = "Fix-Bug | parse_args | reject empty input".parse().unwrap();
+        assert_eq!(action.kind, CodeEditKind::FixBug);
+        assert_eq!(action.region.as_deref(), Some("parse_args"));
+        assert_eq!(
+            action.to_string(),
+            "fix_bug | parse_args | reject empty input"
+        );
+        assert_eq!(action.to_string().parse::<CodeAction>().unwrap(), action);
+
+        let whole_file: CodeAction = "refactor | - | split into modules".parse().unwrap();
+        assert!(whole_file.region.is_none());
+        assert!("rewrite | - | everything".parse::<CodeAction>().is_err());
+        assert!("just some prose".parse::<CodeAction>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_generate_and_apply_code_edits() {
+        let state = CodeEditActions::initial_state("system", "Add two numbers", "fn add() {}");
+        let actions = CodeEditActions
+            .generate(&state, 3, 1.0, &MockEditor)
+            .await
+            .unwrap();
+        assert_eq!(
+            actions,
+            vec![
+                "fix_bug | add | handle overflow",
+                "add_test | - | cover negative numbers"
+            ]
+        );
+
+        let next = CodeEditActions
+            .apply(&state, &actions[0], &MockEditor)
+            .await
+            .unwrap();
+        assert_eq!(
+            CodeEditActions::current_code(&next),
+            "// fixed\nfn add() {}\n"
+        );
+        assert_eq!(CodeEditActions::applied_actions(&next).len(), 1);
+        assert_eq!(next.current_query, "Add two numbers");
+    }
+
+    #[tokio::test]
+    async fn test_mcts_searches_code_edits() {
+        let config = MCTSConfig {
+            num_simulations: 3,
+            num_actions: 2,
+            ..MCTSConfig::default()
+        };
+        let mut mcts =
+            MCTS::new(config).with_action_generator(std::sync::Arc::new(CodeEditActions));
+        let state = CodeEditActions::initial_state("system", "Add two numbers", "fn add() {}");
+
+        let best = mcts.search(state, &MockEditor).await.unwrap();
+        assert_eq!(CodeEditActions::applied_actions(&best).len(), 1);
+        assert!(CodeEditActions::current_code(&best).contains("fn add()"));
+    }
+}
@@ -0,0 +1,631 @@
+//! Execution-based verification for code tasks.
+//!
+//! Extracts fenced code blocks from a solution and runs them in a scratch
+//! directory with a cleared environment, CPU/memory limits and a wall-clock
+//! timeout. By default programs run under code-core's platform sandbox
+//! ([`ExecutionSandbox::Platform`]): Seatbelt on macOS, Landlock and seccomp
+//! on Linux, so they can read files but not write them or use the network.
+//! [`ExecutionSandbox::Unsandboxed`] drops that isolation and must be chosen
+//! explicitly. When test cases are supplied, each case
+//! feeds `input` on stdin and compares trimmed stdout against
+//! `expected_output`; otherwise the program only has to exit successfully.
+//! Test cases can also be generated by a model from the query alone.
+
+use crate::types::{Solution, VerificationResult};
+use crate::{LLMProvider, MarsError, Result};
+use code_core::error::{CodexErr, SandboxErr};
+use code_core::exec::{ExecParams, SandboxType, process_exec_tool_call};
+use code_core::protocol::SandboxPolicy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// File in the scratch directory a program's stdin is read from
+const STDIN_FILE: &str = "stdin.txt";
+
+/// A fenced code block extracted from a solution
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// Language tag of the block (lowercased, may be empty)
+    pub language: String,
+    /// Source code inside the fence
+    pub code: String,
+}
+
+/// A user-supplied test case for execution-based verification
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TestCase {
+    /// Data written to the program's stdin
+    pub input: String,
+    /// Expected stdout (compared after trimming whitespace)
+    pub expected_output: String,
+}
+
+impl TestCase {
+    /// Create a new test case
+    pub fn new(input: impl Into<String>, expected_output: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+            expected_output: expected_output.into(),
+        }
+    }
+}
+
+/// Limits applied to every execution
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExecutionLimits {
+    /// Wall-clock timeout per run in seconds (default: 10)
+    pub timeout_seconds: u64,
+    /// Data-segment (heap) limit in megabytes, set with `ulimit -d`
+    /// (default: 512, unix only)
+    pub memory_limit_mb: u64,
+    /// Maximum bytes of stdout/stderr kept for feedback (default: 4096)
+    pub max_output_bytes: usize,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: 10,
+            memory_limit_mb: 512,
+            max_output_bytes: 4096,
+        }
+    }
+}
+
+/// How programs from solutions are isolated from the host
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecutionSandbox {
+    /// code-core's platform sandbox, as used for the agent's shell commands:
+    /// Seatbelt on macOS, Landlock and seccomp on Linux. Programs can read
+    /// files but cannot write them or reach the network. Not available on
+    /// other platforms.
+    Platform {
+        /// The Linux sandbox helper: the `code` executable, which acts as
+        /// `codex-linux-sandbox`. Required on Linux.
+        #[serde(default)]
+        linux_sandbox_exe: Option<PathBuf>,
+    },
+    /// No isolation. Programs run as the current user with only the
+    /// [`ExecutionLimits`], so they can read and write the user's files and
+    /// use the network. Only for code you would run yourself.
+    Unsandboxed,
+}
+
+impl Default for ExecutionSandbox {
+    fn default() -> Self {
+        Self::Platform {
+            linux_sandbox_exe: None,
+        }
+    }
+}
+
+impl ExecutionSandbox {
+    /// The platform sandbox, using `exe` as the Linux sandbox helper
+    pub fn platform_with_linux_helper(exe: impl Into<PathBuf>) -> Self {
+        Self::Platform {
+            linux_sandbox_exe: Some(exe.into()),
+        }
+    }
+
+    /// Why programs cannot run under this sandbox here, if they cannot
+    pub fn problem(&self) -> Option<String> {
+        self.sandbox_type().err().map(|e| e.to_string())
+    }
+
+    /// The code-core sandbox to run under, with the Linux helper it needs
+    fn sandbox_type(&self) -> Result<(SandboxType, Option<PathBuf>)> {
+        match self {
+            Self::Unsandboxed => Ok((SandboxType::None, None)),
+            Self::Platform { linux_sandbox_exe } => match code_core::get_platform_sandbox() {
+                Some(SandboxType::LinuxSeccomp) if linux_sandbox_exe.is_none() => {
+                    Err(MarsError::InvalidConfiguration(
+                        "code execution on Linux needs the sandbox helper: set \
+                         code_execution_sandbox.linux_sandbox_exe to the `code` executable, \
+                         or opt out of isolation with type = \"unsandboxed\""
+                            .to_string(),
+                    ))
+                }
+                Some(sandbox_type) => Ok((sandbox_type, linux_sandbox_exe.clone())),
+                None => Err(MarsError::InvalidConfiguration(
+                    "no platform sandbox for code execution on this OS; opt out of isolation \
+                     with code_execution_sandbox type = \"unsandboxed\""
+                        .to_string(),
+                )),
+            },
+        }
+    }
+}
+
+/// Outcome of a single execution
+#[derive(Clone, Debug)]
+pub struct ExecutionOutcome {
+    /// Whether the process exited successfully before the timeout
+    pub success: bool,
+    /// Whether the process was killed because it exceeded the timeout
+    pub timed_out: bool,
+    /// Captured stdout (truncated to `max_output_bytes`)
+    pub stdout: String,
+    /// Captured stderr (truncated to `max_output_bytes`)
+    pub stderr: String,
+}
+
+/// Verifier that executes code found in solutions
+pub struct CodeVerifier {
+    test_cases: Vec<TestCase>,
+    limits: ExecutionLimits,
+    sandbox: ExecutionSandbox,
+}
+
+impl CodeVerifier {
+    /// Create a code verifier with the given test cases and limits, running
+    /// programs under the platform sandbox
+    pub fn new(test_cases: Vec<TestCase>, limits: ExecutionLimits) -> Self {
+        Self {
+            test_cases,
+            limits,
+            sandbox: ExecutionSandbox::default(),
+        }
+    }
+
+    /// Run programs under `sandbox`
+    pub fn with_sandbox(mut self, sandbox: ExecutionSandbox) -> Self {
+        if sandbox == ExecutionSandbox::Unsandboxed {
+            tracing::warn!(
+                "Code execution is unsandboxed: model-written programs can read and write \
+                 your files and use the network"
+            );
+        }
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Verify a solution by executing its code
+    ///
+    /// Runs the last code block of the answer (or else the reasoning) tagged
+    /// with a language that can be run; without one there is no verdict and
+    /// `None` is returned. The solution is correct only when the code runs and
+    /// every test case passes. The score is the fraction of passing test cases.
+    pub async fn verify_solution(
+        &self,
+        solution: &Solution,
+        verifying_agent_id: &str,
+    ) -> Result<Option<VerificationResult>> {
+        let block = Self::runnable_block(&solution.answer)
+            .or_else(|| Self::runnable_block(&solution.reasoning));
+        let Some(block) = block else {
+            return Ok(None);
+        };
+
+        let mut failures = Vec::new();
+        let passed = if self.test_cases.is_empty() {
+            let outcome = self.execute(&block, "").await?;
+            if outcome.success {
+                1
+            } else {
+                failures.push(Self::describe_failure(&outcome, None));
+                0
+            }
+        } else {
+            let mut passed = 0;
+            for (idx, test_case) in self.test_cases.iter().enumerate() {
+                let outcome = self.execute(&block, &test_case.input).await?;
+                if outcome.success && outcome.stdout.trim() == test_case.expected_output.trim() {
+                    passed += 1;
+                } else {
+                    failures.push(format!(
+                        "Test {}: {}",
+                        idx + 1,
+                        Self::describe_failure(&outcome, Some(test_case))
+                    ));
+                }
+            }
+            passed
+        };
+
+        let total = self.test_cases.len().max(1);
+        let score = passed as f32 / total as f32;
+        let mut result = VerificationResult::new(
+            solution.id.clone(),
+            failures.is_empty(),
+            score,
+            verifying_agent_id.to_string(),
+        );
+        result.correctness_score = score;
+        result.correctness_feedback = if failures.is_empty() {
+            format!("All {total} execution checks passed")
+        } else {
+            failures.join("\n")
+        };
+        if !failures.is_empty() {
+            result.critique =
+                "Fix the code so that every execution check above passes.".to_string();
+        }
+
+        Ok(Some(result))
+    }
+
+    /// Ask a provider to write test cases from the problem statement alone
+    pub async fn generate_test_cases(
+        query: &str,
+        num_tests: usize,
+        provider: &dyn LLMProvider,
+    ) -> Result<Vec<TestCase>> {
+        let prompt = format!("Problem:\n{query}\n\nWrite {num_tests} test cases.");
+        let response = provider
+            .complete(&prompt, Some(crate::prompts::TEST_GENERATION_PROMPT))
+            .await?;
+
+        let mut test_cases = Self::parse_test_cases(&response.text)?;
+        test_cases.truncate(num_tests);
+        Ok(test_cases)
+    }
+
+    /// Parse a JSON array of `{"input", "expected_output"}` objects from a response
+    ///
+    /// Surrounding prose and code fences are ignored, and non-string values
+    /// are converted to their JSON text.
+    pub fn parse_test_cases(response: &str) -> Result<Vec<TestCase>> {
+        let (Some(start), Some(end)) = (response.find('['), response.rfind(']')) else {
+            return Err(MarsError::ParsingError(
+                "No JSON array of test cases found".to_string(),
+            ));
+        };
+        if end < start {
+            return Err(MarsError::ParsingError(
+                "No JSON array of test cases found".to_string(),
+            ));
+        }
+
+        let values: Vec<serde_json::Value> = serde_json::from_str(&response[start..=end])
+            .map_err(|e| MarsError::ParsingError(format!("Invalid test case JSON: {e}")))?;
+
+        let as_text = |value: &serde_json::Value| match value {
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+
+        Ok(values
+            .iter()
+            .filter_map(|value| {
+                let input = value.get("input").map(as_text).unwrap_or_default();
+                let expected_output = value.get("expected_output").map(as_text)?;
+                Some(TestCase::new(input, expected_output))
+            })
+            .collect())
+    }
+
+    /// Extract fenced code blocks (```lang ... ```) in document order
+    pub fn extract_code_blocks(text: &str) -> Vec<CodeBlock> {
+        let mut blocks = Vec::new();
+        let mut current: Option<CodeBlock> = None;
+
+        for line in text.lines() {
+            let trimmed = line.trim_start();
+            match current.as_mut() {
+                None => {
+                    if let Some(tag) = trimmed.strip_prefix("```") {
+                        current = Some(CodeBlock {
+                            language: tag.trim().to_ascii_lowercase(),
+                            code: String::new(),
+                        });
+                    }
+                }
+                Some(block) => {
+                    if trimmed.starts_with("```") {
+                        blocks.extend(current.take());
+                    } else {
+                        block.code.push_str(line);
+                        block.code.push('\n');
+                    }
+                }
+            }
+        }
+
+        blocks
+    }
+
+    /// The last code block in `text` whose language tag can be run
+    pub fn runnable_block(text: &str) -> Option<CodeBlock> {
+        Self::extract_code_blocks(text)
+            .into_iter()
+            .rev()
+            .find(|block| Self::interpreter_for(&block.language).is_some())
+    }
+
+    /// Execute a code block in a scratch directory under the verifier's
+    /// [`ExecutionSandbox`]
+    pub async fn execute(&self, block: &CodeBlock, stdin: &str) -> Result<ExecutionOutcome> {
+        let (interpreter, extension) = Self::interpreter_for(&block.language).ok_or_else(|| {
+            crate::MarsError::VerificationError(format!(
+                "Unsupported language for execution: {}",
+                block.language
+            ))
+        })?;
+
+        let scratch_dir = std::env::temp_dir().join(format!("code-mars-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&scratch_dir)
+            .await
+            .map_err(|e| crate::MarsError::VerificationError(e.to_string()))?;
+
+        let outcome = self
+            .run_in_dir(&scratch_dir, interpreter, extension, block, stdin)
+            .await;
+        let _ = tokio::fs::remove_dir_all(&scratch_dir).await;
+        outcome
+    }
+
+    async fn run_in_dir(
+        &self,
+        dir: &Path,
+        interpreter: &str,
+        extension: &str,
+        block: &CodeBlock,
+        stdin: &str,
+    ) -> Result<ExecutionOutcome> {
+        let io_err = |e: std::io::Error| crate::MarsError::VerificationError(e.to_string());
+
+        let source = dir.join(format!("main.{extension}"));
+        tokio::fs::write(&source, &block.code)
+            .await
+            .map_err(io_err)?;
+        // Read from a file rather than a pipe, so a program that never reads
+        // its input cannot block the verifier
+        tokio::fs::write(dir.join(STDIN_FILE), stdin)
+            .await
+            .map_err(io_err)?;
+
+        let params = ExecParams {
+            command: self.command(interpreter, &source),
+            cwd: dir.to_path_buf(),
+            timeout_ms: Some(self.limits.timeout_seconds * 1000),
+            env: HashMap::from([
+                (
+                    "PATH".to_string(),
+                    "/usr/local/bin:/usr/bin:/bin".to_string(),
+                ),
+                ("HOME".to_string(), dir.to_string_lossy().into_owned()),
+            ]),
+            with_escalated_permissions: None,
+            justification: None,
+        };
+        let (sandbox_type, linux_sandbox_exe) = self.sandbox.sandbox_type()?;
+        let executed = process_exec_tool_call(
+            params,
+            sandbox_type,
+            &SandboxPolicy::new_read_only_policy(),
+            dir,
+            &linux_sandbox_exe,
+            None,
+        )
+        .await;
+
+        let failed = |stdout: &str, stderr: &str| ExecutionOutcome {
+            success: false,
+            timed_out: false,
+            stdout: self.truncate(stdout),
+            stderr: self.truncate(stderr),
+        };
+        match executed {
+            Ok(output) => Ok(ExecutionOutcome {
+                success: output.exit_code == 0,
+                ..failed(&output.stdout.text, &output.stderr.text)
+            }),
+            Err(CodexErr::Sandbox(SandboxErr::Timeout { .. })) => Ok(ExecutionOutcome {
+                timed_out: true,
+                ..failed("", "")
+            }),
+            Err(CodexErr::Sandbox(SandboxErr::Denied { output })) => Ok(failed(
+                &output.stdout.text,
+                &format!("denied by the sandbox: {}", output.stderr.text),
+            )),
+            // Usually the CPU or memory limit
+            Err(CodexErr::Sandbox(SandboxErr::Signal(signal))) => {
+                Ok(failed("", &format!("killed by signal {signal}")))
+            }
+            Err(e) => Err(crate::MarsError::VerificationError(format!(
+                "Failed to run code: {e}"
+            ))),
+        }
+    }
+
+    /// The interpreter command, reading [`STDIN_FILE`] and with rlimits
+    /// applied through the shell on unix
+    #[cfg(unix)]
+    fn command(&self, interpreter: &str, source: &Path) -> Vec<String> {
+        vec![
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            format!(
+                "ulimit -t {} -d {} 2>/dev/null; exec \"$0\" \"$1\" < {STDIN_FILE}",
+                self.limits.timeout_seconds,
+                self.limits.memory_limit_mb * 1024
+            ),
+            interpreter.to_string(),
+            source.to_string_lossy().into_owned(),
+        ]
+    }
+
+    /// The interpreter command, reading [`STDIN_FILE`] (no rlimits outside unix)
+    #[cfg(not(unix))]
+    fn command(&self, interpreter: &str, source: &Path) -> Vec<String> {
+        vec![
+            "cmd".to_string(),
+            "/C".to_string(),
+            format!("{interpreter} \"{}\" < {STDIN_FILE}", source.display()),
+        ]
+    }
+
+    /// Map a fence language tag to an interpreter and file extension
+    pub(crate) fn interpreter_for(language: &str) -> Option<(&'static str, &'static str)> {
+        match language {
+            "python" | "python3" | "py" => Some(("python3", "py")),
+            "javascript" | "js" | "node" => Some(("node", "js")),
+            "sh" | "bash" | "shell" => Some(("sh", "sh")),
+            _ => None,
+        }
+    }
+
+    fn truncate(&self, text: &str) -> String {
+        let mut end = text.len().min(self.limits.max_output_bytes);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text[..end].to_string()
+    }
+
+    fn describe_failure(outcome: &ExecutionOutcome, test_case: Option<&TestCase>) -> String {
+        if outcome.timed_out {
+            return "execution timed out".to_string();
+        }
+        if !outcome.success {
+            return format!("execution failed: {}", outcome.stderr.trim());
+        }
+        match test_case {
+            Some(test_case) => format!(
+                "expected {:?}, got {:?}",
+                test_case.expected_output.trim(),
+                outcome.stdout.trim()
+            ),
+            None => "unexpected output".to_string(),
+        }
+    }
+}
+
+impl Default for CodeVerifier {
+    fn default() -> Self {
+        Self::new(Vec::new(), ExecutionLimits::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_solution(answer: &str) -> Solution {
+        Solution::new(
+            "agent1".to_string(),
+            "reasoning".to_string(),
+            answer.to_string(),
+            0.5,
+            100,
+        )
+    }
+
+    #[test]
+    fn test_extract_code_blocks() {
+        let text = "Here is the code:\n```Python\nprint(1)\n```\nand\n```sh\necho hi\n```";
+        let blocks = CodeVerifier::extract_code_blocks(text);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].language, "python");
+        assert_eq!(blocks[0].code, "print(1)\n");
+        assert_eq!(blocks[1].language, "sh");
+    }
+
+    #[test]
+    fn test_parse_test_cases() {
+        let response = r#"Here are the tests:
+```json
+[
+  {"input": "2\n", "expected_output": "4"},
+  {"input": "", "expected_output": 0},
+  {"input": "x"}
+]
+```"#;
+        let test_cases = CodeVerifier::parse_test_cases(response).unwrap();
+        assert_eq!(
+            test_cases,
+            vec![TestCase::new("2\n", "4"), TestCase::new("", "0")]
+        );
+
+        assert!(CodeVerifier::parse_test_cases("no tests here").is_err());
+    }
+
+    #[test]
+    fn test_runnable_block_skips_untagged_and_unknown_languages() {
+        let text = "```sh\necho 1\n```\n```python\nprint(2)\n```\n```text\n2\n```\n```\n2\n```";
+        let block = CodeVerifier::runnable_block(text).unwrap();
+        assert_eq!(block.language, "python");
+        assert_eq!(block.code, "print(2)\n");
+
+        assert!(
+            CodeVerifier::runnable_block("```\nprint(1)\n```\n```rust\nfn main() {}\n```")
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_runnable_code_block_has_no_verdict() {
+        let verifier = CodeVerifier::default();
+        for answer in ["42", "```text\n42\n```"] {
+            let result = verifier
+                .verify_solution(&code_solution(answer), "verifier")
+                .await
+                .unwrap();
+            assert!(result.is_none());
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execution_against_test_cases() {
+        let verifier = CodeVerifier::new(
+            vec![TestCase::new("2\n", "4"), TestCase::new("5\n", "10")],
+            ExecutionLimits::default(),
+        )
+        .with_sandbox(ExecutionSandbox::Unsandboxed);
+        let solution = code_solution("```sh\nread x\necho $((x * 2))\n```");
+
+        let result = verifier
+            .verify_solution(&solution, "verifier")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(result.is_correct);
+        assert_eq!(result.score, 1.0);
+
+        let wrong = code_solution("```sh\nread x\necho $((x + 2))\n```");
+        let result = verifier
+            .verify_solution(&wrong, "verifier")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!result.is_correct);
+        assert_eq!(result.score, 0.5);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execution_timeout() {
+        let verifier = CodeVerifier::new(
+            Vec::new(),
+            ExecutionLimits {
+                timeout_seconds: 1,
+                ..Default::default()
+            },
+        )
+        .with_sandbox(ExecutionSandbox::Unsandboxed);
+        let solution = code_solution("```sh\nwhile true; do :; done\n```");
+
+        let result = verifier
+            .verify_solution(&solution, "verifier")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!result.is_correct);
+        assert!(result.correctness_feedback.contains("timed out"));
+    }
+
+    #[test]
+    fn test_sandbox_problems() {
+        assert!(ExecutionSandbox::Unsandboxed.problem().is_none());
+        let helper = ExecutionSandbox::platform_with_linux_helper("/usr/local/bin/code");
+        if cfg!(any(target_os = "linux", target_os = "macos")) {
+            assert!(helper.problem().is_none());
+        }
+        if cfg!(target_os = "linux") {
+            assert!(ExecutionSandbox::default().problem().is_some());
+        }
+    }
+}
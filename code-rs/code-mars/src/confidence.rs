@@ -0,0 +1,110 @@
+//! Confidence in a run's final answer.
+//!
+//! [`answer_confidence`] scores the final answer between 0 and 1 as the mean
+//! of three signals over the candidate solutions:
+//!
+//! - **Vote share:** the fraction of candidates whose answer matches the
+//!   final one (ignoring whitespace).
+//! - **Verification:** the highest verification score among those matching
+//!   candidates, or the final solution's own score when none match (as for
+//!   a synthesized answer).
+//! - **Agreement:** `1 - H / ln(n)`, where `H` is the entropy of the
+//!   distribution of distinct answers over the `n` candidates. It is 1 when
+//!   all candidates agree and 0 when every answer differs.
+//!
+//! The score is a heuristic rather than a probability, but it moves the
+//! right way on every signal, which makes it useful as a gate, e.g. sending
+//! answers below 0.6 to a human.
+
+use crate::types::{Solution, answer_key};
+use std::collections::HashMap;
+
+/// Confidence in `final_solution` given the `candidates` it was chosen from
+pub fn answer_confidence(final_solution: &Solution, candidates: &[Solution]) -> f32 {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for candidate in candidates {
+        let key = answer_key(&candidate.answer);
+        if !key.is_empty() {
+            *counts.entry(key).or_insert(0) += 1;
+        }
+    }
+    let total: usize = counts.values().sum();
+
+    let final_key = answer_key(&final_solution.answer);
+    let votes = counts.get(&final_key).copied().unwrap_or(0);
+    let vote_share = if total == 0 {
+        0.0
+    } else {
+        votes as f32 / total as f32
+    };
+    let verification = candidates
+        .iter()
+        .filter(|c| answer_key(&c.answer) == final_key)
+        .map(|c| c.verification_score)
+        .reduce(f32::max)
+        .unwrap_or(final_solution.verification_score);
+
+    let confidence = (vote_share + verification + agreement(&counts, total)) / 3.0;
+    confidence.clamp(0.0, 1.0)
+}
+
+/// One minus the normalized entropy of the answer distribution
+fn agreement(counts: &HashMap<String, usize>, total: usize) -> f32 {
+    if total <= 1 {
+        return 1.0;
+    }
+    let entropy: f32 = counts
+        .values()
+        .map(|&count| {
+            let p = count as f32 / total as f32;
+            -p * p.ln()
+        })
+        .sum();
+    1.0 - entropy / (total as f32).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solution(answer: &str, score: f32) -> Solution {
+        let mut solution = Solution::new(
+            "agent".to_string(),
+            "reasoning".to_string(),
+            answer.to_string(),
+            0.5,
+            10,
+        );
+        solution.verification_score = score;
+        solution
+    }
+
+    #[test]
+    fn test_unanimous_verified_answer_is_confident() {
+        let candidates = vec![solution("4", 1.0), solution(" 4", 0.5), solution("4 ", 0.0)];
+        let confidence = answer_confidence(&candidates[1], &candidates);
+        assert!((confidence - 1.0).abs() < 1e-6, "{confidence}");
+    }
+
+    #[test]
+    fn test_disagreement_lowers_confidence() {
+        let split = vec![solution("4", 0.9), solution("4", 0.9), solution("5", 0.9)];
+        let scattered = vec![solution("4", 0.9), solution("5", 0.9), solution("6", 0.9)];
+        let majority = answer_confidence(&split[0], &split);
+        let minority = answer_confidence(&split[2], &split);
+        let none = answer_confidence(&scattered[0], &scattered);
+        assert!(majority > minority);
+        assert!(minority > none);
+        // Vote share 1/3, verification 0.9, agreement 0
+        assert!((none - (1.0 / 3.0 + 0.9) / 3.0).abs() < 1e-6, "{none}");
+    }
+
+    #[test]
+    fn test_unmatched_answer_uses_its_own_score() {
+        let candidates = vec![solution("4", 0.9), solution("5", 0.9)];
+        let synthesized = solution("4 or 5", 0.3);
+        let confidence = answer_confidence(&synthesized, &candidates);
+        assert!((confidence - 0.1).abs() < 1e-6, "{confidence}");
+        assert_eq!(answer_confidence(&synthesized, &[]), (0.3 + 1.0) / 3.0);
+    }
+}
@@ -1,6 +1,18 @@
 /// Configuration for MARS (Multi-Agent Reasoning System).
 use serde::{Deserialize, Serialize};
 
+/// Aggregation names [`MarsConfig::with_aggregation_name`] accepts
+pub const AGGREGATION_NAMES: &[&str] = &[
+    "none",
+    "rsa",
+    "moa",
+    "mcts",
+    "genetic",
+    "beam",
+    "best_of_n",
+    "plan_solve",
+];
+
 /// Configuration for MARS execution
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MarsConfig {
@@ -11,10 +23,28 @@ pub struct MarsConfig {
     /// Default: [0.3, 0.6, 1.0] for low, medium, high exploration
     pub temperatures: Vec<f32>,
 
-    /// Number of verification passes required before marking solution as verified
-    /// Default: 2 (must pass 2 consecutive verifications with no failures)
+    /// Agreement needed for consensus: verification passes before a solution
+    /// is verified, failures before it is dropped from improvement, and
+    /// solutions sharing an answer for majority voting
+    /// Default: 2
     pub consensus_threshold: usize,
 
+    /// Number of independent verification passes run per solution
+    /// Default: 2
+    #[serde(default = "default_num_verifiers")]
+    pub num_verifiers: usize,
+
+    /// How failed passes are weighed against successful ones
+    /// Default: All (any failure rejects the solution)
+    #[serde(default)]
+    pub verification_quorum: crate::types::QuorumRule,
+
+    /// Sampling temperature for each verification pass, cycled when shorter
+    /// than the number of passes
+    /// Default: [0.3]
+    #[serde(default = "default_verification_temperatures")]
+    pub verification_temperatures: Vec<f32>,
+
     /// Enable RSA-inspired solution aggregation and refinement
     /// Default: false (phase 2a)
     pub enable_aggregation: bool,
@@ -27,6 +57,12 @@ pub struct MarsConfig {
     /// Default: 5
     pub max_iterations: usize,
 
+    /// Verify each improvement iteration's solutions and undo iterations that
+    /// did not beat the solutions they improved on
+    /// Default: false
+    #[serde(default)]
+    pub rollback_failed_improvements: bool,
+
     /// Whether to wrap reasoning in <think></think> tags
     /// Default: true
     pub use_thinking_tags: bool,
@@ -67,6 +103,68 @@ pub struct MarsConfig {
     /// Default: true
     pub moa_fallback_enabled: bool,
 
+    /// How RSA picks the parents it recombines each loop
+    /// Default: Random
+    #[serde(default)]
+    pub rsa_parent_selection: crate::types::ParentSelection,
+
+    /// How much similarity to an already-chosen RSA parent lowers a
+    /// candidate's selection score (0.0 disables the penalty)
+    /// Default: 0.0
+    #[serde(default)]
+    pub rsa_diversity_penalty: f32,
+
+    /// Number of MOA proposer layers
+    /// Default: 1
+    #[serde(default = "default_moa_num_layers")]
+    pub moa_num_layers: usize,
+
+    /// Model for each MOA proposer layer, by index; unset layers use the
+    /// aggregation provider
+    /// Default: []
+    #[serde(default)]
+    pub moa_layer_models: Vec<crate::provider_config::ProviderSpec>,
+
+    /// Overrides for the aggregation system prompt and the MOA proposer,
+    /// MOA aggregator, and RSA merge templates
+    /// Default: built-in prompts
+    #[serde(default)]
+    pub aggregation_prompts: crate::prompts::AggregationPrompts,
+
+    /// Overrides for the system, exploration, verification, improvement and
+    /// strategy extraction prompts
+    /// Default: built-in prompts
+    #[serde(default)]
+    pub prompts: crate::prompts::PromptSet,
+
+    /// Variables prompt templates may read, with their default values;
+    /// runs override them through `RunOptions::with_variable`
+    /// Default: {}
+    #[serde(default)]
+    pub prompt_variables: crate::templates::PromptVariables,
+
+    /// Named templates prompt overrides can `{% include %}`
+    /// Default: {}
+    #[serde(default)]
+    pub prompt_partials: crate::templates::PromptVariables,
+
+    /// Language for reasoning and final answers, e.g. "German"; added to every
+    /// system prompt and required of the synthesized answer
+    /// Default: None (the model's choice, usually the query's language)
+    #[serde(default)]
+    pub response_language: Option<String>,
+
+    /// Shape of the final answer; anything but free text adds strict-format
+    /// instructions to the prompts and parses `MarsOutput::typed_answer`
+    /// Default: FreeText
+    #[serde(default)]
+    pub answer_type: crate::types::AnswerType,
+
+    /// Distinct answers listed in `MarsOutput::alternatives`, best first
+    /// Default: 3
+    #[serde(default = "default_num_alternatives")]
+    pub num_alternatives: usize,
+
     /// Provider configuration for multi-model support
     /// Default: None (use default ModelClient)
     pub provider_routing: Option<crate::provider_config::ProviderRoutingConfig>,
@@ -95,9 +193,286 @@ pub struct MarsConfig {
     /// Default: 3
     pub mcts_num_actions: usize,
 
-    /// Enable debug logging
+    /// MCTS simulations run concurrently
+    /// Default: 1
+    #[serde(default = "default_mcts_parallel_simulations")]
+    pub mcts_parallel_simulations: usize,
+
+    /// MCTS tree policy
+    /// Default: Ucb1
+    #[serde(default)]
+    pub mcts_selection_policy: crate::types::MctsSelectionPolicy,
+
+    /// Share MCTS statistics and evaluations between duplicate states
+    /// Default: false
+    #[serde(default)]
+    pub mcts_transpositions: bool,
+
+    /// Stop MCTS once the most visited root child has this share of visits
+    /// Default: None (disabled)
+    #[serde(default)]
+    pub mcts_early_stop_visit_share: Option<f32>,
+
+    /// Stop MCTS once the most visited root child's value standard deviation
+    /// is at most this
+    /// Default: None (disabled)
+    #[serde(default)]
+    pub mcts_early_stop_value_std: Option<f32>,
+
+    /// MCTS action-generation temperature by tree depth
+    /// Default: Constant (1.0 at every depth)
+    #[serde(default)]
+    pub mcts_temperature_schedule: crate::types::MctsTemperatureSchedule,
+
+    /// Genetic-algorithm population size
+    /// Default: 6
+    #[serde(default = "default_ga_population_size")]
+    pub ga_population_size: usize,
+
+    /// Number of genetic-algorithm generations
+    /// Default: 3
+    #[serde(default = "default_ga_generations")]
+    pub ga_generations: usize,
+
+    /// Probability that a genetic-algorithm child is mutated
+    /// Default: 0.2
+    #[serde(default = "default_ga_mutation_rate")]
+    pub ga_mutation_rate: f32,
+
+    /// Beam-search width (partial solutions kept per round)
+    /// Default: 3
+    #[serde(default = "default_beam_width")]
+    pub beam_width: usize,
+
+    /// Beam-search depth (expansion rounds)
+    /// Default: 2
+    #[serde(default = "default_beam_depth")]
+    pub beam_depth: usize,
+
+    /// Number of completions sampled by best-of-N aggregation
+    /// Default: 4
+    #[serde(default = "default_best_of_n_samples")]
+    pub best_of_n_samples: usize,
+
+    /// How best-of-N aggregation scores its samples
+    /// Default: Verifier
+    #[serde(default)]
+    pub best_of_n_scorer: crate::types::BestOfNScorer,
+
+    /// How repeated verification scores are combined into a solution's score
+    /// Default: Mean
+    #[serde(default)]
+    pub score_aggregation: crate::types::ScoreAggregation,
+
+    /// Never let a solution be verified by the model that produced it
+    /// Default: false
+    #[serde(default)]
+    pub exclude_self_verification: bool,
+
+    /// Reuse verification results for solutions with identical content
+    /// Default: false
+    #[serde(default)]
+    pub enable_verification_cache: bool,
+
+    /// File the verification cache is persisted to between runs
+    /// Default: None (in-memory only)
+    #[serde(default)]
+    pub verification_cache_path: Option<std::path::PathBuf>,
+
+    /// Where the workspace stores solutions and feedback
+    /// Default: InMemory
+    #[serde(default)]
+    pub workspace_backend: crate::types::WorkspaceBackend,
+
+    /// Whether duplicate solutions are merged as they enter the workspace,
+    /// so near-identical answers do not skew majority voting
+    /// Default: Disabled
+    #[serde(default)]
+    pub solution_dedup: crate::types::SolutionDedup,
+
+    /// Most solutions the workspace holds before evicting some
+    /// Default: None (unbounded)
+    #[serde(default)]
+    pub max_workspace_solutions: Option<usize>,
+
+    /// Which solutions are evicted once `max_workspace_solutions` is reached
+    /// Default: LowestScoreUnverified
+    #[serde(default)]
+    pub workspace_eviction: crate::types::EvictionPolicy,
+
+    /// Record every workspace change in an ordered, replayable log
+    /// Default: false
+    #[serde(default)]
+    pub enable_workspace_log: bool,
+
+    /// File strategies are persisted to between runs
+    /// Default: None (strategies last one run)
+    #[serde(default)]
+    pub strategy_store_path: Option<std::path::PathBuf>,
+
+    /// Namespace (domain or tag) strategies are loaded from and saved under
+    /// Default: "default"
+    #[serde(default = "default_strategy_namespace")]
+    pub strategy_namespace: String,
+
+    /// Word overlap at which a newly extracted strategy counts as a duplicate
+    /// of an existing one and is not added (above 1.0 disables)
+    /// Default: 0.8
+    #[serde(default = "default_strategy_dedup_threshold")]
+    pub strategy_dedup_threshold: f32,
+
+    /// Similarity at which the LLM merges overlapping strategies after
+    /// extraction
+    /// Default: None (no merge step)
+    #[serde(default)]
+    pub strategy_merge_threshold: Option<f32>,
+
+    /// Number of strategies included in improvement prompts (0 disables)
+    ///
+    /// The strategies most similar to the query are chosen when the provider
+    /// supports embeddings, otherwise the highest-rated ones.
+    /// Default: 3
+    #[serde(default = "default_strategy_prompt_limit")]
+    pub strategy_prompt_limit: usize,
+
+    /// Also include known strategies in exploration prompts, e.g. strategies
+    /// loaded from an earlier run's strategy store
+    /// Default: false
+    #[serde(default)]
+    pub strategies_in_exploration: bool,
+
+    /// Use only registered custom verifiers, skipping the built-in LLM verifier
+    /// Default: false (custom verifiers augment the built-in one)
+    #[serde(default)]
+    pub replace_builtin_verifier: bool,
+
+    /// How solutions are verified
+    /// Default: Llm
+    #[serde(default)]
+    pub verification_mode: crate::types::VerificationMode,
+
+    /// Whether runs classify their query to pick a preset or lightweight mode
+    /// Default: Off
+    #[serde(default)]
+    pub query_classification: crate::types::QueryClassification,
+
+    /// Test cases run against extracted code in execution mode
+    /// Default: empty (the code only needs to run successfully)
+    #[serde(default)]
+    pub code_test_cases: Vec<crate::code_verifier::TestCase>,
+
+    /// Number of unit tests to request in generated-tests verification
+    /// Default: 5
+    #[serde(default = "default_num_generated_tests")]
+    pub num_generated_tests: usize,
+
+    /// Resource limits for execution-based verification
+    #[serde(default)]
+    pub code_execution_limits: crate::code_verifier::ExecutionLimits,
+
+    /// Isolation of the programs run by execution-based verification
+    /// Default: the platform sandbox
+    #[serde(default)]
+    pub code_execution_sandbox: crate::code_verifier::ExecutionSandbox,
+
+    /// Per-1k-token prices overriding or extending the built-in pricing
+    /// table, keyed by model name or `provider/model`
+    /// Default: empty
+    #[serde(default)]
+    pub model_pricing: std::collections::HashMap<String, crate::cost::ModelPricing>,
+
+    /// Dollars a run may spend; phases projected to overrun it are skipped
+    /// and the output is marked `budget_truncated`
+    /// Default: None (unlimited)
+    #[serde(default)]
+    pub max_cost_usd: Option<f64>,
+
+    /// Log the full prompt and response of every provider call, the
+    /// workspace after each phase and the selection decisions at debug
+    /// level, with API keys redacted
     /// Default: false
     pub debug: bool,
+
+    /// Directory debug mode also writes each run's workspace dumps and
+    /// selection decisions to, under the run ID
+    /// Default: None (log only)
+    #[serde(default)]
+    pub debug_dir: Option<std::path::PathBuf>,
+
+    /// JSON Lines file every event and provider call of a run is appended to
+    /// Default: None (no trace)
+    #[serde(default)]
+    pub trace_path: Option<std::path::PathBuf>,
+
+    /// Replace prompts and response texts in the trace by their length
+    /// Default: false
+    #[serde(default)]
+    pub redact_trace: bool,
+
+    /// Webhook told the outcome of each run
+    /// Default: None (no notifications)
+    #[serde(default)]
+    pub webhook: Option<crate::webhook::WebhookConfig>,
+}
+
+fn default_num_verifiers() -> usize {
+    2
+}
+
+fn default_verification_temperatures() -> Vec<f32> {
+    vec![0.3]
+}
+
+fn default_num_generated_tests() -> usize {
+    5
+}
+
+fn default_moa_num_layers() -> usize {
+    1
+}
+
+fn default_strategy_dedup_threshold() -> f32 {
+    crate::strategy::DEFAULT_DEDUP_THRESHOLD
+}
+
+fn default_num_alternatives() -> usize {
+    3
+}
+
+fn default_strategy_prompt_limit() -> usize {
+    3
+}
+
+fn default_strategy_namespace() -> String {
+    crate::strategy_store::DEFAULT_NAMESPACE.to_string()
+}
+
+fn default_mcts_parallel_simulations() -> usize {
+    1
+}
+
+fn default_ga_population_size() -> usize {
+    6
+}
+
+fn default_ga_generations() -> usize {
+    3
+}
+
+fn default_ga_mutation_rate() -> f32 {
+    0.2
+}
+
+fn default_beam_width() -> usize {
+    3
+}
+
+fn default_beam_depth() -> usize {
+    2
+}
+
+fn default_best_of_n_samples() -> usize {
+    4
 }
 
 impl Default for MarsConfig {
@@ -105,10 +480,14 @@ impl Default for MarsConfig {
         Self {
             num_agents: 3,
             temperatures: vec![0.3, 0.6, 1.0],
-            consensus_threshold: 2,
+            consensus_threshold: crate::types::DEFAULT_CONSENSUS_THRESHOLD,
+            num_verifiers: default_num_verifiers(),
+            verification_quorum: crate::types::QuorumRule::All,
+            verification_temperatures: default_verification_temperatures(),
             enable_aggregation: false,
             enable_strategy_network: false,
             max_iterations: 5,
+            rollback_failed_improvements: false,
             use_thinking_tags: true,
             token_budget_reasoning: 64000,
             token_budget_lightweight: 4000,
@@ -119,6 +498,17 @@ impl Default for MarsConfig {
             aggregation_method: crate::types::AggregationMethod::RSA,
             moa_num_completions: 3,
             moa_fallback_enabled: true,
+            rsa_parent_selection: crate::types::ParentSelection::Random,
+            rsa_diversity_penalty: 0.0,
+            moa_num_layers: default_moa_num_layers(),
+            moa_layer_models: Vec::new(),
+            aggregation_prompts: crate::prompts::AggregationPrompts::default(),
+            prompts: crate::prompts::PromptSet::default(),
+            prompt_variables: crate::templates::PromptVariables::new(),
+            prompt_partials: crate::templates::PromptVariables::new(),
+            response_language: None,
+            answer_type: crate::types::AnswerType::FreeText,
+            num_alternatives: default_num_alternatives(),
             provider_routing: None,
             enable_multi_provider: false,
             timeout_seconds: 300,
@@ -126,7 +516,48 @@ impl Default for MarsConfig {
             mcts_exploration_weight: 0.2,
             mcts_num_simulations: 2,
             mcts_num_actions: 3,
+            mcts_parallel_simulations: default_mcts_parallel_simulations(),
+            mcts_selection_policy: crate::types::MctsSelectionPolicy::Ucb1,
+            mcts_transpositions: false,
+            mcts_early_stop_visit_share: None,
+            mcts_early_stop_value_std: None,
+            mcts_temperature_schedule: crate::types::MctsTemperatureSchedule::Constant,
+            ga_population_size: default_ga_population_size(),
+            ga_generations: default_ga_generations(),
+            ga_mutation_rate: default_ga_mutation_rate(),
+            beam_width: default_beam_width(),
+            beam_depth: default_beam_depth(),
+            best_of_n_samples: default_best_of_n_samples(),
+            best_of_n_scorer: crate::types::BestOfNScorer::Verifier,
+            score_aggregation: crate::types::ScoreAggregation::Mean,
+            exclude_self_verification: false,
+            enable_verification_cache: false,
+            verification_cache_path: None,
+            workspace_backend: crate::types::WorkspaceBackend::default(),
+            solution_dedup: crate::types::SolutionDedup::default(),
+            max_workspace_solutions: None,
+            workspace_eviction: crate::types::EvictionPolicy::default(),
+            enable_workspace_log: false,
+            strategy_store_path: None,
+            strategy_namespace: default_strategy_namespace(),
+            strategy_dedup_threshold: default_strategy_dedup_threshold(),
+            strategy_merge_threshold: None,
+            strategy_prompt_limit: default_strategy_prompt_limit(),
+            strategies_in_exploration: false,
+            replace_builtin_verifier: false,
+            verification_mode: crate::types::VerificationMode::Llm,
+            query_classification: crate::types::QueryClassification::Off,
+            code_test_cases: Vec::new(),
+            num_generated_tests: default_num_generated_tests(),
+            code_execution_limits: crate::code_verifier::ExecutionLimits::default(),
+            code_execution_sandbox: crate::code_verifier::ExecutionSandbox::default(),
+            model_pricing: std::collections::HashMap::new(),
+            max_cost_usd: None,
             debug: false,
+            debug_dir: None,
+            trace_path: None,
+            redact_trace: false,
+            webhook: None,
         }
     }
 }
@@ -137,6 +568,58 @@ impl MarsConfig {
         Self::default()
     }
 
+    /// Tuned defaults for a common task type
+    ///
+    /// Builder calls on the result override individual settings.
+    pub fn preset(preset: crate::types::Preset) -> Self {
+        Self::default().with_preset(preset)
+    }
+
+    /// Apply a preset's agent, aggregation, verification and prompt settings,
+    /// keeping everything else (providers, pricing, workspace, ...)
+    pub fn with_preset(self, preset: crate::types::Preset) -> Self {
+        use crate::types::{AggregationMethod, Preset, QuorumRule, VerificationMode};
+
+        let config = self.with_aggregation(true);
+        match preset {
+            Preset::Math => config
+                .with_temperatures(vec![0.2, 0.4, 0.6, 0.8, 1.0])
+                .with_num_agents(5)
+                .with_aggregation_method(AggregationMethod::RSA)
+                .with_num_verifiers(3)
+                .with_verification_quorum(QuorumRule::All)
+                .with_strategy_network(true)
+                .with_aggregation_system_prompt(crate::prompts::MATH_PRESET_PROMPT),
+            Preset::Coding => config
+                .with_temperatures(vec![0.2, 0.4, 0.6, 0.8])
+                .with_num_agents(4)
+                .with_aggregation_method(AggregationMethod::PlanAndSolve)
+                .with_verification_mode(VerificationMode::GeneratedTests)
+                .with_max_iterations(3)
+                .with_aggregation_system_prompt(crate::prompts::CODING_PRESET_PROMPT),
+            Preset::Creative => config
+                .with_temperatures(vec![0.7, 0.9, 1.1, 1.3])
+                .with_num_agents(4)
+                .with_moa_aggregation()
+                .with_moa_num_completions(4)
+                .with_verification_mode(VerificationMode::Pairwise)
+                .with_consensus_threshold(1)
+                .with_max_iterations(2)
+                .with_aggregation_system_prompt(crate::prompts::CREATIVE_PRESET_PROMPT),
+            Preset::Research => config
+                .with_temperatures(vec![0.3, 0.5, 0.7, 0.9])
+                .with_num_agents(4)
+                .with_moa_aggregation()
+                .with_moa_num_layers(2)
+                .with_verification_mode(VerificationMode::Adversarial)
+                .with_num_verifiers(2)
+                .with_verification_quorum(QuorumRule::Majority)
+                .with_strategy_network(true)
+                .with_max_iterations(3)
+                .with_aggregation_system_prompt(crate::prompts::RESEARCH_PRESET_PROMPT),
+        }
+    }
+
     /// Enable all advanced features (aggregation and strategy network)
     pub fn with_advanced_features(mut self) -> Self {
         self.enable_aggregation = true;
@@ -201,12 +684,60 @@ impl MarsConfig {
         self
     }
 
+    /// Enable debug mode, writing each run's dumps under `dir` as well
+    pub fn with_debug_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.debug = true;
+        self.debug_dir = Some(dir.into());
+        self
+    }
+
+    /// Append every event and provider call to a JSON Lines trace at `path`
+    pub fn with_trace_path(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.trace_path = Some(path.into());
+        self
+    }
+
+    /// Trace only the length of prompts and response texts
+    pub fn with_trace_redaction(mut self, redact: bool) -> Self {
+        self.redact_trace = redact;
+        self
+    }
+
+    /// POST a summary of each run to `webhook` when it completes or fails
+    pub fn with_webhook(mut self, webhook: crate::webhook::WebhookConfig) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
     /// Set aggregation method
     pub fn with_aggregation_method(mut self, method: crate::types::AggregationMethod) -> Self {
         self.aggregation_method = method;
         self
     }
 
+    /// Turn aggregation off with `none`, or on with the method named `name`,
+    /// one of [`AGGREGATION_NAMES`]
+    pub fn with_aggregation_name(self, name: &str) -> crate::Result<Self> {
+        use crate::types::AggregationMethod;
+        let method = match name {
+            "none" => return Ok(self.with_aggregation(false)),
+            "rsa" => AggregationMethod::RSA,
+            "moa" => AggregationMethod::MixtureOfAgents,
+            "mcts" => AggregationMethod::MonteCarloTreeSearch,
+            "genetic" => AggregationMethod::GeneticAlgorithm,
+            "beam" => AggregationMethod::BeamSearch,
+            "best_of_n" => AggregationMethod::BestOfN,
+            "plan_solve" => AggregationMethod::PlanAndSolve,
+            _ => {
+                return Err(crate::MarsError::InvalidConfiguration(format!(
+                    "unknown aggregation {name}; expected one of {}",
+                    AGGREGATION_NAMES.join(", ")
+                )));
+            }
+        };
+        Ok(self.with_aggregation(true).with_aggregation_method(method))
+    }
+
     /// Set MOA aggregation method
     pub fn with_moa_aggregation(mut self) -> Self {
         self.aggregation_method = crate::types::AggregationMethod::MixtureOfAgents;
@@ -242,6 +773,50 @@ impl MarsConfig {
         self
     }
 
+    /// Load a configuration from a TOML, YAML or JSON file over the defaults,
+    /// with `MARS__` environment overrides (see [`crate::config_file`]) and
+    /// provider secrets read from the environment
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> crate::Result<Self> {
+        let defaults = serde_json::to_value(Self::default())
+            .map_err(|e| crate::MarsError::ParsingError(e.to_string()))?;
+        let config: Self =
+            crate::config_file::load(path.as_ref(), defaults, crate::config_file::ENV_PREFIX)?;
+        config.resolve_env()
+    }
+
+    /// Load a configuration from JSON, reading provider secrets from the
+    /// environment (see [`MarsConfig::resolve_env`])
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        let config: Self = serde_json::from_str(json)
+            .map_err(|e| crate::MarsError::ParsingError(format!("Invalid MARS config: {e}")))?;
+        config.resolve_env()
+    }
+
+    /// The configuration with every provider's `${VAR}` references and
+    /// `api_key_env` read from the environment
+    ///
+    /// Fails with every variable that is not set.
+    pub fn resolve_env(mut self) -> crate::Result<Self> {
+        let mut errors = Vec::new();
+        if let Some(routing) = &self.provider_routing {
+            match routing.resolve_env() {
+                Ok(resolved) => self.provider_routing = Some(resolved),
+                Err(e) => errors.extend(e),
+            }
+        }
+        for spec in self.moa_layer_models.iter_mut().filter(|s| s.enabled) {
+            match spec.resolve_env() {
+                Ok(resolved) => *spec = resolved,
+                Err(e) => errors.push(e),
+            }
+        }
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(crate::MarsError::InvalidConfiguration(errors.join("; ")))
+        }
+    }
+
     /// Get token budget based on mode
     pub fn get_token_budget(&self, is_lightweight: bool) -> usize {
         if is_lightweight {
@@ -259,6 +834,35 @@ impl MarsConfig {
         max_tokens.map(|mt| mt <= 4000).unwrap_or(false)
     }
 
+    /// Set how RSA picks parents (exploitation vs. exploration)
+    pub fn with_rsa_parent_selection(mut self, selection: crate::types::ParentSelection) -> Self {
+        self.rsa_parent_selection = selection;
+        self
+    }
+
+    /// Penalize selecting RSA parents similar to ones already chosen
+    pub fn with_rsa_diversity_penalty(mut self, penalty: f32) -> Self {
+        self.rsa_diversity_penalty = penalty.max(0.0);
+        self
+    }
+
+    /// Set number of MOA proposer layers
+    pub fn with_moa_num_layers(mut self, layers: usize) -> Self {
+        if layers > 0 {
+            self.moa_num_layers = layers;
+        }
+        self
+    }
+
+    /// Assign a model to each MOA proposer layer, in order
+    pub fn with_moa_layer_models(
+        mut self,
+        models: Vec<crate::provider_config::ProviderSpec>,
+    ) -> Self {
+        self.moa_layer_models = models;
+        self
+    }
+
     /// Set MCTS simulation depth
     pub fn with_mcts_simulation_depth(mut self, depth: usize) -> Self {
         self.mcts_simulation_depth = depth;
@@ -283,6 +887,460 @@ impl MarsConfig {
         self
     }
 
+    /// Set how many MCTS simulations run concurrently (at least 1)
+    pub fn with_mcts_parallel_simulations(mut self, num: usize) -> Self {
+        if num > 0 {
+            self.mcts_parallel_simulations = num;
+        }
+        self
+    }
+
+    /// Set the MCTS tree policy (UCB1, PUCT, or Thompson sampling)
+    pub fn with_mcts_selection_policy(mut self, policy: crate::types::MctsSelectionPolicy) -> Self {
+        self.mcts_selection_policy = policy;
+        self
+    }
+
+    /// Enable or disable the MCTS transposition table
+    pub fn with_mcts_transpositions(mut self, enabled: bool) -> Self {
+        self.mcts_transpositions = enabled;
+        self
+    }
+
+    /// Stop MCTS early once the most visited root child holds this share
+    /// (0.0 to 1.0) of the root's visits
+    pub fn with_mcts_early_stop_visit_share(mut self, share: f32) -> Self {
+        self.mcts_early_stop_visit_share = Some(share.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Stop MCTS early once the most visited root child's values vary by at
+    /// most this standard deviation
+    pub fn with_mcts_early_stop_value_std(mut self, std: f32) -> Self {
+        self.mcts_early_stop_value_std = Some(std.max(0.0));
+        self
+    }
+
+    /// Set the MCTS per-depth generation temperature schedule
+    pub fn with_mcts_temperature_schedule(
+        mut self,
+        schedule: crate::types::MctsTemperatureSchedule,
+    ) -> Self {
+        self.mcts_temperature_schedule = schedule;
+        self
+    }
+
+    /// Set genetic-algorithm population size (at least 2)
+    pub fn with_ga_population_size(mut self, size: usize) -> Self {
+        if size >= 2 {
+            self.ga_population_size = size;
+        }
+        self
+    }
+
+    /// Set number of genetic-algorithm generations
+    pub fn with_ga_generations(mut self, generations: usize) -> Self {
+        self.ga_generations = generations;
+        self
+    }
+
+    /// Set genetic-algorithm mutation rate (clamped to 0.0..=1.0)
+    pub fn with_ga_mutation_rate(mut self, rate: f32) -> Self {
+        self.ga_mutation_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set beam-search width
+    pub fn with_beam_width(mut self, width: usize) -> Self {
+        if width > 0 {
+            self.beam_width = width;
+        }
+        self
+    }
+
+    /// Set beam-search depth
+    pub fn with_beam_depth(mut self, depth: usize) -> Self {
+        self.beam_depth = depth;
+        self
+    }
+
+    /// Override the system prompt used for aggregation calls
+    pub fn with_aggregation_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.aggregation_prompts.system = Some(prompt.into());
+        self
+    }
+
+    /// Override the MOA proposer prompt (placeholders: `{query}`, `{references}`)
+    pub fn with_moa_proposer_prompt(mut self, template: impl Into<String>) -> Self {
+        self.aggregation_prompts.moa_proposer = Some(template.into());
+        self
+    }
+
+    /// Override the MOA synthesis prompt (placeholders: `{query}`,
+    /// `{candidates}`, `{critique}`)
+    pub fn with_moa_aggregator_prompt(mut self, template: impl Into<String>) -> Self {
+        self.aggregation_prompts.moa_aggregator = Some(template.into());
+        self
+    }
+
+    /// Override the RSA merge prompt (placeholders: `{query}`, `{solutions}`)
+    pub fn with_rsa_merge_prompt(mut self, template: impl Into<String>) -> Self {
+        self.aggregation_prompts.rsa_merge = Some(template.into());
+        self
+    }
+
+    /// Replace all agent prompt overrides
+    pub fn with_prompts(mut self, prompts: crate::prompts::PromptSet) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Override the system prompt for generation and improvement calls
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompts.system = Some(prompt.into());
+        self
+    }
+
+    /// Override the exploration prompt (placeholders: `{query}`)
+    pub fn with_reasoning_prompt(mut self, template: impl Into<String>) -> Self {
+        self.prompts.reasoning = Some(template.into());
+        self
+    }
+
+    /// Override the verification system prompt (placeholders: `{query}`)
+    pub fn with_verification_prompt(mut self, template: impl Into<String>) -> Self {
+        self.prompts.verification = Some(template.into());
+        self
+    }
+
+    /// Override the improvement prompt (placeholders: `{solution}`,
+    /// `{answer}`, `{feedback}`)
+    pub fn with_improvement_prompt(mut self, template: impl Into<String>) -> Self {
+        self.prompts.improvement = Some(template.into());
+        self
+    }
+
+    /// Override the strategy extraction prompt (placeholders: `{solution}`)
+    pub fn with_strategy_extraction_prompt(mut self, template: impl Into<String>) -> Self {
+        self.prompts.strategy_extraction = Some(template.into());
+        self
+    }
+
+    /// Whether any prompt override is set and needs rendering each run
+    pub fn has_prompt_templates(&self) -> bool {
+        self.prompts != crate::prompts::PromptSet::default()
+            || self.aggregation_prompts != crate::prompts::AggregationPrompts::default()
+    }
+
+    /// Set the language for reasoning and final answers
+    pub fn with_response_language(mut self, language: impl Into<String>) -> Self {
+        self.response_language = Some(language.into());
+        self
+    }
+
+    /// The configuration with `response_language` added to the agent,
+    /// verification and aggregation system prompts
+    ///
+    /// Unchanged when no language is set.
+    pub fn localized(mut self) -> Self {
+        let Some(language) = self.response_language.clone() else {
+            return self;
+        };
+        let instruction = crate::prompts::language_instruction(
+            crate::prompts::RESPONSE_LANGUAGE_PROMPT,
+            &language,
+        );
+        let system = self.prompts.system_prompt(self.use_thinking_tags);
+        self.prompts.system = Some(format!("{system}\n\n{instruction}"));
+        let verification = self
+            .prompts
+            .verification
+            .as_deref()
+            .unwrap_or(crate::prompts::VERIFICATION_SYSTEM_PROMPT);
+        let verification_instruction = crate::prompts::language_instruction(
+            crate::prompts::VERIFICATION_LANGUAGE_PROMPT,
+            &language,
+        );
+        self.prompts.verification = Some(format!("{verification}\n\n{verification_instruction}"));
+        let aggregation = self.aggregation_prompts.system_prompt();
+        self.aggregation_prompts.system = Some(format!("{aggregation}\n\n{instruction}"));
+        self
+    }
+
+    /// Expect the final answer in a given shape
+    pub fn with_answer_type(mut self, answer_type: crate::types::AnswerType) -> Self {
+        self.answer_type = answer_type;
+        self
+    }
+
+    /// Set how many distinct answers the output ranks (0 to list none)
+    pub fn with_num_alternatives(mut self, count: usize) -> Self {
+        self.num_alternatives = count;
+        self
+    }
+
+    /// The configuration with the format instructions of `answer_type` added
+    /// to the agent and aggregation system prompts
+    ///
+    /// Unchanged for free-text answers.
+    pub fn formatted(mut self) -> Self {
+        let Some(instruction) = self.answer_type.format_instruction() else {
+            return self;
+        };
+        let system = self.prompts.system_prompt(self.use_thinking_tags);
+        self.prompts.system = Some(format!("{system}\n\n{instruction}"));
+        let aggregation = self.aggregation_prompts.system_prompt();
+        self.aggregation_prompts.system = Some(format!("{aggregation}\n\n{instruction}"));
+        self
+    }
+
+    /// Declare a prompt template variable with its default value
+    pub fn with_prompt_variable(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.prompt_variables.insert(name.into(), value.into());
+        self
+    }
+
+    /// Add a named template that prompt overrides can include
+    pub fn with_prompt_partial(
+        mut self,
+        name: impl Into<String>,
+        template: impl Into<String>,
+    ) -> Self {
+        self.prompt_partials.insert(name.into(), template.into());
+        self
+    }
+
+    /// Set number of completions sampled by best-of-N aggregation
+    pub fn with_best_of_n_samples(mut self, num: usize) -> Self {
+        if num > 0 {
+            self.best_of_n_samples = num;
+        }
+        self
+    }
+
+    /// Set how best-of-N aggregation scores its samples
+    pub fn with_best_of_n_scorer(mut self, scorer: crate::types::BestOfNScorer) -> Self {
+        self.best_of_n_scorer = scorer;
+        self
+    }
+
+    /// Set number of verification passes per solution
+    pub fn with_num_verifiers(mut self, num: usize) -> Self {
+        if num > 0 {
+            self.num_verifiers = num;
+        }
+        self
+    }
+
+    /// Set quorum rule for verification consensus
+    pub fn with_verification_quorum(mut self, rule: crate::types::QuorumRule) -> Self {
+        self.verification_quorum = rule;
+        self
+    }
+
+    /// Set the agreement needed for consensus (see `consensus_threshold`)
+    pub fn with_consensus_threshold(mut self, threshold: usize) -> Self {
+        self.consensus_threshold = threshold;
+        self
+    }
+
+    /// Set per-pass verification temperatures
+    pub fn with_verification_temperatures(mut self, temps: Vec<f32>) -> Self {
+        if !temps.is_empty() {
+            self.verification_temperatures = temps;
+        }
+        self
+    }
+
+    /// Temperature for the given verification pass (0-based)
+    pub fn verification_temperature(&self, pass: usize) -> f32 {
+        if self.verification_temperatures.is_empty() {
+            return default_verification_temperatures()[0];
+        }
+        self.verification_temperatures[pass % self.verification_temperatures.len()]
+    }
+
+    /// Set how repeated verification scores are aggregated
+    pub fn with_score_aggregation(mut self, method: crate::types::ScoreAggregation) -> Self {
+        self.score_aggregation = method;
+        self
+    }
+
+    /// Exclude a solution's originating model from verifying it
+    pub fn with_self_verification_excluded(mut self, excluded: bool) -> Self {
+        self.exclude_self_verification = excluded;
+        self
+    }
+
+    /// Enable the in-memory verification cache
+    pub fn with_verification_cache(mut self, enabled: bool) -> Self {
+        self.enable_verification_cache = enabled;
+        self
+    }
+
+    /// Enable the verification cache, persisting it to `path`
+    pub fn with_verification_cache_path(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.enable_verification_cache = true;
+        self.verification_cache_path = Some(path.into());
+        self
+    }
+
+    /// Keep the workspace in a persistent backend (SQLite or sled)
+    pub fn with_workspace_backend(mut self, backend: crate::types::WorkspaceBackend) -> Self {
+        self.workspace_backend = backend;
+        self
+    }
+
+    /// Roll back improvement iterations whose solutions do not verify better
+    /// than their originals, and stop improving after one
+    pub fn with_improvement_rollback(mut self, enabled: bool) -> Self {
+        self.rollback_failed_improvements = enabled;
+        self
+    }
+
+    /// Cap the workspace at `max` solutions (at least 1), evicting beyond it
+    pub fn with_max_workspace_solutions(mut self, max: usize) -> Self {
+        self.max_workspace_solutions = Some(max.max(1));
+        self
+    }
+
+    /// Choose which solutions are evicted when the workspace is full
+    pub fn with_workspace_eviction(mut self, policy: crate::types::EvictionPolicy) -> Self {
+        self.workspace_eviction = policy;
+        self
+    }
+
+    /// Log workspace changes so any point of a run can be reconstructed
+    pub fn with_workspace_log(mut self, enabled: bool) -> Self {
+        self.enable_workspace_log = enabled;
+        self
+    }
+
+    /// Merge duplicate solutions as they are added to the workspace
+    ///
+    /// A `Fuzzy` threshold is clamped to 0.0-1.0.
+    pub fn with_solution_dedup(mut self, dedup: crate::types::SolutionDedup) -> Self {
+        self.solution_dedup = match dedup {
+            crate::types::SolutionDedup::Fuzzy(threshold) => {
+                crate::types::SolutionDedup::Fuzzy(threshold.clamp(0.0, 1.0))
+            }
+            other => other,
+        };
+        self
+    }
+
+    /// Persist extracted strategies to `path`, loading earlier ones at startup
+    pub fn with_strategy_store_path(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.strategy_store_path = Some(path.into());
+        self
+    }
+
+    /// Load and save strategies under a namespace (e.g. a domain or tag)
+    pub fn with_strategy_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.strategy_namespace = namespace.into();
+        self
+    }
+
+    /// Skip new strategies whose descriptions overlap an existing one by at
+    /// least `threshold` (above 1.0 disables)
+    pub fn with_strategy_dedup_threshold(mut self, threshold: f32) -> Self {
+        self.strategy_dedup_threshold = threshold;
+        self
+    }
+
+    /// Merge strategies at least `threshold` similar after each extraction
+    pub fn with_strategy_merging(mut self, threshold: f32) -> Self {
+        self.strategy_merge_threshold = Some(threshold.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Include up to `limit` relevant strategies in improvement prompts (0 disables)
+    pub fn with_strategy_prompt_limit(mut self, limit: usize) -> Self {
+        self.strategy_prompt_limit = limit;
+        self
+    }
+
+    /// Include known strategies in exploration prompts as well
+    pub fn with_strategies_in_exploration(mut self, enabled: bool) -> Self {
+        self.strategies_in_exploration = enabled;
+        self
+    }
+
+    /// Let registered custom verifiers replace the built-in LLM verifier
+    pub fn with_builtin_verifier_replaced(mut self, replaced: bool) -> Self {
+        self.replace_builtin_verifier = replaced;
+        self
+    }
+
+    /// Set verification mode
+    pub fn with_verification_mode(mut self, mode: crate::types::VerificationMode) -> Self {
+        self.verification_mode = mode;
+        self
+    }
+
+    /// Classify each run's query to pick a preset or lightweight mode
+    pub fn with_query_classification(
+        mut self,
+        classification: crate::types::QueryClassification,
+    ) -> Self {
+        self.query_classification = classification;
+        self
+    }
+
+    /// Set test cases for execution-based verification
+    pub fn with_code_test_cases(mut self, test_cases: Vec<crate::code_verifier::TestCase>) -> Self {
+        self.code_test_cases = test_cases;
+        self
+    }
+
+    /// Set number of unit tests to generate for generated-tests verification
+    pub fn with_num_generated_tests(mut self, num: usize) -> Self {
+        if num > 0 {
+            self.num_generated_tests = num;
+        }
+        self
+    }
+
+    /// Set resource limits for execution-based verification
+    pub fn with_code_execution_limits(
+        mut self,
+        limits: crate::code_verifier::ExecutionLimits,
+    ) -> Self {
+        self.code_execution_limits = limits;
+        self
+    }
+
+    /// Isolate programs run by execution-based verification with `sandbox`
+    pub fn with_code_execution_sandbox(
+        mut self,
+        sandbox: crate::code_verifier::ExecutionSandbox,
+    ) -> Self {
+        self.code_execution_sandbox = sandbox;
+        self
+    }
+
+    /// Price a model's tokens (name or `provider/model`), overriding the
+    /// built-in price if there is one
+    pub fn with_model_pricing(
+        mut self,
+        model: impl Into<String>,
+        pricing: crate::cost::ModelPricing,
+    ) -> Self {
+        self.model_pricing.insert(model.into(), pricing);
+        self
+    }
+
+    /// Cap a run's spend at `max_cost_usd` dollars, skipping to synthesis
+    /// before a phase that would overrun it
+    pub fn with_max_cost_usd(mut self, max_cost_usd: f64) -> Self {
+        self.max_cost_usd = Some(max_cost_usd);
+        self
+    }
+
     /// Get MCTS configuration from Mars config
     pub fn get_mcts_config(&self) -> crate::mcts::MCTSConfig {
         crate::mcts::MCTSConfig {
@@ -291,10 +1349,220 @@ impl MarsConfig {
             num_simulations: self.mcts_num_simulations,
             num_actions: self.mcts_num_actions,
             generation_temperature: 1.0,
+            temperature_schedule: self.mcts_temperature_schedule.clone(),
             evaluation_temperature: 0.1,
             max_history_length: 10,
+            parallel_simulations: self.mcts_parallel_simulations,
+            selection_policy: self.mcts_selection_policy,
+            use_transpositions: self.mcts_transpositions,
+            early_stop_visit_share: self.mcts_early_stop_visit_share,
+            early_stop_value_std: self.mcts_early_stop_value_std,
+            ..crate::mcts::MCTSConfig::default()
+        }
+    }
+
+    /// Get genetic-algorithm configuration
+    pub fn get_genetic_config(&self) -> crate::genetic::GeneticConfig {
+        crate::genetic::GeneticConfig {
+            population_size: self.ga_population_size,
+            generations: self.ga_generations,
+            mutation_rate: self.ga_mutation_rate,
+            system_prompt: self.aggregation_prompts.system_prompt().to_string(),
+            ..crate::genetic::GeneticConfig::default()
+        }
+    }
+
+    /// Get RSA parent-selection configuration
+    pub fn get_rsa_selection_config(&self) -> crate::aggregator::RsaSelectionConfig {
+        crate::aggregator::RsaSelectionConfig {
+            strategy: self.rsa_parent_selection,
+            diversity_penalty: self.rsa_diversity_penalty,
+        }
+    }
+
+    /// Get beam-search configuration
+    pub fn get_beam_search_config(&self) -> crate::beam::BeamSearchConfig {
+        crate::beam::BeamSearchConfig {
+            beam_width: self.beam_width,
+            depth: self.beam_depth,
+            system_prompt: self.aggregation_prompts.system_prompt().to_string(),
+            ..crate::beam::BeamSearchConfig::default()
+        }
+    }
+
+    /// Get best-of-N configuration
+    pub fn get_best_of_n_config(&self) -> crate::best_of_n::BestOfNConfig {
+        crate::best_of_n::BestOfNConfig {
+            num_samples: self.best_of_n_samples,
+            scorer: self.best_of_n_scorer,
+            temperatures: self.temperatures.clone(),
+            use_thinking_tags: self.use_thinking_tags,
+            prompts: self.prompts.clone(),
         }
     }
+
+    /// Check that the settings make sense together
+    ///
+    /// Returns every problem found. Provider specs are checked separately, by
+    /// [`crate::provider_config::ProviderRoutingConfig::validate`].
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, field: &'static str, message: String| {
+            if !ok {
+                errors.push(ConfigError { field, message });
+            }
+        };
+
+        check(
+            self.num_agents > 0,
+            "num_agents",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.temperatures.len() >= self.num_agents,
+            "temperatures",
+            format!(
+                "has {} values but num_agents is {}; give one per agent (with_num_agents pads them)",
+                self.temperatures.len(),
+                self.num_agents
+            ),
+        );
+        check(
+            self.temperatures.iter().all(|t| t.is_finite() && *t >= 0.0),
+            "temperatures",
+            format!("must all be non-negative, got {:?}", self.temperatures),
+        );
+        check(
+            self.num_verifiers > 0,
+            "num_verifiers",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.consensus_threshold > 0,
+            "consensus_threshold",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.aggregation_selection_size > 0,
+            "aggregation_selection_size",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.aggregation_selection_size <= self.aggregation_population_size,
+            "aggregation_selection_size",
+            format!(
+                "is {} but aggregation_population_size is only {}; select at most the whole population",
+                self.aggregation_selection_size, self.aggregation_population_size
+            ),
+        );
+        check(
+            self.rsa_diversity_penalty >= 0.0,
+            "rsa_diversity_penalty",
+            format!("must be non-negative, got {}", self.rsa_diversity_penalty),
+        );
+        check(
+            self.moa_num_layers > 0,
+            "moa_num_layers",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.moa_num_completions > 0,
+            "moa_num_completions",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.mcts_num_simulations > 0,
+            "mcts_num_simulations",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.mcts_parallel_simulations > 0,
+            "mcts_parallel_simulations",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.mcts_num_actions > 0,
+            "mcts_num_actions",
+            "must be at least 1 (MCTS needs actions to expand)".to_string(),
+        );
+        check(
+            self.mcts_early_stop_visit_share
+                .is_none_or(|share| share > 0.0 && share <= 1.0),
+            "mcts_early_stop_visit_share",
+            format!(
+                "must be a share of visits in (0, 1], got {:?}",
+                self.mcts_early_stop_visit_share
+            ),
+        );
+        check(
+            self.ga_population_size >= 2,
+            "ga_population_size",
+            format!(
+                "must be at least 2 to have parents to cross over, got {}",
+                self.ga_population_size
+            ),
+        );
+        check(
+            (0.0..=1.0).contains(&self.ga_mutation_rate),
+            "ga_mutation_rate",
+            format!(
+                "must be a probability in [0, 1], got {}",
+                self.ga_mutation_rate
+            ),
+        );
+        check(
+            self.beam_width > 0,
+            "beam_width",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.best_of_n_samples > 0,
+            "best_of_n_samples",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.max_workspace_solutions != Some(0),
+            "max_workspace_solutions",
+            "must be at least 1, or None for no limit".to_string(),
+        );
+        check(
+            self.max_cost_usd
+                .is_none_or(|budget| budget.is_finite() && budget >= 0.0),
+            "max_cost_usd",
+            format!("must be a non-negative amount, got {:?}", self.max_cost_usd),
+        );
+        check(
+            self.response_language
+                .as_deref()
+                .is_none_or(|language| !language.trim().is_empty()),
+            "response_language",
+            "must name a language, or be None to leave it to the model".to_string(),
+        );
+        for (field, message) in crate::templates::template_problems(self) {
+            check(false, field, message);
+        }
+        if let Some(webhook) = &self.webhook {
+            for message in webhook.problems() {
+                check(false, "webhook", message);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// A setting that [`MarsConfig::validate`] rejected
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{field} {message}")]
+pub struct ConfigError {
+    /// Name of the offending field
+    pub field: &'static str,
+    /// What is wrong with it and how to fix it
+    pub message: String,
 }
 
 #[cfg(test)]
@@ -331,4 +1599,407 @@ mod tests {
         assert_eq!(config.get_token_budget(false), 64000);
         assert_eq!(config.get_token_budget(true), 4000);
     }
+
+    #[test]
+    fn test_verification_mode_config() {
+        let config = MarsConfig::default();
+        assert_eq!(
+            config.verification_mode,
+            crate::types::VerificationMode::Llm
+        );
+
+        let config = MarsConfig::new()
+            .with_verification_mode(crate::types::VerificationMode::Execution)
+            .with_code_test_cases(vec![crate::code_verifier::TestCase::new("1", "2")]);
+        assert_eq!(
+            config.verification_mode,
+            crate::types::VerificationMode::Execution
+        );
+        assert_eq!(config.code_test_cases.len(), 1);
+    }
+
+    #[test]
+    fn test_verification_quorum_config() {
+        let config = MarsConfig::new()
+            .with_num_verifiers(3)
+            .with_verification_quorum(crate::types::QuorumRule::Majority)
+            .with_verification_temperatures(vec![0.1, 0.5]);
+        assert_eq!(config.num_verifiers, 3);
+        assert_eq!(
+            config.verification_quorum,
+            crate::types::QuorumRule::Majority
+        );
+        assert_eq!(config.verification_temperature(0), 0.1);
+        assert_eq!(config.verification_temperature(1), 0.5);
+        assert_eq!(config.verification_temperature(2), 0.1);
+    }
+
+    #[test]
+    fn test_genetic_config() {
+        let config = MarsConfig::new()
+            .with_aggregation_method(crate::types::AggregationMethod::GeneticAlgorithm)
+            .with_ga_population_size(1)
+            .with_ga_generations(4)
+            .with_ga_mutation_rate(1.5);
+        let genetic = config.get_genetic_config();
+        assert_eq!(genetic.population_size, 6);
+        assert_eq!(genetic.generations, 4);
+        assert_eq!(genetic.mutation_rate, 1.0);
+    }
+
+    #[test]
+    fn test_moa_layer_config() {
+        let config = MarsConfig::new()
+            .with_moa_num_layers(0)
+            .with_moa_layer_models(vec![crate::provider_config::ProviderSpec::new(
+                "openai", "gpt-4o",
+            )]);
+        assert_eq!(config.moa_num_layers, 1);
+        assert_eq!(config.with_moa_num_layers(3).moa_num_layers, 3);
+    }
+
+    #[test]
+    fn test_beam_search_config() {
+        let config = MarsConfig::new().with_beam_width(0).with_beam_depth(4);
+        let beam = config.get_beam_search_config();
+        assert_eq!(beam.beam_width, 3);
+        assert_eq!(beam.depth, 4);
+    }
+
+    #[test]
+    fn test_mcts_parallel_simulations() {
+        let config = MarsConfig::new().with_mcts_parallel_simulations(0);
+        assert_eq!(config.get_mcts_config().parallel_simulations, 1);
+        let config = config.with_mcts_parallel_simulations(4);
+        assert_eq!(config.get_mcts_config().parallel_simulations, 4);
+    }
+
+    #[test]
+    fn test_mcts_selection_policy() {
+        let config = MarsConfig::new();
+        assert_eq!(
+            config.get_mcts_config().selection_policy,
+            crate::types::MctsSelectionPolicy::Ucb1
+        );
+        let config = config.with_mcts_selection_policy(crate::types::MctsSelectionPolicy::Puct);
+        assert_eq!(
+            config.get_mcts_config().selection_policy,
+            crate::types::MctsSelectionPolicy::Puct
+        );
+    }
+
+    #[test]
+    fn test_mcts_transpositions() {
+        let config = MarsConfig::new();
+        assert!(!config.get_mcts_config().use_transpositions);
+        let config = config.with_mcts_transpositions(true);
+        assert!(config.get_mcts_config().use_transpositions);
+    }
+
+    #[test]
+    fn test_mcts_early_stopping() {
+        let mcts = MarsConfig::new().get_mcts_config();
+        assert!(mcts.early_stop_visit_share.is_none());
+        assert!(mcts.early_stop_value_std.is_none());
+
+        let mcts = MarsConfig::new()
+            .with_mcts_early_stop_visit_share(1.5)
+            .with_mcts_early_stop_value_std(0.05)
+            .get_mcts_config();
+        assert_eq!(mcts.early_stop_visit_share, Some(1.0));
+        assert_eq!(mcts.early_stop_value_std, Some(0.05));
+    }
+
+    #[test]
+    fn test_mcts_temperature_schedule() {
+        let schedule = crate::types::MctsTemperatureSchedule::PerDepth(vec![1.2, 0.4]);
+        let mcts = MarsConfig::new()
+            .with_mcts_temperature_schedule(schedule.clone())
+            .get_mcts_config();
+        assert_eq!(mcts.temperature_schedule, schedule);
+    }
+
+    #[test]
+    fn test_best_of_n_config() {
+        let config = MarsConfig::new()
+            .with_best_of_n_samples(0)
+            .with_best_of_n_scorer(crate::types::BestOfNScorer::RewardPrompt);
+        let best_of_n = config.get_best_of_n_config();
+        assert_eq!(best_of_n.num_samples, 4);
+        assert_eq!(best_of_n.scorer, crate::types::BestOfNScorer::RewardPrompt);
+        assert_eq!(best_of_n.temperatures, config.temperatures);
+    }
+
+    #[test]
+    fn test_aggregation_prompt_overrides() {
+        let config = MarsConfig::new()
+            .with_aggregation_system_prompt("You are a legal analyst.")
+            .with_rsa_merge_prompt("Merge: {solutions}");
+        assert_eq!(
+            config.aggregation_prompts.system_prompt(),
+            "You are a legal analyst."
+        );
+        assert_eq!(
+            config.aggregation_prompts.rsa_merge.as_deref(),
+            Some("Merge: {solutions}")
+        );
+        assert!(config.aggregation_prompts.moa_proposer.is_none());
+    }
+
+    #[test]
+    fn test_prompt_set_overrides() {
+        let config = MarsConfig::new()
+            .with_system_prompt("You are a contract lawyer.")
+            .with_reasoning_prompt("Review this clause: {query}");
+        assert_eq!(
+            config.prompts.system_prompt(true),
+            "You are a contract lawyer."
+        );
+        assert_eq!(
+            config.prompts.reasoning_prompt("Clause 4"),
+            "Review this clause: Clause 4"
+        );
+        assert!(config.prompts.improvement.is_none());
+
+        let parsed: crate::prompts::PromptSet =
+            serde_json::from_str(r#"{"verification": "Check: {query}"}"#).unwrap();
+        assert_eq!(parsed.verification_prompt("q"), "Check: q");
+        assert!(parsed.system.is_none());
+    }
+
+    #[test]
+    fn test_solution_dedup() {
+        use crate::types::SolutionDedup;
+        assert_eq!(MarsConfig::new().solution_dedup, SolutionDedup::Disabled);
+        let config = MarsConfig::new().with_solution_dedup(SolutionDedup::Fuzzy(1.4));
+        assert_eq!(config.solution_dedup, SolutionDedup::Fuzzy(1.0));
+    }
+
+    #[test]
+    fn test_workspace_limit() {
+        use crate::types::EvictionPolicy;
+        let config = MarsConfig::new();
+        assert!(config.max_workspace_solutions.is_none());
+        assert_eq!(
+            config.workspace_eviction,
+            EvictionPolicy::LowestScoreUnverified
+        );
+
+        let config = config
+            .with_max_workspace_solutions(0)
+            .with_workspace_eviction(EvictionPolicy::LeastRecentlyUsed);
+        assert_eq!(config.max_workspace_solutions, Some(1));
+        assert_eq!(config.workspace_eviction, EvictionPolicy::LeastRecentlyUsed);
+    }
+
+    #[test]
+    fn test_cost_config() {
+        use crate::cost::ModelPricing;
+        let config = MarsConfig::new();
+        assert!(config.max_cost_usd.is_none());
+        assert!(config.model_pricing.is_empty());
+
+        let config = config
+            .with_max_cost_usd(2.5)
+            .with_model_pricing("my-finetune", ModelPricing::new(0.003, 0.012));
+        assert_eq!(config.max_cost_usd, Some(2.5));
+        assert_eq!(
+            config.model_pricing["my-finetune"],
+            ModelPricing::new(0.003, 0.012)
+        );
+    }
+
+    #[test]
+    fn test_improvement_rollback() {
+        assert!(!MarsConfig::new().rollback_failed_improvements);
+        assert!(
+            MarsConfig::new()
+                .with_improvement_rollback(true)
+                .rollback_failed_improvements
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        assert!(MarsConfig::default().validate().is_ok());
+        assert!(MarsConfig::new().with_num_agents(5).validate().is_ok());
+        assert!(
+            MarsConfig::new()
+                .with_advanced_features()
+                .validate()
+                .is_ok()
+        );
+
+        let errors = MarsConfig::new()
+            .with_consensus_threshold(0)
+            .validate()
+            .unwrap_err();
+        assert_eq!(errors[0].field, "consensus_threshold");
+
+        let mut config = MarsConfig::new().with_temperatures(vec![0.5]);
+        config.aggregation_selection_size = 8;
+        config.mcts_num_simulations = 0;
+        let errors = config.validate().unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(
+            fields,
+            [
+                "temperatures",
+                "aggregation_selection_size",
+                "mcts_num_simulations"
+            ]
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            "aggregation_selection_size is 8 but aggregation_population_size is only 6; \
+             select at most the whole population"
+        );
+    }
+
+    #[test]
+    fn test_localized_prompts() {
+        let unchanged = MarsConfig::new().localized();
+        assert!(unchanged.prompts.system.is_none());
+
+        let config = MarsConfig::new()
+            .with_response_language("Japanese")
+            .with_aggregation_system_prompt("You are a careful editor.")
+            .localized();
+        let system = config.prompts.system_prompt(false);
+        assert!(system.starts_with(crate::prompts::MARS_SYSTEM_PROMPT_WITH_THINKING));
+        assert!(
+            system.ends_with(
+                "final answer in Japanese, whatever language the problem is written in."
+            )
+        );
+        let verification = config.prompts.verification_prompt("q");
+        assert!(verification.starts_with(crate::prompts::VERIFICATION_SYSTEM_PROMPT));
+        assert!(verification.contains("feedback in Japanese"));
+        assert!(
+            config
+                .aggregation_prompts
+                .system_prompt()
+                .starts_with("You are a careful editor.\n\n")
+        );
+        assert_eq!(
+            config.get_beam_search_config().system_prompt,
+            config.aggregation_prompts.system_prompt()
+        );
+
+        let errors = MarsConfig::new()
+            .with_response_language(" ")
+            .validate()
+            .unwrap_err();
+        assert_eq!(errors[0].field, "response_language");
+    }
+
+    #[test]
+    fn test_formatted_prompts() {
+        let unchanged = MarsConfig::new().formatted();
+        assert!(unchanged.prompts.system.is_none());
+        assert!(unchanged.aggregation_prompts.system.is_none());
+
+        let config = MarsConfig::new()
+            .with_answer_type(crate::types::AnswerType::Code)
+            .formatted();
+        let system = config.prompts.system_prompt(false);
+        assert!(system.starts_with(crate::prompts::MARS_SYSTEM_PROMPT_WITH_THINKING));
+        assert!(system.ends_with(crate::prompts::CODE_ANSWER_PROMPT));
+        assert!(
+            config
+                .aggregation_prompts
+                .system_prompt()
+                .ends_with(crate::prompts::CODE_ANSWER_PROMPT)
+        );
+    }
+
+    #[test]
+    fn test_validate_checks_prompt_templates() {
+        let config = MarsConfig::new()
+            .with_prompt_variable("domain", "legal")
+            .with_prompt_partial("format", "Reply in {{ language }}.")
+            .with_reasoning_prompt("{{ domain }} question: {query}\n{% include \"format\" %}")
+            .with_moa_proposer_prompt("{% if %}");
+        let errors = config.clone().validate().unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(
+            fields,
+            ["prompt_partials", "prompts", "aggregation_prompts"]
+        );
+        assert!(errors[0].message.contains("language"), "{}", errors[0]);
+
+        let config = config
+            .with_prompt_variable("language", "English")
+            .with_moa_proposer_prompt("{{ domain }}: {query}");
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_presets_are_valid_and_overridable() {
+        use crate::types::{AggregationMethod, Preset, VerificationMode};
+
+        for preset in Preset::ALL {
+            let config = MarsConfig::preset(preset);
+            assert!(config.validate().is_ok(), "{preset:?}");
+            assert!(config.enable_aggregation, "{preset:?}");
+            assert!(config.aggregation_prompts.system.is_some(), "{preset:?}");
+        }
+
+        let math = MarsConfig::preset(Preset::Math);
+        assert_eq!(math.num_agents, 5);
+        assert_eq!(math.temperatures.len(), 5);
+        assert_eq!(
+            MarsConfig::preset(Preset::Coding).verification_mode,
+            VerificationMode::GeneratedTests
+        );
+        assert!(matches!(
+            MarsConfig::preset(Preset::Creative).aggregation_method,
+            AggregationMethod::MixtureOfAgents
+        ));
+
+        let research = MarsConfig::preset(Preset::Research).with_max_iterations(1);
+        assert_eq!(research.max_iterations, 1);
+        assert_eq!(research.moa_num_layers, 2);
+    }
+
+    #[test]
+    fn test_from_json_resolves_provider_secrets() {
+        use crate::provider_config::{ProviderRoutingConfig, ProviderSpec};
+
+        // SAFETY: the variable is unique to this test
+        unsafe { std::env::set_var("MARS_TEST_CONFIG_KEY", "sk-config") };
+        let routing = ProviderRoutingConfig::single(
+            ProviderSpec::new("openai", "gpt-4o").with_api_key_env("MARS_TEST_CONFIG_KEY"),
+        );
+        let json =
+            serde_json::to_string(&MarsConfig::new().with_provider_routing(routing)).unwrap();
+        assert!(!json.contains("sk-config"));
+
+        let config = MarsConfig::from_json(&json).unwrap();
+        assert_eq!(
+            config.provider_routing.unwrap().primary.api_key,
+            "sk-config"
+        );
+
+        let missing = json.replace("MARS_TEST_CONFIG_KEY", "MARS_TEST_CONFIG_MISSING");
+        let err = MarsConfig::from_json(&missing).unwrap_err();
+        assert!(err.to_string().contains("MARS_TEST_CONFIG_MISSING"));
+    }
+
+    #[test]
+    fn test_with_aggregation_name() {
+        for name in AGGREGATION_NAMES {
+            let config = MarsConfig::default().with_aggregation_name(name).unwrap();
+            assert_eq!(config.enable_aggregation, *name != "none");
+        }
+        let config = MarsConfig::default().with_aggregation_name("moa").unwrap();
+        assert!(matches!(
+            config.aggregation_method,
+            crate::types::AggregationMethod::MixtureOfAgents
+        ));
+        let err = MarsConfig::default()
+            .with_aggregation_name("voting")
+            .unwrap_err();
+        assert!(err.to_string().contains("plan_solve"));
+    }
 }
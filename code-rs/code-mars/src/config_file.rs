@@ -0,0 +1,244 @@
+//! Loading configurations from TOML, YAML or JSON files.
+//!
+//! [`MarsConfig::from_file`](crate::config::MarsConfig::from_file) and
+//! [`ProviderRoutingConfig::from_file`](crate::provider_config::ProviderRoutingConfig::from_file)
+//! layer, lowest first: the built-in defaults, the file (its format picked
+//! by extension), then environment variables named after the field's path
+//! under [`ENV_PREFIX`], with `__` between nested fields
+//! (`MARS__NUM_AGENTS=5`, `MARS__PROVIDER_ROUTING__PRIMARY__MODEL=gpt-4o`).
+//! Builder calls on the loaded config override all three.
+
+use crate::{MarsError, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::path::Path;
+
+/// Prefix of environment variables that override config fields
+pub const ENV_PREFIX: &str = "MARS__";
+
+/// Contents of a `.toml`, `.yaml`/`.yml` or `.json` file
+fn read_file(path: &Path) -> Result<Value> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        MarsError::InvalidConfiguration(format!("Failed to read config {}: {e}", path.display()))
+    })?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let parsed = match extension.as_deref() {
+        Some("toml") => toml::from_str(&contents).map_err(|e| e.to_string()),
+        Some("yaml" | "yml") => serde_yaml::from_str(&contents).map_err(|e| e.to_string()),
+        Some("json") => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+        _ => {
+            return Err(MarsError::InvalidConfiguration(format!(
+                "Unsupported config format {} (expected .toml, .yaml, .yml or .json)",
+                path.display()
+            )));
+        }
+    };
+    parsed.map_err(|e| MarsError::ParsingError(format!("Invalid config {}: {e}", path.display())))
+}
+
+/// Merge `layer` into `base`, object by object; other values replace
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Override fields of `config` from the variables that start with `prefix`
+///
+/// Values are parsed as JSON (numbers, booleans, arrays) unless the field
+/// they replace is a string; ones that don't parse are taken as strings.
+fn apply_env_overrides(
+    config: &mut Value,
+    prefix: &str,
+    vars: impl IntoIterator<Item = (String, String)>,
+) {
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(prefix) else {
+            continue;
+        };
+        let keys: Vec<String> = path.split("__").map(str::to_ascii_lowercase).collect();
+        let Some((last, parents)) = keys.split_last() else {
+            continue;
+        };
+        if keys.iter().any(String::is_empty) {
+            tracing::warn!("Ignoring malformed config override {name}");
+            continue;
+        }
+
+        let mut target = &mut *config;
+        for key in parents {
+            if !target.is_object() {
+                *target = Value::Object(serde_json::Map::new());
+            }
+            let Value::Object(fields) = target else {
+                break;
+            };
+            target = fields.entry(key.clone()).or_insert(Value::Null);
+        }
+        if !target.is_object() {
+            *target = Value::Object(serde_json::Map::new());
+        }
+        if let Value::Object(fields) = target {
+            let value = match fields.get(last) {
+                Some(Value::String(_)) => Value::String(raw),
+                _ => serde_json::from_str(&raw).unwrap_or(Value::String(raw)),
+            };
+            fields.insert(last.clone(), value);
+        }
+    }
+}
+
+/// Load a `T` from `path`, layered over `defaults` and under the process's
+/// environment variables starting with `env_prefix`
+pub(crate) fn load<T: DeserializeOwned>(
+    path: &Path,
+    defaults: Value,
+    env_prefix: &str,
+) -> Result<T> {
+    let mut config = defaults;
+    merge(&mut config, read_file(path)?);
+    apply_env_overrides(&mut config, env_prefix, std::env::vars());
+    serde_json::from_value(config)
+        .map_err(|e| MarsError::ParsingError(format!("Invalid config {}: {e}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MarsConfig;
+    use crate::provider_config::{MarsPhase, ProviderRoutingConfig, RoutingStrategy};
+    use serde_json::json;
+
+    fn write(dir: &tempfile::TempDir, name: &str, contents: &str) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_env_overrides_nested_fields() {
+        let mut config = json!({
+            "num_agents": 3,
+            "provider_routing": {"primary": {"model": "gpt-4o-mini"}},
+            "verifier_model": "",
+        });
+        let vars = [
+            ("MARS__NUM_AGENTS", "5"),
+            ("MARS__PROVIDER_ROUTING__PRIMARY__MODEL", "gpt-4o"),
+            ("MARS__VERIFIER_MODEL", "42"),
+            ("MARS__TEMPERATURES", "[0.2, 0.8]"),
+            ("MARS__MOA_LAYER_MODELS__", "ignored"),
+            ("OTHER__NUM_AGENTS", "9"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        apply_env_overrides(&mut config, ENV_PREFIX, vars);
+
+        assert_eq!(
+            config,
+            json!({
+                "num_agents": 5,
+                "provider_routing": {"primary": {"model": "gpt-4o"}},
+                // Strings stay strings even when they look like numbers
+                "verifier_model": "42",
+                "temperatures": [0.2, 0.8],
+            })
+        );
+    }
+
+    #[test]
+    fn test_mars_config_from_toml_keeps_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            &dir,
+            "mars.toml",
+            r#"
+num_agents = 4
+temperatures = [0.2, 0.5, 0.8, 1.0]
+enable_aggregation = true
+
+[provider_routing]
+strategy = "Cheapest"
+
+[provider_routing.primary]
+provider = "openai"
+model = "gpt-4o-mini"
+api_key = "sk-file"
+
+[provider_routing.phase_providers.Verification]
+provider = "anthropic"
+model = "claude-3-5-sonnet"
+"#,
+        );
+
+        let config = MarsConfig::from_file(&path).unwrap().with_max_iterations(2);
+        assert_eq!(config.num_agents, 4);
+        assert!(config.enable_aggregation);
+        assert_eq!(config.max_iterations, 2);
+        assert_eq!(
+            config.consensus_threshold,
+            MarsConfig::default().consensus_threshold
+        );
+        assert!(config.validate().is_ok());
+
+        let routing = config.provider_routing.unwrap();
+        assert_eq!(routing.strategy, RoutingStrategy::Cheapest);
+        assert_eq!(routing.primary.api_key, "sk-file");
+        assert!(routing.primary.enabled);
+        assert_eq!(routing.timeout_seconds, 300);
+        assert_eq!(
+            routing.provider_for_phase(MarsPhase::Verification).provider,
+            "anthropic"
+        );
+    }
+
+    #[test]
+    fn test_routing_config_from_yaml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            &dir,
+            "routing.yml",
+            "primary:\n  provider: openai\n  model: gpt-4o-mini\nalternatives:\n  - provider: groq\n    model: llama-3.3-70b-versatile\n    weight: 2.0\nstrategy: Random\n",
+        );
+
+        let routing = ProviderRoutingConfig::from_file(&path).unwrap();
+        assert_eq!(routing.strategy, RoutingStrategy::Random);
+        assert_eq!(routing.alternatives[0].weight, Some(2.0));
+        assert!(routing.enable_fallback);
+    }
+
+    #[test]
+    fn test_bad_files_fail_clearly() {
+        let dir = tempfile::tempdir().unwrap();
+        let ini = write(&dir, "mars.ini", "num_agents = 5");
+        assert!(matches!(
+            MarsConfig::from_file(&ini),
+            Err(MarsError::InvalidConfiguration(_))
+        ));
+
+        let broken = write(&dir, "mars.toml", "num_agents = ");
+        assert!(matches!(
+            MarsConfig::from_file(&broken),
+            Err(MarsError::ParsingError(_))
+        ));
+
+        let wrong_type = write(&dir, "mars.yaml", "num_agents: many\n");
+        let err = MarsConfig::from_file(&wrong_type).unwrap_err();
+        assert!(err.to_string().contains("mars.yaml"), "{err}");
+
+        let missing = dir.path().join("missing.toml");
+        assert!(MarsConfig::from_file(&missing).is_err());
+    }
+}
@@ -10,77 +10,1001 @@ use crate::agent::Agent;
 /// 4. Iterative Improvement
 /// 5. Final Synthesis
 use crate::aggregator::Aggregator;
-use crate::config::MarsConfig;
-use crate::model_router::ModelClientRouter;
+use crate::classifier::{self, QueryClass};
+use crate::code_verifier::CodeVerifier;
+use crate::config::{ConfigError, MarsConfig};
+use crate::cost::{CostSummary, CostTracker, CostTrackingProvider};
+use crate::debug::{DebugDir, redact_secrets};
+use crate::event_bus::EventBus;
+use crate::mcts::{ActionGenerator, MCTSTree, ValueFunction};
+use crate::model_router::{ModelClientRouter, provider_from_spec};
+use crate::observe::{CallStats, ObservedProvider};
+use crate::pairwise::{EloRanking, PairwiseVerifier};
+use crate::provider_config::MarsPhase;
+use crate::retry::RetryingProvider;
+use crate::run_handle::RunHandle;
 use crate::strategy::StrategyNetwork;
-use crate::types::{MarsEvent, MarsOutput, SelectionMethod};
-use crate::verifier::Verifier;
-use crate::workspace::Workspace;
+use crate::strategy_store::StrategyStore;
+use crate::templates::{self, PromptVariables};
+use crate::trace::{TraceWriter, TracingProvider};
+use crate::types::{
+    MarsEvent, MarsEventRecord, MarsOutput, PhaseTiming, QueryClassification, RankedAnswer,
+    SelectionMethod, VerificationMode, VerificationResult,
+};
+use crate::verification_cache::VerificationCache;
+use crate::verifier::{SolutionVerifier, Verifier};
+use crate::webhook::RunSummary;
+use crate::workspace::{SnapshotId, Workspace};
+use crate::workspace_store::{SolutionQuery, WorkspaceStore};
 use crate::LLMProvider;
 use chrono::Utc;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use tokio::sync::mpsc;
-use uuid::Uuid;
+use tracing::Instrument;
+
+/// Per-run overrides for [`MarsCoordinator::run_with_options`]
+#[derive(Clone, Debug, Default)]
+pub struct RunOptions {
+    /// Caller's output token limit; small limits switch to lightweight mode
+    /// when `config.auto_lightweight_mode` is set and query classification
+    /// is off
+    pub max_tokens: Option<usize>,
+    /// Force lightweight mode on or off, overriding the query class and the
+    /// `max_tokens` rule
+    pub lightweight: Option<bool>,
+    /// Final-answer selection to try before the usual order;
+    /// [`SelectionMethod::Synthesized`] skips straight to synthesis
+    pub selection: Option<SelectionMethod>,
+    /// Labels recorded on the run's tracing span and copied to the output
+    pub tags: Vec<String>,
+    /// Values for prompt template variables, overriding
+    /// `config.prompt_variables`
+    pub variables: PromptVariables,
+}
+
+impl RunOptions {
+    /// Options that change nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the caller's output token limit
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Force lightweight mode on or off
+    pub fn with_lightweight(mut self, lightweight: bool) -> Self {
+        self.lightweight = Some(lightweight);
+        self
+    }
+
+    /// Prefer a final-answer selection method
+    pub fn with_selection(mut self, selection: SelectionMethod) -> Self {
+        self.selection = Some(selection);
+        self
+    }
+
+    /// Add a tag to the run
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Set a prompt template variable for the run
+    ///
+    /// The variable must be declared in `config.prompt_variables`.
+    pub fn with_variable(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
+}
 
 /// Coordinator for MARS execution
 pub struct MarsCoordinator {
     config: MarsConfig,
     workspace: Workspace,
     strategy_network: StrategyNetwork,
-    client: code_core::ModelClient,
+    /// Where strategies are persisted between runs, if configured
+    strategy_store: Option<StrategyStore>,
+    provider: Arc<dyn LLMProvider>,
+    /// Providers pinned to individual phases, overriding `provider`
+    phase_providers: HashMap<MarsPhase, Arc<dyn LLMProvider>>,
+    /// Proposer providers for individual MOA layers, by layer index
+    moa_layer_providers: HashMap<usize, Arc<dyn LLMProvider>>,
+    /// IDs of solutions that already have an improved successor
+    improved_solution_ids: HashSet<String>,
+    /// Verification results reused for solutions with identical content
+    verification_cache: Option<VerificationCache>,
+    /// User-registered verifiers run alongside (or instead of) the built-in one
+    solution_verifiers: Vec<Arc<dyn SolutionVerifier>>,
+    /// Custom scorer for MCTS simulations (LLM rating when unset)
+    mcts_value_function: Option<Arc<dyn ValueFunction>>,
+    /// Custom MCTS action space (free-form replies when unset)
+    mcts_action_generator: Option<Arc<dyn ActionGenerator>>,
+    /// Search tree from the last MCTS aggregation
+    mcts_tree: Option<MCTSTree>,
+    /// Cost of every provider call, checked against the configured budget
+    cost_tracker: Arc<CostTracker>,
+    /// Latency, usage and errors of every provider call
+    call_stats: Arc<CallStats>,
+    /// Problems `config.validate()` found at construction; runs refuse to start
+    config_errors: Vec<ConfigError>,
+    /// Where each run's events are forwarded, stamped with run ID and sequence
+    event_bus: Arc<EventBus>,
+    /// Phases of the current run measured so far
+    phase_timings: Vec<PhaseTiming>,
+    /// Verdicts given in the current run so far
+    verifications: Vec<VerificationResult>,
+    /// Where events and provider calls are traced, if `config.trace_path` is set
+    trace: Option<Arc<TraceWriter>>,
+    /// ID of the current run, recorded on its phase spans
+    run_id: String,
+    /// Where the current run writes its debug output, if `config.debug_dir` is set
+    debug_dir: Option<DebugDir>,
+}
+
+/// Time and call totals at the start of a phase, to measure it against
+struct PhaseClock {
+    phase: MarsPhase,
+    started_at: chrono::DateTime<Utc>,
+    calls: u64,
+    tokens: u64,
+    cost_usd: f64,
+    /// `mars_phase` span the phase runs in
+    span: tracing::Span,
 }
 
 impl MarsCoordinator {
     /// Create a new coordinator with configuration and ModelClient
     pub fn new(config: MarsConfig, client: code_core::ModelClient) -> Self {
+        Self::with_provider(config, Arc::new(ModelClientRouter::new(client)))
+    }
+
+    /// Create a new coordinator backed by any LLM provider
+    ///
+    /// Phases pinned in `config.provider_routing` get their own provider,
+    /// retrying transient failures up to the routing config's `max_retries`;
+    /// every other phase uses `provider`.
+    ///
+    /// The config is validated here; if it is invalid, the problems are
+    /// logged and [`MarsCoordinator::run`] fails with them.
+    pub fn with_provider(config: MarsConfig, provider: Arc<dyn LLMProvider>) -> Self {
+        let mut config_errors = config.validate().err().unwrap_or_default();
+        // Runs fail with the error rather than silently losing the workspace
+        let workspace = Workspace::open(&config.workspace_backend).unwrap_or_else(|e| {
+            config_errors.push(ConfigError {
+                field: "workspace_backend",
+                message: format!("could not be opened: {e}"),
+            });
+            Workspace::new()
+        });
+        for error in &config_errors {
+            tracing::error!("Invalid MARS config: {error}");
+        }
+
+        let mut phase_providers: HashMap<MarsPhase, Arc<dyn LLMProvider>> = HashMap::new();
+        if let Some(routing) = &config.provider_routing {
+            for (phase, spec) in &routing.phase_providers {
+                if spec.enabled {
+                    let spec = routing.with_default_timeout(spec);
+                    let provider = RetryingProvider::new(provider_from_spec(&spec))
+                        .with_max_retries(routing.max_retries);
+                    phase_providers.insert(*phase, Arc::new(provider));
+                }
+            }
+        }
+
+        let mut moa_layer_providers: HashMap<usize, Arc<dyn LLMProvider>> = HashMap::new();
+        for (layer, spec) in config.moa_layer_models.iter().enumerate() {
+            if spec.enabled {
+                moa_layer_providers.insert(layer, provider_from_spec(spec));
+            }
+        }
+
+        let verification_cache =
+            config
+                .enable_verification_cache
+                .then(|| match &config.verification_cache_path {
+                    Some(path) => VerificationCache::open(path).unwrap_or_else(|e| {
+                        tracing::warn!("{e}; starting with an empty verification cache");
+                        VerificationCache::new()
+                    }),
+                    None => VerificationCache::new(),
+                });
+
+        let strategy_store = config.strategy_store_path.as_ref().map(StrategyStore::new);
+        let strategy_network = match &strategy_store {
+            Some(store) => match store.load(&config.strategy_namespace) {
+                Ok(strategies) => StrategyNetwork::from_strategies(strategies),
+                Err(e) => {
+                    tracing::warn!("{e}; starting with no stored strategies");
+                    StrategyNetwork::new()
+                }
+            },
+            None => StrategyNetwork::new(),
+        }
+        .with_dedup_threshold(config.strategy_dedup_threshold);
+
+        let workspace = Self::configure_workspace(workspace, &config);
+        let cost_tracker = Arc::new(CostTracker::from_config(&config));
+        let trace = config
+            .trace_path
+            .as_ref()
+            .and_then(|path| match TraceWriter::open(path) {
+                Ok(trace) => Some(Arc::new(trace.with_redaction(config.redact_trace))),
+                Err(e) => {
+                    tracing::warn!("{e}; running without a trace");
+                    None
+                }
+            });
+
         Self {
             config,
-            workspace: Workspace::new(),
-            strategy_network: StrategyNetwork::new(),
-            client,
+            workspace,
+            strategy_network,
+            strategy_store,
+            provider,
+            phase_providers,
+            moa_layer_providers,
+            improved_solution_ids: HashSet::new(),
+            verification_cache,
+            solution_verifiers: Vec::new(),
+            mcts_value_function: None,
+            mcts_action_generator: None,
+            mcts_tree: None,
+            cost_tracker,
+            call_stats: Arc::new(CallStats::default()),
+            config_errors,
+            event_bus: Arc::new(EventBus::new()),
+            phase_timings: Vec::new(),
+            verifications: Vec::new(),
+            trace,
+            run_id: String::new(),
+            debug_dir: None,
+        }
+    }
+
+    /// Pin a phase to a specific provider (e.g. verify with a stronger model)
+    pub fn with_phase_provider(mut self, phase: MarsPhase, provider: Arc<dyn LLMProvider>) -> Self {
+        self.phase_providers.insert(phase, provider);
+        self
+    }
+
+    /// Use a specific proposer provider for one MOA layer (0-based)
+    pub fn with_moa_layer_provider(mut self, layer: usize, provider: Arc<dyn LLMProvider>) -> Self {
+        self.moa_layer_providers.insert(layer, provider);
+        self
+    }
+
+    /// Register a custom verifier (linter, theorem prover, schema validator, ...)
+    ///
+    /// Each registered verifier adds one verification pass per solution. With
+    /// `replace_builtin_verifier` set, they replace the built-in LLM verifier.
+    pub fn with_solution_verifier(mut self, verifier: Arc<dyn SolutionVerifier>) -> Self {
+        self.solution_verifiers.push(verifier);
+        self
+    }
+
+    /// Forward every run's events to `sink` as [`MarsEventRecord`]s
+    ///
+    /// Records arrive in emission order and all of a run's records are sent
+    /// before the run returns. A closed sink stops forwarding without failing
+    /// the run. Sinks add up: each one given receives every record.
+    pub fn with_event_sink(self, sink: mpsc::Sender<MarsEventRecord>) -> Self {
+        self.event_bus.add_sink(sink);
+        self
+    }
+
+    /// Receive every run's events from now on, alongside any other
+    /// subscribers, buffering up to `capacity`
+    pub fn subscribe_events(&self, capacity: usize) -> mpsc::Receiver<MarsEventRecord> {
+        self.event_bus.subscribe(capacity)
+    }
+
+    /// Bus the run events are published on, to subscribe from elsewhere
+    /// while a run is in progress
+    pub fn event_bus(&self) -> Arc<EventBus> {
+        Arc::clone(&self.event_bus)
+    }
+
+    /// Keep the workspace in a custom store instead of the configured backend
+    pub fn with_workspace_store(mut self, store: Arc<dyn WorkspaceStore>) -> Self {
+        self.workspace = Self::configure_workspace(Workspace::with_store(store), &self.config);
+        self
+    }
+
+    /// Apply the configured dedup and size limit to a workspace
+    fn configure_workspace(workspace: Workspace, config: &MarsConfig) -> Workspace {
+        let mut workspace = workspace
+            .with_dedup(config.solution_dedup)
+            .with_consensus_threshold(config.consensus_threshold);
+        if config.enable_workspace_log {
+            workspace = workspace.with_change_log();
         }
+        match config.max_workspace_solutions {
+            Some(max) => workspace.with_max_solutions(max, config.workspace_eviction),
+            None => workspace,
+        }
+    }
+
+    /// Score MCTS simulations with a custom value function (reward model,
+    /// execution-based scoring, heuristics, ...)
+    pub fn with_mcts_value_function(mut self, value_function: Arc<dyn ValueFunction>) -> Self {
+        self.mcts_value_function = Some(value_function);
+        self
+    }
+
+    /// Search MCTS over a custom action space, e.g.
+    /// [`crate::code_actions::CodeEditActions`] for typed code edits
+    pub fn with_mcts_action_generator(
+        mut self,
+        action_generator: Arc<dyn ActionGenerator>,
+    ) -> Self {
+        self.mcts_action_generator = Some(action_generator);
+        self
+    }
+
+    /// Strategies discovered so far (including any loaded from the store)
+    ///
+    /// Export how they flowed between agents with
+    /// [`StrategyNetwork::export_graph`].
+    pub fn strategy_network(&self) -> &StrategyNetwork {
+        &self.strategy_network
+    }
+
+    /// Shared workspace holding every solution of the run
+    ///
+    /// Use [`Workspace::get_derivation_tree`] to trace how an answer was derived.
+    pub fn workspace(&self) -> &Workspace {
+        &self.workspace
+    }
+
+    /// Search tree from the last MCTS aggregation, if one ran
+    ///
+    /// Export it with [`MCTSTree::to_json`] or [`MCTSTree::to_dot`].
+    pub fn mcts_tree(&self) -> Option<&MCTSTree> {
+        self.mcts_tree.as_ref()
+    }
+
+    /// Dollars spent on provider calls in the current run, or in the last one
+    /// once it has finished
+    pub fn cost_summary(&self) -> CostSummary {
+        self.cost_tracker.summary()
+    }
+
+    /// Latency, token usage and error counts of provider calls so far
+    pub fn call_stats(&self) -> &CallStats {
+        &self.call_stats
+    }
+
+    /// Problems with the config found at construction (empty if it is valid)
+    pub fn config_errors(&self) -> &[ConfigError] {
+        &self.config_errors
     }
 
     /// Get a provider for LLM operations
     ///
-    /// Returns a ModelClientRouter wrapping the configured ModelClient.
-    /// In the future, this can support multi-provider routing based on config.
-    fn get_provider(&self) -> Box<dyn LLMProvider> {
-        Box::new(ModelClientRouter::new(self.client.clone()))
+    /// Returns the provider the coordinator was constructed with.
+    fn get_provider(&self) -> Arc<dyn LLMProvider> {
+        Arc::clone(&self.provider)
+    }
+
+    /// All distinct providers that may verify solutions, preferred verifier first
+    fn verifier_pool(&self) -> Vec<Arc<dyn LLMProvider>> {
+        let mut pool = vec![self.provider_for(MarsPhase::Verification)];
+        let candidates = std::iter::once(self.get_provider())
+            .chain(self.phase_providers.values().cloned())
+            .map(|provider| self.tracked(MarsPhase::Verification, provider));
+        for provider in candidates {
+            if !pool.iter().any(|p| p.model_id() == provider.model_id()) {
+                pool.push(provider);
+            }
+        }
+        pool
+    }
+
+    /// Get the provider for a phase, falling back to the default provider
+    ///
+    /// Calls through it are costed to the phase.
+    fn provider_for(&self, phase: MarsPhase) -> Arc<dyn LLMProvider> {
+        let provider = self
+            .phase_providers
+            .get(&phase)
+            .map(Arc::clone)
+            .unwrap_or_else(|| self.get_provider());
+        self.tracked(phase, provider)
+    }
+
+    /// Proposer provider for an MOA layer, falling back to the aggregation provider
+    fn moa_layer_provider(&self, layer: usize) -> Arc<dyn LLMProvider> {
+        match self.moa_layer_providers.get(&layer) {
+            Some(provider) => self.tracked(MarsPhase::Aggregation, Arc::clone(provider)),
+            None => self.provider_for(MarsPhase::Aggregation),
+        }
+    }
+
+    /// Record the cost of calls through `provider` against `phase`, and
+    /// trace them (with full prompts and responses when `config.debug` is set,
+    /// and in the JSON Lines trace when `config.trace_path` is)
+    fn tracked(&self, phase: MarsPhase, provider: Arc<dyn LLMProvider>) -> Arc<dyn LLMProvider> {
+        let observed: Arc<dyn LLMProvider> = Arc::new(
+            ObservedProvider::new(provider)
+                .with_stats(Arc::clone(&self.call_stats))
+                .with_phase(phase)
+                .with_capture(self.config.debug)
+                .with_cost_tracker(Arc::clone(&self.cost_tracker)),
+        );
+        let traced: Arc<dyn LLMProvider> = match &self.trace {
+            Some(trace) => {
+                Arc::new(TracingProvider::new(observed, Arc::clone(trace)).with_phase(phase))
+            }
+            None => observed,
+        };
+        Arc::new(CostTrackingProvider::new(
+            traced,
+            Arc::clone(&self.cost_tracker),
+            phase,
+        ))
     }
 
     /// Run the complete MARS process for a given query
     ///
-    /// Returns a stream of events and the final output
+    /// Returns a stream of events and the final output. When the next phase
+    /// is projected to overrun the configured cost budget, or a call hits it,
+    /// the run skips to synthesis and marks the output `budget_truncated`.
+    /// An invalid config fails the run with
+    /// [`crate::MarsError::InvalidConfiguration`] before any provider call.
     pub async fn run(&mut self, query: &str) -> Result<MarsOutput> {
-        let (tx, _rx) = mpsc::channel::<MarsEvent>(100);
+        self.run_with_options(query, RunOptions::default()).await
+    }
+
+    /// Run the complete MARS process with per-run overrides
+    ///
+    /// The overrides apply to this run only; the coordinator's config and
+    /// provider clients are unchanged afterwards. With
+    /// `config.query_classification` on, the query's class picks the run's
+    /// preset and lightweight mode instead of `max_tokens`. With
+    /// `config.webhook` set, every run's summary is posted to it, including
+    /// runs that fail before any provider call.
+    pub async fn run_with_options(
+        &mut self,
+        query: &str,
+        options: RunOptions,
+    ) -> Result<MarsOutput> {
+        let run_id = uuid::Uuid::new_v4().to_string();
+        self.run_id.clone_from(&run_id);
+        // The budget and the reported cost cover this run only
+        self.cost_tracker = Arc::new(CostTracker::from_config(&self.config));
+        let started = std::time::Instant::now();
+        let result = self.run_unnotified(query, run_id, &options).await;
+        if let Some(webhook) = &self.config.webhook {
+            let cost_usd = self.cost_tracker.total_usd();
+            let summary = match &result {
+                Ok(output) => RunSummary::completed(output, cost_usd, started.elapsed()),
+                Err(e) => {
+                    RunSummary::failed(&self.run_id, &options.tags, e, cost_usd, started.elapsed())
+                }
+            };
+            webhook.notify(summary);
+        }
+        result
+    }
+
+    /// The run itself, without the webhook notification
+    async fn run_unnotified(
+        &mut self,
+        query: &str,
+        run_id: String,
+        options: &RunOptions,
+    ) -> Result<MarsOutput> {
+        if !self.config_errors.is_empty() {
+            let errors: Vec<String> = self.config_errors.iter().map(ToString::to_string).collect();
+            return Err(crate::MarsError::InvalidConfiguration(errors.join("; ")));
+        }
+        // Templates fail before the run is traced or gets a debug directory
+        let templated = self.config.has_prompt_templates() || !options.variables.is_empty();
+        let mut run_config = if templated {
+            Some(templates::render_prompts(
+                self.config.clone(),
+                &options.variables,
+            )?)
+        } else {
+            None
+        };
+        // Each run votes on and improves only its own solutions
+        self.workspace.clear().await;
+        self.improved_solution_ids.clear();
+
+        if let Some(trace) = &self.trace {
+            trace.start_run(&run_id);
+        }
+        self.debug_dir = None;
+        if self.config.debug
+            && let Some(root) = &self.config.debug_dir
+        {
+            match DebugDir::create(root, &run_id) {
+                Ok(dir) => self.debug_dir = Some(dir),
+                Err(e) => tracing::warn!("{e}; logging debug output only"),
+            }
+        }
+        let class = self.classify_query(query).await;
+        let lightweight = options.lightweight.unwrap_or_else(|| match class {
+            Some(class) => class.is_lightweight(),
+            None => self.config.should_use_lightweight(options.max_tokens),
+        });
+        if let Some(preset) = class.and_then(QueryClass::preset) {
+            let config = run_config.unwrap_or_else(|| self.config.clone());
+            run_config = Some(config.with_preset(preset));
+        }
+        if lightweight {
+            let config = run_config.unwrap_or_else(|| self.config.clone());
+            run_config = Some(config.lightweight());
+        }
+        if self.config.response_language.is_some() {
+            let config = run_config.unwrap_or_else(|| self.config.clone());
+            run_config = Some(config.localized());
+        }
+        if self.config.answer_type.format_instruction().is_some() {
+            let config = run_config.unwrap_or_else(|| self.config.clone());
+            run_config = Some(config.formatted());
+        }
+        let base_config = run_config.map(|config| std::mem::replace(&mut self.config, config));
+        let span = tracing::info_span!(
+            "mars_run",
+            run_id = %run_id,
+            tags = ?options.tags,
+            class = ?class,
+            lightweight,
+            total_tokens = tracing::field::Empty,
+            cost_usd = tracing::field::Empty,
+        );
+        let result = self
+            .run_phases(query, &run_id, options.selection)
+            .instrument(span.clone())
+            .await;
+        if let Ok(output) = &result {
+            span.record("total_tokens", output.total_tokens as i64);
+            span.record("cost_usd", output.cost.total_usd);
+        }
+        if let Some(config) = base_config {
+            self.config = config;
+        }
+
+        result.map(|mut output| {
+            output.tags = options.tags.clone();
+            output.run_id = run_id;
+            output
+        })
+    }
+
+    /// Run `query` in the background, consuming the coordinator
+    ///
+    /// The returned [`RunHandle`] streams the run's events as they happen
+    /// and can cancel it.
+    pub fn start(self, query: impl Into<String>, options: RunOptions) -> RunHandle {
+        RunHandle::spawn(self, query.into(), options)
+    }
+
+    /// Label `query` as configured by `config.query_classification`
+    ///
+    /// A failed or unparsable provider classification falls back to the
+    /// heuristics.
+    async fn classify_query(&self, query: &str) -> Option<QueryClass> {
+        let class = match self.config.query_classification {
+            QueryClassification::Off => return None,
+            QueryClassification::Heuristic => classifier::classify(query),
+            QueryClassification::Model => {
+                let provider = self.provider_for(MarsPhase::Exploration);
+                match classifier::classify_with_provider(query, provider.as_ref()).await {
+                    Ok(class) => class,
+                    Err(e) => {
+                        tracing::warn!("Query classification failed, using heuristics: {e}");
+                        classifier::classify(query)
+                    }
+                }
+            }
+        };
+        tracing::info!("Classified query as {class:?}");
+        Some(class)
+    }
+
+    /// Phases 1 to 5 of a run
+    async fn run_phases(
+        &mut self,
+        query: &str,
+        run_id: &str,
+        selection: Option<SelectionMethod>,
+    ) -> Result<MarsOutput> {
+        let (tx, rx) = mpsc::channel::<MarsEvent>(100);
+        let forwarder = tokio::spawn(
+            Self::forward_events(
+                rx,
+                Arc::clone(&self.event_bus),
+                self.trace.clone(),
+                run_id.to_string(),
+            )
+            .instrument(tracing::Span::current()),
+        );
+        let output = self.forwarded_phases(query, selection, &tx).await;
+        // Failed runs too have all their events published before returning
+        drop(tx);
+        let _result = forwarder.await;
+        output
+    }
+
+    /// Phases 1 to 5, sending the run's events to `tx`
+    async fn forwarded_phases(
+        &mut self,
+        query: &str,
+        selection: Option<SelectionMethod>,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Result<MarsOutput> {
+        self.phase_timings.clear();
+        self.verifications.clear();
+
+        // Phases 1-4; a spent or overrun budget skips straight to synthesis
+        let budget_truncated = match self.search_phases(query, tx).await {
+            Ok(completed) => !completed,
+            Err(crate::MarsError::BudgetExceeded { .. }) => true,
+            Err(e) => return Err(e),
+        };
+        if budget_truncated {
+            let summary = self.cost_tracker.summary();
+            tracing::warn!(
+                spent_usd = summary.total_usd,
+                "Cost budget reached; skipping to synthesis"
+            );
+            let _result = tx
+                .send(MarsEvent::BudgetTruncated {
+                    spent_usd: summary.total_usd,
+                })
+                .await;
+        }
+
+        // Phase 5: Final Synthesis
+        let clock = self.start_phase(MarsPhase::Synthesis);
+        let output = self
+            .phase_synthesis(query, selection, tx)
+            .instrument(clock.span.clone())
+            .await;
+        self.finish_phase(clock).await;
+        let mut output = output?;
+        self.report_evictions(tx).await;
+        output.budget_truncated = budget_truncated;
+        output.phase_timings = std::mem::take(&mut self.phase_timings);
+        output.verifications = std::mem::take(&mut self.verifications);
+        match self.config.answer_type.extract(&output.answer) {
+            Ok(typed) => output.typed_answer = Some(typed),
+            Err(e) => {
+                tracing::warn!("Could not extract a typed answer: {e}");
+                let _result = tx
+                    .send(MarsEvent::Error {
+                        message: e.to_string(),
+                    })
+                    .await;
+            }
+        }
+        let _result = tx
+            .send(MarsEvent::Completed {
+                final_answer: output.answer.clone(),
+                method: format!("{:?}", output.selection_method),
+            })
+            .await;
+        Ok(output)
+    }
+
+    /// Log a run's events, then stamp them with the time they are forwarded,
+    /// write them to the trace and publish them on `bus`
+    ///
+    /// Error events are logged as warnings and the rest at debug level.
+    /// Stops once the run ends.
+    async fn forward_events(
+        mut rx: mpsc::Receiver<MarsEvent>,
+        bus: Arc<EventBus>,
+        trace: Option<Arc<TraceWriter>>,
+        run_id: String,
+    ) {
+        let mut sequence = 0;
+        while let Some(event) = rx.recv().await {
+            match &event {
+                MarsEvent::Error { message } => tracing::warn!(sequence, "{message}"),
+                event => tracing::debug!(sequence, ?event, "MARS event"),
+            }
+            let record = MarsEventRecord::new(run_id.clone(), sequence, event);
+            if let Some(trace) = &trace {
+                trace.record_event(record.clone());
+            }
+            bus.publish(record).await;
+            sequence += 1;
+        }
+    }
 
+    /// Phases 1 to 4: exploration, aggregation, strategy network,
+    /// verification and improvement
+    ///
+    /// Returns `false` if a phase was skipped because its projected cost
+    /// would overrun the budget.
+    async fn search_phases(&mut self, query: &str, tx: &mpsc::Sender<MarsEvent>) -> Result<bool> {
         // Phase 1: Multi-Agent Exploration
-        self.phase_exploration(query, &tx).await?;
+        if !self.fits_budget(MarsPhase::Exploration) {
+            return Ok(false);
+        }
+        let clock = self.start_phase(MarsPhase::Exploration);
+        let explored = self
+            .phase_exploration(query, tx)
+            .instrument(clock.span.clone())
+            .await;
+        self.finish_phase(clock).await;
+        explored?;
+        self.report_evictions(tx).await;
 
         // Phase 2: Aggregation and Strategy Network (optional)
         if self.config.enable_aggregation {
-            self.phase_aggregation(query, &tx).await?;
+            if !self.fits_budget(MarsPhase::Aggregation) {
+                return Ok(false);
+            }
+            let clock = self.start_phase(MarsPhase::Aggregation);
+            let aggregated = self
+                .phase_aggregation(query, tx)
+                .instrument(clock.span.clone())
+                .await;
+            self.finish_phase(clock).await;
+            aggregated?;
+            self.report_evictions(tx).await;
         }
 
         if self.config.enable_strategy_network {
-            self.phase_strategy_network(&tx).await?;
+            if !self.fits_budget(MarsPhase::StrategyExtraction) {
+                return Ok(false);
+            }
+            let clock = self.start_phase(MarsPhase::StrategyExtraction);
+            let extracted = self
+                .phase_strategy_network(tx)
+                .instrument(clock.span.clone())
+                .await;
+            self.finish_phase(clock).await;
+            extracted?;
         }
 
         // Phase 3: Verification
-        self.phase_verification(&tx).await?;
+        if !self.fits_budget(MarsPhase::Verification) {
+            return Ok(false);
+        }
+        let clock = self.start_phase(MarsPhase::Verification);
+        let verified = self
+            .phase_verification(query, tx)
+            .instrument(clock.span.clone())
+            .await;
+        self.finish_phase(clock).await;
+        verified?;
 
         // Phase 4: Iterative Improvement
         for iteration in 0..self.config.max_iterations {
-            let any_improved = self.phase_improvement(iteration, &tx).await?;
-            if !any_improved {
+            if !self.fits_budget(MarsPhase::Improvement) {
+                return Ok(false);
+            }
+            let clock = self.start_phase(MarsPhase::Improvement);
+            let improved = self
+                .improvement_iteration(query, iteration, tx)
+                .instrument(clock.span.clone())
+                .await;
+            self.finish_phase(clock).await;
+            let (any_improved, rolled_back) = improved?;
+            self.report_evictions(tx).await;
+            if !any_improved || rolled_back {
                 break; // No improvements made, early exit
             }
         }
 
-        // Phase 5: Final Synthesis
-        let output = self.phase_synthesis(&tx).await?;
+        Ok(true)
+    }
 
-        Ok(output)
+    /// One improvement iteration, rolled back if it did not help and
+    /// `config.rollback_failed_improvements` is set
+    ///
+    /// Returns whether any solution improved and whether the improvements
+    /// were rolled back.
+    async fn improvement_iteration(
+        &mut self,
+        query: &str,
+        iteration: usize,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Result<(bool, bool)> {
+        let snapshot = if self.config.rollback_failed_improvements {
+            Some(self.workspace.snapshot().await?)
+        } else {
+            None
+        };
+        let any_improved = self.phase_improvement(query, iteration, tx).await?;
+
+        let mut rolled_back = false;
+        if let Some(snapshot) = snapshot {
+            if any_improved {
+                rolled_back = self
+                    .rollback_unhelpful_improvements(query, iteration, snapshot, tx)
+                    .await?;
+            }
+            self.workspace.discard_snapshot(snapshot).await;
+        }
+        Ok((any_improved, rolled_back))
+    }
+
+    /// Start measuring `phase`, and open the span it runs in
+    fn start_phase(&self, phase: MarsPhase) -> PhaseClock {
+        PhaseClock {
+            phase,
+            started_at: Utc::now(),
+            calls: self.call_stats.calls(),
+            tokens: self.call_stats.prompt_tokens() + self.call_stats.completion_tokens(),
+            cost_usd: self.cost_tracker.total_usd(),
+            span: tracing::info_span!(
+                "mars_phase",
+                run_id = %self.run_id,
+                phase = ?phase,
+                llm_calls = tracing::field::Empty,
+                tokens = tracing::field::Empty,
+                cost_usd = tracing::field::Empty,
+            ),
+        }
+    }
+
+    /// Record the time, calls and tokens of the phase `clock` was started for,
+    /// and dump the workspace in debug mode
+    ///
+    /// Phases that fail are recorded too, up to the point they failed.
+    async fn finish_phase(&mut self, clock: PhaseClock) {
+        let tokens = self.call_stats.prompt_tokens() + self.call_stats.completion_tokens();
+        let timing = PhaseTiming {
+            phase: clock.phase,
+            started_at: clock.started_at,
+            ended_at: Utc::now(),
+            llm_calls: self.call_stats.calls() - clock.calls,
+            tokens: tokens - clock.tokens,
+        };
+        // Signed, as OpenTelemetry exports unsigned values as strings
+        clock.span.record("llm_calls", timing.llm_calls as i64);
+        clock.span.record("tokens", timing.tokens as i64);
+        clock
+            .span
+            .record("cost_usd", self.cost_tracker.total_usd() - clock.cost_usd);
+        clock.span.in_scope(|| {
+            tracing::debug!(
+                duration_ms = timing.duration().as_millis(),
+                llm_calls = timing.llm_calls,
+                tokens = timing.tokens,
+                "Phase finished"
+            );
+        });
+        self.phase_timings.push(timing);
+        if self.config.debug {
+            self.dump_workspace(clock.phase)
+                .instrument(clock.span)
+                .await;
+        }
+    }
+
+    /// Log the workspace after `phase` at debug level, and write its export
+    /// to the debug directory
+    async fn dump_workspace(&mut self, phase: MarsPhase) {
+        for solution in self.workspace.get_all_solutions().await {
+            tracing::debug!(
+                solution_id = %solution.id,
+                agent_id = %solution.agent_id,
+                verification_score = solution.verification_score,
+                is_verified = solution.is_verified,
+                answer = %redact_secrets(&solution.answer),
+                "Workspace solution"
+            );
+        }
+        if let Some(dir) = &mut self.debug_dir {
+            let written = match self.workspace.export_json().await {
+                Ok(json) => dir.write_workspace(phase, &json),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = written {
+                tracing::warn!("{e}; skipping the workspace dump");
+            }
+        }
+    }
+
+    /// Whether `phase` is projected to fit in what is left of the budget,
+    /// at the average cost of the calls so far
+    fn fits_budget(&self, phase: MarsPhase) -> bool {
+        let projected_usd =
+            self.cost_tracker.average_call_usd() * self.projected_calls(phase) as f64;
+        self.cost_tracker.can_afford(projected_usd)
+    }
+
+    /// Rough number of provider calls `phase` makes with the current config
+    fn projected_calls(&self, phase: MarsPhase) -> usize {
+        use crate::types::AggregationMethod;
+
+        let config = &self.config;
+        match phase {
+            MarsPhase::Exploration | MarsPhase::StrategyExtraction => config.num_agents,
+            MarsPhase::Aggregation => match config.aggregation_method {
+                AggregationMethod::RSA => {
+                    config.aggregation_loops * config.aggregation_population_size
+                }
+                AggregationMethod::MixtureOfAgents => {
+                    config.moa_num_completions * config.moa_num_layers + 1
+                }
+                AggregationMethod::MonteCarloTreeSearch => {
+                    config.mcts_num_simulations
+                        * (config.mcts_num_actions + config.mcts_simulation_depth)
+                }
+                AggregationMethod::GeneticAlgorithm => {
+                    config.ga_population_size * config.ga_generations
+                }
+                AggregationMethod::BeamSearch => config.beam_width * config.beam_depth,
+                AggregationMethod::BestOfN => config.best_of_n_samples,
+                AggregationMethod::PlanAndSolve => 2,
+                AggregationMethod::MajorityVoting | AggregationMethod::MixtureOfExperts => {
+                    config.aggregation_population_size
+                }
+            },
+            MarsPhase::Verification => config.num_agents * config.num_verifiers,
+            MarsPhase::Improvement if config.rollback_failed_improvements => {
+                config.num_agents * (1 + config.num_verifiers)
+            }
+            MarsPhase::Improvement => config.num_agents,
+            MarsPhase::Synthesis => 1,
+        }
+    }
+
+    /// Verify the solutions added since `snapshot` and roll back to it unless
+    /// one of them is verified or scores above all of its parents
+    ///
+    /// Returns whether the iteration was rolled back.
+    async fn rollback_unhelpful_improvements(
+        &mut self,
+        query: &str,
+        iteration: usize,
+        snapshot: SnapshotId,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Result<bool> {
+        let improved = self.workspace.diff_since(snapshot).await?.added;
+        if improved.is_empty() {
+            return Ok(false); // Every improvement merged into an existing solution
+        }
+        let solution_ids: Vec<String> = improved.iter().map(|s| s.id.clone()).collect();
+        self.verify_solutions(query, improved, tx).await?;
+
+        for id in &solution_ids {
+            let Some(solution) = self.workspace.get_solution(id).await else {
+                continue;
+            };
+            let best_parent = self
+                .workspace
+                .get_parents(id)
+                .await
+                .iter()
+                .map(|parent| parent.verification_score)
+                .fold(f32::NEG_INFINITY, f32::max);
+            if solution.is_verified || solution.verification_score > best_parent {
+                return Ok(false);
+            }
+        }
+
+        self.workspace.rollback(snapshot).await?;
+        let _result = tx
+            .send(MarsEvent::ImprovementRolledBack {
+                iteration,
+                solution_ids,
+            })
+            .await;
+        Ok(true)
+    }
+
+    /// Emit an event for solutions evicted to respect the workspace size limit
+    async fn report_evictions(&self, tx: &mpsc::Sender<MarsEvent>) {
+        let solution_ids = self.workspace.take_evicted().await;
+        if !solution_ids.is_empty() {
+            let _result = tx.send(MarsEvent::SolutionsEvicted { solution_ids }).await;
+        }
     }
 
     /// Phase 1: Multi-Agent Exploration
@@ -94,30 +1018,33 @@ impl MarsCoordinator {
             .await;
 
         // Create agents with diverse temperatures
+        let provider = self.provider_for(MarsPhase::Exploration);
+        let strategies = if self.config.strategies_in_exploration {
+            self.relevant_strategies(query, provider.as_ref()).await
+        } else {
+            Vec::new()
+        };
+        let temperatures = self.config.temperatures[..self.config.num_agents].to_vec();
         let mut agents = Vec::new();
-        for temp in &self.config.temperatures[..self.config.num_agents] {
-            agents.push(Agent::new(*temp));
+        for temp in temperatures {
+            let agent = Agent::new(temp).with_prompts(self.config.prompts.clone());
+            agents.push(self.share_strategies(agent, &strategies));
         }
 
-        // Generate solutions using ModelClient
+        // Generate solutions using the provider
         for agent in agents {
             match agent
-                .generate_solution_with_client(
-                    query,
-                    self.config.use_thinking_tags,
-                    &self.client,
-                )
+                .generate_solution(query, self.config.use_thinking_tags, provider.as_ref())
                 .await
             {
                 Ok(solution) => {
-                    let _result = tx
-                        .send(MarsEvent::SolutionGenerated {
-                            solution_id: solution.id.clone(),
-                            agent_id: solution.agent_id.clone(),
-                        })
-                        .await;
-
+                    // Stored first, so subscribers can read the solution
+                    let event = MarsEvent::SolutionGenerated {
+                        solution_id: solution.id.clone(),
+                        agent_id: solution.agent_id.clone(),
+                    };
                     self.workspace.add_solution(solution).await;
+                    let _result = tx.send(event).await;
                 }
                 Err(e) => {
                     // Log error but continue with other agents
@@ -143,95 +1070,120 @@ impl MarsCoordinator {
     ) -> Result<()> {
         let _result = tx.send(MarsEvent::AggregationStarted).await;
 
-        match self.config.aggregation_method {
+        // A spent budget stays recognizable, so the run skips to synthesis
+        let failed = |method: &'static str| {
+            move |e: crate::MarsError| match e {
+                crate::MarsError::BudgetExceeded { .. } => e,
+                e => {
+                    crate::MarsError::AggregationError(format!("{method} aggregation failed: {e}"))
+                }
+            }
+        };
+        let provider = self.provider_for(MarsPhase::Aggregation);
+
+        let aggregated = match self.config.aggregation_method {
             crate::types::AggregationMethod::MixtureOfAgents => {
-                // MOA aggregation using provider
-                let provider = self.get_provider();
-                let system_prompt = crate::prompts::MARS_SYSTEM_PROMPT;
+                // MOA aggregation using provider, one proposer provider per layer
+                let system_prompt = self.config.aggregation_prompts.system_prompt();
+                let layers: Vec<Arc<dyn LLMProvider>> = (0..self.config.moa_num_layers)
+                    .map(|layer| self.moa_layer_provider(layer))
+                    .collect();
+                let layer_refs: Vec<&dyn LLMProvider> = layers.iter().map(Arc::as_ref).collect();
 
-                match Aggregator::aggregate_moa(
+                Aggregator::aggregate_layered_moa(
                     query,
                     system_prompt,
                     self.config.moa_num_completions,
                     self.config.moa_fallback_enabled,
+                    &layer_refs,
                     provider.as_ref(),
+                    &self.config.aggregation_prompts,
                 )
                 .await
-                {
-                    Ok(aggregated) => {
-                        for solution in aggregated {
-                            let _result = tx
-                                .send(MarsEvent::SolutionsAggregated {
-                                    result_solution_id: solution.id.clone(),
-                                })
-                                .await;
-
-                            self.workspace.add_solution(solution).await;
-                        }
-                    }
-                    Err(e) => {
-                        return Err(crate::MarsError::AggregationError(format!(
-                            "MOA aggregation failed: {}",
-                            e
-                        )));
-                    }
-                }
+                .map_err(failed("MOA"))?
             }
             crate::types::AggregationMethod::MonteCarloTreeSearch => {
-                // MCTS aggregation
-                let provider = self.get_provider();
-                let system_prompt = crate::prompts::MARS_SYSTEM_PROMPT;
-                let mcts_config = self.config.get_mcts_config();
-
-                match Aggregator::aggregate_mcts(
+                let (aggregated, tree) = Aggregator::aggregate_mcts_with_tree(
                     query,
-                    system_prompt,
-                    mcts_config,
+                    self.config.aggregation_prompts.system_prompt(),
+                    self.config.get_mcts_config(),
+                    self.mcts_value_function.clone(),
+                    self.mcts_action_generator.clone(),
                     provider.as_ref(),
+                    Some(tx),
                 )
                 .await
-                {
-                    Ok(aggregated) => {
-                        for solution in aggregated {
-                            let _result = tx
-                                .send(MarsEvent::SolutionsAggregated {
-                                    result_solution_id: solution.id.clone(),
-                                })
-                                .await;
-
-                            self.workspace.add_solution(solution).await;
-                        }
-                    }
-                    Err(e) => {
-                        return Err(crate::MarsError::AggregationError(format!(
-                            "MCTS aggregation failed: {}",
-                            e
-                        )));
-                    }
-                }
+                .map_err(failed("MCTS"))?;
+                self.mcts_tree = Some(tree);
+                aggregated
             }
-            _ => {
-                // RSA or other aggregation methods
+            crate::types::AggregationMethod::GeneticAlgorithm => {
                 let solutions = self.workspace.get_all_solutions().await;
-
-                let aggregated = Aggregator::aggregate_rsa(
+                Aggregator::aggregate_genetic(
+                    query,
+                    &solutions,
+                    &self.config.get_genetic_config(),
+                    provider.as_ref(),
+                )
+                .await
+                .map_err(failed("Genetic"))?
+            }
+            crate::types::AggregationMethod::BeamSearch => {
+                let solutions = self.workspace.get_all_solutions().await;
+                Aggregator::aggregate_beam_search(
+                    query,
+                    &solutions,
+                    &self.config.get_beam_search_config(),
+                    provider.as_ref(),
+                    tx,
+                )
+                .await
+                .map_err(failed("Beam search"))?
+            }
+            crate::types::AggregationMethod::BestOfN => Aggregator::aggregate_best_of_n(
+                query,
+                &self.config.get_best_of_n_config(),
+                provider.as_ref(),
+            )
+            .await
+            .map_err(failed("Best-of-N"))?,
+            crate::types::AggregationMethod::PlanAndSolve => {
+                let solutions = self.workspace.get_candidate_solutions().await;
+                Aggregator::aggregate_plan_and_solve(
+                    query,
+                    &solutions,
+                    self.config.aggregation_selection_size,
+                    self.config.aggregation_prompts.system_prompt(),
+                    provider.as_ref(),
+                )
+                .await
+                .map_err(failed("Plan-and-solve"))?
+            }
+            _ => {
+                // RSA or other aggregation methods
+                let solutions = self.workspace.get_all_solutions().await;
+                Aggregator::aggregate_rsa(
+                    query,
                     &solutions,
                     self.config.aggregation_population_size,
                     self.config.aggregation_selection_size,
                     self.config.aggregation_loops,
+                    self.config.get_rsa_selection_config(),
+                    &self.config.aggregation_prompts,
+                    provider.as_ref(),
                 )
-                .await?;
-
-                for solution in aggregated {
-                    let _result = tx
-                        .send(MarsEvent::SolutionsAggregated {
-                            result_solution_id: solution.id.clone(),
-                        })
-                        .await;
-
-                    self.workspace.add_solution(solution).await;
-                }
+                .await
+                .map_err(failed("RSA"))?
             }
+        };
+
+        for solution in aggregated {
+            // Stored first, so subscribers can read the solution
+            let event = MarsEvent::SolutionsAggregated {
+                result_solution_id: solution.id.clone(),
+            };
+            self.workspace.add_solution(solution).await;
+            let _result = tx.send(event).await;
         }
 
         Ok(())
@@ -242,13 +1194,15 @@ impl MarsCoordinator {
         let _result = tx.send(MarsEvent::StrategyNetworkStarted).await;
 
         let solutions = self.workspace.get_all_solutions().await;
+        let provider = self.provider_for(MarsPhase::StrategyExtraction);
 
-        // Extract strategies from solutions using ModelClient
+        // Extract strategies from solutions using the provider
         for solution in solutions {
-            let agent = Agent::new(0.3); // Use low temperature for extraction
+            // Use low temperature for extraction
+            let agent = Agent::new(0.3).with_prompts(self.config.prompts.clone());
 
             match agent
-                .extract_strategies_with_client(&solution, &self.client)
+                .extract_strategies(&solution, provider.as_ref())
                 .await
             {
                 Ok(strategies) => {
@@ -274,52 +1228,508 @@ impl MarsCoordinator {
             }
         }
 
+        if let Some(threshold) = self.config.strategy_merge_threshold {
+            match self
+                .strategy_network
+                .merge_similar(threshold, provider.as_ref())
+                .await
+            {
+                Ok(removed) => {
+                    let _result = tx
+                        .send(MarsEvent::StrategiesMerged {
+                            removed,
+                            remaining: self.strategy_network.count_strategies(),
+                        })
+                        .await;
+                }
+                Err(e) => {
+                    let _result = tx
+                        .send(MarsEvent::Error {
+                            message: format!("Failed to merge strategies: {e}"),
+                        })
+                        .await;
+                }
+            }
+        }
+
+        if let Some(store) = &self.strategy_store
+            && let Err(e) = store
+                .save(
+                    &self.config.strategy_namespace,
+                    &self.strategy_network.get_all_strategies(),
+                )
+                .await
+        {
+            let _result = tx
+                .send(MarsEvent::Error {
+                    message: e.to_string(),
+                })
+                .await;
+        }
+
         Ok(())
     }
 
     /// Phase 3: Verification System
     ///
-    /// Cross-agent verification of all solutions
-    async fn phase_verification(&mut self, tx: &mpsc::Sender<MarsEvent>) -> Result<()> {
+    /// Cross-agent verification of all solutions: each solution gets
+    /// `num_verifiers` passes and is verified when the quorum rule is met
+    /// with at least `consensus_threshold` passes
+    async fn phase_verification(
+        &mut self,
+        query: &str,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Result<()> {
         let _result = tx.send(MarsEvent::VerificationStarted).await;
 
-        let solutions = self.workspace.get_all_solutions().await;
+        let solutions = self.workspace.get_candidate_solutions().await;
+        self.verify_solutions(query, solutions, tx).await
+    }
+
+    /// Verify the given solutions with the configured verification mode
+    ///
+    /// Pairwise ratings are relative, so pairwise mode always re-ranks every
+    /// candidate in the workspace.
+    async fn verify_solutions(
+        &mut self,
+        query: &str,
+        solutions: Vec<crate::types::Solution>,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Result<()> {
+        if matches!(
+            self.config.verification_mode,
+            VerificationMode::Execution | VerificationMode::GeneratedTests
+        ) && let Some(problem) = self.config.code_execution_sandbox.problem()
+        {
+            // Skipped rather than failing every solution the same way
+            let _result = tx
+                .send(MarsEvent::Error {
+                    message: format!("Code verification skipped: {problem}"),
+                })
+                .await;
+            return Ok(());
+        }
+        match self.config.verification_mode {
+            VerificationMode::Execution => {
+                let test_cases = self.config.code_test_cases.clone();
+                return self
+                    .phase_execution_verification(test_cases, solutions, tx)
+                    .await;
+            }
+            VerificationMode::GeneratedTests => {
+                return self
+                    .phase_generated_test_verification(query, solutions, tx)
+                    .await;
+            }
+            VerificationMode::Pairwise => return self.phase_pairwise_verification(query, tx).await,
+            VerificationMode::Llm | VerificationMode::Adversarial => {}
+        }
+
+        let pool = self.verifier_pool();
+
+        let num_verifiers = self.config.num_verifiers.max(1);
+        let use_builtin =
+            !self.config.replace_builtin_verifier || self.solution_verifiers.is_empty();
+        let planned_passes =
+            if use_builtin { num_verifiers } else { 0 } + self.solution_verifiers.len();
+        // A threshold above the number of passes could never be met
+        let min_passes = self.config.consensus_threshold.min(planned_passes);
+
+        // Verdicts from different verification modes must not be mixed in the cache
+        let cache_scope = format!("{:?}\n{query}", self.config.verification_mode);
 
         for solution in solutions {
-            // Create agents for verification (can be different from solution agents)
-            let verifier_agents: Vec<_> = (0..2)
-                .map(|_| Agent::new(0.3)) // Use low temperature for verification
-                .collect();
+            let mut results = Vec::new();
 
-            for (_pass_count, verifier) in verifier_agents.iter().enumerate() {
-                match Verifier::verify_solution(&solution, &verifier.id).await {
-                    Ok(verification_result) => {
-                        let mut updated_solution = solution.clone();
+            if use_builtin {
+                match self
+                    .builtin_verification(query, &cache_scope, &solution, &pool, num_verifiers, tx)
+                    .await
+                {
+                    Some(builtin_results) => results.extend(builtin_results),
+                    None if self.solution_verifiers.is_empty() => continue,
+                    None => {}
+                }
+            }
 
-                        if verification_result.is_correct {
-                            updated_solution.add_verification_pass(verification_result.score);
-                        } else {
-                            updated_solution.add_verification_failure();
+            for verifier in &self.solution_verifiers {
+                let span = verification_span(&solution.id, verifier.name());
+                match verifier.verify(&solution, query).instrument(span).await {
+                    Ok(mut verification_result) => {
+                        verification_result.solution_id = solution.id.clone();
+                        if verification_result.verifier_model.is_empty() {
+                            verification_result.verifier_model = verifier.name().to_string();
                         }
-
+                        results.push(verification_result);
+                    }
+                    Err(e) => {
                         let _result = tx
-                            .send(MarsEvent::SolutionVerified {
-                                solution_id: solution.id.clone(),
-                                is_correct: verification_result.is_correct,
-                                score: verification_result.score,
+                            .send(MarsEvent::Error {
+                                message: format!("{} verification failed: {e}", verifier.name()),
                             })
                             .await;
+                    }
+                }
+            }
+
+            for verification_result in &results {
+                let _result = tx
+                    .send(MarsEvent::SolutionVerified {
+                        solution_id: solution.id.clone(),
+                        is_correct: verification_result.is_correct,
+                        score: verification_result.score,
+                    })
+                    .await;
+
+                self.workspace
+                    .add_feedback(
+                        &solution.id,
+                        Self::verdict_feedback(&solution, verification_result),
+                    )
+                    .await;
+            }
 
-                        let _ = self.workspace.update_solution(updated_solution).await;
+            // Applied to the latest stored copy, so concurrent verdicts are not lost
+            let (score_aggregation, quorum) = (
+                self.config.score_aggregation,
+                self.config.verification_quorum,
+            );
+            if let Err(e) = self
+                .workspace
+                .modify_solution(&solution.id, |stored| {
+                    for verification_result in &results {
+                        stored.record_verification(
+                            verification_result.is_correct,
+                            verification_result.score,
+                        );
                     }
-                    Err(e) => {
+                    stored.apply_score_aggregation(score_aggregation);
+                    stored.is_verified = Verifier::meets_quorum(stored, quorum, min_passes);
+                })
+                .await
+            {
+                let _result = tx
+                    .send(MarsEvent::Error {
+                        message: format!(
+                            "Could not record the verification of {}: {e}",
+                            solution.id
+                        ),
+                    })
+                    .await;
+            }
+            self.verifications.extend(results);
+        }
+
+        if let Some(cache) = &self.verification_cache
+            && let Err(e) = cache.persist().await
+        {
+            let _result = tx
+                .send(MarsEvent::Error {
+                    message: e.to_string(),
+                })
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Built-in LLM verification of one solution, replaying cached verdicts when possible
+    ///
+    /// Returns `None` when self-verification is excluded and no independent
+    /// verifier is available.
+    async fn builtin_verification(
+        &self,
+        query: &str,
+        cache_scope: &str,
+        solution: &crate::types::Solution,
+        pool: &[Arc<dyn LLMProvider>],
+        num_verifiers: usize,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Option<Vec<crate::types::VerificationResult>> {
+        let cached = match &self.verification_cache {
+            Some(cache) => cache.get(cache_scope, solution).await,
+            None => None,
+        };
+        // Drop cached verdicts by the solution's own model if self-verification is excluded
+        let cached = cached
+            .map(|results| {
+                results
+                    .into_iter()
+                    .filter(|r| {
+                        !self.config.exclude_self_verification || r.verifier_model != solution.model
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|results| !results.is_empty());
+        if cached.is_some() {
+            return cached;
+        }
+
+        let providers: Vec<_> = if self.config.exclude_self_verification {
+            pool.iter()
+                .filter(|p| solution.model.is_empty() || p.model_id() != solution.model)
+                .cloned()
+                .collect()
+        } else {
+            pool[..1].to_vec()
+        };
+
+        if providers.is_empty() {
+            let _result = tx
+                .send(MarsEvent::Error {
+                    message: format!(
+                        "No verifier independent of {} available for solution {}",
+                        solution.model, solution.id
+                    ),
+                })
+                .await;
+            return None;
+        }
+
+        let results = self
+            .run_verification_passes(query, solution, &providers, num_verifiers, tx)
+            .await;
+        // Only complete sets of passes are worth replaying
+        if let Some(cache) = &self.verification_cache
+            && results.len() == num_verifiers
+        {
+            cache.insert(cache_scope, solution, results.clone()).await;
+        }
+        Some(results)
+    }
+
+    /// Run `num_verifiers` verification passes, rotating through `providers`
+    ///
+    /// Failed passes are reported as error events and left out of the results.
+    async fn run_verification_passes(
+        &self,
+        query: &str,
+        solution: &crate::types::Solution,
+        providers: &[Arc<dyn LLMProvider>],
+        num_verifiers: usize,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Vec<crate::types::VerificationResult> {
+        let mut results = Vec::with_capacity(num_verifiers);
+
+        for pass in 0..num_verifiers {
+            let temperature = self.config.verification_temperature(pass);
+            let verifier = Agent::new(temperature);
+            let provider = &providers[pass % providers.len()];
+            let span = verification_span(&solution.id, &verifier.id);
+
+            let verification = if self.config.verification_mode == VerificationMode::Adversarial {
+                Verifier::verify_adversarially(
+                    query,
+                    solution,
+                    &verifier.id,
+                    temperature,
+                    provider.as_ref(),
+                )
+                .instrument(span)
+                .await
+            } else {
+                Verifier::verify_with_rubric(
+                    query,
+                    solution,
+                    &verifier.id,
+                    temperature,
+                    &self.config.prompts.verification_prompt(query),
+                    provider.as_ref(),
+                )
+                .instrument(span)
+                .await
+            };
+
+            match verification {
+                Ok(verification_result) => results.push(verification_result),
+                Err(e) => {
+                    let _result = tx
+                        .send(MarsEvent::Error {
+                            message: format!("Verification failed: {}", e),
+                        })
+                        .await;
+                }
+            }
+        }
+
+        results
+    }
+
+    /// Phase 3 (execution mode): run extracted code against the given test cases
+    ///
+    /// A single fully passing run marks the solution verified; any failure marks
+    /// it unverified. The pass rate becomes the verification score either way.
+    /// Solutions without runnable code get no verdict.
+    async fn phase_execution_verification(
+        &mut self,
+        test_cases: Vec<crate::code_verifier::TestCase>,
+        solutions: Vec<crate::types::Solution>,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Result<()> {
+        let code_verifier =
+            CodeVerifier::new(test_cases, self.config.code_execution_limits.clone())
+                .with_sandbox(self.config.code_execution_sandbox.clone());
+
+        for solution in solutions {
+            match code_verifier
+                .verify_solution(&solution, "code-verifier")
+                .instrument(verification_span(&solution.id, "code-verifier"))
+                .await
+            {
+                Ok(None) => {
+                    tracing::debug!(
+                        solution_id = %solution.id,
+                        "No runnable code block; left unverified"
+                    );
+                }
+                Ok(Some(verification_result)) => {
+                    let _result = tx
+                        .send(MarsEvent::SolutionVerified {
+                            solution_id: solution.id.clone(),
+                            is_correct: verification_result.is_correct,
+                            score: verification_result.score,
+                        })
+                        .await;
+
+                    if !verification_result.is_correct {
+                        self.workspace
+                            .add_feedback(
+                                &solution.id,
+                                Self::verdict_feedback(&solution, &verification_result),
+                            )
+                            .await;
+                    }
+                    if let Err(e) = self
+                        .workspace
+                        .modify_solution(&solution.id, |stored| {
+                            stored.record_verification(
+                                verification_result.is_correct,
+                                verification_result.score,
+                            );
+                            stored.is_verified = verification_result.is_correct;
+                            stored.verification_score = verification_result.score;
+                        })
+                        .await
+                    {
                         let _result = tx
                             .send(MarsEvent::Error {
-                                message: format!("Verification failed: {}", e),
+                                message: format!(
+                                    "Could not record the verification of {}: {e}",
+                                    solution.id
+                                ),
                             })
                             .await;
                     }
+                    self.verifications.push(verification_result);
+                }
+                Err(e) => {
+                    let _result = tx
+                        .send(MarsEvent::Error {
+                            message: format!("Code verification failed: {e}"),
+                        })
+                        .await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Phase 3 (generated-tests mode): execute solutions against model-written tests
+    ///
+    /// The verification provider writes unit tests from the query alone, so
+    /// they are independent of any candidate; solutions are ranked by pass rate.
+    async fn phase_generated_test_verification(
+        &mut self,
+        query: &str,
+        solutions: Vec<crate::types::Solution>,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Result<()> {
+        let provider = self.provider_for(MarsPhase::Verification);
+        let test_cases = match CodeVerifier::generate_test_cases(
+            query,
+            self.config.num_generated_tests,
+            provider.as_ref(),
+        )
+        .await
+        {
+            Ok(test_cases) if !test_cases.is_empty() => test_cases,
+            Ok(_) => {
+                let _result = tx
+                    .send(MarsEvent::Error {
+                        message: "Test generation produced no test cases".to_string(),
+                    })
+                    .await;
+                return Ok(());
+            }
+            Err(e) => {
+                let _result = tx
+                    .send(MarsEvent::Error {
+                        message: format!("Test generation failed: {e}"),
+                    })
+                    .await;
+                return Ok(());
+            }
+        };
+
+        self.phase_execution_verification(test_cases, solutions, tx)
+            .await
+    }
+
+    /// Phase 3 (pairwise mode): rank solutions by head-to-head comparison
+    ///
+    /// Each solution's Elo rating is stored on it, and its verification score
+    /// becomes its expected score against an average-rated solution.
+    async fn phase_pairwise_verification(
+        &mut self,
+        query: &str,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Result<()> {
+        let solutions = self.workspace.get_candidate_solutions().await;
+        if solutions.len() < 2 {
+            return Ok(());
+        }
+
+        let provider = self.provider_for(MarsPhase::Verification);
+        let ranking =
+            match PairwiseVerifier::rank_solutions(query, &solutions, provider.as_ref()).await {
+                Ok(ranking) => ranking,
+                Err(e) => {
+                    let _result = tx
+                        .send(MarsEvent::Error {
+                            message: format!("Pairwise verification failed: {e}"),
+                        })
+                        .await;
+                    return Ok(());
                 }
+            };
+
+        let mean_rating = ranking.mean_rating();
+        for solution in solutions {
+            let rating = ranking.rating(&solution.id);
+            let score = EloRanking::expected_score(rating, mean_rating);
+
+            let _result = tx
+                .send(MarsEvent::SolutionVerified {
+                    solution_id: solution.id.clone(),
+                    is_correct: rating >= mean_rating,
+                    score,
+                })
+                .await;
+
+            if let Err(e) = self
+                .workspace
+                .modify_solution(&solution.id, |stored| {
+                    stored.elo_rating = Some(rating);
+                    stored.verification_score = score;
+                })
+                .await
+            {
+                let _result = tx
+                    .send(MarsEvent::Error {
+                        message: format!("Could not record the rating of {}: {e}", solution.id),
+                    })
+                    .await;
             }
         }
 
@@ -331,91 +1741,283 @@ impl MarsCoordinator {
     /// Improve unverified solutions based on feedback
     async fn phase_improvement(
         &mut self,
+        query: &str,
         iteration: usize,
         tx: &mpsc::Sender<MarsEvent>,
     ) -> Result<bool> {
         let _result = tx.send(MarsEvent::ImprovementStarted { iteration }).await;
 
-        let solutions = self.workspace.get_all_solutions().await;
+        let solutions = self
+            .workspace
+            .query(&SolutionQuery::new().candidates().verified(false))
+            .await;
+        // Solutions rejected by as many verifiers as it takes to accept one are dropped
         let unverified: Vec<_> = solutions
             .iter()
-            .filter(|s| !s.is_verified && s.verification_failures < 2)
+            .filter(|s| s.verification_failures < self.config.consensus_threshold)
+            .filter(|s| !self.improved_solution_ids.contains(&s.id))
             .collect();
 
         if unverified.is_empty() {
             return Ok(false); // No improvements possible
         }
 
+        let provider = self.provider_for(MarsPhase::Improvement);
+        let strategies = self.relevant_strategies(query, provider.as_ref()).await;
         let mut improvements_made = false;
 
         for solution in unverified {
-            // Placeholder improvement for now
-            // TODO: Integrate with ModelClient for actual improvement
-            let mut improved = solution.clone();
-            improved.id = Uuid::new_v4().to_string();
-            improved.phase = crate::types::GenerationPhase::Improved;
-            improved.answer = format!("Improved: {}", improved.answer);
-
-            let _result = tx
-                .send(MarsEvent::SolutionImproved {
-                    solution_id: improved.id.clone(),
-                })
-                .await;
+            // Improve with the same temperature that produced the solution
+            let agent = Agent::new(solution.temperature).with_prompts(self.config.prompts.clone());
+            let agent = self.share_strategies(agent, &strategies);
+            let verifier_feedback = self.workspace.get_feedback(&solution.id).await;
+            let feedback = Self::improvement_feedback(solution, &verifier_feedback);
 
-            self.workspace.add_solution(improved).await;
-            improvements_made = true;
+            match agent
+                .improve_solution(
+                    solution,
+                    &feedback,
+                    self.config.use_thinking_tags,
+                    provider.as_ref(),
+                )
+                .await
+            {
+                Ok(improved) => {
+                    let event = MarsEvent::SolutionImproved {
+                        solution_id: improved.id.clone(),
+                    };
+                    self.improved_solution_ids.insert(solution.id.clone());
+                    self.workspace.add_solution(improved).await;
+                    let _result = tx.send(event).await;
+                    improvements_made = true;
+                }
+                Err(e) => {
+                    let _result = tx
+                        .send(MarsEvent::Error {
+                            message: format!("Failed to improve solution: {e}"),
+                        })
+                        .await;
+                }
+            }
         }
 
         Ok(improvements_made)
     }
 
-    /// Phase 5: Final Synthesis
-    ///
-    /// Select the best answer using consensus voting, verification score, or synthesis
-    async fn phase_synthesis(&self, tx: &mpsc::Sender<MarsEvent>) -> Result<MarsOutput> {
-        let _result = tx.send(MarsEvent::SynthesisStarted).await;
-
-        let all_solutions = self.workspace.get_all_solutions().await;
-
-        // Try consensus voting
-        if let Some(final_solution) = self.select_by_majority_voting(&all_solutions) {
-            let _result = tx
-                .send(MarsEvent::AnswerSynthesized {
-                    answer: final_solution.answer.clone(),
-                })
-                .await;
+    /// Strategies most relevant to the query to include in agent prompts
+    async fn relevant_strategies(
+        &mut self,
+        query: &str,
+        provider: &dyn LLMProvider,
+    ) -> Vec<crate::types::Strategy> {
+        self.strategy_network
+            .find_relevant(query, self.config.strategy_prompt_limit, provider)
+            .await
+    }
 
-            return Ok(self.create_output(
-                all_solutions,
-                final_solution,
-                SelectionMethod::MajorityVoting,
-            ));
+    /// Include strategies in an agent's prompts, recording the usage
+    fn share_strategies(&mut self, agent: Agent, strategies: &[crate::types::Strategy]) -> Agent {
+        let Some(prompt) = StrategyNetwork::format_strategies(strategies) else {
+            return agent;
+        };
+        for strategy in strategies {
+            self.strategy_network.record_usage(&strategy.id, &agent.id);
         }
+        agent.with_strategies(prompt)
+    }
 
-        // Try best verified solution
-        if let Some(final_solution) = self.select_best_verified(&all_solutions) {
-            let _result = tx
-                .send(MarsEvent::AnswerSynthesized {
-                    answer: final_solution.answer.clone(),
-                })
-                .await;
+    /// Feedback to store for a verdict on `solution`, leading with the step
+    /// the verifier found wrong
+    fn verdict_feedback(
+        solution: &crate::types::Solution,
+        result: &crate::types::VerificationResult,
+    ) -> String {
+        let summary = result.feedback_summary();
+        let failed_step = result
+            .failed_step
+            .and_then(|step| solution.steps.get(step.checked_sub(1)?));
+        match failed_step {
+            Some(step) => format!("Step {} is wrong: {}\n{summary}", step.index, step.claim),
+            None => summary,
+        }
+    }
 
-            return Ok(self.create_output(
-                all_solutions,
-                final_solution,
-                SelectionMethod::BestVerified,
-            ));
+    /// Build improvement feedback from the verifiers' critiques of a solution
+    ///
+    /// Falls back to a generic summary of the verification record when no
+    /// critique was captured. Solutions reasoned in numbered steps are asked
+    /// to keep the steps no verifier faulted.
+    fn improvement_feedback(
+        solution: &crate::types::Solution,
+        verifier_feedback: &[String],
+    ) -> String {
+        if !verifier_feedback.is_empty() {
+            let instruction = if solution.steps.is_empty() {
+                "Address each issue and state the final answer clearly."
+            } else {
+                "Keep the steps no issue applies to, redo the wrong steps and the ones \
+                 that depend on them, and state the final answer clearly."
+            };
+            return format!(
+                "Verifiers raised the following issues:\n\n{}\n\n{instruction}",
+                verifier_feedback.join("\n\n")
+            );
+        }
+
+        format!(
+            "The solution has not been verified yet ({} passes, {} failures, score {:.2}). \
+             Re-check each step, correct any errors, and state the final answer clearly.",
+            solution.verification_passes,
+            solution.verification_failures,
+            solution.verification_score
+        )
+    }
+
+    /// Phase 5: Final Synthesis
+    ///
+    /// Select the best answer using consensus voting, verification score, or
+    /// synthesis, trying the `preferred` method first
+    async fn phase_synthesis(
+        &self,
+        query: &str,
+        preferred: Option<SelectionMethod>,
+        tx: &mpsc::Sender<MarsEvent>,
+    ) -> Result<MarsOutput> {
+        let _result = tx.send(MarsEvent::SynthesisStarted).await;
+
+        let all_solutions = self.workspace.get_all_solutions().await;
+        // Plans stay in the output but never become the final answer
+        let candidates = self.workspace.get_candidate_solutions().await;
+
+        // The preferred method first; in pairwise mode the Elo ranking decides
+        // before consensus voting and the best verified solution
+        let mut methods: Vec<SelectionMethod> = preferred.into_iter().collect();
+        if self.config.verification_mode == VerificationMode::Pairwise {
+            methods.push(SelectionMethod::PairwiseRanking);
+        }
+        methods.extend([
+            SelectionMethod::MajorityVoting,
+            SelectionMethod::BestVerified,
+        ]);
+        let mut decisions = Vec::new();
+        for method in methods {
+            let selected = match method {
+                SelectionMethod::PairwiseRanking => Self::select_by_elo_rating(&candidates),
+                SelectionMethod::MajorityVoting => self.select_by_majority_voting(&candidates),
+                SelectionMethod::BestVerified => self.select_best_verified(&candidates),
+                SelectionMethod::Synthesized => break,
+                SelectionMethod::Manual => None,
+            };
+            if self.config.debug {
+                decisions.push(self.explain_selection(method, &candidates, selected.as_ref()));
+            }
+            if let Some(final_solution) = selected {
+                self.log_selection(&decisions);
+                let _result = tx
+                    .send(MarsEvent::AnswerSynthesized {
+                        answer: final_solution.answer.clone(),
+                    })
+                    .await;
+
+                let mut output = self.create_output(all_solutions, final_solution, method);
+                self.rank_answers(&mut output, &candidates);
+                return Ok(output);
+            }
         }
 
         // Fallback: use synthesized answer from top solutions
-        let final_solution = self.synthesize_final_answer(&all_solutions)?;
+        if self.config.debug {
+            decisions.push(format!(
+                "Synthesized: no method picked an answer, so the top of {} candidates were combined",
+                candidates.len()
+            ));
+            self.log_selection(&decisions);
+        }
+        let final_solution = self.synthesize_with_provider(query, &candidates).await?;
+        // Keep the synthesized answer in the workspace so its lineage is queryable
+        self.workspace.add_solution(final_solution.clone()).await;
+        let mut all_solutions = all_solutions;
+        all_solutions.push(final_solution.clone());
         let _result = tx
             .send(MarsEvent::AnswerSynthesized {
                 answer: final_solution.answer.clone(),
             })
             .await;
 
-        Ok(self.create_output(all_solutions, final_solution, SelectionMethod::Synthesized))
+        let mut output =
+            self.create_output(all_solutions, final_solution, SelectionMethod::Synthesized);
+        self.rank_answers(&mut output, &candidates);
+        Ok(output)
+    }
+
+    /// Fill in the alternatives and confidence of `output` from the
+    /// `candidates` its answer was chosen from
+    fn rank_answers(&self, output: &mut MarsOutput, candidates: &[crate::types::Solution]) {
+        output.alternatives = RankedAnswer::rank(candidates, self.config.num_alternatives);
+        if let Some(final_solution) = output.final_solution() {
+            output.confidence = crate::confidence::answer_confidence(final_solution, candidates);
+        }
+    }
+
+    /// Why `method` did or did not pick `selected` from `candidates`
+    fn explain_selection(
+        &self,
+        method: SelectionMethod,
+        candidates: &[crate::types::Solution],
+        selected: Option<&crate::types::Solution>,
+    ) -> String {
+        match (method, selected) {
+            (SelectionMethod::MajorityVoting, _) => {
+                let votes = |answer: &str| candidates.iter().filter(|s| s.answer == answer).count();
+                let top = candidates
+                    .iter()
+                    .map(|s| votes(&s.answer))
+                    .max()
+                    .unwrap_or(0);
+                let threshold = self.config.consensus_threshold;
+                match selected {
+                    Some(s) => format!(
+                        "MajorityVoting: picked {} ({} of {} candidates agree, threshold {threshold})",
+                        s.id,
+                        votes(&s.answer),
+                        candidates.len()
+                    ),
+                    None => format!(
+                        "MajorityVoting: no answer reached {threshold} votes (top had {top} of {})",
+                        candidates.len()
+                    ),
+                }
+            }
+            (SelectionMethod::PairwiseRanking, Some(s)) => format!(
+                "PairwiseRanking: picked {} with the highest Elo rating ({:.0})",
+                s.id,
+                s.elo_rating.unwrap_or_default()
+            ),
+            (SelectionMethod::PairwiseRanking, None) => {
+                "PairwiseRanking: no candidate was ranked".to_string()
+            }
+            (SelectionMethod::BestVerified, Some(s)) => format!(
+                "BestVerified: picked {} with verification score {:.2}",
+                s.id, s.verification_score
+            ),
+            (SelectionMethod::BestVerified, None) => {
+                "BestVerified: no candidate passed verification".to_string()
+            }
+            (method, _) => format!("{method:?}: not selectable automatically"),
+        }
+    }
+
+    /// Log the selection `decisions` at debug level, and write them to the
+    /// debug directory
+    fn log_selection(&self, decisions: &[String]) {
+        for decision in decisions {
+            tracing::debug!("Selection: {decision}");
+        }
+        if let Some(dir) = &self.debug_dir
+            && let Err(e) = dir.write_selection(decisions)
+        {
+            tracing::warn!("{e}; skipping the selection dump");
+        }
     }
 
     /// Select answer by majority voting
@@ -427,19 +2029,33 @@ impl MarsCoordinator {
             return solutions.first().cloned();
         }
 
-        let mut answer_counts: std::collections::HashMap<String, usize> = Default::default();
+        let mut answer_counts: std::collections::HashMap<&str, usize> = Default::default();
         for sol in solutions {
-            *answer_counts.entry(sol.answer.clone()).or_insert(0) += 1;
+            *answer_counts.entry(sol.answer.as_str()).or_insert(0) += 1;
         }
 
-        // Return answer that appears 2+ times
-        for (answer, count) in answer_counts {
-            if count >= 2 {
-                return solutions.iter().find(|s| s.answer == answer).cloned();
+        // The most common answer, if at least `consensus_threshold` solutions
+        // agree on it; ties go to the answer seen first
+        let mut best: Option<(&crate::types::Solution, usize)> = None;
+        for sol in solutions {
+            let count = answer_counts[sol.answer.as_str()];
+            if best.is_none_or(|(_, best_count)| count > best_count) {
+                best = Some((sol, count));
             }
         }
+        best.filter(|&(_, count)| count >= self.config.consensus_threshold)
+            .map(|(sol, _)| sol.clone())
+    }
 
-        None
+    /// Select the solution with the highest Elo rating, if any were ranked
+    fn select_by_elo_rating(
+        solutions: &[crate::types::Solution],
+    ) -> Option<crate::types::Solution> {
+        solutions
+            .iter()
+            .filter_map(|s| s.elo_rating.map(|rating| (s, rating)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(s, _)| s.clone())
     }
 
     /// Select best verified solution
@@ -450,6 +2066,55 @@ impl MarsCoordinator {
         Verifier::find_best_verified(solutions)
     }
 
+    /// Have the synthesis provider write a final answer from the top solutions
+    ///
+    /// Falls back to the top solution's answer if the call fails.
+    async fn synthesize_with_provider(
+        &self,
+        query: &str,
+        solutions: &[crate::types::Solution],
+    ) -> Result<crate::types::Solution> {
+        let mut synthesized = self.synthesize_final_answer(solutions)?;
+        let provider = self.provider_for(MarsPhase::Synthesis);
+        let mut problem = format!("Problem: {query}");
+        // Already carries the response language during a run
+        let system_prompt = self
+            .config
+            .prompts
+            .system_prompt(self.config.use_thinking_tags);
+        // Require the response language of the final answer itself, since the
+        // solutions being combined may not all be in it
+        if let Some(language) = &self.config.response_language {
+            let instruction = crate::prompts::language_instruction(
+                crate::prompts::SYNTHESIS_LANGUAGE_PROMPT,
+                language,
+            );
+            problem = format!("{problem}\n\n{instruction}");
+        }
+        if let Some(instruction) = self.config.answer_type.format_instruction() {
+            problem = format!("{problem}\n\n{instruction}");
+        }
+        let prompt = format!(
+            "{problem}\n\n{}",
+            crate::prompts::SYNTHESIS_PROMPT.replace("{solutions}", &synthesized.reasoning)
+        );
+        match provider.complete(&prompt, Some(system_prompt)).await {
+            Ok(response) if !response.text.trim().is_empty() => {
+                // Drop any thinking that precedes the answer
+                let answer = match response.text.rfind("</think>") {
+                    Some(end) => &response.text[end + "</think>".len()..],
+                    None => response.text.as_str(),
+                };
+                synthesized.answer = answer.trim().to_string();
+                synthesized.record_completion(&response, prompt.len() + system_prompt.len());
+                synthesized.record_provider(provider.as_ref(), None);
+            }
+            Ok(_) => tracing::warn!("Synthesis returned nothing; using the top solution's answer"),
+            Err(e) => tracing::warn!("Synthesis failed ({e}); using the top solution's answer"),
+        }
+        Ok(synthesized)
+    }
+
     /// Synthesize final answer from top solutions
     fn synthesize_final_answer(
         &self,
@@ -477,13 +2142,15 @@ impl MarsCoordinator {
 
         let final_answer = top_3.first().map(|s| s.answer.clone()).unwrap_or_default();
 
-        Ok(crate::types::Solution::new(
+        let mut synthesized = crate::types::Solution::new(
             "synthesizer".to_string(),
             combined_reasoning,
             final_answer,
             0.5,
             solutions.iter().map(|s| s.token_count).sum(),
-        ))
+        );
+        synthesized.parent_ids = top_3.iter().map(|s| s.id.clone()).collect();
+        Ok(synthesized)
     }
 
     /// Create the final output
@@ -497,6 +2164,13 @@ impl MarsCoordinator {
         let answer = final_solution.answer.clone();
         let reasoning = final_solution.reasoning.clone();
 
+        let total_prompt_tokens = all_solutions.iter().map(|s| s.prompt_tokens).sum();
+        let total_completion_tokens = all_solutions.iter().map(|s| s.completion_tokens).sum();
+        let total_tokens = all_solutions.iter().map(|s| s.token_count).sum();
+        let usage_estimated = all_solutions.iter().any(|s| s.usage_estimated);
+        let mut strategies = self.strategy_network.get_all_strategies();
+        strategies.sort_by_key(|s| s.discovered_at);
+
         MarsOutput {
             answer,
             reasoning,
@@ -505,62 +2179,2080 @@ impl MarsCoordinator {
             final_solution_id,
             selection_method,
             iterations: 0,
-            total_tokens: 0,
+            total_tokens,
+            total_prompt_tokens,
+            total_completion_tokens,
+            usage_estimated,
+            cost: self.cost_summary(),
+            tags: Vec::new(),
+            run_id: String::new(),
+            budget_truncated: false,
+            typed_answer: None,
+            alternatives: Vec::new(),
+            confidence: 0.0,
+            strategies,
+            phase_timings: Vec::new(),
             completed_at: Utc::now(),
         }
     }
 }
 
+/// Span of one verification of `solution_id` by `verifier`
+fn verification_span(solution_id: &str, verifier: &str) -> tracing::Span {
+    tracing::info_span!("verification", solution_id, verifier)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+
+    /// Mock provider that always answers with the same thinking-tagged response
+    struct MockProvider;
+
+    #[async_trait]
+    impl LLMProvider for MockProvider {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Ok("<think>2 + 2 = 4</think>\n4".into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-model"
+        }
+    }
+
+    /// Mock provider that approves solutions when asked to verify them
+    struct ApprovingProvider;
+
+    #[async_trait]
+    impl LLMProvider for ApprovingProvider {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if system_prompt == Some(crate::prompts::VERIFICATION_SYSTEM_PROMPT) {
+                Ok("RESULT: CORRECT\nSCORE: 0.9\nFEEDBACK: Looks right.".into())
+            } else {
+                Ok("<think>2 + 2 = 4</think>\n4".into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "approving"
+        }
+
+        fn model_name(&self) -> &str {
+            "approving-model"
+        }
+    }
 
-    // Note: Coordinator tests that instantiate MarsCoordinator are skipped because
-    // code_core::ModelClient doesn't have a Default implementation.
-    // Multi-provider integration tests are in tests/multi_provider_integration.rs
-    // These unit tests would need a mock ModelClient to work properly.
+    fn mock_coordinator(config: MarsConfig) -> MarsCoordinator {
+        MarsCoordinator::with_provider(config, Arc::new(MockProvider))
+    }
 
     #[tokio::test]
-    #[ignore]
     async fn test_coordinator_creation() {
-        // TODO: Implement mock ModelClient or use test fixtures
-        // let config = MarsConfig::default();
-        // let coordinator = MarsCoordinator::new(config, mock_client);
-        // assert_eq!(coordinator.config.num_agents, 3);
+        let coordinator = mock_coordinator(MarsConfig::default());
+        assert_eq!(coordinator.config.num_agents, 3);
+        assert_eq!(coordinator.get_provider().provider_name(), "mock");
     }
 
     #[tokio::test]
-    #[ignore]
     async fn test_majority_voting() {
-        // TODO: Implement mock ModelClient or use test fixtures
-        // let config = MarsConfig::default();
-        // let coordinator = MarsCoordinator::new(config, mock_client);
-        //
-        // let sol1 = crate::types::Solution::new(
-        //     "agent1".to_string(),
-        //     "r1".to_string(),
-        //     "42".to_string(),
-        //     0.3,
-        //     100,
-        // );
-        // let sol2 = crate::types::Solution::new(
-        //     "agent2".to_string(),
-        //     "r2".to_string(),
-        //     "42".to_string(),
-        //     0.6,
-        //     100,
-        // );
-        // let sol3 = crate::types::Solution::new(
-        //     "agent3".to_string(),
-        //     "r3".to_string(),
-        //     "43".to_string(),
-        //     1.0,
-        //     100,
-        // );
-        //
-        // let solutions = vec![sol1, sol2, sol3];
-        // let selected = coordinator.select_by_majority_voting(&solutions);
-        // assert!(selected.is_some());
-        // assert_eq!(selected.unwrap().answer, "42");
+        let coordinator = mock_coordinator(MarsConfig::default());
+
+        let sol1 = crate::types::Solution::new(
+            "agent1".to_string(),
+            "r1".to_string(),
+            "42".to_string(),
+            0.3,
+            100,
+        );
+        let sol2 = crate::types::Solution::new(
+            "agent2".to_string(),
+            "r2".to_string(),
+            "42".to_string(),
+            0.6,
+            100,
+        );
+        let sol3 = crate::types::Solution::new(
+            "agent3".to_string(),
+            "r3".to_string(),
+            "43".to_string(),
+            1.0,
+            100,
+        );
+
+        let solutions = vec![sol1, sol2, sol3];
+        let selected = coordinator.select_by_majority_voting(&solutions);
+        assert!(selected.is_some());
+        assert_eq!(selected.unwrap().answer, "42");
+
+        // Two agreeing solutions fall short of a threshold of three
+        let strict = mock_coordinator(MarsConfig::default().with_consensus_threshold(3));
+        assert!(strict.select_by_majority_voting(&solutions).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_consensus_threshold_bounds_improvement() {
+        // Both verifiers reject every solution: rejected by consensus at the
+        // default threshold, but not at a threshold of three
+        for (threshold, improvable) in [(2, false), (3, true)] {
+            let config = MarsConfig::default().with_consensus_threshold(threshold);
+            let mut coordinator = mock_coordinator(config);
+            let (tx, _rx) = mpsc::channel(100);
+            coordinator
+                .phase_exploration("What is 2+2?", &tx)
+                .await
+                .unwrap();
+            coordinator
+                .phase_verification("What is 2+2?", &tx)
+                .await
+                .unwrap();
+
+            let improved = coordinator
+                .phase_improvement("What is 2+2?", 0, &tx)
+                .await
+                .unwrap();
+            assert_eq!(improved, improvable, "threshold {threshold}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_end_to_end_with_provider() {
+        let mut coordinator = mock_coordinator(MarsConfig::default());
+
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+        assert_eq!(output.answer, "4");
+        assert!(output.all_solutions.len() >= 3);
+        assert!(output.total_tokens > 0);
+        // The mock reports no usage
+        assert!(output.usage_estimated);
+    }
+
+    #[tokio::test]
+    async fn test_run_reports_cost_and_enforces_budget() {
+        use crate::cost::ModelPricing;
+        let config =
+            MarsConfig::default().with_model_pricing("mock-model", ModelPricing::new(1.0, 1.0));
+
+        let output = mock_coordinator(config.clone())
+            .run("What is 2+2?")
+            .await
+            .unwrap();
+        assert!(output.cost.total_usd > 0.0);
+        assert!(output.cost.by_phase[&MarsPhase::Exploration] > 0.0);
+        assert!(output.cost.by_provider.contains_key("mock/mock-model"));
+        assert!(!output.budget_truncated);
+        let exploration_usd = output.cost.by_phase[&MarsPhase::Exploration];
+
+        // Spent during exploration: the run skips to synthesis instead of failing
+        let output = mock_coordinator(config.clone().with_max_cost_usd(0.001))
+            .run("What is 2+2?")
+            .await
+            .unwrap();
+        assert!(output.budget_truncated);
+        assert_eq!(output.answer, "4");
+        assert!(!output.cost.by_phase.contains_key(&MarsPhase::Verification));
+
+        // Verification is projected to cost more than what is left, so it is
+        // skipped before any of its calls overrun the budget
+        let output = mock_coordinator(config.with_max_cost_usd(exploration_usd * 1.5))
+            .run("What is 2+2?")
+            .await
+            .unwrap();
+        assert!(output.budget_truncated);
+        assert!(output.cost.total_usd <= exploration_usd * 1.5);
+        assert!(!output.cost.by_phase.contains_key(&MarsPhase::Verification));
+    }
+
+    #[tokio::test]
+    async fn test_budget_and_cost_cover_one_run() {
+        use crate::cost::ModelPricing;
+        let config =
+            MarsConfig::default().with_model_pricing("mock-model", ModelPricing::new(1.0, 1.0));
+        let unbudgeted = mock_coordinator(config.clone())
+            .run("What is 2+2?")
+            .await
+            .unwrap();
+        let config = config.with_max_cost_usd(unbudgeted.cost.total_usd * 1.5);
+        let fresh = mock_coordinator(config.clone())
+            .run("What is 2+2?")
+            .await
+            .unwrap();
+
+        // A reused coordinator spends and reports as if each run were its first
+        let mut coordinator = mock_coordinator(config);
+        for _ in 0..2 {
+            let output = coordinator.run("What is 2+2?").await.unwrap();
+            assert_eq!(output.budget_truncated, fresh.budget_truncated);
+            assert!((output.cost.total_usd - fresh.cost.total_usd).abs() < 1e-9);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_records_call_stats() {
+        let mut coordinator = mock_coordinator(MarsConfig::default().with_debug(true));
+        coordinator.run("What is 2+2?").await.unwrap();
+
+        let stats = coordinator.call_stats();
+        assert!(stats.calls() > 0);
+        assert_eq!(stats.errors(), 0);
+        assert!(stats.prompt_tokens() > 0);
+        assert!(stats.completion_tokens() > 0);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_run_is_traced_in_phase_agent_and_verification_spans() {
+        let mut coordinator = mock_coordinator(MarsConfig::default().with_max_iterations(1));
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+
+        let phase = format!("mars_phase{{run_id={} phase=Exploration}}", output.run_id);
+        assert!(logs_contain(&phase));
+        assert!(logs_contain("agent_call{operation=\"generate\""));
+        assert!(logs_contain(
+            "llm_call{method=\"complete_with_temperature\""
+        ));
+        assert!(logs_contain("verification{solution_id="));
+        // Phase spans carry their totals once finished
+        assert!(logs_contain("phase=Exploration llm_calls=3 tokens="));
+        assert!(logs_contain("Phase finished"));
+        assert!(logs_contain("MARS event"));
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_debug_mode_dumps_workspace_and_explains_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = MarsConfig::default()
+            .with_max_iterations(1)
+            .with_debug_dir(dir.path());
+        let mut coordinator = mock_coordinator(config);
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+
+        assert!(logs_contain("LLM request"));
+        assert!(logs_contain("Workspace solution"));
+        assert!(logs_contain("Selection: MajorityVoting: picked"));
+
+        let run_dir = dir.path().join(&output.run_id);
+        let mut files: Vec<String> = std::fs::read_dir(&run_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                "01-exploration.json",
+                "02-verification.json",
+                "03-improvement.json",
+                "04-synthesis.json",
+                "selection.md",
+            ]
+        );
+        let dump = std::fs::read_to_string(run_dir.join("04-synthesis.json")).unwrap();
+        let imported = Workspace::new().import_json(&dump).await.unwrap();
+        assert_eq!(imported, output.all_solutions.len());
+        let selection = std::fs::read_to_string(run_dir.join("selection.md")).unwrap();
+        let final_id = &output.final_solution().unwrap().id;
+        assert!(selection.contains(&format!("MajorityVoting: picked {final_id}")));
+    }
+
+    #[tokio::test]
+    async fn test_output_reports_phase_timings() {
+        let mut coordinator = mock_coordinator(MarsConfig::default());
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+
+        let timings = &output.phase_timings;
+        assert_eq!(timings[0].phase, MarsPhase::Exploration);
+        assert_eq!(timings[timings.len() - 1].phase, MarsPhase::Synthesis);
+        assert!(timings.iter().all(|t| t.ended_at >= t.started_at));
+        assert!(
+            timings
+                .windows(2)
+                .all(|pair| pair[1].started_at >= pair[0].ended_at)
+        );
+        assert!(timings[0].llm_calls > 0);
+        assert!(timings[0].tokens > 0);
+
+        let stats = coordinator.call_stats();
+        let calls: u64 = timings.iter().map(|t| t.llm_calls).sum();
+        let tokens: u64 = timings.iter().map(|t| t.tokens).sum();
+        assert_eq!(calls, stats.calls());
+        assert_eq!(tokens, stats.prompt_tokens() + stats.completion_tokens());
+
+        // The next run reports only its own phases
+        let output = coordinator.run("What is 3+3?").await.unwrap();
+        let next_calls: u64 = output.phase_timings.iter().map(|t| t.llm_calls).sum();
+        assert_eq!(next_calls, coordinator.call_stats().calls() - calls);
+    }
+
+    #[tokio::test]
+    async fn test_output_ranks_alternatives_and_scores_confidence() {
+        let mut coordinator = mock_coordinator(MarsConfig::default());
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+
+        // Every agent answers 4, so there is one distinct answer
+        assert_eq!(output.alternatives.len(), 1);
+        let top = &output.alternatives[0];
+        assert_eq!(top.answer, output.answer);
+        assert!(top.votes >= 3);
+        assert_eq!(top.solution_ids.len(), top.votes);
+        assert!(top.solution_ids.contains(&output.final_solution_id));
+        // Full vote share and agreement put confidence at 2/3 or more
+        assert!(output.confidence >= 2.0 / 3.0 - 1e-6);
+        assert!(output.confidence <= 1.0);
+
+        let mut coordinator = mock_coordinator(MarsConfig::default().with_num_alternatives(0));
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+        assert!(output.alternatives.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_output_carries_strategies_and_verdicts_for_reports() {
+        let mut coordinator = mock_coordinator(MarsConfig::default());
+        coordinator.strategy_network.register_strategy(
+            "agent1".to_string(),
+            "Check small cases first".to_string(),
+            "Enumerate n = 1, 2, 3".to_string(),
+        );
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+
+        assert_eq!(output.strategies[0].description, "Check small cases first");
+        assert!(!output.verifications.is_empty());
+        assert!(
+            output
+                .verifications
+                .iter()
+                .all(|v| output.all_solutions.iter().any(|s| s.id == v.solution_id))
+        );
+        let report = output.to_markdown();
+        assert!(report.contains("**Check small cases first**"), "{report}");
+        assert!(report.contains("## Timing"), "{report}");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_config_fails_run_before_any_call() {
+        let mut config = MarsConfig::default();
+        config.num_agents = 5;
+        let mut coordinator = mock_coordinator(config);
+        assert_eq!(coordinator.config_errors()[0].field, "temperatures");
+
+        let err = coordinator.run("What is 2+2?").await.unwrap_err();
+        assert!(matches!(err, crate::MarsError::InvalidConfiguration(_)));
+        assert!(err.to_string().contains("num_agents is 5"), "{err}");
+        assert_eq!(coordinator.call_stats().calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_query_classification_picks_run_settings() {
+        let explored = |output: &MarsOutput| {
+            output
+                .all_solutions
+                .iter()
+                .filter(|s| s.parent_ids.is_empty())
+                .count()
+        };
+        let config = MarsConfig::default()
+            .with_max_iterations(1)
+            .with_query_classification(QueryClassification::Heuristic);
+
+        // Math: the math preset's five agents
+        let mut coordinator = mock_coordinator(config.clone());
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+        assert_eq!(explored(&output), 5);
+        assert_eq!(coordinator.config.num_agents, 3);
+
+        // Short-form: lightweight mode's two agents, even with a large limit
+        let output = mock_coordinator(config.clone())
+            .run_with_options(
+                "What is the capital of France?",
+                RunOptions::new().with_max_tokens(100_000),
+            )
+            .await
+            .unwrap();
+        assert_eq!(explored(&output), 2);
+
+        // An explicit lightweight option wins over the class
+        let output = mock_coordinator(config)
+            .run_with_options(
+                "What is the capital of France?",
+                RunOptions::new().with_lightweight(false),
+            )
+            .await
+            .unwrap();
+        assert_eq!(explored(&output), 3);
+    }
+
+    #[tokio::test]
+    async fn test_output_reports_solution_provenance() {
+        let mut coordinator = mock_coordinator(MarsConfig::default().with_max_iterations(1));
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+
+        let winner = output.final_solution().unwrap();
+        assert_eq!(winner.provenance.provider, "mock");
+        assert_eq!(winner.provenance.model, "mock-model");
+        assert!(winner.provenance.latency_ms.is_some());
+        let explored = output
+            .all_solutions
+            .iter()
+            .filter(|s| s.phase == crate::types::GenerationPhase::Initial);
+        for solution in explored {
+            assert_eq!(solution.provenance.temperature, Some(solution.temperature));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_sink_receives_stamped_events() {
+        let (sink, mut records) = mpsc::channel(1000);
+        let mut coordinator =
+            mock_coordinator(MarsConfig::default().with_max_iterations(1)).with_event_sink(sink);
+
+        let first = coordinator.run("What is 2+2?").await.unwrap();
+        let second = coordinator.run("What is 2+2?").await.unwrap();
+        assert_ne!(first.run_id, second.run_id);
+        drop(coordinator);
+
+        let mut received = Vec::new();
+        while let Some(record) = records.recv().await {
+            received.push(record);
+        }
+        for output in [&first, &second] {
+            let run: Vec<&MarsEventRecord> = received
+                .iter()
+                .filter(|r| r.run_id == output.run_id)
+                .collect();
+            assert!(matches!(
+                run[0].event,
+                MarsEvent::ExplorationStarted { num_agents: 3 }
+            ));
+            assert!(run.iter().enumerate().all(|(i, r)| r.sequence == i as u64));
+            assert!(matches!(
+                &run[run.len() - 1].event,
+                MarsEvent::Completed { final_answer, .. } if *final_answer == output.answer
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_subscribers_each_receive_the_run() {
+        let (sink, mut tui) = mpsc::channel(1000);
+        let mut coordinator =
+            mock_coordinator(MarsConfig::default().with_max_iterations(1)).with_event_sink(sink);
+        let mut metrics = coordinator.subscribe_events(1000);
+        // Gone before the run; must not hold up the others
+        drop(coordinator.subscribe_events(1));
+
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+        assert_eq!(coordinator.event_bus().subscriber_count(), 2);
+        drop(coordinator);
+
+        let mut streams = Vec::new();
+        for receiver in [&mut tui, &mut metrics] {
+            let mut sequences = Vec::new();
+            while let Some(record) = receiver.recv().await {
+                assert_eq!(record.run_id, output.run_id);
+                sequences.push(record.sequence);
+            }
+            streams.push(sequences);
+        }
+        assert!(!streams[0].is_empty());
+        assert_eq!(streams[0], streams[1]);
+    }
+
+    /// Provider whose every call fails
+    struct Down;
+
+    #[async_trait]
+    impl LLMProvider for Down {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Err(crate::MarsError::AgentError("down".to_string()))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "down"
+        }
+
+        fn model_name(&self) -> &str {
+            "down-model"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_run_publishes_its_events_before_returning() {
+        let mut coordinator = MarsCoordinator::with_provider(
+            MarsConfig::default().with_max_iterations(1),
+            Arc::new(Down),
+        );
+        let mut events = coordinator.subscribe_events(1000);
+        coordinator.run("What is 2+2?").await.unwrap_err();
+
+        // Every agent's failure, with no forwarder left running to publish them
+        let mut failures = 0;
+        while let Ok(record) = events.try_recv() {
+            if matches!(record.event, MarsEvent::Error { .. }) {
+                failures += 1;
+            }
+        }
+        assert!(failures >= 3);
+    }
+
+    #[tokio::test]
+    async fn test_trace_records_events_and_calls() {
+        use crate::trace::{TraceEntry, TraceWriter};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.jsonl");
+        let config = MarsConfig::default()
+            .with_max_iterations(1)
+            .with_trace_path(&path);
+        let mut coordinator = mock_coordinator(config);
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+
+        let entries = TraceWriter::read(&path).unwrap();
+        let events: Vec<&MarsEventRecord> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                TraceEntry::Event(record) => Some(record),
+                TraceEntry::Call(_) => None,
+            })
+            .collect();
+        let calls: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                TraceEntry::Call(call) => Some(call),
+                TraceEntry::Event(_) => None,
+            })
+            .collect();
+        assert!(events.iter().all(|r| r.run_id == output.run_id));
+        assert!(matches!(
+            events[events.len() - 1].event,
+            MarsEvent::Completed { .. }
+        ));
+        assert_eq!(calls.len() as u64, coordinator.call_stats().calls());
+        assert!(
+            calls
+                .iter()
+                .all(|c| c.run_id == output.run_id && c.phase.is_some())
+        );
+        assert!(
+            calls
+                .iter()
+                .any(|c| c.request.prompt.contains("What is 2+2?"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_options_apply_to_one_run() {
+        let mut coordinator = mock_coordinator(MarsConfig::default().with_max_iterations(1));
+        let options = RunOptions::new()
+            .with_max_tokens(1000)
+            .with_selection(SelectionMethod::Synthesized)
+            .with_tag("batch-7");
+        let output = coordinator
+            .run_with_options("What is 2+2?", options)
+            .await
+            .unwrap();
+
+        assert_eq!(output.selection_method, SelectionMethod::Synthesized);
+        assert_eq!(output.tags, ["batch-7"]);
+        let explored = |output: &MarsOutput| {
+            output
+                .all_solutions
+                .iter()
+                .filter(|s| s.parent_ids.is_empty())
+                .count()
+        };
+        // A 1000-token limit switches to lightweight mode: two agents
+        assert_eq!(explored(&output), 2);
+        assert_eq!(coordinator.config.num_agents, 3);
+
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+        assert_eq!(output.selection_method, SelectionMethod::MajorityVoting);
+        assert!(output.tags.is_empty());
+        // Only this run's three agents, none of the previous run's solutions
+        assert_eq!(explored(&output), 3);
+    }
+
+    /// Server accepting webhook notifications on `/runs`
+    async fn webhook_server() -> wiremock::MockServer {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/runs"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    /// Summaries posted to `server`, waiting for the first to arrive
+    async fn webhook_summaries(server: &wiremock::MockServer) -> Vec<RunSummary> {
+        // Sent in the background, after the run has returned
+        let mut requests = Vec::new();
+        for _ in 0..100 {
+            requests = server.received_requests().await.unwrap_or_default();
+            if !requests.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        requests
+            .iter()
+            .map(|request| request.body_json().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_webhook_receives_run_summary() {
+        let server = webhook_server().await;
+        let config = MarsConfig::default().with_webhook(crate::webhook::WebhookConfig::new(
+            format!("{}/runs", server.uri()),
+        ));
+        let output = mock_coordinator(config)
+            .run_with_options("What is 2+2?", RunOptions::new().with_tag("nightly"))
+            .await
+            .unwrap();
+
+        let summaries = webhook_summaries(&server).await;
+        assert_eq!(summaries.len(), 1);
+        let summary = &summaries[0];
+        assert_eq!(summary.run_id, output.run_id);
+        assert_eq!(summary.status, crate::webhook::RunStatus::Completed);
+        assert_eq!(summary.answer.as_deref(), Some("4"));
+        assert_eq!(summary.tags, ["nightly"]);
+        assert_eq!(summary.total_tokens, output.total_tokens);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_receives_runs_failing_before_any_call() {
+        let server = webhook_server().await;
+        let config = MarsConfig::default()
+            .with_webhook(crate::webhook::WebhookConfig::new(format!(
+                "{}/runs",
+                server.uri()
+            )))
+            .with_reasoning_prompt("Answer: {query}");
+        let mut coordinator = mock_coordinator(config);
+
+        let options = RunOptions::new()
+            .with_tag("nightly")
+            .with_variable("tone", "formal");
+        let err = coordinator
+            .run_with_options("What is 2+2?", options)
+            .await
+            .unwrap_err();
+
+        let summaries = webhook_summaries(&server).await;
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].status, crate::webhook::RunStatus::Failed);
+        assert_eq!(summaries[0].error, Some(err.to_string()));
+        assert_eq!(summaries[0].tags, ["nightly"]);
+    }
+
+    #[test]
+    fn test_improvement_feedback_uses_verifier_critique() {
+        let solution = crate::types::Solution::new(
+            "agent1".to_string(),
+            "r1".to_string(),
+            "5".to_string(),
+            0.3,
+            100,
+        );
+
+        let feedback = MarsCoordinator::improvement_feedback(
+            &solution,
+            &["Correctness: 2 + 2 is not 5".to_string()],
+        );
+        assert!(feedback.contains("2 + 2 is not 5"));
+
+        let fallback = MarsCoordinator::improvement_feedback(&solution, &[]);
+        assert!(fallback.contains("not been verified"));
+    }
+
+    #[test]
+    fn test_improvement_feedback_targets_the_failed_step() {
+        let mut solution = crate::types::Solution::new(
+            "agent1".to_string(),
+            "Step 1: 2 + 2 = 5\nStep 2: So the answer is 5".to_string(),
+            "5".to_string(),
+            0.3,
+            100,
+        );
+        solution.steps = crate::agent::Agent::parse_steps(&solution.reasoning);
+        let mut verdict =
+            crate::types::VerificationResult::new(solution.id.clone(), false, 0.1, "v".into());
+        verdict.critique = "2 + 2 is 4".to_string();
+        verdict.failed_step = Some(1);
+
+        let stored = MarsCoordinator::verdict_feedback(&solution, &verdict);
+        assert!(
+            stored.starts_with("Step 1 is wrong: 2 + 2 = 5\n"),
+            "{stored}"
+        );
+        assert!(stored.ends_with("2 + 2 is 4"), "{stored}");
+        let feedback = MarsCoordinator::improvement_feedback(&solution, &[stored]);
+        assert!(feedback.contains("Keep the steps no issue applies to"));
+
+        // A step the solution does not have is ignored
+        verdict.failed_step = Some(3);
+        let stored = MarsCoordinator::verdict_feedback(&solution, &verdict);
+        assert_eq!(stored, verdict.feedback_summary());
+    }
+
+    #[tokio::test]
+    async fn test_genetic_aggregation_adds_offspring() {
+        let config = MarsConfig::default()
+            .with_aggregation(true)
+            .with_aggregation_method(crate::types::AggregationMethod::GeneticAlgorithm)
+            .with_ga_population_size(3)
+            .with_ga_generations(2)
+            .with_ga_mutation_rate(0.0);
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(ApprovingProvider));
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_aggregation("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        let solutions = coordinator.workspace.get_all_solutions().await;
+        let offspring: Vec<_> = solutions
+            .iter()
+            .filter(|s| s.agent_id.starts_with("genetic-crossover"))
+            .collect();
+        assert_eq!(solutions.len(), 5);
+        assert_eq!(offspring.len(), 2);
+        assert!(
+            offspring
+                .iter()
+                .all(|s| s.phase == crate::types::GenerationPhase::Aggregated)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_aggregation_records_lineage() {
+        let config = MarsConfig::default().with_aggregation(true);
+        let mut coordinator = mock_coordinator(config);
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_aggregation("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        let solutions = coordinator.workspace.get_all_solutions().await;
+        let latest = solutions
+            .iter()
+            .rfind(|s| s.phase == crate::types::GenerationPhase::Aggregated)
+            .unwrap();
+        let tree = coordinator
+            .workspace
+            .get_derivation_tree(&latest.id)
+            .await
+            .unwrap();
+
+        assert!(tree.depth() >= 1);
+        for solution in tree.solutions() {
+            // Every link resolves, and the derivation bottoms out in exploration
+            assert_eq!(
+                coordinator.workspace.get_parents(&solution.id).await.len(),
+                solution.parent_ids.len()
+            );
+            if solution.parent_ids.is_empty() {
+                assert_eq!(solution.phase, crate::types::GenerationPhase::Initial);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exploration_dedup_merges_identical_answers() {
+        let config =
+            MarsConfig::default().with_solution_dedup(crate::types::SolutionDedup::ContentHash);
+        let mut coordinator = mock_coordinator(config);
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        // The mock answers identically, so the three agents collapse into one
+        let solutions = coordinator.workspace.get_all_solutions().await;
+        assert_eq!(solutions.len(), 1);
+        assert!(solutions[0].token_count > 0);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_limit_evicts_and_reports() {
+        let config = MarsConfig::default().with_max_workspace_solutions(2);
+        let mut coordinator = mock_coordinator(config);
+        let (tx, mut rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator.report_evictions(&tx).await;
+        drop(tx);
+
+        assert_eq!(coordinator.workspace.count_solutions().await, 2);
+        let mut evicted = Vec::new();
+        while let Some(event) = rx.recv().await {
+            if let MarsEvent::SolutionsEvicted { solution_ids } = event {
+                evicted.extend(solution_ids);
+            }
+        }
+        assert_eq!(evicted.len(), 1);
+    }
+
+    #[test]
+    fn test_synthesized_answer_links_top_solutions() {
+        let coordinator = mock_coordinator(MarsConfig::default());
+        let solutions: Vec<_> = ["3", "4", "5", "6"]
+            .iter()
+            .map(|answer| {
+                crate::types::Solution::new(
+                    "agent".to_string(),
+                    "r".to_string(),
+                    answer.to_string(),
+                    0.5,
+                    10,
+                )
+            })
+            .collect();
+
+        let synthesized = coordinator.synthesize_final_answer(&solutions).unwrap();
+        assert_eq!(synthesized.parent_ids.len(), 3);
+
+        let mut all_solutions = solutions;
+        all_solutions.push(synthesized.clone());
+        let output =
+            coordinator.create_output(all_solutions, synthesized, SelectionMethod::Synthesized);
+        let tree = output.derivation_tree().unwrap();
+        assert_eq!(tree.parents.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_synthesis_uses_synthesis_provider() {
+        let coordinator =
+            MarsCoordinator::with_provider(MarsConfig::default(), Arc::new(ApprovingProvider))
+                .with_phase_provider(MarsPhase::Synthesis, Arc::new(MockProvider));
+        let solutions: Vec<_> = ["3", "5", "6"]
+            .iter()
+            .map(|answer| {
+                crate::types::Solution::new(
+                    "agent".to_string(),
+                    "r".to_string(),
+                    answer.to_string(),
+                    0.5,
+                    10,
+                )
+            })
+            .collect();
+
+        let synthesized = coordinator
+            .synthesize_with_provider("What is 2+2?", &solutions)
+            .await
+            .unwrap();
+        assert_eq!(synthesized.answer, "4");
+        assert_eq!(synthesized.model, "mock/mock-model");
+        assert_eq!(synthesized.parent_ids.len(), 3);
+        assert!(synthesized.token_count > 0);
+        let summary = coordinator.cost_summary();
+        assert!(summary.by_phase.contains_key(&MarsPhase::Synthesis));
+    }
+
+    #[tokio::test]
+    async fn test_beam_search_aggregation_emits_expansions() {
+        let config = MarsConfig::default()
+            .with_aggregation(true)
+            .with_aggregation_method(crate::types::AggregationMethod::BeamSearch)
+            .with_beam_width(2)
+            .with_beam_depth(1);
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(ApprovingProvider));
+        let (tx, mut rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_aggregation("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        drop(tx);
+
+        let mut expansions = 0;
+        while let Some(event) = rx.recv().await {
+            if matches!(event, MarsEvent::BeamExpanded { depth: 0, .. }) {
+                expansions += 1;
+            }
+        }
+        assert_eq!(expansions, 4);
+        assert_eq!(coordinator.workspace.get_all_solutions().await.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_best_of_n_aggregation_adds_single_solution() {
+        let config = MarsConfig::default()
+            .with_aggregation(true)
+            .with_aggregation_method(crate::types::AggregationMethod::BestOfN)
+            .with_best_of_n_samples(3);
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(ApprovingProvider));
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_aggregation("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        let solutions = coordinator.workspace.get_all_solutions().await;
+        let aggregated: Vec<_> = solutions
+            .iter()
+            .filter(|s| s.phase == crate::types::GenerationPhase::Aggregated)
+            .collect();
+        assert_eq!(solutions.len(), 4);
+        assert_eq!(aggregated.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_mcts_aggregation_keeps_tree() {
+        let config = MarsConfig::default()
+            .with_aggregation(true)
+            .with_aggregation_method(crate::types::AggregationMethod::MonteCarloTreeSearch);
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(ApprovingProvider));
+        let (tx, mut rx) = mpsc::channel(100);
+        assert!(coordinator.mcts_tree().is_none());
+
+        coordinator
+            .phase_aggregation("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        let tree = coordinator.mcts_tree().unwrap();
+        assert_eq!(tree.root, Some(0));
+        assert!(tree.nodes.len() > 1);
+        assert!(tree.to_json().unwrap().contains("conversation_history"));
+
+        let mut simulations = 0;
+        while let Ok(event) = rx.try_recv() {
+            if matches!(event, MarsEvent::SimulationCompleted { .. }) {
+                simulations += 1;
+            }
+        }
+        assert_eq!(simulations, coordinator.config.mcts_num_simulations);
+    }
+
+    /// Value function that scores every MCTS state the same
+    struct FixedValue;
+
+    #[async_trait]
+    impl ValueFunction for FixedValue {
+        async fn score(&self, _state: &crate::mcts::DialogueState) -> Result<f32> {
+            Ok(0.3)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mcts_aggregation_uses_value_function() {
+        let config = MarsConfig::default()
+            .with_aggregation(true)
+            .with_aggregation_method(crate::types::AggregationMethod::MonteCarloTreeSearch);
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(ApprovingProvider))
+            .with_mcts_value_function(Arc::new(FixedValue));
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_aggregation("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        let tree = coordinator.mcts_tree().unwrap();
+        let root = &tree.nodes[0];
+        assert!(root.visits > 0);
+        assert!((root.value / root.visits as f32 - 0.3).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_plan_and_solve_plan_is_never_the_answer() {
+        let config = MarsConfig::default()
+            .with_aggregation(true)
+            .with_aggregation_method(crate::types::AggregationMethod::PlanAndSolve);
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(ApprovingProvider));
+
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+
+        let plan = output
+            .all_solutions
+            .iter()
+            .find(|s| s.phase == crate::types::GenerationPhase::Planned)
+            .unwrap();
+        assert_eq!(plan.parent_ids.len(), 3);
+        assert!(
+            output
+                .all_solutions
+                .iter()
+                .any(|s| s.parent_ids == [plan.id.clone()])
+        );
+        assert_ne!(output.final_solution_id, plan.id);
+        assert_eq!(plan.verification_passes, 0);
+        assert_eq!(output.answer, "4");
+    }
+
+    #[tokio::test]
+    async fn test_verification_passes_accumulate() {
+        let config = MarsConfig::default()
+            .with_num_verifiers(3)
+            .with_consensus_threshold(3)
+            .with_verification_quorum(crate::types::QuorumRule::Majority);
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(ApprovingProvider));
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        let solutions = coordinator.workspace.get_all_solutions().await;
+        assert_eq!(solutions.len(), 3);
+        for solution in solutions {
+            assert_eq!(solution.verification_passes, 3);
+            assert!(solution.is_verified);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verification_failures_block_all_quorum() {
+        let mut coordinator = mock_coordinator(MarsConfig::default());
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        for solution in coordinator.workspace.get_all_solutions().await {
+            assert_eq!(solution.verification_failures, 2);
+            assert!(!solution.is_verified);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verification_routed_to_phase_provider() {
+        // Solve with the mock model, verify with a different (approving) one
+        let mut coordinator = mock_coordinator(MarsConfig::default())
+            .with_phase_provider(MarsPhase::Verification, Arc::new(ApprovingProvider));
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            coordinator
+                .provider_for(MarsPhase::Verification)
+                .provider_name(),
+            "approving"
+        );
+        assert_eq!(
+            coordinator
+                .provider_for(MarsPhase::Exploration)
+                .provider_name(),
+            "mock"
+        );
+        let verified = coordinator.workspace.get_verified_solutions().await;
+        assert_eq!(verified.len(), 3);
+    }
+
+    #[test]
+    fn test_phase_providers_from_routing_config() {
+        use crate::provider_config::{ProviderRoutingConfig, ProviderSpec};
+
+        let routing = ProviderRoutingConfig::single(ProviderSpec::new("openai", "gpt-4o-mini"))
+            .with_phase_provider(
+                MarsPhase::Verification,
+                ProviderSpec::new("anthropic", "claude-3-5-sonnet"),
+            );
+        let coordinator = mock_coordinator(MarsConfig::default().with_provider_routing(routing));
+
+        let verifier = coordinator.provider_for(MarsPhase::Verification);
+        assert_eq!(verifier.provider_name(), "anthropic");
+        assert_eq!(verifier.model_name(), "claude-3-5-sonnet");
+        assert_eq!(
+            coordinator
+                .provider_for(MarsPhase::Improvement)
+                .provider_name(),
+            "mock"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_self_verification_excluded() {
+        let config = MarsConfig::default().with_self_verification_excluded(true);
+        let (tx, _rx) = mpsc::channel(100);
+
+        // Only the generating model is available, so nothing may be verified
+        let mut coordinator = mock_coordinator(config.clone());
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        for solution in coordinator.workspace.get_all_solutions().await {
+            assert_eq!(solution.model, "mock/mock-model");
+            assert_eq!(
+                solution.verification_passes + solution.verification_failures,
+                0
+            );
+        }
+
+        // An independent verifier is picked even when the generating model is preferred
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(ApprovingProvider))
+            .with_phase_provider(MarsPhase::Exploration, Arc::new(MockProvider))
+            .with_phase_provider(MarsPhase::Verification, Arc::new(MockProvider));
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        assert_eq!(
+            coordinator.workspace.get_verified_solutions().await.len(),
+            3
+        );
+    }
+
+    /// Mock judge that prefers whichever solution answers "4" when comparing pairs
+    struct PairwiseJudge;
+
+    #[async_trait]
+    impl LLMProvider for PairwiseJudge {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            let (a, b) = prompt.split_once("Solution B:").unwrap_or((prompt, ""));
+            let winner = match (a.contains("Answer: 4"), b.contains("Answer: 4")) {
+                (true, false) => "A",
+                (false, true) => "B",
+                _ => "TIE",
+            };
+            Ok(format!("WINNER: {winner}").into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "judge"
+        }
+
+        fn model_name(&self) -> &str {
+            "judge-model"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pairwise_ranking_drives_selection() {
+        let config =
+            MarsConfig::default().with_verification_mode(crate::types::VerificationMode::Pairwise);
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(PairwiseJudge));
+        let (tx, _rx) = mpsc::channel(100);
+
+        for answer in ["5", "4", "3"] {
+            let solution = crate::types::Solution::new(
+                "agent".to_string(),
+                "reasoning".to_string(),
+                answer.to_string(),
+                0.5,
+                10,
+            );
+            coordinator.workspace.add_solution(solution).await;
+        }
+
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        let output = coordinator
+            .phase_synthesis("What is 2+2?", None, &tx)
+            .await
+            .unwrap();
+
+        assert_eq!(output.answer, "4");
+        assert!(matches!(
+            output.selection_method,
+            SelectionMethod::PairwiseRanking
+        ));
+        assert!(output.all_solutions.iter().all(|s| s.elo_rating.is_some()));
+    }
+
+    /// Approving provider that counts how many verifications it performs
+    #[derive(Default)]
+    struct CountingProvider {
+        verifications: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLMProvider for CountingProvider {
+        async fn complete(
+            &self,
+            prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if system_prompt == Some(crate::prompts::VERIFICATION_SYSTEM_PROMPT) {
+                self.verifications
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            ApprovingProvider.complete(prompt, system_prompt).await
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "counting"
+        }
+
+        fn model_name(&self) -> &str {
+            "counting-model"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verification_cache_skips_duplicates() {
+        let provider = Arc::new(CountingProvider::default());
+        let config = MarsConfig::default().with_verification_cache(true);
+        let mut coordinator = MarsCoordinator::with_provider(config, provider.clone());
+        let (tx, _rx) = mpsc::channel(100);
+
+        // All three agents produce identical content, so only the first is verified
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            provider
+                .verifications
+                .load(std::sync::atomic::Ordering::SeqCst),
+            2
+        );
+        let solutions = coordinator.workspace.get_all_solutions().await;
+        assert!(
+            solutions
+                .iter()
+                .all(|s| s.is_verified && s.verification_passes == 2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_workspace_store() {
+        let store = Arc::new(crate::workspace_store::MemoryWorkspaceStore::new());
+        let mut coordinator =
+            MarsCoordinator::with_provider(MarsConfig::default(), Arc::new(MockProvider))
+                .with_workspace_store(store.clone());
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        assert_eq!(store.count().await.unwrap(), 3);
+    }
+
+    /// Store whose updates always fail
+    struct ReadOnlyStore(crate::workspace_store::MemoryWorkspaceStore);
+
+    #[async_trait]
+    impl WorkspaceStore for ReadOnlyStore {
+        async fn add(&self, solution: crate::types::Solution) -> Result<()> {
+            self.0.add(solution).await
+        }
+
+        async fn get(&self, id: &str) -> Result<Option<crate::types::Solution>> {
+            self.0.get(id).await
+        }
+
+        async fn update(&self, _solution: crate::types::Solution) -> Result<()> {
+            Err(crate::MarsError::StorageError("read-only".to_string()))
+        }
+
+        async fn remove(&self, id: &str) -> Result<()> {
+            self.0.remove(id).await
+        }
+
+        async fn all(&self) -> Result<Vec<crate::types::Solution>> {
+            self.0.all().await
+        }
+
+        async fn add_feedback(&self, solution_id: &str, feedback: String) -> Result<()> {
+            self.0.add_feedback(solution_id, feedback).await
+        }
+
+        async fn feedback(&self, solution_id: &str) -> Result<Vec<String>> {
+            self.0.feedback(solution_id).await
+        }
+
+        async fn clear(&self) -> Result<()> {
+            self.0.clear().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unsaved_verification_is_reported() {
+        let store = Arc::new(ReadOnlyStore(
+            crate::workspace_store::MemoryWorkspaceStore::new(),
+        ));
+        let mut coordinator =
+            MarsCoordinator::with_provider(MarsConfig::default(), Arc::new(MockProvider))
+                .with_workspace_store(store);
+        let (tx, mut rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        drop(tx);
+
+        let mut errors = Vec::new();
+        while let Some(event) = rx.recv().await {
+            if let MarsEvent::Error { message } = event {
+                errors.push(message);
+            }
+        }
+        assert!(
+            errors.iter().any(
+                |message| message.starts_with("Could not record the verification")
+                    && message.contains("read-only")
+            ),
+            "{errors:?}"
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_sqlite_workspace_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("workspace.db");
+        let config = MarsConfig::default()
+            .with_workspace_backend(crate::types::WorkspaceBackend::Sqlite(path.clone()));
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(MockProvider));
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        let reopened = crate::workspace_store::SqliteWorkspaceStore::open(&path);
+        assert_eq!(reopened.count().await.unwrap(), 3);
+    }
+
+    #[cfg(feature = "sled")]
+    #[tokio::test]
+    async fn test_unopenable_workspace_backend_fails_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("workspace.sled");
+        let _held = crate::workspace_store::SledWorkspaceStore::open(&path).unwrap();
+        let config = MarsConfig::default()
+            .with_workspace_backend(crate::types::WorkspaceBackend::Sled(path));
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(MockProvider));
+
+        assert_eq!(coordinator.config_errors()[0].field, "workspace_backend");
+        assert!(matches!(
+            coordinator.run("What is 2+2?").await,
+            Err(crate::MarsError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_strategies_persist_across_coordinators() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("strategies.json");
+        let config = MarsConfig::default()
+            .with_strategy_store_path(&path)
+            .with_strategy_namespace("math");
+        let (tx, _rx) = mpsc::channel(100);
+
+        let mut first = MarsCoordinator::with_provider(config.clone(), Arc::new(MockProvider));
+        first.strategy_network.register_strategy(
+            "agent1".to_string(),
+            "Check small cases first".to_string(),
+            "Enumerate n = 1, 2, 3".to_string(),
+        );
+        first.phase_strategy_network(&tx).await.unwrap();
+
+        let second = MarsCoordinator::with_provider(config, Arc::new(MockProvider));
+        let loaded = second.strategy_network.get_all_strategies();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].description, "Check small cases first");
+
+        let other = MarsCoordinator::with_provider(
+            MarsConfig::default()
+                .with_strategy_store_path(&path)
+                .with_strategy_namespace("code"),
+            Arc::new(MockProvider),
+        );
+        assert_eq!(other.strategy_network.count_strategies(), 0);
+    }
+
+    /// Mock provider that records every prompt and system prompt it receives
+    #[derive(Default)]
+    struct PromptRecorder {
+        prompts: std::sync::Mutex<Vec<String>>,
+        system_prompts: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl LLMProvider for PromptRecorder {
+        async fn complete(
+            &self,
+            prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            self.prompts.lock().unwrap().push(prompt.to_string());
+            if let Some(system_prompt) = system_prompt {
+                self.system_prompts
+                    .lock()
+                    .unwrap()
+                    .push(system_prompt.to_string());
+            }
+            Ok("<think>2 + 2 = 4</think>\n4".into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "prompt-recorder"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_improvement_prompts_include_strategies() {
+        let provider = Arc::new(PromptRecorder::default());
+        let mut coordinator =
+            MarsCoordinator::with_provider(MarsConfig::default(), provider.clone());
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator.strategy_network.register_strategy(
+            "agent1".to_string(),
+            "Check small cases first".to_string(),
+            "Enumerate n = 1, 2, 3".to_string(),
+        );
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        assert!(
+            provider
+                .prompts
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|p| !p.contains("Check small cases first"))
+        );
+
+        assert!(
+            coordinator
+                .phase_improvement("What is 2+2?", 0, &tx)
+                .await
+                .unwrap()
+        );
+        let prompts = provider.prompts.lock().unwrap();
+        let improvement: Vec<_> = prompts
+            .iter()
+            .filter(|p| p.starts_with(crate::prompts::IMPROVEMENT_PROMPT))
+            .collect();
+        assert!(!improvement.is_empty());
+        assert!(
+            improvement
+                .iter()
+                .all(|p| p.contains("Check small cases first"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_prompts_reach_provider() {
+        let provider = Arc::new(PromptRecorder::default());
+        let config = MarsConfig::default()
+            .with_reasoning_prompt("Review this contract: {query}")
+            .with_improvement_prompt("Revise {answer} given: {feedback}");
+        let mut coordinator = MarsCoordinator::with_provider(config, provider.clone());
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("Clause 4", &tx)
+            .await
+            .unwrap();
+        assert!(
+            coordinator
+                .phase_improvement("Clause 4", 0, &tx)
+                .await
+                .unwrap()
+        );
+
+        let prompts = provider.prompts.lock().unwrap();
+        assert_eq!(
+            prompts
+                .iter()
+                .filter(|p| *p == "Review this contract: Clause 4")
+                .count(),
+            3
+        );
+        assert!(prompts.iter().any(|p| p.starts_with("Revise 4 given: ")));
+        assert!(
+            prompts
+                .iter()
+                .all(|p| !p.starts_with(crate::prompts::MARS_REASONING_PROMPT))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_variables_fill_prompt_templates() {
+        let provider = Arc::new(PromptRecorder::default());
+        let config = MarsConfig::default()
+            .with_max_iterations(1)
+            .with_prompt_variable("language", "English")
+            .with_reasoning_prompt("Answer in {{ language }}: {query}");
+        let mut coordinator = MarsCoordinator::with_provider(config, provider.clone());
+
+        let options = RunOptions::new().with_variable("language", "German");
+        coordinator
+            .run_with_options("What is 2+2?", options)
+            .await
+            .unwrap();
+        coordinator.run("What is 2+2?").await.unwrap();
+        {
+            let prompts = provider.prompts.lock().unwrap();
+            let count = |prompt: &str| prompts.iter().filter(|p| *p == prompt).count();
+            assert_eq!(count("Answer in German: What is 2+2?"), 3);
+            assert_eq!(count("Answer in English: What is 2+2?"), 3);
+        }
+
+        let options = RunOptions::new().with_variable("tone", "formal");
+        let err = coordinator
+            .run_with_options("What is 2+2?", options)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, crate::MarsError::InvalidConfiguration(_)));
+    }
+
+    #[tokio::test]
+    async fn test_template_errors_fail_before_the_debug_dir_is_created() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = MarsConfig::default()
+            .with_max_iterations(1)
+            .with_debug_dir(dir.path())
+            .with_reasoning_prompt("Answer: {query}");
+        let mut coordinator = mock_coordinator(config);
+
+        let options = RunOptions::new().with_variable("tone", "formal");
+        let err = coordinator
+            .run_with_options("What is 2+2?", options)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, crate::MarsError::InvalidConfiguration(_)));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_synthesis_uses_the_configured_system_prompt() {
+        let provider = Arc::new(PromptRecorder::default());
+        let config = MarsConfig::default().with_system_prompt("Answer tersely.");
+        let coordinator = MarsCoordinator::with_provider(config, Arc::new(MockProvider))
+            .with_phase_provider(MarsPhase::Synthesis, provider.clone());
+        let solution = crate::types::Solution::new(
+            "agent".to_string(),
+            "r".to_string(),
+            "4".to_string(),
+            0.5,
+            10,
+        );
+
+        coordinator
+            .synthesize_with_provider("What is 2+2?", &[solution])
+            .await
+            .unwrap();
+        assert_eq!(
+            *provider.system_prompts.lock().unwrap(),
+            ["Answer tersely."]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response_language_reaches_every_phase() {
+        let provider = Arc::new(PromptRecorder::default());
+        let config = MarsConfig::default()
+            .with_max_iterations(1)
+            .with_response_language("German");
+        let mut coordinator = MarsCoordinator::with_provider(config, provider.clone());
+
+        let options = RunOptions::new().with_selection(SelectionMethod::Synthesized);
+        coordinator
+            .run_with_options("What is 2+2?", options)
+            .await
+            .unwrap();
+        assert!(coordinator.config.prompts.system.is_none());
+
+        let system_prompts = provider.system_prompts.lock().unwrap();
+        assert!(
+            system_prompts
+                .iter()
+                .any(|p| p.starts_with(crate::prompts::VERIFICATION_SYSTEM_PROMPT))
+        );
+        assert!(system_prompts.iter().all(|p| p.contains("German")));
+        let synthesis = crate::prompts::language_instruction(
+            crate::prompts::SYNTHESIS_LANGUAGE_PROMPT,
+            "German",
+        );
+        let prompts = provider.prompts.lock().unwrap();
+        assert!(prompts.iter().any(|p| p.contains(&synthesis)));
+    }
+
+    #[tokio::test]
+    async fn test_answer_type_formats_prompts_and_types_output() {
+        let provider = Arc::new(PromptRecorder::default());
+        let config = MarsConfig::default()
+            .with_max_iterations(1)
+            .with_answer_type(crate::types::AnswerType::Numeric);
+        let mut coordinator = MarsCoordinator::with_provider(config, provider.clone());
+
+        let options = RunOptions::new().with_selection(SelectionMethod::Synthesized);
+        let output = coordinator
+            .run_with_options("What is 2+2?", options)
+            .await
+            .unwrap();
+        assert_eq!(
+            output.typed_answer,
+            Some(crate::types::TypedAnswer::Numeric { value: 4.0 })
+        );
+        assert!(coordinator.config.prompts.system.is_none());
+        assert!(
+            provider
+                .system_prompts
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|p| p.ends_with(crate::prompts::NUMERIC_ANSWER_PROMPT))
+        );
+        let prompts = provider.prompts.lock().unwrap();
+        assert!(
+            prompts
+                .iter()
+                .any(|p| p.contains(crate::prompts::NUMERIC_ANSWER_PROMPT)
+                    && p.contains("Problem: What is 2+2?"))
+        );
+        drop(prompts);
+
+        // An answer of the wrong type leaves the output untyped
+        let config =
+            MarsConfig::default().with_answer_type(crate::types::AnswerType::MultipleChoice);
+        let mut coordinator = MarsCoordinator::with_provider(config, provider);
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+        assert_eq!(output.answer, "4");
+        assert!(output.typed_answer.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_exploration_strategies_are_opt_in() {
+        let provider = Arc::new(PromptRecorder::default());
+        let config = MarsConfig::default().with_strategies_in_exploration(true);
+        let mut coordinator = MarsCoordinator::with_provider(config, provider.clone());
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator.strategy_network.register_strategy(
+            "agent1".to_string(),
+            "Check small cases first".to_string(),
+            "Enumerate n = 1, 2, 3".to_string(),
+        );
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        let prompts = provider.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 3);
+        assert!(
+            prompts
+                .iter()
+                .all(|p| p.contains("Check small cases first"))
+        );
+
+        let graph = coordinator.strategy_network().export_graph();
+        let usages = graph
+            .edges
+            .iter()
+            .filter(|e| e.kind == crate::strategy::StrategyEdgeKind::UsedBy)
+            .count();
+        assert_eq!(usages, 3);
+    }
+
+    /// Mock adversary that always finds a counterexample
+    struct AdversaryProvider;
+
+    #[async_trait]
+    impl LLMProvider for AdversaryProvider {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if system_prompt == Some(crate::prompts::ADVERSARIAL_VERIFICATION_PROMPT) {
+                Ok("COUNTEREXAMPLE: x = 0\nPLAUSIBILITY: 0.8\nREASON: Divides by x.".into())
+            } else {
+                Ok("<think>2 + 2 = 4</think>\n4".into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "adversary"
+        }
+
+        fn model_name(&self) -> &str {
+            "adversary-model"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_adversarial_verification_refutes() {
+        let config = MarsConfig::default()
+            .with_verification_mode(crate::types::VerificationMode::Adversarial);
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(AdversaryProvider));
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        assert!(
+            coordinator
+                .workspace
+                .get_verified_solutions()
+                .await
+                .is_empty()
+        );
+        let solution = &coordinator.workspace.get_all_solutions().await[0];
+        let feedback = coordinator.workspace.get_feedback(&solution.id).await;
+        assert!(feedback[0].contains("Counterexample: x = 0"));
+    }
+
+    /// Mock provider that writes tests for a doubling program and answers with sh code
+    struct TestWritingProvider;
+
+    #[async_trait]
+    impl LLMProvider for TestWritingProvider {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if system_prompt == Some(crate::prompts::TEST_GENERATION_PROMPT) {
+                Ok(concat!(
+                    r#"[{"input": "2", "expected_output": "4"},"#,
+                    r#" {"input": "7", "expected_output": "14"}]"#
+                )
+                .into())
+            } else {
+                Ok("<think>Double the input</think>\n```sh\nread x\necho $((x * 2))\n```".into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "test-writer"
+        }
+
+        fn model_name(&self) -> &str {
+            "test-writer-model"
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_generated_tests_verification() {
+        let config = MarsConfig::default()
+            .with_verification_mode(crate::types::VerificationMode::GeneratedTests)
+            .with_code_execution_sandbox(crate::code_verifier::ExecutionSandbox::Unsandboxed);
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(TestWritingProvider));
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("Double a number", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("Double a number", &tx)
+            .await
+            .unwrap();
+
+        let solutions = coordinator.workspace.get_all_solutions().await;
+        assert_eq!(solutions.len(), 3);
+        assert!(
+            solutions
+                .iter()
+                .all(|s| s.is_verified && s.verification_score == 1.0)
+        );
+    }
+
+    /// Custom verifier that accepts every solution answering "4"
+    struct AnswerChecker;
+
+    #[async_trait]
+    impl SolutionVerifier for AnswerChecker {
+        async fn verify(
+            &self,
+            solution: &crate::types::Solution,
+            _query: &str,
+        ) -> Result<crate::types::VerificationResult> {
+            let is_correct = solution.answer.trim() == "4";
+            Ok(crate::types::VerificationResult::new(
+                solution.id.clone(),
+                is_correct,
+                if is_correct { 1.0 } else { 0.0 },
+                self.name().to_string(),
+            ))
+        }
+
+        fn name(&self) -> &str {
+            "answer-checker"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_verifier_augments_builtin() {
+        let mut coordinator =
+            mock_coordinator(MarsConfig::default()).with_solution_verifier(Arc::new(AnswerChecker));
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        for solution in coordinator.workspace.get_all_solutions().await {
+            // The mock's built-in verdicts fail; the custom check passes
+            assert_eq!(solution.verification_failures, 2);
+            assert_eq!(solution.verification_passes, 1);
+            assert!(!solution.is_verified);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_verifier_replaces_builtin() {
+        let config = MarsConfig::default().with_builtin_verifier_replaced(true);
+        let mut coordinator =
+            mock_coordinator(config).with_solution_verifier(Arc::new(AnswerChecker));
+        let (tx, _rx) = mpsc::channel(100);
+
+        coordinator
+            .phase_exploration("What is 2+2?", &tx)
+            .await
+            .unwrap();
+        coordinator
+            .phase_verification("What is 2+2?", &tx)
+            .await
+            .unwrap();
+
+        for solution in coordinator.workspace.get_all_solutions().await {
+            assert_eq!(solution.verification_failures, 0);
+            assert_eq!(solution.verification_passes, 1);
+            assert!(solution.is_verified);
+        }
+    }
+
+    /// Mock provider whose improvements are re-checked; verifiers only
+    /// approve them when `improvements_help` is set
+    struct ImprovingProvider {
+        improvements_help: bool,
+    }
+
+    #[async_trait]
+    impl LLMProvider for ImprovingProvider {
+        async fn complete(
+            &self,
+            prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if system_prompt == Some(crate::prompts::VERIFICATION_SYSTEM_PROMPT) {
+                if self.improvements_help && prompt.contains("re-checked") {
+                    Ok("RESULT: CORRECT\nSCORE: 0.9\nFEEDBACK: Fixed.".into())
+                } else {
+                    Ok("RESULT: INCORRECT\nSCORE: 0.4\nFEEDBACK: Unclear.".into())
+                }
+            } else if prompt.starts_with(crate::prompts::IMPROVEMENT_PROMPT) {
+                Ok("<think>re-checked: 2 + 2 = 4</think>\n4".into())
+            } else {
+                Ok("<think>2 + 2 = 4</think>\n4".into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "improving-model"
+        }
+    }
+
+    fn improvement_coordinator(improvements_help: bool) -> MarsCoordinator {
+        let config = MarsConfig::default()
+            .with_num_verifiers(1)
+            .with_improvement_rollback(true);
+        MarsCoordinator::with_provider(config, Arc::new(ImprovingProvider { improvements_help }))
+    }
+
+    #[tokio::test]
+    async fn test_unhelpful_improvement_rolled_back() {
+        let mut coordinator = improvement_coordinator(false);
+        let (tx, mut rx) = mpsc::channel(100);
+        let query = "What is 2+2?";
+
+        coordinator.phase_exploration(query, &tx).await.unwrap();
+        coordinator.phase_verification(query, &tx).await.unwrap();
+        let snapshot = coordinator.workspace.snapshot().await.unwrap();
+        assert!(coordinator.phase_improvement(query, 0, &tx).await.unwrap());
+        assert_eq!(coordinator.workspace.count_solutions().await, 6);
+
+        let rolled_back = coordinator
+            .rollback_unhelpful_improvements(query, 0, snapshot, &tx)
+            .await
+            .unwrap();
+        assert!(rolled_back);
+        assert_eq!(coordinator.workspace.count_solutions().await, 3);
+
+        drop(tx);
+        let mut discarded = Vec::new();
+        while let Some(event) = rx.recv().await {
+            if let MarsEvent::ImprovementRolledBack {
+                iteration,
+                solution_ids,
+            } = event
+            {
+                assert_eq!(iteration, 0);
+                discarded = solution_ids;
+            }
+        }
+        assert_eq!(discarded.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_helpful_improvement_kept() {
+        let mut coordinator = improvement_coordinator(true);
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+
+        let improved: Vec<_> = output
+            .all_solutions
+            .iter()
+            .filter(|s| s.phase == crate::types::GenerationPhase::Improved)
+            .collect();
+        assert_eq!(improved.len(), 3);
+        assert!(improved.iter().all(|s| s.verification_score > 0.4));
     }
 }
@@ -0,0 +1,555 @@
+//! Dollar cost of provider calls, with an optional per-run budget.
+//!
+//! A [`PricingTable`] holds per-1k-token prompt and completion prices by
+//! model, with built-in prices for well-known hosted models that
+//! `MarsConfig::model_pricing` can override or extend. The coordinator wraps
+//! each phase's provider in a [`CostTrackingProvider`], which prices every
+//! call's usage into a shared [`CostTracker`]; the run's [`CostSummary`] ends
+//! up in `MarsOutput::cost`. Once a configured budget is spent, further calls
+//! fail with [`MarsError::BudgetExceeded`]; the coordinator also projects each
+//! phase's cost from [`CostTracker::average_call_usd`] and skips to synthesis
+//! when [`CostTracker::can_afford`] says the phase would overrun the budget.
+//!
+//! Usage the provider did not report is estimated at four characters per
+//! token. Models without a price count as free and are listed in
+//! [`CostSummary::unpriced_models`].
+
+use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+use crate::provider_config::MarsPhase;
+use crate::structured::ResponseFormat;
+use crate::tools::{ToolAwareResponse, ToolSpec};
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Providers that run models locally, at no per-token cost
+const LOCAL_PROVIDERS: &[&str] = &["ollama", "vllm", "lmstudio", "llamacpp", "local"];
+
+/// Price of a model's tokens in dollars per 1,000
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ModelPricing {
+    /// Dollars per 1k prompt (input) tokens
+    pub prompt_per_1k: f64,
+    /// Dollars per 1k completion (output) tokens
+    pub completion_per_1k: f64,
+}
+
+impl ModelPricing {
+    /// Pricing from per-1k-token prompt and completion prices
+    pub fn new(prompt_per_1k: f64, completion_per_1k: f64) -> Self {
+        Self {
+            prompt_per_1k,
+            completion_per_1k,
+        }
+    }
+
+    /// Dollar cost of a call's usage
+    pub fn cost(&self, prompt_tokens: usize, completion_tokens: usize) -> f64 {
+        (prompt_tokens as f64 * self.prompt_per_1k
+            + completion_tokens as f64 * self.completion_per_1k)
+            / 1000.0
+    }
+}
+
+/// Prices by model name (`gpt-4o`) or `provider/model` id
+///
+/// The default table covers well-known hosted models. Lookups fall back to
+/// the longest listed name the model starts with, so dated snapshots such as
+/// `gpt-4o-2024-08-06` get their family's price.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(transparent)]
+pub struct PricingTable {
+    prices: HashMap<String, ModelPricing>,
+}
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        let prices = [
+            ("gpt-4o", 0.0025, 0.01),
+            ("gpt-4o-mini", 0.00015, 0.0006),
+            ("gpt-4.1", 0.002, 0.008),
+            ("gpt-4.1-mini", 0.0004, 0.0016),
+            ("gpt-4.1-nano", 0.0001, 0.0004),
+            ("gpt-4-turbo", 0.01, 0.03),
+            ("gpt-3.5-turbo", 0.0005, 0.0015),
+            ("o1", 0.015, 0.06),
+            ("o1-mini", 0.0011, 0.0044),
+            ("o3-mini", 0.0011, 0.0044),
+            ("claude-3-5-sonnet", 0.003, 0.015),
+            ("claude-3-7-sonnet", 0.003, 0.015),
+            ("claude-sonnet-4", 0.003, 0.015),
+            ("claude-3-5-haiku", 0.0008, 0.004),
+            ("claude-3-haiku", 0.00025, 0.00125),
+            ("claude-3-opus", 0.015, 0.075),
+            ("claude-opus-4", 0.015, 0.075),
+            ("gemini-1.5-pro", 0.00125, 0.005),
+            ("gemini-1.5-flash", 0.000075, 0.0003),
+            ("gemini-2.0-flash", 0.0001, 0.0004),
+            ("gemini-2.5-pro", 0.00125, 0.01),
+            ("gemini-2.5-flash", 0.0003, 0.0025),
+            ("deepseek-chat", 0.00027, 0.0011),
+            ("deepseek-reasoner", 0.00055, 0.00219),
+        ];
+        Self {
+            prices: prices
+                .into_iter()
+                .map(|(model, prompt, completion)| {
+                    (model.to_string(), ModelPricing::new(prompt, completion))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl PricingTable {
+    /// Table with no prices
+    pub fn empty() -> Self {
+        Self {
+            prices: HashMap::new(),
+        }
+    }
+
+    /// Set the price of a model name or `provider/model` id
+    pub fn with_price(mut self, model: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.prices.insert(model.into(), pricing);
+        self
+    }
+
+    /// Add or replace prices
+    pub fn with_overrides(mut self, overrides: &HashMap<String, ModelPricing>) -> Self {
+        self.prices.extend(
+            overrides
+                .iter()
+                .map(|(model, pricing)| (model.clone(), *pricing)),
+        );
+        self
+    }
+
+    /// Price of a provider's model, if known
+    ///
+    /// Exact `provider/model` entries win over model names; models of local
+    /// providers without an entry are free.
+    pub fn pricing(&self, provider: &str, model: &str) -> Option<ModelPricing> {
+        let model_id = format!("{provider}/{model}");
+        for name in [model_id.as_str(), model] {
+            if let Some(pricing) = self.prices.get(name) {
+                return Some(*pricing);
+            }
+        }
+        let by_prefix = [model_id.as_str(), model].into_iter().find_map(|name| {
+            self.prices
+                .iter()
+                .filter(|(listed, _)| name.starts_with(listed.as_str()))
+                .max_by_key(|(listed, _)| listed.len())
+                .map(|(_, pricing)| *pricing)
+        });
+        by_prefix.or_else(|| {
+            LOCAL_PROVIDERS
+                .contains(&provider.to_ascii_lowercase().as_str())
+                .then(ModelPricing::default)
+        })
+    }
+}
+
+/// Dollars spent during a run
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct CostSummary {
+    /// Total dollars across all calls
+    pub total_usd: f64,
+    /// Dollars by the phase that made the calls
+    #[serde(default)]
+    pub by_phase: HashMap<MarsPhase, f64>,
+    /// Dollars by `provider/model` id
+    #[serde(default)]
+    pub by_provider: HashMap<String, f64>,
+    /// Models called without a known price (counted as free)
+    #[serde(default)]
+    pub unpriced_models: Vec<String>,
+    /// Number of calls recorded
+    #[serde(default)]
+    pub calls: usize,
+}
+
+/// Accumulates the cost of a run's calls and enforces its budget
+#[derive(Debug)]
+pub struct CostTracker {
+    pricing: PricingTable,
+    /// Dollars the run may spend, if limited
+    budget_usd: Option<f64>,
+    summary: Mutex<CostSummary>,
+}
+
+impl CostTracker {
+    /// Tracker pricing calls with `pricing`, with no budget
+    pub fn new(pricing: PricingTable) -> Self {
+        Self {
+            pricing,
+            budget_usd: None,
+            summary: Mutex::new(CostSummary::default()),
+        }
+    }
+
+    /// Fail calls once `budget_usd` dollars have been spent
+    pub fn with_budget(mut self, budget_usd: f64) -> Self {
+        self.budget_usd = Some(budget_usd);
+        self
+    }
+
+    /// Tracker for a run with `config`'s price overrides and budget
+    pub fn from_config(config: &crate::config::MarsConfig) -> Self {
+        let tracker = Self::new(PricingTable::default().with_overrides(&config.model_pricing));
+        match config.max_cost_usd {
+            Some(budget) => tracker.with_budget(budget),
+            None => tracker,
+        }
+    }
+
+    /// Record a call's usage and return its cost
+    pub fn record(
+        &self,
+        phase: MarsPhase,
+        provider: &str,
+        model: &str,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+    ) -> f64 {
+        let model_id = format!("{provider}/{model}");
+        let pricing = self.pricing.pricing(provider, model);
+        let cost = pricing.map_or(0.0, |pricing| {
+            pricing.cost(prompt_tokens, completion_tokens)
+        });
+
+        let mut summary = self.summary.lock().unwrap_or_else(PoisonError::into_inner);
+        summary.total_usd += cost;
+        summary.calls += 1;
+        *summary.by_phase.entry(phase).or_default() += cost;
+        *summary.by_provider.entry(model_id.clone()).or_default() += cost;
+        if pricing.is_none() && !summary.unpriced_models.contains(&model_id) {
+            summary.unpriced_models.push(model_id);
+        }
+        cost
+    }
+
+    /// Cost of a call's usage at this tracker's prices, without recording it
+    ///
+    /// `None` for models without a price.
+    pub fn price(
+        &self,
+        provider: &str,
+        model: &str,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+    ) -> Option<f64> {
+        self.pricing
+            .pricing(provider, model)
+            .map(|pricing| pricing.cost(prompt_tokens, completion_tokens))
+    }
+
+    /// Dollars spent so far
+    pub fn total_usd(&self) -> f64 {
+        self.summary
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .total_usd
+    }
+
+    /// Average cost of the calls so far (zero before the first call)
+    pub fn average_call_usd(&self) -> f64 {
+        let summary = self.summary.lock().unwrap_or_else(PoisonError::into_inner);
+        if summary.calls == 0 {
+            0.0
+        } else {
+            summary.total_usd / summary.calls as f64
+        }
+    }
+
+    /// Whether spending another `projected_usd` dollars stays within the budget
+    pub fn can_afford(&self, projected_usd: f64) -> bool {
+        self.budget_usd.is_none_or(|budget_usd| {
+            let spent_usd = self.total_usd();
+            spent_usd < budget_usd && spent_usd + projected_usd <= budget_usd
+        })
+    }
+
+    /// Everything spent so far
+    pub fn summary(&self) -> CostSummary {
+        self.summary
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Error if the budget has been spent
+    ///
+    /// Calls already in flight when the budget runs out still complete, so a
+    /// run can overshoot it by their cost.
+    pub fn check_budget(&self) -> Result<()> {
+        match self.budget_usd {
+            Some(budget_usd) if self.total_usd() >= budget_usd => Err(MarsError::BudgetExceeded {
+                spent_usd: self.total_usd(),
+                budget_usd,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Provider wrapper that records the cost of every call made for a phase
+///
+/// Calls are refused once the tracker's budget is spent. Embedding calls are
+/// passed through untracked.
+pub struct CostTrackingProvider {
+    inner: Arc<dyn LLMProvider>,
+    tracker: Arc<CostTracker>,
+    phase: MarsPhase,
+}
+
+impl CostTrackingProvider {
+    /// Record calls to `inner` as made by `phase`
+    pub fn new(inner: Arc<dyn LLMProvider>, tracker: Arc<CostTracker>, phase: MarsPhase) -> Self {
+        Self {
+            inner,
+            tracker,
+            phase,
+        }
+    }
+
+    /// Record a completion of a prompt `prompt_len` bytes long
+    fn record(&self, response: &CompletionResponse, prompt_len: usize) {
+        self.tracker.record(
+            self.phase,
+            self.inner.provider_name(),
+            self.inner.model_name(),
+            response.prompt_tokens_or_estimate(prompt_len),
+            response.completion_tokens_or_estimate(),
+        );
+    }
+}
+
+/// Length in bytes of a prompt and its system prompt
+fn prompt_len(prompt: &str, system_prompt: Option<&str>) -> usize {
+    prompt.len() + system_prompt.map_or(0, str::len)
+}
+
+#[async_trait]
+impl LLMProvider for CostTrackingProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        self.tracker.check_budget()?;
+        let response = self.inner.complete(prompt, system_prompt).await?;
+        self.record(&response, prompt_len(prompt, system_prompt));
+        Ok(response)
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        self.tracker.check_budget()?;
+        let response = self
+            .inner
+            .complete_with_temperature(prompt, system_prompt, temperature)
+            .await?;
+        self.record(&response, prompt_len(prompt, system_prompt));
+        Ok(response)
+    }
+
+    async fn complete_n(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        n: usize,
+        temperature: Option<f32>,
+    ) -> Result<Vec<CompletionResponse>> {
+        self.tracker.check_budget()?;
+        let responses = self
+            .inner
+            .complete_n(prompt, system_prompt, n, temperature)
+            .await?;
+        for response in &responses {
+            self.record(response, prompt_len(prompt, system_prompt));
+        }
+        Ok(responses)
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        self.tracker.check_budget()?;
+        let stream = self.inner.stream(prompt, system_prompt).await?;
+        let tracker = Arc::clone(&self.tracker);
+        let inner = Arc::clone(&self.inner);
+        let phase = self.phase;
+        let prompt_tokens = prompt_len(prompt, system_prompt) / 4;
+        // Streams report no usage, so both sides are estimated once the text is in
+        Ok(stream.on_end(move |outcome| {
+            if let Ok(text) = outcome {
+                tracker.record(
+                    phase,
+                    inner.provider_name(),
+                    inner.model_name(),
+                    prompt_tokens,
+                    text.len() / 4,
+                );
+            }
+        }))
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed(texts).await
+    }
+
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        self.tracker.check_budget()?;
+        let response = self
+            .inner
+            .complete_structured(prompt, system_prompt, format)
+            .await?;
+        self.record(&response, prompt_len(&format.prompt(prompt), system_prompt));
+        Ok(response)
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        self.tracker.check_budget()?;
+        let response = self
+            .inner
+            .complete_with_tools(prompt, system_prompt, tools)
+            .await?;
+        self.record(&response.response, prompt_len(prompt, system_prompt));
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Echoes the prompt back, reporting 1000 prompt and 500 completion tokens
+    struct Echo;
+
+    #[async_trait]
+    impl LLMProvider for Echo {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Ok(CompletionResponse::new(prompt).with_usage(1000, 500))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "openai"
+        }
+
+        fn model_name(&self) -> &str {
+            "gpt-4o-2024-08-06"
+        }
+    }
+
+    fn tracked(tracker: &Arc<CostTracker>, phase: MarsPhase) -> CostTrackingProvider {
+        CostTrackingProvider::new(Arc::new(Echo), Arc::clone(tracker), phase)
+    }
+
+    #[test]
+    fn test_pricing_lookup() {
+        let table = PricingTable::default();
+        let mini = table.pricing("openai", "gpt-4o-mini-2024-07-18").unwrap();
+        assert_eq!(mini, ModelPricing::new(0.00015, 0.0006));
+        assert_eq!(
+            table.pricing("ollama", "llama3.2"),
+            Some(ModelPricing::default())
+        );
+        assert_eq!(table.pricing("acme", "mystery-model"), None);
+
+        // Provider-qualified entries win over model names
+        let table = table.with_price("azure/gpt-4o", ModelPricing::new(0.005, 0.02));
+        assert_eq!(
+            table.pricing("azure", "gpt-4o").unwrap().prompt_per_1k,
+            0.005
+        );
+        assert_eq!(
+            table.pricing("openai", "gpt-4o").unwrap().prompt_per_1k,
+            0.0025
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tracks_cost_by_phase_and_provider() {
+        let tracker = Arc::new(CostTracker::new(PricingTable::default()));
+        tracked(&tracker, MarsPhase::Exploration)
+            .complete("q", None)
+            .await
+            .unwrap();
+        tracked(&tracker, MarsPhase::Verification)
+            .complete_n("q", None, 2, None)
+            .await
+            .unwrap();
+
+        // 1000 prompt tokens at $0.0025/1k plus 500 completion tokens at $0.01/1k
+        let summary = tracker.summary();
+        assert!((summary.total_usd - 3.0 * 0.0075).abs() < 1e-9);
+        assert!((summary.by_phase[&MarsPhase::Exploration] - 0.0075).abs() < 1e-9);
+        assert!((summary.by_phase[&MarsPhase::Verification] - 0.015).abs() < 1e-9);
+        assert!((summary.by_provider["openai/gpt-4o-2024-08-06"] - 0.0225).abs() < 1e-9);
+        assert!(summary.unpriced_models.is_empty());
+        let json = serde_json::to_string(&summary).unwrap();
+        assert_eq!(serde_json::from_str::<CostSummary>(&json).unwrap(), summary);
+    }
+
+    #[tokio::test]
+    async fn test_budget_stops_calls() {
+        let tracker = Arc::new(CostTracker::new(PricingTable::default()).with_budget(0.01));
+        let provider = tracked(&tracker, MarsPhase::Exploration);
+        provider.complete("q", None).await.unwrap();
+        provider.complete("q", None).await.unwrap();
+
+        let err = provider.complete("q", None).await.unwrap_err();
+        assert!(matches!(err, MarsError::BudgetExceeded { .. }));
+        assert!(tracker.check_budget().is_err());
+    }
+
+    #[test]
+    fn test_projected_spend_against_budget() {
+        let tracker = CostTracker::new(PricingTable::default()).with_budget(0.05);
+        assert_eq!(tracker.average_call_usd(), 0.0);
+        assert!(tracker.can_afford(0.05));
+        assert!(!tracker.can_afford(0.06));
+
+        tracker.record(MarsPhase::Exploration, "openai", "gpt-4o", 1000, 500);
+        tracker.record(MarsPhase::Exploration, "ollama", "llama3", 1000, 500);
+        assert_eq!(tracker.summary().calls, 2);
+        let average = tracker.average_call_usd();
+        assert!((average - tracker.total_usd() / 2.0).abs() < 1e-12);
+        assert!(tracker.can_afford(average * 2.0));
+        assert!(!tracker.can_afford(0.05));
+        assert!(CostTracker::new(PricingTable::default()).can_afford(f64::MAX));
+    }
+}
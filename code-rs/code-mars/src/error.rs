@@ -1,5 +1,6 @@
 /// Error types for MARS operations.
 use code_core::error::CodexErr;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Result type for MARS operations
@@ -49,6 +50,110 @@ pub enum MarsError {
 
     #[error("Coordinator error: {0}")]
     CoordinatorError(String),
+
+    /// A persistent storage backend failed
+    #[error("Storage error: {0}")]
+    StorageError(String),
+
+    /// A provider call failed in a way that may succeed if retried
+    /// (rate limits, server errors, dropped connections)
+    #[error("Transient provider error: {message}")]
+    TransientError {
+        /// What went wrong
+        message: String,
+        /// How long the provider asked callers to wait, from `Retry-After`
+        retry_after: Option<Duration>,
+    },
+
+    /// The provider rejected the API key (401/403)
+    #[error("Authentication failed: {0}")]
+    Unauthorized(String),
+
+    /// The provider rate limited the request (429); retrying later may succeed
+    #[error("Rate limited: {message}")]
+    RateLimited {
+        /// What went wrong
+        message: String,
+        /// How long the provider asked callers to wait, from `Retry-After`
+        retry_after: Option<Duration>,
+    },
+
+    /// A provider call ran past one of its HTTP timeouts
+    #[error("Timed out ({kind}): {message}")]
+    TimedOut {
+        /// What went wrong
+        message: String,
+        /// Which timeout expired
+        kind: TimeoutKind,
+    },
+
+    /// The run spent its configured cost budget
+    #[error("Cost budget exceeded: spent ${spent_usd:.4} of ${budget_usd:.4}")]
+    BudgetExceeded {
+        /// Dollars spent so far
+        spent_usd: f64,
+        /// Dollars the run was allowed
+        budget_usd: f64,
+    },
+
+    /// A solution update was based on an outdated copy
+    #[error("Solution {id} was modified concurrently (expected version {expected}, found {found})")]
+    VersionConflict {
+        /// ID of the solution
+        id: String,
+        /// Version the update was based on
+        expected: u64,
+        /// Version currently stored
+        found: u64,
+    },
+
+    /// The run was cancelled through its [`crate::RunHandle`]
+    #[error("Run cancelled")]
+    Cancelled,
+}
+
+impl MarsError {
+    /// Whether retrying the failed call may succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::TransientError { .. }
+                | Self::RateLimited { .. }
+                | Self::Timeout(_)
+                | Self::TimedOut { .. }
+        )
+    }
+
+    /// Delay the provider asked for before retrying, if any
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::TransientError { retry_after, .. } | Self::RateLimited { retry_after, .. } => {
+                *retry_after
+            }
+            _ => None,
+        }
+    }
+}
+
+/// HTTP timeout that a [`MarsError::TimedOut`] call ran past
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// No connection was established in time
+    Connect,
+    /// The server went quiet for too long, before or while sending the response
+    Read,
+    /// The whole request took too long
+    Request,
+}
+
+impl std::fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Connect => "connect",
+            Self::Read => "read",
+            Self::Request => "request",
+        })
+    }
 }
 
 // Implement conversion from code_core's CodexErr
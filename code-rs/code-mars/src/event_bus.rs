@@ -0,0 +1,119 @@
+//! Fan-out of run events to any number of subscribers.
+//!
+//! An [`EventBus`] holds one bounded channel per subscriber and publishes
+//! each [`MarsEventRecord`] to all of them, so a TUI, a log shipper and a
+//! metrics collector can each consume the same run at their own pace. Every
+//! subscriber receives every record published after it subscribed, in
+//! order; a full channel applies backpressure rather than dropping records.
+//! Subscribers whose receiver is dropped are removed on the next publish.
+
+use crate::types::MarsEventRecord;
+use std::sync::{Mutex, PoisonError};
+use tokio::sync::mpsc;
+
+/// Publishes run events to every subscriber
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<mpsc::Sender<MarsEventRecord>>>,
+}
+
+impl EventBus {
+    /// Create a bus with no subscribers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Receive every record published from now on, buffering up to
+    /// `capacity` (at least 1)
+    pub fn subscribe(&self, capacity: usize) -> mpsc::Receiver<MarsEventRecord> {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        self.add_sink(sender);
+        receiver
+    }
+
+    /// Send every record published from now on to `sink`
+    pub fn add_sink(&self, sink: mpsc::Sender<MarsEventRecord>) {
+        self.lock().push(sink);
+    }
+
+    /// Number of subscribers, including closed ones not yet removed
+    pub fn subscriber_count(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Send `record` to every subscriber, waiting for room in full channels
+    ///
+    /// Subscribers that have gone away are removed.
+    pub async fn publish(&self, record: MarsEventRecord) {
+        let subscribers = self.lock().clone();
+        if subscribers.is_empty() {
+            return;
+        }
+        let sent = futures::future::join_all(
+            subscribers
+                .iter()
+                .map(|subscriber| subscriber.send(record.clone())),
+        )
+        .await;
+        if sent.iter().any(Result::is_err) {
+            self.lock().retain(|subscriber| !subscriber.is_closed());
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<mpsc::Sender<MarsEventRecord>>> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MarsEvent;
+
+    fn record(sequence: u64) -> MarsEventRecord {
+        MarsEventRecord::new("run-1", sequence, MarsEvent::SynthesisStarted)
+    }
+
+    #[tokio::test]
+    async fn test_every_subscriber_receives_every_record() {
+        let bus = EventBus::new();
+        let mut tui = bus.subscribe(1);
+        let (sink, mut metrics) = mpsc::channel(10);
+        bus.add_sink(sink);
+
+        // The TUI drains concurrently; its one-slot buffer holds up the rest
+        let drained = tokio::spawn(async move {
+            let mut sequences = Vec::new();
+            while let Some(record) = tui.recv().await {
+                sequences.push(record.sequence);
+            }
+            sequences
+        });
+        for sequence in 0..5 {
+            bus.publish(record(sequence)).await;
+        }
+        drop(bus);
+
+        assert_eq!(drained.await.unwrap(), [0, 1, 2, 3, 4]);
+        let mut sequences = Vec::new();
+        while let Some(record) = metrics.recv().await {
+            sequences.push(record.sequence);
+        }
+        assert_eq!(sequences, [0, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_subscribers_are_removed() {
+        let bus = EventBus::new();
+        let mut kept = bus.subscribe(10);
+        let late = bus.subscribe(10);
+        drop(late);
+        assert_eq!(bus.subscriber_count(), 2);
+
+        bus.publish(record(0)).await;
+        assert_eq!(bus.subscriber_count(), 1);
+        assert_eq!(kept.recv().await.unwrap().sequence, 0);
+    }
+}
@@ -0,0 +1,410 @@
+//! Google Gemini provider speaking the Generative Language API.
+//!
+//! Gemini has its own request schema: the conversation is a list of
+//! `contents`, each with a `role` (`user` or `model`) and a list of `parts`;
+//! the system prompt goes in `systemInstruction` and sampling options in
+//! `generationConfig`. Safety settings from the [`ProviderSpec`] are sent as-is
+//! in `safetySettings`, and responses the API blocks become errors rather
+//! than empty answers.
+//!
+//! Select it in the routing config with `ProviderSpec::new("gemini", model)`.
+//!
+//! [`ProviderSpec`]: crate::provider_config::ProviderSpec
+
+use crate::model_router::{
+    CompletionResponse, HttpOptions, HttpProviderBase, HttpTimeouts, LLMProvider, ModelStream,
+    StreamLine, http_json, invalid_response, reported_str, reported_usage, stream_sse_data,
+};
+use crate::structured::ResponseFormat;
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::time::Duration;
+
+/// Public Generative Language API root
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Request timeout used unless [`GeminiProvider::with_timeout`] overrides it
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Finish reasons meaning the response was withheld rather than completed
+const BLOCKED_FINISH_REASONS: &[&str] = &["SAFETY", "BLOCKLIST", "PROHIBITED_CONTENT", "SPII"];
+
+/// Blocking threshold for one harm category, e.g.
+/// `HARM_CATEGORY_DANGEROUS_CONTENT` / `BLOCK_ONLY_HIGH`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SafetySetting {
+    /// Harm category name as the API spells it
+    pub category: String,
+    /// Blocking threshold name as the API spells it
+    pub threshold: String,
+}
+
+impl SafetySetting {
+    /// Create a setting for `category` blocking at `threshold`
+    pub fn new(category: &str, threshold: &str) -> Self {
+        Self {
+            category: category.to_string(),
+            threshold: threshold.to_string(),
+        }
+    }
+}
+
+/// Gemini models through the Generative Language API
+pub struct GeminiProvider {
+    /// Model name, without the `models/` prefix
+    model: String,
+    api_key: String,
+    /// API root, without a trailing slash
+    base_url: String,
+    safety_settings: Vec<SafetySetting>,
+    base: HttpProviderBase,
+}
+
+impl GeminiProvider {
+    /// Create a provider for `model` authenticating with `api_key`
+    pub fn new(model: String, api_key: String) -> Self {
+        let model = model
+            .strip_prefix("models/")
+            .map(str::to_string)
+            .unwrap_or(model);
+        Self {
+            model,
+            api_key,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            safety_settings: Vec::new(),
+            base: HttpProviderBase::new(DEFAULT_REQUEST_TIMEOUT),
+        }
+    }
+
+    /// Create a provider from a provider specification, including its safety settings
+    pub fn from_spec(spec: &crate::provider_config::ProviderSpec) -> Self {
+        let provider = Self::new(spec.model.clone(), spec.api_key.clone())
+            .with_safety_settings(spec.safety_settings.clone())
+            .with_http_options(spec.http_options())
+            .with_extra_params(spec.extra_params.clone());
+        match &spec.base_url {
+            Some(base_url) => provider.with_base_url(base_url.clone()),
+            None => provider,
+        }
+    }
+
+    /// Send requests to a different API root
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Override the API's default safety thresholds
+    pub fn with_safety_settings(mut self, safety_settings: Vec<SafetySetting>) -> Self {
+        self.safety_settings = safety_settings;
+        self
+    }
+
+    /// Give up on requests that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.base.set_timeout(timeout);
+        self
+    }
+
+    /// Apply request, connect and read timeouts (unset ones keep their defaults)
+    pub fn with_timeouts(mut self, timeouts: &HttpTimeouts) -> Self {
+        self.base.set_timeouts(timeouts);
+        self
+    }
+
+    /// Merge provider-specific parameters into every request body
+    pub fn with_extra_params(mut self, extra_params: Map<String, Value>) -> Self {
+        self.base.set_extra_params(extra_params);
+        self
+    }
+
+    /// Apply timeouts, a proxy and extra headers to every request
+    pub fn with_http_options(mut self, http: HttpOptions) -> Self {
+        self.base.set_http_options(http);
+        self
+    }
+
+    /// `generateContent` request body
+    fn request_body(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: Option<f32>,
+    ) -> Value {
+        let mut body = json!({
+            "contents": [{ "role": "user", "parts": [{ "text": prompt }] }],
+        });
+        if let Some(system) = system_prompt {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+        if let Some(temperature) = temperature {
+            body["generationConfig"] = json!({ "temperature": temperature });
+        }
+        if !self.safety_settings.is_empty() {
+            body["safetySettings"] = json!(self.safety_settings);
+        }
+        body
+    }
+
+    /// POST to a model method such as `generateContent`
+    async fn send(&self, method: &str, body: &Value) -> Result<reqwest::Response> {
+        let request = self
+            .base
+            .post_json(
+                format!("{}/models/{}:{method}", self.base_url, self.model),
+                body,
+            )?
+            .header("x-goog-api-key", &self.api_key);
+        self.base.send(request, &self.model_id()).await
+    }
+
+    /// Run a non-streaming request and return the first candidate's text
+    async fn generate(&self, body: &Value) -> Result<CompletionResponse> {
+        let response =
+            http_json(self.send("generateContent", body).await?, &self.model_id()).await?;
+        let text = candidate_text(&response, &self.model_id())?
+            .ok_or_else(|| invalid_response(&self.model_id(), "no candidates"))?;
+        let (prompt_tokens, completion_tokens) = reported_usage(
+            &response,
+            "/usageMetadata/promptTokenCount",
+            "/usageMetadata/candidatesTokenCount",
+        );
+        Ok(CompletionResponse {
+            text,
+            prompt_tokens,
+            completion_tokens,
+            finish_reason: reported_str(&response, "/candidates/0/finishReason"),
+            model: reported_str(&response, "/modelVersion"),
+            latency_ms: None,
+        })
+    }
+}
+
+/// Text of the first candidate in a response, or `None` if there is no candidate
+///
+/// Blocked prompts and responses stopped by safety filters are errors.
+fn candidate_text(response: &Value, model_id: &str) -> Result<Option<String>> {
+    if let Some(reason) = response
+        .pointer("/promptFeedback/blockReason")
+        .and_then(Value::as_str)
+    {
+        return Err(MarsError::ClientError(format!(
+            "{model_id} blocked the prompt ({reason})"
+        )));
+    }
+    let Some(candidate) = response.pointer("/candidates/0") else {
+        return Ok(None);
+    };
+    if let Some(reason) = candidate.get("finishReason").and_then(Value::as_str)
+        && BLOCKED_FINISH_REASONS.contains(&reason)
+    {
+        return Err(MarsError::ClientError(format!(
+            "{model_id} withheld the response ({reason})"
+        )));
+    }
+
+    // Thought summaries are parts flagged with `"thought": true`
+    let parts = candidate
+        .pointer("/content/parts")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    Ok(Some(
+        parts
+            .iter()
+            .filter(|part| {
+                !part
+                    .get("thought")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false)
+            })
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect(),
+    ))
+}
+
+#[async_trait]
+impl LLMProvider for GeminiProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        self.generate(&self.request_body(prompt, system_prompt, None))
+            .await
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        self.generate(&self.request_body(prompt, system_prompt, Some(temperature)))
+            .await
+    }
+
+    /// JSON responses use Gemini's `responseMimeType`, constrained by
+    /// `responseJsonSchema` when there is a schema
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        let mut body = self.request_body(&format.prompt(prompt), system_prompt, None);
+        if format.is_json() {
+            body["generationConfig"]["responseMimeType"] = json!("application/json");
+        }
+        if let ResponseFormat::JsonSchema { schema, .. } = format {
+            body["generationConfig"]["responseJsonSchema"] = schema.clone();
+        }
+        format.normalize(self.generate(&body).await?)
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let body = self.request_body(prompt, system_prompt, None);
+        let response = self.send("streamGenerateContent?alt=sse", &body).await?;
+        let model_id = self.model_id();
+        let owner = model_id.clone();
+
+        // Each event is a partial `generateContent` response; the stream simply ends
+        Ok(stream_sse_data(response, &model_id, move |data| {
+            let event: Value =
+                serde_json::from_str(data).map_err(|e| invalid_response(&owner, &e.to_string()))?;
+            if let Some(message) = event.pointer("/error/message").and_then(Value::as_str) {
+                return Err(MarsError::ClientError(format!(
+                    "{owner} failed mid-stream: {message}"
+                )));
+            }
+            Ok(candidate_text(&event, &owner)?.map_or(StreamLine::Skip, StreamLine::Chunk))
+        }))
+    }
+
+    fn provider_name(&self) -> &str {
+        "gemini"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider_config::ProviderSpec;
+    use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer) -> GeminiProvider {
+        GeminiProvider::new("gemini-2.0-flash".to_string(), "test-key".to_string())
+            .with_base_url(format!("{}/v1beta", server.uri()))
+    }
+
+    #[tokio::test]
+    async fn test_generate_content_schema_and_safety_settings() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+            .and(header("x-goog-api-key", "test-key"))
+            .and(body_partial_json(json!({
+                "contents": [{ "role": "user", "parts": [{ "text": "What is 2+2?" }] }],
+                "systemInstruction": { "parts": [{ "text": "Be brief." }] },
+                "generationConfig": { "temperature": 0.5 },
+                "safetySettings": [{
+                    "category": "HARM_CATEGORY_DANGEROUS_CONTENT",
+                    "threshold": "BLOCK_ONLY_HIGH",
+                }],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [{
+                    "content": {
+                        "role": "model",
+                        "parts": [
+                            { "text": "adding", "thought": true },
+                            { "text": "2 + 2 " },
+                            { "text": "= 4" },
+                        ],
+                    },
+                    "finishReason": "STOP",
+                }],
+                "usageMetadata": { "promptTokenCount": 9, "candidatesTokenCount": 5 },
+                "modelVersion": "gemini-2.0-flash-001",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let spec = ProviderSpec::new("gemini", "models/gemini-2.0-flash")
+            .with_api_key("test-key".to_string())
+            .with_base_url(format!("{}/v1beta/", server.uri()))
+            .with_safety_setting("HARM_CATEGORY_DANGEROUS_CONTENT", "BLOCK_ONLY_HIGH");
+        let answer = GeminiProvider::from_spec(&spec)
+            .complete_with_temperature("What is 2+2?", Some("Be brief."), 0.5)
+            .await
+            .unwrap();
+        assert_eq!(answer.text, "2 + 2 = 4");
+        assert_eq!(answer.prompt_tokens, Some(9));
+        assert_eq!(answer.completion_tokens, Some(5));
+        assert_eq!(answer.finish_reason.as_deref(), Some("STOP"));
+        assert_eq!(answer.model.as_deref(), Some("gemini-2.0-flash-001"));
+    }
+
+    #[tokio::test]
+    async fn test_blocked_prompt_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "promptFeedback": { "blockReason": "SAFETY" },
+            })))
+            .mount(&server)
+            .await;
+
+        let err = provider(&server).complete("q", None).await.unwrap_err();
+        assert!(err.to_string().contains("blocked the prompt (SAFETY)"));
+    }
+
+    #[tokio::test]
+    async fn test_error_status_uses_api_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": { "code": 400, "message": "API key not valid", "status": "INVALID_ARGUMENT" },
+            })))
+            .mount(&server)
+            .await;
+
+        let err = provider(&server).complete("q", None).await.unwrap_err();
+        assert!(err.to_string().contains("API key not valid"));
+    }
+
+    #[tokio::test]
+    async fn test_streams_candidate_text() {
+        let events = [
+            r#"data: {"candidates":[{"content":{"role":"model","parts":[{"text":"2 + 2"}]}}]}"#,
+            "",
+            r#"data: {"candidates":[{"content":{"role":"model","parts":[{"text":" = 4"}]},"finishReason":"STOP"}]}"#,
+        ];
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(
+                "/v1beta/models/gemini-2.0-flash:streamGenerateContent",
+            ))
+            .and(query_param("alt", "sse"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(events.join("\n") + "\n\n"),
+            )
+            .mount(&server)
+            .await;
+
+        let mut stream = provider(&server).stream("q", None).await.unwrap();
+        assert_eq!(stream.next_chunk().await.unwrap().unwrap(), "2 + 2");
+        assert_eq!(stream.next_chunk().await.unwrap().unwrap(), " = 4");
+        assert!(stream.next_chunk().await.is_none());
+    }
+}
@@ -0,0 +1,374 @@
+//! Genetic-algorithm aggregation.
+//!
+//! Solutions form a population that evolves over a number of generations.
+//! Crossover asks the LLM to merge two parent solutions, mutation asks it to
+//! perturb a child, and fitness is the solution's verification score.
+//!
+//! # Algorithm
+//!
+//! 1. **Evaluation**: Score every individual (existing verification score, or a
+//!    fresh verification pass for unverified solutions)
+//! 2. **Selection**: Pick parents by binary tournament on fitness
+//! 3. **Crossover**: Merge two parents into a child via the LLM
+//! 4. **Mutation**: With probability `mutation_rate`, perturb the child
+//! 5. **Replacement**: Keep the fittest individual, fill the rest with children
+
+use crate::LLMProvider;
+use crate::Result;
+use crate::aggregator::{split_final_answer, verifier_score};
+use crate::model_router::CompletionResponse;
+use crate::prompts;
+use crate::types::{GenerationPhase, Solution};
+use rand::Rng;
+
+/// Configuration for genetic-algorithm aggregation
+#[derive(Clone, Debug)]
+pub struct GeneticConfig {
+    /// Number of individuals per generation (default: 6)
+    pub population_size: usize,
+    /// Number of generations to evolve (default: 3)
+    pub generations: usize,
+    /// Probability that a child is mutated (default: 0.2)
+    pub mutation_rate: f32,
+    /// Temperature used for crossover (default: 0.5)
+    pub crossover_temperature: f32,
+    /// Temperature used for mutation (default: 1.0)
+    pub mutation_temperature: f32,
+    /// System prompt for crossover and mutation (default:
+    /// [`prompts::MARS_SYSTEM_PROMPT`])
+    pub system_prompt: String,
+}
+
+impl Default for GeneticConfig {
+    fn default() -> Self {
+        Self {
+            population_size: 6,
+            generations: 3,
+            mutation_rate: 0.2,
+            crossover_temperature: 0.5,
+            mutation_temperature: 1.0,
+            system_prompt: prompts::MARS_SYSTEM_PROMPT.to_string(),
+        }
+    }
+}
+
+/// An individual in the population with its cached fitness
+#[derive(Clone, Debug)]
+struct Individual {
+    solution: Solution,
+    fitness: f32,
+}
+
+/// Evolves a population of solutions with LLM-driven crossover and mutation
+pub struct GeneticAggregator;
+
+impl GeneticAggregator {
+    /// Evolve the given solutions and return the final generation's offspring
+    ///
+    /// The returned solutions are sorted by fitness, best first. Initial
+    /// solutions are never returned, so the caller can add the result to a
+    /// workspace that already holds them.
+    pub async fn evolve(
+        query: &str,
+        solutions: &[Solution],
+        config: &GeneticConfig,
+        provider: &dyn LLMProvider,
+    ) -> Result<Vec<Solution>> {
+        if solutions.is_empty() || config.generations == 0 {
+            return Ok(Vec::new());
+        }
+
+        let population_size = config.population_size.max(2);
+        let mut population = Vec::with_capacity(population_size);
+        for solution in solutions.iter().take(population_size) {
+            let fitness = Self::fitness(query, solution, provider).await?;
+            population.push(Individual {
+                solution: solution.clone(),
+                fitness,
+            });
+        }
+
+        let mut offspring = Vec::new();
+        for generation in 0..config.generations {
+            offspring = Vec::with_capacity(population_size);
+            while offspring.len() + 1 < population_size {
+                let first = Self::tournament_select(&population);
+                let second = Self::tournament_select(&population);
+                let mut child = Self::crossover(
+                    query,
+                    &population[first].solution,
+                    &population[second].solution,
+                    generation,
+                    config.crossover_temperature,
+                    &config.system_prompt,
+                    provider,
+                )
+                .await?;
+
+                if rand::rng().random::<f32>() < config.mutation_rate {
+                    child = Self::mutate(
+                        query,
+                        &child,
+                        generation,
+                        config.mutation_temperature,
+                        &config.system_prompt,
+                        provider,
+                    )
+                    .await?;
+                }
+
+                let fitness = Self::fitness(query, &child, provider).await?;
+                offspring.push(Individual {
+                    solution: child,
+                    fitness,
+                });
+            }
+
+            // Elitism: carry the fittest individual into the next generation
+            let elite = population.swap_remove(Self::fittest(&population));
+            population = offspring.clone();
+            population.push(elite);
+        }
+
+        offspring.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+        Ok(offspring
+            .into_iter()
+            .map(|individual| individual.solution)
+            .collect())
+    }
+
+    /// Fitness of a solution: its verification score, verifying it if needed
+    async fn fitness(query: &str, solution: &Solution, provider: &dyn LLMProvider) -> Result<f32> {
+        verifier_score(query, solution, provider).await
+    }
+
+    /// Binary tournament: the fitter of two random individuals
+    fn tournament_select(population: &[Individual]) -> usize {
+        let mut rng = rand::rng();
+        let a = rng.random_range(0..population.len());
+        let b = rng.random_range(0..population.len());
+        if population[a].fitness >= population[b].fitness {
+            a
+        } else {
+            b
+        }
+    }
+
+    /// Index of the individual with the highest fitness
+    fn fittest(population: &[Individual]) -> usize {
+        (0..population.len())
+            .max_by(|&a, &b| population[a].fitness.total_cmp(&population[b].fitness))
+            .unwrap_or(0)
+    }
+
+    /// Merge two parent solutions into a child
+    async fn crossover(
+        query: &str,
+        first: &Solution,
+        second: &Solution,
+        generation: usize,
+        temperature: f32,
+        system_prompt: &str,
+        provider: &dyn LLMProvider,
+    ) -> Result<Solution> {
+        let prompt = format!(
+            "{}\n\nProblem:\n{}\n\nParent A:\nReasoning: {}\nAnswer: {}\n\n\
+             Parent B:\nReasoning: {}\nAnswer: {}",
+            prompts::GENETIC_CROSSOVER_PROMPT,
+            query,
+            first.reasoning,
+            first.answer,
+            second.reasoning,
+            second.answer
+        );
+        let response = provider
+            .complete_with_temperature(&prompt, Some(system_prompt), temperature)
+            .await?;
+
+        let mut child = Self::offspring(
+            format!("genetic-crossover-gen-{generation}"),
+            &prompt,
+            &response,
+            temperature,
+            provider,
+        );
+        child.parent_ids = vec![first.id.clone(), second.id.clone()];
+        Ok(child)
+    }
+
+    /// Perturb a solution to explore a nearby variant
+    async fn mutate(
+        query: &str,
+        solution: &Solution,
+        generation: usize,
+        temperature: f32,
+        system_prompt: &str,
+        provider: &dyn LLMProvider,
+    ) -> Result<Solution> {
+        let prompt = format!(
+            "{}\n\nProblem:\n{}\n\nSolution:\nReasoning: {}\nAnswer: {}",
+            prompts::GENETIC_MUTATION_PROMPT,
+            query,
+            solution.reasoning,
+            solution.answer
+        );
+        let response = provider
+            .complete_with_temperature(&prompt, Some(system_prompt), temperature)
+            .await?;
+
+        let mut mutant = Self::offspring(
+            format!("genetic-mutation-gen-{generation}"),
+            &prompt,
+            &response,
+            temperature,
+            provider,
+        );
+        mutant.parent_ids = vec![solution.id.clone()];
+        Ok(mutant)
+    }
+
+    /// Build a solution from an LLM response, splitting on the final `ANSWER:` line
+    fn offspring(
+        agent_id: String,
+        prompt: &str,
+        response: &CompletionResponse,
+        temperature: f32,
+        provider: &dyn LLMProvider,
+    ) -> Solution {
+        let (reasoning, answer) = split_final_answer(&response.text);
+        let mut solution = Solution::new(agent_id, reasoning, answer, temperature, 0);
+        solution.record_completion(response, prompt.len());
+        solution.phase = GenerationPhase::Aggregated;
+        solution.record_provider(provider, Some(temperature));
+        solution
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_router::ModelStream;
+    use async_trait::async_trait;
+
+    /// Mock provider that merges to "4", mutates to "5", and only approves "4"
+    struct MockBreeder;
+
+    #[async_trait]
+    impl LLMProvider for MockBreeder {
+        async fn complete(
+            &self,
+            prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if system_prompt == Some(prompts::VERIFICATION_SYSTEM_PROMPT) {
+                let verdict = if prompt.contains("Answer: 4") {
+                    "RESULT: CORRECT\nSCORE: 0.9"
+                } else {
+                    "RESULT: INCORRECT\nSCORE: 0.2"
+                };
+                Ok(verdict.into())
+            } else if prompt.starts_with(prompts::GENETIC_MUTATION_PROMPT) {
+                Ok("Perturbed the approach.\nANSWER: 5".into())
+            } else {
+                Ok("Combined both parents.\nANSWER: 4".into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-breeder"
+        }
+    }
+
+    fn solution(answer: &str) -> Solution {
+        Solution::new(
+            "agent".to_string(),
+            "reasoning".to_string(),
+            answer.to_string(),
+            0.5,
+            10,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_evolve_crossover_only() {
+        let config = GeneticConfig {
+            population_size: 4,
+            generations: 2,
+            mutation_rate: 0.0,
+            ..GeneticConfig::default()
+        };
+        let initial = vec![solution("3"), solution("4"), solution("6")];
+
+        let evolved = GeneticAggregator::evolve("What is 2+2?", &initial, &config, &MockBreeder)
+            .await
+            .unwrap();
+
+        assert_eq!(evolved.len(), 3);
+        for child in &evolved {
+            assert_eq!(child.answer, "4");
+            assert_eq!(child.reasoning, "Combined both parents.");
+            assert_eq!(child.phase, GenerationPhase::Aggregated);
+            assert_eq!(child.model, "mock/mock-breeder");
+            assert_eq!(child.parent_ids.len(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evolve_always_mutates() {
+        let config = GeneticConfig {
+            population_size: 3,
+            generations: 1,
+            mutation_rate: 1.0,
+            ..GeneticConfig::default()
+        };
+        let initial = vec![solution("4"), solution("4")];
+
+        let evolved = GeneticAggregator::evolve("What is 2+2?", &initial, &config, &MockBreeder)
+            .await
+            .unwrap();
+
+        assert_eq!(evolved.len(), 2);
+        assert!(evolved.iter().all(|child| child.answer == "5"));
+        assert!(evolved[0].agent_id.starts_with("genetic-mutation"));
+        // A mutant descends from the crossover child it perturbed
+        assert_eq!(evolved[0].parent_ids.len(), 1);
+        assert!(initial.iter().all(|s| s.id != evolved[0].parent_ids[0]));
+    }
+
+    #[tokio::test]
+    async fn test_evolve_without_generations() {
+        let config = GeneticConfig {
+            generations: 0,
+            ..GeneticConfig::default()
+        };
+        let evolved = GeneticAggregator::evolve("q", &[solution("4")], &config, &MockBreeder)
+            .await
+            .unwrap();
+        assert!(evolved.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fitness_prefers_existing_score() {
+        let mut verified = solution("3");
+        verified.add_verification_pass(0.7);
+
+        let fitness = GeneticAggregator::fitness("q", &verified, &MockBreeder)
+            .await
+            .unwrap();
+        assert_eq!(fitness, verified.verification_score);
+
+        let fresh = GeneticAggregator::fitness("q", &solution("3"), &MockBreeder)
+            .await
+            .unwrap();
+        assert!((fresh - 0.2).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,395 @@
+//! gRPC service, behind the `grpc` feature.
+//!
+//! [`MarsGrpcService`] serves the `code_mars.v1.Mars` service defined in
+//! `proto/mars.proto`, for backends in other languages that embed MARS as a
+//! microservice. Its one method, `SolveQuery`, is server streaming: each
+//! event of the run is sent as it is emitted, then the final output. A
+//! failed run ends the stream with an error status instead, and a client
+//! that hangs up cancels its run.
+//!
+//! The messages in [`proto`] are written out by hand rather than generated
+//! at build time, so building the crate needs no `protoc`.
+
+use crate::config::MarsConfig;
+use crate::coordinator::{MarsCoordinator, RunOptions};
+use crate::model_router::LLMProvider;
+use crate::run_handle::RunUpdate;
+use crate::types::{MarsEventRecord, MarsOutput};
+use crate::{MarsError, Result};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::codegen::{BoxFuture, Context, Poll, Service, StdError, http};
+use tonic::server::{Grpc, NamedService, ServerStreamingService};
+use tonic::{Code, Status};
+
+/// Messages of `proto/mars.proto`
+pub mod proto {
+    /// Query to answer, with per-run overrides of the server's config
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SolveQueryRequest {
+        /// Problem or question to solve
+        #[prost(string, tag = "1")]
+        pub query: String,
+        /// Number of exploring agents
+        #[prost(uint32, optional, tag = "2")]
+        pub num_agents: Option<u32>,
+        /// Aggregation name, as for `MarsConfig::with_aggregation_name`
+        #[prost(string, optional, tag = "3")]
+        pub aggregation: Option<String>,
+        /// Labels copied to the output
+        #[prost(string, repeated, tag = "4")]
+        pub tags: Vec<String>,
+    }
+
+    /// One message of the `SolveQuery` stream
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SolveQueryResponse {
+        /// An event, or the output
+        #[prost(oneof = "solve_query_response::Item", tags = "1, 2")]
+        pub item: Option<solve_query_response::Item>,
+    }
+
+    /// Nested types of [`SolveQueryResponse`]
+    pub mod solve_query_response {
+        /// What a response carries
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Item {
+            /// An event of the run
+            #[prost(message, tag = "1")]
+            Event(super::RunEvent),
+            /// The final output; always the last message
+            #[prost(message, tag = "2")]
+            Output(super::RunOutput),
+        }
+    }
+
+    /// An event of the run
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RunEvent {
+        /// ID of the run
+        #[prost(string, tag = "1")]
+        pub run_id: String,
+        /// Position of the event in its run
+        #[prost(uint64, tag = "2")]
+        pub sequence: u64,
+        /// When the event was forwarded to subscribers, RFC 3339
+        #[prost(string, tag = "3")]
+        pub timestamp: String,
+        /// Event name in snake_case
+        #[prost(string, tag = "4")]
+        pub r#type: String,
+        /// The whole `MarsEventRecord` as JSON
+        #[prost(string, tag = "5")]
+        pub record_json: String,
+    }
+
+    /// The run's final output
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RunOutput {
+        /// ID of the run
+        #[prost(string, tag = "1")]
+        pub run_id: String,
+        /// The final answer
+        #[prost(string, tag = "2")]
+        pub answer: String,
+        /// How the answer was selected
+        #[prost(string, tag = "3")]
+        pub selection_method: String,
+        /// Confidence in the answer
+        #[prost(float, tag = "4")]
+        pub confidence: f32,
+        /// Tokens used by the run
+        #[prost(uint64, tag = "5")]
+        pub total_tokens: u64,
+        /// Dollars spent by the run
+        #[prost(double, tag = "6")]
+        pub cost_usd: f64,
+        /// Whether the cost budget cut the search short
+        #[prost(bool, tag = "7")]
+        pub budget_truncated: bool,
+        /// The whole `MarsOutput` as JSON
+        #[prost(string, tag = "8")]
+        pub output_json: String,
+    }
+}
+
+use proto::solve_query_response::Item;
+
+/// Full name of the service
+pub const SERVICE_NAME: &str = "code_mars.v1.Mars";
+
+/// Path of the `SolveQuery` method
+pub const SOLVE_QUERY_PATH: &str = "/code_mars.v1.Mars/SolveQuery";
+
+/// Most agents a request may ask for
+const MAX_AGENTS: u32 = 16;
+
+/// Messages buffered before the run waits for the client
+const STREAM_CAPACITY: usize = 64;
+
+type SolveQueryStream = ReceiverStream<std::result::Result<proto::SolveQueryResponse, Status>>;
+
+/// Serves MARS runs over gRPC
+#[derive(Clone)]
+pub struct MarsGrpcService {
+    config: Arc<MarsConfig>,
+    provider: Arc<dyn LLMProvider>,
+}
+
+impl MarsGrpcService {
+    /// Answer queries with runs of `config` on `provider`
+    pub fn new(config: MarsConfig, provider: Arc<dyn LLMProvider>) -> Self {
+        Self {
+            config: Arc::new(config),
+            provider,
+        }
+    }
+
+    /// Serve HTTP/2 connections from `listener` until the task is dropped
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        tonic::transport::Server::builder()
+            .serve_with_incoming(self, TcpListenerStream::new(listener))
+            .await
+            .map_err(|e| MarsError::ClientError(format!("gRPC server failed: {e}")))
+    }
+
+    /// Start a run for `request`, streaming its events and then its output
+    ///
+    /// Fails if the request's overrides are invalid.
+    fn solve_query(&self, request: proto::SolveQueryRequest) -> Result<SolveQueryStream> {
+        let config = self.run_config(&request)?;
+        let options = RunOptions {
+            tags: request.tags,
+            ..RunOptions::default()
+        };
+        let mut run = MarsCoordinator::with_provider(config, Arc::clone(&self.provider))
+            .start(request.query, options);
+
+        let (tx, rx) = mpsc::channel(STREAM_CAPACITY);
+        tokio::spawn(async move {
+            while let Some(update) = run.next_update().await {
+                let item = match update {
+                    RunUpdate::Event { record, .. } => {
+                        run_event(&record).map(Item::Event).map_err(internal_error)
+                    }
+                    RunUpdate::Finished(Ok(output)) => run_output(&output)
+                        .map(Item::Output)
+                        .map_err(internal_error),
+                    RunUpdate::Finished(Err(e)) => Err(run_error_status(&e)),
+                };
+                let response = item.map(|item| proto::SolveQueryResponse { item: Some(item) });
+                if tx.send(response).await.is_err() {
+                    // The client hung up; dropping the handle cancels the run
+                    return;
+                }
+            }
+        });
+        Ok(ReceiverStream::new(rx))
+    }
+
+    /// The server's config with the request's overrides
+    fn run_config(&self, request: &proto::SolveQueryRequest) -> Result<MarsConfig> {
+        if request.query.trim().is_empty() {
+            return Err(MarsError::InvalidConfiguration(
+                "query must not be empty".to_string(),
+            ));
+        }
+        let mut config = MarsConfig::clone(&self.config);
+        if let Some(agents) = request.num_agents {
+            if !(1..=MAX_AGENTS).contains(&agents) {
+                return Err(MarsError::InvalidConfiguration(format!(
+                    "num_agents must be from 1 to {MAX_AGENTS}"
+                )));
+            }
+            config = config.with_num_agents(agents as usize);
+        }
+        if let Some(aggregation) = &request.aggregation {
+            config = config.with_aggregation_name(aggregation)?;
+        }
+        Ok(config)
+    }
+}
+
+fn run_event(record: &MarsEventRecord) -> serde_json::Result<proto::RunEvent> {
+    let event = serde_json::to_value(&record.event)?;
+    Ok(proto::RunEvent {
+        run_id: record.run_id.clone(),
+        sequence: record.sequence,
+        timestamp: record.timestamp.to_rfc3339(),
+        r#type: event["type"].as_str().unwrap_or_default().to_string(),
+        record_json: serde_json::to_string(record)?,
+    })
+}
+
+fn run_output(output: &MarsOutput) -> serde_json::Result<proto::RunOutput> {
+    Ok(proto::RunOutput {
+        run_id: output.run_id.clone(),
+        answer: output.answer.clone(),
+        selection_method: format!("{:?}", output.selection_method),
+        confidence: output.confidence,
+        total_tokens: output.total_tokens as u64,
+        cost_usd: output.cost.total_usd,
+        budget_truncated: output.budget_truncated,
+        output_json: serde_json::to_string(output)?,
+    })
+}
+
+fn internal_error(error: serde_json::Error) -> Status {
+    Status::internal(format!("Failed to serialize: {error}"))
+}
+
+/// Bad configurations are the server's fault; provider failures leave the
+/// service unavailable
+fn run_error_status(error: &MarsError) -> Status {
+    let code = match error {
+        MarsError::InvalidConfiguration(_) => Code::Internal,
+        MarsError::Cancelled => Code::Cancelled,
+        MarsError::BudgetExceeded { .. } => Code::ResourceExhausted,
+        _ => Code::Unavailable,
+    };
+    Status::new(code, error.to_string())
+}
+
+/// The `SolveQuery` method
+struct SolveQuery(MarsGrpcService);
+
+impl ServerStreamingService<proto::SolveQueryRequest> for SolveQuery {
+    type Response = proto::SolveQueryResponse;
+    type ResponseStream = SolveQueryStream;
+    type Future =
+        std::future::Ready<std::result::Result<tonic::Response<SolveQueryStream>, Status>>;
+
+    fn call(&mut self, request: tonic::Request<proto::SolveQueryRequest>) -> Self::Future {
+        std::future::ready(
+            self.0
+                .solve_query(request.into_inner())
+                .map(tonic::Response::new)
+                .map_err(|e| Status::invalid_argument(e.to_string())),
+        )
+    }
+}
+
+impl<B> Service<http::Request<B>> for MarsGrpcService
+where
+    B: tonic::codegen::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = std::convert::Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        if request.uri().path() != SOLVE_QUERY_PATH {
+            let unimplemented =
+                Status::unimplemented(format!("Unknown method {}", request.uri().path()));
+            return Box::pin(async move { Ok(unimplemented.into_http()) });
+        }
+        let method = SolveQuery(self.clone());
+        Box::pin(async move {
+            let mut grpc = Grpc::new(tonic::codec::ProstCodec::default());
+            Ok(grpc.server_streaming(method, request).await)
+        })
+    }
+}
+
+impl NamedService for MarsGrpcService {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Four;
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::transport::Channel;
+
+    async fn client() -> tonic::client::Grpc<Channel> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let service =
+            MarsGrpcService::new(MarsConfig::default().with_max_iterations(1), Arc::new(Four));
+        tokio::spawn(service.serve(listener));
+        let channel = Channel::from_shared(url).unwrap().connect().await.unwrap();
+        tonic::client::Grpc::new(channel)
+    }
+
+    async fn solve(
+        request: proto::SolveQueryRequest,
+    ) -> std::result::Result<Vec<proto::SolveQueryResponse>, Status> {
+        let mut client = client().await;
+        client.ready().await.unwrap();
+        let mut stream = client
+            .server_streaming(
+                tonic::Request::new(request),
+                PathAndQuery::from_static(SOLVE_QUERY_PATH),
+                tonic::codec::ProstCodec::default(),
+            )
+            .await?
+            .into_inner();
+        let mut responses = Vec::new();
+        while let Some(response) = stream.message().await? {
+            responses.push(response);
+        }
+        Ok(responses)
+    }
+
+    #[tokio::test]
+    async fn test_solve_query_streams_events_then_output() {
+        let responses = solve(proto::SolveQueryRequest {
+            query: "What is 2+2?".to_string(),
+            num_agents: Some(2),
+            aggregation: Some("none".to_string()),
+            tags: vec!["grpc".to_string()],
+        })
+        .await
+        .unwrap();
+
+        let (last, events) = responses.split_last().unwrap();
+        let Some(Item::Output(output)) = &last.item else {
+            panic!("last message is not the output: {last:?}");
+        };
+        assert!(output.answer.contains('4'));
+        assert!(output.output_json.contains("\"grpc\""));
+
+        let events: Vec<&proto::RunEvent> = events
+            .iter()
+            .map(|response| match &response.item {
+                Some(Item::Event(event)) => event,
+                other => panic!("expected an event, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(events[0].r#type, "exploration_started");
+        assert!(events[0].record_json.contains("\"num_agents\":2"));
+        assert!(events.iter().all(|event| event.run_id == output.run_id));
+        let sequences: Vec<u64> = events.iter().map(|event| event.sequence).collect();
+        assert_eq!(sequences, (0..events.len() as u64).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_requests_are_rejected() {
+        let status = solve(proto::SolveQueryRequest {
+            query: "q".to_string(),
+            aggregation: Some("unknown".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().contains("best_of_n"));
+
+        let status = solve(proto::SolveQueryRequest {
+            query: "q".to_string(),
+            num_agents: Some(0),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,412 @@
+//! Standalone HTML reports of finished runs, behind the `report-html` feature.
+//!
+//! [`HtmlReport`] renders the lineage tree of the final answer as nested,
+//! collapsible nodes, each overlaid with its verification verdicts, followed
+//! by the solutions outside that lineage and, when MCTS aggregation ran, the
+//! search tree with the most-visited path highlighted. The page has no
+//! external assets, so it can be attached to an issue or opened offline.
+
+use crate::mcts::MCTSTree;
+use crate::types::{DerivationTree, MarsOutput, Solution};
+use crate::{MarsError, Result};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Characters of a solution ID shown in node labels
+const SHORT_ID_LEN: usize = 8;
+
+/// Characters of an MCTS action shown in node labels
+const ACTION_PREVIEW_LEN: usize = 80;
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #1f2328; max-width: 72rem; }
+h1 { font-size: 1.5rem; } h2 { font-size: 1.2rem; margin-top: 2rem; }
+details { margin: 0.25rem 0 0.25rem 1.25rem; border-left: 2px solid #d0d7de; padding-left: 0.5rem; }
+summary { cursor: pointer; padding: 0.15rem 0; }
+pre { white-space: pre-wrap; background: #f6f8fa; padding: 0.5rem; border-radius: 4px; }
+.badge { display: inline-block; border-radius: 3px; padding: 0 0.4rem; font-size: 0.8rem; margin-left: 0.3rem; }
+.verified { background: #dafbe1; } .failed { background: #ffebe9; } .unverified { background: #eaeef2; }
+.final > summary, .best > summary { font-weight: bold; }
+.final { border-left-color: #1a7f37; } .best { border-left-color: #0969da; }
+.meta { color: #656d76; font-size: 0.85rem; }
+ul.verdicts { margin: 0.25rem 0; }
+"#;
+
+const SCRIPT: &str = r#"
+function setAll(open) { document.querySelectorAll('details').forEach(d => d.open = open); }
+"#;
+
+/// Renders a run as a standalone HTML page
+pub struct HtmlReport<'a> {
+    output: &'a MarsOutput,
+    mcts_tree: Option<&'a MCTSTree>,
+}
+
+impl<'a> HtmlReport<'a> {
+    /// Report on `output`
+    pub fn new(output: &'a MarsOutput) -> Self {
+        Self {
+            output,
+            mcts_tree: None,
+        }
+    }
+
+    /// Include the search tree of the run's MCTS aggregation
+    ///
+    /// Pass [`MarsCoordinator::mcts_tree`](crate::MarsCoordinator::mcts_tree).
+    pub fn with_mcts_tree(mut self, tree: Option<&'a MCTSTree>) -> Self {
+        self.mcts_tree = tree;
+        self
+    }
+
+    /// The report as an HTML document
+    pub fn render(&self) -> String {
+        let output = self.output;
+        let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+        html.push_str("<meta charset=\"utf-8\">\n<title>MARS Run Report</title>\n");
+        html.push_str(&format!(
+            "<style>{STYLE}</style>\n<script>{SCRIPT}</script>\n"
+        ));
+        html.push_str("</head>\n<body>\n<h1>MARS Run Report</h1>\n");
+        html.push_str(&format!(
+            "<p class=\"meta\">Selected by {:?} after {} iterations, {} tokens, ${:.4}{}</p>\n",
+            output.selection_method,
+            output.iterations,
+            output.total_tokens,
+            output.cost.total_usd,
+            if output.run_id.is_empty() {
+                String::new()
+            } else {
+                format!(" &middot; run <code>{}</code>", escape(&output.run_id))
+            }
+        ));
+        html.push_str(&format!(
+            "<h2>Answer</h2>\n<pre>{}</pre>\n",
+            escape(output.answer.trim())
+        ));
+        html.push_str(
+            "<p><button onclick=\"setAll(true)\">Expand all</button> \
+             <button onclick=\"setAll(false)\">Collapse all</button></p>\n",
+        );
+
+        html.push_str("<h2>Solution lineage</h2>\n");
+        let tree = output.derivation_tree();
+        match &tree {
+            Some(tree) => self.render_lineage(tree, true, &mut html),
+            None => html.push_str("<p>The final solution is not among the run's solutions.</p>\n"),
+        }
+
+        let in_lineage: HashSet<&str> = tree
+            .as_ref()
+            .map(|tree| tree.solutions().iter().map(|s| s.id.as_str()).collect())
+            .unwrap_or_default();
+        let others: Vec<&Solution> = output
+            .all_solutions
+            .iter()
+            .filter(|s| !in_lineage.contains(s.id.as_str()))
+            .collect();
+        if !others.is_empty() {
+            html.push_str("<h2>Other solutions</h2>\n");
+            for solution in others {
+                self.open_solution(solution, "", false, &mut html);
+                html.push_str("</details>\n");
+            }
+        }
+
+        if let Some(tree) = self.mcts_tree
+            && let Some(root) = tree.root
+        {
+            html.push_str("<h2>MCTS search tree</h2>\n");
+            let best_path = most_visited_path(tree, root);
+            render_mcts_node(tree, root, &best_path, &mut HashSet::new(), &mut html);
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// Write the report to `path`
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.render()).map_err(|e| {
+            MarsError::StorageError(format!("Failed to write report {}: {e}", path.display()))
+        })
+    }
+
+    fn render_lineage(&self, tree: &DerivationTree, is_final: bool, html: &mut String) {
+        let class = if is_final { "final" } else { "" };
+        self.open_solution(&tree.solution, class, true, html);
+        for parent in &tree.parents {
+            self.render_lineage(parent, false, html);
+        }
+        html.push_str("</details>\n");
+    }
+
+    /// Open a `<details>` node for `solution` with its verification overlay
+    fn open_solution(&self, solution: &Solution, class: &str, open: bool, html: &mut String) {
+        let (status, label) = if solution.is_verified {
+            ("verified", "verified")
+        } else if solution.verification_failures > 0 {
+            ("failed", "failed")
+        } else {
+            ("unverified", "unverified")
+        };
+        html.push_str(&format!(
+            "<details class=\"{class}\"{}>\n<summary><code>{}</code> {:?} by {}{} \
+             <span class=\"badge {status}\" title=\"{} passed, {} failed\">{label} {:.2}</span></summary>\n",
+            if open { " open" } else { "" },
+            escape(short_id(&solution.id)),
+            solution.phase,
+            escape(&solution.agent_id),
+            if solution.model.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", escape(&solution.model))
+            },
+            solution.verification_passes,
+            solution.verification_failures,
+            solution.verification_score,
+        ));
+        html.push_str(&format!(
+            "<p class=\"meta\">temperature {:.2} &middot; {} tokens</p>\n",
+            solution.temperature, solution.token_count
+        ));
+
+        let verdicts: Vec<_> = self
+            .output
+            .verifications
+            .iter()
+            .filter(|v| v.solution_id == solution.id)
+            .collect();
+        if !verdicts.is_empty() {
+            html.push_str("<ul class=\"verdicts\">\n");
+            for verdict in verdicts {
+                let verifier = if verdict.verifier_model.is_empty() {
+                    &verdict.verifying_agent_id
+                } else {
+                    &verdict.verifier_model
+                };
+                html.push_str(&format!(
+                    "<li><span class=\"badge {}\">{}</span> {:.2} by {}: {}</li>\n",
+                    if verdict.is_correct {
+                        "verified"
+                    } else {
+                        "failed"
+                    },
+                    if verdict.is_correct { "pass" } else { "fail" },
+                    verdict.score,
+                    escape(verifier),
+                    escape(&verdict.critique)
+                ));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str(&format!(
+            "<pre>{}</pre>\n<details><summary>Reasoning</summary><pre>{}</pre></details>\n",
+            escape(solution.answer.trim()),
+            escape(solution.reasoning.trim())
+        ));
+    }
+}
+
+/// Node indices from `root` following the most-visited child at each step
+fn most_visited_path(tree: &MCTSTree, root: usize) -> HashSet<usize> {
+    let mut path = HashSet::new();
+    let mut current = Some(root);
+    while let Some(idx) = current {
+        if !path.insert(idx) {
+            break;
+        }
+        current = tree.nodes.get(idx).and_then(|node| {
+            node.children
+                .iter()
+                .copied()
+                .filter(|child| *child < tree.nodes.len())
+                .max_by_key(|child| tree.nodes[*child].visits)
+        });
+    }
+    path
+}
+
+fn render_mcts_node(
+    tree: &MCTSTree,
+    idx: usize,
+    best_path: &HashSet<usize>,
+    rendered: &mut HashSet<usize>,
+    html: &mut String,
+) {
+    let Some(node) = tree.nodes.get(idx) else {
+        return;
+    };
+    if !rendered.insert(idx) {
+        return;
+    }
+    let on_path = best_path.contains(&idx);
+    let action = node
+        .state
+        .conversation_history
+        .last()
+        .map(|msg| msg.content.as_str())
+        .unwrap_or("root");
+    let preview: String = action.chars().take(ACTION_PREVIEW_LEN).collect();
+    html.push_str(&format!(
+        "<details class=\"{}\"{}>\n<summary>#{idx} {}<span class=\"meta\"> \
+         visits {} &middot; mean {:.2} &middot; std {:.2} &middot; prior {:.2}</span></summary>\n",
+        if on_path { "best" } else { "" },
+        if on_path { " open" } else { "" },
+        escape(&preview),
+        node.visits,
+        node.mean_value(),
+        node.value_std(),
+        node.prior,
+    ));
+    if preview.len() < action.len() {
+        html.push_str(&format!("<pre>{}</pre>\n", escape(action)));
+    }
+    for child in &node.children {
+        render_mcts_node(tree, *child, best_path, rendered, html);
+    }
+    html.push_str("</details>\n");
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..SHORT_ID_LEN).unwrap_or(id)
+}
+
+/// `text` with HTML special characters escaped
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::{DialogueState, MCTSNode, Message};
+    use crate::types::{GenerationPhase, SelectionMethod, VerificationResult};
+    use chrono::Utc;
+
+    fn solution(agent: &str, answer: &str, parents: &[&Solution]) -> Solution {
+        let mut solution = Solution::new(
+            agent.to_string(),
+            "Because.".to_string(),
+            answer.to_string(),
+            0.5,
+            10,
+        );
+        solution.parent_ids = parents.iter().map(|p| p.id.clone()).collect();
+        solution
+    }
+
+    fn output() -> MarsOutput {
+        let first = solution("agent-1", "4", &[]);
+        let second = solution("agent-2", "<b>5</b>", &[]);
+        let stray = solution("agent-3", "6", &[]);
+        let mut merged = solution("aggregator", "4", &[&first, &second]);
+        merged.phase = GenerationPhase::Aggregated;
+        merged.is_verified = true;
+        merged.verification_passes = 2;
+        let mut verdict =
+            VerificationResult::new(merged.id.clone(), true, 0.95, "verifier".to_string());
+        verdict.critique = "Sound & complete".to_string();
+
+        MarsOutput {
+            answer: "4".to_string(),
+            reasoning: String::new(),
+            final_solution_id: merged.id.clone(),
+            all_solutions: vec![first, second, stray, merged],
+            verifications: vec![verdict],
+            selection_method: SelectionMethod::BestVerified,
+            iterations: 0,
+            total_tokens: 40,
+            total_prompt_tokens: 0,
+            total_completion_tokens: 0,
+            usage_estimated: false,
+            cost: Default::default(),
+            tags: Vec::new(),
+            run_id: "run-1".to_string(),
+            budget_truncated: false,
+            typed_answer: None,
+            alternatives: Vec::new(),
+            confidence: 0.0,
+            strategies: Vec::new(),
+            phase_timings: Vec::new(),
+            completed_at: Utc::now(),
+        }
+    }
+
+    fn mcts_tree() -> MCTSTree {
+        let state = |content: &str| {
+            DialogueState::new(
+                String::new(),
+                vec![Message::new("assistant", content)],
+                "q".to_string(),
+            )
+        };
+        let mut root = MCTSNode::new(
+            DialogueState::new(String::new(), Vec::new(), "q".to_string()),
+            None,
+        );
+        root.children = vec![1, 2];
+        root.visits = 5;
+        let mut explored = MCTSNode::new(state("Try induction"), Some(0));
+        explored.visits = 4;
+        let mut abandoned = MCTSNode::new(state("Guess"), Some(0));
+        abandoned.visits = 1;
+        MCTSTree {
+            root: Some(0),
+            nodes: vec![root, explored, abandoned],
+        }
+    }
+
+    #[test]
+    fn test_renders_lineage_with_verification_overlay() {
+        let output = output();
+        let html = HtmlReport::new(&output).render();
+
+        let lineage = html.find("Solution lineage").unwrap();
+        let others = html.find("Other solutions").unwrap();
+        let final_node = html.find("<details class=\"final\" open>").unwrap();
+        assert!(lineage < final_node && final_node < others);
+        // Both parents sit in the lineage; the stray solution does not
+        assert_eq!(html[lineage..others].matches("by agent-").count(), 2);
+        assert!(html[others..].contains("by agent-3"));
+
+        assert!(html.contains("<span class=\"badge verified\">pass</span> 0.95"));
+        assert!(html.contains("Sound &amp; complete"));
+        assert!(html.contains("&lt;b&gt;5&lt;/b&gt;"));
+        assert!(!html.contains("MCTS search tree"));
+    }
+
+    #[test]
+    fn test_renders_mcts_tree_with_best_path() {
+        let output = output();
+        let tree = mcts_tree();
+        let html = HtmlReport::new(&output)
+            .with_mcts_tree(Some(&tree))
+            .render();
+
+        assert!(html.contains("MCTS search tree"));
+        assert!(html.contains("<details class=\"best\" open>\n<summary>#1 Try induction"));
+        assert!(html.contains("<details class=\"\">\n<summary>#2 Guess"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.html");
+        HtmlReport::new(&output).write(&path).unwrap();
+        assert!(
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .starts_with("<!DOCTYPE html>")
+        );
+    }
+}
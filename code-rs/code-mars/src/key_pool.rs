@@ -0,0 +1,495 @@
+//! Spreading provider calls across several API keys.
+//!
+//! Heavy parallel runs hit per-key rate limits long before per-account ones.
+//! [`KeyPoolProvider`] holds one provider per key and picks a key for every
+//! call, either in turn or by fewest requests in flight ([`KeySelection`]).
+//! A key that is rejected ([`MarsError::Unauthorized`]) or rate limited
+//! ([`MarsError::RateLimited`]) is quarantined for a while and the call moves
+//! on to the next key; other errors are returned as they are. When every key
+//! is quarantined, calls go to the key released soonest.
+//!
+//! [`provider_from_spec`](crate::model_router::provider_from_spec) builds a
+//! pool for specs with more than one key.
+
+use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+use crate::provider_config::ProviderSpec;
+use crate::structured::ResponseFormat;
+use crate::tools::{ToolAwareResponse, ToolSpec};
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Quarantine for a rate-limited key that sent no `Retry-After`, unless overridden
+const DEFAULT_RATE_LIMIT_QUARANTINE: Duration = Duration::from_secs(60);
+
+/// Quarantine for a rejected key, unless overridden
+const DEFAULT_AUTH_QUARANTINE: Duration = Duration::from_secs(15 * 60);
+
+/// How a [`KeyPoolProvider`] picks the key for a call
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySelection {
+    /// Each key in turn
+    #[default]
+    RoundRobin,
+    /// The key with the fewest requests in flight, in turn among ties
+    LeastLoaded,
+}
+
+impl KeySelection {
+    /// Whether this is the default, round-robin selection
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// One key's provider and load
+struct KeySlot {
+    provider: Arc<dyn LLMProvider>,
+    in_flight: AtomicUsize,
+    /// When the key may be used again, if quarantined
+    quarantined_until: Mutex<Option<Instant>>,
+}
+
+impl KeySlot {
+    fn quarantined_until(&self) -> Option<Instant> {
+        *self
+            .quarantined_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn is_available(&self, now: Instant) -> bool {
+        self.quarantined_until().is_none_or(|until| until <= now)
+    }
+}
+
+/// Counts a call as in flight on a key until dropped
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn start(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Provider that spreads calls across one provider per API key
+pub struct KeyPoolProvider {
+    slots: Vec<KeySlot>,
+    selection: KeySelection,
+    /// Round-robin position
+    next: AtomicUsize,
+    rate_limit_quarantine: Duration,
+    auth_quarantine: Duration,
+}
+
+impl KeyPoolProvider {
+    /// Pool `providers`, which should be the same model behind different keys
+    ///
+    /// Fails if `providers` is empty.
+    pub fn new(providers: Vec<Arc<dyn LLMProvider>>) -> Result<Self> {
+        if providers.is_empty() {
+            return Err(MarsError::InvalidConfiguration(
+                "A key pool needs at least one provider".to_string(),
+            ));
+        }
+        Ok(Self {
+            slots: providers
+                .into_iter()
+                .map(|provider| KeySlot {
+                    provider,
+                    in_flight: AtomicUsize::new(0),
+                    quarantined_until: Mutex::new(None),
+                })
+                .collect(),
+            selection: KeySelection::default(),
+            next: AtomicUsize::new(0),
+            rate_limit_quarantine: DEFAULT_RATE_LIMIT_QUARANTINE,
+            auth_quarantine: DEFAULT_AUTH_QUARANTINE,
+        })
+    }
+
+    /// Pool a provider for each of the spec's keys, built by `build` from a
+    /// copy of the spec holding just that key
+    pub fn from_spec(
+        spec: &ProviderSpec,
+        build: impl Fn(&ProviderSpec) -> Arc<dyn LLMProvider>,
+    ) -> Result<Self> {
+        let providers = spec
+            .all_api_keys()
+            .into_iter()
+            .map(|key| {
+                let mut single = spec.clone().with_api_key(key);
+                single.api_keys.clear();
+                build(&single)
+            })
+            .collect();
+        Ok(Self::new(providers)?.with_selection(spec.key_selection))
+    }
+
+    /// Pick keys by `selection`
+    pub fn with_selection(mut self, selection: KeySelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Quarantine rate-limited keys for `quarantine`, or as long as their
+    /// `Retry-After` asks
+    pub fn with_rate_limit_quarantine(mut self, quarantine: Duration) -> Self {
+        self.rate_limit_quarantine = quarantine;
+        self
+    }
+
+    /// Quarantine rejected keys for `quarantine`
+    pub fn with_auth_quarantine(mut self, quarantine: Duration) -> Self {
+        self.auth_quarantine = quarantine;
+        self
+    }
+
+    /// Number of keys in the pool
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Whether the pool has no keys (never true for a constructed pool)
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Number of keys not currently quarantined
+    pub fn available(&self) -> usize {
+        let now = Instant::now();
+        self.slots.iter().filter(|s| s.is_available(now)).count()
+    }
+
+    /// Index of the key for the next attempt, skipping keys already `tried`
+    fn select(&self, tried: &[usize]) -> Option<usize> {
+        let now = Instant::now();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let candidates = (0..self.slots.len())
+            .map(|offset| (start + offset) % self.slots.len())
+            .filter(|i| !tried.contains(i));
+        let mut available = candidates
+            .clone()
+            .filter(|&i| self.slots[i].is_available(now));
+        let chosen = match self.selection {
+            KeySelection::RoundRobin => available.next(),
+            KeySelection::LeastLoaded => {
+                available.min_by_key(|&i| self.slots[i].in_flight.load(Ordering::Relaxed))
+            }
+        };
+        // With every key quarantined, the first attempt goes to the one released soonest
+        chosen.or_else(|| {
+            tried
+                .is_empty()
+                .then(|| candidates.min_by_key(|&i| self.slots[i].quarantined_until()))
+                .flatten()
+        })
+    }
+
+    /// Quarantine key `index` after `error`, if the error is the key's fault
+    fn quarantine(&self, index: usize, error: &MarsError) -> bool {
+        let duration = match error {
+            MarsError::Unauthorized(_) => self.auth_quarantine,
+            MarsError::RateLimited { retry_after, .. } => {
+                retry_after.unwrap_or(self.rate_limit_quarantine)
+            }
+            _ => return false,
+        };
+        let slot = &self.slots[index];
+        tracing::warn!(
+            "{} key {} of {} quarantined for {duration:?}: {error}",
+            slot.provider.model_id(),
+            index + 1,
+            self.slots.len()
+        );
+        *slot
+            .quarantined_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now() + duration);
+        true
+    }
+
+    /// Run `call` on a key, moving on to the next key while keys are rejected
+    /// or rate limited
+    async fn call<T, F, Fut>(&self, call: F) -> Result<T>
+    where
+        F: Fn(Arc<dyn LLMProvider>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut tried = Vec::new();
+        let mut last_error = None;
+        while let Some(index) = self.select(&tried) {
+            tried.push(index);
+            let slot = &self.slots[index];
+            let result = {
+                let _in_flight = InFlight::start(&slot.in_flight);
+                call(Arc::clone(&slot.provider)).await
+            };
+            match result {
+                Err(e) if self.quarantine(index, &e) => last_error = Some(e),
+                result => return result,
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            MarsError::ClientError(format!("{}: no API key available", self.model_id()))
+        }))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for KeyPoolProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        self.call(|p| async move { p.complete(prompt, system_prompt).await })
+            .await
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        self.call(|p| async move {
+            p.complete_with_temperature(prompt, system_prompt, temperature)
+                .await
+        })
+        .await
+    }
+
+    async fn complete_n(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        n: usize,
+        temperature: Option<f32>,
+    ) -> Result<Vec<CompletionResponse>> {
+        self.call(|p| async move { p.complete_n(prompt, system_prompt, n, temperature).await })
+            .await
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        self.call(|p| async move { p.stream(prompt, system_prompt).await })
+            .await
+    }
+
+    fn provider_name(&self) -> &str {
+        self.slots[0].provider.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.slots[0].provider.model_name()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.call(|p| async move { p.embed(texts).await }).await
+    }
+
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        self.call(|p| async move { p.complete_structured(prompt, system_prompt, format).await })
+            .await
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        self.call(|p| async move { p.complete_with_tools(prompt, system_prompt, tools).await })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers with its key, or fails with `error` while it is set
+    struct Keyed {
+        key: &'static str,
+        error: Mutex<Option<fn() -> MarsError>>,
+        calls: AtomicUsize,
+    }
+
+    impl Keyed {
+        fn new(key: &'static str) -> Arc<Self> {
+            Arc::new(Self {
+                key,
+                error: Mutex::new(None),
+                calls: AtomicUsize::new(0),
+            })
+        }
+
+        fn failing(key: &'static str, error: fn() -> MarsError) -> Arc<Self> {
+            let provider = Self::new(key);
+            *provider.error.lock().unwrap() = Some(error);
+            provider
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for Keyed {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            match *self.error.lock().unwrap() {
+                Some(error) => Err(error()),
+                None => Ok(self.key.into()),
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "keyed"
+        }
+    }
+
+    fn pool(providers: &[&Arc<Keyed>]) -> KeyPoolProvider {
+        let providers = providers
+            .iter()
+            .map(|p| Arc::clone(p) as Arc<dyn LLMProvider>)
+            .collect();
+        KeyPoolProvider::new(providers).unwrap()
+    }
+
+    fn rate_limited() -> MarsError {
+        MarsError::RateLimited {
+            message: "429".to_string(),
+            retry_after: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_spreads_calls() {
+        let (a, b) = (Keyed::new("a"), Keyed::new("b"));
+        let pool = pool(&[&a, &b]);
+        for _ in 0..4 {
+            pool.complete("q", None).await.unwrap();
+        }
+        assert_eq!((a.calls(), b.calls()), (2, 2));
+        assert!(KeyPoolProvider::new(Vec::new()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_least_loaded_avoids_busy_keys() {
+        let (a, b) = (Keyed::new("a"), Keyed::new("b"));
+        let pool = pool(&[&a, &b]).with_selection(KeySelection::LeastLoaded);
+        pool.slots[0].in_flight.store(3, Ordering::Relaxed);
+        for _ in 0..3 {
+            assert_eq!(pool.complete("q", None).await.unwrap().text, "b");
+        }
+        assert_eq!(a.calls(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_quarantines_rate_limited_and_rejected_keys() {
+        let limited = Keyed::failing("a", rate_limited);
+        let rejected = Keyed::failing("b", || MarsError::Unauthorized("401".to_string()));
+        let good = Keyed::new("c");
+        let pool =
+            pool(&[&limited, &rejected, &good]).with_rate_limit_quarantine(Duration::from_secs(10));
+
+        for _ in 0..3 {
+            assert_eq!(pool.complete("q", None).await.unwrap().text, "c");
+        }
+        assert_eq!((limited.calls(), rejected.calls()), (1, 1));
+        assert_eq!(pool.available(), 1);
+
+        // The rate-limited key comes back once its quarantine ends
+        *limited.error.lock().unwrap() = None;
+        tokio::time::advance(Duration::from_secs(11)).await;
+        assert_eq!(pool.available(), 2);
+        let texts: Vec<String> = [
+            pool.complete("q", None).await,
+            pool.complete("q", None).await,
+        ]
+        .into_iter()
+        .map(|r| r.unwrap().text)
+        .collect();
+        assert!(texts.contains(&"a".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried_on_other_keys() {
+        let broken = Keyed::failing("a", || MarsError::ClientError("400".to_string()));
+        let good = Keyed::new("b");
+        let pool = pool(&[&broken, &good]);
+        let err = pool.complete("q", None).await.unwrap_err();
+        assert!(matches!(err, MarsError::ClientError(_)));
+        assert_eq!(good.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_all_keys_quarantined_returns_last_error() {
+        let (a, b) = (
+            Keyed::failing("a", rate_limited),
+            Keyed::failing("b", rate_limited),
+        );
+        let pool = pool(&[&a, &b]);
+        let err = pool.complete("q", None).await.unwrap_err();
+        assert!(matches!(err, MarsError::RateLimited { .. }));
+        assert_eq!(pool.available(), 0);
+
+        // Still tries the key released soonest rather than failing outright
+        assert!(pool.complete("q", None).await.is_err());
+        assert_eq!(a.calls() + b.calls(), 3);
+    }
+
+    #[test]
+    fn test_from_spec_builds_a_provider_per_key() {
+        let spec = ProviderSpec::new("openai", "gpt-4o")
+            .with_api_key("k1".to_string())
+            .with_api_keys(vec!["k2".to_string(), "k1".to_string(), "k3".to_string()])
+            .with_key_selection(KeySelection::LeastLoaded);
+        let keys = Mutex::new(Vec::new());
+        let pool = KeyPoolProvider::from_spec(&spec, |single| {
+            keys.lock().unwrap().push(single.all_api_keys());
+            Keyed::new("x")
+        })
+        .unwrap();
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.selection, KeySelection::LeastLoaded);
+        assert_eq!(
+            keys.into_inner().unwrap(),
+            vec![vec!["k1"], vec!["k2"], vec!["k3"]]
+        );
+    }
+}
@@ -23,35 +23,137 @@
 //! ```
 
 pub mod config;
+pub mod config_file;
 pub mod error;
 pub mod types;
 
-pub use config::MarsConfig;
-pub use error::{MarsError, Result};
-pub use types::{MarsEvent, MarsOutput, Solution};
+pub use config::{ConfigError, MarsConfig};
+pub use error::{MarsError, Result, TimeoutKind};
+pub use types::{
+    AnswerType, DerivationTree, MarsEvent, MarsEventRecord, MarsOutput, PhaseTiming, Preset,
+    Provenance, QueryClassification, RankedAnswer, Solution, TypedAnswer,
+};
 
 // These will be implemented next
 pub mod agent;
 pub mod aggregator;
+pub mod answer;
+pub mod anthropic;
+pub mod azure;
+pub mod beam;
+pub mod bench;
+pub mod best_of_n;
+pub mod classifier;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod code_actions;
+pub mod code_verifier;
+pub mod confidence;
 pub mod coordinator;
+pub mod cost;
+pub mod debug;
+pub mod event_bus;
+pub mod gemini;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod genetic;
+#[cfg(feature = "report-html")]
+pub mod html_report;
+pub mod key_pool;
+#[cfg(feature = "mcp")]
+pub mod mcp;
 pub mod mcts;
 pub mod moa;
 pub mod model_router;
+pub mod multi_router;
+pub mod observe;
+pub mod ollama;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod pairwise;
+pub mod plan_solve;
 pub mod prompts;
 pub mod provider_config;
+pub mod rate_limit;
+#[cfg(feature = "test-utils")]
+pub mod replay;
+pub mod report;
+pub mod response_cache;
+pub mod retry;
+pub mod run_handle;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod strategy;
+pub mod strategy_store;
+pub mod structured;
+pub mod templates;
+#[cfg(test)]
+mod test_support;
+pub mod tools;
+pub mod trace;
+pub mod verification_cache;
 pub mod verifier;
+pub mod webhook;
 pub mod workspace;
+pub mod workspace_log;
+pub mod workspace_store;
 
 pub use agent::Agent;
 pub use aggregator::Aggregator;
-pub use coordinator::MarsCoordinator;
+pub use anthropic::AnthropicProvider;
+pub use azure::AzureOpenAIProvider;
+pub use beam::{BeamSearchAggregator, BeamSearchConfig};
+pub use bench::{BenchItem, BenchReport, Benchmark, Dataset, DatasetFormat, Grader};
+pub use best_of_n::{BestOfNAggregator, BestOfNConfig};
+pub use classifier::QueryClass;
+pub use code_actions::{CodeAction, CodeEditActions, CodeEditKind};
+pub use code_verifier::{CodeVerifier, ExecutionLimits, ExecutionSandbox, TestCase};
+pub use coordinator::{MarsCoordinator, RunOptions};
+pub use cost::{CostSummary, CostTracker, CostTrackingProvider, ModelPricing, PricingTable};
+pub use event_bus::EventBus;
+pub use gemini::{GeminiProvider, SafetySetting};
+#[cfg(feature = "report-html")]
+pub use html_report::HtmlReport;
+pub use genetic::{GeneticAggregator, GeneticConfig};
+#[cfg(feature = "grpc")]
+pub use grpc::MarsGrpcService;
+pub use key_pool::{KeyPoolProvider, KeySelection};
+#[cfg(feature = "mcp")]
+pub use mcp::MarsMcpServer;
+pub use mcts::{ActionGenerator, LlmValueFunction, ValueFunction};
 pub use moa::MoaAggregator;
-pub use model_router::{LLMProvider, LiteLLMRouter, ModelClientRouter, ModelStream};
-pub use provider_config::{ProviderRoutingConfig, ProviderSpec, RoutingStrategy};
-pub use strategy::StrategyNetwork;
-pub use verifier::Verifier;
-pub use workspace::Workspace;
+pub use multi_router::MultiProviderRouter;
+pub use observe::{CallStats, ObservedProvider};
+pub use ollama::OllamaProvider;
+#[cfg(feature = "otel")]
+pub use otel::{OtelTracing, OtlpProtocol};
+pub use model_router::{
+    CompletionResponse, HttpOptions, HttpTimeouts, LLMProvider, LiteLLMRouter, ModelClientRouter,
+    ModelStream, provider_from_spec,
+};
+pub use pairwise::{EloRanking, PairwiseVerifier};
+pub use plan_solve::PlanAndSolveAggregator;
+pub use provider_config::{MarsPhase, ProviderRoutingConfig, ProviderSpec, RoutingStrategy};
+pub use rate_limit::{RateLimitedProvider, RateLimits};
+#[cfg(feature = "test-utils")]
+pub use replay::{Cassette, RecordingProvider, ReplayProvider};
+pub use response_cache::CachingProvider;
+pub use retry::RetryingProvider;
+pub use run_handle::{RunCanceller, RunHandle, RunUpdate};
+#[cfg(feature = "server")]
+pub use server::MarsServer;
+pub use strategy::{StrategyGraph, StrategyNetwork};
+pub use strategy_store::StrategyStore;
+pub use structured::ResponseFormat;
+pub use templates::{PromptTemplates, PromptVariables};
+pub use tools::{ToolAwareResponse, ToolCall, ToolSpec};
+pub use trace::{TraceEntry, TraceWriter, TracingProvider};
+pub use verification_cache::VerificationCache;
+pub use verifier::{SolutionVerifier, Verifier};
+pub use webhook::{RunSummary, WebhookConfig};
+pub use workspace::{SnapshotId, Workspace, WorkspaceDiff, WorkspaceExport};
+pub use workspace_log::{WorkspaceChange, WorkspaceLogEntry};
+pub use workspace_store::{MemoryWorkspaceStore, SolutionOrder, SolutionQuery, WorkspaceStore};
 
 /// MARS module version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,8 @@
+use clap::Parser;
+use code_mars::cli::{Cli, run_main};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    run_main(Cli::parse()).await?;
+    Ok(())
+}
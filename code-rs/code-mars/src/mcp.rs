@@ -0,0 +1,524 @@
+//! Model Context Protocol server, behind the `mcp` feature.
+//!
+//! [`MarsMcpServer`] exposes MARS to MCP hosts (Claude Desktop, IDEs, other
+//! agents) as a single `mars_solve` tool taking a query and, optionally, the
+//! number of agents and the aggregation method. Each call is a full run; its
+//! answer comes back as text content, with the selection method, confidence,
+//! tokens and cost as structured content. Hosts that send a progress token
+//! get a `notifications/progress` message as each phase starts.
+//!
+//! Two transports are supported: newline-delimited JSON-RPC over stdio
+//! ([`MarsMcpServer::serve_stdio`]), for hosts that launch the server as a
+//! subprocess, and HTTP with server-sent events
+//! ([`MarsMcpServer::serve_sse`]): a client opens `GET /sse`, is told the
+//! `/messages?session_id=...` URL to POST requests to, and receives the
+//! responses on its event stream.
+
+use crate::config::{AGGREGATION_NAMES, MarsConfig};
+use crate::coordinator::MarsCoordinator;
+use crate::model_router::LLMProvider;
+use crate::server::{
+    Body, event_stream_response, json_response, phase_started, read_body, serve_connections,
+};
+use crate::{MarsError, Result};
+use bytes::Bytes;
+use hyper::body::Incoming;
+use hyper::{Method, Request, Response, StatusCode};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// Name of the one tool served
+pub const TOOL_NAME: &str = "mars_solve";
+
+/// Protocol versions the server speaks, newest last
+const PROTOCOL_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+
+/// Most agents a tool call may ask for
+const MAX_AGENTS: u64 = 16;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Serves MARS as an MCP tool
+pub struct MarsMcpServer {
+    config: MarsConfig,
+    provider: Arc<dyn LLMProvider>,
+    /// Event streams of open SSE sessions, by session ID
+    sessions: Mutex<HashMap<String, mpsc::Sender<Value>>>,
+}
+
+impl MarsMcpServer {
+    /// Answer tool calls with runs of `config` on `provider`
+    pub fn new(config: MarsConfig, provider: Arc<dyn LLMProvider>) -> Self {
+        Self {
+            config,
+            provider,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Serve the host on stdin and stdout until stdin closes
+    pub async fn serve_stdio(self) -> Result<()> {
+        let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+        Arc::new(self).serve_io(stdin, tokio::io::stdout()).await
+    }
+
+    /// Serve newline-delimited JSON-RPC read from `reader`, writing to
+    /// `writer`, until `reader` ends
+    ///
+    /// Requests are handled concurrently, so a host can ping or list tools
+    /// while a run is in progress.
+    pub async fn serve_io<R, W>(self: Arc<Self>, reader: R, mut writer: W) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::channel::<Value>(64);
+        let written = tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                writer
+                    .write_all(format!("{message}\n").as_bytes())
+                    .await
+                    .and(writer.flush().await)
+                    .map_err(|e| MarsError::ClientError(format!("MCP output closed: {e}")))?;
+            }
+            Ok(())
+        });
+
+        let mut lines = reader.lines();
+        let mut handlers = Vec::new();
+        while let Some(line) = lines
+            .next_line()
+            .await
+            .map_err(|e| MarsError::ClientError(format!("MCP input failed: {e}")))?
+        {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let server = Arc::clone(&self);
+            let tx = tx.clone();
+            handlers.push(tokio::spawn(async move {
+                server.handle_line(&line, &tx).await;
+            }));
+        }
+        // Finish the calls already made before closing the output
+        for handler in handlers {
+            let _result = handler.await;
+        }
+        drop(tx);
+        written
+            .await
+            .map_err(|e| MarsError::ClientError(format!("MCP output task failed: {e}")))?
+    }
+
+    /// Serve the SSE transport on `listener` until the task is dropped
+    pub async fn serve_sse(self, listener: TcpListener) -> Result<()> {
+        let server = Arc::new(self);
+        serve_connections(listener, move |request| {
+            let server = Arc::clone(&server);
+            async move { server.handle_http(request).await }
+        })
+        .await
+    }
+
+    async fn handle_http(self: Arc<Self>, request: Request<Incoming>) -> Response<Body> {
+        match (request.method(), request.uri().path()) {
+            (&Method::GET, "/sse") => self.open_session(),
+            (&Method::POST, "/messages") => self.post_message(request).await,
+            _ => plain_response(StatusCode::NOT_FOUND, "Not found"),
+        }
+    }
+
+    /// Open an event stream, announcing where to POST its messages
+    fn open_session(self: Arc<Self>) -> Response<Body> {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let (tx, mut messages) = mpsc::channel::<Value>(64);
+        let (events_tx, events) = mpsc::channel::<Bytes>(64);
+        self.lock_sessions().insert(session_id.clone(), tx);
+        tracing::info!(%session_id, "MCP session opened");
+
+        tokio::spawn(async move {
+            let endpoint = format!("event: endpoint\ndata: /messages?session_id={session_id}\n\n");
+            if events_tx.send(Bytes::from(endpoint)).await.is_ok() {
+                while let Some(message) = messages.recv().await {
+                    let event = format!("event: message\ndata: {message}\n\n");
+                    if events_tx.send(Bytes::from(event)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            self.lock_sessions().remove(&session_id);
+            tracing::info!(%session_id, "MCP session closed");
+        });
+        event_stream_response(events)
+    }
+
+    /// Accept a message for a session; its response goes to the session's
+    /// event stream
+    async fn post_message(self: Arc<Self>, request: Request<Incoming>) -> Response<Body> {
+        let session_id = request.uri().query().and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("session_id="))
+                .map(str::to_string)
+        });
+        let Some(tx) = session_id.and_then(|id| self.lock_sessions().get(&id).cloned()) else {
+            return plain_response(StatusCode::NOT_FOUND, "Unknown session");
+        };
+        let body = match read_body(request.into_body()).await {
+            Ok(body) => body,
+            Err(e) => return plain_response(StatusCode::BAD_REQUEST, &e),
+        };
+        let Ok(line) = String::from_utf8(body.to_vec()) else {
+            return plain_response(StatusCode::BAD_REQUEST, "Body is not UTF-8");
+        };
+        tokio::spawn(async move {
+            self.handle_line(&line, &tx).await;
+        });
+        plain_response(StatusCode::ACCEPTED, "Accepted")
+    }
+
+    async fn handle_line(&self, line: &str, tx: &mpsc::Sender<Value>) {
+        let response = match serde_json::from_str::<Value>(line) {
+            Ok(message) => self.handle(message, tx).await,
+            Err(e) => Some(error_response(
+                Value::Null,
+                PARSE_ERROR,
+                &format!("Invalid JSON: {e}"),
+            )),
+        };
+        if let Some(response) = response {
+            // The host has gone; nothing is left to tell it
+            let _result = tx.send(response).await;
+        }
+    }
+
+    /// The response to one JSON-RPC `message`, or `None` for notifications
+    ///
+    /// Progress notifications for a tool call are sent to `notifications`
+    /// while it runs.
+    pub async fn handle(
+        &self,
+        message: Value,
+        notifications: &mpsc::Sender<Value>,
+    ) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // Responses to requests we never make, or junk
+            return id.map(|id| error_response(id, INVALID_REQUEST, "Expected a request"));
+        };
+        // Notifications (initialized, cancelled, ...) need no answer
+        let id = id?;
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = match method {
+            "initialize" => Ok(initialize_result(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": [tool()] })),
+            "tools/call" => self.call_tool(&params, notifications).await,
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method {method}"))),
+        };
+        Some(match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        params: &Value,
+        notifications: &mpsc::Sender<Value>,
+    ) -> std::result::Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if name != TOOL_NAME {
+            return Err((INVALID_PARAMS, format!("Unknown tool {name}")));
+        }
+        let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+        let (query, config) = self
+            .tool_config(&arguments)
+            .map_err(|message| (INVALID_PARAMS, message))?;
+
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::clone(&self.provider));
+        let progress = params
+            .pointer("/_meta/progressToken")
+            .cloned()
+            .map(|token| {
+                let mut events = coordinator.subscribe_events(64);
+                let notifications = notifications.clone();
+                tokio::spawn(async move {
+                    let mut progress = 0;
+                    while let Some(record) = events.recv().await {
+                        if let Some(phase) = phase_started(&record.event) {
+                            progress += 1;
+                            let notification = json!({
+                                "jsonrpc": "2.0",
+                                "method": "notifications/progress",
+                                "params": {
+                                    "progressToken": token,
+                                    "progress": progress,
+                                    "message": phase,
+                                },
+                            });
+                            let _result = notifications.send(notification).await;
+                        }
+                    }
+                })
+            });
+        let output = coordinator.run(&query).await;
+        drop(coordinator);
+        if let Some(progress) = progress {
+            let _result = progress.await;
+        }
+
+        // Run failures are tool errors the model can see, not protocol errors
+        Ok(match output {
+            Ok(output) => json!({
+                "content": [{"type": "text", "text": output.answer.trim()}],
+                "structuredContent": {
+                    "answer": output.answer.trim(),
+                    "selection_method": format!("{:?}", output.selection_method),
+                    "confidence": output.confidence,
+                    "total_tokens": output.total_tokens,
+                    "cost_usd": output.cost.total_usd,
+                    "run_id": output.run_id,
+                },
+                "isError": false,
+            }),
+            Err(e) => json!({
+                "content": [{"type": "text", "text": format!("MARS run failed: {e}")}],
+                "isError": true,
+            }),
+        })
+    }
+
+    /// The query and run config for the tool's `arguments`
+    fn tool_config(&self, arguments: &Value) -> std::result::Result<(String, MarsConfig), String> {
+        let query = arguments
+            .get("query")
+            .and_then(Value::as_str)
+            .filter(|query| !query.trim().is_empty())
+            .ok_or("query must be a non-empty string")?;
+        let mut config = self.config.clone();
+        if let Some(agents) = arguments.get("num_agents").filter(|v| !v.is_null()) {
+            let agents = agents
+                .as_u64()
+                .filter(|agents| (1..=MAX_AGENTS).contains(agents))
+                .ok_or(format!(
+                    "num_agents must be an integer from 1 to {MAX_AGENTS}"
+                ))?;
+            config = config.with_num_agents(agents as usize);
+        }
+        if let Some(aggregation) = arguments.get("aggregation").filter(|v| !v.is_null()) {
+            let name = aggregation.as_str().ok_or("aggregation must be a string")?;
+            config = config
+                .with_aggregation_name(name)
+                .map_err(|e| e.to_string())?;
+        }
+        Ok((query.to_string(), config))
+    }
+
+    fn lock_sessions(&self) -> std::sync::MutexGuard<'_, HashMap<String, mpsc::Sender<Value>>> {
+        self.sessions.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The client's protocol version if supported, else the newest one
+fn initialize_result(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|version| PROTOCOL_VERSIONS.contains(version))
+        .or(PROTOCOL_VERSIONS.last().copied())
+        .unwrap_or_default();
+    json!({
+        "protocolVersion": version,
+        "capabilities": {"tools": {"listChanged": false}},
+        "serverInfo": {"name": "code-mars", "version": env!("CARGO_PKG_VERSION")},
+        "instructions": format!(
+            "Call {TOOL_NAME} for problems that benefit from several independent attempts \
+             that are cross-verified before an answer is chosen. Runs take several model calls."
+        ),
+    })
+}
+
+/// The `mars_solve` tool definition
+fn tool() -> Value {
+    json!({
+        "name": TOOL_NAME,
+        "title": "MARS multi-agent reasoning",
+        "description": "Solve a problem with MARS: several agents explore solutions at different \
+                        temperatures, verify each other's work, improve what failed and \
+                        synthesize a final answer. Slower and costlier than answering directly; \
+                        use for hard reasoning, math and code questions.",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Problem or question to solve, with all the context it needs",
+                },
+                "num_agents": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_AGENTS,
+                    "description": "Number of exploring agents; defaults to the server's config",
+                },
+                "aggregation": {
+                    "type": "string",
+                    "enum": AGGREGATION_NAMES,
+                    "description": "How solutions are combined before verification; \
+                                    defaults to the server's config",
+                },
+            },
+            "required": ["query"],
+        },
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn plain_response(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &json!({ "message": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Four;
+    use tokio::io::BufReader;
+
+    fn server() -> MarsMcpServer {
+        MarsMcpServer::new(MarsConfig::default().with_max_iterations(1), Arc::new(Four))
+    }
+
+    /// Everything the server writes for `requests`, one JSON value per line
+    async fn exchange(requests: &[Value]) -> Vec<Value> {
+        let input: String = requests.iter().map(|r| format!("{r}\n")).collect();
+        let (writer, reader) = tokio::io::duplex(1 << 20);
+        Arc::new(server())
+            .serve_io(BufReader::new(input.as_bytes()), writer)
+            .await
+            .unwrap();
+        let mut lines = BufReader::new(reader).lines();
+        let mut messages = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            messages.push(serde_json::from_str(&line).unwrap());
+        }
+        messages
+    }
+
+    fn response(messages: &[Value], id: i64) -> &Value {
+        messages.iter().find(|m| m["id"] == id).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stdio_initialize_list_and_call() {
+        let messages = exchange(&[
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                   "params": {"protocolVersion": "2025-03-26", "capabilities": {}}}),
+            json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}),
+            json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {
+                "name": TOOL_NAME,
+                "arguments": {"query": "What is 2+2?", "num_agents": 2, "aggregation": "none"},
+                "_meta": {"progressToken": "p1"},
+            }}),
+        ])
+        .await;
+
+        // One response per request and progress for the call; none for the notification
+        let responses: Vec<&Value> = messages.iter().filter(|m| m.get("id").is_some()).collect();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(
+            response(&messages, 1)["result"]["protocolVersion"],
+            "2025-03-26"
+        );
+        let tool = &response(&messages, 2)["result"]["tools"][0];
+        assert_eq!(tool["name"], TOOL_NAME);
+        assert_eq!(tool["inputSchema"]["required"], json!(["query"]));
+
+        let result = &response(&messages, 3)["result"];
+        assert_eq!(result["isError"], false);
+        assert_eq!(
+            result["content"][0]["text"],
+            result["structuredContent"]["answer"]
+        );
+        let progress: Vec<&Value> = messages
+            .iter()
+            .filter(|m| m["method"] == "notifications/progress")
+            .collect();
+        assert_eq!(progress[0]["params"]["progressToken"], "p1");
+        assert_eq!(progress[0]["params"]["message"], "exploration");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_calls() {
+        let messages = exchange(&[
+            json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                   "params": {"name": "other", "arguments": {}}}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                   "params": {"name": TOOL_NAME, "arguments": {"query": "q", "num_agents": 0}}}),
+            json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                   "params": {"name": TOOL_NAME, "arguments": {"query": "q", "aggregation": "x"}}}),
+            json!({"jsonrpc": "2.0", "id": 4, "method": "resources/list"}),
+        ])
+        .await;
+        for id in 1..=3 {
+            assert_eq!(response(&messages, id)["error"]["code"], INVALID_PARAMS);
+        }
+        assert_eq!(response(&messages, 4)["error"]["code"], METHOD_NOT_FOUND);
+
+        let server = server();
+        let (tx, mut rx) = mpsc::channel(1);
+        server.handle_line("not json", &tx).await;
+        assert_eq!(rx.recv().await.unwrap()["error"]["code"], PARSE_ERROR);
+        let initialize = initialize_result(&json!({"protocolVersion": "1999-01-01"}));
+        assert_eq!(initialize["protocolVersion"], "2025-06-18");
+    }
+
+    #[tokio::test]
+    async fn test_sse_session_receives_responses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(server().serve_sse(listener));
+        let client = reqwest::Client::new();
+
+        let mut events = client.get(format!("{base}/sse")).send().await.unwrap();
+        let endpoint = String::from_utf8(events.chunk().await.unwrap().unwrap().to_vec()).unwrap();
+        let path = endpoint
+            .strip_prefix("event: endpoint\ndata: ")
+            .unwrap()
+            .trim_end();
+        assert!(path.starts_with("/messages?session_id="));
+
+        let posted = client
+            .post(format!("{base}{path}"))
+            .body(json!({"jsonrpc": "2.0", "id": 7, "method": "ping"}).to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(posted.status(), 202);
+        let event = String::from_utf8(events.chunk().await.unwrap().unwrap().to_vec()).unwrap();
+        let data = event.strip_prefix("event: message\ndata: ").unwrap();
+        let message: Value = serde_json::from_str(data.trim_end()).unwrap();
+        assert_eq!(message, json!({"jsonrpc": "2.0", "id": 7, "result": {}}));
+
+        let unknown = client
+            .post(format!("{base}/messages?session_id=nope"))
+            .body("{}")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), 404);
+    }
+}
@@ -6,11 +6,34 @@
 //!
 //! # Algorithm
 //!
-//! 1. **Selection**: Traverse tree using UCB formula to select promising nodes
+//! 1. **Selection**: Traverse tree with the configured tree policy (UCB1,
+//!    PUCT, or Thompson sampling) to select promising nodes
 //! 2. **Expansion**: Generate N actions (LLM completions) and create child nodes
-//! 3. **Simulation**: Rollout to depth D using random actions
+//! 3. **Simulation**: Rollout to depth D using random actions, then score the
+//!    final state with a [`ValueFunction`] (an LLM rating by default)
 //! 4. **Backpropagation**: Update visit counts and values up the tree
 //!
+//! With `parallel_simulations > 1`, batches of simulations run concurrently
+//! (leaf parallelism). Each in-flight simulation puts a virtual loss on its
+//! path so the other selections in the batch spread across the tree instead
+//! of expanding the same leaf.
+//!
+//! The simulation loop stops early once the search has converged: when the
+//! most visited root child holds `early_stop_visit_share` of the visits, or
+//! its values vary by less than `early_stop_value_std`.
+//!
+//! With `use_transpositions`, nodes whose dialogue states normalize to the
+//! same text share visit and value statistics through a transposition table,
+//! and a state that was already evaluated is not sent to the LLM again.
+//!
+//! Actions are free-form assistant replies by default. Plug in an
+//! [`ActionGenerator`] (such as [`crate::code_actions::CodeEditActions`]) to
+//! search over a different action space.
+//!
+//! Attach an event channel with [`MCTS::with_events`] to receive
+//! [`MarsEvent::NodeExpanded`] and [`MarsEvent::SimulationCompleted`] as the
+//! search runs.
+//!
 //! # Example
 //!
 //! ```no_run
@@ -27,19 +50,28 @@
 //! );
 //!
 //! // let final_state = mcts.search(state, provider).await?;
+//! // std::fs::write("tree.json", mcts.export_tree()?)?;
 //! # Ok(())
 //! # }
 //! ```
 
+use crate::types::{MarsEvent, MctsSelectionPolicy, MctsTemperatureSchedule};
 use crate::{LLMProvider, Result};
+use async_trait::async_trait;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::Instrument;
 
 /// Configuration for MCTS algorithm
 #[derive(Clone, Debug)]
 pub struct MCTSConfig {
     /// How deep to simulate (default: 1)
     pub simulation_depth: usize,
-    /// UCB exploration weight (default: 0.2)
+    /// UCB/PUCT exploration weight (default: 0.2)
     pub exploration_weight: f32,
     /// Number of MCTS iterations (default: 2)
     pub num_simulations: usize,
@@ -47,10 +79,29 @@ pub struct MCTSConfig {
     pub num_actions: usize,
     /// Temperature for action generation (default: 1.0)
     pub generation_temperature: f32,
+    /// How the generation temperature changes with depth (default: constant)
+    pub temperature_schedule: MctsTemperatureSchedule,
     /// Temperature for evaluation (default: 0.1)
     pub evaluation_temperature: f32,
     /// Max conversation history length (default: 10)
     pub max_history_length: usize,
+    /// Simulations run concurrently per batch (default: 1, sequential)
+    pub parallel_simulations: usize,
+    /// Value subtracted per in-flight simulation through a node during
+    /// selection (default: 1.0)
+    pub virtual_loss: f32,
+    /// Tree policy used during selection (default: UCB1)
+    pub selection_policy: MctsSelectionPolicy,
+    /// Share statistics and evaluations between duplicate states (default: false)
+    pub use_transpositions: bool,
+    /// Stop once the most visited root child has this share of root visits
+    /// (default: None, disabled)
+    pub early_stop_visit_share: Option<f32>,
+    /// Stop once the most visited root child's value standard deviation is
+    /// at most this (default: None, disabled)
+    pub early_stop_value_std: Option<f32>,
+    /// Simulations to run before early stopping is considered (default: 4)
+    pub early_stop_min_simulations: usize,
 }
 
 impl Default for MCTSConfig {
@@ -61,14 +112,22 @@ impl Default for MCTSConfig {
             num_simulations: 2,
             num_actions: 3,
             generation_temperature: 1.0,
+            temperature_schedule: MctsTemperatureSchedule::Constant,
             evaluation_temperature: 0.1,
             max_history_length: 10,
+            parallel_simulations: 1,
+            virtual_loss: 1.0,
+            selection_policy: MctsSelectionPolicy::Ucb1,
+            use_transpositions: false,
+            early_stop_visit_share: None,
+            early_stop_value_std: None,
+            early_stop_min_simulations: 4,
         }
     }
 }
 
 /// Represents a single message in dialogue history
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Message {
     /// Role: "user" or "assistant"
     pub role: String,
@@ -87,7 +146,7 @@ impl Message {
 }
 
 /// Represents a dialogue state in the MCTS tree
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DialogueState {
     /// System prompt for the conversation
     pub system_prompt: String,
@@ -106,10 +165,111 @@ impl DialogueState {
             current_query: query,
         }
     }
+
+    /// Hash of the state with case and whitespace normalized
+    ///
+    /// States reached along different paths with the same key are treated
+    /// as the same position by the transposition table.
+    pub fn transposition_key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        normalize_text(&self.system_prompt).hash(&mut hasher);
+        for msg in &self.conversation_history {
+            msg.role.hash(&mut hasher);
+            normalize_text(&msg.content).hash(&mut hasher);
+        }
+        normalize_text(&self.current_query).hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Scores the dialogue state reached at the end of an MCTS simulation
+///
+/// Implement this to plug reward models, execution-based scoring, or cheap
+/// heuristics into the search via [`MCTS::with_value_function`].
+#[async_trait]
+pub trait ValueFunction: Send + Sync {
+    /// Score a state from 0.0 (poor) to 1.0 (excellent)
+    async fn score(&self, state: &DialogueState) -> Result<f32>;
+}
+
+/// Proposes and applies the actions MCTS searches over
+///
+/// Actions are plain strings so the tree, priors, and exports stay generic;
+/// implementations choose their own encoding.
+#[async_trait]
+pub trait ActionGenerator: Send + Sync {
+    /// Propose up to `num_actions` actions from a state, sampling at
+    /// `temperature`
+    async fn generate(
+        &self,
+        state: &DialogueState,
+        num_actions: usize,
+        temperature: f32,
+        provider: &dyn LLMProvider,
+    ) -> Result<Vec<String>>;
+
+    /// Apply an action, returning the state it leads to
+    async fn apply(
+        &self,
+        state: &DialogueState,
+        action: &str,
+        provider: &dyn LLMProvider,
+    ) -> Result<DialogueState>;
+}
+
+/// Default value function: asks an LLM to rate the conversation
+pub struct LlmValueFunction {
+    provider: Arc<dyn LLMProvider>,
+    temperature: f32,
+}
+
+impl LlmValueFunction {
+    /// Create a value function that rates states with `provider`
+    pub fn new(provider: Arc<dyn LLMProvider>, temperature: f32) -> Self {
+        Self {
+            provider,
+            temperature,
+        }
+    }
+}
+
+#[async_trait]
+impl ValueFunction for LlmValueFunction {
+    async fn score(&self, state: &DialogueState) -> Result<f32> {
+        llm_evaluate(state, self.provider.as_ref(), self.temperature).await
+    }
+}
+
+/// Ask an LLM to rate a conversation from 0.0 to 1.0 (0.5 if unparsable)
+async fn llm_evaluate(
+    state: &DialogueState,
+    provider: &dyn LLMProvider,
+    temperature: f32,
+) -> Result<f32> {
+    let mut prompt = String::new();
+    for msg in &state.conversation_history {
+        prompt.push_str(&format!("{}: {}\n", msg.role, msg.content));
+    }
+    prompt.push_str(
+        "\n\nEvaluate the quality of this conversation on a scale from 0 to 1, where 0 is poor and 1 is excellent. \
+         Consider factors such as coherence, relevance, and engagement. Respond with only a number.",
+    );
+
+    let response = provider
+        .complete_with_temperature(&prompt, Some(&state.system_prompt), temperature)
+        .await?;
+
+    // Parse score from response
+    Ok(response
+        .text
+        .trim()
+        .parse::<f32>()
+        .unwrap_or(0.5)
+        .clamp(0.0, 1.0))
 }
 
 /// Node in the MCTS tree
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MCTSNode {
     /// The dialogue state at this node
     pub state: DialogueState,
@@ -121,6 +281,16 @@ pub struct MCTSNode {
     pub visits: usize,
     /// Cumulative value from simulations
     pub value: f32,
+    /// Prior probability of the action leading here, used by PUCT
+    #[serde(default = "default_prior")]
+    pub prior: f32,
+    /// Cumulative squared value from simulations
+    #[serde(default)]
+    pub value_sq: f32,
+}
+
+fn default_prior() -> f32 {
+    1.0
 }
 
 impl MCTSNode {
@@ -132,8 +302,143 @@ impl MCTSNode {
             children: Vec::new(),
             visits: 0,
             value: 0.0,
+            prior: default_prior(),
+            value_sq: 0.0,
+        }
+    }
+
+    /// Mean simulation value (0.0 before the first visit)
+    pub fn mean_value(&self) -> f32 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.value / self.visits as f32
         }
     }
+
+    /// Standard deviation of the simulation values (0.0 before the first visit)
+    pub fn value_std(&self) -> f32 {
+        if self.visits == 0 {
+            return 0.0;
+        }
+        let mean = self.mean_value();
+        (self.value_sq / self.visits as f32 - mean * mean)
+            .max(0.0)
+            .sqrt()
+    }
+}
+
+/// Snapshot of a search tree, for inspection after the search has run
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MCTSTree {
+    /// Root node index (None before the first search)
+    pub root: Option<usize>,
+    /// All nodes; `parent` and `children` index into this list
+    pub nodes: Vec<MCTSNode>,
+}
+
+impl MCTSTree {
+    /// Serialize the tree as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| {
+            crate::MarsError::AggregationError(format!("Failed to serialize MCTS tree: {e}"))
+        })
+    }
+
+    /// Render the tree in Graphviz DOT format
+    ///
+    /// Each node shows its visits, mean value, and the start of the last
+    /// message that led to it.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph mcts {\n    node [shape=box];\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let mean = node.mean_value();
+            let action = node
+                .state
+                .conversation_history
+                .last()
+                .map(|msg| msg.content.chars().take(40).collect::<String>())
+                .unwrap_or_else(|| "root".to_string());
+            dot.push_str(&format!(
+                "    n{idx} [label=\"#{idx} visits={} mean={mean:.2}\\n{}\"];\n",
+                node.visits,
+                escape_dot(&action)
+            ));
+            for child in &node.children {
+                dot.push_str(&format!("    n{idx} -> n{child};\n"));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Prior for each generated action: the share of samples that produced it
+///
+/// Providers expose no logprobs, so agreement between the sampled
+/// completions stands in for the model's confidence in an action.
+fn action_priors(actions: &[String]) -> Vec<f32> {
+    let normalized: Vec<String> = actions
+        .iter()
+        .map(|action| normalize_text(action))
+        .collect();
+
+    normalized
+        .iter()
+        .map(|action| {
+            let count = normalized.iter().filter(|other| *other == action).count();
+            count as f32 / normalized.len() as f32
+        })
+        .collect()
+}
+
+/// Lowercase text and collapse runs of whitespace
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Draw a sample from Beta(alpha, beta) via two Gamma draws
+fn sample_beta(rng: &mut impl Rng, alpha: f64, beta: f64) -> f64 {
+    let x = sample_gamma(rng, alpha);
+    let y = sample_gamma(rng, beta);
+    if x + y > 0.0 { x / (x + y) } else { 0.5 }
+}
+
+/// Draw a sample from Gamma(shape, 1) (Marsaglia-Tsang)
+fn sample_gamma(rng: &mut impl Rng, shape: f64) -> f64 {
+    if shape < 1.0 {
+        // Boost: Gamma(a) = Gamma(a + 1) * U^(1/a)
+        let u: f64 = rng.random();
+        return sample_gamma(rng, shape + 1.0) * u.powf(1.0 / shape);
+    }
+
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        // Standard normal via Box-Muller
+        let u1: f64 = 1.0 - rng.random::<f64>();
+        let u2: f64 = rng.random();
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+
+        let v = (1.0 + c * z).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+        let u: f64 = 1.0 - rng.random::<f64>();
+        if u.ln() < 0.5 * z * z + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+/// Escape text for use inside a quoted DOT label
+pub(crate) fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
 }
 
 /// Monte Carlo Tree Search for reasoning exploration
@@ -146,6 +451,43 @@ pub struct MCTS {
     root_idx: Option<usize>,
     /// Token usage tracking
     pub completion_tokens: usize,
+    /// Custom state scorer; `None` rates states with the search provider
+    value_function: Option<Arc<dyn ValueFunction>>,
+    /// In-flight simulations through each node (virtual loss counts)
+    in_flight: Vec<usize>,
+    /// Channel for progress events, if attached
+    events: Option<mpsc::Sender<MarsEvent>>,
+    /// Shared statistics per transposition key (when enabled)
+    transpositions: HashMap<u64, TranspositionEntry>,
+    /// Simulations run by the last search
+    simulations_run: usize,
+    /// Custom action space; `None` uses free-form dialogue replies
+    action_generator: Option<Arc<dyn ActionGenerator>>,
+}
+
+/// Statistics shared by every node whose state has the same transposition key
+#[derive(Clone, Debug, Default)]
+struct TranspositionEntry {
+    /// Visits summed over all matching nodes
+    visits: usize,
+    /// Value summed over all matching nodes
+    value: f32,
+    /// Value function result for the state, once evaluated
+    evaluation: Option<f32>,
+}
+
+/// Result of one simulation, applied to the tree once its batch finishes
+struct Rollout {
+    /// Leaf the simulation was selected from
+    leaf: usize,
+    /// States and priors of the children created by expanding the leaf
+    children: Vec<(DialogueState, f32)>,
+    /// Child the rollout started from (`None` when the leaf was not expanded)
+    chosen: Option<usize>,
+    /// Value of the rollout's final state
+    value: f32,
+    /// Transposition key of the final state (when transpositions are enabled)
+    final_key: Option<u64>,
 }
 
 impl MCTS {
@@ -156,9 +498,21 @@ impl MCTS {
             nodes: Vec::new(),
             root_idx: None,
             completion_tokens: 0,
+            value_function: None,
+            in_flight: Vec::new(),
+            events: None,
+            transpositions: HashMap::new(),
+            simulations_run: 0,
+            action_generator: None,
         }
     }
 
+    /// Score simulated states with a custom value function instead of the LLM
+    pub fn with_value_function(mut self, value_function: Arc<dyn ValueFunction>) -> Self {
+        self.value_function = Some(value_function);
+        self
+    }
+
     /// Select most promising node using UCB formula
     fn select(&self, node_idx: usize) -> usize {
         let node = &self.nodes[node_idx];
@@ -168,72 +522,251 @@ impl MCTS {
             return node_idx;
         }
 
-        // UCB formula: value/visits + exploration * sqrt(ln(parent_visits)/visits)
-        let parent_visits = node.visits as f32;
+        // In-flight simulations count as visits that lost `virtual_loss`
+        let parent_visits = self.stats(node_idx).0 as f32 + self.pending(node_idx);
         let mut best_idx = node.children[0];
         let mut best_score = f32::NEG_INFINITY;
+        let mut rng = rand::rng();
 
         for &child_idx in &node.children {
             let child = &self.nodes[child_idx];
             let epsilon = 1e-8;
+            let pending = self.pending(child_idx);
+            let (child_visits, child_value) = self.stats(child_idx);
+            let visits = child_visits as f32 + pending;
+            let value = child_value - pending * self.config.virtual_loss;
+
+            let score = match self.config.selection_policy {
+                // value/visits + exploration * sqrt(ln(parent_visits)/visits)
+                MctsSelectionPolicy::Ucb1 => {
+                    let exploitation = value / (visits + epsilon);
+                    let exploration = self.config.exploration_weight
+                        * ((parent_visits + 1.0).ln() / (visits + epsilon)).sqrt();
+                    exploitation + exploration
+                }
+                // value/visits + exploration * prior * sqrt(parent_visits)/(1 + visits),
+                // with parent visits floored at 1 so priors count before any visit
+                MctsSelectionPolicy::Puct => {
+                    let exploitation = if visits > 0.0 { value / visits } else { 0.0 };
+                    let exploration = self.config.exploration_weight
+                        * child.prior
+                        * parent_visits.max(1.0).sqrt()
+                        / (1.0 + visits);
+                    exploitation + exploration
+                }
+                // Draw from Beta(1 + value, 1 + visits - value)
+                MctsSelectionPolicy::Thompson => sample_beta(
+                    &mut rng,
+                    1.0 + f64::from(value.max(0.0)),
+                    1.0 + f64::from((visits - value).max(0.0)),
+                ) as f32,
+            };
+
+            if score > best_score {
+                best_score = score;
+                best_idx = child_idx;
+            }
+        }
+
+        best_idx
+    }
 
-            let exploitation = child.value / (child.visits as f32 + epsilon);
-            let exploration = self.config.exploration_weight
-                * ((parent_visits + 1.0).ln() / (child.visits as f32 + epsilon)).sqrt();
+    /// Search over a custom action space instead of free-form replies
+    pub fn with_action_generator(mut self, action_generator: Arc<dyn ActionGenerator>) -> Self {
+        self.action_generator = Some(action_generator);
+        self
+    }
 
-            let ucb_score = exploitation + exploration;
+    /// Send node-expansion and simulation events to `tx` during search
+    pub fn with_events(mut self, tx: mpsc::Sender<MarsEvent>) -> Self {
+        self.events = Some(tx);
+        self
+    }
 
-            if ucb_score > best_score {
-                best_score = ucb_score;
-                best_idx = child_idx;
+    /// Node indices from the root down to `node_idx`
+    pub fn path_to(&self, mut node_idx: usize) -> Vec<usize> {
+        let mut path = vec![node_idx];
+        while let Some(parent_idx) = self.nodes[node_idx].parent {
+            path.push(parent_idx);
+            node_idx = parent_idx;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Visits and cumulative value of a node, shared across transpositions
+    fn stats(&self, node_idx: usize) -> (usize, f32) {
+        let node = &self.nodes[node_idx];
+        if self.config.use_transpositions
+            && let Some(entry) = self.transpositions.get(&node.state.transposition_key())
+        {
+            return (entry.visits, entry.value);
+        }
+        (node.visits, node.value)
+    }
+
+    /// Number of in-flight simulations passing through a node
+    fn pending(&self, node_idx: usize) -> f32 {
+        self.in_flight.get(node_idx).copied().unwrap_or(0) as f32
+    }
+
+    /// Add or remove a virtual loss on every node from `node_idx` up to the root
+    fn set_virtual_loss(&mut self, mut node_idx: usize, add: bool) {
+        self.in_flight.resize(self.nodes.len(), 0);
+        loop {
+            let count = &mut self.in_flight[node_idx];
+            *count = if add {
+                *count + 1
+            } else {
+                count.saturating_sub(1)
+            };
+
+            match self.nodes[node_idx].parent {
+                Some(parent_idx) => node_idx = parent_idx,
+                None => break,
             }
         }
+    }
 
-        best_idx
+    /// Descend from `node_idx` to a leaf using UCB selection
+    fn select_leaf(&self, mut node_idx: usize) -> usize {
+        while !self.nodes[node_idx].children.is_empty() {
+            node_idx = self.select(node_idx);
+        }
+        node_idx
     }
 
-    /// Expand node by generating possible actions
+    /// Expand a state by generating possible actions
+    ///
+    /// Returns the child states with their priors, and the index of the one
+    /// picked at random for simulation (`None` when no actions were generated).
     async fn expand(
-        &mut self,
-        node_idx: usize,
+        &self,
+        state: &DialogueState,
+        depth: usize,
         provider: &dyn LLMProvider,
-    ) -> Result<usize> {
-        let node = self.nodes[node_idx].clone();
-        let actions = self.generate_actions(&node.state, provider).await?;
-
-        // Create child nodes for each action
-        for action in actions {
-            let new_state = self.apply_action(&node.state, &action, provider).await?;
-            let child = MCTSNode::new(new_state, Some(node_idx));
-            let child_idx = self.nodes.len();
-            self.nodes.push(child);
-            self.nodes[node_idx].children.push(child_idx);
+    ) -> Result<(Vec<(DialogueState, f32)>, Option<usize>)> {
+        let actions = self.generate_actions(state, depth, provider).await?;
+        let priors = action_priors(&actions);
+
+        // Create a child state for each action
+        let mut children = Vec::with_capacity(actions.len());
+        for (action, prior) in actions.iter().zip(priors) {
+            children.push((self.apply_action(state, action, provider).await?, prior));
         }
 
         // Randomly select one child for simulation
-        if !self.nodes[node_idx].children.is_empty() {
-            let children = &self.nodes[node_idx].children;
-            let idx = rand::rng().random_range(0..children.len());
-            Ok(children[idx])
+        let chosen = if children.is_empty() {
+            None
         } else {
-            Ok(node_idx)
+            Some(rand::rng().random_range(0..children.len()))
+        };
+        Ok((children, chosen))
+    }
+
+    /// Expand (when asked) and simulate from a selected leaf
+    async fn rollout(
+        &self,
+        leaf: usize,
+        expand: bool,
+        provider: &dyn LLMProvider,
+    ) -> Result<Rollout> {
+        let state = &self.nodes[leaf].state;
+        let depth = self.path_to(leaf).len() - 1;
+        let (children, chosen) = if expand {
+            self.expand(state, depth, provider).await?
+        } else {
+            (Vec::new(), None)
+        };
+
+        let (start, start_depth) = match chosen {
+            Some(idx) => (children[idx].0.clone(), depth + 1),
+            None => (state.clone(), depth),
+        };
+        let (value, final_key) = self.simulate(start, start_depth, provider).await?;
+
+        Ok(Rollout {
+            leaf,
+            children,
+            chosen,
+            value,
+            final_key,
+        })
+    }
+
+    /// Add a finished rollout's children to the tree and backpropagate its value
+    ///
+    /// Returns the node the rollout started from.
+    fn apply_rollout(&mut self, rollout: Rollout) -> usize {
+        let mut node_idx = rollout.leaf;
+        for (idx, (state, prior)) in rollout.children.into_iter().enumerate() {
+            let child_idx = self.nodes.len();
+            let mut child = MCTSNode::new(state, Some(rollout.leaf));
+            child.prior = prior;
+            self.nodes.push(child);
+            self.nodes[rollout.leaf].children.push(child_idx);
+            if rollout.chosen == Some(idx) {
+                node_idx = child_idx;
+            }
+        }
+
+        if let Some(key) = rollout.final_key {
+            self.transpositions.entry(key).or_default().evaluation = Some(rollout.value);
         }
+        self.backpropagate(node_idx, rollout.value);
+        node_idx
     }
 
-    /// Simulate from node to terminal state
+    /// Report a finished rollout on the event channel, if one is attached
+    async fn emit_rollout(
+        &self,
+        simulation: usize,
+        leaf: usize,
+        expanded: bool,
+        start: usize,
+        value: f32,
+    ) {
+        let Some(tx) = &self.events else {
+            return;
+        };
+
+        if expanded {
+            let _result = tx
+                .send(MarsEvent::NodeExpanded {
+                    path: self.path_to(leaf),
+                    num_children: self.nodes[leaf].children.len(),
+                })
+                .await;
+        }
+
+        let _result = tx
+            .send(MarsEvent::SimulationCompleted {
+                simulation,
+                path: self.path_to(start),
+                value,
+                visits: self.nodes[start].visits,
+            })
+            .await;
+    }
+
+    /// Simulate from a state to terminal state
+    ///
+    /// Returns the final state's value, plus its transposition key when
+    /// transpositions are enabled.
     async fn simulate(
-        &mut self,
-        node_idx: usize,
+        &self,
+        mut state: DialogueState,
+        depth: usize,
         provider: &dyn LLMProvider,
-    ) -> Result<f32> {
-        let mut state = self.nodes[node_idx].state.clone();
-
-        for _ in 0..self.config.simulation_depth {
+    ) -> Result<(f32, Option<u64>)> {
+        for step in 0..self.config.simulation_depth {
             if self.is_terminal(&state) {
                 break;
             }
 
-            let actions = self.generate_actions(&state, provider).await?;
+            let actions = self
+                .generate_actions(&state, depth + step, provider)
+                .await?;
             if actions.is_empty() {
                 break;
             }
@@ -243,15 +776,37 @@ impl MCTS {
             state = self.apply_action(&state, &actions[idx], provider).await?;
         }
 
-        self.evaluate_state(&state, provider).await
+        if !self.config.use_transpositions {
+            return Ok((self.evaluate_state(&state, provider).await?, None));
+        }
+
+        // Reuse the evaluation of a transposed state instead of asking again
+        let key = state.transposition_key();
+        let cached = self
+            .transpositions
+            .get(&key)
+            .and_then(|entry| entry.evaluation);
+        let value = match cached {
+            Some(value) => value,
+            None => self.evaluate_state(&state, provider).await?,
+        };
+        Ok((value, Some(key)))
     }
 
-    /// Backpropagate value up the tree
+    /// Backpropagate value up the tree (and into the transposition table)
     fn backpropagate(&mut self, mut node_idx: usize, value: f32) {
         loop {
+            if self.config.use_transpositions {
+                let key = self.nodes[node_idx].state.transposition_key();
+                let entry = self.transpositions.entry(key).or_default();
+                entry.visits += 1;
+                entry.value += value;
+            }
+
             let node = &mut self.nodes[node_idx];
             node.visits += 1;
             node.value += value;
+            node.value_sq += value * value;
 
             if let Some(parent_idx) = node.parent {
                 node_idx = parent_idx;
@@ -261,12 +816,27 @@ impl MCTS {
         }
     }
 
+    /// Temperature for generating actions from a state at `depth`
+    fn generation_temperature(&self, depth: usize) -> f32 {
+        self.config
+            .temperature_schedule
+            .temperature_at(depth, self.config.generation_temperature)
+    }
+
     /// Generate possible actions using LLM
     async fn generate_actions(
-        &mut self,
+        &self,
         state: &DialogueState,
+        depth: usize,
         provider: &dyn LLMProvider,
     ) -> Result<Vec<String>> {
+        let temperature = self.generation_temperature(depth);
+        if let Some(action_generator) = &self.action_generator {
+            return action_generator
+                .generate(state, self.config.num_actions, temperature, provider)
+                .await;
+        }
+
         // Build prompt from state
         let mut prompt = String::new();
 
@@ -276,16 +846,19 @@ impl MCTS {
 
         prompt.push_str(&format!("user: {}", state.current_query));
 
-        // Generate N completions at high temperature
+        // Generate N completions at the scheduled temperature
         let mut actions = Vec::new();
         for _ in 0..self.config.num_actions {
-            match provider.complete(&prompt, Some(&state.system_prompt)).await {
+            match provider
+                .complete_with_temperature(&prompt, Some(&state.system_prompt), temperature)
+                .await
+            {
                 Ok(completion) => {
-                    actions.push(completion.trim().to_string());
+                    actions.push(completion.text.trim().to_string());
                 }
                 Err(e) => {
                     // Log error but continue
-                    eprintln!("Action generation error: {}", e);
+                    tracing::warn!(error = %e, "Action generation failed");
                 }
             }
         }
@@ -295,11 +868,15 @@ impl MCTS {
 
     /// Apply action to state and predict next query
     async fn apply_action(
-        &mut self,
+        &self,
         state: &DialogueState,
         action: &str,
         provider: &dyn LLMProvider,
     ) -> Result<DialogueState> {
+        if let Some(action_generator) = &self.action_generator {
+            return action_generator.apply(state, action, provider).await;
+        }
+
         // Add assistant response to history
         let mut new_history = state.conversation_history.clone();
         new_history.push(Message::new("assistant", action));
@@ -314,6 +891,7 @@ impl MCTS {
         let next_query = provider
             .complete(&prompt, Some(&state.system_prompt))
             .await?
+            .text
             .trim()
             .to_string();
 
@@ -326,32 +904,14 @@ impl MCTS {
 
     /// Evaluate quality of dialogue state (0.0 to 1.0)
     async fn evaluate_state(
-        &mut self,
+        &self,
         state: &DialogueState,
         provider: &dyn LLMProvider,
     ) -> Result<f32> {
-        let mut prompt = String::new();
-        for msg in &state.conversation_history {
-            prompt.push_str(&format!("{}: {}\n", msg.role, msg.content));
+        match &self.value_function {
+            Some(value_function) => Ok(value_function.score(state).await?.clamp(0.0, 1.0)),
+            None => llm_evaluate(state, provider, self.config.evaluation_temperature).await,
         }
-        prompt.push_str(
-            "\n\nEvaluate the quality of this conversation on a scale from 0 to 1, where 0 is poor and 1 is excellent. \
-             Consider factors such as coherence, relevance, and engagement. Respond with only a number.",
-        );
-
-        let response = provider
-            .complete(&prompt, Some(&state.system_prompt))
-            .await?;
-
-        // Parse score from response
-        let score = response
-            .trim()
-            .parse::<f32>()
-            .unwrap_or(0.5)
-            .max(0.0)
-            .min(1.0);
-
-        Ok(score)
     }
 
     /// Check if the current state is terminal (conversation should end)
@@ -360,41 +920,150 @@ impl MCTS {
             || state.current_query.to_lowercase().contains("goodbye")
     }
 
+    /// Simulations run by the last search (fewer than configured if it stopped early)
+    pub fn simulations_run(&self) -> usize {
+        self.simulations_run
+    }
+
+    /// Whether further simulations are unlikely to change the chosen root child
+    fn converged(&self, root_idx: usize, simulations: usize) -> bool {
+        if simulations < self.config.early_stop_min_simulations {
+            return false;
+        }
+
+        let root = &self.nodes[root_idx];
+        let Some(best) = root
+            .children
+            .iter()
+            .map(|&idx| &self.nodes[idx])
+            .max_by_key(|child| child.visits)
+        else {
+            return false;
+        };
+
+        if let Some(threshold) = self.config.early_stop_visit_share {
+            let total: usize = root
+                .children
+                .iter()
+                .map(|&idx| self.nodes[idx].visits)
+                .sum();
+            if total > 0 && best.visits as f32 / total as f32 >= threshold {
+                return true;
+            }
+        }
+
+        if let Some(threshold) = self.config.early_stop_value_std
+            && best.visits >= 2
+            && best.value_std() <= threshold
+        {
+            return true;
+        }
+
+        false
+    }
+
+    /// Snapshot of the current search tree
+    pub fn tree(&self) -> MCTSTree {
+        MCTSTree {
+            root: self.root_idx,
+            nodes: self.nodes.clone(),
+        }
+    }
+
+    /// Export the search tree as JSON, e.g. to see why a branch was preferred
+    pub fn export_tree(&self) -> Result<String> {
+        self.tree().to_json()
+    }
+
+    /// Export the search tree in Graphviz DOT format
+    pub fn export_dot(&self) -> String {
+        self.tree().to_dot()
+    }
+
     /// Run MCTS search and return best state
     pub async fn search(
         &mut self,
         initial_state: DialogueState,
         provider: &dyn LLMProvider,
+    ) -> Result<DialogueState> {
+        let span = tracing::info_span!(
+            "mcts_search",
+            num_simulations = self.config.num_simulations,
+            num_actions = self.config.num_actions,
+        );
+        self.run_search(initial_state, provider)
+            .instrument(span)
+            .await
+    }
+
+    /// Body of [`MCTS::search`], run in its span
+    async fn run_search(
+        &mut self,
+        initial_state: DialogueState,
+        provider: &dyn LLMProvider,
     ) -> Result<DialogueState> {
         // Initialize root if needed
-        if self.root_idx.is_none() {
-            let root = MCTSNode::new(initial_state, None);
-            self.nodes.push(root);
-            self.root_idx = Some(0);
-        }
-
-        // Run simulations
-        for _ in 0..self.config.num_simulations {
-            // Selection: traverse to most promising node
-            let mut node_idx = self.root_idx.unwrap();
-            while !self.nodes[node_idx].children.is_empty() {
-                node_idx = self.select(node_idx);
+        let root_idx = match self.root_idx {
+            Some(idx) => idx,
+            None => {
+                self.nodes.push(MCTSNode::new(initial_state, None));
+                self.root_idx = Some(0);
+                0
             }
-
-            // Expansion: generate children if not terminal
-            if !self.is_terminal(&self.nodes[node_idx].state.clone()) {
-                node_idx = self.expand(node_idx, provider).await?;
+        };
+
+        // Run simulations in batches of up to `parallel_simulations`
+        let batch_size = self.config.parallel_simulations.max(1);
+        let mut remaining = self.config.num_simulations;
+        let mut simulation = 0;
+        while remaining > 0 {
+            let batch = remaining.min(batch_size);
+            remaining -= batch;
+
+            // Selection: traverse to promising leaves, with virtual loss
+            // steering later picks in the batch away from earlier ones
+            let mut jobs: Vec<(usize, bool)> = Vec::with_capacity(batch);
+            for _ in 0..batch {
+                let leaf = self.select_leaf(root_idx);
+                // Expand non-terminal leaves once; repeats roll out directly
+                let expand = !self.is_terminal(&self.nodes[leaf].state)
+                    && !jobs
+                        .iter()
+                        .any(|&(other, expanding)| other == leaf && expanding);
+                self.set_virtual_loss(leaf, true);
+                jobs.push((leaf, expand));
             }
 
-            // Simulation: rollout from selected node
-            let value = self.simulate(node_idx, provider).await?;
+            // Expansion and simulation: run the batch concurrently
+            let rollouts = futures::future::join_all(
+                jobs.iter()
+                    .map(|&(leaf, expand)| self.rollout(leaf, expand, provider)),
+            )
+            .await;
 
             // Backpropagation: update values up the tree
-            self.backpropagate(node_idx, value);
+            for &(leaf, _) in &jobs {
+                self.set_virtual_loss(leaf, false);
+            }
+            for rollout in rollouts {
+                let rollout = rollout?;
+                let (leaf, value) = (rollout.leaf, rollout.value);
+                let expanded = !rollout.children.is_empty();
+                let start = self.apply_rollout(rollout);
+                self.emit_rollout(simulation, leaf, expanded, start, value)
+                    .await;
+                simulation += 1;
+            }
+
+            if self.converged(root_idx, simulation) {
+                tracing::debug!("MCTS converged after {simulation} simulations");
+                break;
+            }
         }
+        self.simulations_run = simulation;
 
         // Return best child (most visited)
-        let root = &self.nodes[self.root_idx.unwrap()];
+        let root = &self.nodes[root_idx];
         if root.children.is_empty() {
             return Ok(root.state.clone());
         }
@@ -413,6 +1082,39 @@ impl MCTS {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+
+    /// Mock provider that answers every prompt with a fixed reply
+    struct MockResponder;
+
+    #[async_trait]
+    impl LLMProvider for MockResponder {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if prompt.contains("Evaluate the quality") {
+                Ok("0.8".into())
+            } else {
+                Ok("The answer is \"4\"".into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-responder"
+        }
+    }
 
     #[test]
     fn test_message_creation() {
@@ -458,8 +1160,18 @@ mod tests {
         assert_eq!(config.num_simulations, 2);
         assert_eq!(config.num_actions, 3);
         assert_eq!(config.generation_temperature, 1.0);
+        assert_eq!(
+            config.temperature_schedule,
+            MctsTemperatureSchedule::Constant
+        );
         assert_eq!(config.evaluation_temperature, 0.1);
         assert_eq!(config.max_history_length, 10);
+        assert_eq!(config.parallel_simulations, 1);
+        assert_eq!(config.virtual_loss, 1.0);
+        assert_eq!(config.selection_policy, MctsSelectionPolicy::Ucb1);
+        assert!(!config.use_transpositions);
+        assert!(config.early_stop_visit_share.is_none());
+        assert!(config.early_stop_value_std.is_none());
     }
 
     #[test]
@@ -506,4 +1218,533 @@ mod tests {
         let state = DialogueState::new("".to_string(), history, "hello".to_string());
         assert!(mcts.is_terminal(&state));
     }
+
+    #[tokio::test]
+    async fn test_export_tree_after_search() {
+        let config = MCTSConfig {
+            num_simulations: 2,
+            num_actions: 2,
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config);
+        let state = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        mcts.search(state, &MockResponder).await.unwrap();
+
+        let json = mcts.export_tree().unwrap();
+        let tree: MCTSTree = serde_json::from_str(&json).unwrap();
+        assert_eq!(tree.root, Some(0));
+        assert_eq!(tree.nodes.len(), mcts.nodes.len());
+        assert_eq!(tree.nodes[0].visits, 2);
+        assert_eq!(tree.nodes[0].children.len(), 2);
+
+        let dot = mcts.export_dot();
+        assert!(dot.starts_with("digraph mcts {"));
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains("#0 visits=2 mean=0.80\\nroot"));
+        assert!(dot.contains("The answer is \\\"4\\\""));
+    }
+
+    /// Mock provider whose every call fails
+    struct FailingResponder;
+
+    #[async_trait]
+    impl LLMProvider for FailingResponder {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Err(crate::MarsError::ClientError("boom".to_string()))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "failing-responder"
+        }
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_action_generation_failures_are_logged_in_search_span() {
+        let config = MCTSConfig {
+            num_simulations: 1,
+            num_actions: 1,
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config);
+        let state = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        let _outcome = mcts.search(state, &FailingResponder).await;
+
+        assert!(logs_contain("mcts_search{num_simulations=1 num_actions=1}"));
+        assert!(logs_contain(
+            "Action generation failed error=Client error: boom"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_parallel_simulations_expand_each_leaf_once() {
+        let config = MCTSConfig {
+            num_simulations: 5,
+            num_actions: 2,
+            parallel_simulations: 3,
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config);
+        let state = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        mcts.search(state, &MockResponder).await.unwrap();
+
+        // First batch: one expansion of the root, two direct rollouts
+        assert_eq!(mcts.nodes[0].visits, 5);
+        assert_eq!(mcts.nodes[0].children.len(), 2);
+        assert!(mcts.in_flight.iter().all(|&count| count == 0));
+        for node in &mcts.nodes {
+            assert!(node.children.len() <= 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_emits_progress_events() {
+        let config = MCTSConfig {
+            num_simulations: 3,
+            num_actions: 2,
+            ..MCTSConfig::default()
+        };
+        let (tx, mut rx) = mpsc::channel(100);
+        let mut mcts = MCTS::new(config).with_events(tx);
+        let state = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        mcts.search(state, &MockResponder).await.unwrap();
+        drop(mcts);
+
+        let mut expansions = Vec::new();
+        let mut simulations = Vec::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                MarsEvent::NodeExpanded { path, num_children } => {
+                    assert_eq!(num_children, 2);
+                    expansions.push(path);
+                }
+                MarsEvent::SimulationCompleted {
+                    simulation,
+                    path,
+                    value,
+                    visits,
+                } => {
+                    assert_eq!(simulation, simulations.len());
+                    assert_eq!(value, 0.8);
+                    assert!(visits >= 1);
+                    simulations.push(path);
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(expansions.len(), 3);
+        assert_eq!(expansions[0], vec![0]);
+        assert_eq!(simulations.len(), 3);
+        assert!(
+            simulations
+                .iter()
+                .all(|path| path[0] == 0 && path.len() >= 2)
+        );
+    }
+
+    #[test]
+    fn test_virtual_loss_steers_selection() {
+        let mut mcts = MCTS::new(MCTSConfig::default());
+        let state = DialogueState::new("system".to_string(), vec![], "q".to_string());
+        mcts.nodes.push(MCTSNode::new(state.clone(), None));
+        for _ in 0..2 {
+            let child_idx = mcts.nodes.len();
+            let mut child = MCTSNode::new(state.clone(), Some(0));
+            child.visits = 1;
+            child.value = 0.8;
+            mcts.nodes.push(child);
+            mcts.nodes[0].children.push(child_idx);
+        }
+        mcts.nodes[0].visits = 2;
+
+        let first = mcts.select_leaf(0);
+        mcts.set_virtual_loss(first, true);
+        let second = mcts.select_leaf(0);
+        assert_ne!(first, second);
+
+        mcts.set_virtual_loss(first, false);
+        assert_eq!(mcts.select_leaf(0), first);
+    }
+
+    /// Search with a root whose children have the given (visits, value, prior)
+    fn mcts_with_children(policy: MctsSelectionPolicy, children: &[(usize, f32, f32)]) -> MCTS {
+        let config = MCTSConfig {
+            selection_policy: policy,
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config);
+        let state = DialogueState::new("system".to_string(), vec![], "q".to_string());
+        mcts.nodes.push(MCTSNode::new(state.clone(), None));
+        for &(visits, value, prior) in children {
+            let child_idx = mcts.nodes.len();
+            let mut child = MCTSNode::new(state.clone(), Some(0));
+            child.visits = visits;
+            child.value = value;
+            child.prior = prior;
+            mcts.nodes.push(child);
+            mcts.nodes[0].children.push(child_idx);
+            mcts.nodes[0].visits += visits;
+        }
+        mcts
+    }
+
+    #[test]
+    fn test_action_priors_follow_agreement() {
+        let actions = vec![
+            "The answer is 4".to_string(),
+            "the answer  is 4".to_string(),
+            "5".to_string(),
+        ];
+        let priors = action_priors(&actions);
+        assert!((priors[0] - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(priors[0], priors[1]);
+        assert!((priors[2] - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_puct_tries_high_prior_child_first() {
+        let mcts = mcts_with_children(MctsSelectionPolicy::Puct, &[(0, 0.0, 0.2), (0, 0.0, 0.8)]);
+        assert_eq!(mcts.select(0), 2);
+
+        // UCB ignores priors and keeps the first unvisited child
+        let mcts = mcts_with_children(MctsSelectionPolicy::Ucb1, &[(0, 0.0, 0.2), (0, 0.0, 0.8)]);
+        assert_eq!(mcts.select(0), 1);
+    }
+
+    #[test]
+    fn test_thompson_prefers_stronger_child() {
+        let mcts = mcts_with_children(
+            MctsSelectionPolicy::Thompson,
+            &[(20, 1.0, 1.0), (20, 19.0, 1.0)],
+        );
+        for _ in 0..20 {
+            assert_eq!(mcts.select(0), 2);
+        }
+    }
+
+    #[test]
+    fn test_sample_beta_mean() {
+        let mut rng = rand::rng();
+        let samples = 4000;
+        let mean = (0..samples)
+            .map(|_| sample_beta(&mut rng, 2.0, 5.0))
+            .sum::<f64>()
+            / f64::from(samples);
+        assert!((mean - 2.0 / 7.0).abs() < 0.03);
+
+        let small = sample_beta(&mut rng, 0.5, 0.5);
+        assert!((0.0..=1.0).contains(&small));
+    }
+
+    #[tokio::test]
+    async fn test_search_with_each_policy() {
+        for policy in [
+            MctsSelectionPolicy::Ucb1,
+            MctsSelectionPolicy::Puct,
+            MctsSelectionPolicy::Thompson,
+        ] {
+            let config = MCTSConfig {
+                num_simulations: 3,
+                num_actions: 2,
+                selection_policy: policy,
+                ..MCTSConfig::default()
+            };
+            let mut mcts = MCTS::new(config);
+            let state =
+                DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+            mcts.search(state, &MockResponder).await.unwrap();
+            assert_eq!(mcts.nodes[0].visits, 3);
+            // Both sampled actions agree, so each child carries the full prior
+            assert_eq!(mcts.nodes[1].prior, 1.0);
+        }
+    }
+
+    /// Mock provider like [`MockResponder`] that counts evaluation prompts
+    struct CountingResponder {
+        evaluations: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLMProvider for CountingResponder {
+        async fn complete(
+            &self,
+            prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if prompt.contains("Evaluate the quality") {
+                self.evaluations
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            MockResponder.complete(prompt, system_prompt).await
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-counting-responder"
+        }
+    }
+
+    /// Mock provider that records (depth, temperature) of action generations
+    struct TemperatureRecorder {
+        generations: std::sync::Mutex<Vec<(usize, f32)>>,
+    }
+
+    #[async_trait]
+    impl LLMProvider for TemperatureRecorder {
+        async fn complete(
+            &self,
+            prompt: &str,
+            system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            MockResponder.complete(prompt, system_prompt).await
+        }
+
+        async fn complete_with_temperature(
+            &self,
+            prompt: &str,
+            system_prompt: Option<&str>,
+            temperature: f32,
+        ) -> Result<CompletionResponse> {
+            if !prompt.contains("Evaluate the quality") {
+                // Each action adds one assistant turn, so turns give the depth
+                let depth = prompt.matches("assistant: ").count();
+                if let Ok(mut generations) = self.generations.lock() {
+                    generations.push((depth, temperature));
+                }
+            }
+            self.complete(prompt, system_prompt).await
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-temperature-recorder"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generation_follows_temperature_schedule() {
+        let config = MCTSConfig {
+            num_simulations: 3,
+            num_actions: 2,
+            temperature_schedule: MctsTemperatureSchedule::PerDepth(vec![1.2, 0.6, 0.2]),
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config);
+        let provider = TemperatureRecorder {
+            generations: std::sync::Mutex::new(Vec::new()),
+        };
+        let state = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        mcts.search(state, &provider).await.unwrap();
+
+        let generations = provider.generations.lock().unwrap();
+        assert!(generations.iter().any(|&(depth, _)| depth == 0));
+        assert!(generations.iter().any(|&(depth, _)| depth >= 2));
+        for &(depth, temperature) in generations.iter() {
+            let expected = [1.2, 0.6, 0.2][depth.min(2)];
+            assert_eq!(temperature, expected, "depth {depth}");
+        }
+    }
+
+    #[test]
+    fn test_transposition_key_normalizes_text() {
+        let history = vec![Message::new("assistant", "The answer is 4")];
+        let a = DialogueState::new("system".to_string(), history, "What is  2+2?".to_string());
+        let history = vec![Message::new("assistant", "the answer is 4 ")];
+        let b = DialogueState::new("System".to_string(), history, "what is 2+2?".to_string());
+        assert_eq!(a.transposition_key(), b.transposition_key());
+
+        let c = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        assert_ne!(a.transposition_key(), c.transposition_key());
+    }
+
+    #[tokio::test]
+    async fn test_transpositions_share_stats_and_evaluations() {
+        let config = MCTSConfig {
+            simulation_depth: 0,
+            use_transpositions: true,
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config);
+        let root = DialogueState::new("system".to_string(), vec![], "q".to_string());
+        let history = vec![Message::new("assistant", "The answer is \"4\"")];
+        let child = DialogueState::new("system".to_string(), history, "next".to_string());
+        mcts.nodes.push(MCTSNode::new(root, None));
+        for idx in [1, 2] {
+            mcts.nodes.push(MCTSNode::new(child.clone(), Some(0)));
+            mcts.nodes[0].children.push(idx);
+        }
+
+        let provider = CountingResponder {
+            evaluations: std::sync::atomic::AtomicUsize::new(0),
+        };
+        for leaf in [1, 2] {
+            let rollout = mcts.rollout(leaf, false, &provider).await.unwrap();
+            mcts.apply_rollout(rollout);
+        }
+
+        // The second, transposed state reused the first evaluation
+        assert_eq!(
+            provider
+                .evaluations
+                .load(std::sync::atomic::Ordering::SeqCst),
+            1
+        );
+        assert_eq!(mcts.nodes[2].visits, 1);
+        assert_eq!(mcts.stats(2), (2, 1.6));
+        assert_eq!(mcts.stats(0), (2, 1.6));
+    }
+
+    #[tokio::test]
+    async fn test_search_with_transpositions() {
+        let config = MCTSConfig {
+            num_simulations: 4,
+            num_actions: 2,
+            use_transpositions: true,
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config);
+        let state = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        mcts.search(state, &MockResponder).await.unwrap();
+
+        assert_eq!(mcts.nodes[0].visits, 4);
+        // Sibling states are identical, so they report the same shared stats
+        assert_eq!(mcts.stats(1), mcts.stats(2));
+    }
+
+    #[tokio::test]
+    async fn test_early_stop_on_visit_share() {
+        let config = MCTSConfig {
+            num_simulations: 10,
+            num_actions: 2,
+            early_stop_visit_share: Some(0.5),
+            early_stop_min_simulations: 2,
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config);
+        let state = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        mcts.search(state, &MockResponder).await.unwrap();
+
+        assert_eq!(mcts.simulations_run(), 2);
+        assert_eq!(mcts.nodes[0].visits, 2);
+    }
+
+    #[tokio::test]
+    async fn test_early_stop_on_value_std() {
+        let config = MCTSConfig {
+            num_simulations: 10,
+            num_actions: 2,
+            early_stop_value_std: Some(0.01),
+            early_stop_min_simulations: 3,
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config);
+        let state = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        mcts.search(state, &MockResponder).await.unwrap();
+
+        // Every rollout scores 0.8, so the best child has converged once it
+        // has two visits
+        assert_eq!(mcts.simulations_run(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_no_early_stop_by_default() {
+        let config = MCTSConfig {
+            num_simulations: 6,
+            num_actions: 2,
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config);
+        let state = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        mcts.search(state, &MockResponder).await.unwrap();
+        assert_eq!(mcts.simulations_run(), 6);
+    }
+
+    #[test]
+    fn test_node_value_std() {
+        let state = DialogueState::new("system".to_string(), vec![], "q".to_string());
+        let mut node = MCTSNode::new(state, None);
+        assert_eq!(node.value_std(), 0.0);
+        for value in [0.2, 0.8] {
+            node.visits += 1;
+            node.value += value;
+            node.value_sq += value * value;
+        }
+        assert!((node.mean_value() - 0.5).abs() < 1e-6);
+        assert!((node.value_std() - 0.3).abs() < 1e-6);
+    }
+
+    /// Value function that scores every state the same
+    struct FixedValue(f32);
+
+    #[async_trait]
+    impl ValueFunction for FixedValue {
+        async fn score(&self, _state: &DialogueState) -> Result<f32> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_value_function_replaces_llm_evaluation() {
+        let config = MCTSConfig {
+            num_simulations: 2,
+            num_actions: 2,
+            ..MCTSConfig::default()
+        };
+        let mut mcts = MCTS::new(config).with_value_function(Arc::new(FixedValue(0.25)));
+        let state = DialogueState::new("system".to_string(), vec![], "What is 2+2?".to_string());
+        mcts.search(state, &MockResponder).await.unwrap();
+
+        let root = &mcts.nodes[0];
+        assert_eq!(root.visits, 2);
+        assert_eq!(root.value / root.visits as f32, 0.25);
+    }
+
+    #[tokio::test]
+    async fn test_custom_value_function_is_clamped() {
+        let mut mcts =
+            MCTS::new(MCTSConfig::default()).with_value_function(Arc::new(FixedValue(3.0)));
+        let state = DialogueState::new("system".to_string(), vec![], "q".to_string());
+        let value = mcts.evaluate_state(&state, &MockResponder).await.unwrap();
+        assert_eq!(value, 1.0);
+    }
+
+    #[test]
+    fn test_export_empty_tree() {
+        let mcts = MCTS::new(MCTSConfig::default());
+        assert!(mcts.tree().root.is_none());
+        assert_eq!(
+            mcts.export_dot(),
+            "digraph mcts {\n    node [shape=box];\n}\n"
+        );
+    }
 }
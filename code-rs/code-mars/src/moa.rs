@@ -10,6 +10,7 @@
 ///
 /// Based on references/optillm/optillm/moa.py
 
+use crate::prompts::{AggregationPrompts, render_template};
 use crate::{LLMProvider, Result};
 use crate::types::Solution;
 
@@ -29,6 +30,8 @@ pub struct MoaMetadata {
     pub phase3_tokens: usize,
     /// Number of completions generated
     pub num_completions: usize,
+    /// Number of proposer layers
+    pub num_layers: usize,
     /// Whether fallback was used
     pub fallback_used: bool,
 }
@@ -36,8 +39,9 @@ pub struct MoaMetadata {
 impl MoaAggregator {
     /// Generate N initial completions (Phase 1)
     ///
-    /// Generates N completions sequentially using the provided LLM provider.
-    /// If fewer than N completions succeed, pads with the first completion.
+    /// Requests all N completions with one [`LLMProvider::complete_n`] call.
+    /// If fewer than N completions succeed, pads with the first completion
+    /// when fallback is enabled.
     async fn generate_initial_completions(
         query: &str,
         system_prompt: &str,
@@ -45,34 +49,37 @@ impl MoaAggregator {
         provider: &dyn LLMProvider,
         fallback_enabled: bool,
     ) -> Result<(Vec<String>, usize, bool)> {
-        let mut completions = Vec::new();
-        let mut total_tokens = 0;
-        let mut fallback_used = false;
+        let responses = match provider
+            .complete_n(query, Some(system_prompt), num_completions, None)
+            .await
+        {
+            Ok(responses) => responses,
+            Err(e) if fallback_enabled => {
+                tracing::warn!("MOA phase 1 completions failed: {e}");
+                Vec::new()
+            }
+            Err(e) => {
+                return Err(crate::MarsError::AggregationError(format!(
+                    "Failed to generate completions: {e}"
+                )));
+            }
+        };
 
-        // Generate completions sequentially
-        for i in 0..num_completions {
-            match provider
-                .complete(query, Some(system_prompt))
-                .await
-            {
-                Ok(completion) => {
-                    if !completion.is_empty() {
-                        // Estimate tokens (rough heuristic: 4 chars per token)
-                        total_tokens += query.len() / 4 + completion.len() / 4;
-                        completions.push(completion);
-                    }
-                }
-                Err(e) => {
-                    if !fallback_enabled {
-                        return Err(crate::MarsError::AggregationError(format!(
-                            "Failed to generate completion {}: {}",
-                            i + 1,
-                            e
-                        )));
-                    }
-                    // Continue trying other completions even if one fails
-                    fallback_used = true;
-                }
+        let fallback_used = responses.len() < num_completions;
+        if fallback_used && !fallback_enabled {
+            return Err(crate::MarsError::AggregationError(format!(
+                "Generated {} of {num_completions} completions",
+                responses.len()
+            )));
+        }
+
+        let mut completions = Vec::with_capacity(num_completions);
+        let mut total_tokens = 0;
+        for completion in responses {
+            if !completion.text.is_empty() {
+                total_tokens += completion.prompt_tokens_or_estimate(query.len())
+                    + completion.completion_tokens_or_estimate();
+                completions.push(completion.text);
             }
         }
 
@@ -120,24 +127,27 @@ impl MoaAggregator {
             .complete(&critique_prompt, Some(system_prompt))
             .await?;
 
-        if critique.is_empty() {
+        if critique.text.is_empty() {
             return Err(crate::MarsError::AggregationError(
                 "Failed to generate critique in MOA phase 2".to_string(),
             ));
         }
 
-        // Estimate tokens
-        let token_count = critique_prompt.len() / 4 + critique.len() / 4;
+        let token_count = critique.prompt_tokens_or_estimate(critique_prompt.len())
+            + critique.completion_tokens_or_estimate();
 
-        Ok((critique, token_count))
+        Ok((critique.text, token_count))
     }
 
     /// Generate final synthesis (Phase 3)
+    ///
+    /// A custom `template` sees every candidate, not just the first three.
     async fn generate_final_synthesis(
         query: &str,
         completions: &[String],
         critique: &str,
         system_prompt: &str,
+        template: Option<&str>,
         provider: &dyn LLMProvider,
     ) -> Result<(String, usize)> {
         if completions.len() < 3 {
@@ -146,32 +156,49 @@ impl MoaAggregator {
             ));
         }
 
-        let synthesis_prompt = format!(
-            "Original query: {}\n\n\
-             Based on the following candidate responses and their critiques, \
-             generate a final response to the original query.\n\n\
-             Candidate 1:\n{}\n\n\
-             Candidate 2:\n{}\n\n\
-             Candidate 3:\n{}\n\n\
-             Critiques of all candidates:\n{}\n\n\
-             Please provide a final, optimized response to the original query:",
-            query, completions[0], completions[1], completions[2], critique
-        );
+        let synthesis_prompt = if let Some(template) = template {
+            let candidates = completions
+                .iter()
+                .enumerate()
+                .map(|(idx, completion)| format!("Candidate {}:\n{}", idx + 1, completion))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            render_template(
+                template,
+                &[
+                    ("query", query),
+                    ("candidates", &candidates),
+                    ("critique", critique),
+                ],
+            )
+        } else {
+            format!(
+                "Original query: {}\n\n\
+                 Based on the following candidate responses and their critiques, \
+                 generate a final response to the original query.\n\n\
+                 Candidate 1:\n{}\n\n\
+                 Candidate 2:\n{}\n\n\
+                 Candidate 3:\n{}\n\n\
+                 Critiques of all candidates:\n{}\n\n\
+                 Please provide a final, optimized response to the original query:",
+                query, completions[0], completions[1], completions[2], critique
+            )
+        };
 
         let synthesis = provider
             .complete(&synthesis_prompt, Some(system_prompt))
             .await?;
 
-        if synthesis.is_empty() {
+        if synthesis.text.is_empty() {
             return Err(crate::MarsError::AggregationError(
                 "Failed to generate final synthesis in MOA phase 3".to_string(),
             ));
         }
 
-        // Estimate tokens
-        let token_count = synthesis_prompt.len() / 4 + synthesis.len() / 4;
+        let token_count = synthesis.prompt_tokens_or_estimate(synthesis_prompt.len())
+            + synthesis.completion_tokens_or_estimate();
 
-        Ok((synthesis, token_count))
+        Ok((synthesis.text, token_count))
     }
 
     /// Run the full MOA aggregation pipeline
@@ -184,42 +211,97 @@ impl MoaAggregator {
         fallback_enabled: bool,
         provider: &dyn LLMProvider,
     ) -> Result<(Solution, MoaMetadata)> {
-        // Phase 1: Generate initial completions
-        let (completions, phase1_tokens, fallback_used) = Self::generate_initial_completions(
+        Self::run_layered_moa(
             query,
             system_prompt,
             num_completions,
-            provider,
             fallback_enabled,
+            &[provider],
+            provider,
+            &AggregationPrompts::default(),
         )
-        .await?;
+        .await
+    }
+
+    /// Run multi-layer MOA, as in the original Mixture-of-Agents architecture
+    ///
+    /// Layer 1's proposers answer the query directly. Every later layer's
+    /// proposers see the previous layer's outputs as reference answers. Each
+    /// entry of `layer_providers` is one layer, so layers can use different
+    /// models. The last layer's outputs are critiqued and synthesized by
+    /// `aggregator`. `prompts` can override the proposer and synthesis prompts.
+    pub async fn run_layered_moa(
+        query: &str,
+        system_prompt: &str,
+        num_completions: usize,
+        fallback_enabled: bool,
+        layer_providers: &[&dyn LLMProvider],
+        aggregator: &dyn LLMProvider,
+        prompts: &AggregationPrompts,
+    ) -> Result<(Solution, MoaMetadata)> {
+        if layer_providers.is_empty() {
+            return Err(crate::MarsError::AggregationError(
+                "MOA requires at least one proposer layer".to_string(),
+            ));
+        }
+
+        // Phase 1: Generate completions, layer by layer
+        let mut completions: Vec<String> = Vec::new();
+        let mut phase1_tokens = 0;
+        let mut fallback_used = false;
+        for provider in layer_providers {
+            // The first layer has no references yet
+            let prompt = Self::layer_prompt(query, &completions, prompts.moa_proposer.as_deref());
+            let (layer_completions, layer_tokens, layer_fallback) =
+                Self::generate_initial_completions(
+                    &prompt,
+                    system_prompt,
+                    num_completions,
+                    *provider,
+                    fallback_enabled,
+                )
+                .await?;
+
+            completions = layer_completions;
+            phase1_tokens += layer_tokens;
+            fallback_used |= layer_fallback;
+        }
 
         // Phase 2: Generate critique
         let (critique, phase2_tokens) =
-            Self::generate_critique(query, &completions, system_prompt, provider).await?;
+            Self::generate_critique(query, &completions, system_prompt, aggregator).await?;
 
         // Phase 3: Generate final synthesis
-        let (final_answer, phase3_tokens) =
-            Self::generate_final_synthesis(query, &completions, &critique, system_prompt, provider)
-                .await?;
+        let (final_answer, phase3_tokens) = Self::generate_final_synthesis(
+            query,
+            &completions,
+            &critique,
+            system_prompt,
+            prompts.moa_aggregator.as_deref(),
+            aggregator,
+        )
+        .await?;
 
         // Calculate total tokens
         let total_tokens = phase1_tokens + phase2_tokens + phase3_tokens;
 
         // Create solution from final synthesis
         let reasoning = format!(
-            "MOA Aggregation:\n\nCandidates generated: {}\n\nCritique:\n{}\n\nFinal Synthesis:",
+            "MOA Aggregation:\n\nLayers: {}\n\nCandidates generated: {}\n\nCritique:\n{}\n\n\
+             Final Synthesis:",
+            layer_providers.len(),
             completions.len(),
             critique
         );
 
-        let solution = Solution::new(
+        let mut solution = Solution::new(
             "moa-aggregator".to_string(),
             reasoning,
             final_answer,
             0.5, // Use medium temperature for aggregated solution
             total_tokens,
         );
+        solution.record_provider(aggregator, None);
 
         let metadata = MoaMetadata {
             total_tokens,
@@ -227,16 +309,174 @@ impl MoaAggregator {
             phase2_tokens,
             phase3_tokens,
             num_completions: completions.len(),
+            num_layers: layer_providers.len(),
             fallback_used,
         };
 
         Ok((solution, metadata))
     }
+
+    /// Build a proposer prompt that includes the previous layer's outputs
+    ///
+    /// Without references or a custom `template`, the query is used as is.
+    fn layer_prompt(query: &str, references: &[String], template: Option<&str>) -> String {
+        let listing = references
+            .iter()
+            .enumerate()
+            .map(|(idx, reference)| format!("{}. {}", idx + 1, reference))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        match template {
+            Some(template) => {
+                render_template(template, &[("query", query), ("references", &listing)])
+            }
+            None if references.is_empty() => query.to_string(),
+            None => format!(
+                "{}\n\nResponses from models:\n{}\n\nOriginal query: {}",
+                crate::prompts::MOA_LAYER_PROMPT,
+                listing,
+                query
+            ),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Mock provider that records every prompt and answers with a fixed reply
+    struct RecordingProvider {
+        reply: &'static str,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl RecordingProvider {
+        fn new(reply: &'static str) -> Self {
+            Self {
+                reply,
+                prompts: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn prompts(&self) -> Vec<String> {
+            self.prompts.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for RecordingProvider {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            self.prompts.lock().unwrap().push(prompt.to_string());
+            Ok(self.reply.into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            self.reply
+        }
+    }
+
+    #[tokio::test]
+    async fn test_layered_moa_feeds_references_forward() {
+        let first = RecordingProvider::new("draft");
+        let second = RecordingProvider::new("refined");
+        let aggregator = RecordingProvider::new("final");
+
+        let (solution, metadata) = MoaAggregator::run_layered_moa(
+            "What is 2+2?",
+            "system",
+            3,
+            false,
+            &[&first, &second],
+            &aggregator,
+            &AggregationPrompts::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(first.prompts(), vec!["What is 2+2?"; 3]);
+        let second_prompts = second.prompts();
+        assert_eq!(second_prompts.len(), 3);
+        for prompt in &second_prompts {
+            assert!(prompt.starts_with(crate::prompts::MOA_LAYER_PROMPT));
+            assert!(prompt.contains("1. draft"));
+            assert!(prompt.contains("Original query: What is 2+2?"));
+        }
+
+        // Critique and synthesis both see the last layer's outputs
+        let aggregator_prompts = aggregator.prompts();
+        assert_eq!(aggregator_prompts.len(), 2);
+        assert!(aggregator_prompts.iter().all(|p| p.contains("refined")));
+
+        assert_eq!(solution.answer, "final");
+        assert_eq!(metadata.num_layers, 2);
+        assert_eq!(metadata.num_completions, 3);
+    }
+
+    #[tokio::test]
+    async fn test_layered_moa_requires_a_layer() {
+        let aggregator = RecordingProvider::new("final");
+        let result = MoaAggregator::run_layered_moa(
+            "q",
+            "system",
+            3,
+            false,
+            &[],
+            &aggregator,
+            &AggregationPrompts::default(),
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_layered_moa_custom_templates() {
+        let first = RecordingProvider::new("draft");
+        let second = RecordingProvider::new("refined");
+        let aggregator = RecordingProvider::new("final");
+        let prompts = AggregationPrompts {
+            moa_proposer: Some("Draft a contract clause for: {query}\n{references}".to_string()),
+            moa_aggregator: Some("Merge for {query}:\n{candidates}\nNotes: {critique}".to_string()),
+            ..AggregationPrompts::default()
+        };
+
+        MoaAggregator::run_layered_moa(
+            "an NDA",
+            "system",
+            3,
+            false,
+            &[&first, &second],
+            &aggregator,
+            &prompts,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            first.prompts(),
+            vec!["Draft a contract clause for: an NDA\n"; 3]
+        );
+        assert!(second.prompts()[0].ends_with("1. draft\n\n2. draft\n\n3. draft"));
+        let synthesis = &aggregator.prompts()[1];
+        assert!(synthesis.starts_with("Merge for an NDA:\nCandidate 1:\nrefined"));
+        assert!(synthesis.ends_with("Notes: final"));
+    }
 
     #[test]
     fn test_moa_metadata_creation() {
@@ -246,6 +486,7 @@ mod tests {
             phase2_tokens: 200,
             phase3_tokens: 400,
             num_completions: 3,
+            num_layers: 1,
             fallback_used: false,
         };
 
@@ -265,6 +506,7 @@ mod tests {
             phase2_tokens: 250,
             phase3_tokens: 350,
             num_completions: 3,
+            num_layers: 1,
             fallback_used: true,
         };
 
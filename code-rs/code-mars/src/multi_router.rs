@@ -0,0 +1,516 @@
+//! Routing calls across several providers.
+//!
+//! [`MultiProviderRouter`] holds one provider per configured
+//! [`ProviderSpec`] and picks one for every call by its [`RoutingStrategy`]:
+//! always the primary, the highest priority, each in turn, the fewest calls
+//! in flight, weighted random, the cheapest by the pricing table, or the
+//! fastest by measured latency. With fallback on, a failed call moves on to
+//! the next provider in the strategy's order.
+//!
+//! The router reports the first provider's name and model, so cost tracking
+//! around it prices every call as the first provider's.
+
+use crate::cost::PricingTable;
+use crate::model_router::{CompletionResponse, LLMProvider, ModelStream, provider_from_spec};
+use crate::provider_config::{ProviderRoutingConfig, RoutingStrategy};
+use crate::structured::ResponseFormat;
+use crate::tools::{ToolAwareResponse, ToolSpec};
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use rand::Rng;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Weight of the newest call in a provider's moving-average latency
+const LATENCY_SMOOTHING: f64 = 0.3;
+
+/// One provider and what the strategies know about it
+struct Route {
+    provider: Arc<dyn LLMProvider>,
+    /// Relative share of calls under [`RoutingStrategy::Random`]
+    weight: f64,
+    priority: usize,
+    /// Dollars per 1k prompt plus 1k completion tokens, if priced
+    price: Option<f64>,
+    /// Moving average of successful call latency, once measured
+    latency: Mutex<Option<Duration>>,
+    in_flight: AtomicUsize,
+}
+
+impl Route {
+    fn latency(&self) -> Option<Duration> {
+        *self.latency.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record_latency(&self, sample: Duration) {
+        let mut latency = self.latency.lock().unwrap_or_else(PoisonError::into_inner);
+        *latency = Some(match *latency {
+            Some(average) => {
+                average.mul_f64(1.0 - LATENCY_SMOOTHING) + sample.mul_f64(LATENCY_SMOOTHING)
+            }
+            None => sample,
+        });
+    }
+}
+
+/// Counts a call as in flight on a route until dropped
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn start(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Provider that routes each call to one of several providers
+pub struct MultiProviderRouter {
+    routes: Vec<Route>,
+    strategy: RoutingStrategy,
+    fallback: bool,
+    /// Round-robin position
+    next: AtomicUsize,
+}
+
+impl MultiProviderRouter {
+    /// Route across `providers`, the first being the primary
+    ///
+    /// Starts with the primary strategy, fallback on, every provider weighted
+    /// 1 and priced from the default [`PricingTable`]. Fails if `providers`
+    /// is empty.
+    pub fn new(providers: Vec<Arc<dyn LLMProvider>>) -> Result<Self> {
+        if providers.is_empty() {
+            return Err(MarsError::InvalidConfiguration(
+                "A router needs at least one provider".to_string(),
+            ));
+        }
+        let router = Self {
+            routes: providers
+                .into_iter()
+                .map(|provider| Route {
+                    provider,
+                    weight: 1.0,
+                    priority: 0,
+                    price: None,
+                    latency: Mutex::new(None),
+                    in_flight: AtomicUsize::new(0),
+                })
+                .collect(),
+            strategy: RoutingStrategy::Primary,
+            fallback: true,
+            next: AtomicUsize::new(0),
+        };
+        Ok(router.with_pricing(&PricingTable::default()))
+    }
+
+    /// Route across the enabled providers of `config` with its strategy and
+    /// fallback, priced from `pricing`
+    ///
+    /// Each spec's `priority` and `weight` carry over.
+    pub fn from_config(config: &ProviderRoutingConfig, pricing: &PricingTable) -> Result<Self> {
+        let specs = config.get_enabled_providers();
+        let providers = specs
+            .iter()
+            .map(|spec| provider_from_spec(&config.with_default_timeout(spec)))
+            .collect();
+        let mut router = Self::new(providers)?
+            .with_strategy(config.strategy.clone())
+            .with_fallback(config.enable_fallback)
+            .with_pricing(pricing);
+        for (route, spec) in router.routes.iter_mut().zip(specs) {
+            route.priority = spec.priority;
+            route.weight = spec.weight.unwrap_or(1.0);
+        }
+        Ok(router)
+    }
+
+    /// Pick providers by `strategy`
+    pub fn with_strategy(mut self, strategy: RoutingStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Whether failed calls move on to the next provider
+    pub fn with_fallback(mut self, fallback: bool) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Look up the providers' prices for [`RoutingStrategy::Cheapest`]
+    pub fn with_pricing(mut self, pricing: &PricingTable) -> Self {
+        for route in &mut self.routes {
+            route.price = pricing
+                .pricing(route.provider.provider_name(), route.provider.model_name())
+                .map(|p| p.prompt_per_1k + p.completion_per_1k);
+        }
+        self
+    }
+
+    /// Weight the providers, in order, for [`RoutingStrategy::Random`]
+    ///
+    /// Providers without a weight keep theirs.
+    pub fn with_weights(mut self, weights: &[f64]) -> Self {
+        for (route, weight) in self.routes.iter_mut().zip(weights) {
+            route.weight = *weight;
+        }
+        self
+    }
+
+    /// Number of providers routed across
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Whether the router has no providers (never true for a constructed router)
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// Measured latency of provider `index`, once it has answered a call
+    pub fn latency(&self, index: usize) -> Option<Duration> {
+        self.routes.get(index).and_then(Route::latency)
+    }
+
+    /// Indices of the providers to try for a call, in order
+    fn order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.routes.len()).collect();
+        match self.strategy {
+            RoutingStrategy::Primary => {}
+            RoutingStrategy::HighestPriority => {
+                order.sort_by_key(|&i| std::cmp::Reverse(self.routes[i].priority));
+            }
+            RoutingStrategy::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                order.rotate_left(start % self.routes.len());
+            }
+            RoutingStrategy::BalanceLoad => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                order.rotate_left(start % self.routes.len());
+                order.sort_by_key(|&i| self.routes[i].in_flight.load(Ordering::Relaxed));
+            }
+            RoutingStrategy::Random => order = self.weighted_shuffle(order),
+            RoutingStrategy::Cheapest => {
+                // Unpriced providers go last
+                order.sort_by(|&a, &b| {
+                    let price = |i: usize| self.routes[i].price.unwrap_or(f64::INFINITY);
+                    price(a).total_cmp(&price(b))
+                });
+            }
+            RoutingStrategy::Fastest => {
+                // Unmeasured providers go first, so each gets measured
+                order.sort_by_key(|&i| self.routes[i].latency());
+            }
+        }
+        order
+    }
+
+    /// `order` shuffled so each provider is as likely to come next as its
+    /// share of the remaining weight
+    fn weighted_shuffle(&self, mut order: Vec<usize>) -> Vec<usize> {
+        let mut rng = rand::rng();
+        let mut shuffled = Vec::with_capacity(order.len());
+        while !order.is_empty() {
+            let weight = |i: usize| self.routes[i].weight.max(0.0);
+            let total: f64 = order.iter().map(|&i| weight(i)).sum();
+            let mut pick = if total > 0.0 {
+                rng.random_range(0.0..total)
+            } else {
+                0.0
+            };
+            let position = order
+                .iter()
+                .position(|&i| {
+                    pick -= weight(i);
+                    pick < 0.0
+                })
+                .unwrap_or(0);
+            shuffled.push(order.remove(position));
+        }
+        shuffled
+    }
+
+    /// Run `call` on the provider the strategy picks, moving on to the next
+    /// one on failure while fallback is on
+    async fn call<T, F, Fut>(&self, call: F) -> Result<T>
+    where
+        F: Fn(Arc<dyn LLMProvider>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let order = self.order();
+        let attempts = if self.fallback { order.len() } else { 1 };
+        let mut last_error = None;
+        for index in order.into_iter().take(attempts) {
+            let route = &self.routes[index];
+            let started = Instant::now();
+            let result = {
+                let _in_flight = InFlight::start(&route.in_flight);
+                call(Arc::clone(&route.provider)).await
+            };
+            match result {
+                Ok(value) => {
+                    route.record_latency(started.elapsed());
+                    return Ok(value);
+                }
+                Err(e) => {
+                    if self.fallback {
+                        tracing::warn!(
+                            "{} failed, trying the next provider: {e}",
+                            route.provider.model_id()
+                        );
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            MarsError::ClientError("No provider available to route to".to_string())
+        }))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for MultiProviderRouter {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        self.call(|p| async move { p.complete(prompt, system_prompt).await })
+            .await
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        self.call(|p| async move {
+            p.complete_with_temperature(prompt, system_prompt, temperature)
+                .await
+        })
+        .await
+    }
+
+    async fn complete_n(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        n: usize,
+        temperature: Option<f32>,
+    ) -> Result<Vec<CompletionResponse>> {
+        self.call(|p| async move { p.complete_n(prompt, system_prompt, n, temperature).await })
+            .await
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        self.call(|p| async move { p.stream(prompt, system_prompt).await })
+            .await
+    }
+
+    fn provider_name(&self) -> &str {
+        self.routes[0].provider.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.routes[0].provider.model_name()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.call(|p| async move { p.embed(texts).await }).await
+    }
+
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        self.call(|p| async move { p.complete_structured(prompt, system_prompt, format).await })
+            .await
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        self.call(|p| async move { p.complete_with_tools(prompt, system_prompt, tools).await })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::ModelPricing;
+    use crate::provider_config::ProviderSpec;
+
+    /// Answers with its model name after `delay`, or fails while `fail` is set
+    struct Named {
+        model: &'static str,
+        delay: Duration,
+        fail: bool,
+        calls: AtomicUsize,
+    }
+
+    impl Named {
+        fn new(model: &'static str) -> Arc<Self> {
+            Self::slow(model, Duration::ZERO)
+        }
+
+        fn slow(model: &'static str, delay: Duration) -> Arc<Self> {
+            Arc::new(Self {
+                model,
+                delay,
+                fail: false,
+                calls: AtomicUsize::new(0),
+            })
+        }
+
+        fn failing(model: &'static str) -> Arc<Self> {
+            Arc::new(Self {
+                model,
+                delay: Duration::ZERO,
+                fail: true,
+                calls: AtomicUsize::new(0),
+            })
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for Named {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            if self.fail {
+                return Err(MarsError::ClientError("503".to_string()));
+            }
+            Ok(self.model.into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            self.model
+        }
+    }
+
+    fn router(providers: &[&Arc<Named>]) -> MultiProviderRouter {
+        let providers = providers
+            .iter()
+            .map(|p| Arc::clone(p) as Arc<dyn LLMProvider>)
+            .collect();
+        MultiProviderRouter::new(providers).unwrap()
+    }
+
+    async fn answer(router: &MultiProviderRouter) -> String {
+        router.complete("q", None).await.unwrap().text
+    }
+
+    #[tokio::test]
+    async fn test_primary_and_round_robin() {
+        let (a, b) = (Named::new("a"), Named::new("b"));
+        let primary = router(&[&a, &b]);
+        assert_eq!(answer(&primary).await, "a");
+        assert_eq!(answer(&primary).await, "a");
+        assert_eq!(primary.model_id(), "mock/a");
+
+        let round_robin = router(&[&a, &b]).with_strategy(RoutingStrategy::RoundRobin);
+        for _ in 0..4 {
+            answer(&round_robin).await;
+        }
+        assert_eq!((a.calls(), b.calls()), (4, 2));
+        assert!(MultiProviderRouter::new(Vec::new()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cheapest_uses_the_pricing_table() {
+        let (pricey, cheap, unpriced) =
+            (Named::new("pricey"), Named::new("cheap"), Named::new("x"));
+        let pricing = PricingTable::empty()
+            .with_price("pricey", ModelPricing::new(0.01, 0.03))
+            .with_price("cheap", ModelPricing::new(0.0001, 0.0004));
+        let router = router(&[&unpriced, &pricey, &cheap])
+            .with_strategy(RoutingStrategy::Cheapest)
+            .with_pricing(&pricing);
+        assert_eq!(answer(&router).await, "cheap");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fastest_prefers_measured_latency() {
+        let slow = Named::slow("slow", Duration::from_millis(500));
+        let fast = Named::slow("fast", Duration::from_millis(50));
+        let router = router(&[&slow, &fast]).with_strategy(RoutingStrategy::Fastest);
+
+        // Each provider is measured once before latency decides
+        answer(&router).await;
+        answer(&router).await;
+        assert!(router.latency(0) > router.latency(1));
+        for _ in 0..3 {
+            assert_eq!(answer(&router).await, "fast");
+        }
+        assert_eq!(slow.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_random_follows_weights() {
+        let (a, b) = (Named::new("a"), Named::new("b"));
+        let router = router(&[&a, &b])
+            .with_strategy(RoutingStrategy::Random)
+            .with_weights(&[0.0, 1.0]);
+        for _ in 0..20 {
+            assert_eq!(answer(&router).await, "b");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_the_next_provider() {
+        let (down, up) = (Named::failing("down"), Named::new("up"));
+        assert_eq!(answer(&router(&[&down, &up])).await, "up");
+
+        let no_fallback = router(&[&down, &up]).with_fallback(false);
+        let err = no_fallback.complete("q", None).await.unwrap_err();
+        assert!(matches!(err, MarsError::ClientError(_)));
+        assert_eq!(up.calls(), 1);
+    }
+
+    #[test]
+    fn test_from_config_carries_priority_and_weight() {
+        let config = ProviderRoutingConfig::multi(
+            ProviderSpec::new("openai", "gpt-4o-mini").with_weight(2.0),
+            vec![ProviderSpec::new("anthropic", "claude-3-5-sonnet").with_priority(5)],
+        )
+        .with_strategy(RoutingStrategy::HighestPriority);
+        let router = MultiProviderRouter::from_config(&config, &PricingTable::default()).unwrap();
+        assert_eq!(router.len(), 2);
+        assert_eq!(router.order()[0], 1);
+        assert_eq!(router.routes[0].weight, 2.0);
+        assert!(router.routes[1].price.is_some());
+    }
+}
@@ -0,0 +1,403 @@
+//! Tracing and call statistics for provider calls.
+//!
+//! [`ObservedProvider`] wraps any [`LLMProvider`] and runs every call in an
+//! `llm_call` tracing span, logging its latency, token usage, whether it was
+//! truncated, and any error. Totals accumulate in a [`CallStats`] that
+//! several wrappers can share. With capture on (the coordinator turns it on
+//! for `MarsConfig::debug`), full prompts and responses are logged at debug
+//! level as well, with API keys and bearer tokens redacted.
+//!
+//! The span also records the call's token usage, latency and, given a
+//! [`CostTracker`] to price it with, its cost as attributes, so exporters
+//! such as OpenTelemetry see them on the span itself.
+
+use crate::Result;
+use crate::cost::CostTracker;
+use crate::debug::redact_secrets;
+use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+use crate::provider_config::MarsPhase;
+use crate::structured::ResponseFormat;
+use crate::tools::{ToolAwareResponse, ToolSpec};
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+
+/// Totals over observed calls
+#[derive(Debug, Default)]
+pub struct CallStats {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    truncated: AtomicU64,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+    latency_micros: AtomicU64,
+}
+
+impl CallStats {
+    /// Calls made, including failed ones
+    pub fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// Calls that failed
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// Completions that hit the token limit
+    pub fn truncated(&self) -> u64 {
+        self.truncated.load(Ordering::Relaxed)
+    }
+
+    /// Prompt tokens, reported or estimated
+    pub fn prompt_tokens(&self) -> u64 {
+        self.prompt_tokens.load(Ordering::Relaxed)
+    }
+
+    /// Completion tokens, reported or estimated
+    pub fn completion_tokens(&self) -> u64 {
+        self.completion_tokens.load(Ordering::Relaxed)
+    }
+
+    /// Time spent waiting on calls, summed across concurrent ones
+    pub fn total_latency(&self) -> Duration {
+        Duration::from_micros(self.latency_micros.load(Ordering::Relaxed))
+    }
+}
+
+/// Provider wrapper that traces every call and counts its usage
+pub struct ObservedProvider {
+    inner: Arc<dyn LLMProvider>,
+    stats: Arc<CallStats>,
+    /// Phase the calls are made for, recorded on each span
+    phase: Option<MarsPhase>,
+    /// Whether full prompts and responses are logged
+    capture: bool,
+    /// Prices calls for the span's `cost_usd`, if set
+    cost_tracker: Option<Arc<CostTracker>>,
+}
+
+impl ObservedProvider {
+    /// Observe calls to `inner`, with statistics of its own
+    pub fn new(inner: Arc<dyn LLMProvider>) -> Self {
+        Self {
+            inner,
+            stats: Arc::new(CallStats::default()),
+            phase: None,
+            capture: false,
+            cost_tracker: None,
+        }
+    }
+
+    /// Count calls in shared statistics
+    pub fn with_stats(mut self, stats: Arc<CallStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Label spans with the phase the calls are made for
+    pub fn with_phase(mut self, phase: MarsPhase) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    /// Log full prompts and responses at debug level, secrets redacted
+    pub fn with_capture(mut self, capture: bool) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// Price calls at `tracker`'s prices for the span's `cost_usd`
+    ///
+    /// Only prices; recording spend is left to [`crate::cost::CostTrackingProvider`].
+    pub fn with_cost_tracker(mut self, tracker: Arc<CostTracker>) -> Self {
+        self.cost_tracker = Some(tracker);
+        self
+    }
+
+    /// Totals over the calls observed so far
+    pub fn stats(&self) -> &CallStats {
+        &self.stats
+    }
+
+    /// Run `call` in a span, then log and count its outcome
+    ///
+    /// `completions` picks the completions out of a successful result; each
+    /// is stamped with the call's latency unless it already has one.
+    async fn observe<T, F>(
+        &self,
+        method: &'static str,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        call: F,
+        completions: impl Fn(&mut T) -> Vec<&mut CompletionResponse>,
+    ) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let span = tracing::info_span!(
+            "llm_call",
+            method,
+            model = %self.inner.model_id(),
+            phase = ?self.phase,
+            latency_ms = tracing::field::Empty,
+            prompt_tokens = tracing::field::Empty,
+            completion_tokens = tracing::field::Empty,
+            cost_usd = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        let mut result = call.instrument(span.clone()).await;
+        let latency = started.elapsed();
+
+        self.stats.calls.fetch_add(1, Ordering::Relaxed);
+        self.stats.latency_micros.fetch_add(
+            u64::try_from(latency.as_micros()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+        // Signed, as OpenTelemetry exports unsigned values as strings
+        span.record(
+            "latency_ms",
+            i64::try_from(latency.as_millis()).unwrap_or(i64::MAX),
+        );
+        span.in_scope(|| {
+            let latency_ms = latency.as_millis();
+            if self.capture {
+                tracing::debug!(
+                    prompt = %redact_secrets(prompt),
+                    system_prompt = system_prompt.map(redact_secrets),
+                    "LLM request"
+                );
+            }
+            let responses = match &mut result {
+                Ok(value) => completions(value),
+                Err(e) => {
+                    self.stats.errors.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!(latency_ms, error = %e, "LLM call failed");
+                    return;
+                }
+            };
+
+            let prompt_len = prompt.len() + system_prompt.map_or(0, str::len);
+            let (mut total_prompt, mut total_completion) = (0, 0);
+            for response in responses {
+                // Keep the latency of an inner observer, which is closer to the call
+                response
+                    .latency_ms
+                    .get_or_insert(u64::try_from(latency_ms).unwrap_or(u64::MAX));
+                let prompt_tokens = response.prompt_tokens_or_estimate(prompt_len);
+                let completion_tokens = response.completion_tokens_or_estimate();
+                let truncated = response.is_truncated();
+                total_prompt += prompt_tokens;
+                total_completion += completion_tokens;
+                self.stats
+                    .prompt_tokens
+                    .fetch_add(prompt_tokens as u64, Ordering::Relaxed);
+                self.stats
+                    .completion_tokens
+                    .fetch_add(completion_tokens as u64, Ordering::Relaxed);
+                if truncated {
+                    self.stats.truncated.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!(
+                        completion_tokens,
+                        "LLM response was truncated at the token limit"
+                    );
+                }
+                tracing::info!(
+                    latency_ms,
+                    prompt_tokens,
+                    completion_tokens,
+                    truncated,
+                    finish_reason = response.finish_reason.as_deref(),
+                    "LLM call completed"
+                );
+                if self.capture {
+                    tracing::debug!(response = %redact_secrets(&response.text), "LLM response");
+                }
+            }
+            span.record("prompt_tokens", total_prompt as i64);
+            span.record("completion_tokens", total_completion as i64);
+            if let Some(tracker) = &self.cost_tracker
+                && let Some(cost_usd) = tracker.price(
+                    self.inner.provider_name(),
+                    self.inner.model_name(),
+                    total_prompt,
+                    total_completion,
+                )
+            {
+                span.record("cost_usd", cost_usd);
+            }
+        });
+        result
+    }
+}
+
+#[async_trait]
+impl LLMProvider for ObservedProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        let call = self.inner.complete(prompt, system_prompt);
+        self.observe("complete", prompt, system_prompt, call, |r| vec![r])
+            .await
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        let call = self
+            .inner
+            .complete_with_temperature(prompt, system_prompt, temperature);
+        self.observe(
+            "complete_with_temperature",
+            prompt,
+            system_prompt,
+            call,
+            |r| vec![r],
+        )
+        .await
+    }
+
+    async fn complete_n(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        n: usize,
+        temperature: Option<f32>,
+    ) -> Result<Vec<CompletionResponse>> {
+        let call = self.inner.complete_n(prompt, system_prompt, n, temperature);
+        self.observe("complete_n", prompt, system_prompt, call, |r| {
+            r.iter_mut().collect()
+        })
+        .await
+    }
+
+    /// Streams are logged once fully received, with usage estimated from
+    /// their length
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let call = async {
+            let stream = self.inner.stream(prompt, system_prompt).await?;
+            let summary = CompletionResponse {
+                completion_tokens: Some(stream.content_len() / 4),
+                ..CompletionResponse::default()
+            };
+            Ok((stream, summary))
+        };
+        self.observe("stream", prompt, system_prompt, call, |(_, summary)| {
+            vec![summary]
+        })
+        .await
+        .map(|(stream, _)| stream)
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let call = self.inner.embed(texts);
+        self.observe("embed", "", None, call, |_| Vec::new()).await
+    }
+
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        let call = self
+            .inner
+            .complete_structured(prompt, system_prompt, format);
+        self.observe("complete_structured", prompt, system_prompt, call, |r| {
+            vec![r]
+        })
+        .await
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        let call = self.inner.complete_with_tools(prompt, system_prompt, tools);
+        self.observe("complete_with_tools", prompt, system_prompt, call, |r| {
+            vec![&mut r.response]
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MarsError;
+
+    /// Answers "ok" at the token limit, or fails for prompts starting with "fail"
+    struct Truncating;
+
+    #[async_trait]
+    impl LLMProvider for Truncating {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if prompt.starts_with("fail") {
+                return Err(MarsError::ClientError("boom".to_string()));
+            }
+            Ok(CompletionResponse::new("ok")
+                .with_usage(7, 3)
+                .with_finish_reason("length"))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "truncating"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_counts_usage_truncation_and_errors() {
+        let stats = Arc::new(CallStats::default());
+        let provider = ObservedProvider::new(Arc::new(Truncating))
+            .with_stats(Arc::clone(&stats))
+            .with_phase(MarsPhase::Exploration)
+            .with_capture(true);
+
+        let response = provider.complete("q", None).await.unwrap();
+        assert!(response.latency_ms.is_some());
+        let responses = provider
+            .complete_n("q", Some("system"), 2, None)
+            .await
+            .unwrap();
+        assert!(responses.iter().all(|r| r.latency_ms.is_some()));
+        assert!(provider.complete("fail", None).await.is_err());
+
+        assert_eq!(stats.calls(), 3);
+        assert_eq!(stats.errors(), 1);
+        assert_eq!(stats.truncated(), 3);
+        assert_eq!(stats.prompt_tokens(), 21);
+        assert_eq!(stats.completion_tokens(), 9);
+        assert_eq!(provider.model_id(), "mock/truncating");
+    }
+}
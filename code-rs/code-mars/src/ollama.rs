@@ -0,0 +1,337 @@
+//! Provider for local models served by Ollama.
+//!
+//! Talks to Ollama's native `/api/chat` endpoint, so MARS can run without
+//! sending anything to a cloud API. No API key is needed; streamed responses
+//! arrive as one JSON object per line. Other self-hosted servers that speak
+//! the OpenAI protocol (vLLM, LM Studio, llama.cpp) are reached through
+//! [`LiteLLMRouter::local`](crate::model_router::LiteLLMRouter::local).
+
+use crate::model_router::{
+    CompletionResponse, HttpOptions, HttpProviderBase, HttpTimeouts, LLMProvider, ModelStream,
+    StreamLine, http_json, invalid_response, reported_str, reported_usage, stream_lines,
+};
+use crate::structured::ResponseFormat;
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use serde_json::{Map, Value, json};
+use std::time::Duration;
+
+/// Address of a default Ollama install
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// Local models can be slow to load, so the default timeout is generous
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Models served by a local (or self-hosted) Ollama instance
+pub struct OllamaProvider {
+    model: String,
+    /// Server root, without a trailing slash
+    base_url: String,
+    base: HttpProviderBase,
+}
+
+impl OllamaProvider {
+    /// Create a provider for `model` on the default local server
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            base: HttpProviderBase::new(DEFAULT_REQUEST_TIMEOUT),
+        }
+    }
+
+    /// Create a provider from a provider specification (its API key is ignored)
+    ///
+    /// A base URL ending in `/v1`, the OpenAI-compatible path, is accepted too.
+    pub fn from_spec(spec: &crate::provider_config::ProviderSpec) -> Self {
+        let provider = Self::new(spec.model.clone())
+            .with_http_options(spec.http_options())
+            .with_extra_params(spec.extra_params.clone());
+        match &spec.base_url {
+            Some(base_url) => provider.with_base_url(base_url.clone()),
+            None => provider,
+        }
+    }
+
+    /// Talk to a server other than `http://localhost:11434`
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into();
+        let base_url = base_url.trim_end_matches('/');
+        self.base_url = base_url.strip_suffix("/v1").unwrap_or(base_url).to_string();
+        self
+    }
+
+    /// Give up on requests that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.base.set_timeout(timeout);
+        self
+    }
+
+    /// Apply request, connect and read timeouts (unset ones keep their defaults)
+    pub fn with_timeouts(mut self, timeouts: &HttpTimeouts) -> Self {
+        self.base.set_timeouts(timeouts);
+        self
+    }
+
+    /// Merge provider-specific parameters into every request body
+    pub fn with_extra_params(mut self, extra_params: Map<String, Value>) -> Self {
+        self.base.set_extra_params(extra_params);
+        self
+    }
+
+    /// Apply timeouts, a proxy and extra headers to every request
+    pub fn with_http_options(mut self, http: HttpOptions) -> Self {
+        self.base.set_http_options(http);
+        self
+    }
+
+    /// Server root requests are sent to
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// `/api/chat` request body
+    fn request_body(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: Option<f32>,
+        stream: bool,
+    ) -> Value {
+        let mut messages = Vec::with_capacity(2);
+        if let Some(system) = system_prompt {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        messages.push(json!({ "role": "user", "content": prompt }));
+
+        // Ollama streams unless told otherwise
+        let mut body = json!({ "model": self.model, "messages": messages, "stream": stream });
+        if let Some(temperature) = temperature {
+            body["options"] = json!({ "temperature": temperature });
+        }
+        body
+    }
+
+    /// POST a chat request
+    async fn send(&self, body: &Value) -> Result<reqwest::Response> {
+        let request = self
+            .base
+            .post_json(format!("{}/api/chat", self.base_url), body)?;
+        self.base.send(request, &self.model_id()).await
+    }
+
+    /// Run a non-streaming chat request and return the message content
+    async fn chat(&self, body: &Value) -> Result<CompletionResponse> {
+        let response = http_json(self.send(body).await?, &self.model_id()).await?;
+        let text = reported_str(&response, "/message/content")
+            .ok_or_else(|| invalid_response(&self.model_id(), "no message content"))?;
+        let (prompt_tokens, completion_tokens) =
+            reported_usage(&response, "/prompt_eval_count", "/eval_count");
+        Ok(CompletionResponse {
+            text,
+            prompt_tokens,
+            completion_tokens,
+            finish_reason: reported_str(&response, "/done_reason"),
+            model: reported_str(&response, "/model"),
+            latency_ms: None,
+        })
+    }
+}
+
+#[async_trait]
+impl LLMProvider for OllamaProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        self.chat(&self.request_body(prompt, system_prompt, None, false))
+            .await
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        self.chat(&self.request_body(prompt, system_prompt, Some(temperature), false))
+            .await
+    }
+
+    /// Ollama's `format` takes `"json"` or a JSON Schema
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        let mut body = self.request_body(&format.prompt(prompt), system_prompt, None, false);
+        match format {
+            ResponseFormat::Text => {}
+            ResponseFormat::JsonObject => body["format"] = json!("json"),
+            ResponseFormat::JsonSchema { schema, .. } => body["format"] = schema.clone(),
+        }
+        format.normalize(self.chat(&body).await?)
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let body = self.request_body(prompt, system_prompt, None, true);
+        let response = self.send(&body).await?;
+        let model_id = self.model_id();
+        let owner = model_id.clone();
+
+        // One JSON object per line; the last has `"done": true`
+        Ok(stream_lines(response, &model_id, move |line| {
+            if line.trim().is_empty() {
+                return Ok(StreamLine::Skip);
+            }
+            let event: Value =
+                serde_json::from_str(line).map_err(|e| invalid_response(&owner, &e.to_string()))?;
+            if let Some(message) = event.get("error").and_then(Value::as_str) {
+                return Err(MarsError::ClientError(format!(
+                    "{owner} failed mid-stream: {message}"
+                )));
+            }
+            let content = event
+                .pointer("/message/content")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if event.get("done").and_then(Value::as_bool).unwrap_or(false) {
+                return Ok(StreamLine::End(content));
+            }
+            Ok(content.map_or(StreamLine::Skip, StreamLine::Chunk))
+        }))
+    }
+
+    fn provider_name(&self) -> &str {
+        "ollama"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_chat_without_api_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(json!({
+                "model": "llama3.2",
+                "stream": false,
+                "options": { "temperature": 0.5 },
+                "messages": [
+                    { "role": "system", "content": "Be brief." },
+                    { "role": "user", "content": "What is 2+2?" },
+                ],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "llama3.2",
+                "message": { "role": "assistant", "content": "4" },
+                "done": true,
+                "done_reason": "stop",
+                "prompt_eval_count": 26,
+                "eval_count": 2,
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OllamaProvider::new("llama3.2").with_base_url(server.uri());
+        let answer = provider
+            .complete_with_temperature("What is 2+2?", Some("Be brief."), 0.5)
+            .await
+            .unwrap();
+        assert_eq!(answer.text, "4");
+        assert_eq!(answer.prompt_tokens, Some(26));
+        assert_eq!(answer.completion_tokens, Some(2));
+        assert_eq!(answer.finish_reason.as_deref(), Some("stop"));
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[0].headers.get("authorization").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_sets_format() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "answer": { "type": "integer" } },
+            "required": ["answer"],
+        });
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(json!({ "format": schema })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "message": { "role": "assistant", "content": "{ \"answer\": 4 }\n" },
+                "done": true,
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OllamaProvider::new("llama3.2").with_base_url(server.uri());
+        let format = ResponseFormat::json_schema("sum", schema);
+        let answer = provider
+            .complete_structured("What is 2+2?", None, &format)
+            .await
+            .unwrap();
+        assert_eq!(answer.text, r#"{"answer":4}"#);
+    }
+
+    #[tokio::test]
+    async fn test_streams_ndjson() {
+        let lines = [
+            r#"{"message":{"role":"assistant","content":"2 + 2"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":" = 4"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":""},"done":true}"#,
+        ];
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_string(lines.join("\n")))
+            .mount(&server)
+            .await;
+
+        let provider = OllamaProvider::new("llama3.2").with_base_url(server.uri());
+        let mut stream = provider.stream("q", None).await.unwrap();
+        assert_eq!(stream.next_chunk().await.unwrap().unwrap(), "2 + 2");
+        assert_eq!(stream.next_chunk().await.unwrap().unwrap(), " = 4");
+        assert!(stream.next_chunk().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_missing_model_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(404)
+                    .set_body_json(json!({ "error": "model \"llama9\" not found" })),
+            )
+            .mount(&server)
+            .await;
+
+        let provider = OllamaProvider::new("llama9").with_base_url(server.uri());
+        let err = provider.complete("q", None).await.unwrap_err();
+        assert!(err.to_string().contains("model \"llama9\" not found"));
+    }
+
+    #[test]
+    fn test_base_url_accepts_openai_path() {
+        let spec = crate::provider_config::ProviderSpec::new("ollama", "llama3.2")
+            .with_base_url("http://gpu-box:11434/v1/".to_string());
+        assert_eq!(
+            OllamaProvider::from_spec(&spec).base_url(),
+            "http://gpu-box:11434"
+        );
+        assert_eq!(OllamaProvider::new("llama3.2").base_url(), DEFAULT_BASE_URL);
+    }
+}
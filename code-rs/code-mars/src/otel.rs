@@ -0,0 +1,212 @@
+//! OpenTelemetry export of run traces, behind the `otel` feature.
+//!
+//! [`OtelTracing`] sends the `tracing` spans a run records to an OTLP
+//! collector such as Jaeger or Tempo. Each run becomes one trace: the
+//! `mars_run` span (a child of the caller's current span, if any) with a
+//! `mars_phase` span per phase, and below those the `agent_call`,
+//! `verification` and `llm_call` spans. Phase spans carry `llm_calls`,
+//! `tokens` and `cost_usd` attributes; call spans carry `latency_ms`,
+//! `prompt_tokens`, `completion_tokens` and `cost_usd`.
+//!
+//! Add [`OtelTracing::layer`] to the application's own subscriber so MARS
+//! spans land in the same traces as its other work, or call
+//! [`OtelTracing::install`] when the application has no subscriber.
+
+use crate::{MarsError, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Name of the instrumentation scope spans are exported under
+const TRACER_NAME: &str = "code-mars";
+
+/// Transport to an OTLP collector
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    /// gRPC, usually on port 4317
+    #[default]
+    Grpc,
+    /// Protobuf over HTTP, usually on port 4318 at `/v1/traces`
+    HttpProtobuf,
+}
+
+/// Exports MARS spans over OTLP
+///
+/// Spans are exported in batches; [`OtelTracing::shutdown`] (also run on
+/// drop) flushes the rest.
+pub struct OtelTracing {
+    provider: SdkTracerProvider,
+}
+
+impl OtelTracing {
+    /// Export to the OTLP collector at `endpoint`, as `service_name`
+    pub fn otlp(
+        endpoint: &str,
+        protocol: OtlpProtocol,
+        service_name: impl Into<String>,
+    ) -> Result<Self> {
+        let exporter = match protocol {
+            OtlpProtocol::Grpc => SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build(),
+            OtlpProtocol::HttpProtobuf => SpanExporter::builder()
+                .with_http()
+                .with_endpoint(endpoint)
+                .build(),
+        }
+        .map_err(|e| MarsError::InvalidConfiguration(format!("OTLP exporter: {e}")))?;
+
+        let resource = Resource::builder()
+            .with_service_name(service_name.into())
+            .build();
+        Ok(Self::from_provider(
+            SdkTracerProvider::builder()
+                .with_resource(resource)
+                .with_batch_exporter(exporter)
+                .build(),
+        ))
+    }
+
+    /// Export through an already configured tracer provider
+    pub fn from_provider(provider: SdkTracerProvider) -> Self {
+        Self { provider }
+    }
+
+    /// Layer that turns spans into OpenTelemetry spans, for the
+    /// application's subscriber
+    pub fn layer<S>(&self) -> OpenTelemetryLayer<S, SdkTracer>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.provider.tracer(TRACER_NAME))
+    }
+
+    /// Make a subscriber that only exports to OpenTelemetry the global default
+    ///
+    /// Fails if a global subscriber is already set; use [`OtelTracing::layer`]
+    /// then.
+    pub fn install(&self) -> Result<()> {
+        tracing_subscriber::registry()
+            .with(self.layer())
+            .try_init()
+            .map_err(|e| MarsError::InvalidConfiguration(format!("tracing subscriber: {e}")))
+    }
+
+    /// Export the spans still buffered
+    pub fn flush(&self) -> Result<()> {
+        self.provider
+            .force_flush()
+            .map_err(|e| MarsError::ClientError(format!("OTLP export: {e}")))
+    }
+
+    /// Flush buffered spans and stop exporting
+    pub fn shutdown(&self) -> Result<()> {
+        self.provider
+            .shutdown()
+            .map_err(|e| MarsError::ClientError(format!("OTLP export: {e}")))
+    }
+}
+
+impl Drop for OtelTracing {
+    fn drop(&mut self) {
+        // Already shut down is fine
+        let _result = self.provider.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MarsCoordinator;
+    use crate::config::MarsConfig;
+    use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+    use async_trait::async_trait;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SpanData};
+    use std::sync::Arc;
+
+    /// Answers 4 with usage, at a price the cost tracker knows
+    struct Priced;
+
+    #[async_trait]
+    impl LLMProvider for Priced {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Ok(CompletionResponse::new("4").with_usage(1000, 500))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "priced"
+        }
+    }
+
+    fn attribute<'a>(span: &'a SpanData, key: &str) -> Option<&'a Value> {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| &kv.value)
+    }
+
+    #[tokio::test]
+    async fn test_run_exports_one_trace_with_phase_and_call_spans() {
+        let exporter = InMemorySpanExporter::default();
+        let otel = OtelTracing::from_provider(
+            SdkTracerProvider::builder()
+                .with_simple_exporter(exporter.clone())
+                .build(),
+        );
+        let subscriber = tracing_subscriber::registry().with(otel.layer());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let config = MarsConfig::default()
+            .with_max_iterations(1)
+            .with_model_pricing("mock/priced", crate::cost::ModelPricing::new(0.01, 0.02));
+        let mut coordinator = MarsCoordinator::with_provider(config, Arc::new(Priced));
+        let output = coordinator.run("What is 2+2?").await.unwrap();
+        otel.flush().unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let run = spans.iter().find(|s| s.name == "mars_run").unwrap();
+        assert_eq!(
+            attribute(run, "run_id"),
+            Some(&Value::from(output.run_id.clone()))
+        );
+        let trace_id = run.span_context.trace_id();
+        assert!(spans.iter().all(|s| s.span_context.trace_id() == trace_id));
+
+        let exploration = spans
+            .iter()
+            .find(|s| {
+                s.name == "mars_phase" && attribute(s, "phase") == Some(&Value::from("Exploration"))
+            })
+            .unwrap();
+        assert_eq!(exploration.parent_span_id, run.span_context.span_id());
+        assert_eq!(attribute(exploration, "llm_calls"), Some(&Value::I64(3)));
+        assert!(matches!(attribute(exploration, "cost_usd"), Some(Value::F64(usd)) if *usd > 0.0));
+
+        let call = spans.iter().find(|s| s.name == "llm_call").unwrap();
+        assert_eq!(attribute(call, "prompt_tokens"), Some(&Value::I64(1000)));
+        assert_eq!(attribute(call, "completion_tokens"), Some(&Value::I64(500)));
+        // 1k prompt tokens at $0.01 and 0.5k completion tokens at $0.02
+        assert_eq!(attribute(call, "cost_usd"), Some(&Value::F64(0.02)));
+    }
+}
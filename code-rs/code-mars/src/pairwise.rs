@@ -0,0 +1,275 @@
+//! Pairwise comparison verification with Elo-style ranking.
+//!
+//! Instead of scoring solutions in isolation, a judge model is shown two
+//! solutions at a time and picks the better one. Outcomes are folded into Elo
+//! ratings, which selection can then rank by.
+
+use crate::LLMProvider;
+use crate::Result;
+use crate::prompts;
+use crate::types::Solution;
+use std::collections::HashMap;
+
+/// Rating every solution starts from
+pub const INITIAL_RATING: f32 = 1000.0;
+
+/// Default Elo K-factor (maximum rating change per comparison)
+pub const DEFAULT_K_FACTOR: f32 = 32.0;
+
+/// Outcome of comparing two solutions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PairwiseOutcome {
+    /// The first solution (A) is better
+    FirstBetter,
+    /// The second solution (B) is better
+    SecondBetter,
+    /// Neither solution is clearly better
+    Tie,
+}
+
+/// Elo-style ranking built from pairwise outcomes
+#[derive(Clone, Debug)]
+pub struct EloRanking {
+    ratings: HashMap<String, f32>,
+    k_factor: f32,
+}
+
+impl EloRanking {
+    /// Create an empty ranking with the given K-factor
+    pub fn new(k_factor: f32) -> Self {
+        Self {
+            ratings: HashMap::new(),
+            k_factor,
+        }
+    }
+
+    /// Current rating of a solution (the initial rating if never compared)
+    pub fn rating(&self, solution_id: &str) -> f32 {
+        self.ratings
+            .get(solution_id)
+            .copied()
+            .unwrap_or(INITIAL_RATING)
+    }
+
+    /// Expected score of a player rated `rating` against one rated `opponent`
+    pub fn expected_score(rating: f32, opponent: f32) -> f32 {
+        1.0 / (1.0 + 10f32.powf((opponent - rating) / 400.0))
+    }
+
+    /// Update both ratings from the outcome of comparing `first` with `second`
+    pub fn record(&mut self, first: &str, second: &str, outcome: PairwiseOutcome) {
+        let first_rating = self.rating(first);
+        let second_rating = self.rating(second);
+        let first_actual = match outcome {
+            PairwiseOutcome::FirstBetter => 1.0,
+            PairwiseOutcome::SecondBetter => 0.0,
+            PairwiseOutcome::Tie => 0.5,
+        };
+        let first_expected = Self::expected_score(first_rating, second_rating);
+        let delta = self.k_factor * (first_actual - first_expected);
+
+        self.ratings.insert(first.to_string(), first_rating + delta);
+        self.ratings
+            .insert(second.to_string(), second_rating - delta);
+    }
+
+    /// Solution IDs with their ratings, best first
+    pub fn ranked(&self) -> Vec<(String, f32)> {
+        let mut ranked: Vec<_> = self
+            .ratings
+            .iter()
+            .map(|(id, rating)| (id.clone(), *rating))
+            .collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranked
+    }
+
+    /// Mean rating across all ranked solutions
+    pub fn mean_rating(&self) -> f32 {
+        if self.ratings.is_empty() {
+            return INITIAL_RATING;
+        }
+        self.ratings.values().sum::<f32>() / self.ratings.len() as f32
+    }
+}
+
+impl Default for EloRanking {
+    fn default() -> Self {
+        Self::new(DEFAULT_K_FACTOR)
+    }
+}
+
+/// Verifier that judges solutions against each other rather than in isolation
+pub struct PairwiseVerifier;
+
+impl PairwiseVerifier {
+    /// Ask the provider which of two solutions is better
+    pub async fn compare(
+        query: &str,
+        first: &Solution,
+        second: &Solution,
+        provider: &dyn LLMProvider,
+    ) -> Result<PairwiseOutcome> {
+        let prompt = format!(
+            "Problem:\n{query}\n\n\
+             Solution A:\n{}\nAnswer: {}\n\n\
+             Solution B:\n{}\nAnswer: {}",
+            first.reasoning, first.answer, second.reasoning, second.answer
+        );
+
+        let response = provider
+            .complete(&prompt, Some(prompts::PAIRWISE_COMPARISON_PROMPT))
+            .await?;
+
+        Ok(Self::parse_outcome(&response.text))
+    }
+
+    /// Parse the `WINNER:` line of a comparison response (ties when missing)
+    pub fn parse_outcome(response: &str) -> PairwiseOutcome {
+        for line in response.lines() {
+            let line = line.trim().trim_start_matches(['*', '#']).trim();
+            let Some((label, value)) = line.split_once(':') else {
+                continue;
+            };
+            if !label
+                .trim()
+                .trim_end_matches('*')
+                .eq_ignore_ascii_case("WINNER")
+            {
+                continue;
+            }
+
+            let value = value.trim().trim_matches('*').trim().to_ascii_uppercase();
+            return match value.as_str() {
+                "A" | "SOLUTION A" => PairwiseOutcome::FirstBetter,
+                "B" | "SOLUTION B" => PairwiseOutcome::SecondBetter,
+                _ => PairwiseOutcome::Tie,
+            };
+        }
+
+        PairwiseOutcome::Tie
+    }
+
+    /// Rank solutions with a round-robin of pairwise comparisons
+    ///
+    /// Comparisons that fail are skipped; the error of the last failure is
+    /// returned only if every comparison failed.
+    pub async fn rank_solutions(
+        query: &str,
+        solutions: &[Solution],
+        provider: &dyn LLMProvider,
+    ) -> Result<EloRanking> {
+        let mut ranking = EloRanking::default();
+        let mut last_error = None;
+        let mut compared = 0;
+
+        for (i, first) in solutions.iter().enumerate() {
+            for second in &solutions[i + 1..] {
+                match Self::compare(query, first, second, provider).await {
+                    Ok(outcome) => {
+                        ranking.record(&first.id, &second.id, outcome);
+                        compared += 1;
+                    }
+                    Err(e) => last_error = Some(e),
+                }
+            }
+        }
+
+        match last_error {
+            Some(e) if compared == 0 => Err(e),
+            _ => Ok(ranking),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+
+    /// Mock judge that prefers whichever solution answers "4"
+    struct MockJudge;
+
+    #[async_trait]
+    impl LLMProvider for MockJudge {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            let (a, b) = prompt.split_once("Solution B:").unwrap_or((prompt, ""));
+            let winner = match (a.contains("Answer: 4"), b.contains("Answer: 4")) {
+                (true, false) => "A",
+                (false, true) => "B",
+                _ => "TIE",
+            };
+            Ok(format!("WINNER: {winner}\nREASON: checked the answer").into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-judge"
+        }
+    }
+
+    fn solution(answer: &str) -> Solution {
+        Solution::new(
+            "agent".to_string(),
+            "reasoning".to_string(),
+            answer.to_string(),
+            0.5,
+            10,
+        )
+    }
+
+    #[test]
+    fn test_parse_outcome() {
+        assert_eq!(
+            PairwiseVerifier::parse_outcome("WINNER: A\nREASON: x"),
+            PairwiseOutcome::FirstBetter
+        );
+        assert_eq!(
+            PairwiseVerifier::parse_outcome("**Winner:** b"),
+            PairwiseOutcome::SecondBetter
+        );
+        assert_eq!(
+            PairwiseVerifier::parse_outcome("no verdict"),
+            PairwiseOutcome::Tie
+        );
+    }
+
+    #[test]
+    fn test_elo_updates_are_zero_sum() {
+        let mut ranking = EloRanking::default();
+        ranking.record("a", "b", PairwiseOutcome::FirstBetter);
+
+        assert!(ranking.rating("a") > INITIAL_RATING);
+        assert!(ranking.rating("b") < INITIAL_RATING);
+        assert!((ranking.rating("a") + ranking.rating("b") - 2.0 * INITIAL_RATING).abs() < 1e-3);
+
+        ranking.record("a", "c", PairwiseOutcome::Tie);
+        assert!(ranking.rating("c") > INITIAL_RATING);
+    }
+
+    #[tokio::test]
+    async fn test_rank_solutions() {
+        let solutions = vec![solution("5"), solution("4"), solution("3")];
+
+        let ranking = PairwiseVerifier::rank_solutions("What is 2+2?", &solutions, &MockJudge)
+            .await
+            .unwrap();
+
+        let ranked = ranking.ranked();
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(ranked[0].0, solutions[1].id);
+    }
+}
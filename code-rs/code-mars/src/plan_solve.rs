@@ -0,0 +1,222 @@
+//! Plan-and-solve aggregation.
+//!
+//! A first call reads the strongest existing solutions and writes a
+//! step-by-step plan that combines their best ideas. A second call carries the
+//! plan out and produces the final answer.
+//!
+//! Both the plan and the executed solution are returned so they can enter the
+//! workspace. The plan lists its source solutions in `parent_ids`, and the
+//! executed solution lists the plan.
+
+use crate::LLMProvider;
+use crate::MarsError;
+use crate::Result;
+use crate::agent::Agent;
+use crate::aggregator::split_final_answer;
+use crate::prompts;
+use crate::types::{GenerationPhase, Solution};
+
+/// Plans from the best existing solutions, then executes the plan
+pub struct PlanAndSolveAggregator;
+
+impl PlanAndSolveAggregator {
+    /// Plan from the `num_sources` highest-scoring solutions, then execute
+    ///
+    /// Returns `[plan, executed]`, or nothing when there are no solutions.
+    pub async fn run(
+        query: &str,
+        solutions: &[Solution],
+        num_sources: usize,
+        system_prompt: &str,
+        provider: &dyn LLMProvider,
+    ) -> Result<Vec<Solution>> {
+        if solutions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut sources = solutions.to_vec();
+        sources.sort_by(|a, b| b.verification_score.total_cmp(&a.verification_score));
+        sources.truncate(num_sources.max(1));
+
+        let plan = Self::plan(query, &sources, system_prompt, provider).await?;
+        let executed = Self::execute(query, &plan, system_prompt, provider).await?;
+
+        Ok(vec![plan, executed])
+    }
+
+    /// Ask the LLM for a plan that synthesizes the source solutions
+    async fn plan(
+        query: &str,
+        sources: &[Solution],
+        system_prompt: &str,
+        provider: &dyn LLMProvider,
+    ) -> Result<Solution> {
+        let listing = sources
+            .iter()
+            .enumerate()
+            .map(|(idx, source)| {
+                format!(
+                    "Solution {}:\nReasoning: {}\nAnswer: {}",
+                    idx + 1,
+                    source.reasoning,
+                    source.answer
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let prompt = format!(
+            "{}\n\nProblem:\n{}\n\nSolutions:\n{}",
+            prompts::PLAN_AND_SOLVE_PLAN_PROMPT,
+            query,
+            listing
+        );
+
+        let response = provider.complete(&prompt, Some(system_prompt)).await?;
+        if response.text.trim().is_empty() {
+            return Err(MarsError::AggregationError(
+                "Empty plan from plan-and-solve".to_string(),
+            ));
+        }
+
+        let mut plan = Solution::new(
+            "plan-and-solve-planner".to_string(),
+            response.text.trim().to_string(),
+            String::new(),
+            0.5,
+            0,
+        );
+        plan.record_completion(&response, prompt.len());
+        plan.phase = GenerationPhase::Planned;
+        plan.steps = Agent::parse_steps(&plan.reasoning);
+        plan.record_provider(provider, None);
+        plan.parent_ids = sources.iter().map(|source| source.id.clone()).collect();
+
+        Ok(plan)
+    }
+
+    /// Carry out the plan and produce the final answer
+    async fn execute(
+        query: &str,
+        plan: &Solution,
+        system_prompt: &str,
+        provider: &dyn LLMProvider,
+    ) -> Result<Solution> {
+        let prompt = format!(
+            "{}\n\nProblem:\n{}\n\nPlan:\n{}",
+            prompts::PLAN_AND_SOLVE_EXECUTE_PROMPT,
+            query,
+            plan.reasoning
+        );
+
+        let response = provider.complete(&prompt, Some(system_prompt)).await?;
+        if response.text.trim().is_empty() {
+            return Err(MarsError::AggregationError(
+                "Empty response executing plan-and-solve plan".to_string(),
+            ));
+        }
+
+        let (reasoning, answer) = split_final_answer(&response.text);
+        let mut executed = Solution::new(
+            "plan-and-solve-executor".to_string(),
+            reasoning,
+            answer,
+            0.5,
+            0,
+        );
+        executed.record_completion(&response, prompt.len());
+        executed.phase = GenerationPhase::Aggregated;
+        executed.steps = Agent::parse_steps(&executed.reasoning);
+        executed.record_provider(provider, None);
+        executed.parent_ids = vec![plan.id.clone()];
+
+        Ok(executed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+
+    /// Mock provider that writes a two-step plan and executes it to "4"
+    struct MockPlanner;
+
+    #[async_trait]
+    impl LLMProvider for MockPlanner {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if prompt.starts_with(prompts::PLAN_AND_SOLVE_PLAN_PROMPT) {
+                assert!(prompt.contains("Solution 1:\nReasoning: strong"));
+                assert!(!prompt.contains("weak"));
+                Ok("Step 1: Take both numbers\nStep 2: Add them".into())
+            } else {
+                assert!(prompt.contains("Step 2: Add them"));
+                Ok("Step 1: 2 + 2 = 4\nANSWER: 4".into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-planner"
+        }
+    }
+
+    fn scored(reasoning: &str, score: f32) -> Solution {
+        let mut solution = Solution::new(
+            "agent".to_string(),
+            reasoning.to_string(),
+            "4".to_string(),
+            0.5,
+            10,
+        );
+        solution.verification_score = score;
+        solution
+    }
+
+    #[tokio::test]
+    async fn test_plan_and_solve_links_lineage() {
+        let sources = vec![scored("weak", 0.1), scored("strong", 0.9)];
+
+        let results = PlanAndSolveAggregator::run(
+            "What is 2+2?",
+            &sources,
+            1,
+            prompts::MARS_SYSTEM_PROMPT,
+            &MockPlanner,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), 2);
+        let (plan, executed) = (&results[0], &results[1]);
+        assert_eq!(plan.phase, GenerationPhase::Planned);
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.parent_ids, vec![sources[1].id.clone()]);
+
+        assert_eq!(executed.answer, "4");
+        assert_eq!(executed.phase, GenerationPhase::Aggregated);
+        assert_eq!(executed.parent_ids, vec![plan.id.clone()]);
+        assert_eq!(executed.model, "mock/mock-planner");
+    }
+
+    #[tokio::test]
+    async fn test_plan_and_solve_without_solutions() {
+        let results =
+            PlanAndSolveAggregator::run("q", &[], 3, prompts::MARS_SYSTEM_PROMPT, &MockPlanner)
+                .await
+                .unwrap();
+        assert!(results.is_empty());
+    }
+}
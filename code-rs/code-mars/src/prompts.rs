@@ -23,30 +23,85 @@ Your goal is to arrive at the correct solution through systematic analysis."#;
 /// Initial reasoning prompt for agents
 pub const MARS_REASONING_PROMPT: &str = r#"Please solve the following problem step by step.
 Show all your work and reasoning. Be thorough and systematic.
-Consider edge cases and verify your logic at each step."#;
+Consider edge cases and verify your logic at each step.
+
+Structure your reasoning as numbered steps:
+Step 1: [claim]
+Justification: [why the claim holds]
+Result: [intermediate result, if any]"#;
 
 /// System prompt for the verification agent
 pub const VERIFICATION_SYSTEM_PROMPT: &str = r#"You are an expert verifier tasked with evaluating solutions.
-Assess the provided solution for:
-1. Mathematical correctness - Is the answer actually correct?
+Assess the provided solution against this rubric:
+1. Correctness - Is the answer actually correct?
 2. Completeness - Does the solution address all aspects of the problem?
 3. Rigor - Is the reasoning sound and well-justified?
-4. Clarity - Is the solution easy to follow?
 
-Provide a verification result: CORRECT or INCORRECT
-Also provide a confidence score from 0.0 to 1.0.
+Score each criterion from 0.0 to 1.0 and briefly justify the score.
+Then provide a verification result (CORRECT or INCORRECT) and an overall
+confidence score from 0.0 to 1.0.
 
 Format your response as:
+CORRECTNESS: [0.0-1.0] | [Feedback on correctness]
+COMPLETENESS: [0.0-1.0] | [Feedback on completeness]
+RIGOR: [0.0-1.0] | [Feedback on rigor]
 RESULT: CORRECT|INCORRECT
 SCORE: [0.0-1.0]
 FEEDBACK: [Your detailed feedback]"#;
 
+/// System prompt for adversarial verification that hunts for counterexamples
+pub const ADVERSARIAL_VERIFICATION_PROMPT: &str = r#"You are an adversarial reviewer. Your goal is to break the given solution.
+Try to construct a concrete counterexample: a failing input, an edge case, or a
+step where the argument does not hold. Check boundary values, empty or degenerate
+inputs, and every assumption the solution makes without proof.
+
+Only report a counterexample you can state concretely. If you cannot find one
+after a genuine search, say NONE.
+
+Format your response as:
+COUNTEREXAMPLE: NONE|[The concrete counterexample or failing input]
+PLAUSIBILITY: [0.0-1.0 confidence that the counterexample really breaks the solution]
+REASON: [Why the counterexample breaks the solution, or what you checked]"#;
+
+/// System prompt for generating unit tests from a problem statement alone
+pub const TEST_GENERATION_PROMPT: &str = r#"You are writing a hidden test suite for a programming problem.
+You will see only the problem statement, not any solution. Write test cases that
+a correct program must pass, covering typical inputs, edge cases and boundary
+values. Each test feeds `input` to the program on stdin and expects exactly
+`expected_output` on stdout (surrounding whitespace is ignored).
+
+Only include tests whose expected output you are certain of.
+
+Respond with a JSON array and nothing else:
+[{"input": "...", "expected_output": "..."}]"#;
+
+/// System prompt for comparing two candidate solutions head to head
+pub const PAIRWISE_COMPARISON_PROMPT: &str = r#"You are an expert judge comparing two solutions to the same problem.
+Decide which solution is more likely to be correct, considering the final answer
+first and the soundness of the reasoning second. Do not prefer a solution for
+being longer or for appearing first.
+
+Format your response as:
+WINNER: A|B|TIE
+REASON: [One or two sentences explaining the decision]"#;
+
 /// Prompt for improving unverified solutions
 pub const IMPROVEMENT_PROMPT: &str = r#"The previous solution needs improvement.
 Please revise it to address the feedback provided.
 Be particularly careful to fix any errors in reasoning.
 Provide your improved solution with clear step-by-step reasoning."#;
 
+/// Introduces strategy-network strategies appended to agent prompts
+pub const STRATEGY_GUIDANCE_PROMPT: &str = r#"The following strategies worked well for other agents on this kind of problem.
+Use them where they apply, but do not force a strategy that does not fit."#;
+
+/// Prompt for proposers in later layers of multi-layer MOA
+pub const MOA_LAYER_PROMPT: &str = r#"You have been provided with a set of responses from various models
+to the latest user query. Your task is to synthesize these responses into a single, high-quality
+response. Critically evaluate the information provided in these responses, recognizing that some
+of it may be biased or incorrect. Your response should not simply replicate the given answers but
+should offer a refined, accurate, and comprehensive reply to the query."#;
+
 /// Prompt for aggregating multiple solutions
 pub const AGGREGATION_PROMPT: &str = r#"You are given multiple solutions to the same problem.
 Your task is to synthesize the best elements from each solution.
@@ -60,8 +115,103 @@ Please create an improved solution that:
 3. Provides clear, step-by-step reasoning
 4. Arrives at the most likely correct answer
 
+End your response with a final line of the form:
+ANSWER: [final answer]
+
 Synthesized solution:"#;
 
+/// Prompt for labelling a query before a run; placeholder: `{query}`
+pub const QUERY_CLASSIFICATION_PROMPT: &str = r#"Classify the request below into exactly one category:
+- math: a mathematical problem, proof or calculation
+- code: writing, fixing or explaining code
+- short: a short factual question with a brief answer
+- general: anything else
+
+Request:
+{query}
+
+Reply with only the category name."#;
+
+/// Aggregation system prompt for [`Preset::Math`](crate::types::Preset::Math)
+pub const MATH_PRESET_PROMPT: &str = r#"You are an expert mathematician.
+Check every step of the candidate solutions, prefer rigorous derivations over
+plausible guesses, and make sure the final answer is exact and fully simplified."#;
+
+/// Aggregation system prompt for [`Preset::Coding`](crate::types::Preset::Coding)
+pub const CODING_PRESET_PROMPT: &str = r#"You are an expert software engineer.
+Prefer correct, complete and idiomatic code over clever code. Handle edge cases,
+keep the requested function signatures, and put the final program in a single
+fenced code block."#;
+
+/// Aggregation system prompt for [`Preset::Creative`](crate::types::Preset::Creative)
+pub const CREATIVE_PRESET_PROMPT: &str = r#"You are a skilled writer and editor.
+Combine the most original ideas and the strongest passages of the candidates into
+one coherent piece with a consistent voice. Favor vivid, specific language."#;
+
+/// Aggregation system prompt for [`Preset::Research`](crate::types::Preset::Research)
+pub const RESEARCH_PRESET_PROMPT: &str = r#"You are a careful research analyst.
+Keep only claims the candidates support with evidence or sound reasoning, point
+out where they disagree, and state the uncertainty that remains."#;
+
+/// Prompt for genetic-algorithm crossover of two parent solutions
+pub const GENETIC_CROSSOVER_PROMPT: &str = r#"You are given two candidate solutions (parents) to the same problem.
+Produce a single child solution that combines the strongest ideas of both parents.
+Keep steps that are correct in either parent and discard steps that are wrong.
+
+End your response with a final line of the form:
+ANSWER: [final answer]"#;
+
+/// Prompt for genetic-algorithm mutation of a solution
+pub const GENETIC_MUTATION_PROMPT: &str = r#"You are given a candidate solution to a problem.
+Produce a variant of it: try a different method for at least one key step,
+or challenge an assumption the solution relies on, then re-derive the answer.
+
+End your response with a final line of the form:
+ANSWER: [final answer]"#;
+
+/// Prompt for continuing a partial solution during beam search
+pub const BEAM_CONTINUATION_PROMPT: &str = r#"You are given a partial solution to a problem.
+Continue it with the next few reasoning steps. Do not repeat the steps already taken;
+fix any mistake you notice in them before moving on.
+
+Number new steps as "Step N: [claim]" and end your response with a final line of the form:
+ANSWER: [best answer so far]"#;
+
+/// Prompt for rating a best-of-N sample with a single reward score
+pub const BEST_OF_N_REWARD_PROMPT: &str = r#"You are a reward model scoring a response to a problem.
+Judge how likely the response is to be correct and how well it is reasoned.
+
+Reply with a single line of the form:
+SCORE: [0.0-1.0]"#;
+
+/// Prompt for writing a plan that synthesizes existing solutions
+pub const PLAN_AND_SOLVE_PLAN_PROMPT: &str = r#"You are given several solutions to the same problem.
+Do not solve the problem yet. Write a step-by-step plan for solving it that combines
+the best ideas from these solutions and avoids the mistakes they make.
+
+Number the steps as "Step N: [what to do]"."#;
+
+/// Prompt for executing a plan to produce the final solution
+pub const PLAN_AND_SOLVE_EXECUTE_PROMPT: &str = r#"You are given a problem and a plan for solving it.
+Carry out the plan step by step, fixing any step that turns out to be wrong.
+
+End your response with a final line of the form:
+ANSWER: [final answer]"#;
+
+/// Prompt for proposing typed code-edit operations during MCTS
+pub const CODE_ACTION_PROPOSAL_PROMPT: &str = r#"You are improving code for the task below.
+Propose distinct next edits, one per line, in the form:
+kind | region | description
+
+kind is one of: add_test, refactor, fix_bug, implement
+region names the function, type, or lines the edit touches (use - for the whole file)"#;
+
+/// Prompt for applying one code-edit operation during MCTS
+pub const CODE_ACTION_APPLY_PROMPT: &str = r#"You are editing code for the task below.
+Apply exactly the requested edit and nothing else.
+
+Reply with the complete updated code in a single fenced code block."#;
+
 /// Prompt for extracting strategies from successful solutions
 pub const STRATEGY_EXTRACTION_PROMPT: &str = r#"Analyze the following successful solution and identify key strategies and techniques used.
 
@@ -71,6 +221,14 @@ Solution:
 Please identify and list 3-5 key strategies or techniques that contributed to solving this problem well.
 Format as a numbered list with brief explanations."#;
 
+/// Prompt for consolidating overlapping strategies into one canonical entry
+pub const STRATEGY_MERGE_PROMPT: &str = r#"The following strategies overlap. Consolidate them into a single canonical strategy
+that keeps every distinct idea they contain.
+
+Respond in exactly this format:
+DESCRIPTION: <one-sentence description of the strategy>
+TECHNIQUE: <how to apply it>"#;
+
 /// Prompt for cross-agent strategy sharing
 pub const STRATEGY_SHARING_PROMPT: &str = r#"You have access to strategies that other agents have successfully used:
 
@@ -79,6 +237,25 @@ pub const STRATEGY_SHARING_PROMPT: &str = r#"You have access to strategies that
 Use these insights to improve your reasoning approach. Which of these strategies might be helpful?
 How can you incorporate them into solving the current problem?"#;
 
+/// Asks for reasoning and answers in a response language; placeholders: `{language}`
+pub const RESPONSE_LANGUAGE_PROMPT: &str = "Write all of your reasoning and your final answer in {language}, whatever language the problem is written in.";
+
+/// Asks verifiers for feedback in a response language without translating
+/// the verdict keywords; placeholders: `{language}`
+pub const VERIFICATION_LANGUAGE_PROMPT: &str = "Write your feedback in {language}, but keep the labels of the response format (RESULT:, SCORE:, ...) and the CORRECT/INCORRECT verdict in English.";
+
+/// Requires the synthesized answer in a response language; placeholders: `{language}`
+pub const SYNTHESIS_LANGUAGE_PROMPT: &str = "Write the final answer and its explanation in {language}, even if the approaches above use another language.";
+
+/// Asks for a final `ANSWER:` line holding a single number
+pub const NUMERIC_ANSWER_PROMPT: &str = "End your response with a final line of the form `ANSWER: <number>`, giving the number alone, without units, words or LaTeX.";
+
+/// Asks for the solution as one fenced code block
+pub const CODE_ANSWER_PROMPT: &str = "Give your final solution as a single fenced code block tagged with its language (for example ```python), after any explanation.";
+
+/// Asks for a final `ANSWER:` line holding an option letter
+pub const MULTIPLE_CHOICE_ANSWER_PROMPT: &str = "End your response with a final line of the form `ANSWER: <letter>`, giving only the letter of the option you choose.";
+
 /// Prompt for final synthesis when no consensus is reached
 pub const SYNTHESIS_PROMPT: &str = r#"Multiple reasoning approaches have been tried for this problem:
 
@@ -123,6 +300,167 @@ If the response is wrapped in <think></think> tags, extract the content after th
 The answer should be concise and directly answerable.
 If no clear answer is provided, indicate that the answer could not be extracted."#;
 
+/// User overrides for the prompts used during aggregation
+///
+/// Unset fields keep the built-in prompts. Templates may reference the
+/// placeholders listed on each field; see [`render_template`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AggregationPrompts {
+    /// System prompt for aggregation calls (default: [`MARS_SYSTEM_PROMPT`])
+    pub system: Option<String>,
+    /// MOA proposer prompt; placeholders: `{query}`, `{references}` (the
+    /// previous layer's outputs, empty on the first layer)
+    pub moa_proposer: Option<String>,
+    /// MOA final synthesis prompt; placeholders: `{query}`, `{candidates}`,
+    /// `{critique}`
+    pub moa_aggregator: Option<String>,
+    /// RSA merge prompt; placeholders: `{query}`, `{solutions}`
+    pub rsa_merge: Option<String>,
+}
+
+impl AggregationPrompts {
+    /// System prompt for aggregation calls
+    pub fn system_prompt(&self) -> &str {
+        self.system.as_deref().unwrap_or(MARS_SYSTEM_PROMPT)
+    }
+
+    /// Every override slot, by field name
+    pub(crate) fn templates_mut(&mut self) -> [(&'static str, &mut Option<String>); 4] {
+        [
+            ("system", &mut self.system),
+            ("moa_proposer", &mut self.moa_proposer),
+            ("moa_aggregator", &mut self.moa_aggregator),
+            ("rsa_merge", &mut self.rsa_merge),
+        ]
+    }
+}
+
+/// A language instruction such as [`RESPONSE_LANGUAGE_PROMPT`] for `language`
+pub fn language_instruction(template: &str, language: &str) -> String {
+    render_template(template, &[("language", language)])
+}
+
+/// Overrides for the agent prompts of exploration, verification,
+/// improvement and strategy extraction
+///
+/// Unset fields keep the built-in prompt. Templates substitute the
+/// placeholders listed on each field; see [`render_template`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PromptSet {
+    /// System prompt for generation and improvement calls (default:
+    /// [`MARS_SYSTEM_PROMPT_WITH_THINKING`] or [`MARS_SYSTEM_PROMPT`],
+    /// following `use_thinking_tags`)
+    pub system: Option<String>,
+    /// Exploration prompt; placeholders: `{query}`
+    pub reasoning: Option<String>,
+    /// Verification system prompt; placeholders: `{query}`. Keep the
+    /// `RESULT:`/`SCORE:`/`FEEDBACK:` lines of [`VERIFICATION_SYSTEM_PROMPT`]
+    /// so verdicts still parse.
+    pub verification: Option<String>,
+    /// Improvement prompt; placeholders: `{solution}`, `{answer}`, `{feedback}`
+    pub improvement: Option<String>,
+    /// Strategy extraction prompt; placeholders: `{solution}`
+    pub strategy_extraction: Option<String>,
+}
+
+impl PromptSet {
+    /// System prompt for generation and improvement calls
+    pub fn system_prompt(&self, use_thinking_tags: bool) -> &str {
+        match &self.system {
+            Some(system) => system,
+            None if use_thinking_tags => MARS_SYSTEM_PROMPT_WITH_THINKING,
+            None => MARS_SYSTEM_PROMPT,
+        }
+    }
+
+    /// Every override slot, by field name
+    pub(crate) fn templates_mut(&mut self) -> [(&'static str, &mut Option<String>); 5] {
+        [
+            ("system", &mut self.system),
+            ("reasoning", &mut self.reasoning),
+            ("verification", &mut self.verification),
+            ("improvement", &mut self.improvement),
+            ("strategy_extraction", &mut self.strategy_extraction),
+        ]
+    }
+
+    /// Exploration prompt for `query`
+    pub fn reasoning_prompt(&self, query: &str) -> String {
+        match &self.reasoning {
+            Some(template) => render_template(template, &[("query", query)]),
+            None => format!("{MARS_REASONING_PROMPT}\n\n{query}"),
+        }
+    }
+
+    /// Verification system prompt for `query`
+    pub fn verification_prompt(&self, query: &str) -> String {
+        match &self.verification {
+            Some(template) => render_template(template, &[("query", query)]),
+            None => VERIFICATION_SYSTEM_PROMPT.to_string(),
+        }
+    }
+
+    /// Improvement prompt for a solution and the feedback on it
+    pub fn improvement_prompt(&self, solution: &crate::types::Solution, feedback: &str) -> String {
+        match &self.improvement {
+            Some(template) => render_template(
+                template,
+                &[
+                    ("solution", &solution.reasoning),
+                    ("answer", &solution.answer),
+                    ("feedback", feedback),
+                ],
+            ),
+            None => format!(
+                "{IMPROVEMENT_PROMPT}\n\nOriginal solution:\nReasoning: {}\nAnswer: {}\n\nFeedback: {feedback}",
+                solution.reasoning, solution.answer
+            ),
+        }
+    }
+
+    /// Strategy extraction prompt for a solution
+    pub fn strategy_extraction_prompt(&self, solution: &crate::types::Solution) -> String {
+        let template = self
+            .strategy_extraction
+            .as_deref()
+            .unwrap_or(STRATEGY_EXTRACTION_PROMPT);
+        render_template(template, &[("solution", &solution.reasoning)])
+    }
+}
+
+/// Substitute `{name}` placeholders in a template
+///
+/// Substitution is a single pass, so placeholders that appear inside a
+/// substituted value are left alone. Unknown placeholders are kept verbatim.
+pub fn render_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            values
+                .iter()
+                .find(|(name, _)| *name == &after[..end])
+                .map(|(_, value)| (*value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                rendered.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,10 +471,36 @@ mod tests {
         assert!(!MARS_REASONING_PROMPT.is_empty());
         assert!(!VERIFICATION_SYSTEM_PROMPT.is_empty());
         assert!(!IMPROVEMENT_PROMPT.is_empty());
+        assert!(!STRATEGY_GUIDANCE_PROMPT.is_empty());
+        assert!(!STRATEGY_MERGE_PROMPT.is_empty());
+        assert!(!PAIRWISE_COMPARISON_PROMPT.is_empty());
+        assert!(!ADVERSARIAL_VERIFICATION_PROMPT.is_empty());
+        assert!(!TEST_GENERATION_PROMPT.is_empty());
     }
 
     #[test]
     fn test_thinking_tags_in_prompt() {
         assert!(MARS_SYSTEM_PROMPT_WITH_THINKING.contains("think"));
     }
+
+    #[test]
+    fn test_render_template() {
+        let rendered = render_template(
+            "Q: {query}\n{solutions} {unknown} {",
+            &[("query", "{solutions}"), ("solutions", "A, B")],
+        );
+        assert_eq!(rendered, "Q: {solutions}\nA, B {unknown} {");
+    }
+
+    #[test]
+    fn test_aggregation_prompts_default_system() {
+        let prompts = AggregationPrompts::default();
+        assert_eq!(prompts.system_prompt(), MARS_SYSTEM_PROMPT);
+
+        let custom = AggregationPrompts {
+            system: Some("You are a legal analyst.".to_string()),
+            ..AggregationPrompts::default()
+        };
+        assert_eq!(custom.system_prompt(), "You are a legal analyst.");
+    }
 }
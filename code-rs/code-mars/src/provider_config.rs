@@ -2,7 +2,12 @@
 ///
 /// Manages provider selection, API keys, and routing strategies.
 
+use crate::key_pool::KeySelection;
+use crate::model_router::{HttpOptions, HttpTimeouts};
+use crate::rate_limit::RateLimits;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
 
 /// Specification for a single LLM provider
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -14,16 +19,72 @@ pub struct ProviderSpec {
     pub model: String,
 
     /// API key for authentication (can be loaded from env)
+    ///
+    /// May reference environment variables as `${OPENAI_API_KEY}`, resolved
+    /// by [`ProviderSpec::resolve_env`].
+    #[serde(default)]
     pub api_key: String,
 
+    /// Environment variable to read the API key from when `api_key` is empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+
+    /// Further API keys to spread requests across, alongside `api_key`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<String>,
+
+    /// How requests pick among several keys
+    #[serde(default, skip_serializing_if = "KeySelection::is_default")]
+    pub key_selection: KeySelection,
+
     /// Optional custom base URL for provider
+    #[serde(default)]
     pub base_url: Option<String>,
 
     /// Whether this provider is available for multi-agent use
+    #[serde(default = "default_true")]
     pub enabled: bool,
 
     /// Priority for selection (higher = preferred)
+    #[serde(default)]
     pub priority: usize,
+
+    /// Relative share of calls under [`RoutingStrategy::Random`] (default 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+
+    /// Azure OpenAI deployment name (defaults to the model name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<String>,
+
+    /// Azure OpenAI `api-version` query parameter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+
+    /// Request, token and concurrency limits applied to this provider
+    #[serde(default, skip_serializing_if = "RateLimits::is_unlimited")]
+    pub rate_limits: RateLimits,
+
+    /// Request, connect and read timeouts for this provider's HTTP calls
+    #[serde(default, skip_serializing_if = "HttpTimeouts::is_unset")]
+    pub timeouts: HttpTimeouts,
+
+    /// Proxy this provider's requests go through (e.g. `http://proxy.corp:3128`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    /// Extra headers sent with every request (e.g. a gateway token)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+
+    /// Provider-specific parameters merged into every request body (e.g.
+    /// `reasoning_effort`, `logprobs`), overriding the crate's own values
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra_params: serde_json::Map<String, serde_json::Value>,
+
+    /// Safety thresholds passed through to Gemini (ignored by other providers)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub safety_settings: Vec<crate::gemini::SafetySetting>,
 }
 
 impl ProviderSpec {
@@ -33,12 +94,36 @@ impl ProviderSpec {
             provider: provider.to_string(),
             model: model.to_string(),
             api_key: String::new(),
+            api_key_env: None,
+            api_keys: Vec::new(),
+            key_selection: KeySelection::default(),
             base_url: None,
             enabled: true,
             priority: 0,
+            weight: None,
+            deployment: None,
+            api_version: None,
+            rate_limits: RateLimits::default(),
+            timeouts: HttpTimeouts::default(),
+            proxy: None,
+            headers: HashMap::new(),
+            extra_params: serde_json::Map::new(),
+            safety_settings: Vec::new(),
         }
     }
 
+    /// Parse `provider/model`, reading the API key from `<PROVIDER>_API_KEY`
+    /// (e.g. `OPENAI_API_KEY` for `openai/gpt-4o`)
+    pub fn from_model_id(id: &str) -> crate::Result<Self> {
+        let Some((provider, model)) = id.split_once('/') else {
+            return Err(crate::MarsError::InvalidConfiguration(format!(
+                "model must be provider/model, got {id}"
+            )));
+        };
+        let key_env = format!("{}_API_KEY", provider.to_ascii_uppercase());
+        Ok(Self::new(provider, model).with_env_key(&key_env))
+    }
+
     /// Create from environment variable for API key
     pub fn with_env_key(mut self, env_var: &str) -> Self {
         if let Ok(key) = std::env::var(env_var) {
@@ -53,6 +138,36 @@ impl ProviderSpec {
         self
     }
 
+    /// Read the API key from `env_var` when the spec is resolved, keeping
+    /// the key itself out of serialized configs
+    pub fn with_api_key_env(mut self, env_var: &str) -> Self {
+        self.api_key_env = Some(env_var.to_string());
+        self
+    }
+
+    /// Spread requests across these keys as well as `api_key`
+    pub fn with_api_keys(mut self, keys: Vec<String>) -> Self {
+        self.api_keys = keys;
+        self
+    }
+
+    /// Set how requests pick among several keys
+    pub fn with_key_selection(mut self, selection: KeySelection) -> Self {
+        self.key_selection = selection;
+        self
+    }
+
+    /// Every distinct, non-empty API key, `api_key` first
+    pub fn all_api_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::new();
+        for key in std::iter::once(&self.api_key).chain(&self.api_keys) {
+            if !key.is_empty() && !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        keys
+    }
+
     /// Set base URL
     pub fn with_base_url(mut self, url: String) -> Self {
         self.base_url = Some(url);
@@ -71,7 +186,127 @@ impl ProviderSpec {
         self
     }
 
+    /// Set the relative share of calls under [`RoutingStrategy::Random`]
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    /// Set the Azure OpenAI deployment to route requests to
+    pub fn with_deployment(mut self, deployment: String) -> Self {
+        self.deployment = Some(deployment);
+        self
+    }
+
+    /// Set the Azure OpenAI API version
+    pub fn with_api_version(mut self, api_version: String) -> Self {
+        self.api_version = Some(api_version);
+        self
+    }
+
+    /// Start at most `rpm` requests per minute
+    pub fn with_requests_per_minute(mut self, rpm: u32) -> Self {
+        self.rate_limits.requests_per_minute = Some(rpm);
+        self
+    }
+
+    /// Send at most `tpm` (estimated) tokens per minute
+    pub fn with_tokens_per_minute(mut self, tpm: u32) -> Self {
+        self.rate_limits.tokens_per_minute = Some(tpm);
+        self
+    }
+
+    /// Keep at most `max` requests in flight at once
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.rate_limits.max_in_flight = Some(max);
+        self
+    }
+
+    /// Give up on requests that take longer than `timeout` in total
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.request_secs = Some(timeout.as_secs_f64());
+        self
+    }
+
+    /// Give up on connections not established within `timeout`
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.connect_secs = Some(timeout.as_secs_f64());
+        self
+    }
+
+    /// Give up on responses that go quiet for longer than `timeout`
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.read_secs = Some(timeout.as_secs_f64());
+        self
+    }
+
+    /// Send requests through a proxy
+    pub fn with_proxy(mut self, url: String) -> Self {
+        self.proxy = Some(url);
+        self
+    }
+
+    /// Send an extra header with every request
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Merge a provider-specific parameter into every request body
+    pub fn with_extra_param(mut self, name: &str, value: serde_json::Value) -> Self {
+        self.extra_params.insert(name.to_string(), value);
+        self
+    }
+
+    /// Timeouts, proxy and extra headers for the provider's HTTP client
+    pub fn http_options(&self) -> HttpOptions {
+        HttpOptions {
+            timeouts: self.timeouts.clone(),
+            proxy: self.proxy.clone(),
+            headers: self.headers.clone(),
+        }
+    }
+
+    /// Add a Gemini safety threshold for a harm category
+    pub fn with_safety_setting(mut self, category: &str, threshold: &str) -> Self {
+        self.safety_settings
+            .push(crate::gemini::SafetySetting::new(category, threshold));
+        self
+    }
+
+    /// Copy of the spec with its secrets read from the environment
+    ///
+    /// `${VAR}` references in the API keys, header values, proxy and base URL
+    /// are replaced by the variables' values, and `api_key_env` fills in an
+    /// empty `api_key`. Fails, naming the variable, if one is not set.
+    pub fn resolve_env(&self) -> Result<Self, String> {
+        let context =
+            |field: &str, e: String| format!("{field} of {}/{}: {e}", self.provider, self.model);
+        let mut spec = self.clone();
+        spec.api_key = interpolate_env(&spec.api_key).map_err(|e| context("api_key", e))?;
+        for key in &mut spec.api_keys {
+            *key = interpolate_env(key).map_err(|e| context("api_keys", e))?;
+        }
+        if spec.api_key.is_empty()
+            && let Some(var) = &self.api_key_env
+        {
+            spec.api_key = env_var(var).map_err(|e| context("api_key_env", e))?;
+        }
+        for value in spec.headers.values_mut() {
+            *value = interpolate_env(value).map_err(|e| context("headers", e))?;
+        }
+        if let Some(proxy) = &mut spec.proxy {
+            *proxy = interpolate_env(proxy).map_err(|e| context("proxy", e))?;
+        }
+        if let Some(url) = &mut spec.base_url {
+            *url = interpolate_env(url).map_err(|e| context("base_url", e))?;
+        }
+        Ok(spec)
+    }
+
     /// Validate that required fields are set
+    ///
+    /// Environment variable references are resolved first.
     pub fn validate(&self) -> Result<(), String> {
         if self.provider.is_empty() {
             return Err("Provider name cannot be empty".to_string());
@@ -79,16 +314,67 @@ impl ProviderSpec {
         if self.model.is_empty() {
             return Err("Model name cannot be empty".to_string());
         }
-        if self.api_key.is_empty() {
+        let resolved = self.resolve_env()?;
+        if resolved.all_api_keys().is_empty() {
             return Err(format!(
                 "API key not set for provider: {}",
                 self.provider
             ));
         }
+        resolved.http_options().validate()?;
+        if let Some(weight) = self.weight
+            && !(weight.is_finite() && weight >= 0.0)
+        {
+            return Err(format!("Routing weight must be non-negative, got {weight}"));
+        }
+        if crate::azure::is_azure(&self.provider) && self.base_url.is_none() {
+            return Err(format!(
+                "Azure endpoint (base URL) not set for model: {}",
+                self.model
+            ));
+        }
         Ok(())
     }
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_retries() -> usize {
+    1
+}
+
+fn default_timeout_seconds() -> u64 {
+    300
+}
+
+/// Value of environment variable `name`
+fn env_var(name: &str) -> Result<String, String> {
+    std::env::var(name).map_err(|_| format!("environment variable {name} is not set"))
+}
+
+/// `value` with each `${VAR}` replaced by the variable's value
+pub(crate) fn interpolate_env(value: &str) -> Result<String, String> {
+    let mut resolved = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        resolved.push_str(&rest[..start]);
+        let reference = &rest[start + 2..];
+        let end = reference
+            .find('}')
+            .ok_or_else(|| "unterminated ${ reference".to_string())?;
+        let name = &reference[..end];
+        if name.is_empty() {
+            return Err("empty ${} reference".to_string());
+        }
+        resolved.push_str(&env_var(name)?);
+        rest = &reference[end + 1..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
 /// Strategy for routing requests to providers
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum RoutingStrategy {
@@ -101,16 +387,16 @@ pub enum RoutingStrategy {
     /// Use provider with highest priority
     HighestPriority,
 
-    /// Random selection from enabled providers
+    /// Random selection from enabled providers, weighted by their `weight`
     Random,
 
-    /// Use cheapest provider (requires cost data)
+    /// Use cheapest provider by the pricing table (unpriced providers last)
     Cheapest,
 
-    /// Use fastest provider (requires latency data)
+    /// Use fastest provider by measured latency (unmeasured providers first)
     Fastest,
 
-    /// Distribute evenly across all providers
+    /// Use the provider with the fewest calls in flight
     BalanceLoad,
 }
 
@@ -120,6 +406,23 @@ impl Default for RoutingStrategy {
     }
 }
 
+/// MARS phase that can be pinned to its own provider
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum MarsPhase {
+    /// Multi-agent exploration (initial solutions)
+    Exploration,
+    /// Solution aggregation
+    Aggregation,
+    /// Cross-agent verification
+    Verification,
+    /// Iterative improvement
+    Improvement,
+    /// Strategy extraction for the strategy network
+    StrategyExtraction,
+    /// Final answer synthesis, when no consensus or verified solution decides
+    Synthesis,
+}
+
 /// Configuration for multi-provider routing
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProviderRoutingConfig {
@@ -127,19 +430,28 @@ pub struct ProviderRoutingConfig {
     pub primary: ProviderSpec,
 
     /// Alternative providers for diversity/failover
+    #[serde(default)]
     pub alternatives: Vec<ProviderSpec>,
 
     /// Routing strategy for selecting providers
+    #[serde(default)]
     pub strategy: RoutingStrategy,
 
     /// Enable fallback on provider failure
+    #[serde(default = "default_true")]
     pub enable_fallback: bool,
 
     /// Maximum retries per provider on failure
+    #[serde(default = "default_max_retries")]
     pub max_retries: usize,
 
-    /// Timeout per provider in seconds
+    /// Request timeout in seconds for providers whose spec sets none
+    #[serde(default = "default_timeout_seconds")]
     pub timeout_seconds: u64,
+
+    /// Providers pinned to specific phases (e.g. a stronger model for verification)
+    #[serde(default)]
+    pub phase_providers: HashMap<MarsPhase, ProviderSpec>,
 }
 
 impl ProviderRoutingConfig {
@@ -150,8 +462,9 @@ impl ProviderRoutingConfig {
             alternatives: Vec::new(),
             strategy: RoutingStrategy::Primary,
             enable_fallback: true,
-            max_retries: 1,
-            timeout_seconds: 300,
+            max_retries: default_max_retries(),
+            timeout_seconds: default_timeout_seconds(),
+            phase_providers: HashMap::new(),
         }
     }
 
@@ -164,6 +477,7 @@ impl ProviderRoutingConfig {
             enable_fallback: true,
             max_retries: 2,
             timeout_seconds: 300,
+            phase_providers: HashMap::new(),
         }
     }
 
@@ -179,6 +493,58 @@ impl ProviderRoutingConfig {
         providers
     }
 
+    /// Get the enabled provider pinned to a phase, if any
+    pub fn phase_provider(&self, phase: MarsPhase) -> Option<&ProviderSpec> {
+        self.phase_providers.get(&phase).filter(|p| p.enabled)
+    }
+
+    /// Get the provider to use for a phase, falling back to the primary
+    pub fn provider_for_phase(&self, phase: MarsPhase) -> &ProviderSpec {
+        self.phase_provider(phase).unwrap_or(&self.primary)
+    }
+
+    /// `spec` with `timeout_seconds` as its request timeout, unless it sets its own
+    pub fn with_default_timeout(&self, spec: &ProviderSpec) -> ProviderSpec {
+        let mut spec = spec.clone();
+        spec.timeouts
+            .request_secs
+            .get_or_insert(self.timeout_seconds as f64);
+        spec
+    }
+
+    /// Load a routing config from a TOML, YAML or JSON file, with
+    /// `MARS__PROVIDER_ROUTING__` environment overrides (see
+    /// [`crate::config_file`]) and secrets read from the environment
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> crate::Result<Self> {
+        let prefix = format!("{}PROVIDER_ROUTING__", crate::config_file::ENV_PREFIX);
+        let config: Self =
+            crate::config_file::load(path.as_ref(), serde_json::Value::Null, &prefix)?;
+        config
+            .resolve_env()
+            .map_err(|errors| crate::MarsError::InvalidConfiguration(errors.join("; ")))
+    }
+
+    /// Copy of the config with every provider's secrets read from the
+    /// environment (see [`ProviderSpec::resolve_env`])
+    pub fn resolve_env(&self) -> Result<Self, Vec<String>> {
+        let mut config = self.clone();
+        let mut errors = Vec::new();
+        let specs = std::iter::once(&mut config.primary)
+            .chain(&mut config.alternatives)
+            .chain(config.phase_providers.values_mut());
+        for spec in specs.filter(|spec| spec.enabled) {
+            match spec.resolve_env() {
+                Ok(resolved) => *spec = resolved,
+                Err(e) => errors.push(e),
+            }
+        }
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
     /// Validate all provider configurations
     pub fn validate(&self) -> Result<(), Vec<String>> {
         let mut errors = Vec::new();
@@ -195,6 +561,12 @@ impl ProviderRoutingConfig {
             }
         }
 
+        for (phase, spec) in self.phase_providers.iter().filter(|(_, p)| p.enabled) {
+            if let Err(e) = spec.validate() {
+                errors.push(format!("{phase:?} provider: {e}"));
+            }
+        }
+
         if errors.is_empty() {
             Ok(())
         } else {
@@ -225,6 +597,22 @@ impl ProviderRoutingConfig {
         self.timeout_seconds = seconds;
         self
     }
+
+    /// Pin a phase to a specific provider
+    pub fn with_phase_provider(mut self, phase: MarsPhase, provider: ProviderSpec) -> Self {
+        self.phase_providers.insert(phase, provider);
+        self
+    }
+
+    /// Pin a phase to another model of the primary provider (same key, base
+    /// URL and HTTP options)
+    pub fn with_phase_model(self, phase: MarsPhase, model: &str) -> Self {
+        let mut spec = self.primary.clone();
+        spec.model = model.to_string();
+        // An Azure deployment names one model, so the new model gets its own
+        spec.deployment = None;
+        self.with_phase_provider(phase, spec)
+    }
 }
 
 #[cfg(test)]
@@ -239,6 +627,17 @@ mod tests {
         assert!(spec.enabled);
     }
 
+    #[test]
+    fn test_provider_spec_from_model_id() {
+        // SAFETY: the variable is unique to this test
+        unsafe { std::env::set_var("MARSTESTMODELID_API_KEY", "sk-model-id") };
+        let spec = ProviderSpec::from_model_id("marstestmodelid/llama3:8b").unwrap();
+        assert_eq!(spec.provider, "marstestmodelid");
+        assert_eq!(spec.model, "llama3:8b");
+        assert_eq!(spec.api_key, "sk-model-id");
+        assert!(ProviderSpec::from_model_id("gpt-4o").is_err());
+    }
+
     #[test]
     fn test_provider_spec_validation() {
         let spec = ProviderSpec::new("openai", "gpt-4o");
@@ -246,6 +645,86 @@ mod tests {
 
         let spec = spec.with_api_key("test-key".to_string());
         assert!(spec.validate().is_ok());
+        assert!(spec.clone().with_weight(-1.0).validate().is_err());
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let spec = ProviderSpec::new("openai", "gpt-4o")
+            .with_connect_timeout(Duration::from_secs(5))
+            .with_read_timeout(Duration::from_millis(1500));
+        assert_eq!(spec.timeouts.connect(), Some(Duration::from_secs(5)));
+        assert_eq!(spec.timeouts.read(), Some(Duration::from_millis(1500)));
+
+        // The routing config's timeout only fills in a missing request timeout
+        let routing = ProviderRoutingConfig::single(spec.clone()).with_timeout(90);
+        let defaulted = routing.with_default_timeout(&spec);
+        assert_eq!(defaulted.timeouts.request(), Some(Duration::from_secs(90)));
+        let own = spec.with_request_timeout(Duration::from_secs(30));
+        let kept = routing.with_default_timeout(&own);
+        assert_eq!(kept.timeouts.request(), Some(Duration::from_secs(30)));
+
+        let json = serde_json::to_value(&kept).unwrap();
+        assert_eq!(json["timeouts"]["request_secs"], 30.0);
+        assert!(
+            serde_json::to_value(ProviderSpec::new("openai", "gpt-4o"))
+                .unwrap()
+                .get("timeouts")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_proxy_and_headers_are_validated() {
+        let spec = ProviderSpec::new("openai", "gpt-4o")
+            .with_api_key("key".to_string())
+            .with_proxy("http://proxy.corp:3128".to_string())
+            .with_header("X-Gateway-Token", "secret");
+        assert!(spec.validate().is_ok());
+        assert_eq!(spec.http_options().headers["X-Gateway-Token"], "secret");
+
+        let bad_header = spec.clone().with_header("Bad Header", "x");
+        assert!(bad_header.validate().unwrap_err().contains("Bad Header"));
+        let bad_proxy = spec.with_proxy("not a url".to_string());
+        assert!(bad_proxy.validate().unwrap_err().contains("proxy"));
+    }
+
+    #[test]
+    fn test_secrets_resolve_from_env() {
+        // SAFETY: the variables are unique to this test
+        unsafe {
+            std::env::set_var("MARS_TEST_SPEC_KEY", "sk-env");
+            std::env::set_var("MARS_TEST_SPEC_TOKEN", "gw-token");
+        }
+        let spec = ProviderSpec::new("openai", "gpt-4o")
+            .with_api_key("${MARS_TEST_SPEC_KEY}".to_string())
+            .with_header("X-Gateway-Token", "Bearer ${MARS_TEST_SPEC_TOKEN}");
+        let resolved = spec.resolve_env().unwrap();
+        assert_eq!(resolved.api_key, "sk-env");
+        assert_eq!(resolved.headers["X-Gateway-Token"], "Bearer gw-token");
+        // The reference, not the key, is what gets serialized
+        assert!(
+            serde_json::to_string(&spec)
+                .unwrap()
+                .contains("${MARS_TEST_SPEC_KEY}")
+        );
+
+        let from_env = ProviderSpec::new("openai", "gpt-4o").with_api_key_env("MARS_TEST_SPEC_KEY");
+        assert_eq!(from_env.resolve_env().unwrap().api_key, "sk-env");
+        assert!(from_env.validate().is_ok());
+
+        let missing = ProviderSpec::new("openai", "gpt-4o")
+            .with_api_key("${MARS_TEST_SPEC_MISSING}".to_string());
+        let err = missing.validate().unwrap_err();
+        assert!(err.contains("MARS_TEST_SPEC_MISSING"), "{err}");
+        let unterminated =
+            ProviderSpec::new("openai", "gpt-4o").with_api_key("${MARS_TEST_SPEC_KEY".to_string());
+        assert!(unterminated.resolve_env().is_err());
+
+        let routing = ProviderRoutingConfig::single(missing)
+            .with_phase_provider(MarsPhase::Verification, from_env);
+        let errors = routing.resolve_env().unwrap_err();
+        assert_eq!(errors.len(), 1);
     }
 
     #[test]
@@ -274,4 +753,68 @@ mod tests {
         assert_eq!(config.strategy, RoutingStrategy::RoundRobin);
         assert_eq!(config.get_enabled_providers().len(), 2);
     }
+
+    #[test]
+    fn test_phase_provider_routing() {
+        let primary = ProviderSpec::new("openai", "gpt-4o-mini").with_api_key("key1".to_string());
+        let verifier =
+            ProviderSpec::new("anthropic", "claude-3-5-sonnet").with_api_key("key2".to_string());
+
+        let config = ProviderRoutingConfig::single(primary)
+            .with_phase_provider(MarsPhase::Verification, verifier);
+
+        assert_eq!(
+            config.provider_for_phase(MarsPhase::Verification).provider,
+            "anthropic"
+        );
+        assert_eq!(
+            config.provider_for_phase(MarsPhase::Exploration).model,
+            "gpt-4o-mini"
+        );
+        assert!(config.phase_provider(MarsPhase::Improvement).is_none());
+        assert!(config.validate().is_ok());
+
+        let config = config.with_phase_model(MarsPhase::Synthesis, "gpt-4o");
+        let synthesizer = config.provider_for_phase(MarsPhase::Synthesis);
+        assert_eq!(synthesizer.provider, "openai");
+        assert_eq!(synthesizer.model, "gpt-4o");
+        assert_eq!(synthesizer.api_key, "key1");
+    }
+
+    #[test]
+    fn test_azure_spec_requires_endpoint() {
+        let spec = ProviderSpec::new("azure", "gpt-4o")
+            .with_api_key("key".to_string())
+            .with_deployment("gpt4o-prod".to_string());
+        assert!(spec.validate().is_err());
+
+        let spec = spec.with_base_url("https://contoso.openai.azure.com".to_string());
+        assert!(spec.validate().is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit_builders() {
+        let spec = ProviderSpec::new("openai", "gpt-4o")
+            .with_requests_per_minute(500)
+            .with_max_in_flight(8);
+        assert_eq!(spec.rate_limits.requests_per_minute, Some(500));
+        assert_eq!(spec.rate_limits.max_in_flight, Some(8));
+        assert!(spec.rate_limits.tokens_per_minute.is_none());
+
+        let json = serde_json::to_value(ProviderSpec::new("openai", "gpt-4o")).unwrap();
+        assert!(json.get("rate_limits").is_none());
+    }
+
+    #[test]
+    fn test_safety_settings_optional_in_json() {
+        let spec: ProviderSpec = serde_json::from_str(
+            r#"{"provider":"gemini","model":"gemini-2.0-flash","api_key":"k","base_url":null,"enabled":true,"priority":0}"#,
+        )
+        .unwrap();
+        assert!(spec.safety_settings.is_empty());
+
+        let spec = spec.with_safety_setting("HARM_CATEGORY_HARASSMENT", "BLOCK_NONE");
+        let json = serde_json::to_value(&spec).unwrap();
+        assert_eq!(json["safety_settings"][0]["threshold"], "BLOCK_NONE");
+    }
 }
@@ -0,0 +1,358 @@
+//! Per-provider rate limiting and concurrency control.
+//!
+//! Parallel exploration fires one request per agent at once, which trips most
+//! providers' rate limits immediately. [`RateLimitedProvider`] wraps any
+//! [`LLMProvider`] and holds requests back until they fit within a sliding
+//! one-minute window of requests and tokens, with at most a fixed number in
+//! flight. Token counts are estimated from text length (about four characters
+//! per token), matching the estimates used elsewhere in MARS.
+
+use crate::Result;
+use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+use crate::structured::ResponseFormat;
+use crate::tools::{ToolAwareResponse, ToolSpec};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+/// Span over which per-minute limits are counted
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits for one provider; unset limits are not enforced
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Requests started per minute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+    /// Prompt and completion tokens per minute (estimated)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_minute: Option<u32>,
+    /// Requests awaiting a response at the same time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_in_flight: Option<usize>,
+}
+
+impl RateLimits {
+    /// Whether no limit is set
+    pub fn is_unlimited(&self) -> bool {
+        self.requests_per_minute.is_none()
+            && self.tokens_per_minute.is_none()
+            && self.max_in_flight.is_none()
+    }
+}
+
+/// Requests and tokens used at one moment
+struct Usage {
+    at: Instant,
+    requests: u32,
+    tokens: u64,
+}
+
+/// Provider wrapper that enforces [`RateLimits`]
+pub struct RateLimitedProvider {
+    inner: Arc<dyn LLMProvider>,
+    limits: RateLimits,
+    /// Span over which per-minute limits are counted (shortened in tests)
+    window: Duration,
+    in_flight: Option<Arc<Semaphore>>,
+    /// Usage within the current window, oldest first
+    usage: Mutex<VecDeque<Usage>>,
+}
+
+impl RateLimitedProvider {
+    /// Wrap `inner`, enforcing `limits` on every call
+    pub fn new(inner: Arc<dyn LLMProvider>, limits: RateLimits) -> Self {
+        // Zero permits would block forever
+        let in_flight = limits
+            .max_in_flight
+            .map(|max| Arc::new(Semaphore::new(max.max(1))));
+        Self {
+            inner,
+            limits,
+            window: WINDOW,
+            in_flight,
+            usage: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Limits being enforced
+    pub fn limits(&self) -> &RateLimits {
+        &self.limits
+    }
+
+    /// Wait for an in-flight slot and room in the window for one request of
+    /// `tokens` prompt tokens, then record it
+    ///
+    /// The returned permit frees the in-flight slot when dropped.
+    async fn acquire(&self, tokens: u64) -> Result<Option<OwnedSemaphorePermit>> {
+        let permit = match &self.in_flight {
+            Some(semaphore) => Some(semaphore.clone().acquire_owned().await.map_err(|e| {
+                crate::MarsError::ClientError(format!("{}: {e}", self.inner.model_id()))
+            })?),
+            None => None,
+        };
+
+        loop {
+            let mut usage = self.usage.lock().await;
+            let now = Instant::now();
+            while usage
+                .front()
+                .is_some_and(|oldest| now.duration_since(oldest.at) >= self.window)
+            {
+                usage.pop_front();
+            }
+
+            let requests: u32 = usage.iter().map(|u| u.requests).sum();
+            let used_tokens: u64 = usage.iter().map(|u| u.tokens).sum();
+            let requests_ok = self
+                .limits
+                .requests_per_minute
+                .is_none_or(|rpm| requests < rpm.max(1));
+            // A prompt larger than the whole budget still goes out once the window is empty
+            let tokens_ok = self
+                .limits
+                .tokens_per_minute
+                .is_none_or(|tpm| usage.is_empty() || used_tokens + tokens <= u64::from(tpm));
+
+            if requests_ok && tokens_ok {
+                usage.push_back(Usage {
+                    at: now,
+                    requests: 1,
+                    tokens,
+                });
+                return Ok(permit);
+            }
+
+            // Retry once the oldest usage leaves the window
+            let wait_until = usage.front().map_or(now, |oldest| oldest.at + self.window);
+            drop(usage);
+            tracing::debug!(
+                "{} rate limited; waiting {:?}",
+                self.inner.model_id(),
+                wait_until.saturating_duration_since(now)
+            );
+            tokio::time::sleep_until(wait_until).await;
+        }
+    }
+
+    /// Count completion tokens against the per-minute budget
+    async fn record_tokens(&self, tokens: u64) {
+        if self.limits.tokens_per_minute.is_some() && tokens > 0 {
+            self.usage.lock().await.push_back(Usage {
+                at: Instant::now(),
+                requests: 0,
+                tokens,
+            });
+        }
+    }
+}
+
+/// Estimated tokens in a prompt and its system prompt
+fn prompt_tokens(prompt: &str, system_prompt: Option<&str>) -> u64 {
+    ((prompt.len() + system_prompt.map_or(0, str::len)) / 4) as u64
+}
+
+#[async_trait]
+impl LLMProvider for RateLimitedProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        let _permit = self.acquire(prompt_tokens(prompt, system_prompt)).await?;
+        let response = self.inner.complete(prompt, system_prompt).await?;
+        self.record_tokens(response.completion_tokens_or_estimate() as u64)
+            .await;
+        Ok(response)
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        let _permit = self.acquire(prompt_tokens(prompt, system_prompt)).await?;
+        let response = self
+            .inner
+            .complete_with_temperature(prompt, system_prompt, temperature)
+            .await?;
+        self.record_tokens(response.completion_tokens_or_estimate() as u64)
+            .await;
+        Ok(response)
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let _permit = self.acquire(prompt_tokens(prompt, system_prompt)).await?;
+        let stream = self.inner.stream(prompt, system_prompt).await?;
+        self.record_tokens((stream.content_len() / 4) as u64).await;
+        Ok(stream)
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let tokens = texts.iter().map(|text| (text.len() / 4) as u64).sum();
+        let _permit = self.acquire(tokens).await?;
+        self.inner.embed(texts).await
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        let _permit = self.acquire(prompt_tokens(prompt, system_prompt)).await?;
+        let response = self
+            .inner
+            .complete_with_tools(prompt, system_prompt, tools)
+            .await?;
+        self.record_tokens(response.response.completion_tokens_or_estimate() as u64)
+            .await;
+        Ok(response)
+    }
+
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        let _permit = self.acquire(prompt_tokens(prompt, system_prompt)).await?;
+        let response = self
+            .inner
+            .complete_structured(prompt, system_prompt, format)
+            .await?;
+        self.record_tokens(response.completion_tokens_or_estimate() as u64)
+            .await;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Echoes prompts after a delay, tracking peak concurrency
+    #[derive(Default)]
+    struct SlowEcho {
+        delay: Duration,
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLMProvider for SlowEcho {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(prompt.into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            Ok(ModelStream::new(
+                self.complete(prompt, system_prompt).await?.text,
+            ))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "slow-echo"
+        }
+    }
+
+    fn limited(inner: Arc<SlowEcho>, limits: RateLimits, window: Duration) -> RateLimitedProvider {
+        let mut provider = RateLimitedProvider::new(inner, limits);
+        provider.window = window;
+        provider
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight_caps_concurrency() {
+        let inner = Arc::new(SlowEcho {
+            delay: Duration::from_millis(30),
+            ..Default::default()
+        });
+        let limits = RateLimits {
+            max_in_flight: Some(2),
+            ..Default::default()
+        };
+        let provider = limited(inner.clone(), limits, WINDOW);
+
+        let calls = (0..6).map(|i| {
+            let provider = &provider;
+            async move { provider.complete(&format!("q{i}"), None).await }
+        });
+        let answers = futures::future::join_all(calls).await;
+
+        assert!(answers.iter().all(Result::is_ok));
+        assert_eq!(inner.peak.load(Ordering::SeqCst), 2);
+        assert_eq!(provider.model_id(), "mock/slow-echo");
+    }
+
+    #[tokio::test]
+    async fn test_requests_per_minute_waits_for_window() {
+        let window = Duration::from_millis(200);
+        let limits = RateLimits {
+            requests_per_minute: Some(2),
+            ..Default::default()
+        };
+        let provider = limited(Arc::new(SlowEcho::default()), limits, window);
+
+        let start = Instant::now();
+        provider.complete("a", None).await.unwrap();
+        provider.complete("b", None).await.unwrap();
+        assert!(start.elapsed() < window);
+
+        provider.complete("c", None).await.unwrap();
+        assert!(start.elapsed() >= window);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_per_minute_counts_prompt_and_completion() {
+        let window = Duration::from_millis(200);
+        let limits = RateLimits {
+            tokens_per_minute: Some(10),
+            ..Default::default()
+        };
+        let provider = limited(Arc::new(SlowEcho::default()), limits, window);
+
+        // 4 prompt tokens plus 4 echoed back fill most of the budget
+        let start = Instant::now();
+        provider.complete(&"x".repeat(16), None).await.unwrap();
+        assert!(start.elapsed() < window);
+
+        provider.complete(&"x".repeat(16), None).await.unwrap();
+        assert!(start.elapsed() >= window);
+    }
+
+    #[test]
+    fn test_limits_serde_defaults() {
+        let limits: RateLimits = serde_json::from_str(r#"{"requests_per_minute":60}"#).unwrap();
+        assert_eq!(limits.requests_per_minute, Some(60));
+        assert!(limits.max_in_flight.is_none());
+        assert!(!limits.is_unlimited());
+        assert!(RateLimits::default().is_unlimited());
+    }
+}
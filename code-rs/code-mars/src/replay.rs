@@ -0,0 +1,563 @@
+//! Record/replay providers for tests that run without API keys.
+//!
+//! [`RecordingProvider`] wraps a real provider and writes every successful
+//! call, request and response, to a JSON [`Cassette`]. [`ReplayProvider`]
+//! loads a cassette and answers identical requests with the recorded
+//! responses, so a coordinator run recorded once against a live API can be
+//! replayed in CI deterministically and for free.
+//!
+//! Requests match on the method called and all of its arguments. Repeated
+//! identical requests get their recorded responses in order, then the last
+//! one again; requests that were never recorded are an error. Failed calls
+//! are not recorded.
+//!
+//! Only available with the `test-utils` feature.
+
+use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+use crate::structured::ResponseFormat;
+use crate::tools::{ToolAwareResponse, ToolSpec};
+use crate::trace::{TraceEntry, TraceWriter};
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+pub use crate::trace::{RecordedRequest, RecordedResponse};
+
+impl RecordedRequest {
+    /// Key identical requests share
+    fn key(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// A recorded call and its response
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Interaction {
+    /// The call
+    pub request: RecordedRequest,
+    /// What it returned
+    pub response: RecordedResponse,
+}
+
+/// Calls recorded against one provider, as stored in a fixture file
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Cassette {
+    /// Name of the provider the calls were made to
+    pub provider: String,
+    /// Name of the model the calls were made to
+    pub model: String,
+    /// Calls in the order they completed
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Read a cassette from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            MarsError::StorageError(format!("Failed to read cassette {}: {e}", path.display()))
+        })?;
+        serde_json::from_str(&contents).map_err(|e| {
+            MarsError::StorageError(format!("Invalid cassette {}: {e}", path.display()))
+        })
+    }
+
+    /// Cassette of the calls in a JSON Lines trace, in the order they completed
+    ///
+    /// Failed and redacted calls are left out; the provider and model are
+    /// those of the first call.
+    pub fn from_trace(path: impl AsRef<Path>) -> Result<Self> {
+        let mut cassette = Self::default();
+        for entry in TraceWriter::read(path)? {
+            let TraceEntry::Call(call) = entry else {
+                continue;
+            };
+            let call = *call;
+            let Some(response) = call.response.filter(|_| !call.redacted) else {
+                continue;
+            };
+            if cassette.interactions.is_empty() {
+                cassette.provider = call.provider;
+                cassette.model = call.model;
+            }
+            cassette.interactions.push(Interaction {
+                request: call.request,
+                response,
+            });
+        }
+        Ok(cassette)
+    }
+
+    /// Write the cassette to a JSON file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| MarsError::StorageError(format!("Failed to serialize cassette: {e}")))?;
+        std::fs::write(path, contents).map_err(|e| {
+            MarsError::StorageError(format!("Failed to write cassette {}: {e}", path.display()))
+        })
+    }
+}
+
+/// Provider wrapper that records every successful call to a cassette file
+///
+/// The file is rewritten after each call, replacing any earlier recording.
+pub struct RecordingProvider {
+    inner: Arc<dyn LLMProvider>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordingProvider {
+    /// Record calls to `inner` in a cassette at `path`
+    pub fn new(inner: Arc<dyn LLMProvider>, path: impl Into<PathBuf>) -> Self {
+        let cassette = Cassette {
+            provider: inner.provider_name().to_string(),
+            model: inner.model_name().to_string(),
+            interactions: Vec::new(),
+        };
+        Self {
+            inner,
+            path: path.into(),
+            cassette: Mutex::new(cassette),
+        }
+    }
+
+    /// File the cassette is written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Calls recorded so far
+    pub fn cassette(&self) -> Cassette {
+        self.cassette
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Add a call to the cassette and rewrite its file
+    fn record(&self, request: RecordedRequest, response: RecordedResponse) {
+        let mut cassette = self.cassette.lock().unwrap_or_else(PoisonError::into_inner);
+        cassette
+            .interactions
+            .push(Interaction { request, response });
+        // A failed write only loses the fixture, not the call
+        if let Err(e) = cassette.save(&self.path) {
+            tracing::warn!("{e}");
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for RecordingProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        let response = self.inner.complete(prompt, system_prompt).await?;
+        self.record(
+            RecordedRequest::new("complete", prompt, system_prompt),
+            RecordedResponse::Completion(response.clone()),
+        );
+        Ok(response)
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        let response = self
+            .inner
+            .complete_with_temperature(prompt, system_prompt, temperature)
+            .await?;
+        let request = RecordedRequest {
+            temperature: Some(temperature),
+            ..RecordedRequest::new("complete_with_temperature", prompt, system_prompt)
+        };
+        self.record(request, RecordedResponse::Completion(response.clone()));
+        Ok(response)
+    }
+
+    async fn complete_n(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        n: usize,
+        temperature: Option<f32>,
+    ) -> Result<Vec<CompletionResponse>> {
+        let completions = self
+            .inner
+            .complete_n(prompt, system_prompt, n, temperature)
+            .await?;
+        let request = RecordedRequest {
+            temperature,
+            n: Some(n),
+            ..RecordedRequest::new("complete_n", prompt, system_prompt)
+        };
+        self.record(
+            request,
+            RecordedResponse::Completions {
+                completions: completions.clone(),
+            },
+        );
+        Ok(completions)
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let mut stream = self.inner.stream(prompt, system_prompt).await?;
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next_chunk() {
+            chunks.push(chunk);
+        }
+        self.record(
+            RecordedRequest::new("stream", prompt, system_prompt),
+            RecordedResponse::Completion(CompletionResponse::new(chunks.concat())),
+        );
+        Ok(ModelStream::from_chunks(chunks))
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let embeddings = self.inner.embed(texts).await?;
+        let request = RecordedRequest {
+            method: "embed".to_string(),
+            texts: texts.to_vec(),
+            ..RecordedRequest::default()
+        };
+        self.record(
+            request,
+            RecordedResponse::Embeddings {
+                embeddings: embeddings.clone(),
+            },
+        );
+        Ok(embeddings)
+    }
+
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        let response = self
+            .inner
+            .complete_structured(prompt, system_prompt, format)
+            .await?;
+        let request = RecordedRequest {
+            format: Some(format.clone()),
+            ..RecordedRequest::new("complete_structured", prompt, system_prompt)
+        };
+        self.record(request, RecordedResponse::Completion(response.clone()));
+        Ok(response)
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        let response = self
+            .inner
+            .complete_with_tools(prompt, system_prompt, tools)
+            .await?;
+        let request = RecordedRequest {
+            tools: tools.to_vec(),
+            ..RecordedRequest::new("complete_with_tools", prompt, system_prompt)
+        };
+        self.record(request, RecordedResponse::ToolCalls(response.clone()));
+        Ok(response)
+    }
+}
+
+/// Responses recorded for one request, and how many have been served
+struct Recorded {
+    responses: Vec<RecordedResponse>,
+    served: usize,
+}
+
+/// Provider that answers from a cassette instead of calling a model
+pub struct ReplayProvider {
+    provider: String,
+    model: String,
+    recorded: Mutex<HashMap<String, Recorded>>,
+}
+
+impl ReplayProvider {
+    /// Replay the calls in `cassette`
+    pub fn new(cassette: Cassette) -> Self {
+        let mut recorded: HashMap<String, Recorded> = HashMap::new();
+        for interaction in cassette.interactions {
+            recorded
+                .entry(interaction.request.key())
+                .or_insert_with(|| Recorded {
+                    responses: Vec::new(),
+                    served: 0,
+                })
+                .responses
+                .push(interaction.response);
+        }
+        Self {
+            provider: cassette.provider,
+            model: cassette.model,
+            recorded: Mutex::new(recorded),
+        }
+    }
+
+    /// Replay the cassette in a JSON file
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Cassette::load(path).map(Self::new)
+    }
+
+    /// Next recorded response to `request`
+    fn replay(&self, request: &RecordedRequest) -> Result<RecordedResponse> {
+        let mut recorded = self.recorded.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = recorded.get_mut(&request.key()).ok_or_else(|| {
+            MarsError::ClientError(format!(
+                "No recorded response to {} for prompt: {}",
+                request.method,
+                request.prompt.chars().take(200).collect::<String>()
+            ))
+        })?;
+        let index = entry.served.min(entry.responses.len().saturating_sub(1));
+        entry.served += 1;
+        entry.responses.get(index).cloned().ok_or_else(|| {
+            MarsError::ClientError(format!("Empty recording for {}", request.method))
+        })
+    }
+
+    /// Replay a single completion
+    fn replay_completion(&self, request: &RecordedRequest) -> Result<CompletionResponse> {
+        match self.replay(request)? {
+            RecordedResponse::Completion(response) => Ok(response),
+            other => Err(mismatch(request, &other)),
+        }
+    }
+}
+
+/// Error for a recording of the wrong kind of response
+fn mismatch(request: &RecordedRequest, response: &RecordedResponse) -> MarsError {
+    MarsError::ClientError(format!(
+        "Recorded response to {} has the wrong kind: {response:?}",
+        request.method
+    ))
+}
+
+#[async_trait]
+impl LLMProvider for ReplayProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        self.replay_completion(&RecordedRequest::new("complete", prompt, system_prompt))
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        self.replay_completion(&RecordedRequest {
+            temperature: Some(temperature),
+            ..RecordedRequest::new("complete_with_temperature", prompt, system_prompt)
+        })
+    }
+
+    async fn complete_n(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        n: usize,
+        temperature: Option<f32>,
+    ) -> Result<Vec<CompletionResponse>> {
+        let request = RecordedRequest {
+            temperature,
+            n: Some(n),
+            ..RecordedRequest::new("complete_n", prompt, system_prompt)
+        };
+        match self.replay(&request)? {
+            RecordedResponse::Completions { completions } => Ok(completions),
+            other => Err(mismatch(&request, &other)),
+        }
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let response =
+            self.replay_completion(&RecordedRequest::new("stream", prompt, system_prompt))?;
+        Ok(ModelStream::new(response.text))
+    }
+
+    fn provider_name(&self) -> &str {
+        &self.provider
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let request = RecordedRequest {
+            method: "embed".to_string(),
+            texts: texts.to_vec(),
+            ..RecordedRequest::default()
+        };
+        match self.replay(&request)? {
+            RecordedResponse::Embeddings { embeddings } => Ok(embeddings),
+            other => Err(mismatch(&request, &other)),
+        }
+    }
+
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        self.replay_completion(&RecordedRequest {
+            format: Some(format.clone()),
+            ..RecordedRequest::new("complete_structured", prompt, system_prompt)
+        })
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        let request = RecordedRequest {
+            tools: tools.to_vec(),
+            ..RecordedRequest::new("complete_with_tools", prompt, system_prompt)
+        };
+        match self.replay(&request)? {
+            RecordedResponse::ToolCalls(response) => Ok(response),
+            other => Err(mismatch(&request, &other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Numbers its responses so repeated calls are distinguishable
+    #[derive(Default)]
+    struct Counter {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLMProvider for Counter {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CompletionResponse::new(format!("{prompt} #{call}")).with_usage(3, 2))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "counter"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replays_recorded_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+
+        let recorder = RecordingProvider::new(Arc::new(Counter::default()), &path);
+        let first = recorder.complete("q", Some("system")).await.unwrap();
+        let second = recorder.complete("q", Some("system")).await.unwrap();
+        let hot = recorder
+            .complete_with_temperature("q", None, 0.5)
+            .await
+            .unwrap();
+        let samples = recorder.complete_n("q", None, 2, None).await.unwrap();
+
+        let replay = ReplayProvider::open(&path).unwrap();
+        assert_eq!(replay.model_id(), "mock/counter");
+        // Identical requests replay in order, then repeat the last response
+        assert_eq!(replay.complete("q", Some("system")).await.unwrap(), first);
+        assert_eq!(replay.complete("q", Some("system")).await.unwrap(), second);
+        assert_eq!(replay.complete("q", Some("system")).await.unwrap(), second);
+        assert_eq!(
+            replay
+                .complete_with_temperature("q", None, 0.5)
+                .await
+                .unwrap(),
+            hot
+        );
+        assert_eq!(
+            replay.complete_n("q", None, 2, None).await.unwrap(),
+            samples
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replays_calls_from_a_trace() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.jsonl");
+        let trace = Arc::new(TraceWriter::open(&path).unwrap());
+        let traced = crate::trace::TracingProvider::new(Arc::new(Counter::default()), trace);
+        let first = traced.complete("q", None).await.unwrap();
+        let samples = traced.complete_n("q", None, 2, Some(0.9)).await.unwrap();
+
+        let replay = ReplayProvider::new(Cassette::from_trace(&path).unwrap());
+        assert_eq!(replay.model_id(), "mock/counter");
+        assert_eq!(replay.complete("q", None).await.unwrap(), first);
+        assert_eq!(
+            replay.complete_n("q", None, 2, Some(0.9)).await.unwrap(),
+            samples
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unrecorded_request_is_an_error() {
+        let replay = ReplayProvider::new(Cassette::default());
+        let err = replay.complete("never asked", None).await.unwrap_err();
+        assert!(err.to_string().contains("never asked"));
+
+        // Same prompt, different temperature
+        let mut cassette = Cassette::default();
+        cassette.interactions.push(Interaction {
+            request: RecordedRequest::new("complete", "q", None),
+            response: RecordedResponse::Completion("a".into()),
+        });
+        let replay = ReplayProvider::new(cassette);
+        assert!(replay.complete("q", None).await.is_ok());
+        assert!(
+            replay
+                .complete_with_temperature("q", None, 0.7)
+                .await
+                .is_err()
+        );
+    }
+}
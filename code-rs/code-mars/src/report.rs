@@ -0,0 +1,390 @@
+//! Markdown reports of finished runs.
+//!
+//! [`MarsOutput::to_markdown`] renders the final answer, how it was
+//! selected, every solution with its scores, a verification summary, the
+//! strategies in play and tables of cost and phase timings, ready to paste
+//! into a pull request or issue thread.
+
+use crate::types::{MarsOutput, Solution};
+
+/// Characters of a solution ID shown in tables
+const SHORT_ID_LEN: usize = 8;
+
+impl MarsOutput {
+    /// A Markdown report of the run
+    pub fn to_markdown(&self) -> String {
+        let mut report = String::from("# MARS Run Report\n\n");
+        self.write_summary(&mut report);
+        self.write_alternatives(&mut report);
+        self.write_solutions(&mut report);
+        self.write_verification(&mut report);
+        self.write_strategies(&mut report);
+        self.write_cost(&mut report);
+        self.write_timings(&mut report);
+        report
+    }
+
+    fn write_summary(&self, report: &mut String) {
+        report.push_str(&format!("## Answer\n\n{}\n\n", self.answer.trim()));
+        report.push_str(&format!(
+            "- **Selected by:** {:?} (solution `{}`)\n",
+            self.selection_method,
+            short_id(&self.final_solution_id)
+        ));
+        if let Some(solution) = self.final_solution() {
+            report.push_str(&format!("- **Model:** {}\n", model_of(solution)));
+        }
+        report.push_str(&format!("- **Confidence:** {:.2}\n", self.confidence));
+        report.push_str(&format!("- **Iterations:** {}\n", self.iterations));
+        report.push_str(&format!(
+            "- **Tokens:** {} ({} prompt, {} completion{})\n",
+            self.total_tokens,
+            self.total_prompt_tokens,
+            self.total_completion_tokens,
+            if self.usage_estimated {
+                ", partly estimated"
+            } else {
+                ""
+            }
+        ));
+        if !self.run_id.is_empty() {
+            report.push_str(&format!("- **Run:** `{}`\n", self.run_id));
+        }
+        if !self.tags.is_empty() {
+            report.push_str(&format!("- **Tags:** {}\n", self.tags.join(", ")));
+        }
+        report.push_str(&format!(
+            "- **Completed:** {}\n",
+            self.completed_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+        if self.budget_truncated {
+            report.push_str("\n> The cost budget cut the search short before synthesis.\n");
+        }
+        report.push('\n');
+    }
+
+    fn write_alternatives(&self, report: &mut String) {
+        // A single alternative is just the answer again
+        if self.alternatives.len() < 2 {
+            return;
+        }
+        report.push_str(
+            "## Alternatives\n\n| Answer | Score | Votes | Verified |\n|---|---|---|---|\n",
+        );
+        for alternative in &self.alternatives {
+            report.push_str(&format!(
+                "| {} | {:.2} | {} | {} |\n",
+                cell(&alternative.answer),
+                alternative.score,
+                alternative.votes,
+                alternative.verified_votes
+            ));
+        }
+        report.push('\n');
+    }
+
+    fn write_solutions(&self, report: &mut String) {
+        report.push_str("## Solutions\n\n");
+        if self.all_solutions.is_empty() {
+            report.push_str("No solutions were generated.\n\n");
+            return;
+        }
+        report.push_str(
+            "| Solution | Agent | Phase | Model | Temperature | Score | Verified | Tokens | Answer |\n\
+             |---|---|---|---|---|---|---|---|---|\n",
+        );
+        for solution in &self.all_solutions {
+            let marker = if solution.id == self.final_solution_id {
+                " (final)"
+            } else {
+                ""
+            };
+            report.push_str(&format!(
+                "| `{}`{marker} | {} | {:?} | {} | {:.2} | {:.2} | {} | {} | {} |\n",
+                short_id(&solution.id),
+                cell(&solution.agent_id),
+                solution.phase,
+                cell(&model_of(solution)),
+                solution.temperature,
+                solution.verification_score,
+                if solution.is_verified { "yes" } else { "no" },
+                solution.token_count,
+                cell(&solution.answer),
+            ));
+        }
+        report.push('\n');
+    }
+
+    fn write_verification(&self, report: &mut String) {
+        report.push_str("## Verification\n\n");
+        let verified = self.all_solutions.iter().filter(|s| s.is_verified).count();
+        let passes: usize = self
+            .all_solutions
+            .iter()
+            .map(|s| s.verification_passes)
+            .sum();
+        let failures: usize = self
+            .all_solutions
+            .iter()
+            .map(|s| s.verification_failures)
+            .sum();
+        report.push_str(&format!("{verified} of {} solutions verified ({passes} passing and {failures} failing verdicts).\n\n",
+            self.all_solutions.len()
+        ));
+        if self.verifications.is_empty() {
+            return;
+        }
+        report.push_str(
+            "| Solution | Verifier | Result | Score | Critique |\n|---|---|---|---|---|\n",
+        );
+        for verification in &self.verifications {
+            let verifier = if verification.verifier_model.is_empty() {
+                &verification.verifying_agent_id
+            } else {
+                &verification.verifier_model
+            };
+            report.push_str(&format!(
+                "| `{}` | {} | {} | {:.2} | {} |\n",
+                short_id(&verification.solution_id),
+                cell(verifier),
+                if verification.is_correct {
+                    "pass"
+                } else {
+                    "fail"
+                },
+                verification.score,
+                cell(&verification.critique),
+            ));
+        }
+        report.push('\n');
+    }
+
+    fn write_strategies(&self, report: &mut String) {
+        report.push_str("## Strategies\n\n");
+        if self.strategies.is_empty() {
+            report.push_str("No strategies were extracted.\n\n");
+            return;
+        }
+        for strategy in &self.strategies {
+            report.push_str(&format!(
+                "- **{}** (discovered by {}, success rate {:.0}%)\n",
+                strategy.description.trim(),
+                strategy.discovered_by,
+                strategy.success_rate * 100.0
+            ));
+        }
+        report.push('\n');
+    }
+
+    fn write_cost(&self, report: &mut String) {
+        let cost = &self.cost;
+        report.push_str(&format!(
+            "## Cost\n\n${:.4} over {} calls.\n\n",
+            cost.total_usd, cost.calls
+        ));
+        if !cost.by_phase.is_empty() {
+            let mut by_phase: Vec<_> = cost
+                .by_phase
+                .iter()
+                .map(|(phase, usd)| (format!("{phase:?}"), *usd))
+                .collect();
+            write_cost_table(report, "Phase", &mut by_phase);
+        }
+        if !cost.by_provider.is_empty() {
+            let mut by_provider: Vec<_> = cost
+                .by_provider
+                .iter()
+                .map(|(provider, usd)| (provider.clone(), *usd))
+                .collect();
+            write_cost_table(report, "Provider", &mut by_provider);
+        }
+        if !cost.unpriced_models.is_empty() {
+            report.push_str(&format!(
+                "Unpriced models (counted as free): {}\n\n",
+                cost.unpriced_models.join(", ")
+            ));
+        }
+    }
+
+    fn write_timings(&self, report: &mut String) {
+        if self.phase_timings.is_empty() {
+            return;
+        }
+        report.push_str("## Timing\n\n| Phase | Duration | Calls | Tokens |\n|---|---|---|---|\n");
+        for timing in &self.phase_timings {
+            report.push_str(&format!(
+                "| {:?} | {:.1}s | {} | {} |\n",
+                timing.phase,
+                timing.duration().as_secs_f64(),
+                timing.llm_calls,
+                timing.tokens
+            ));
+        }
+        report.push('\n');
+    }
+}
+
+/// A table of dollars by `label`, most expensive first
+fn write_cost_table(report: &mut String, label: &str, rows: &mut [(String, f64)]) {
+    rows.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    report.push_str(&format!("| {label} | Cost |\n|---|---|\n"));
+    for (name, usd) in rows.iter() {
+        report.push_str(&format!("| {} | ${usd:.4} |\n", cell(name)));
+    }
+    report.push('\n');
+}
+
+/// `provider/model` of a solution, or `unknown`
+fn model_of(solution: &Solution) -> String {
+    if solution.model.is_empty() {
+        "unknown".to_string()
+    } else {
+        solution.model.clone()
+    }
+}
+
+/// First characters of `id`, enough to tell solutions apart
+fn short_id(id: &str) -> &str {
+    id.get(..SHORT_ID_LEN).unwrap_or(id)
+}
+
+/// `text` flattened onto one line, with pipes escaped, for a table cell
+fn cell(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::CostSummary;
+    use crate::provider_config::MarsPhase;
+    use crate::types::{PhaseTiming, RankedAnswer, SelectionMethod, Strategy, VerificationResult};
+    use chrono::{Duration, Utc};
+
+    fn output() -> MarsOutput {
+        let mut winner = Solution::new(
+            "agent-1".to_string(),
+            "Add them.".to_string(),
+            "4".to_string(),
+            0.3,
+            20,
+        );
+        winner.model = "openai/gpt-4o".to_string();
+        winner.is_verified = true;
+        winner.verification_passes = 2;
+        winner.verification_score = 0.9;
+        let mut runner_up = Solution::new(
+            "agent-2".to_string(),
+            "Guess.".to_string(),
+            "4 | maybe\n5".to_string(),
+            1.0,
+            15,
+        );
+        runner_up.verification_failures = 1;
+
+        let mut verdict =
+            VerificationResult::new(winner.id.clone(), true, 0.9, "verifier".to_string());
+        verdict.critique = "Correct.".to_string();
+
+        let mut cost = CostSummary {
+            total_usd: 0.0125,
+            calls: 4,
+            ..CostSummary::default()
+        };
+        cost.by_phase.insert(MarsPhase::Exploration, 0.01);
+        cost.by_phase.insert(MarsPhase::Verification, 0.0025);
+        cost.by_provider.insert("openai/gpt-4o".to_string(), 0.0125);
+
+        let alternatives = RankedAnswer::rank(&[winner.clone(), runner_up.clone()], 3);
+        let started_at = Utc::now();
+        MarsOutput {
+            answer: "4".to_string(),
+            reasoning: "Add them.".to_string(),
+            final_solution_id: winner.id.clone(),
+            all_solutions: vec![winner, runner_up],
+            verifications: vec![verdict],
+            selection_method: SelectionMethod::BestVerified,
+            iterations: 1,
+            total_tokens: 35,
+            total_prompt_tokens: 20,
+            total_completion_tokens: 15,
+            usage_estimated: false,
+            cost,
+            tags: vec!["ci".to_string()],
+            run_id: "run-1".to_string(),
+            budget_truncated: false,
+            typed_answer: None,
+            alternatives,
+            confidence: 0.75,
+            strategies: vec![Strategy {
+                id: "s1".to_string(),
+                description: "Add the numbers directly".to_string(),
+                technique: "addition".to_string(),
+                discovered_by: "agent-1".to_string(),
+                success_rate: 0.5,
+                discovered_at: started_at,
+            }],
+            phase_timings: vec![PhaseTiming {
+                phase: MarsPhase::Exploration,
+                started_at,
+                ended_at: started_at + Duration::milliseconds(1500),
+                llm_calls: 2,
+                tokens: 35,
+            }],
+            completed_at: started_at,
+        }
+    }
+
+    #[test]
+    fn test_markdown_report_sections() {
+        let output = output();
+        let report = output.to_markdown();
+
+        assert!(report.starts_with("# MARS Run Report\n\n## Answer\n\n4\n"));
+        assert!(report.contains("- **Selected by:** BestVerified"));
+        assert!(report.contains("- **Model:** openai/gpt-4o"));
+        assert!(report.contains("- **Confidence:** 0.75"));
+        assert!(report.contains("| 4 | 0.90 | 1 | 1 |\n| 4 \\| maybe 5 | 0.00 | 1 | 0 |"));
+        let final_row = format!(
+            "| `{}` (final) | agent-1 |",
+            short_id(&output.final_solution_id)
+        );
+        assert!(report.contains(&final_row), "{report}");
+        // Answers stay on one table row
+        assert!(report.contains("| 4 \\| maybe 5 |"), "{report}");
+        assert!(report.contains("1 of 2 solutions verified (2 passing and 1 failing verdicts)"));
+        assert!(report.contains("| pass | 0.90 | Correct. |"));
+        assert!(
+            report.contains(
+                "- **Add the numbers directly** (discovered by agent-1, success rate 50%)"
+            )
+        );
+        assert!(report.contains("$0.0125 over 4 calls."));
+        let exploration = report.find("| Exploration | $0.0100 |").unwrap();
+        let verification = report.find("| Verification | $0.0025 |").unwrap();
+        assert!(exploration < verification);
+        assert!(report.contains("| Exploration | 1.5s | 2 | 35 |"));
+    }
+
+    #[test]
+    fn test_markdown_report_without_optional_sections() {
+        let mut output = output();
+        output.strategies.clear();
+        output.verifications.clear();
+        output.phase_timings.clear();
+        output.alternatives.truncate(1);
+        output.cost = CostSummary::default();
+        output.budget_truncated = true;
+
+        let report = output.to_markdown();
+        assert!(report.contains("No strategies were extracted."));
+        assert!(report.contains("cut the search short"));
+        assert!(!report.contains("| Verifier |"));
+        assert!(!report.contains("## Timing"));
+        assert!(!report.contains("## Alternatives"));
+        assert!(!report.contains("| Phase | Cost |"));
+    }
+}
@@ -0,0 +1,475 @@
+//! Cache of LLM responses keyed by request.
+//!
+//! [`CachingProvider`] wraps any [`LLMProvider`] and replays the response of
+//! an identical earlier call (same model, system prompt, prompt and sampling
+//! temperature) instead of paying for it again. Entries live in a bounded
+//! in-memory LRU, can expire after a TTL, and can be persisted to a JSON file
+//! that is rewritten after every new response, so a re-run benchmark or a
+//! batch resumed after a crash starts from where it left off.
+//!
+//! Because repeated calls at the same temperature return the same response,
+//! only wrap providers whose callers vary the prompt or temperature when they
+//! want diverse samples.
+
+use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+use crate::structured::ResponseFormat;
+use crate::tools::{ToolAwareResponse, ToolSpec};
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+
+/// Entries kept unless [`CachingProvider::with_capacity`] overrides it
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// A cached response
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    response: CompletionResponse,
+    created_at: DateTime<Utc>,
+    /// Recency stamp; the entry with the lowest is evicted first
+    last_used: u64,
+}
+
+/// Provider wrapper that caches responses
+pub struct CachingProvider {
+    inner: Arc<dyn LLMProvider>,
+    entries: RwLock<HashMap<String, CacheEntry>>,
+    capacity: usize,
+    ttl: Option<Duration>,
+    path: Option<PathBuf>,
+    /// Held while writing the file so concurrent writes don't interleave
+    persist_lock: Mutex<()>,
+    /// Source of recency stamps
+    clock: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CachingProvider {
+    /// Cache `inner`'s responses in memory
+    pub fn new(inner: Arc<dyn LLMProvider>) -> Self {
+        Self {
+            inner,
+            entries: RwLock::new(HashMap::new()),
+            capacity: DEFAULT_CAPACITY,
+            ttl: None,
+            path: None,
+            persist_lock: Mutex::new(()),
+            clock: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Cache `inner`'s responses in a file at `path`, loading it if it exists
+    pub fn open(inner: Arc<dyn LLMProvider>, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let entries: HashMap<String, CacheEntry> = if path.exists() {
+            let contents = std::fs::read_to_string(&path).map_err(|e| {
+                MarsError::StorageError(format!(
+                    "Failed to read response cache {}: {e}",
+                    path.display()
+                ))
+            })?;
+            serde_json::from_str(&contents).map_err(|e| {
+                MarsError::StorageError(format!("Invalid response cache {}: {e}", path.display()))
+            })?
+        } else {
+            HashMap::new()
+        };
+
+        let clock = entries.values().map(|e| e.last_used).max().unwrap_or(0);
+        let mut cache = Self::new(inner);
+        cache.entries = RwLock::new(entries);
+        cache.clock = AtomicU64::new(clock);
+        cache.path = Some(path);
+        Ok(cache)
+    }
+
+    /// Keep at most `capacity` responses (at least 1), evicting the least recently used
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Stop replaying responses older than `ttl`
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// File the cache is persisted to, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Calls answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Calls passed through to the wrapped provider
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Number of cached responses
+    pub async fn len(&self) -> usize {
+        self.entries.read().await.len()
+    }
+
+    /// Whether the cache is empty
+    pub async fn is_empty(&self) -> bool {
+        self.entries.read().await.is_empty()
+    }
+
+    /// Hash identifying a request to the wrapped model
+    fn request_key(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: Option<f32>,
+    ) -> String {
+        // The prefix tells an empty system prompt apart from none
+        let system = system_prompt.map_or_else(String::new, |s| format!("system:{s}"));
+        let temperature = temperature.map_or_else(|| "default".to_string(), |t| t.to_string());
+        let mut hasher = Sha256::new();
+        let model_id = self.inner.model_id();
+        for part in [model_id.as_str(), &system, prompt, &temperature] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Fresh cached response for `key`, marking it recently used
+    async fn get(&self, key: &str) -> Option<CompletionResponse> {
+        let mut entries = self.entries.write().await;
+        let expired = entries.get(key).is_some_and(|entry| {
+            self.ttl.is_some_and(|ttl| {
+                (Utc::now() - entry.created_at)
+                    .to_std()
+                    .is_ok_and(|age| age > ttl)
+            })
+        });
+        if expired {
+            entries.remove(key);
+            return None;
+        }
+        let entry = entries.get_mut(key)?;
+        entry.last_used = self.clock.fetch_add(1, Ordering::Relaxed) + 1;
+        Some(entry.response.clone())
+    }
+
+    /// Cache a response, evicting the least recently used entries over capacity
+    async fn insert(&self, key: String, response: CompletionResponse) {
+        {
+            let mut entries = self.entries.write().await;
+            entries.insert(
+                key,
+                CacheEntry {
+                    response,
+                    created_at: Utc::now(),
+                    last_used: self.clock.fetch_add(1, Ordering::Relaxed) + 1,
+                },
+            );
+            while entries.len() > self.capacity {
+                let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone())
+                else {
+                    break;
+                };
+                entries.remove(&oldest);
+            }
+        }
+
+        // A failed write only costs a repeated call later
+        if let Err(e) = self.persist().await {
+            tracing::warn!("{e}");
+        }
+    }
+
+    /// Write the cache to its file (no-op for in-memory caches)
+    pub async fn persist(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let _writing = self.persist_lock.lock().await;
+
+        let contents = {
+            let entries = self.entries.read().await;
+            serde_json::to_string(&*entries).map_err(|e| {
+                MarsError::StorageError(format!("Failed to serialize response cache: {e}"))
+            })?
+        };
+
+        // Write to a sibling file first so a crash never leaves a truncated cache
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, contents).await.map_err(|e| {
+            MarsError::StorageError(format!(
+                "Failed to write response cache {}: {e}",
+                tmp_path.display()
+            ))
+        })?;
+        tokio::fs::rename(&tmp_path, path).await.map_err(|e| {
+            MarsError::StorageError(format!(
+                "Failed to write response cache {}: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Cached response for the request, or the wrapped provider's (then cached)
+    async fn cached<F>(&self, key: String, call: F) -> Result<CompletionResponse>
+    where
+        F: Future<Output = Result<CompletionResponse>>,
+    {
+        if let Some(response) = self.get(&key).await {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(response);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let response = call.await?;
+        self.insert(key, response.clone()).await;
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for CachingProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        let key = self.request_key(prompt, system_prompt, None);
+        self.cached(key, self.inner.complete(prompt, system_prompt))
+            .await
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        let key = self.request_key(prompt, system_prompt, Some(temperature));
+        let call = self
+            .inner
+            .complete_with_temperature(prompt, system_prompt, temperature);
+        self.cached(key, call).await
+    }
+
+    /// Passed through uncached: callers asking for several completions want
+    /// distinct samples, not one cached response repeated
+    async fn complete_n(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        n: usize,
+        temperature: Option<f32>,
+    ) -> Result<Vec<CompletionResponse>> {
+        self.inner
+            .complete_n(prompt, system_prompt, n, temperature)
+            .await
+    }
+
+    /// Streams share cache entries with [`LLMProvider::complete`]; a cached
+    /// response is replayed as a single chunk, and a missed one is read in
+    /// full before it is cached and replayed
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let key = self.request_key(prompt, system_prompt, None);
+        if let Some(response) = self.get(&key).await {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(ModelStream::new(response.text));
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let chunks = self
+            .inner
+            .stream(prompt, system_prompt)
+            .await?
+            .collect_chunks()
+            .await?;
+        self.insert(key, CompletionResponse::new(chunks.concat()))
+            .await;
+        Ok(ModelStream::from_chunks(chunks))
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed(texts).await
+    }
+
+    /// Keyed as a completion of the prompt with the format's instructions
+    /// appended, so each format gets its own entry
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        let key = self.request_key(&format.prompt(prompt), system_prompt, None);
+        let call = self
+            .inner
+            .complete_structured(prompt, system_prompt, format);
+        self.cached(key, call).await
+    }
+
+    /// Tool calls are passed through uncached; callers act on them
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        self.inner
+            .complete_with_tools(prompt, system_prompt, tools)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Numbers its responses so repeated calls are distinguishable
+    #[derive(Default)]
+    struct Counter {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLMProvider for Counter {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(CompletionResponse::new(format!("{prompt} #{n}")).with_usage(1, 2))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            Ok(ModelStream::new(
+                self.complete(prompt, system_prompt).await?.text,
+            ))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "counter"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_identical_calls_hit_cache() {
+        let inner = Arc::new(Counter::default());
+        let cache = CachingProvider::new(inner.clone());
+
+        assert_eq!(cache.complete("q", Some("s")).await.unwrap().text, "q #1");
+        assert_eq!(cache.complete("q", Some("s")).await.unwrap().text, "q #1");
+        assert_eq!(cache.complete("q", None).await.unwrap().text, "q #2");
+        assert_eq!(
+            cache
+                .complete_with_temperature("q", Some("s"), 0.7)
+                .await
+                .unwrap()
+                .text,
+            "q #3"
+        );
+        assert_eq!(
+            cache
+                .complete_with_temperature("q", Some("s"), 0.9)
+                .await
+                .unwrap()
+                .text,
+            "q #4"
+        );
+        assert_eq!(
+            cache
+                .complete_with_temperature("q", Some("s"), 0.7)
+                .await
+                .unwrap()
+                .text,
+            "q #3"
+        );
+
+        // Streams replay completions of the same request
+        let mut stream = cache.stream("q", None).await.unwrap();
+        assert_eq!(stream.next_chunk().await.unwrap().unwrap(), "q #2");
+
+        assert_eq!(cache.hits(), 3);
+        assert_eq!(cache.misses(), 4);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() {
+        let cache = CachingProvider::new(Arc::new(Counter::default())).with_capacity(2);
+        cache.complete("a", None).await.unwrap();
+        cache.complete("b", None).await.unwrap();
+        // Touch "a" so "b" is the oldest
+        cache.complete("a", None).await.unwrap();
+        cache.complete("c", None).await.unwrap();
+
+        assert_eq!(cache.len().await, 2);
+        assert_eq!(cache.complete("a", None).await.unwrap().text, "a #1");
+        assert_eq!(cache.complete("b", None).await.unwrap().text, "b #4");
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_refetched() {
+        let cache =
+            CachingProvider::new(Arc::new(Counter::default())).with_ttl(Duration::from_millis(20));
+        assert_eq!(cache.complete("q", None).await.unwrap().text, "q #1");
+        assert_eq!(cache.complete("q", None).await.unwrap().text, "q #1");
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(cache.complete("q", None).await.unwrap().text, "q #2");
+    }
+
+    #[tokio::test]
+    async fn test_persists_across_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("responses.json");
+
+        let first_run = CachingProvider::open(Arc::new(Counter::default()), &path).unwrap();
+        assert!(first_run.is_empty().await);
+        first_run.complete("q", None).await.unwrap();
+
+        let inner = Arc::new(Counter::default());
+        let second_run = CachingProvider::open(inner.clone(), &path).unwrap();
+        let replayed = second_run.complete("q", None).await.unwrap();
+        assert_eq!(replayed.text, "q #1");
+        // Usage reported by the provider survives the round trip
+        assert_eq!(replayed.prompt_tokens, Some(1));
+        assert_eq!(replayed.completion_tokens, Some(2));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 0);
+        assert_eq!(second_run.path(), Some(path.as_path()));
+    }
+}
@@ -0,0 +1,303 @@
+//! Retries with exponential backoff for provider calls.
+//!
+//! [`RetryingProvider`] wraps any [`LLMProvider`] and retries calls that fail
+//! with a transient error (see [`MarsError::is_transient`]): rate limits,
+//! 5xx responses, timeouts and dropped connections. Delays double from an
+//! initial backoff up to a cap, with jitter so that agents failing together do
+//! not retry together; a `Retry-After` delay sent by the provider takes
+//! precedence. Wrap a [`RateLimitedProvider`](crate::rate_limit::RateLimitedProvider)
+//! to have retries wait their turn as well.
+
+use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+use crate::structured::ResponseFormat;
+use crate::tools::{ToolAwareResponse, ToolSpec};
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use rand::Rng;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Retries after the first attempt, unless overridden
+const DEFAULT_MAX_RETRIES: usize = 3;
+
+/// Delay before the first retry, unless overridden
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest computed delay between attempts, unless overridden
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Provider wrapper that retries transient failures
+pub struct RetryingProvider {
+    inner: Arc<dyn LLMProvider>,
+    max_retries: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RetryingProvider {
+    /// Wrap `inner` with the default policy: 3 retries, backing off from 500ms up to 30s
+    pub fn new(inner: Arc<dyn LLMProvider>) -> Self {
+        Self {
+            inner,
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+
+    /// Retry at most `max_retries` times after the first attempt
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Wait about `backoff` before the first retry
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Never back off longer than `backoff` (a `Retry-After` delay may still exceed it)
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Delay before retry number `retry` (starting at 0) after `error`
+    fn delay(&self, retry: usize, error: &MarsError) -> Duration {
+        if let Some(retry_after) = error.retry_after() {
+            return retry_after;
+        }
+        let exponent = u32::try_from(retry).unwrap_or(u32::MAX);
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(self.max_backoff);
+        // Equal jitter: somewhere between half and all of the backoff
+        backoff.mul_f64(rand::rng().random_range(0.5..=1.0))
+    }
+
+    /// Run `call` until it succeeds, fails permanently or runs out of retries
+    async fn retry<T, F, Fut>(&self, mut call: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut retry = 0;
+        loop {
+            match call().await {
+                Err(e) if e.is_transient() && retry < self.max_retries => {
+                    let delay = self.delay(retry, &e);
+                    retry += 1;
+                    tracing::warn!(
+                        "{} failed ({e}); retry {retry}/{} in {delay:?}",
+                        self.inner.model_id(),
+                        self.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for RetryingProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        self.retry(|| self.inner.complete(prompt, system_prompt))
+            .await
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        self.retry(|| {
+            self.inner
+                .complete_with_temperature(prompt, system_prompt, temperature)
+        })
+        .await
+    }
+
+    async fn complete_n(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        n: usize,
+        temperature: Option<f32>,
+    ) -> Result<Vec<CompletionResponse>> {
+        self.retry(|| self.inner.complete_n(prompt, system_prompt, n, temperature))
+            .await
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        self.retry(|| self.inner.stream(prompt, system_prompt))
+            .await
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.retry(|| self.inner.embed(texts)).await
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        self.retry(|| self.inner.complete_with_tools(prompt, system_prompt, tools))
+            .await
+    }
+
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        self.retry(|| {
+            self.inner
+                .complete_structured(prompt, system_prompt, format)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::time::Instant;
+
+    /// Fails with a fixed error until it has been called `failures` times
+    struct Flaky {
+        failures: usize,
+        calls: AtomicUsize,
+        error: fn() -> MarsError,
+    }
+
+    impl Flaky {
+        fn new(failures: usize, error: fn() -> MarsError) -> Arc<Self> {
+            Arc::new(Self {
+                failures,
+                calls: AtomicUsize::new(0),
+                error,
+            })
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for Flaky {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                Err((self.error)())
+            } else {
+                Ok("4".into())
+            }
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            Ok(ModelStream::new(
+                self.complete(prompt, system_prompt).await?.text,
+            ))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "flaky"
+        }
+    }
+
+    fn overloaded() -> MarsError {
+        MarsError::TransientError {
+            message: "503 overloaded".to_string(),
+            retry_after: None,
+        }
+    }
+
+    fn retrying(inner: Arc<Flaky>) -> RetryingProvider {
+        RetryingProvider::new(inner).with_initial_backoff(Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors_until_success() {
+        let inner = Flaky::new(2, overloaded);
+        let answer = retrying(inner.clone()).complete("q", None).await.unwrap();
+        assert_eq!(answer.text, "4");
+        assert_eq!(inner.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let inner = Flaky::new(10, overloaded);
+        let err = retrying(inner.clone())
+            .with_max_retries(2)
+            .complete("q", None)
+            .await
+            .unwrap_err();
+        assert!(err.is_transient());
+        assert_eq!(inner.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_errors_are_not_retried() {
+        let inner = Flaky::new(1, || MarsError::ClientError("bad API key".to_string()));
+        let err = retrying(inner.clone())
+            .complete("q", None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MarsError::ClientError(_)));
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_honors_retry_after() {
+        let inner = Flaky::new(1, || MarsError::TransientError {
+            message: "429".to_string(),
+            retry_after: Some(Duration::from_millis(80)),
+        });
+        let start = Instant::now();
+        retrying(inner).complete("q", None).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let provider = RetryingProvider::new(Flaky::new(0, overloaded))
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_millis(300));
+        let error = overloaded();
+        for (retry, full) in [(0, 100), (1, 200), (2, 300), (40, 300)] {
+            let delay = provider.delay(retry, &error);
+            assert!(delay >= Duration::from_millis(full / 2), "retry {retry}");
+            assert!(delay <= Duration::from_millis(full), "retry {retry}");
+        }
+    }
+}
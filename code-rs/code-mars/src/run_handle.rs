@@ -0,0 +1,300 @@
+//! Background runs for interactive front ends.
+//!
+//! [`MarsCoordinator::start`] spawns a run and returns a [`RunHandle`] for a
+//! front end such as a chat view to drive. The handle pushes a
+//! [`RunUpdate`] for each event as it is emitted, so phase progress can be
+//! shown without polling. Events that add a solution to the workspace
+//! (`SolutionGenerated`, `SolutionsAggregated`, `SolutionImproved`) carry
+//! the whole [`Solution`], so each answer can be rendered as it arrives.
+//! The last update is always [`RunUpdate::Finished`] with the run's outcome.
+//!
+//! [`RunHandle::cancel`], or a [`RunCanceller`] handed to a key binding,
+//! stops the run at once, dropping any provider calls in flight; it then
+//! finishes with [`MarsError::Cancelled`]. Dropping the handle cancels the
+//! run too.
+
+use crate::coordinator::{MarsCoordinator, RunOptions};
+use crate::provider_config::MarsPhase;
+use crate::types::{MarsEventRecord, MarsOutput, Solution};
+use crate::workspace_store::WorkspaceStore;
+use crate::{MarsError, Result};
+use std::sync::Arc;
+use tokio::sync::{Notify, mpsc};
+
+/// Updates buffered before the run waits for the front end to catch up
+const UPDATE_CAPACITY: usize = 64;
+
+/// Something that happened in a background run
+#[derive(Debug)]
+pub enum RunUpdate {
+    /// The run emitted an event
+    Event {
+        /// The event, stamped with the run ID and its sequence number
+        record: MarsEventRecord,
+        /// The solution the event added, for `SolutionGenerated`,
+        /// `SolutionsAggregated` and `SolutionImproved`; `None` if it was
+        /// merged into a duplicate
+        solution: Option<Solution>,
+    },
+    /// The run ended; always the last update
+    Finished(Result<MarsOutput>),
+}
+
+impl RunUpdate {
+    /// Phase this update starts, if it starts one
+    pub fn phase_started(&self) -> Option<MarsPhase> {
+        match self {
+            Self::Event { record, .. } => record.event.phase_started(),
+            Self::Finished(_) => None,
+        }
+    }
+}
+
+/// Cancels a background run from anywhere, e.g. a key handler
+#[derive(Clone, Debug, Default)]
+pub struct RunCanceller {
+    notify: Arc<Notify>,
+}
+
+impl RunCanceller {
+    /// Stop the run; it finishes with [`MarsError::Cancelled`] unless it
+    /// has already finished
+    pub fn cancel(&self) {
+        self.notify.notify_one();
+    }
+}
+
+/// A run in progress in the background
+pub struct RunHandle {
+    updates: mpsc::Receiver<RunUpdate>,
+    canceller: RunCanceller,
+}
+
+impl RunHandle {
+    /// Run `query` on `coordinator` in a new task
+    pub(crate) fn spawn(coordinator: MarsCoordinator, query: String, options: RunOptions) -> Self {
+        let (tx, updates) = mpsc::channel(UPDATE_CAPACITY);
+        let canceller = RunCanceller::default();
+        let cancelled = Arc::clone(&canceller.notify);
+        tokio::spawn(async move {
+            let mut coordinator = coordinator;
+            let events = coordinator.subscribe_events(UPDATE_CAPACITY);
+            let store = Arc::clone(coordinator.workspace().store());
+            let forwarder = tokio::spawn(Self::forward_events(events, store, tx.clone()));
+            let output = tokio::select! {
+                output = coordinator.run_with_options(&query, options) => output,
+                () = cancelled.notified() => Err(MarsError::Cancelled),
+            };
+            // Closes the event stream, so the forwarder finishes
+            drop(coordinator);
+            let _result = forwarder.await;
+            let _result = tx.send(RunUpdate::Finished(output)).await;
+        });
+        Self { updates, canceller }
+    }
+
+    /// Pass each event on as an update, with the solution it added
+    async fn forward_events(
+        mut events: mpsc::Receiver<MarsEventRecord>,
+        store: Arc<dyn WorkspaceStore>,
+        tx: mpsc::Sender<RunUpdate>,
+    ) {
+        while let Some(record) = events.recv().await {
+            let solution = match record.event.new_solution_id() {
+                Some(id) => store.get(id).await.unwrap_or_else(|e| {
+                    tracing::warn!("Failed to read solution {id} for the run's update: {e}");
+                    None
+                }),
+                None => None,
+            };
+            // Keep draining after the front end has gone, so the run is not
+            // held up
+            let _result = tx.send(RunUpdate::Event { record, solution }).await;
+        }
+    }
+
+    /// The next update, or `None` after [`RunUpdate::Finished`]
+    pub async fn next_update(&mut self) -> Option<RunUpdate> {
+        self.updates.recv().await
+    }
+
+    /// Stop the run
+    pub fn cancel(&self) {
+        self.canceller.cancel();
+    }
+
+    /// A canceller for the run that can be moved elsewhere
+    pub fn canceller(&self) -> RunCanceller {
+        self.canceller.clone()
+    }
+
+    /// Wait for the run to end, skipping the remaining events
+    pub async fn finish(mut self) -> Result<MarsOutput> {
+        while let Some(update) = self.next_update().await {
+            if let RunUpdate::Finished(output) = update {
+                return output;
+            }
+        }
+        Err(MarsError::CoordinatorError(
+            "Run task ended without an outcome".to_string(),
+        ))
+    }
+}
+
+impl Drop for RunHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MarsConfig;
+    use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+    use crate::test_support::Four;
+    use crate::types::MarsEvent;
+    use async_trait::async_trait;
+
+    /// Gives a different answer to every prompt, so no solution is merged
+    /// into a duplicate
+    #[derive(Default)]
+    struct Numbered(std::sync::atomic::AtomicUsize);
+
+    #[async_trait]
+    impl LLMProvider for Numbered {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            let n = self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(CompletionResponse::new(format!(
+                "Attempt {n}: the answer is 4"
+            )))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "numbered"
+        }
+    }
+
+    /// Never answers
+    struct Silent;
+
+    #[async_trait]
+    impl LLMProvider for Silent {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            std::future::pending().await
+        }
+
+        async fn stream(&self, _prompt: &str, _system_prompt: Option<&str>) -> Result<ModelStream> {
+            std::future::pending().await
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "silent"
+        }
+    }
+
+    fn coordinator(provider: Arc<dyn LLMProvider>) -> MarsCoordinator {
+        let config = MarsConfig::default()
+            .with_num_agents(2)
+            .with_max_iterations(1);
+        MarsCoordinator::with_provider(config, provider)
+    }
+
+    #[tokio::test]
+    async fn test_updates_carry_phases_and_solutions() {
+        let mut run = coordinator(Arc::new(Four)).start("What is 2+2?", RunOptions::new());
+
+        let mut phases = Vec::new();
+        let mut solutions = Vec::new();
+        let output = loop {
+            let update = run.next_update().await.unwrap();
+            phases.extend(update.phase_started());
+            match update {
+                RunUpdate::Event {
+                    record,
+                    solution: Some(solution),
+                } => {
+                    if let MarsEvent::SolutionGenerated { agent_id, .. } = record.event {
+                        assert_eq!(solution.agent_id, agent_id);
+                        solutions.push(solution);
+                    }
+                }
+                RunUpdate::Event { .. } => {}
+                RunUpdate::Finished(output) => break output.unwrap(),
+            }
+        };
+        assert!(run.next_update().await.is_none());
+
+        assert_eq!(phases.first(), Some(&MarsPhase::Exploration));
+        assert_eq!(solutions.len(), 2);
+        assert!(solutions[0].answer.contains('4'));
+        assert!(output.answer.contains('4'));
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_solutions_are_pushed() {
+        let config = MarsConfig::default()
+            .with_num_agents(2)
+            .with_max_iterations(1)
+            .with_aggregation(true);
+        let coordinator = MarsCoordinator::with_provider(config, Arc::new(Numbered::default()));
+        let mut run = coordinator.start("What is 2+2?", RunOptions::new());
+
+        let mut aggregated = Vec::new();
+        while let Some(update) = run.next_update().await {
+            match update {
+                RunUpdate::Event { record, solution } => {
+                    if let MarsEvent::SolutionsAggregated { result_solution_id } = record.event {
+                        let solution = solution.expect("aggregated solution is pushed");
+                        assert_eq!(solution.id, result_solution_id);
+                        aggregated.push(solution);
+                    }
+                }
+                RunUpdate::Finished(output) => {
+                    output.unwrap();
+                }
+            }
+        }
+
+        assert!(!aggregated.is_empty());
+        assert!(
+            aggregated
+                .iter()
+                .all(|s| s.phase == crate::types::GenerationPhase::Aggregated)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_the_run() {
+        let mut run = coordinator(Arc::new(Silent)).start("What is 2+2?", RunOptions::new());
+        let first = run.next_update().await.unwrap();
+        assert_eq!(first.phase_started(), Some(MarsPhase::Exploration));
+
+        run.canceller().cancel();
+        let outcome = tokio::time::timeout(std::time::Duration::from_secs(5), run.finish())
+            .await
+            .unwrap();
+        assert!(matches!(outcome, Err(MarsError::Cancelled)));
+    }
+}
@@ -0,0 +1,558 @@
+//! OpenAI-compatible HTTP server, behind the `server` feature.
+//!
+//! [`MarsServer`] answers `POST /v1/chat/completions` with a full MARS run
+//! per request, so applications written against the OpenAI API get
+//! multi-agent answers by changing only their base URL. The conversation
+//! becomes the run's query: system messages first, then the single user
+//! message, or a transcript of the turns when there are several.
+//!
+//! With `"stream": true` the response is a server-sent event stream. While
+//! the run is in progress, each phase is sent as an SSE comment, which
+//! clients ignore but which keeps proxies from timing out the connection;
+//! the synthesized answer then streams as `chat.completion.chunk` deltas,
+//! ending with `data: [DONE]`. `GET /v1/models` lists the one model served.
+
+use crate::config::MarsConfig;
+use crate::coordinator::MarsCoordinator;
+use crate::model_router::LLMProvider;
+use crate::provider_config::MarsPhase;
+use crate::types::{MarsEvent, MarsOutput};
+use crate::{MarsError, Result};
+use bytes::Bytes;
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full, Limited, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// Largest request body accepted
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Model name served unless [`MarsServer::with_model_name`] sets another
+const DEFAULT_MODEL_NAME: &str = "mars";
+
+pub(crate) type Body = UnsyncBoxBody<Bytes, Infallible>;
+
+/// Serves MARS runs over the OpenAI chat completions API
+pub struct MarsServer {
+    config: MarsConfig,
+    provider: Arc<dyn LLMProvider>,
+    model_name: String,
+    /// Bearer token requests must carry, if set
+    api_key: Option<String>,
+}
+
+impl MarsServer {
+    /// Answer each request with a run of `config` on `provider`
+    pub fn new(config: MarsConfig, provider: Arc<dyn LLMProvider>) -> Self {
+        Self {
+            config,
+            provider,
+            model_name: DEFAULT_MODEL_NAME.to_string(),
+            api_key: None,
+        }
+    }
+
+    /// Name the served model in `/v1/models` (requests may name any model)
+    pub fn with_model_name(mut self, name: impl Into<String>) -> Self {
+        self.model_name = name.into();
+        self
+    }
+
+    /// Reject requests without `Authorization: Bearer <key>`
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    /// Serve connections from `listener` until the task is dropped
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        let server = Arc::new(self);
+        serve_connections(listener, move |request| {
+            let server = Arc::clone(&server);
+            async move { server.handle(request).await }
+        })
+        .await
+    }
+
+    async fn handle(self: Arc<Self>, request: Request<Incoming>) -> Response<Body> {
+        if !self.authorized(&request) {
+            return error_response(
+                StatusCode::UNAUTHORIZED,
+                "invalid_api_key",
+                "Missing or invalid API key",
+            );
+        }
+        match (request.method(), request.uri().path()) {
+            (&Method::GET, "/v1/models") => json_response(
+                StatusCode::OK,
+                &json!({
+                    "object": "list",
+                    "data": [{"id": self.model_name, "object": "model", "owned_by": "code-mars"}],
+                }),
+            ),
+            (&Method::POST, "/v1/chat/completions") => self.chat_completions(request).await,
+            _ => error_response(
+                StatusCode::NOT_FOUND,
+                "not_found",
+                &format!("No route for {} {}", request.method(), request.uri().path()),
+            ),
+        }
+    }
+
+    fn authorized(&self, request: &Request<Incoming>) -> bool {
+        let Some(key) = &self.api_key else {
+            return true;
+        };
+        request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| token == key)
+    }
+
+    async fn chat_completions(self: Arc<Self>, request: Request<Incoming>) -> Response<Body> {
+        let body = match read_body(request.into_body()).await {
+            Ok(body) => body,
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, "invalid_request_error", &e),
+        };
+        let request: ChatCompletionRequest = match serde_json::from_slice(&body) {
+            Ok(request) => request,
+            Err(e) => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_request_error",
+                    &format!("Invalid chat completion request: {e}"),
+                );
+            }
+        };
+        let Some(query) = query_from_messages(&request.messages) else {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "invalid_request_error",
+                "messages must include a user message",
+            );
+        };
+        let completion = Completion {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            created: chrono::Utc::now().timestamp(),
+            model: request.model.unwrap_or_else(|| self.model_name.clone()),
+        };
+        tracing::info!(id = %completion.id, stream = request.stream, "Chat completion request");
+
+        if request.stream {
+            return self.stream_completion(query, completion);
+        }
+        let mut coordinator = self.coordinator();
+        match coordinator.run(&query).await {
+            Ok(output) => json_response(StatusCode::OK, &completion.response(&output)),
+            Err(e) => run_error_response(&e),
+        }
+    }
+
+    /// Respond with an event stream, running MARS in the background
+    fn stream_completion(&self, query: String, completion: Completion) -> Response<Body> {
+        let (tx, rx) = mpsc::channel::<Bytes>(64);
+        let mut coordinator = self.coordinator();
+        let mut events = coordinator.subscribe_events(64);
+        tokio::spawn(async move {
+            let progress_tx = tx.clone();
+            let progress = tokio::spawn(async move {
+                while let Some(record) = events.recv().await {
+                    if let Some(phase) = phase_started(&record.event) {
+                        // A closed stream ends the sends, not the run
+                        let _result = progress_tx
+                            .send(Bytes::from(format!(": {phase}\n\n")))
+                            .await;
+                    }
+                }
+            });
+            let output = coordinator.run(&query).await;
+            drop(coordinator);
+            let _result = progress.await;
+
+            let send = |value: Value| tx.send(Bytes::from(format!("data: {value}\n\n")));
+            match output {
+                Ok(output) => {
+                    let _result = send(completion.chunk(json!({"role": "assistant"}), None)).await;
+                    for piece in output.answer.trim().split_inclusive(char::is_whitespace) {
+                        let _result = send(completion.chunk(json!({"content": piece}), None)).await;
+                    }
+                    let _result = send(completion.chunk(json!({}), Some("stop"))).await;
+                }
+                Err(e) => {
+                    let _result = send(error_body("server_error", &e.to_string())).await;
+                }
+            }
+            let _result = tx.send(Bytes::from_static(b"data: [DONE]\n\n")).await;
+        });
+        event_stream_response(rx)
+    }
+
+    fn coordinator(&self) -> MarsCoordinator {
+        MarsCoordinator::with_provider(self.config.clone(), Arc::clone(&self.provider))
+    }
+}
+
+/// The fields of an OpenAI chat completion request MARS uses
+#[derive(Debug, Deserialize)]
+struct ChatCompletionRequest {
+    #[serde(default)]
+    model: Option<String>,
+    messages: Vec<ChatMessage>,
+    #[serde(default)]
+    stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    role: String,
+    #[serde(default)]
+    content: Option<MessageContent>,
+}
+
+/// Plain text, or an array of content parts of which the text ones count
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentPart {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+impl ChatMessage {
+    fn text(&self) -> String {
+        match &self.content {
+            Some(MessageContent::Text(text)) => text.clone(),
+            Some(MessageContent::Parts(parts)) => parts
+                .iter()
+                .filter_map(|part| part.text.as_deref())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => String::new(),
+        }
+    }
+}
+
+/// The query a run answers for `messages`, if they include a user message
+fn query_from_messages(messages: &[ChatMessage]) -> Option<String> {
+    if !messages.iter().any(|m| m.role == "user") {
+        return None;
+    }
+    let mut query = String::new();
+    for message in messages
+        .iter()
+        .filter(|m| m.role == "system" || m.role == "developer")
+    {
+        query.push_str(&format!("{}\n\n", message.text()));
+    }
+    let turns: Vec<&ChatMessage> = messages
+        .iter()
+        .filter(|m| m.role != "system" && m.role != "developer")
+        .collect();
+    if let [only] = turns.as_slice() {
+        query.push_str(&only.text());
+        return Some(query);
+    }
+    query.push_str("Conversation so far:\n\n");
+    for turn in turns {
+        let role = match turn.role.as_str() {
+            "user" => "User",
+            "assistant" => "Assistant",
+            other => other,
+        };
+        query.push_str(&format!("{role}: {}\n\n", turn.text()));
+    }
+    query.push_str("Reply to the last user message.");
+    Some(query)
+}
+
+/// Name of the phase `event` starts, if it starts one
+pub(crate) fn phase_started(event: &MarsEvent) -> Option<&'static str> {
+    Some(match event.phase_started()? {
+        MarsPhase::Exploration => "exploration",
+        MarsPhase::Aggregation => "aggregation",
+        MarsPhase::StrategyExtraction => "strategy extraction",
+        MarsPhase::Verification => "verification",
+        MarsPhase::Improvement => "improvement",
+        MarsPhase::Synthesis => "synthesis",
+    })
+}
+
+/// Identity shared by a completion and all of its chunks
+struct Completion {
+    id: String,
+    created: i64,
+    model: String,
+}
+
+impl Completion {
+    fn response(&self, output: &MarsOutput) -> Value {
+        json!({
+            "id": self.id,
+            "object": "chat.completion",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": output.answer.trim()},
+                "finish_reason": "stop",
+            }],
+            "usage": {
+                "prompt_tokens": output.total_prompt_tokens,
+                "completion_tokens": output.total_completion_tokens,
+                "total_tokens": output.total_tokens,
+            },
+        })
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Option<&str>) -> Value {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+        })
+    }
+}
+
+/// Serve HTTP/1 connections from `listener`, answering each request with
+/// `handler`, until the task is dropped
+pub(crate) async fn serve_connections<H, F>(listener: TcpListener, handler: H) -> Result<()>
+where
+    H: Fn(Request<Incoming>) -> F + Clone + Send + 'static,
+    F: Future<Output = Response<Body>> + Send + 'static,
+{
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // Usually out of file descriptors; later accepts may work
+                tracing::warn!("Failed to accept a connection: {e}");
+                continue;
+            }
+        };
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let response = handler(request);
+                async move { Ok::<_, Infallible>(response.await) }
+            });
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::debug!(%peer, "Connection closed: {e}");
+            }
+        });
+    }
+}
+
+/// The whole of a request body, up to [`MAX_BODY_BYTES`]
+pub(crate) async fn read_body(body: Incoming) -> std::result::Result<Bytes, String> {
+    Limited::new(body, MAX_BODY_BYTES)
+        .collect()
+        .await
+        .map(http_body_util::Collected::to_bytes)
+        .map_err(|e| e.to_string())
+}
+
+/// A server-sent event stream of what arrives on `rx`, until it closes
+pub(crate) fn event_stream_response(rx: mpsc::Receiver<Bytes>) -> Response<Body> {
+    let frames = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|bytes| (Ok::<_, Infallible>(Frame::data(bytes)), rx))
+    });
+    let mut response = Response::new(StreamBody::new(frames).boxed_unsync());
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+pub(crate) fn json_response(status: StatusCode, body: &Value) -> Response<Body> {
+    let mut response = Response::new(Full::new(Bytes::from(body.to_string())).boxed_unsync());
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+fn error_body(kind: &str, message: &str) -> Value {
+    json!({"error": {"message": message, "type": kind, "code": Value::Null}})
+}
+
+fn error_response(status: StatusCode, kind: &str, message: &str) -> Response<Body> {
+    json_response(status, &error_body(kind, message))
+}
+
+/// Bad configurations are the server's fault; provider failures are a bad
+/// gateway
+fn run_error_response(error: &MarsError) -> Response<Body> {
+    let status = match error {
+        MarsError::InvalidConfiguration(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_GATEWAY,
+    };
+    error_response(status, "server_error", &error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Four;
+
+    async fn start(server: MarsServer) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(server.serve(listener));
+        format!("http://{addr}")
+    }
+
+    fn server() -> MarsServer {
+        MarsServer::new(MarsConfig::default().with_max_iterations(1), Arc::new(Four))
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion_runs_mars() {
+        let base = start(server()).await;
+        let response = reqwest::Client::new()
+            .post(format!("{base}/v1/chat/completions"))
+            .json(&json!({
+                "model": "gpt-4o",
+                "messages": [
+                    {"role": "system", "content": "Be brief."},
+                    {"role": "user", "content": "What is 2+2?"},
+                ],
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["object"], "chat.completion");
+        assert_eq!(body["model"], "gpt-4o");
+        assert_eq!(body["choices"][0]["message"]["role"], "assistant");
+        assert_eq!(body["choices"][0]["finish_reason"], "stop");
+        assert!(
+            !body["choices"][0]["message"]["content"]
+                .as_str()
+                .unwrap()
+                .is_empty()
+        );
+        assert!(body["usage"]["total_tokens"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_streams_progress_then_answer_chunks() {
+        let base = start(server()).await;
+        let response = reqwest::Client::new()
+            .post(format!("{base}/v1/chat/completions"))
+            .json(&json!({
+                "stream": true,
+                "messages": [{"role": "user", "content": "What is 2+2?"}],
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let text = response.text().await.unwrap();
+
+        assert!(text.starts_with(": exploration\n\n"));
+        assert!(text.ends_with("data: [DONE]\n\n"));
+        let chunks: Vec<Value> = text
+            .split("\n\n")
+            .filter_map(|event| event.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        assert!(
+            chunks
+                .iter()
+                .all(|c| c["object"] == "chat.completion.chunk")
+        );
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(
+            chunks[chunks.len() - 1]["choices"][0]["finish_reason"],
+            "stop"
+        );
+        let content: String = chunks
+            .iter()
+            .filter_map(|c| c["choices"][0]["delta"]["content"].as_str())
+            .collect();
+        assert!(!content.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_requests_and_keys() {
+        let base = start(server().with_api_key("secret").with_model_name("mars-pro")).await;
+        let client = reqwest::Client::new();
+
+        let unauthorized = client
+            .get(format!("{base}/v1/models"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unauthorized.status(), 401);
+
+        let models: Value = client
+            .get(format!("{base}/v1/models"))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(models["data"][0]["id"], "mars-pro");
+
+        let no_user = client
+            .post(format!("{base}/v1/chat/completions"))
+            .bearer_auth("secret")
+            .json(&json!({"messages": [{"role": "system", "content": "hi"}]}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(no_user.status(), 400);
+        let body: Value = no_user.json().await.unwrap();
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+    }
+
+    #[test]
+    fn test_query_from_messages() {
+        let messages: Vec<ChatMessage> = serde_json::from_value(json!([
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": [{"type": "text", "text": "What is 2+2?"}]},
+            {"role": "assistant", "content": "4"},
+            {"role": "user", "content": "And times 3?"},
+        ]))
+        .unwrap();
+        assert_eq!(
+            query_from_messages(&messages).unwrap(),
+            "Be brief.\n\nConversation so far:\n\nUser: What is 2+2?\n\nAssistant: 4\n\n\
+             User: And times 3?\n\nReply to the last user message."
+        );
+        assert_eq!(
+            query_from_messages(&messages[1..2]).unwrap(),
+            "What is 2+2?"
+        );
+        assert!(query_from_messages(&messages[..1]).is_none());
+    }
+}
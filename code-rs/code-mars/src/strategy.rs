@@ -1,13 +1,29 @@
-use crate::Result;
 /// Strategy network for sharing successful reasoning approaches across agents.
+use crate::LLMProvider;
+use crate::Result;
+use crate::aggregator::cosine_similarity;
+use crate::mcts::escape_dot;
+use crate::prompts;
 use crate::types::Strategy;
 use chrono::Utc;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use uuid::Uuid;
 
+/// Default word overlap above which a new strategy counts as a duplicate
+pub const DEFAULT_DEDUP_THRESHOLD: f32 = 0.8;
+
 /// Network of strategies shared across agents
 pub struct StrategyNetwork {
     strategies: HashMap<String, Strategy>,
+    /// Embedding of each strategy by ID, filled lazily when needed
+    embeddings: HashMap<String, Vec<f32>>,
+    /// Word overlap at which `register_strategy` reuses an existing strategy
+    dedup_threshold: f32,
+    /// Rediscoveries, merges, and usages recorded during the run
+    edges: Vec<StrategyEdge>,
+    /// Strategies merged into others, kept so the graph can still show them
+    retired: HashMap<String, Strategy>,
 }
 
 impl StrategyNetwork {
@@ -15,16 +31,52 @@ impl StrategyNetwork {
     pub fn new() -> Self {
         Self {
             strategies: HashMap::new(),
+            embeddings: HashMap::new(),
+            dedup_threshold: DEFAULT_DEDUP_THRESHOLD,
+            edges: Vec::new(),
+            retired: HashMap::new(),
+        }
+    }
+
+    /// Treat new strategies whose descriptions overlap an existing one by at
+    /// least `threshold` (Jaccard over words) as duplicates; above 1.0 disables
+    pub fn with_dedup_threshold(mut self, threshold: f32) -> Self {
+        self.dedup_threshold = threshold;
+        self
+    }
+
+    /// Create a network seeded with previously extracted strategies
+    pub fn from_strategies(strategies: Vec<Strategy>) -> Self {
+        let mut network = Self::new();
+        network.load_strategies(strategies);
+        network
+    }
+
+    /// Add previously extracted strategies, replacing any with the same ID
+    pub fn load_strategies(&mut self, strategies: Vec<Strategy>) {
+        for strategy in strategies {
+            self.embeddings.remove(&strategy.id);
+            self.strategies.insert(strategy.id.clone(), strategy);
         }
     }
 
     /// Register a new strategy discovered by an agent
+    ///
+    /// Returns the strategy's ID. A near-duplicate of an existing strategy is
+    /// not added; the existing strategy's ID is returned instead.
     pub fn register_strategy(
         &mut self,
         agent_id: String,
         description: String,
         technique: String,
     ) -> String {
+        if let Some(existing) = self.find_duplicate(&description) {
+            if self.strategies[&existing].discovered_by != agent_id {
+                self.add_edge(agent_id, existing.clone(), StrategyEdgeKind::Discovered);
+            }
+            return existing;
+        }
+
         let strategy = Strategy {
             id: Uuid::new_v4().to_string(),
             description,
@@ -82,15 +134,26 @@ impl StrategyNetwork {
 
     /// Format strategies for sharing with agents
     pub fn format_strategies_for_agents(&self) -> String {
-        let top_strategies = self.get_top_strategies(5);
+        self.format_top_strategies(5)
+            .unwrap_or_else(|| "No strategies discovered yet.".to_string())
+    }
 
-        if top_strategies.is_empty() {
-            return "No strategies discovered yet.".to_string();
+    /// Format the `n` highest-rated strategies for agent prompts
+    ///
+    /// Returns `None` when there are no strategies (or `n` is zero).
+    pub fn format_top_strategies(&self, n: usize) -> Option<String> {
+        Self::format_strategies(&self.get_top_strategies(n))
+    }
+
+    /// Format strategies for agent prompts, or `None` when there are none
+    pub fn format_strategies(strategies: &[Strategy]) -> Option<String> {
+        if strategies.is_empty() {
+            return None;
         }
 
         let mut formatted = String::from("Successful strategies discovered by other agents:\n\n");
 
-        for (idx, strategy) in top_strategies.iter().enumerate() {
+        for (idx, strategy) in strategies.iter().enumerate() {
             formatted.push_str(&format!(
                 "{}. {} (Success rate: {:.1}%)\n   Technique: {}\n\n",
                 idx + 1,
@@ -100,12 +163,301 @@ impl StrategyNetwork {
             ));
         }
 
-        formatted
+        Some(formatted)
+    }
+
+    /// The `k` strategies most semantically similar to `query`
+    ///
+    /// Strategies are embedded on first use and their vectors cached. When the
+    /// provider cannot embed, falls back to the `k` highest-rated strategies.
+    pub async fn find_relevant(
+        &mut self,
+        query: &str,
+        k: usize,
+        provider: &dyn LLMProvider,
+    ) -> Vec<Strategy> {
+        if k == 0 || self.strategies.is_empty() {
+            return Vec::new();
+        }
+
+        // Embed unindexed strategies and the query in one request
+        let Some(mut vectors) = self.index_embeddings(&[query.to_string()], provider).await else {
+            return self.get_top_strategies(k);
+        };
+        let query_vector = vectors.pop().unwrap_or_default();
+
+        let mut scored: Vec<(f32, &Strategy)> = self
+            .strategies
+            .values()
+            .filter_map(|strategy| {
+                let vector = self.embeddings.get(&strategy.id)?;
+                Some((cosine_similarity(vector, &query_vector), strategy))
+            })
+            .collect();
+        scored.sort_by(|(a_sim, a), (b_sim, b)| {
+            b_sim
+                .total_cmp(a_sim)
+                .then(b.success_rate.total_cmp(&a.success_rate))
+        });
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(_, strategy)| strategy.clone())
+            .collect()
+    }
+
+    /// Consolidate overlapping strategies into canonical entries
+    ///
+    /// Strategies whose similarity (embedding cosine, or word overlap when the
+    /// provider cannot embed) reaches `threshold` are grouped, and the LLM
+    /// rewrites each group as one strategy. The group's earliest strategy keeps
+    /// its ID and takes the best success rate. Returns how many were removed.
+    pub async fn merge_similar(
+        &mut self,
+        threshold: f32,
+        provider: &dyn LLMProvider,
+    ) -> Result<usize> {
+        let embedded = self.index_embeddings(&[], provider).await.is_some();
+        let similarity = |a: &Strategy, b: &Strategy| match (
+            embedded,
+            self.embeddings.get(&a.id),
+            self.embeddings.get(&b.id),
+        ) {
+            (true, Some(a), Some(b)) => cosine_similarity(a, b),
+            _ => lexical_similarity(&a.description, &b.description),
+        };
+
+        let mut ordered: Vec<&Strategy> = self.strategies.values().collect();
+        ordered.sort_by(|a, b| {
+            a.discovered_at
+                .cmp(&b.discovered_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        let mut groups: Vec<Vec<&Strategy>> = Vec::new();
+        for strategy in ordered {
+            match groups
+                .iter_mut()
+                .find(|group| similarity(group[0], strategy) >= threshold)
+            {
+                Some(group) => group.push(strategy),
+                None => groups.push(vec![strategy]),
+            }
+        }
+        let groups: Vec<Vec<Strategy>> = groups
+            .into_iter()
+            .filter(|group| group.len() > 1)
+            .map(|group| group.into_iter().cloned().collect())
+            .collect();
+
+        let mut removed = 0;
+        for group in groups {
+            let merged = Self::merge_group(&group, provider).await?;
+            for duplicate in &group[1..] {
+                self.strategies.remove(&duplicate.id);
+                self.embeddings.remove(&duplicate.id);
+                self.retired.insert(duplicate.id.clone(), duplicate.clone());
+                self.add_edge(
+                    duplicate.id.clone(),
+                    merged.id.clone(),
+                    StrategyEdgeKind::MergedInto,
+                );
+            }
+            self.embeddings.remove(&merged.id);
+            self.strategies.insert(merged.id.clone(), merged);
+            removed += group.len() - 1;
+        }
+
+        Ok(removed)
+    }
+
+    /// Ask the LLM to rewrite a group of overlapping strategies as one
+    async fn merge_group(group: &[Strategy], provider: &dyn LLMProvider) -> Result<Strategy> {
+        let listing = group
+            .iter()
+            .enumerate()
+            .map(|(idx, strategy)| {
+                format!(
+                    "{}. {}\n   Technique: {}",
+                    idx + 1,
+                    strategy.description,
+                    strategy.technique
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = format!(
+            "{}\n\nStrategies:\n{}",
+            prompts::STRATEGY_MERGE_PROMPT,
+            listing
+        );
+        let response = provider.complete(&prompt, None).await?;
+
+        let field = |name: &str| {
+            response.text.lines().find_map(|line| {
+                let value = line.trim().strip_prefix(name)?.trim();
+                (!value.is_empty()).then(|| value.to_string())
+            })
+        };
+        let mut merged = group[0].clone();
+        merged.description = field("DESCRIPTION:").ok_or_else(|| {
+            crate::MarsError::StrategyExtractionError(
+                "Strategy merge response has no DESCRIPTION line".to_string(),
+            )
+        })?;
+        if let Some(technique) = field("TECHNIQUE:") {
+            merged.technique = technique;
+        }
+        merged.success_rate = group
+            .iter()
+            .map(|strategy| strategy.success_rate)
+            .fold(0.0, f32::max);
+
+        Ok(merged)
+    }
+
+    /// Existing strategy whose description overlaps `description` enough to
+    /// count as a duplicate
+    fn find_duplicate(&self, description: &str) -> Option<String> {
+        self.strategies
+            .values()
+            .map(|strategy| {
+                (
+                    lexical_similarity(&strategy.description, description),
+                    strategy,
+                )
+            })
+            .filter(|(similarity, _)| *similarity >= self.dedup_threshold)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, strategy)| strategy.id.clone())
+    }
+
+    /// Embed strategies that have no vector yet, along with `extra` texts
+    ///
+    /// Returns the vectors for `extra`, or `None` when the provider cannot embed.
+    async fn index_embeddings(
+        &mut self,
+        extra: &[String],
+        provider: &dyn LLMProvider,
+    ) -> Option<Vec<Vec<f32>>> {
+        let unindexed: Vec<String> = self
+            .strategies
+            .keys()
+            .filter(|id| !self.embeddings.contains_key(*id))
+            .cloned()
+            .collect();
+        let mut texts: Vec<String> = unindexed
+            .iter()
+            .map(|id| Self::embedding_text(&self.strategies[id]))
+            .collect();
+        texts.extend_from_slice(extra);
+        if texts.is_empty() {
+            return Some(Vec::new());
+        }
+
+        let mut vectors = match provider.embed(&texts).await {
+            Ok(vectors) if vectors.len() == texts.len() => vectors,
+            Ok(vectors) => {
+                tracing::warn!(
+                    "Expected {} strategy embeddings, got {}",
+                    texts.len(),
+                    vectors.len()
+                );
+                return None;
+            }
+            Err(e) => {
+                tracing::debug!("Strategy embeddings unavailable: {e}");
+                return None;
+            }
+        };
+        let extra_vectors = vectors.split_off(unindexed.len());
+        self.embeddings.extend(unindexed.into_iter().zip(vectors));
+        Some(extra_vectors)
+    }
+
+    /// Text embedded to index a strategy
+    fn embedding_text(strategy: &Strategy) -> String {
+        format!("{}\n{}", strategy.description, strategy.technique)
     }
 
     /// Clear all strategies (useful for testing)
     pub fn clear(&mut self) {
         self.strategies.clear();
+        self.embeddings.clear();
+        self.edges.clear();
+        self.retired.clear();
+    }
+
+    /// Record that a strategy was included in an agent's prompt
+    pub fn record_usage(&mut self, strategy_id: &str, agent_id: &str) {
+        self.add_edge(
+            strategy_id.to_string(),
+            agent_id.to_string(),
+            StrategyEdgeKind::UsedBy,
+        );
+    }
+
+    /// Graph of strategies and agents, showing how insights flowed in the run
+    ///
+    /// Every strategy links from the agent that discovered it; rediscoveries,
+    /// merges, and prompt usages recorded during the run add further edges.
+    pub fn export_graph(&self) -> StrategyGraph {
+        let mut strategies: Vec<&Strategy> = self
+            .strategies
+            .values()
+            .chain(self.retired.values())
+            .collect();
+        strategies.sort_by(|a, b| {
+            a.discovered_at
+                .cmp(&b.discovered_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let mut edges: Vec<StrategyEdge> = strategies
+            .iter()
+            .map(|strategy| StrategyEdge {
+                from: strategy.discovered_by.clone(),
+                to: strategy.id.clone(),
+                kind: StrategyEdgeKind::Discovered,
+            })
+            .collect();
+        edges.extend(self.edges.iter().cloned());
+
+        let strategy_ids: HashSet<&str> = strategies.iter().map(|s| s.id.as_str()).collect();
+        let agents: BTreeSet<&str> = edges
+            .iter()
+            .flat_map(|edge| [edge.from.as_str(), edge.to.as_str()])
+            .filter(|id| !strategy_ids.contains(id))
+            .collect();
+
+        let mut nodes: Vec<StrategyGraphNode> = agents
+            .into_iter()
+            .map(|id| StrategyGraphNode {
+                id: id.to_string(),
+                kind: StrategyNodeKind::Agent,
+                label: id.to_string(),
+                success_rate: None,
+            })
+            .collect();
+        nodes.extend(strategies.iter().map(|strategy| StrategyGraphNode {
+            id: strategy.id.clone(),
+            kind: if self.retired.contains_key(&strategy.id) {
+                StrategyNodeKind::MergedStrategy
+            } else {
+                StrategyNodeKind::Strategy
+            },
+            label: strategy.description.clone(),
+            success_rate: Some(strategy.success_rate),
+        }));
+
+        StrategyGraph { nodes, edges }
+    }
+
+    /// Record an edge unless the same edge already exists
+    fn add_edge(&mut self, from: String, to: String, kind: StrategyEdgeKind) {
+        let edge = StrategyEdge { from, to, kind };
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
     }
 
     /// Get number of registered strategies
@@ -139,6 +491,130 @@ impl Default for StrategyNetwork {
     }
 }
 
+/// Kind of node in a [`StrategyGraph`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyNodeKind {
+    /// An agent that discovered or used strategies
+    Agent,
+    /// A strategy in the network
+    Strategy,
+    /// A strategy that was merged into another
+    MergedStrategy,
+}
+
+/// Kind of edge in a [`StrategyGraph`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyEdgeKind {
+    /// Agent -> strategy it extracted
+    Discovered,
+    /// Duplicate strategy -> the canonical strategy it was merged into
+    MergedInto,
+    /// Strategy -> agent whose prompt included it
+    UsedBy,
+}
+
+/// Node in a [`StrategyGraph`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StrategyGraphNode {
+    /// Agent or strategy ID
+    pub id: String,
+    /// Whether this is an agent or a strategy
+    pub kind: StrategyNodeKind,
+    /// Agent ID or strategy description
+    pub label: String,
+    /// Strategy success rate (None for agents)
+    pub success_rate: Option<f32>,
+}
+
+/// Directed edge in a [`StrategyGraph`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrategyEdge {
+    /// Source node ID
+    pub from: String,
+    /// Target node ID
+    pub to: String,
+    /// What the edge records
+    pub kind: StrategyEdgeKind,
+}
+
+/// Strategies and agents linked by derivation and usage
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StrategyGraph {
+    /// Agent nodes (sorted by ID) followed by strategy nodes (oldest first)
+    pub nodes: Vec<StrategyGraphNode>,
+    /// Directed edges between node IDs
+    pub edges: Vec<StrategyEdge>,
+}
+
+impl StrategyGraph {
+    /// Serialize the graph as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| {
+            crate::MarsError::StrategyExtractionError(format!(
+                "Failed to serialize strategy graph: {e}"
+            ))
+        })
+    }
+
+    /// Render the graph in Graphviz DOT format
+    ///
+    /// Agents are ellipses, strategies are boxes (dashed once merged), and
+    /// usage edges are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph strategies {\n");
+        for node in &self.nodes {
+            let (shape, style) = match node.kind {
+                StrategyNodeKind::Agent => ("ellipse", "solid"),
+                StrategyNodeKind::Strategy => ("box", "solid"),
+                StrategyNodeKind::MergedStrategy => ("box", "dashed"),
+            };
+            let label = match node.success_rate {
+                Some(rate) => format!(
+                    "{}\\nsuccess={rate:.2}",
+                    escape_dot(&node.label.chars().take(40).collect::<String>())
+                ),
+                None => escape_dot(&node.label),
+            };
+            dot.push_str(&format!(
+                "    \"{}\" [shape={shape}, style={style}, label=\"{label}\"];\n",
+                escape_dot(&node.id)
+            ));
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                StrategyEdgeKind::Discovered => "solid",
+                StrategyEdgeKind::MergedInto => "bold",
+                StrategyEdgeKind::UsedBy => "dashed",
+            };
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [style={style}];\n",
+                escape_dot(&edge.from),
+                escape_dot(&edge.to)
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Jaccard similarity of the lowercase words in two texts
+fn lexical_similarity(a: &str, b: &str) -> f32 {
+    let words = |text: &str| -> HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
 /// Metrics about strategy diversity in the network
 #[derive(Debug, Clone)]
 pub struct StrategyDiversity {
@@ -153,6 +629,86 @@ pub struct StrategyDiversity {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+
+    /// Mock provider that embeds texts by the topics they mention
+    struct TopicEmbedder;
+
+    #[async_trait]
+    impl LLMProvider for TopicEmbedder {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Ok(String::new().into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "topic-embedder"
+        }
+
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            let topic = |text: &str, word: &str| f32::from(u8::from(text.contains(word)));
+            Ok(texts
+                .iter()
+                .map(|text| vec![topic(text, "triangle"), topic(text, "prime")])
+                .collect())
+        }
+    }
+
+    /// Mock provider without an embeddings endpoint
+    struct NoEmbedder;
+
+    #[async_trait]
+    impl LLMProvider for NoEmbedder {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Ok(String::new().into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "no-embedder"
+        }
+    }
+
+    fn network_with_topics() -> (StrategyNetwork, String, String) {
+        let mut network = StrategyNetwork::new();
+        let geometry = network.register_strategy(
+            "agent1".to_string(),
+            "Draw the triangle's altitudes".to_string(),
+            "Use similar triangles".to_string(),
+        );
+        let number_theory = network.register_strategy(
+            "agent2".to_string(),
+            "Factor into primes".to_string(),
+            "Compare prime exponents".to_string(),
+        );
+        network.update_success_rate(&number_theory, true).unwrap();
+        (network, geometry, number_theory)
+    }
 
     #[test]
     fn test_register_strategy() {
@@ -250,6 +806,228 @@ mod tests {
         assert_eq!(diversity.unique_agents, 2);
     }
 
+    #[tokio::test]
+    async fn test_find_relevant_by_embedding() {
+        let (mut network, geometry, number_theory) = network_with_topics();
+
+        let relevant = network
+            .find_relevant("Find the area of this triangle", 1, &TopicEmbedder)
+            .await;
+        assert_eq!(relevant.len(), 1);
+        assert_eq!(relevant[0].id, geometry);
+        assert_eq!(network.embeddings.len(), 2);
+
+        let relevant = network
+            .find_relevant("Is 91 prime?", 2, &TopicEmbedder)
+            .await;
+        assert_eq!(relevant[0].id, number_theory);
+        assert_eq!(relevant.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_find_relevant_falls_back_to_top_rated() {
+        let (mut network, _geometry, number_theory) = network_with_topics();
+
+        let relevant = network
+            .find_relevant("Find the area of this triangle", 1, &NoEmbedder)
+            .await;
+        assert_eq!(relevant.len(), 1);
+        assert_eq!(relevant[0].id, number_theory);
+        assert!(network.embeddings.is_empty());
+        assert!(
+            network
+                .find_relevant("q", 0, &TopicEmbedder)
+                .await
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_register_deduplicates_near_identical_strategies() {
+        let mut network = StrategyNetwork::new();
+        let id = network.register_strategy(
+            "agent1".to_string(),
+            "Break the problem into smaller parts".to_string(),
+            "T1".to_string(),
+        );
+        let duplicate = network.register_strategy(
+            "agent2".to_string(),
+            "break the problem into parts.".to_string(),
+            "T2".to_string(),
+        );
+        assert_eq!(duplicate, id);
+        assert_eq!(network.count_strategies(), 1);
+
+        let mut strict = StrategyNetwork::new().with_dedup_threshold(1.1);
+        strict.register_strategy("agent1".to_string(), "Same".to_string(), "T".to_string());
+        strict.register_strategy("agent2".to_string(), "Same".to_string(), "T".to_string());
+        assert_eq!(strict.count_strategies(), 2);
+    }
+
+    /// Mock provider that merges strategies into one canonical entry
+    struct Merger;
+
+    #[async_trait]
+    impl LLMProvider for Merger {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            assert!(prompt.starts_with(prompts::STRATEGY_MERGE_PROMPT));
+            assert!(prompt.contains("2. "));
+            Ok("DESCRIPTION: Decompose the problem\nTECHNIQUE: Solve each part separately".into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "merger"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merge_similar_strategies() {
+        let mut network = StrategyNetwork::new().with_dedup_threshold(1.1);
+        let first = network.register_strategy(
+            "agent1".to_string(),
+            "Split the problem into parts".to_string(),
+            "T1".to_string(),
+        );
+        let second = network.register_strategy(
+            "agent2".to_string(),
+            "Split the problem into cases".to_string(),
+            "T2".to_string(),
+        );
+        network.update_success_rate(&second, true).unwrap();
+        network.strategies.get_mut(&second).unwrap().discovered_at += chrono::Duration::seconds(1);
+        let best_rate = network.strategies[&second].success_rate;
+        network.register_strategy(
+            "agent3".to_string(),
+            "Check units".to_string(),
+            "T3".to_string(),
+        );
+
+        let removed = network.merge_similar(0.5, &Merger).await.unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(network.count_strategies(), 2);
+        let merged = &network.strategies[&first];
+        assert_eq!(merged.description, "Decompose the problem");
+        assert_eq!(merged.technique, "Solve each part separately");
+        assert_eq!(merged.success_rate, best_rate);
+        assert!(!network.strategies.contains_key(&second));
+    }
+
+    #[tokio::test]
+    async fn test_export_graph() {
+        let mut network = StrategyNetwork::new().with_dedup_threshold(0.9);
+        let first = network.register_strategy(
+            "agent-a".to_string(),
+            "Split the problem into parts".to_string(),
+            "T1".to_string(),
+        );
+        let second = network.register_strategy(
+            "agent-b".to_string(),
+            "Split the problem into cases".to_string(),
+            "T2".to_string(),
+        );
+        network.strategies.get_mut(&second).unwrap().discovered_at += chrono::Duration::seconds(1);
+        // Rediscovered by another agent
+        network.register_strategy(
+            "agent-c".to_string(),
+            "split the problem into parts".to_string(),
+            "T3".to_string(),
+        );
+        network.merge_similar(0.5, &Merger).await.unwrap();
+        network.record_usage(&first, "agent-d");
+
+        let graph = network.export_graph();
+        let ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "agent-a",
+                "agent-b",
+                "agent-c",
+                "agent-d",
+                first.as_str(),
+                second.as_str()
+            ]
+        );
+        assert_eq!(graph.nodes[5].kind, StrategyNodeKind::MergedStrategy);
+
+        let has_edge = |from: &str, to: &str, kind| {
+            graph.edges.contains(&StrategyEdge {
+                from: from.to_string(),
+                to: to.to_string(),
+                kind,
+            })
+        };
+        assert!(has_edge("agent-a", &first, StrategyEdgeKind::Discovered));
+        assert!(has_edge("agent-c", &first, StrategyEdgeKind::Discovered));
+        assert!(has_edge(&second, &first, StrategyEdgeKind::MergedInto));
+        assert!(has_edge(&first, "agent-d", StrategyEdgeKind::UsedBy));
+        assert_eq!(graph.edges.len(), 5);
+
+        let json = graph.to_json().unwrap();
+        assert!(json.contains("\"merged_into\""));
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph strategies {"));
+        assert!(dot.contains(&format!("\"{first}\" -> \"agent-d\" [style=dashed];")));
+    }
+
+    #[test]
+    fn test_lexical_similarity() {
+        assert_eq!(lexical_similarity("Check units!", "check UNITS"), 1.0);
+        assert_eq!(lexical_similarity("a b", "c d"), 0.0);
+        assert_eq!(lexical_similarity("", ""), 1.0);
+    }
+
+    #[test]
+    fn test_format_top_strategies() {
+        let mut network = StrategyNetwork::new();
+        assert!(network.format_top_strategies(3).is_none());
+
+        network.register_strategy(
+            "agent1".to_string(),
+            "Decomposition".to_string(),
+            "Break into parts".to_string(),
+        );
+        assert!(network.format_top_strategies(0).is_none());
+        assert!(
+            network
+                .format_top_strategies(3)
+                .unwrap()
+                .contains("1. Decomposition")
+        );
+    }
+
+    #[test]
+    fn test_load_strategies_merges_by_id() {
+        let mut original = StrategyNetwork::new();
+        let id = original.register_strategy(
+            "agent1".to_string(),
+            "Decomposition".to_string(),
+            "Break into parts".to_string(),
+        );
+        original.update_success_rate(&id, true).unwrap();
+
+        let mut network = StrategyNetwork::from_strategies(original.get_all_strategies());
+        network.load_strategies(original.get_all_strategies());
+        assert_eq!(network.count_strategies(), 1);
+        assert_eq!(
+            network.strategies[&id].success_rate,
+            original.strategies[&id].success_rate
+        );
+    }
+
     #[test]
     fn test_format_strategies() {
         let mut network = StrategyNetwork::new();
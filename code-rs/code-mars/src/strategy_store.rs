@@ -0,0 +1,128 @@
+//! Persistent storage for extracted strategies.
+//!
+//! A [`StrategyNetwork`](crate::strategy::StrategyNetwork) lives only as long
+//! as its coordinator. The store keeps strategies in a JSON file between runs,
+//! grouped by namespace (a domain or tag such as `"math"` or `"code/rust"`),
+//! so strategies extracted in one run can inform the next.
+
+use crate::types::Strategy;
+use crate::{MarsError, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Namespace used when none is configured
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// JSON file of strategies, grouped by namespace
+#[derive(Clone, Debug)]
+pub struct StrategyStore {
+    path: PathBuf,
+}
+
+impl StrategyStore {
+    /// Use the store at `path` (created on first save)
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// File the strategies are persisted to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Strategies saved under `namespace` (empty if none were saved)
+    pub fn load(&self, namespace: &str) -> Result<Vec<Strategy>> {
+        Ok(self.read_all()?.remove(namespace).unwrap_or_default())
+    }
+
+    /// Namespaces that have saved strategies, sorted
+    pub fn namespaces(&self) -> Result<Vec<String>> {
+        let mut namespaces: Vec<String> = self.read_all()?.into_keys().collect();
+        namespaces.sort();
+        Ok(namespaces)
+    }
+
+    /// Replace the strategies saved under `namespace`, keeping other namespaces
+    pub async fn save(&self, namespace: &str, strategies: &[Strategy]) -> Result<()> {
+        let mut all = self.read_all()?;
+        all.insert(namespace.to_string(), strategies.to_vec());
+        let contents = serde_json::to_string_pretty(&all).map_err(|e| {
+            MarsError::StrategyExtractionError(format!("Failed to serialize strategies: {e}"))
+        })?;
+
+        // Write to a sibling file first so a crash never leaves a truncated store
+        let tmp_path = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, contents).await.map_err(|e| {
+            MarsError::StrategyExtractionError(format!(
+                "Failed to write strategy store {}: {e}",
+                tmp_path.display()
+            ))
+        })?;
+        tokio::fs::rename(&tmp_path, &self.path).await.map_err(|e| {
+            MarsError::StrategyExtractionError(format!(
+                "Failed to write strategy store {}: {e}",
+                self.path.display()
+            ))
+        })
+    }
+
+    /// Every namespace in the file (empty if the file does not exist yet)
+    fn read_all(&self) -> Result<HashMap<String, Vec<Strategy>>> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let contents = std::fs::read_to_string(&self.path).map_err(|e| {
+            MarsError::StrategyExtractionError(format!(
+                "Failed to read strategy store {}: {e}",
+                self.path.display()
+            ))
+        })?;
+        serde_json::from_str(&contents).map_err(|e| {
+            MarsError::StrategyExtractionError(format!(
+                "Invalid strategy store {}: {e}",
+                self.path.display()
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::StrategyNetwork;
+
+    #[tokio::test]
+    async fn test_save_and_load_by_namespace() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StrategyStore::new(dir.path().join("strategies.json"));
+        assert!(store.load("math").unwrap().is_empty());
+
+        let mut network = StrategyNetwork::new();
+        network.register_strategy(
+            "agent1".to_string(),
+            "Work backwards from the answer".to_string(),
+            "Invert the problem".to_string(),
+        );
+        store
+            .save("math", &network.get_all_strategies())
+            .await
+            .unwrap();
+        store.save("code", &[]).await.unwrap();
+
+        let reopened = StrategyStore::new(store.path());
+        let math = reopened.load("math").unwrap();
+        assert_eq!(math.len(), 1);
+        assert_eq!(math[0].description, "Work backwards from the answer");
+        assert!(reopened.load("code").unwrap().is_empty());
+        assert_eq!(reopened.namespaces().unwrap(), vec!["code", "math"]);
+    }
+
+    #[test]
+    fn test_invalid_store_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("strategies.json");
+        std::fs::write(&path, "not json").unwrap();
+        assert!(StrategyStore::new(path).load("math").is_err());
+    }
+}
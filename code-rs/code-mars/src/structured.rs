@@ -0,0 +1,333 @@
+//! JSON and schema-constrained output from provider calls.
+//!
+//! [`LLMProvider::complete_structured`] asks for a response in a
+//! [`ResponseFormat`]. Providers with a native JSON mode (OpenAI-compatible
+//! APIs, Azure OpenAI, Gemini, Ollama) constrain generation server-side; the
+//! rest get format instructions appended to the prompt and are re-prompted
+//! with the parse error when they answer with something else. Either way the
+//! returned text is bare JSON that [`ResponseFormat::parse`] accepts.
+
+use crate::model_router::{CompletionResponse, LLMProvider};
+use crate::{MarsError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// Attempts made by re-prompting emulation before giving up
+const EMULATION_ATTEMPTS: usize = 3;
+
+/// Shape a response must take
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free-form text
+    #[default]
+    Text,
+    /// Any JSON object
+    JsonObject,
+    /// JSON matching `schema` (a JSON Schema), identified by `name`
+    JsonSchema {
+        /// Schema name, as reported to providers that take one
+        name: String,
+        /// JSON Schema of the response
+        schema: Value,
+    },
+}
+
+impl ResponseFormat {
+    /// JSON matching `schema`
+    pub fn json_schema(name: impl Into<String>, schema: Value) -> Self {
+        Self::JsonSchema {
+            name: name.into(),
+            schema,
+        }
+    }
+
+    /// Whether the response must be JSON
+    pub fn is_json(&self) -> bool {
+        !matches!(self, Self::Text)
+    }
+
+    /// OpenAI chat completions `response_format` value (None for text)
+    pub(crate) fn to_openai(&self) -> Option<Value> {
+        match self {
+            Self::Text => None,
+            Self::JsonObject => Some(json!({ "type": "json_object" })),
+            Self::JsonSchema { name, schema } => Some(json!({
+                "type": "json_schema",
+                "json_schema": { "name": name, "schema": schema },
+            })),
+        }
+    }
+
+    /// Instructions appended to the prompt asking for the format
+    ///
+    /// OpenAI's JSON mode also requires the prompt to mention JSON.
+    pub(crate) fn instructions(&self) -> Option<String> {
+        match self {
+            Self::Text => None,
+            Self::JsonObject => Some(
+                "Respond with a single JSON object and nothing else: no prose, no code fences."
+                    .to_string(),
+            ),
+            Self::JsonSchema { schema, .. } => Some(format!(
+                "Respond with a single JSON value matching this JSON Schema and nothing else: \
+                 no prose, no code fences.\n{schema}"
+            )),
+        }
+    }
+
+    /// `prompt` with the format's instructions appended
+    pub(crate) fn prompt(&self, prompt: &str) -> String {
+        match self.instructions() {
+            Some(instructions) => format!("{prompt}\n\n{instructions}"),
+            None => prompt.to_string(),
+        }
+    }
+
+    /// Parse a response in this format, tolerating code fences and
+    /// surrounding prose
+    ///
+    /// Schemas are checked shallowly: the top-level `type` and the presence
+    /// of `required` properties.
+    pub fn parse(&self, text: &str) -> Result<Value> {
+        let schema = match self {
+            Self::Text => return Ok(Value::String(text.to_string())),
+            Self::JsonObject => json!({ "type": "object" }),
+            Self::JsonSchema { schema, .. } => schema.clone(),
+        };
+        let value = extract_json(text)?;
+        check_shape(&value, &schema)?;
+        Ok(value)
+    }
+
+    /// Replace a response's text with the bare JSON it contains
+    pub(crate) fn normalize(&self, mut response: CompletionResponse) -> Result<CompletionResponse> {
+        if self.is_json() {
+            response.text = self.parse(&response.text)?.to_string();
+        }
+        Ok(response)
+    }
+}
+
+/// The JSON value in `text`, which may be fenced or surrounded by prose
+fn extract_json(text: &str) -> Result<Value> {
+    let trimmed = text.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Ok(value);
+    }
+    // Fall back to the outermost object or array
+    let start = trimmed.find(['{', '[']);
+    let end = trimmed.rfind(['}', ']']);
+    if let (Some(start), Some(end)) = (start, end)
+        && start < end
+        && let Ok(value) = serde_json::from_str(&trimmed[start..=end])
+    {
+        return Ok(value);
+    }
+    Err(MarsError::ParsingError(format!(
+        "Response is not valid JSON: {}",
+        trimmed.chars().take(200).collect::<String>()
+    )))
+}
+
+/// Check the top-level type and required properties of `value` against `schema`
+fn check_shape(value: &Value, schema: &Value) -> Result<()> {
+    let type_matches = match schema.get("type").and_then(Value::as_str) {
+        Some("object") => value.is_object(),
+        Some("array") => value.is_array(),
+        Some("string") => value.is_string(),
+        Some("number") => value.is_number(),
+        Some("integer") => value.is_i64() || value.is_u64(),
+        Some("boolean") => value.is_boolean(),
+        _ => true,
+    };
+    if !type_matches {
+        return Err(MarsError::ParsingError(format!(
+            "Expected a JSON {}, got: {value}",
+            schema["type"]
+        )));
+    }
+    let missing: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter(|key| value.get(key).is_none())
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(MarsError::ParsingError(format!(
+            "Response is missing required fields: {}",
+            missing.join(", ")
+        )))
+    }
+}
+
+/// Emulate a structured-output mode by instructing the model and
+/// re-prompting it with the parse error until its response parses
+pub(crate) async fn complete_by_reprompting<P: LLMProvider + ?Sized>(
+    provider: &P,
+    prompt: &str,
+    system_prompt: Option<&str>,
+    format: &ResponseFormat,
+) -> Result<CompletionResponse> {
+    if !format.is_json() {
+        return provider.complete(prompt, system_prompt).await;
+    }
+
+    let mut request = format.prompt(prompt);
+    // Usage summed across attempts; unknown once any attempt goes unreported
+    let (mut prompt_tokens, mut completion_tokens) = (Some(0), Some(0));
+    let mut last_error = None;
+    for _ in 0..EMULATION_ATTEMPTS {
+        let response = provider.complete(&request, system_prompt).await?;
+        prompt_tokens = prompt_tokens
+            .zip(response.prompt_tokens)
+            .map(|(a, b)| a + b);
+        completion_tokens = completion_tokens
+            .zip(response.completion_tokens)
+            .map(|(a, b)| a + b);
+        match format.parse(&response.text) {
+            Ok(value) => {
+                return Ok(CompletionResponse {
+                    text: value.to_string(),
+                    prompt_tokens,
+                    completion_tokens,
+                    ..response
+                });
+            }
+            Err(e) => {
+                request = format!(
+                    "{}\n\nYour previous response could not be used ({e}):\n{}\n\n{}",
+                    format.prompt(prompt),
+                    response.text,
+                    format.instructions().unwrap_or_default()
+                );
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        MarsError::ParsingError(format!(
+            "{} returned no structured output",
+            provider.model_id()
+        ))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_router::ModelStream;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Replies with each canned response in turn, recording prompts
+    struct Scripted {
+        replies: Mutex<Vec<&'static str>>,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl Scripted {
+        fn new(replies: &[&'static str]) -> Self {
+            Self {
+                replies: Mutex::new(replies.iter().rev().copied().collect()),
+                prompts: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for Scripted {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            self.prompts.lock().unwrap().push(prompt.to_string());
+            let reply = self.replies.lock().unwrap().pop().unwrap_or_default();
+            Ok(CompletionResponse::new(reply).with_usage(10, 5))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "scripted"
+        }
+    }
+
+    fn verdict_format() -> ResponseFormat {
+        ResponseFormat::json_schema(
+            "verdict",
+            json!({
+                "type": "object",
+                "properties": { "correct": { "type": "boolean" } },
+                "required": ["correct"],
+            }),
+        )
+    }
+
+    #[test]
+    fn test_parse_tolerates_fences_and_prose() {
+        let format = ResponseFormat::JsonObject;
+        let fenced = "Here you go:\n```json\n{\"correct\": true}\n```";
+        assert_eq!(format.parse(fenced).unwrap(), json!({ "correct": true }));
+        assert!(format.parse("no json here").is_err());
+        assert!(format.parse("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn test_parse_checks_required_fields() {
+        let format = verdict_format();
+        assert!(format.parse(r#"{"correct": false}"#).is_ok());
+        let err = format.parse(r#"{"score": 0.5}"#).unwrap_err();
+        assert!(err.to_string().contains("correct"));
+    }
+
+    #[test]
+    fn test_openai_response_format() {
+        assert_eq!(ResponseFormat::Text.to_openai(), None);
+        let format = verdict_format().to_openai().unwrap();
+        assert_eq!(format["type"], "json_schema");
+        assert_eq!(format["json_schema"]["name"], "verdict");
+        let roundtrip: ResponseFormat =
+            serde_json::from_value(serde_json::to_value(verdict_format()).unwrap()).unwrap();
+        assert_eq!(roundtrip, verdict_format());
+    }
+
+    #[tokio::test]
+    async fn test_emulation_reprompts_until_valid() {
+        let provider = Scripted::new(&["Sure! It is correct.", r#"{"correct": true}"#]);
+        let response = provider
+            .complete_structured("Is 2+2=4?", None, &verdict_format())
+            .await
+            .unwrap();
+        assert_eq!(response.text, r#"{"correct":true}"#);
+        assert_eq!(response.prompt_tokens, Some(20));
+
+        let prompts = provider.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[0].contains("JSON Schema"));
+        assert!(prompts[1].contains("Sure! It is correct."));
+    }
+
+    #[tokio::test]
+    async fn test_emulation_gives_up() {
+        let provider = Scripted::new(&["no", "still no", "nope", r#"{"correct": true}"#]);
+        let err = provider
+            .complete_structured("q", None, &ResponseFormat::JsonObject)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MarsError::ParsingError(_)));
+        assert_eq!(provider.prompts.lock().unwrap().len(), EMULATION_ATTEMPTS);
+    }
+}
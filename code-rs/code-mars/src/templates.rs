@@ -0,0 +1,232 @@
+//! Jinja templating for prompt overrides.
+//!
+//! The overrides in [`PromptSet`](crate::prompts::PromptSet) and
+//! [`AggregationPrompts`](crate::prompts::AggregationPrompts) are
+//! [minijinja](https://docs.rs/minijinja) templates, rendered once at the
+//! start of each run: `{{ domain }}` reads a prompt variable,
+//! `{% if language %}` branches on one and `{% include "format" %}` inserts a
+//! partial. Variables are declared with defaults in
+//! `MarsConfig::prompt_variables` and may be overridden per run through
+//! [`RunOptions::with_variable`](crate::RunOptions::with_variable).
+//!
+//! Single-brace placeholders such as `{query}` and `{solution}` are plain text
+//! to Jinja, so they survive rendering and are filled per call by
+//! [`render_template`](crate::prompts::render_template).
+
+use crate::config::MarsConfig;
+use crate::{MarsError, Result};
+use minijinja::{Environment, UndefinedBehavior};
+use std::collections::BTreeMap;
+
+/// Prompt variables or partials, by name
+pub type PromptVariables = BTreeMap<String, String>;
+
+/// Name the template under check is registered as
+const CHECKED_TEMPLATE: &str = "<prompt>";
+
+/// Renders prompt templates that may include a set of partials
+#[derive(Clone, Debug)]
+pub struct PromptTemplates {
+    env: Environment<'static>,
+}
+
+impl PromptTemplates {
+    /// Templates that can include `partials` by name
+    ///
+    /// Fails with [`MarsError::InvalidConfiguration`] when a partial does not
+    /// compile.
+    pub fn new(partials: &PromptVariables) -> Result<Self> {
+        let mut env = Environment::new();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.set_keep_trailing_newline(true);
+        for (name, source) in partials {
+            env.add_template_owned(name.clone(), source.clone())
+                .map_err(|e| {
+                    MarsError::InvalidConfiguration(format!("prompt partial {name}: {e}"))
+                })?;
+        }
+        Ok(Self { env })
+    }
+
+    /// Render `template` with `variables`
+    pub fn render(&self, template: &str, variables: &PromptVariables) -> Result<String> {
+        self.env
+            .render_str(template, variables)
+            .map_err(|e| MarsError::InvalidConfiguration(format!("prompt template: {e}")))
+    }
+
+    /// Why `template` would fail to render with `variables`, if it would
+    ///
+    /// Catches syntax errors, missing partials and variables that are not
+    /// declared, including ones only used in branches a trial render skips.
+    pub fn check(&self, template: &str, variables: &PromptVariables) -> Option<String> {
+        let mut env = self.env.clone();
+        if let Err(e) = env.add_template_owned(CHECKED_TEMPLATE, template.to_string()) {
+            return Some(e.to_string());
+        }
+        let compiled = match env.get_template(CHECKED_TEMPLATE) {
+            Ok(compiled) => compiled,
+            Err(e) => return Some(e.to_string()),
+        };
+
+        let mut undeclared: Vec<String> = compiled
+            .undeclared_variables(false)
+            .into_iter()
+            .filter(|name| {
+                !variables.contains_key(name)
+                    && self.env.globals().all(|(global, _)| global != name)
+            })
+            .collect();
+        if !undeclared.is_empty() {
+            undeclared.sort();
+            return Some(format!(
+                "uses undeclared variables {}; add them to prompt_variables",
+                undeclared.join(", ")
+            ));
+        }
+        compiled.render(variables).err().map(|e| e.to_string())
+    }
+}
+
+/// `config` with its prompt overrides rendered
+///
+/// `run_variables` override the defaults in `config.prompt_variables`; naming
+/// a variable the config does not declare is an error.
+pub fn render_prompts(
+    mut config: MarsConfig,
+    run_variables: &PromptVariables,
+) -> Result<MarsConfig> {
+    if let Some(name) = run_variables
+        .keys()
+        .find(|name| !config.prompt_variables.contains_key(*name))
+    {
+        return Err(MarsError::InvalidConfiguration(format!(
+            "unknown prompt variable {name}; declare it in prompt_variables"
+        )));
+    }
+    let mut variables = config.prompt_variables.clone();
+    variables.extend(run_variables.clone());
+
+    let templates = PromptTemplates::new(&config.prompt_partials)?;
+    let slots = config
+        .prompts
+        .templates_mut()
+        .into_iter()
+        .chain(config.aggregation_prompts.templates_mut());
+    for (_, slot) in slots {
+        if let Some(template) = slot {
+            *template = templates.render(template, &variables)?;
+        }
+    }
+    Ok(config)
+}
+
+/// Problems with `config`'s templates, as `(field, message)` pairs
+pub(crate) fn template_problems(config: &MarsConfig) -> Vec<(&'static str, String)> {
+    let templates = match PromptTemplates::new(&config.prompt_partials) {
+        Ok(templates) => templates,
+        Err(e) => return vec![("prompt_partials", e.to_string())],
+    };
+    let variables = &config.prompt_variables;
+
+    let mut problems = Vec::new();
+    for (name, partial) in &config.prompt_partials {
+        if let Some(problem) = templates.check(partial, variables) {
+            problems.push(("prompt_partials", format!("{name}: {problem}")));
+        }
+    }
+    let mut prompts = config.prompts.clone();
+    for (name, slot) in prompts.templates_mut() {
+        if let Some(problem) = slot.as_deref().and_then(|t| templates.check(t, variables)) {
+            problems.push(("prompts", format!("{name}: {problem}")));
+        }
+    }
+    let mut aggregation_prompts = config.aggregation_prompts.clone();
+    for (name, slot) in aggregation_prompts.templates_mut() {
+        if let Some(problem) = slot.as_deref().and_then(|t| templates.check(t, variables)) {
+            problems.push(("aggregation_prompts", format!("{name}: {problem}")));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> PromptVariables {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_keeps_call_placeholders() {
+        let partials = variables(&[("format", "Answer in {{ language }}.")]);
+        let templates = PromptTemplates::new(&partials).unwrap();
+        let rendered = templates
+            .render(
+                "You review {{ domain }} documents.\n{query}\n{% include \"format\" %}",
+                &variables(&[("domain", "legal"), ("language", "French")]),
+            )
+            .unwrap();
+        assert_eq!(
+            rendered,
+            "You review legal documents.\n{query}\nAnswer in French."
+        );
+    }
+
+    #[test]
+    fn test_check_reports_problems() {
+        let templates = PromptTemplates::new(&PromptVariables::new()).unwrap();
+        let declared = variables(&[("domain", "legal")]);
+        assert_eq!(templates.check("{{ domain }}: {query}", &declared), None);
+        assert_eq!(
+            templates.check("{% for i in range(2) %}{{ i }}{% endfor %}", &declared),
+            None
+        );
+
+        let undeclared = templates
+            .check("{% if strict %}{{ tone }}{% endif %}", &declared)
+            .unwrap();
+        assert!(undeclared.contains("strict, tone"), "{undeclared}");
+        assert!(templates.check("{% if %}", &declared).is_some());
+        assert!(
+            templates
+                .check("{% include \"missing\" %}", &declared)
+                .is_some()
+        );
+        assert!(PromptTemplates::new(&variables(&[("bad", "{{")])).is_err());
+    }
+
+    #[test]
+    fn test_render_prompts() {
+        let config = MarsConfig::new()
+            .with_prompt_variable("domain", "medical")
+            .with_reasoning_prompt("Answer this {{ domain }} question: {query}")
+            .with_rsa_merge_prompt("Merge {{ domain }} answers:\n{solutions}");
+
+        let rendered = render_prompts(config.clone(), &PromptVariables::new()).unwrap();
+        assert_eq!(
+            rendered.prompts.reasoning.as_deref(),
+            Some("Answer this medical question: {query}")
+        );
+        assert_eq!(
+            rendered.aggregation_prompts.rsa_merge.as_deref(),
+            Some("Merge medical answers:\n{solutions}")
+        );
+
+        let rendered =
+            render_prompts(config.clone(), &variables(&[("domain", "veterinary")])).unwrap();
+        assert_eq!(
+            rendered
+                .prompts
+                .reasoning_prompt("Is chocolate safe for dogs?"),
+            "Answer this veterinary question: Is chocolate safe for dogs?"
+        );
+
+        let err = render_prompts(config, &variables(&[("tone", "formal")])).unwrap_err();
+        assert!(matches!(err, MarsError::InvalidConfiguration(_)));
+    }
+}
@@ -0,0 +1,32 @@
+//! Providers shared by the unit tests of several modules
+
+use crate::Result;
+use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+use async_trait::async_trait;
+
+/// Answers every prompt with "The answer is 4"
+pub(crate) struct Four;
+
+#[async_trait]
+impl LLMProvider for Four {
+    async fn complete(
+        &self,
+        _prompt: &str,
+        _system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        Ok(CompletionResponse::new("The answer is 4").with_usage(10, 5))
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let content = self.complete(prompt, system_prompt).await?.text;
+        Ok(ModelStream::new(content))
+    }
+
+    fn provider_name(&self) -> &str {
+        "mock"
+    }
+
+    fn model_name(&self) -> &str {
+        "four"
+    }
+}
@@ -0,0 +1,251 @@
+//! Tool (function) calling on top of [`LLMProvider`].
+//!
+//! [`LLMProvider::complete_with_tools`] offers the model a set of
+//! [`ToolSpec`]s and returns the text it generated together with any
+//! [`ToolCall`]s it made. Specs are mapped to each API's own schema: OpenAI
+//! chat completions take `{"type": "function", "function": {...}}` entries and
+//! return calls with JSON-encoded arguments, while the Anthropic Messages API
+//! takes `input_schema` and returns `tool_use` content blocks.
+//!
+//! Only one round is run: executing the calls and sending their results back
+//! is up to the caller.
+
+use crate::Result;
+use crate::model_router::{
+    CompletionResponse, chat_completion_text, invalid_response, reported_str, reported_usage,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// A tool the model may call
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ToolSpec {
+    /// Name the model calls the tool by
+    pub name: String,
+    /// What the tool does and when to use it
+    pub description: String,
+    /// JSON Schema of the tool's arguments (an `object` schema)
+    pub parameters: Value,
+}
+
+impl ToolSpec {
+    /// Describe a tool taking arguments matching the `parameters` schema
+    pub fn new(name: impl Into<String>, description: impl Into<String>, parameters: Value) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+        }
+    }
+
+    /// Entry of an OpenAI chat completions `tools` array
+    pub(crate) fn to_openai(&self) -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            },
+        })
+    }
+
+    /// Entry of an Anthropic Messages API `tools` array
+    pub(crate) fn to_anthropic(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "input_schema": self.parameters,
+        })
+    }
+}
+
+/// A call the model made to one of the offered tools
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ToolCall {
+    /// Provider-assigned ID, used to match the call with its result
+    pub id: String,
+    /// Name of the tool called
+    pub name: String,
+    /// Arguments, decoded from JSON
+    pub arguments: Value,
+}
+
+/// A completion that may call tools instead of, or as well as, answering
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ToolAwareResponse {
+    /// Generated text (empty if the model only called tools) and usage
+    pub response: CompletionResponse,
+    /// Tool calls, in the order the model made them
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl ToolAwareResponse {
+    /// Whether the model called any tools
+    pub fn has_tool_calls(&self) -> bool {
+        !self.tool_calls.is_empty()
+    }
+
+    /// First call to the tool named `name`, if any
+    pub fn tool_call(&self, name: &str) -> Option<&ToolCall> {
+        self.tool_calls.iter().find(|call| call.name == name)
+    }
+}
+
+/// Parse an OpenAI chat completion response that may contain tool calls
+pub(crate) fn openai_tool_response(response: &Value, model_id: &str) -> Result<ToolAwareResponse> {
+    let Some(calls) = response
+        .pointer("/choices/0/message/tool_calls")
+        .and_then(Value::as_array)
+        .filter(|calls| !calls.is_empty())
+    else {
+        return Ok(ToolAwareResponse {
+            response: chat_completion_text(response, model_id)?,
+            tool_calls: Vec::new(),
+        });
+    };
+
+    let tool_calls = calls
+        .iter()
+        .map(|call| {
+            let name = reported_str(call, "/function/name")
+                .ok_or_else(|| invalid_response(model_id, "tool call without a name"))?;
+            // Arguments arrive JSON-encoded; an empty string means no arguments
+            let arguments = match call.pointer("/function/arguments").and_then(Value::as_str) {
+                Some(arguments) if !arguments.trim().is_empty() => serde_json::from_str(arguments)
+                    .map_err(|e| {
+                        invalid_response(model_id, &format!("bad arguments for {name}: {e}"))
+                    })?,
+                _ => json!({}),
+            };
+            Ok(ToolCall {
+                id: reported_str(call, "/id").unwrap_or_default(),
+                name,
+                arguments,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (prompt_tokens, completion_tokens) =
+        reported_usage(response, "/usage/prompt_tokens", "/usage/completion_tokens");
+    Ok(ToolAwareResponse {
+        response: CompletionResponse {
+            text: reported_str(response, "/choices/0/message/content").unwrap_or_default(),
+            prompt_tokens,
+            completion_tokens,
+            finish_reason: reported_str(response, "/choices/0/finish_reason"),
+            model: reported_str(response, "/model"),
+            latency_ms: None,
+        },
+        tool_calls,
+    })
+}
+
+/// Tool calls among Anthropic Messages API content blocks
+pub(crate) fn anthropic_tool_calls(blocks: &[Value]) -> Vec<ToolCall> {
+    blocks
+        .iter()
+        .filter(|block| block.get("type").and_then(Value::as_str) == Some("tool_use"))
+        .filter_map(|block| {
+            Some(ToolCall {
+                id: reported_str(block, "/id").unwrap_or_default(),
+                name: reported_str(block, "/name")?,
+                arguments: block.get("input").cloned().unwrap_or_else(|| json!({})),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weather() -> ToolSpec {
+        ToolSpec::new(
+            "get_weather",
+            "Current weather in a city",
+            json!({
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"],
+            }),
+        )
+    }
+
+    #[test]
+    fn test_schema_mapping() {
+        let tool = weather();
+        assert_eq!(tool.to_openai()["type"], "function");
+        assert_eq!(tool.to_openai()["function"]["name"], "get_weather");
+        assert_eq!(tool.to_openai()["function"]["parameters"], tool.parameters);
+        assert_eq!(tool.to_anthropic()["name"], "get_weather");
+        assert_eq!(tool.to_anthropic()["input_schema"], tool.parameters);
+    }
+
+    #[test]
+    fn test_parses_openai_tool_calls() {
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" },
+                    }],
+                },
+                "finish_reason": "tool_calls",
+            }],
+            "usage": { "prompt_tokens": 50, "completion_tokens": 12 },
+        });
+        let parsed = openai_tool_response(&response, "openai/gpt-4o").unwrap();
+        assert!(parsed.has_tool_calls());
+        assert_eq!(parsed.response.text, "");
+        assert_eq!(parsed.response.finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(parsed.response.prompt_tokens, Some(50));
+        let call = parsed.tool_call("get_weather").unwrap();
+        assert_eq!(call.id, "call_1");
+        assert_eq!(call.arguments, json!({ "city": "Paris" }));
+    }
+
+    #[test]
+    fn test_openai_without_tool_calls_is_plain_text() {
+        let response = json!({
+            "choices": [{ "message": { "role": "assistant", "content": "Sunny" } }],
+        });
+        let parsed = openai_tool_response(&response, "openai/gpt-4o").unwrap();
+        assert!(!parsed.has_tool_calls());
+        assert_eq!(parsed.response.text, "Sunny");
+    }
+
+    #[test]
+    fn test_malformed_openai_arguments_are_an_error() {
+        let response = json!({
+            "choices": [{ "message": { "tool_calls": [{
+                "id": "call_1",
+                "function": { "name": "get_weather", "arguments": "{\"city\":" },
+            }] } }],
+        });
+        let err = openai_tool_response(&response, "openai/gpt-4o").unwrap_err();
+        assert!(err.to_string().contains("get_weather"));
+    }
+
+    #[test]
+    fn test_parses_anthropic_tool_use_blocks() {
+        let blocks = [
+            json!({ "type": "text", "text": "Let me check." }),
+            json!({
+                "type": "tool_use",
+                "id": "toolu_1",
+                "name": "get_weather",
+                "input": { "city": "Paris" },
+            }),
+        ];
+        let calls = anthropic_tool_calls(&blocks);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, "toolu_1");
+        assert_eq!(calls[0].arguments, json!({ "city": "Paris" }));
+    }
+}
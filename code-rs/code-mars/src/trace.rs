@@ -0,0 +1,587 @@
+//! JSON Lines traces of runs, for offline analysis and replay.
+//!
+//! A [`TraceWriter`] appends one JSON object per line to a file: every
+//! [`MarsEvent`](crate::MarsEvent) of a run as a [`MarsEventRecord`], and
+//! every provider call made through a [`TracingProvider`] with its request
+//! and response (or error). The coordinator writes one when
+//! `MarsConfig::trace_path` is set.
+//!
+//! With redaction on, prompts, system prompts and response texts are
+//! replaced by their length, so traces can be shared without their
+//! contents; redacted calls cannot be replayed. With the `test-utils`
+//! feature, `Cassette::from_trace` turns the calls of an unredacted trace
+//! into a cassette for a `ReplayProvider`.
+
+use crate::model_router::{CompletionResponse, LLMProvider, ModelStream};
+use crate::provider_config::MarsPhase;
+use crate::structured::ResponseFormat;
+use crate::tools::{ToolAwareResponse, ToolSpec};
+use crate::types::MarsEventRecord;
+use crate::{MarsError, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// A provider call, identified by its method and arguments
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct RecordedRequest {
+    /// Provider method called (`complete`, `complete_n`, `stream`, ...)
+    pub method: String,
+    /// User prompt
+    #[serde(default)]
+    pub prompt: String,
+    /// System prompt, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Sampling temperature, if one was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Number of completions requested by `complete_n`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<usize>,
+    /// Response format requested by `complete_structured`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<ResponseFormat>,
+    /// Tools offered by `complete_with_tools`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolSpec>,
+    /// Texts passed to `embed`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub texts: Vec<String>,
+}
+
+impl RecordedRequest {
+    /// Request to `method` with a prompt
+    pub(crate) fn new(method: &str, prompt: &str, system_prompt: Option<&str>) -> Self {
+        Self {
+            method: method.to_string(),
+            prompt: prompt.to_string(),
+            system_prompt: system_prompt.map(str::to_string),
+            ..Self::default()
+        }
+    }
+}
+
+/// What a recorded call returned
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedResponse {
+    /// A single completion (also used for streams)
+    Completion(CompletionResponse),
+    /// The completions of a `complete_n` call
+    Completions {
+        /// Completions, in order
+        completions: Vec<CompletionResponse>,
+    },
+    /// A completion that may call tools
+    ToolCalls(ToolAwareResponse),
+    /// Embedding vectors, one per input text
+    Embeddings {
+        /// Vectors, in input order
+        embeddings: Vec<Vec<f32>>,
+    },
+}
+
+/// A provider call as written to a trace
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TracedCall {
+    /// Run the call was made in (empty outside a run)
+    #[serde(default)]
+    pub run_id: String,
+    /// When the call finished
+    pub timestamp: DateTime<Utc>,
+    /// Phase the call was made for, if known
+    #[serde(default)]
+    pub phase: Option<MarsPhase>,
+    /// Name of the provider called
+    pub provider: String,
+    /// Name of the model called
+    pub model: String,
+    /// The call
+    pub request: RecordedRequest,
+    /// What it returned, if it succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<RecordedResponse>,
+    /// Why it failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Whether prompts and response texts were replaced by their length
+    #[serde(default)]
+    pub redacted: bool,
+}
+
+impl TracedCall {
+    /// Replace prompts and response texts by their length
+    fn redact(&mut self) {
+        self.request.prompt = redacted(&self.request.prompt);
+        if let Some(system_prompt) = &mut self.request.system_prompt {
+            *system_prompt = redacted(system_prompt);
+        }
+        for text in &mut self.request.texts {
+            *text = redacted(text);
+        }
+        match &mut self.response {
+            Some(RecordedResponse::Completion(response)) => {
+                response.text = redacted(&response.text);
+            }
+            Some(RecordedResponse::Completions { completions }) => {
+                for response in completions {
+                    response.text = redacted(&response.text);
+                }
+            }
+            Some(RecordedResponse::ToolCalls(response)) => {
+                response.response.text = redacted(&response.response.text);
+                for call in &mut response.tool_calls {
+                    call.arguments =
+                        serde_json::Value::String(redacted(&call.arguments.to_string()));
+                }
+            }
+            Some(RecordedResponse::Embeddings { .. }) | None => {}
+        }
+        self.redacted = true;
+    }
+}
+
+fn redacted(text: &str) -> String {
+    format!("[redacted {} chars]", text.chars().count())
+}
+
+/// One line of a trace
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TraceEntry {
+    /// An event of a run
+    Event(MarsEventRecord),
+    /// A provider call
+    Call(Box<TracedCall>),
+}
+
+/// Appends [`TraceEntry`] lines to a file
+///
+/// Writes are synchronous and flushed per line, so a trace is complete up
+/// to the last entry even if the process dies. A failed write is logged and
+/// does not fail the run.
+pub struct TraceWriter {
+    path: PathBuf,
+    file: Mutex<File>,
+    redact: bool,
+    /// Run that calls are currently made in
+    run_id: Mutex<String>,
+}
+
+impl TraceWriter {
+    /// Append to the trace at `path`, creating it if needed
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| {
+                MarsError::StorageError(format!("Failed to open trace {}: {e}", path.display()))
+            })?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            redact: false,
+            run_id: Mutex::new(String::new()),
+        })
+    }
+
+    /// Replace prompts and response texts of calls by their length
+    pub fn with_redaction(mut self, redact: bool) -> Self {
+        self.redact = redact;
+        self
+    }
+
+    /// File the trace is written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stamp calls traced from now on with `run_id`
+    pub fn start_run(&self, run_id: &str) {
+        *self.run_id.lock().unwrap_or_else(PoisonError::into_inner) = run_id.to_string();
+    }
+
+    /// Append an event
+    pub fn record_event(&self, record: MarsEventRecord) {
+        self.write(&TraceEntry::Event(record));
+    }
+
+    /// Append a call to `provider` and its outcome
+    pub fn record_call(
+        &self,
+        provider: &dyn LLMProvider,
+        phase: Option<MarsPhase>,
+        request: RecordedRequest,
+        outcome: std::result::Result<RecordedResponse, String>,
+    ) {
+        let (response, error) = match outcome {
+            Ok(response) => (Some(response), None),
+            Err(error) => (None, Some(error)),
+        };
+        let mut call = TracedCall {
+            run_id: self
+                .run_id
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+            timestamp: Utc::now(),
+            phase,
+            provider: provider.provider_name().to_string(),
+            model: provider.model_name().to_string(),
+            request,
+            response,
+            error,
+            redacted: false,
+        };
+        if self.redact {
+            call.redact();
+        }
+        self.write(&TraceEntry::Call(Box::new(call)));
+    }
+
+    /// Append `entry` as one line
+    pub fn write(&self, entry: &TraceEntry) {
+        let line = match serde_json::to_string(entry) {
+            Ok(json) => json + "\n",
+            Err(e) => {
+                tracing::warn!("Failed to serialize trace entry: {e}");
+                return;
+            }
+        };
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(e) = file.write_all(line.as_bytes()).and_then(|()| file.flush()) {
+            tracing::warn!("Failed to write trace {}: {e}", self.path.display());
+        }
+    }
+
+    /// Read every entry of the trace at `path`
+    pub fn read(path: impl AsRef<Path>) -> Result<Vec<TraceEntry>> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            MarsError::StorageError(format!("Failed to read trace {}: {e}", path.display()))
+        })?;
+        contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    MarsError::StorageError(format!(
+                        "Invalid trace {} line {}: {e}",
+                        path.display(),
+                        index + 1
+                    ))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Provider wrapper that writes every call to a [`TraceWriter`]
+pub struct TracingProvider {
+    inner: Arc<dyn LLMProvider>,
+    trace: Arc<TraceWriter>,
+    phase: Option<MarsPhase>,
+}
+
+impl TracingProvider {
+    /// Trace calls to `inner` in `trace`
+    pub fn new(inner: Arc<dyn LLMProvider>, trace: Arc<TraceWriter>) -> Self {
+        Self {
+            inner,
+            trace,
+            phase: None,
+        }
+    }
+
+    /// Label calls with the phase they are made for
+    pub fn with_phase(mut self, phase: MarsPhase) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    /// Trace the outcome of a call and pass it on
+    fn record<T>(
+        &self,
+        request: RecordedRequest,
+        result: Result<T>,
+        response: impl FnOnce(&T) -> RecordedResponse,
+    ) -> Result<T> {
+        let outcome = match &result {
+            Ok(value) => Ok(response(value)),
+            Err(e) => Err(e.to_string()),
+        };
+        self.trace
+            .record_call(self.inner.as_ref(), self.phase, request, outcome);
+        result
+    }
+}
+
+#[async_trait]
+impl LLMProvider for TracingProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        let result = self.inner.complete(prompt, system_prompt).await;
+        self.record(
+            RecordedRequest::new("complete", prompt, system_prompt),
+            result,
+            |response| RecordedResponse::Completion(response.clone()),
+        )
+    }
+
+    async fn complete_with_temperature(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        temperature: f32,
+    ) -> Result<CompletionResponse> {
+        let result = self
+            .inner
+            .complete_with_temperature(prompt, system_prompt, temperature)
+            .await;
+        let request = RecordedRequest {
+            temperature: Some(temperature),
+            ..RecordedRequest::new("complete_with_temperature", prompt, system_prompt)
+        };
+        self.record(request, result, |response| {
+            RecordedResponse::Completion(response.clone())
+        })
+    }
+
+    async fn complete_n(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        n: usize,
+        temperature: Option<f32>,
+    ) -> Result<Vec<CompletionResponse>> {
+        let result = self
+            .inner
+            .complete_n(prompt, system_prompt, n, temperature)
+            .await;
+        let request = RecordedRequest {
+            temperature,
+            n: Some(n),
+            ..RecordedRequest::new("complete_n", prompt, system_prompt)
+        };
+        self.record(request, result, |completions| {
+            RecordedResponse::Completions {
+                completions: completions.clone(),
+            }
+        })
+    }
+
+    /// Streams are traced once fully received, or when they fail
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let request = RecordedRequest::new("stream", prompt, system_prompt);
+        let stream = match self.inner.stream(prompt, system_prompt).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.trace.record_call(
+                    self.inner.as_ref(),
+                    self.phase,
+                    request,
+                    Err(e.to_string()),
+                );
+                return Err(e);
+            }
+        };
+        let inner = Arc::clone(&self.inner);
+        let trace = Arc::clone(&self.trace);
+        let phase = self.phase;
+        Ok(stream.on_end(move |outcome| {
+            let outcome = outcome
+                .map(|text| RecordedResponse::Completion(CompletionResponse::new(text)))
+                .map_err(ToString::to_string);
+            trace.record_call(inner.as_ref(), phase, request, outcome);
+        }))
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let result = self.inner.embed(texts).await;
+        let request = RecordedRequest {
+            method: "embed".to_string(),
+            texts: texts.to_vec(),
+            ..RecordedRequest::default()
+        };
+        self.record(request, result, |embeddings| RecordedResponse::Embeddings {
+            embeddings: embeddings.clone(),
+        })
+    }
+
+    async fn complete_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        format: &ResponseFormat,
+    ) -> Result<CompletionResponse> {
+        let result = self
+            .inner
+            .complete_structured(prompt, system_prompt, format)
+            .await;
+        let request = RecordedRequest {
+            format: Some(format.clone()),
+            ..RecordedRequest::new("complete_structured", prompt, system_prompt)
+        };
+        self.record(request, result, |response| {
+            RecordedResponse::Completion(response.clone())
+        })
+    }
+
+    async fn complete_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        tools: &[ToolSpec],
+    ) -> Result<ToolAwareResponse> {
+        let result = self
+            .inner
+            .complete_with_tools(prompt, system_prompt, tools)
+            .await;
+        let request = RecordedRequest {
+            tools: tools.to_vec(),
+            ..RecordedRequest::new("complete_with_tools", prompt, system_prompt)
+        };
+        self.record(request, result, |response| {
+            RecordedResponse::ToolCalls(response.clone())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MarsEvent;
+
+    /// Echoes prompts, or fails for prompts starting with "fail"
+    struct Echo;
+
+    #[async_trait]
+    impl LLMProvider for Echo {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            if prompt.starts_with("fail") {
+                return Err(MarsError::ClientError("boom".to_string()));
+            }
+            Ok(CompletionResponse::new(format!("echo: {prompt}")))
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "echo"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_traces_events_and_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.jsonl");
+        let trace = Arc::new(TraceWriter::open(&path).unwrap());
+        trace.start_run("run-1");
+        trace.record_event(MarsEventRecord::new(
+            "run-1",
+            0,
+            MarsEvent::VerificationStarted,
+        ));
+
+        let provider = TracingProvider::new(Arc::new(Echo), Arc::clone(&trace))
+            .with_phase(MarsPhase::Exploration);
+        provider.complete("2+2?", Some("system")).await.unwrap();
+        assert!(provider.complete("fail", None).await.is_err());
+        let streamed = provider.stream("hi", None).await.unwrap();
+        assert_eq!(
+            streamed.collect_chunks().await.unwrap().concat(),
+            "echo: hi"
+        );
+
+        let entries = TraceWriter::read(&path).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert!(matches!(&entries[0], TraceEntry::Event(record) if record.run_id == "run-1"));
+        let TraceEntry::Call(call) = &entries[1] else {
+            panic!("expected a call, got {:?}", entries[1]);
+        };
+        assert_eq!(call.run_id, "run-1");
+        assert_eq!(call.phase, Some(MarsPhase::Exploration));
+        assert_eq!(call.model, "echo");
+        assert_eq!(call.request.system_prompt.as_deref(), Some("system"));
+        assert_eq!(
+            call.response,
+            Some(RecordedResponse::Completion(CompletionResponse::new(
+                "echo: 2+2?"
+            )))
+        );
+        let TraceEntry::Call(failed) = &entries[2] else {
+            panic!("expected a call, got {:?}", entries[2]);
+        };
+        assert!(failed.response.is_none());
+        assert!(failed.error.as_deref().unwrap().contains("boom"));
+
+        // Reopening appends
+        drop(provider);
+        drop(trace);
+        let trace = TraceWriter::open(&path).unwrap();
+        trace.record_event(MarsEventRecord::new(
+            "run-2",
+            0,
+            MarsEvent::VerificationStarted,
+        ));
+        assert_eq!(TraceWriter::read(&path).unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_redacts_prompts_and_responses() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.jsonl");
+        let trace = Arc::new(TraceWriter::open(&path).unwrap().with_redaction(true));
+        let provider = TracingProvider::new(Arc::new(Echo), trace);
+        provider.complete("secret", Some("sys")).await.unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("secret"), "{contents}");
+        let entries = TraceWriter::read(&path).unwrap();
+        let TraceEntry::Call(call) = &entries[0] else {
+            panic!("expected a call, got {:?}", entries[0]);
+        };
+        assert!(call.redacted);
+        assert_eq!(call.request.prompt, "[redacted 6 chars]");
+        assert_eq!(
+            call.request.system_prompt.as_deref(),
+            Some("[redacted 3 chars]")
+        );
+        assert_eq!(
+            call.response,
+            Some(RecordedResponse::Completion(CompletionResponse::new(
+                "[redacted 12 chars]"
+            )))
+        );
+    }
+}
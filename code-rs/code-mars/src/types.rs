@@ -1,8 +1,32 @@
 /// Core types for the MARS (Multi-Agent Reasoning System) implementation.
+use crate::model_router::{CompletionResponse, LLMProvider};
+use crate::provider_config::MarsPhase;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
 use uuid::Uuid;
 
+/// Passes, with no failures, that mark a solution verified unless
+/// `MarsConfig::consensus_threshold` says otherwise
+pub const DEFAULT_CONSENSUS_THRESHOLD: usize = 2;
+
+/// Where a solution came from, for analyzing runs that mix models
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Provenance {
+    /// Provider name, e.g. `openai` (empty if unknown)
+    pub provider: String,
+    /// Model requested from the provider (empty if unknown)
+    pub model: String,
+    /// Model the provider reported serving, e.g. a dated snapshot
+    pub served_model: Option<String>,
+    /// Sampling temperature sent with the request (None for the provider default)
+    pub temperature: Option<f32>,
+    /// Wall-clock latency of the call in milliseconds (None if not measured)
+    pub latency_ms: Option<u64>,
+}
+
 /// A solution generated by an agent.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Solution {
@@ -18,6 +42,19 @@ pub struct Solution {
     pub temperature: f32,
     /// Token count used
     pub token_count: usize,
+    /// Prompt (input) tokens consumed generating this solution
+    #[serde(default)]
+    pub prompt_tokens: usize,
+    /// Completion (output) tokens consumed generating this solution
+    #[serde(default)]
+    pub completion_tokens: usize,
+    /// Whether the token counts are estimated from text lengths because the
+    /// provider reported no usage
+    #[serde(default)]
+    pub usage_estimated: bool,
+    /// Why the provider stopped generating this solution (None if not reported)
+    #[serde(default)]
+    pub finish_reason: Option<String>,
     /// Timestamp when created
     pub created_at: DateTime<Utc>,
     /// Number of verification passes this solution has received
@@ -30,6 +67,32 @@ pub struct Solution {
     pub verification_score: f32,
     /// Generation phase (initial, aggregated, improved, etc.)
     pub phase: GenerationPhase,
+    /// Structured reasoning steps parsed from the agent output (empty if none were found)
+    #[serde(default)]
+    pub steps: Vec<ReasoningStep>,
+    /// Provider/model that generated this solution (empty if unknown)
+    #[serde(default)]
+    pub model: String,
+    /// Provider, model, sampling and latency of the call that generated
+    /// this solution
+    #[serde(default)]
+    pub provenance: Provenance,
+    /// Elo rating from pairwise comparison (None if not ranked)
+    #[serde(default)]
+    pub elo_rating: Option<f32>,
+    /// Every verification score received, failures included
+    #[serde(default)]
+    pub verification_scores: Vec<f32>,
+    /// Uncertainty of `verification_score` under the configured aggregation
+    #[serde(default)]
+    pub verification_uncertainty: f32,
+    /// IDs of the solutions this one was derived from (empty for initial solutions)
+    #[serde(default)]
+    pub parent_ids: Vec<String>,
+    /// Number of times the stored copy has been updated; an update based on
+    /// an older version is rejected as a conflict
+    #[serde(default)]
+    pub version: u64,
 }
 
 impl Solution {
@@ -48,21 +111,70 @@ impl Solution {
             answer,
             temperature,
             token_count,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            usage_estimated: false,
+            finish_reason: None,
             created_at: Utc::now(),
             verification_passes: 0,
             verification_failures: 0,
             is_verified: false,
             verification_score: 0.0,
             phase: GenerationPhase::Initial,
+            steps: Vec::new(),
+            model: String::new(),
+            provenance: Provenance::default(),
+            elo_rating: None,
+            verification_scores: Vec::new(),
+            verification_uncertainty: 0.0,
+            parent_ids: Vec::new(),
+            version: 0,
         }
     }
 
+    /// Record prompt/completion token usage, keeping `token_count` as their sum
+    pub fn set_token_usage(&mut self, prompt_tokens: usize, completion_tokens: usize) {
+        self.prompt_tokens = prompt_tokens;
+        self.completion_tokens = completion_tokens;
+        self.token_count = prompt_tokens + completion_tokens;
+    }
+
+    /// Record the usage and finish reason of the completion this solution
+    /// came from, estimating usage the provider didn't report (`prompt_len`
+    /// is the length of everything sent, system prompt included)
+    pub fn record_completion(&mut self, response: &CompletionResponse, prompt_len: usize) {
+        self.set_token_usage(
+            response.prompt_tokens_or_estimate(prompt_len),
+            response.completion_tokens_or_estimate(),
+        );
+        self.usage_estimated =
+            response.prompt_tokens.is_none() || response.completion_tokens.is_none();
+        self.finish_reason = response.finish_reason.clone();
+        self.provenance.served_model = response.model.clone();
+        self.provenance.latency_ms = response.latency_ms;
+    }
+
+    /// Record which provider generated this solution, and the sampling
+    /// temperature sent with the request (None for the provider default)
+    pub fn record_provider(&mut self, provider: &dyn LLMProvider, temperature: Option<f32>) {
+        self.model = provider.model_id();
+        self.provenance.provider = provider.provider_name().to_string();
+        self.provenance.model = provider.model_name().to_string();
+        self.provenance.temperature = temperature;
+    }
+
     /// Update verification status
+    ///
+    /// Marks the solution verified after [`DEFAULT_CONSENSUS_THRESHOLD`]
+    /// passes; the coordinator re-derives `is_verified` from the configured
+    /// threshold and quorum.
     pub fn add_verification_pass(&mut self, score: f32) {
         self.verification_passes += 1;
+        self.verification_scores.push(score);
         self.verification_score = (self.verification_score + score) / 2.0;
-        // Mark as verified after 2 consecutive passes
-        if self.verification_passes >= 2 && self.verification_failures == 0 {
+        if self.verification_passes >= DEFAULT_CONSENSUS_THRESHOLD
+            && self.verification_failures == 0
+        {
             self.is_verified = true;
         }
     }
@@ -72,6 +184,315 @@ impl Solution {
         self.verification_failures += 1;
         self.is_verified = false;
     }
+
+    /// Record a verification verdict, keeping the score of failures as well as passes
+    pub fn record_verification(&mut self, is_correct: bool, score: f32) {
+        if is_correct {
+            self.add_verification_pass(score);
+        } else {
+            self.add_verification_failure();
+            self.verification_scores.push(score);
+        }
+    }
+
+    /// Recompute `verification_score` and its uncertainty from all recorded scores
+    ///
+    /// Leaves the score untouched when no scores have been recorded.
+    pub fn apply_score_aggregation(&mut self, method: ScoreAggregation) {
+        let scores = &self.verification_scores;
+        if scores.is_empty() {
+            return;
+        }
+
+        let n = scores.len() as f32;
+        let mean = scores.iter().sum::<f32>() / n;
+        let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / n;
+        let standard_error = (variance / n).sqrt();
+
+        let (score, uncertainty) = match method {
+            ScoreAggregation::Mean => (mean, standard_error),
+            ScoreAggregation::Min => (
+                scores.iter().copied().fold(f32::INFINITY, f32::min),
+                standard_error,
+            ),
+            ScoreAggregation::BetaPosterior => {
+                // Each score is a fractional success under a uniform Beta(1, 1) prior
+                let alpha = 1.0 + scores.iter().sum::<f32>();
+                let beta = 1.0 + scores.iter().map(|s| 1.0 - s).sum::<f32>();
+                let total = alpha + beta;
+                let posterior_mean = alpha / total;
+                let posterior_variance = alpha * beta / (total * total * (total + 1.0));
+                (posterior_mean, posterior_variance.sqrt())
+            }
+        };
+
+        self.verification_score = score;
+        self.verification_uncertainty = uncertainty;
+    }
+
+    /// Hash of the reasoning and answer, ignoring differences in whitespace
+    pub fn content_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [&self.reasoning, &self.answer] {
+            let normalized = part.split_whitespace().collect::<Vec<_>>().join(" ");
+            hasher.update((normalized.len() as u64).to_le_bytes());
+            hasher.update(normalized.as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Fold a duplicate of this solution into it
+    ///
+    /// Verification passes, failures and scores are combined so the pair
+    /// counts once, with the score becoming the mean of all recorded scores;
+    /// the result is verified with `consensus_threshold` passes and no
+    /// failures. Token usage is added up and the duplicate's parents join
+    /// this solution's lineage.
+    pub fn merge_duplicate(&mut self, duplicate: &Solution, consensus_threshold: usize) {
+        self.verification_passes += duplicate.verification_passes;
+        self.verification_failures += duplicate.verification_failures;
+        self.is_verified =
+            self.verification_passes >= consensus_threshold && self.verification_failures == 0;
+        if !duplicate.verification_scores.is_empty() {
+            self.verification_scores
+                .extend_from_slice(&duplicate.verification_scores);
+            self.verification_score = self.verification_scores.iter().sum::<f32>()
+                / self.verification_scores.len() as f32;
+        }
+
+        self.prompt_tokens += duplicate.prompt_tokens;
+        self.completion_tokens += duplicate.completion_tokens;
+        self.token_count += duplicate.token_count;
+
+        for parent_id in &duplicate.parent_ids {
+            if parent_id != &self.id && !self.parent_ids.contains(parent_id) {
+                self.parent_ids.push(parent_id.clone());
+            }
+        }
+    }
+}
+
+/// A solution together with the solutions it was derived from, recursively
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DerivationTree {
+    /// The solution at this node
+    pub solution: Solution,
+    /// Trees of the solutions listed in `solution.parent_ids` that could be found
+    pub parents: Vec<DerivationTree>,
+}
+
+impl DerivationTree {
+    /// Reconstruct the derivation tree of `id` from a set of solutions
+    ///
+    /// Parents missing from `solutions` are left out, and a parent already on
+    /// the current path is not expanded again, so malformed cycles terminate.
+    /// A solution shared by several descendants appears under each of them.
+    pub fn build(id: &str, solutions: &[Solution]) -> Option<Self> {
+        let by_id: HashMap<&str, &Solution> = solutions
+            .iter()
+            .map(|solution| (solution.id.as_str(), solution))
+            .collect();
+        Self::expand(id, &by_id, &mut Vec::new())
+    }
+
+    fn expand<'a>(
+        id: &'a str,
+        by_id: &HashMap<&'a str, &'a Solution>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Self> {
+        if path.contains(&id) {
+            return None;
+        }
+        let solution = *by_id.get(id)?;
+
+        path.push(id);
+        let parents = solution
+            .parent_ids
+            .iter()
+            .filter_map(|parent_id| Self::expand(parent_id, by_id, path))
+            .collect();
+        path.pop();
+
+        Some(Self {
+            solution: solution.clone(),
+            parents,
+        })
+    }
+
+    /// Every solution in the tree, each listed once, root first
+    pub fn solutions(&self) -> Vec<&Solution> {
+        let mut seen = HashSet::new();
+        let mut ordered = Vec::new();
+        let mut queue = VecDeque::from([self]);
+        while let Some(node) = queue.pop_front() {
+            if seen.insert(node.solution.id.as_str()) {
+                ordered.push(&node.solution);
+            }
+            queue.extend(node.parents.iter());
+        }
+        ordered
+    }
+
+    /// Number of derivation steps on the longest path to an initial solution
+    pub fn depth(&self) -> usize {
+        self.parents
+            .iter()
+            .map(|parent| parent.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// How repeated verification scores are combined into a solution's score
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ScoreAggregation {
+    /// Arithmetic mean of all scores
+    #[default]
+    Mean,
+    /// Lowest score received (most pessimistic verifier wins)
+    Min,
+    /// Beta-posterior mean, which shrinks sparsely sampled solutions toward 0.5
+    BetaPosterior,
+}
+
+/// How RSA aggregation picks the parents it recombines each loop
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ParentSelection {
+    /// Uniformly random parents
+    #[default]
+    Random,
+    /// The highest-scoring parents (pure exploitation)
+    TopK,
+    /// Winners of binary tournaments on score
+    Tournament,
+    /// Sampled with probability proportional to score
+    Roulette,
+    /// Parents that are as dissimilar from each other as possible (exploration)
+    DiversityAware,
+}
+
+/// How best-of-N aggregation scores its samples
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BestOfNScorer {
+    /// Score each sample with the built-in verifier
+    #[default]
+    Verifier,
+    /// Ask the model for a single reward score per sample (one call, cheaper)
+    RewardPrompt,
+}
+
+/// Tree policy MCTS uses to pick which child to descend into
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MctsSelectionPolicy {
+    /// Upper confidence bound: mean value plus a visit-count exploration bonus
+    #[default]
+    Ucb1,
+    /// AlphaZero-style PUCT: the exploration bonus is weighted by each
+    /// child's prior, so likely actions are tried first
+    Puct,
+    /// Sample each child's value from its Beta posterior and take the best;
+    /// behaves well with very few simulations
+    Thompson,
+}
+
+/// Where the workspace keeps solutions and verifier feedback
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum WorkspaceBackend {
+    /// In memory; lost when the process exits
+    #[default]
+    InMemory,
+    /// SQLite database file (requires the `sqlite` feature)
+    Sqlite(std::path::PathBuf),
+    /// sled database directory (requires the `sled` feature)
+    Sled(std::path::PathBuf),
+}
+
+/// How the workspace collapses duplicate solutions as they are added
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum SolutionDedup {
+    /// Keep every solution
+    #[default]
+    Disabled,
+    /// Merge solutions whose reasoning and answer match, ignoring whitespace
+    ContentHash,
+    /// Also merge solutions with the same answer whose words overlap at least
+    /// this much (Jaccard similarity in 0.0-1.0)
+    Fuzzy(f32),
+}
+
+/// Which solutions the workspace drops once it reaches its size limit
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Lowest-scoring unverified solution first (least recently used among
+    /// equal scores), then the least recently used verified solution
+    #[default]
+    LowestScoreUnverified,
+    /// Least recently added, read or updated solution
+    LeastRecentlyUsed,
+}
+
+/// Action-generation temperature by MCTS tree depth
+///
+/// Depth 0 is the root. Shallow levels typically run hot for diverse
+/// proposals and deeper levels cool down for focused completion.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum MctsTemperatureSchedule {
+    /// The base generation temperature at every depth
+    #[default]
+    Constant,
+    /// Linear from `start` at the root to `end` at `depth` and below
+    Linear {
+        /// Temperature at the root
+        start: f32,
+        /// Temperature at `depth` and deeper
+        end: f32,
+        /// Depth at which `end` is reached
+        depth: usize,
+    },
+    /// One temperature per depth; the last entry applies to deeper levels
+    PerDepth(Vec<f32>),
+}
+
+impl MctsTemperatureSchedule {
+    /// Temperature for generating actions at `depth`
+    pub fn temperature_at(&self, depth: usize, base: f32) -> f32 {
+        match self {
+            Self::Constant => base,
+            Self::Linear {
+                start,
+                end,
+                depth: end_depth,
+            } => {
+                if *end_depth == 0 || depth >= *end_depth {
+                    *end
+                } else {
+                    start + (end - start) * depth as f32 / *end_depth as f32
+                }
+            }
+            Self::PerDepth(temperatures) => temperatures
+                .get(depth)
+                .or(temperatures.last())
+                .copied()
+                .unwrap_or(base),
+        }
+    }
+}
+
+/// A single step of structured reasoning within a solution
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReasoningStep {
+    /// 1-based position of the step within the reasoning chain
+    pub index: usize,
+    /// The claim made in this step
+    pub claim: String,
+    /// Justification supporting the claim
+    pub justification: String,
+    /// Intermediate result produced by this step, if any
+    pub intermediate_result: Option<String>,
 }
 
 /// Phase in which the solution was generated
@@ -85,6 +506,8 @@ pub enum GenerationPhase {
     Improved,
     /// Synthesized from multiple solutions
     Synthesized,
+    /// A plan for solving the problem rather than an answer
+    Planned,
 }
 
 /// Result of verifying a solution
@@ -102,8 +525,27 @@ pub struct VerificationResult {
     pub completeness_feedback: String,
     /// Feedback on rigor
     pub rigor_feedback: String,
+    /// Rubric score for correctness (0.0-1.0)
+    #[serde(default)]
+    pub correctness_score: f32,
+    /// Rubric score for completeness (0.0-1.0)
+    #[serde(default)]
+    pub completeness_score: f32,
+    /// Rubric score for rigor (0.0-1.0)
+    #[serde(default)]
+    pub rigor_score: f32,
+    /// Free-form critique from the verifier describing what to fix
+    #[serde(default)]
+    pub critique: String,
+    /// First of the solution's numbered reasoning steps the verifier found
+    /// wrong (None if it named none)
+    #[serde(default)]
+    pub failed_step: Option<usize>,
     /// Verifying agent ID
     pub verifying_agent_id: String,
+    /// Provider/model that produced the verdict (empty if not model-based)
+    #[serde(default)]
+    pub verifier_model: String,
     /// Timestamp of verification
     pub verified_at: DateTime<Utc>,
 }
@@ -123,10 +565,34 @@ impl VerificationResult {
             correctness_feedback: String::new(),
             completeness_feedback: String::new(),
             rigor_feedback: String::new(),
+            correctness_score: 0.0,
+            completeness_score: 0.0,
+            rigor_score: 0.0,
+            critique: String::new(),
+            failed_step: None,
             verifying_agent_id,
+            verifier_model: String::new(),
             verified_at: Utc::now(),
         }
     }
+
+    /// Combine the critique and per-criterion feedback into one actionable summary
+    pub fn feedback_summary(&self) -> String {
+        let mut parts = Vec::new();
+        for (label, feedback) in [
+            ("Correctness", &self.correctness_feedback),
+            ("Completeness", &self.completeness_feedback),
+            ("Rigor", &self.rigor_feedback),
+        ] {
+            if !feedback.is_empty() {
+                parts.push(format!("{label}: {feedback}"));
+            }
+        }
+        if !self.critique.is_empty() {
+            parts.push(self.critique.clone());
+        }
+        parts.join("\n")
+    }
 }
 
 /// Aggregation result combining multiple solutions
@@ -155,6 +621,123 @@ pub enum AggregationMethod {
     MixtureOfAgents,
     /// Monte Carlo Tree Search - explores reasoning tree with UCB selection
     MonteCarloTreeSearch,
+    /// Genetic algorithm - evolves solutions with LLM crossover and mutation
+    GeneticAlgorithm,
+    /// Beam search - keeps the top-k partial solutions and expands them
+    BeamSearch,
+    /// Plan-and-solve - plans from the best solutions, then executes the plan
+    PlanAndSolve,
+}
+
+/// How solutions are checked during the verification phase
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum VerificationMode {
+    /// Cross-agent LLM verification against a rubric
+    #[default]
+    Llm,
+    /// Execute extracted code blocks, optionally against test cases
+    Execution,
+    /// Compare solutions head to head and rank them by Elo rating
+    Pairwise,
+    /// Search for counterexamples; verified only when none plausible is found
+    Adversarial,
+    /// Execute solutions against unit tests generated from the query alone
+    GeneratedTests,
+}
+
+/// Shape the final answer is expected to take; see
+/// [`MarsConfig::answer_type`](crate::config::MarsConfig::answer_type)
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnswerType {
+    /// Any text; no format is asked for
+    #[default]
+    FreeText,
+    /// A single number, given on a final `ANSWER:` line
+    Numeric,
+    /// A fenced code block tagged with its language
+    Code,
+    /// The letter of one option, given on a final `ANSWER:` line
+    MultipleChoice,
+}
+
+/// Final answer parsed as its [`AnswerType`]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TypedAnswer {
+    /// The answer text, trimmed
+    Text {
+        /// Answer text
+        text: String,
+    },
+    /// A number
+    Numeric {
+        /// Value of the number
+        value: f64,
+    },
+    /// The last code block of the answer
+    Code {
+        /// Language tag of the block (lowercased, may be empty)
+        language: String,
+        /// Source code inside the fence
+        code: String,
+    },
+    /// An option letter
+    Choice {
+        /// Uppercase letter of the chosen option
+        letter: char,
+    },
+}
+
+/// Bundled settings for a common task type; see
+/// [`MarsConfig::preset`](crate::config::MarsConfig::preset)
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Preset {
+    /// Competition-style math: low temperatures, RSA aggregation and strict
+    /// LLM verification
+    Math,
+    /// Programming tasks: solutions are run against generated unit tests
+    Coding,
+    /// Open-ended writing: high temperatures, MOA synthesis and pairwise
+    /// ranking instead of pass/fail checks
+    Creative,
+    /// Analysis and fact-finding: layered MOA and adversarial verification
+    Research,
+}
+
+impl Preset {
+    /// Every preset, in declaration order
+    pub const ALL: [Preset; 4] = [
+        Preset::Math,
+        Preset::Coding,
+        Preset::Creative,
+        Preset::Research,
+    ];
+}
+
+/// How a run picks its settings from the query; see [`crate::classifier`]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum QueryClassification {
+    /// Use the config as is; small `max_tokens` limits still switch to
+    /// lightweight mode
+    #[default]
+    Off,
+    /// Classify with keyword and length heuristics (no provider call)
+    Heuristic,
+    /// Ask the exploration provider, falling back to the heuristics
+    Model,
+}
+
+/// Rule deciding whether a solution's verification passes reach consensus
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum QuorumRule {
+    /// Every verification pass must succeed
+    #[default]
+    All,
+    /// More passes than failures
+    Majority,
+    /// Failures are tolerated as long as enough passes succeed
+    Any,
 }
 
 /// Strategy extracted from a solution
@@ -193,25 +776,182 @@ pub struct MarsOutput {
     pub iterations: usize,
     /// Total tokens used
     pub total_tokens: usize,
+    /// Total prompt (input) tokens across all solutions
+    ///
+    /// Counts the providers did not report are estimated at about four bytes
+    /// per token; see `usage_estimated`.
+    #[serde(default)]
+    pub total_prompt_tokens: usize,
+    /// Total completion (output) tokens across all solutions, estimated like
+    /// `total_prompt_tokens` where not reported
+    #[serde(default)]
+    pub total_completion_tokens: usize,
+    /// Whether any solution's token counts are estimates
+    #[serde(default)]
+    pub usage_estimated: bool,
+    /// Dollars spent on provider calls, by phase and provider
+    #[serde(default)]
+    pub cost: crate::cost::CostSummary,
+    /// Tags the run was started with
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// ID of the run, matching the `run_id` of its [`MarsEventRecord`]s
+    #[serde(default)]
+    pub run_id: String,
+    /// Whether the cost budget cut the search short before synthesis
+    #[serde(default)]
+    pub budget_truncated: bool,
+    /// The answer parsed as `MarsConfig::answer_type` (None if it did not parse)
+    #[serde(default)]
+    pub typed_answer: Option<TypedAnswer>,
+    /// The best distinct answers among the candidates, best first (see
+    /// [`RankedAnswer::rank`])
+    #[serde(default)]
+    pub alternatives: Vec<RankedAnswer>,
+    /// Confidence in the answer from 0 to 1 (see [`crate::confidence`])
+    #[serde(default)]
+    pub confidence: f32,
+    /// Strategies in the strategy network at the end of the run, oldest first
+    #[serde(default)]
+    pub strategies: Vec<Strategy>,
+    /// Wall-clock time, provider calls and tokens of each phase, in run order
+    #[serde(default)]
+    pub phase_timings: Vec<PhaseTiming>,
     /// Timestamp when completed
     pub completed_at: DateTime<Utc>,
 }
 
+impl MarsOutput {
+    /// The solution whose answer was returned, if it is in `all_solutions`
+    ///
+    /// Its [`Provenance`] tells which model produced the answer.
+    pub fn final_solution(&self) -> Option<&Solution> {
+        self.all_solutions
+            .iter()
+            .find(|s| s.id == self.final_solution_id)
+    }
+
+    /// Derivation tree of the final solution, reconstructed from `all_solutions`
+    pub fn derivation_tree(&self) -> Option<DerivationTree> {
+        DerivationTree::build(&self.final_solution_id, &self.all_solutions)
+    }
+}
+
+/// Where one phase of a run spent its time and budget
+///
+/// Each improvement iteration gets its own entry. Calls and tokens include
+/// failed calls and estimates for providers that report no usage.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTiming {
+    /// Phase that ran
+    pub phase: MarsPhase,
+    /// When the phase started
+    pub started_at: DateTime<Utc>,
+    /// When the phase ended
+    pub ended_at: DateTime<Utc>,
+    /// Provider calls made during the phase
+    pub llm_calls: u64,
+    /// Prompt and completion tokens used during the phase
+    pub tokens: u64,
+}
+
+impl PhaseTiming {
+    /// Wall-clock time the phase took
+    pub fn duration(&self) -> std::time::Duration {
+        (self.ended_at - self.started_at)
+            .to_std()
+            .unwrap_or_default()
+    }
+}
+
+/// `answer` with whitespace collapsed, so answers that differ only in
+/// whitespace compare equal
+pub(crate) fn answer_key(answer: &str) -> String {
+    answer.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A distinct answer and the solutions that support it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RankedAnswer {
+    /// The answer, as the first supporting solution gave it
+    pub answer: String,
+    /// Highest verification score among the supporting solutions
+    pub score: f32,
+    /// Number of supporting solutions
+    pub votes: usize,
+    /// Supporting solutions that passed verification
+    pub verified_votes: usize,
+    /// IDs of the supporting solutions, in the order given
+    pub solution_ids: Vec<String>,
+}
+
+impl RankedAnswer {
+    /// The `limit` best distinct answers among `solutions`
+    ///
+    /// Answers that differ only in whitespace count as one. They rank by
+    /// score, then votes, then which was given first.
+    pub fn rank(solutions: &[Solution], limit: usize) -> Vec<Self> {
+        let mut ranked: Vec<Self> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for solution in solutions {
+            let key = answer_key(&solution.answer);
+            if key.is_empty() {
+                continue;
+            }
+            let i = *index.entry(key).or_insert_with(|| {
+                ranked.push(Self {
+                    answer: solution.answer.trim().to_string(),
+                    score: solution.verification_score,
+                    votes: 0,
+                    verified_votes: 0,
+                    solution_ids: Vec::new(),
+                });
+                ranked.len() - 1
+            });
+            let entry = &mut ranked[i];
+            entry.score = entry.score.max(solution.verification_score);
+            entry.votes += 1;
+            entry.verified_votes += usize::from(solution.is_verified);
+            entry.solution_ids.push(solution.id.clone());
+        }
+        // A stable sort keeps ties in the order the answers were first given
+        ranked.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.votes.cmp(&a.votes))
+        });
+        ranked.truncate(limit);
+        ranked
+    }
+}
+
 /// Method used to select the final answer
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum SelectionMethod {
     /// Selected via majority voting
     MajorityVoting,
     /// Selected as best verified solution
     BestVerified,
+    /// Highest Elo rating from pairwise comparison
+    PairwiseRanking,
     /// Synthesized from top solutions
     Synthesized,
     /// Manual selection
     Manual,
 }
 
+/// Version of the [`MarsEventRecord`] JSON schema
+///
+/// Adding an event or a field keeps the version; renaming or removing one,
+/// or changing a field's type, bumps it.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
 /// Event emitted during MARS execution for progress tracking
-#[derive(Clone, Debug, Serialize, Deserialize)]
+///
+/// Serializes as an object whose `type` is the variant name in snake_case,
+/// next to the variant's fields.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum MarsEvent {
     /// Initial exploration phase started
     ExplorationStarted { num_agents: usize },
@@ -232,14 +972,67 @@ pub enum MarsEvent {
     AggregationStarted,
     /// Solutions were aggregated
     SolutionsAggregated { result_solution_id: String },
+    /// Beam search expanded a partial solution
+    BeamExpanded {
+        /// Expansion round, starting at 0
+        depth: usize,
+        /// Beam entry that was continued
+        parent_id: String,
+        /// Continuation that was produced
+        solution_id: String,
+        /// Verifier score of the continuation
+        score: f32,
+    },
+    /// MCTS expanded a leaf node
+    NodeExpanded {
+        /// Node indices from the root down to the expanded node
+        path: Vec<usize>,
+        /// Number of children created
+        num_children: usize,
+    },
+    /// MCTS finished a simulation and backpropagated its value
+    SimulationCompleted {
+        /// Simulation number, starting at 0
+        simulation: usize,
+        /// Node indices from the root down to where the rollout started
+        path: Vec<usize>,
+        /// Value of the rollout's final state
+        value: f32,
+        /// Visits of the rollout's start node after backpropagation
+        visits: usize,
+    },
     /// Improvement phase started
     ImprovementStarted { iteration: usize },
     /// Solution was improved
     SolutionImproved { solution_id: String },
+    /// An improvement iteration was undone for not beating its originals
+    ImprovementRolledBack {
+        /// Iteration that was undone
+        iteration: usize,
+        /// IDs of the discarded improved solutions
+        solution_ids: Vec<String>,
+    },
     /// Strategy network phase started
     StrategyNetworkStarted,
     /// Strategy was extracted
     StrategyExtracted { strategy_id: String },
+    /// Overlapping strategies were consolidated
+    StrategiesMerged {
+        /// Number of duplicate strategies removed
+        removed: usize,
+        /// Number of strategies left in the network
+        remaining: usize,
+    },
+    /// Solutions were dropped to keep the workspace within its size limit
+    SolutionsEvicted {
+        /// IDs of the evicted solutions, in eviction order
+        solution_ids: Vec<String>,
+    },
+    /// The cost budget was reached; remaining phases are skipped for synthesis
+    BudgetTruncated {
+        /// Dollars spent when the search stopped
+        spent_usd: f64,
+    },
     /// Synthesis phase started
     SynthesisStarted,
     /// Final answer synthesized
@@ -252,3 +1045,321 @@ pub enum MarsEvent {
     /// Error occurred
     Error { message: String },
 }
+
+impl MarsEvent {
+    /// Phase this event starts, if it starts one
+    pub fn phase_started(&self) -> Option<MarsPhase> {
+        match self {
+            Self::ExplorationStarted { .. } => Some(MarsPhase::Exploration),
+            Self::AggregationStarted => Some(MarsPhase::Aggregation),
+            Self::StrategyNetworkStarted => Some(MarsPhase::StrategyExtraction),
+            Self::VerificationStarted => Some(MarsPhase::Verification),
+            Self::ImprovementStarted { .. } => Some(MarsPhase::Improvement),
+            Self::SynthesisStarted => Some(MarsPhase::Synthesis),
+            _ => None,
+        }
+    }
+
+    /// ID of the solution this event reports as added to the workspace
+    pub fn new_solution_id(&self) -> Option<&str> {
+        match self {
+            Self::SolutionGenerated { solution_id, .. }
+            | Self::SolutionImproved { solution_id }
+            | Self::SolutionsAggregated {
+                result_solution_id: solution_id,
+            } => Some(solution_id),
+            _ => None,
+        }
+    }
+}
+
+/// A [`MarsEvent`] stamped for logging or forwarding to another process
+///
+/// Serializes to one flat JSON object with `schema_version`, `run_id`,
+/// `sequence`, `timestamp` (RFC 3339) and the event's `type` and fields.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MarsEventRecord {
+    /// [`EVENT_SCHEMA_VERSION`] of the producer
+    pub schema_version: u32,
+    /// ID of the run that emitted the event
+    pub run_id: String,
+    /// Position of the event in its run, starting at 0 with no gaps
+    pub sequence: u64,
+    /// When the event was forwarded to subscribers
+    ///
+    /// Records are stamped one at a time after the run emits them, once
+    /// earlier records have been delivered, so a subscriber that is slow to
+    /// receive can delay the stamps of later events. `sequence`, not the
+    /// timestamp, gives the order the events were emitted in.
+    pub timestamp: DateTime<Utc>,
+    /// The event itself
+    #[serde(flatten)]
+    pub event: MarsEvent,
+}
+
+impl MarsEventRecord {
+    /// Stamp `event` as number `sequence` of run `run_id`, forwarded now
+    pub fn new(run_id: impl Into<String>, sequence: u64, event: MarsEvent) -> Self {
+        Self {
+            schema_version: EVENT_SCHEMA_VERSION,
+            run_id: run_id.into(),
+            sequence,
+            timestamp: Utc::now(),
+            event,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scored_solution(scores: &[(bool, f32)]) -> Solution {
+        let mut solution = Solution::new(
+            "agent".to_string(),
+            "reasoning".to_string(),
+            "answer".to_string(),
+            0.5,
+            10,
+        );
+        for (is_correct, score) in scores {
+            solution.record_verification(*is_correct, *score);
+        }
+        solution
+    }
+
+    #[test]
+    fn test_mcts_temperature_schedules() {
+        assert_eq!(
+            MctsTemperatureSchedule::Constant.temperature_at(5, 0.7),
+            0.7
+        );
+
+        let linear = MctsTemperatureSchedule::Linear {
+            start: 1.2,
+            end: 0.2,
+            depth: 4,
+        };
+        assert_eq!(linear.temperature_at(0, 0.7), 1.2);
+        assert!((linear.temperature_at(2, 0.7) - 0.7).abs() < 1e-6);
+        assert_eq!(linear.temperature_at(9, 0.7), 0.2);
+
+        let per_depth = MctsTemperatureSchedule::PerDepth(vec![1.0, 0.5]);
+        assert_eq!(per_depth.temperature_at(0, 0.7), 1.0);
+        assert_eq!(per_depth.temperature_at(3, 0.7), 0.5);
+        assert_eq!(
+            MctsTemperatureSchedule::PerDepth(vec![]).temperature_at(1, 0.7),
+            0.7
+        );
+    }
+
+    #[test]
+    fn test_mean_and_min_aggregation() {
+        let mut solution = scored_solution(&[(true, 0.9), (false, 0.3), (true, 0.6)]);
+        assert_eq!(solution.verification_scores, vec![0.9, 0.3, 0.6]);
+
+        solution.apply_score_aggregation(ScoreAggregation::Mean);
+        assert!((solution.verification_score - 0.6).abs() < 1e-6);
+        assert!(solution.verification_uncertainty > 0.0);
+
+        solution.apply_score_aggregation(ScoreAggregation::Min);
+        assert_eq!(solution.verification_score, 0.3);
+    }
+
+    #[test]
+    fn test_beta_posterior_prefers_well_sampled() {
+        let mut sparse = scored_solution(&[(true, 0.9)]);
+        let mut dense = scored_solution(&[(true, 0.9); 5]);
+        sparse.apply_score_aggregation(ScoreAggregation::BetaPosterior);
+        dense.apply_score_aggregation(ScoreAggregation::BetaPosterior);
+
+        assert!(dense.verification_score > sparse.verification_score);
+        assert!(dense.verification_uncertainty < sparse.verification_uncertainty);
+    }
+
+    #[test]
+    fn test_aggregation_without_scores_is_noop() {
+        let mut solution = scored_solution(&[]);
+        solution.apply_score_aggregation(ScoreAggregation::BetaPosterior);
+        assert_eq!(solution.verification_score, 0.0);
+        assert_eq!(solution.verification_uncertainty, 0.0);
+    }
+
+    fn derived(id: &str, parent_ids: &[&str]) -> Solution {
+        let mut solution = scored_solution(&[]);
+        solution.id = id.to_string();
+        solution.parent_ids = parent_ids.iter().map(|p| p.to_string()).collect();
+        solution
+    }
+
+    #[test]
+    fn test_derivation_tree_shares_ancestors() {
+        let solutions = vec![
+            derived("a", &[]),
+            derived("b", &[]),
+            derived("left", &["a", "b"]),
+            derived("right", &["b", "missing"]),
+            derived("final", &["left", "right"]),
+        ];
+
+        let tree = DerivationTree::build("final", &solutions).unwrap();
+        assert_eq!(tree.depth(), 2);
+        assert_eq!(tree.parents.len(), 2);
+        assert_eq!(tree.parents[1].parents.len(), 1);
+
+        let ids: Vec<_> = tree.solutions().iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["final", "left", "right", "a", "b"]);
+        assert!(DerivationTree::build("missing", &solutions).is_none());
+    }
+
+    #[test]
+    fn test_derivation_tree_stops_on_cycles() {
+        let solutions = vec![derived("x", &["y"]), derived("y", &["x"])];
+
+        let tree = DerivationTree::build("x", &solutions).unwrap();
+        assert_eq!(tree.depth(), 1);
+        assert!(tree.parents[0].parents.is_empty());
+    }
+
+    #[test]
+    fn test_content_hash_ignores_whitespace() {
+        let a = derived("a", &[]);
+        let mut b = derived("b", &[]);
+        b.reasoning = format!("  {}\n", a.reasoning.replace(' ', "\n "));
+        assert_eq!(a.content_hash(), b.content_hash());
+
+        b.answer = "5".to_string();
+        assert_ne!(a.content_hash(), b.content_hash());
+    }
+
+    #[test]
+    fn test_merge_duplicate_counts_once() {
+        let mut kept = scored_solution(&[(true, 0.8)]);
+        kept.token_count = 100;
+        let mut duplicate = scored_solution(&[(true, 0.6), (false, 0.2)]);
+        duplicate.token_count = 50;
+        duplicate.parent_ids = vec![kept.id.clone(), "p".to_string()];
+
+        kept.merge_duplicate(&duplicate, DEFAULT_CONSENSUS_THRESHOLD);
+        assert_eq!(kept.verification_passes, 2);
+        assert_eq!(kept.verification_failures, 1);
+        assert!(!kept.is_verified);
+        assert_eq!(kept.verification_scores, vec![0.8, 0.6, 0.2]);
+        assert!((kept.verification_score - 1.6 / 3.0).abs() < 1e-6);
+        assert_eq!(kept.token_count, 150);
+        assert_eq!(kept.parent_ids, vec!["p".to_string()]);
+    }
+
+    #[test]
+    fn test_record_completion_prefers_reported_usage() {
+        let mut solution = scored_solution(&[]);
+        let reported = CompletionResponse::new("4")
+            .with_usage(30, 2)
+            .with_finish_reason("stop")
+            .with_model("gpt-4o-2024-08-06")
+            .with_latency_ms(850);
+        solution.record_completion(&reported, 400);
+        assert_eq!(
+            solution.provenance.served_model.as_deref(),
+            Some("gpt-4o-2024-08-06")
+        );
+        assert_eq!(solution.provenance.latency_ms, Some(850));
+        assert_eq!(
+            (solution.prompt_tokens, solution.completion_tokens),
+            (30, 2)
+        );
+        assert_eq!(solution.token_count, 32);
+        assert!(!solution.usage_estimated);
+        assert_eq!(solution.finish_reason.as_deref(), Some("stop"));
+
+        // Usage the provider didn't report is estimated from lengths
+        solution.record_completion(&CompletionResponse::new("x".repeat(40)), 400);
+        assert_eq!(
+            (solution.prompt_tokens, solution.completion_tokens),
+            (100, 10)
+        );
+        assert!(solution.usage_estimated);
+        assert_eq!(solution.finish_reason, None);
+        assert_eq!(solution.provenance.latency_ms, None);
+    }
+
+    #[test]
+    fn test_event_record_json_schema() {
+        let record = MarsEventRecord {
+            schema_version: EVENT_SCHEMA_VERSION,
+            run_id: "run-1".to_string(),
+            sequence: 3,
+            timestamp: DateTime::parse_from_rfc3339("2025-01-02T03:04:05Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            event: MarsEvent::SolutionVerified {
+                solution_id: "s1".to_string(),
+                is_correct: true,
+                score: 0.5,
+            },
+        };
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "schema_version": 1,
+                "run_id": "run-1",
+                "sequence": 3,
+                "timestamp": "2025-01-02T03:04:05Z",
+                "type": "solution_verified",
+                "solution_id": "s1",
+                "is_correct": true,
+                "score": 0.5,
+            })
+        );
+        let parsed: MarsEventRecord = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, record);
+
+        let unit = MarsEventRecord::new("run-1", 0, MarsEvent::SynthesisStarted);
+        let json = serde_json::to_value(&unit).unwrap();
+        assert_eq!(json["type"], "synthesis_started");
+        assert_eq!(
+            serde_json::from_value::<MarsEventRecord>(json)
+                .unwrap()
+                .event,
+            MarsEvent::SynthesisStarted
+        );
+    }
+
+    #[test]
+    fn test_rank_answers() {
+        let solution = |answer: &str, score: f32, verified: bool| {
+            let mut solution = Solution::new(
+                "agent".to_string(),
+                "reasoning".to_string(),
+                answer.to_string(),
+                0.5,
+                10,
+            );
+            solution.verification_score = score;
+            solution.is_verified = verified;
+            solution
+        };
+        let solutions = vec![
+            solution("42", 0.6, false),
+            solution("  41 ", 0.9, true),
+            solution("42", 0.8, true),
+            solution("", 1.0, true),
+            solution("40", 0.8, false),
+            solution("4 1", 0.2, false),
+        ];
+
+        let ranked = RankedAnswer::rank(&solutions, 3);
+        let answers: Vec<&str> = ranked.iter().map(|r| r.answer.as_str()).collect();
+        assert_eq!(answers, ["41", "42", "40"]);
+        assert_eq!(ranked[1].votes, 2);
+        assert_eq!(ranked[1].verified_votes, 1);
+        assert_eq!(ranked[1].score, 0.8);
+        assert_eq!(
+            ranked[1].solution_ids,
+            [solutions[0].id.clone(), solutions[2].id.clone()]
+        );
+        assert_eq!(RankedAnswer::rank(&solutions, 5).len(), 4);
+        assert!(RankedAnswer::rank(&solutions, 0).is_empty());
+    }
+}
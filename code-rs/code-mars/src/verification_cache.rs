@@ -0,0 +1,202 @@
+//! Cache of verification results keyed by solution content.
+//!
+//! Improved solutions often re-derive an identical answer, and re-running the
+//! same dataset repeats the same verifications. Results are keyed on a SHA-256
+//! hash of the query, reasoning and answer so duplicates can be replayed
+//! instead of re-verified. The cache lives in memory and can optionally be
+//! persisted to a JSON file between runs.
+
+use crate::types::{Solution, VerificationResult};
+use crate::{MarsError, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Verification results cached by solution content hash
+#[derive(Clone, Default)]
+pub struct VerificationCache {
+    entries: Arc<RwLock<HashMap<String, Vec<VerificationResult>>>>,
+    path: Option<PathBuf>,
+}
+
+impl VerificationCache {
+    /// Create an empty in-memory cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a cache persisted at `path`, loading existing entries if the file exists
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let entries = if path.exists() {
+            let contents = std::fs::read_to_string(&path).map_err(|e| {
+                MarsError::VerificationError(format!(
+                    "Failed to read verification cache {}: {e}",
+                    path.display()
+                ))
+            })?;
+            serde_json::from_str(&contents).map_err(|e| {
+                MarsError::VerificationError(format!(
+                    "Invalid verification cache {}: {e}",
+                    path.display()
+                ))
+            })?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            entries: Arc::new(RwLock::new(entries)),
+            path: Some(path),
+        })
+    }
+
+    /// File the cache is persisted to, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Hash identifying a solution's content for a given query
+    pub fn content_hash(query: &str, solution: &Solution) -> String {
+        let mut hasher = Sha256::new();
+        for part in [query, &solution.reasoning, &solution.answer] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Cached results for a solution, re-targeted to its ID
+    pub async fn get(&self, query: &str, solution: &Solution) -> Option<Vec<VerificationResult>> {
+        let entries = self.entries.read().await;
+        let results = entries.get(&Self::content_hash(query, solution))?;
+        Some(
+            results
+                .iter()
+                .cloned()
+                .map(|mut result| {
+                    result.solution_id = solution.id.clone();
+                    result
+                })
+                .collect(),
+        )
+    }
+
+    /// Cache the verification results for a solution
+    pub async fn insert(&self, query: &str, solution: &Solution, results: Vec<VerificationResult>) {
+        if results.is_empty() {
+            return;
+        }
+        let mut entries = self.entries.write().await;
+        entries.insert(Self::content_hash(query, solution), results);
+    }
+
+    /// Number of cached solutions
+    pub async fn len(&self) -> usize {
+        self.entries.read().await.len()
+    }
+
+    /// Whether the cache is empty
+    pub async fn is_empty(&self) -> bool {
+        self.entries.read().await.is_empty()
+    }
+
+    /// Write the cache to its file (no-op for in-memory caches)
+    pub async fn persist(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let contents = {
+            let entries = self.entries.read().await;
+            serde_json::to_string(&*entries).map_err(|e| {
+                MarsError::VerificationError(format!("Failed to serialize verification cache: {e}"))
+            })?
+        };
+
+        // Write to a sibling file first so a crash never leaves a truncated cache
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, contents).await.map_err(|e| {
+            MarsError::VerificationError(format!(
+                "Failed to write verification cache {}: {e}",
+                tmp_path.display()
+            ))
+        })?;
+        tokio::fs::rename(&tmp_path, path).await.map_err(|e| {
+            MarsError::VerificationError(format!(
+                "Failed to write verification cache {}: {e}",
+                path.display()
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solution(reasoning: &str, answer: &str) -> Solution {
+        Solution::new(
+            "agent".to_string(),
+            reasoning.to_string(),
+            answer.to_string(),
+            0.5,
+            10,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_content_hits_cache() {
+        let cache = VerificationCache::new();
+        let original = solution("2 + 2 = 4", "4");
+        let duplicate = solution("2 + 2 = 4", "4");
+
+        let result = VerificationResult::new(original.id.clone(), true, 0.9, "v".to_string());
+        cache.insert("What is 2+2?", &original, vec![result]).await;
+
+        let cached = cache.get("What is 2+2?", &duplicate).await.unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].solution_id, duplicate.id);
+        assert!(cached[0].is_correct);
+
+        assert!(cache.get("What is 3+1?", &duplicate).await.is_none());
+        assert!(
+            cache
+                .get("What is 2+2?", &solution("2 + 2 = 5", "5"))
+                .await
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_content_hash_separates_fields() {
+        let query = "q";
+        assert_ne!(
+            VerificationCache::content_hash(query, &solution("ab", "c")),
+            VerificationCache::content_hash(query, &solution("a", "bc"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_persistence_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("verification-cache.json");
+        let sol = solution("2 + 2 = 4", "4");
+
+        let cache = VerificationCache::open(&path).unwrap();
+        assert!(cache.is_empty().await);
+        let result = VerificationResult::new(sol.id.clone(), true, 0.9, "v".to_string());
+        cache.insert("q", &sol, vec![result]).await;
+        cache.persist().await.unwrap();
+
+        let reopened = VerificationCache::open(&path).unwrap();
+        assert_eq!(reopened.len().await, 1);
+        assert!(reopened.get("q", &sol).await.is_some());
+    }
+}
@@ -1,59 +1,356 @@
+use crate::LLMProvider;
 use crate::Result;
 /// Verification system for cross-agent solution checking.
-use crate::types::{Solution, VerificationResult};
+use crate::prompts;
+use crate::types::{QuorumRule, Solution, VerificationResult};
+use async_trait::async_trait;
+
+/// Pluggable verifier for domain-specific checks
+///
+/// Implement this to plug linters, theorem provers, schema validators and the
+/// like into the verification phase via
+/// [`MarsCoordinator::with_solution_verifier`](crate::MarsCoordinator::with_solution_verifier).
+#[async_trait]
+pub trait SolutionVerifier: Send + Sync {
+    /// Check a solution to `query` and report a verdict
+    async fn verify(&self, solution: &Solution, query: &str) -> Result<VerificationResult>;
+
+    /// Name used as the verifier identity in results and error messages
+    fn name(&self) -> &str;
+}
+
+#[async_trait]
+impl SolutionVerifier for crate::code_verifier::CodeVerifier {
+    /// A solution without runnable code fails, since the registered check
+    /// cannot be applied to it
+    async fn verify(&self, solution: &Solution, _query: &str) -> Result<VerificationResult> {
+        let result = self.verify_solution(solution, "code-verifier").await?;
+        Ok(result.unwrap_or_else(|| {
+            let mut result = VerificationResult::new(
+                solution.id.clone(),
+                false,
+                0.0,
+                "code-verifier".to_string(),
+            );
+            result.correctness_feedback = "No runnable code block found in solution".to_string();
+            result
+        }))
+    }
+
+    fn name(&self) -> &str {
+        "code-verifier"
+    }
+}
 
 /// Verifier that checks solution correctness
 pub struct Verifier;
 
+/// Overall score at or above which a verdict without an explicit RESULT line counts as correct
+const DEFAULT_PASS_SCORE: f32 = 0.5;
+
+/// Plausibility at or above which a counterexample refutes a solution
+const COUNTEREXAMPLE_PLAUSIBILITY_THRESHOLD: f32 = 0.5;
+
 impl Verifier {
     /// Verify a solution using another agent
     ///
-    /// This simulates cross-agent verification where one agent checks
-    /// another agent's solution for correctness.
+    /// Sends the problem and candidate solution to the provider together with
+    /// the verification rubric (correctness, completeness, rigor), then parses
+    /// the structured verdict into a [`VerificationResult`].
     pub async fn verify_solution(
+        query: &str,
+        solution: &Solution,
+        verifying_agent_id: &str,
+        provider: &dyn LLMProvider,
+    ) -> Result<VerificationResult> {
+        let response = provider
+            .complete(
+                &Self::verification_prompt(query, solution),
+                Some(prompts::VERIFICATION_SYSTEM_PROMPT),
+            )
+            .await?;
+
+        let mut result = Self::parse_verdict(&response.text, &solution.id, verifying_agent_id);
+        result.verifier_model = provider.model_id();
+        Ok(result)
+    }
+
+    /// Verify a solution with the verifier sampling at the given temperature
+    pub async fn verify_solution_with_temperature(
+        query: &str,
+        solution: &Solution,
+        verifying_agent_id: &str,
+        temperature: f32,
+        provider: &dyn LLMProvider,
+    ) -> Result<VerificationResult> {
+        Self::verify_with_rubric(
+            query,
+            solution,
+            verifying_agent_id,
+            temperature,
+            prompts::VERIFICATION_SYSTEM_PROMPT,
+            provider,
+        )
+        .await
+    }
+
+    /// Verify a solution against a custom verification system prompt
+    ///
+    /// `rubric` must ask for the same `RESULT:`/`SCORE:` verdict lines as
+    /// [`prompts::VERIFICATION_SYSTEM_PROMPT`].
+    pub async fn verify_with_rubric(
+        query: &str,
+        solution: &Solution,
+        verifying_agent_id: &str,
+        temperature: f32,
+        rubric: &str,
+        provider: &dyn LLMProvider,
+    ) -> Result<VerificationResult> {
+        let response = provider
+            .complete_with_temperature(
+                &Self::verification_prompt(query, solution),
+                Some(rubric),
+                temperature,
+            )
+            .await?;
+
+        let mut result = Self::parse_verdict(&response.text, &solution.id, verifying_agent_id);
+        result.verifier_model = provider.model_id();
+        Ok(result)
+    }
+
+    /// Verify a solution by asking the provider to construct a counterexample
+    ///
+    /// The solution passes only when no plausible counterexample is found.
+    pub async fn verify_adversarially(
+        query: &str,
         solution: &Solution,
         verifying_agent_id: &str,
+        temperature: f32,
+        provider: &dyn LLMProvider,
     ) -> Result<VerificationResult> {
-        // In a real implementation, this would:
-        // 1. Call the verifying agent to evaluate the solution
-        // 2. Parse the verification response
-        // 3. Extract correctness, score, and feedback
-        //
-        // For now, we'll create a placeholder that always returns a positive verification
-        // TODO: Integrate with code-core's ModelClient for actual verification
+        let response = provider
+            .complete_with_temperature(
+                &Self::verification_prompt(query, solution),
+                Some(prompts::ADVERSARIAL_VERIFICATION_PROMPT),
+                temperature,
+            )
+            .await?;
+
+        let mut result =
+            Self::parse_adversarial_verdict(&response.text, &solution.id, verifying_agent_id);
+        result.verifier_model = provider.model_id();
+        Ok(result)
+    }
+
+    /// Parse an adversarial verdict (`COUNTEREXAMPLE`, `PLAUSIBILITY`, `REASON`)
+    ///
+    /// A counterexample without a plausibility line is treated as fully
+    /// plausible. The score is the confidence that the solution survived.
+    pub fn parse_adversarial_verdict(
+        response: &str,
+        solution_id: &str,
+        verifying_agent_id: &str,
+    ) -> VerificationResult {
+        let mut counterexample: Option<String> = None;
+        let mut plausibility: Option<f32> = None;
+        let mut reason = String::new();
+
+        for line in response.lines() {
+            let line = line.trim().trim_start_matches(['*', '-', '#']).trim();
+            let Some((label, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim().trim_start_matches('*').trim();
 
-        let is_correct = true; // Placeholder
-        let score = 0.9; // Placeholder score (0.0-1.0)
+            match label
+                .trim()
+                .trim_end_matches('*')
+                .to_ascii_uppercase()
+                .as_str()
+            {
+                "COUNTEREXAMPLE" => {
+                    let none = value.trim_end_matches('.').eq_ignore_ascii_case("none");
+                    counterexample = (!none && !value.is_empty()).then(|| value.to_string());
+                }
+                "PLAUSIBILITY" => {
+                    plausibility = Self::parse_score(value).map(|(score, _)| score);
+                }
+                "REASON" => reason = value.to_string(),
+                _ => {}
+            }
+        }
+
+        let plausibility = match &counterexample {
+            Some(_) => plausibility.unwrap_or(1.0),
+            None => 0.0,
+        };
+        let is_correct = plausibility < COUNTEREXAMPLE_PLAUSIBILITY_THRESHOLD;
 
         let mut result = VerificationResult::new(
-            solution.id.clone(),
+            solution_id.to_string(),
             is_correct,
-            score,
+            1.0 - plausibility,
             verifying_agent_id.to_string(),
         );
+        result.correctness_score = result.score;
+        if let Some(counterexample) = counterexample {
+            result.correctness_feedback = format!("Counterexample: {counterexample}");
+            if !is_correct {
+                result.critique = reason;
+            }
+        }
 
-        result.correctness_feedback = "Answer appears mathematically sound".to_string();
-        result.completeness_feedback = "Solution addresses all aspects of the problem".to_string();
-        result.rigor_feedback = "Reasoning is well-justified and systematic".to_string();
+        result
+    }
 
-        Ok(result)
+    /// Build the user prompt presenting the problem and candidate solution
+    ///
+    /// Solutions reasoned in numbered steps also ask the verifier to name the
+    /// first wrong one.
+    fn verification_prompt(query: &str, solution: &Solution) -> String {
+        let prompt = format!(
+            "Problem:\n{query}\n\nSolution to verify:\n{}\n\nAnswer: {}",
+            solution.reasoning, solution.answer
+        );
+        if solution.steps.is_empty() {
+            return prompt;
+        }
+        format!(
+            "{prompt}\n\nThe reasoning has {} numbered steps. If any of them is wrong, add a \
+             line FAILED_STEP: [number of the first wrong step] before FEEDBACK.",
+            solution.steps.len()
+        )
     }
 
     /// Verify multiple solutions in parallel
     pub async fn verify_solutions(
+        query: &str,
         solutions: &[Solution],
         verifying_agent_id: &str,
+        provider: &dyn LLMProvider,
     ) -> Result<Vec<VerificationResult>> {
         let mut verification_results = Vec::new();
 
         for solution in solutions {
-            let result = Self::verify_solution(solution, verifying_agent_id).await?;
+            let result =
+                Self::verify_solution(query, solution, verifying_agent_id, provider).await?;
             verification_results.push(result);
         }
 
         Ok(verification_results)
     }
 
+    /// Parse a rubric verdict from a verifier response
+    ///
+    /// Missing criterion scores default to the overall score; a missing overall
+    /// score defaults to the weighted rubric score (50% correctness, 25%
+    /// completeness, 25% rigor). Without an explicit RESULT line the solution
+    /// is considered correct when its overall score reaches 0.5. A
+    /// `FAILED_STEP` line names the first wrong reasoning step.
+    pub fn parse_verdict(
+        response: &str,
+        solution_id: &str,
+        verifying_agent_id: &str,
+    ) -> VerificationResult {
+        let mut result_line: Option<bool> = None;
+        let mut overall: Option<f32> = None;
+        let mut criteria: [Option<(f32, String)>; 3] = [None, None, None];
+        let mut critique: Option<Vec<&str>> = None;
+        let mut failed_step = None;
+
+        for raw_line in response.lines() {
+            // Everything after FEEDBACK: belongs to the critique
+            if let Some(lines) = critique.as_mut() {
+                lines.push(raw_line.trim());
+                continue;
+            }
+
+            let line = raw_line.trim().trim_start_matches(['*', '-', '#']).trim();
+            let Some((label, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim().trim_start_matches('*').trim();
+
+            match label
+                .trim()
+                .trim_end_matches('*')
+                .to_ascii_uppercase()
+                .as_str()
+            {
+                "RESULT" => {
+                    let verdict = value.to_ascii_uppercase();
+                    result_line = Some(!verdict.starts_with("INCORRECT"));
+                }
+                "SCORE" => overall = Self::parse_score(value).map(|(score, _)| score),
+                "CORRECTNESS" => criteria[0] = Self::parse_score(value),
+                "COMPLETENESS" => criteria[1] = Self::parse_score(value),
+                "RIGOR" => criteria[2] = Self::parse_score(value),
+                "FAILED_STEP" => failed_step = Self::parse_step(value),
+                "FEEDBACK" => critique = Some(vec![value]),
+                _ => {}
+            }
+        }
+
+        let weighted = match &criteria {
+            [Some((c, _)), Some((m, _)), Some((r, _))] => Some(c * 0.5 + m * 0.25 + r * 0.25),
+            _ => None,
+        };
+        let score = overall.or(weighted).unwrap_or(0.0);
+        let is_correct = result_line.unwrap_or(score >= DEFAULT_PASS_SCORE);
+
+        let mut result = VerificationResult::new(
+            solution_id.to_string(),
+            is_correct,
+            score,
+            verifying_agent_id.to_string(),
+        );
+
+        let [correctness, completeness, rigor] = criteria;
+        let (score, feedback) = correctness.unwrap_or((score, String::new()));
+        result.correctness_score = score;
+        result.correctness_feedback = feedback;
+        let (score, feedback) = completeness.unwrap_or((result.score, String::new()));
+        result.completeness_score = score;
+        result.completeness_feedback = feedback;
+        let (score, feedback) = rigor.unwrap_or((result.score, String::new()));
+        result.rigor_score = score;
+        result.rigor_feedback = feedback;
+        result.critique = critique
+            .map(|lines| lines.join("\n").trim().to_string())
+            .unwrap_or_default();
+        result.failed_step = failed_step;
+
+        result
+    }
+
+    /// Parse a step number such as `3` or `Step 3`; `NONE` or `0` is no step
+    fn parse_step(value: &str) -> Option<usize> {
+        value
+            .split(|c: char| !c.is_ascii_digit())
+            .find(|digits| !digits.is_empty())?
+            .parse()
+            .ok()
+            .filter(|&step| step > 0)
+    }
+
+    /// Parse `"<score> | <feedback>"`, clamping the score to 0.0-1.0
+    fn parse_score(value: &str) -> Option<(f32, String)> {
+        let (score, feedback) = match value.split_once('|') {
+            Some((score, feedback)) => (score, feedback.trim()),
+            None => match value.split_once(char::is_whitespace) {
+                Some((score, feedback)) => (score, feedback.trim_start_matches(['-', ' '])),
+                None => (value, ""),
+            },
+        };
+        let score = score
+            .trim()
+            .trim_end_matches(|c: char| !c.is_ascii_digit())
+            .parse::<f32>()
+            .ok()?;
+        Some((score.clamp(0.0, 1.0), feedback.trim().to_string()))
+    }
+
     /// Check if a solution meets consensus criteria
     ///
     /// A solution is considered verified when it receives multiple
@@ -62,6 +359,24 @@ impl Verifier {
         solution.verification_passes >= consensus_threshold && solution.verification_failures == 0
     }
 
+    /// Check if a solution's verification record satisfies a quorum rule
+    ///
+    /// At least `min_passes` passes are always required; the rule decides how
+    /// many failures are tolerated alongside them.
+    pub fn meets_quorum(solution: &Solution, rule: QuorumRule, min_passes: usize) -> bool {
+        let passes = solution.verification_passes;
+        let failures = solution.verification_failures;
+        if passes == 0 || passes < min_passes {
+            return false;
+        }
+
+        match rule {
+            QuorumRule::All => failures == 0,
+            QuorumRule::Majority => passes > failures,
+            QuorumRule::Any => true,
+        }
+    }
+
     /// Calculate overall verification confidence
     ///
     /// Combines verification passes, failures, and scores into a single confidence metric.
@@ -105,6 +420,164 @@ impl Verifier {
 mod tests {
     use super::*;
 
+    use crate::model_router::{CompletionResponse, ModelStream};
+    use async_trait::async_trait;
+
+    /// Mock provider returning a fixed verifier response
+    struct MockVerifierProvider {
+        response: String,
+    }
+
+    #[async_trait]
+    impl LLMProvider for MockVerifierProvider {
+        async fn complete(
+            &self,
+            _prompt: &str,
+            _system_prompt: Option<&str>,
+        ) -> Result<CompletionResponse> {
+            Ok(self.response.clone().into())
+        }
+
+        async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+            let content = self.complete(prompt, system_prompt).await?.text;
+            Ok(ModelStream::new(content))
+        }
+
+        fn provider_name(&self) -> &str {
+            "mock"
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-verifier"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_solution_with_rubric() {
+        let provider = MockVerifierProvider {
+            response: "CORRECTNESS: 0.9 | The arithmetic checks out\n\
+                       COMPLETENESS: 0.6 | Skips the negative case\n\
+                       RIGOR: 0.8 | Each step is justified\n\
+                       RESULT: CORRECT\n\
+                       SCORE: 0.85\n\
+                       FEEDBACK: Mostly solid."
+                .to_string(),
+        };
+        let solution = Solution::new(
+            "agent1".to_string(),
+            "reasoning".to_string(),
+            "4".to_string(),
+            0.5,
+            100,
+        );
+
+        let result = Verifier::verify_solution("What is 2+2?", &solution, "verifier", &provider)
+            .await
+            .unwrap();
+
+        assert!(result.is_correct);
+        assert_eq!(result.solution_id, solution.id);
+        assert_eq!(result.score, 0.85);
+        assert_eq!(result.correctness_score, 0.9);
+        assert_eq!(result.completeness_score, 0.6);
+        assert_eq!(result.rigor_score, 0.8);
+        assert_eq!(result.completeness_feedback, "Skips the negative case");
+        assert_eq!(result.critique, "Mostly solid.");
+        assert_eq!(result.verifier_model, "mock/mock-verifier");
+    }
+
+    #[test]
+    fn test_parse_verdict_multiline_critique() {
+        let response = "RESULT: INCORRECT\n\
+                        SCORE: 0.3\n\
+                        FEEDBACK: Step 2 divides by zero.\n\
+                        Handle x = 0 separately.";
+
+        let result = Verifier::parse_verdict(response, "sol", "verifier");
+        assert_eq!(
+            result.critique,
+            "Step 2 divides by zero.\nHandle x = 0 separately."
+        );
+        assert!(
+            result
+                .feedback_summary()
+                .contains("Handle x = 0 separately.")
+        );
+    }
+
+    #[test]
+    fn test_stepped_solutions_are_checked_step_by_step() {
+        let mut solution = Solution::new(
+            "agent1".to_string(),
+            "Step 1: 2 + 2 = 5\nStep 2: So the answer is 5".to_string(),
+            "5".to_string(),
+            0.5,
+            100,
+        );
+        let prompt = Verifier::verification_prompt("What is 2+2?", &solution);
+        assert!(!prompt.contains("FAILED_STEP"));
+        solution.steps = crate::agent::Agent::parse_steps(&solution.reasoning);
+        let prompt = Verifier::verification_prompt("What is 2+2?", &solution);
+        assert!(prompt.contains("2 numbered steps"), "{prompt}");
+
+        let response = "RESULT: INCORRECT\nFAILED_STEP: Step 1\nFEEDBACK: 2 + 2 is 4.";
+        let result = Verifier::parse_verdict(response, "sol", "verifier");
+        assert_eq!(result.failed_step, Some(1));
+        assert_eq!(result.critique, "2 + 2 is 4.");
+        for none in ["NONE", "0"] {
+            let response = format!("RESULT: CORRECT\nFAILED_STEP: {none}");
+            let result = Verifier::parse_verdict(&response, "sol", "verifier");
+            assert_eq!(result.failed_step, None);
+        }
+    }
+
+    #[test]
+    fn test_parse_verdict_without_result_or_score() {
+        let response = "CORRECTNESS: 0.2 - wrong final answer\n\
+                        COMPLETENESS: 0.4\n\
+                        RIGOR: 0.4";
+
+        let result = Verifier::parse_verdict(response, "sol", "verifier");
+        assert!(!result.is_correct);
+        assert!((result.score - 0.3).abs() < 1e-6);
+        assert_eq!(result.correctness_feedback, "wrong final answer");
+    }
+
+    #[test]
+    fn test_parse_verdict_incorrect() {
+        let result = Verifier::parse_verdict("RESULT: INCORRECT\nSCORE: 1.7", "sol", "verifier");
+        assert!(!result.is_correct);
+        assert_eq!(result.score, 1.0);
+    }
+
+    #[test]
+    fn test_parse_adversarial_verdict() {
+        let refuted = Verifier::parse_adversarial_verdict(
+            "COUNTEREXAMPLE: n = 0\nPLAUSIBILITY: 0.9\nREASON: Division by n fails.",
+            "sol",
+            "verifier",
+        );
+        assert!(!refuted.is_correct);
+        assert!((refuted.score - 0.1).abs() < 1e-6);
+        assert_eq!(refuted.correctness_feedback, "Counterexample: n = 0");
+        assert_eq!(refuted.critique, "Division by n fails.");
+
+        let survived = Verifier::parse_adversarial_verdict(
+            "COUNTEREXAMPLE: NONE\nREASON: Checked n = 0 and negatives.",
+            "sol",
+            "verifier",
+        );
+        assert!(survived.is_correct);
+        assert_eq!(survived.score, 1.0);
+
+        let implausible = Verifier::parse_adversarial_verdict(
+            "COUNTEREXAMPLE: very large n might overflow\nPLAUSIBILITY: 0.2",
+            "sol",
+            "verifier",
+        );
+        assert!(implausible.is_correct);
+    }
+
     #[test]
     fn test_meets_consensus() {
         let mut solution = Solution::new(
@@ -127,6 +600,31 @@ mod tests {
         assert!(!Verifier::meets_consensus(&solution, 2));
     }
 
+    #[test]
+    fn test_meets_quorum() {
+        let mut solution = Solution::new(
+            "agent1".to_string(),
+            "reasoning".to_string(),
+            "answer".to_string(),
+            0.5,
+            100,
+        );
+        assert!(!Verifier::meets_quorum(&solution, QuorumRule::Any, 0));
+
+        solution.add_verification_pass(0.9);
+        solution.add_verification_pass(0.8);
+        solution.add_verification_failure();
+
+        assert!(!Verifier::meets_quorum(&solution, QuorumRule::All, 2));
+        assert!(Verifier::meets_quorum(&solution, QuorumRule::Majority, 2));
+        assert!(!Verifier::meets_quorum(&solution, QuorumRule::Majority, 3));
+        assert!(Verifier::meets_quorum(&solution, QuorumRule::Any, 1));
+
+        solution.add_verification_failure();
+        assert!(!Verifier::meets_quorum(&solution, QuorumRule::Majority, 1));
+        assert!(Verifier::meets_quorum(&solution, QuorumRule::Any, 1));
+    }
+
     #[test]
     fn test_calculate_confidence() {
         let mut solution = Solution::new(
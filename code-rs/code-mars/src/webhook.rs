@@ -0,0 +1,286 @@
+//! Notifications POSTed when a run completes or fails.
+//!
+//! With `MarsConfig::webhook` set, the coordinator sends a [`RunSummary`] of
+//! every run to the webhook's URL once the run has an outcome. Sending is
+//! fire-and-forget: the POST runs in a background task and a failure is only
+//! logged, so a run's result never depends on the receiver. By default the
+//! body is the summary as JSON:
+//!
+//! ```json
+//! {"run_id": "5f0c…", "status": "completed", "answer": "42", "confidence": 0.83,
+//!  "cost_usd": 0.0123, "duration_ms": 5400, "total_tokens": 9120, "tags": [],
+//!  "error": null}
+//! ```
+//!
+//! `payload_template` replaces the body with a
+//! [minijinja](https://docs.rs/minijinja) template rendered with the
+//! summary's fields. Values placed in JSON should go through `tojson`, e.g.
+//! `{"text": {{ ("MARS " ~ status ~ ": " ~ (answer or error)) | tojson }}}`
+//! for a Slack incoming webhook. The URL and `auth_header` may reference
+//! environment variables as `${VAR}`, read when each notification is sent.
+
+use crate::provider_config::interpolate_env;
+use crate::types::MarsOutput;
+use crate::{MarsError, Result};
+use minijinja::Environment;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Where and how run summaries are sent
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WebhookConfig {
+    /// URL the summary is POSTed to
+    pub url: String,
+
+    /// Value of the `Authorization` header, e.g. `Bearer ${HOOK_TOKEN}`
+    /// Default: None (no header)
+    #[serde(default)]
+    pub auth_header: Option<String>,
+
+    /// Template rendering the request body from the summary's fields
+    /// Default: None (the summary as JSON)
+    #[serde(default)]
+    pub payload_template: Option<String>,
+
+    /// Seconds before an unanswered POST is abandoned
+    /// Default: 10
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+impl WebhookConfig {
+    /// POST summaries to `url` as JSON
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            auth_header: None,
+            payload_template: None,
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+
+    /// Send `Authorization: <value>` with each summary
+    pub fn with_auth_header(mut self, value: impl Into<String>) -> Self {
+        self.auth_header = Some(value.into());
+        self
+    }
+
+    /// Render the body from `template` instead of sending the summary as JSON
+    pub fn with_payload_template(mut self, template: impl Into<String>) -> Self {
+        self.payload_template = Some(template.into());
+        self
+    }
+
+    /// Abandon POSTs unanswered after `secs` seconds
+    pub fn with_timeout_secs(mut self, secs: u64) -> Self {
+        self.timeout_secs = secs;
+        self
+    }
+
+    /// The request body for `summary`
+    pub fn render_payload(&self, summary: &RunSummary) -> Result<String> {
+        match &self.payload_template {
+            Some(template) => Environment::new()
+                .render_str(template, summary)
+                .map_err(|e| MarsError::InvalidConfiguration(format!("webhook payload: {e}"))),
+            None => serde_json::to_string(summary)
+                .map_err(|e| MarsError::ParsingError(format!("Failed to serialize summary: {e}"))),
+        }
+    }
+
+    /// Problems [`MarsConfig::validate`](crate::MarsConfig::validate)
+    /// reports for this webhook
+    pub(crate) fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !(self.url.starts_with("http://")
+            || self.url.starts_with("https://")
+            || self.url.starts_with("${"))
+        {
+            problems.push(format!("url must be an http(s) URL, got {:?}", self.url));
+        }
+        if let Some(template) = &self.payload_template
+            && let Err(e) = Environment::new().template_from_str(template)
+        {
+            problems.push(format!("payload_template does not compile: {e}"));
+        }
+        problems
+    }
+
+    /// POST `summary`, failing unless the receiver answers with success
+    pub async fn send(&self, summary: &RunSummary) -> Result<()> {
+        let url = interpolate_env(&self.url)
+            .map_err(|e| MarsError::InvalidConfiguration(format!("webhook url: {e}")))?;
+        let mut request = reqwest::Client::new()
+            .post(&url)
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(self.render_payload(summary)?);
+        if let Some(auth_header) = &self.auth_header {
+            let value = interpolate_env(auth_header).map_err(|e| {
+                MarsError::InvalidConfiguration(format!("webhook auth_header: {e}"))
+            })?;
+            request = request.header(reqwest::header::AUTHORIZATION, value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| MarsError::ClientError(format!("Webhook POST failed: {e}")))?;
+        if !response.status().is_success() {
+            return Err(MarsError::ClientError(format!(
+                "Webhook answered {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Send `summary` in the background, logging a failure
+    pub(crate) fn notify(&self, summary: RunSummary) {
+        let webhook = self.clone();
+        tokio::spawn(async move {
+            match webhook.send(&summary).await {
+                Ok(()) => tracing::debug!(run_id = %summary.run_id, "Webhook notified"),
+                Err(e) => tracing::warn!(run_id = %summary.run_id, "{e}"),
+            }
+        });
+    }
+}
+
+/// How a run ended
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    /// The run produced an answer
+    Completed,
+    /// The run failed with an error
+    Failed,
+}
+
+/// What a webhook is told about a run
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RunSummary {
+    /// ID of the run
+    pub run_id: String,
+    /// How the run ended
+    pub status: RunStatus,
+    /// The final answer, if the run completed
+    pub answer: Option<String>,
+    /// Confidence in the answer, if the run completed
+    pub confidence: Option<f32>,
+    /// Dollars the run spent, including on a failed run's calls
+    pub cost_usd: f64,
+    /// Wall-clock time of the run in milliseconds
+    pub duration_ms: u64,
+    /// Tokens the run used, if it completed
+    pub total_tokens: usize,
+    /// Tags the run was started with
+    pub tags: Vec<String>,
+    /// What went wrong, if the run failed
+    pub error: Option<String>,
+}
+
+impl RunSummary {
+    /// Summarize the run that produced `output`
+    pub fn completed(output: &MarsOutput, cost_usd: f64, duration: Duration) -> Self {
+        Self {
+            run_id: output.run_id.clone(),
+            status: RunStatus::Completed,
+            answer: Some(output.answer.trim().to_string()),
+            confidence: Some(output.confidence),
+            cost_usd,
+            duration_ms: duration.as_millis() as u64,
+            total_tokens: output.total_tokens,
+            tags: output.tags.clone(),
+            error: None,
+        }
+    }
+
+    /// Summarize run `run_id`, which failed with `error`
+    pub fn failed(
+        run_id: &str,
+        tags: &[String],
+        error: &MarsError,
+        cost_usd: f64,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            status: RunStatus::Failed,
+            answer: None,
+            confidence: None,
+            cost_usd,
+            duration_ms: duration.as_millis() as u64,
+            total_tokens: 0,
+            tags: tags.to_vec(),
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn failed() -> RunSummary {
+        RunSummary::failed(
+            "run-1",
+            &["batch".to_string()],
+            &MarsError::NoSolutions,
+            0.5,
+            Duration::from_millis(1500),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_send_posts_summary_with_auth_header() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(header("authorization", "Bearer hook-secret"))
+            .and(body_json(serde_json::json!({
+                "run_id": "run-1",
+                "status": "failed",
+                "answer": null,
+                "confidence": null,
+                "cost_usd": 0.5,
+                "duration_ms": 1500,
+                "total_tokens": 0,
+                "tags": ["batch"],
+                "error": "No solutions available",
+            })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // SAFETY: the variable is unique to this test
+        unsafe { std::env::set_var("MARS_TEST_WEBHOOK_TOKEN", "hook-secret") };
+        let webhook = WebhookConfig::new(format!("{}/hook", server.uri()))
+            .with_auth_header("Bearer ${MARS_TEST_WEBHOOK_TOKEN}");
+        webhook.send(&failed()).await.unwrap();
+
+        let rejected = WebhookConfig::new(format!("{}/missing", server.uri()));
+        assert!(rejected.send(&failed()).await.is_err());
+    }
+
+    #[test]
+    fn test_payload_template_renders_summary_fields() {
+        let webhook = WebhookConfig::new("https://hooks.example.com/x").with_payload_template(
+            r#"{"text": {{ ("MARS " ~ status ~ ": " ~ (answer or error)) | tojson }}}"#,
+        );
+        assert_eq!(
+            webhook.render_payload(&failed()).unwrap(),
+            r#"{"text": "MARS failed: No solutions available"}"#
+        );
+        assert!(webhook.problems().is_empty());
+
+        let broken = WebhookConfig::new("hooks.example.com").with_payload_template("{{ status");
+        assert_eq!(broken.problems().len(), 2);
+    }
+}
@@ -1,113 +1,782 @@
 /// Shared workspace for storing and managing solutions across agents.
-use crate::types::Solution;
+use crate::aggregator::Aggregator;
+use crate::types::{
+    DEFAULT_CONSENSUS_THRESHOLD, DerivationTree, EvictionPolicy, Solution, SolutionDedup,
+    WorkspaceBackend,
+};
+use crate::workspace_log::{ChangeLog, WorkspaceChange, WorkspaceLogEntry};
+use crate::workspace_store::{MemoryWorkspaceStore, SolutionOrder, SolutionQuery, WorkspaceStore};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Attempts [`Workspace::modify_solution`] makes before giving up on conflicts
+const MAX_UPDATE_ATTEMPTS: usize = 16;
+
+/// Format version written by [`Workspace::export`]
+pub(crate) const EXPORT_FORMAT_VERSION: u32 = 1;
+
 /// Shared workspace for all agents to store and access solutions
+///
+/// Storage is delegated to a [`WorkspaceStore`] (in memory by default).
+/// Backend errors are logged and treated as an empty result, except by
+/// [`Workspace::update_solution`] and [`Workspace::modify_solution`]; use
+/// [`Workspace::store`] to handle them.
 #[derive(Clone)]
 pub struct Workspace {
-    solutions: Arc<RwLock<Vec<Solution>>>,
+    store: Arc<dyn WorkspaceStore>,
+    dedup: SolutionDedup,
+    /// Passes a merged duplicate needs to count as verified
+    consensus_threshold: usize,
+    /// IDs of solutions merged into a duplicate, mapped to the solution kept
+    merged_ids: Arc<RwLock<HashMap<String, String>>>,
+    max_solutions: Option<usize>,
+    eviction: EvictionPolicy,
+    eviction_state: Arc<RwLock<EvictionState>>,
+    snapshots: Arc<RwLock<SnapshotState>>,
+    change_log: Option<Arc<RwLock<ChangeLog>>>,
+}
+
+/// Recency of use and not yet reported evictions, for the size limit
+#[derive(Default)]
+struct EvictionState {
+    clock: u64,
+    last_used: HashMap<String, u64>,
+    evicted: Vec<String>,
+}
+
+/// Handle to a workspace state saved by [`Workspace::snapshot`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SnapshotId(u64);
+
+impl std::fmt::Display for SnapshotId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Saved workspace states, by ID
+#[derive(Default)]
+struct SnapshotState {
+    next_id: u64,
+    saved: HashMap<SnapshotId, WorkspaceSnapshot>,
+}
+
+/// Everything needed to restore the workspace to an earlier state
+#[derive(Clone)]
+struct WorkspaceSnapshot {
+    solutions: Vec<Solution>,
+    feedback: BTreeMap<String, Vec<String>>,
+    merged_ids: HashMap<String, String>,
+    last_used: HashMap<String, u64>,
+}
+
+/// A workspace's full contents in a portable form
+///
+/// Verification verdicts (passes, failures, scores) and lineage
+/// (`parent_ids`) travel on the solutions themselves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceExport {
+    /// Version of this format
+    pub format_version: u32,
+    /// When the export was taken
+    pub exported_at: DateTime<Utc>,
+    /// Every solution, in insertion order
+    pub solutions: Vec<Solution>,
+    /// Verifier feedback by solution ID
+    #[serde(default)]
+    pub feedback: BTreeMap<String, Vec<String>>,
+    /// IDs of deduplicated solutions, mapped to the solution they merged into
+    #[serde(default)]
+    pub merged_ids: BTreeMap<String, String>,
+}
+
+/// Differences between two workspace states
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceDiff {
+    /// Solutions present only in the later state
+    pub added: Vec<Solution>,
+    /// Solutions present only in the earlier state
+    pub removed: Vec<Solution>,
+    /// Solutions changed in between, as they are in the later state
+    pub updated: Vec<Solution>,
+}
+
+impl WorkspaceDiff {
+    /// Compare two sets of solutions, keeping the order of each
+    fn between(before: &[Solution], after: &[Solution]) -> Self {
+        let before_by_id: HashMap<&str, &Solution> =
+            before.iter().map(|s| (s.id.as_str(), s)).collect();
+        let after_by_id: HashMap<&str, &Solution> =
+            after.iter().map(|s| (s.id.as_str(), s)).collect();
+
+        let mut diff = Self::default();
+        for solution in after {
+            match before_by_id.get(solution.id.as_str()) {
+                None => diff.added.push(solution.clone()),
+                Some(earlier) if earlier.version != solution.version => {
+                    diff.updated.push(solution.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = before
+            .iter()
+            .filter(|s| !after_by_id.contains_key(s.id.as_str()))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    /// Whether the two states hold the same solutions
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
 }
 
 impl Workspace {
-    /// Create a new workspace
+    /// Create a new in-memory workspace
     pub fn new() -> Self {
+        Self::with_store(Arc::new(MemoryWorkspaceStore::new()))
+    }
+
+    /// Create a workspace on top of a custom store
+    pub fn with_store(store: Arc<dyn WorkspaceStore>) -> Self {
         Self {
-            solutions: Arc::new(RwLock::new(Vec::new())),
+            store,
+            dedup: SolutionDedup::default(),
+            consensus_threshold: DEFAULT_CONSENSUS_THRESHOLD,
+            merged_ids: Arc::default(),
+            max_solutions: None,
+            eviction: EvictionPolicy::default(),
+            eviction_state: Arc::default(),
+            snapshots: Arc::default(),
+            change_log: None,
         }
     }
 
-    /// Add a solution to the workspace
-    pub async fn add_solution(&self, solution: Solution) {
-        let mut solutions = self.solutions.write().await;
-        solutions.push(solution);
+    /// Hold at most `max` solutions, evicting by `policy` beyond that
+    ///
+    /// The solution just added is never the one evicted. Collect the IDs of
+    /// evicted solutions with [`Workspace::take_evicted`].
+    pub fn with_max_solutions(mut self, max: usize, policy: EvictionPolicy) -> Self {
+        self.max_solutions = Some(max.max(1));
+        self.eviction = policy;
+        self
+    }
+
+    /// Record every change in an ordered log (see [`crate::workspace_log`])
+    pub fn with_change_log(mut self) -> Self {
+        self.change_log = Some(Arc::default());
+        self
+    }
+
+    /// Collapse duplicate solutions as they are added
+    pub fn with_dedup(mut self, dedup: SolutionDedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Set the passes a merged duplicate needs to count as verified
+    pub fn with_consensus_threshold(mut self, threshold: usize) -> Self {
+        self.consensus_threshold = threshold;
+        self
+    }
+
+    /// Create a workspace on the configured backend
+    ///
+    /// Fails if the backend cannot be opened or was not compiled in (the
+    /// `sqlite` and `sled` features).
+    pub fn open(backend: &WorkspaceBackend) -> crate::Result<Self> {
+        let store: Arc<dyn WorkspaceStore> = match backend {
+            WorkspaceBackend::InMemory => Arc::new(MemoryWorkspaceStore::new()),
+            #[cfg(feature = "sqlite")]
+            WorkspaceBackend::Sqlite(path) => {
+                Arc::new(crate::workspace_store::SqliteWorkspaceStore::open(path))
+            }
+            #[cfg(feature = "sled")]
+            WorkspaceBackend::Sled(path) => {
+                Arc::new(crate::workspace_store::SledWorkspaceStore::open(path)?)
+            }
+            #[cfg(not(feature = "sqlite"))]
+            WorkspaceBackend::Sqlite(_) => return Err(Self::not_compiled("sqlite")),
+            #[cfg(not(feature = "sled"))]
+            WorkspaceBackend::Sled(_) => return Err(Self::not_compiled("sled")),
+        };
+        Ok(Self::with_store(store))
+    }
+
+    /// Error for a backend whose cargo feature is disabled
+    #[cfg(not(all(feature = "sqlite", feature = "sled")))]
+    fn not_compiled(feature: &str) -> crate::MarsError {
+        crate::MarsError::StorageError(format!(
+            "The {feature} workspace backend requires the `{feature}` cargo feature"
+        ))
+    }
+
+    /// The underlying store
+    pub fn store(&self) -> &Arc<dyn WorkspaceStore> {
+        &self.store
+    }
+
+    /// Log a store error and fall back to the default value
+    fn logged<T: Default>(result: crate::Result<T>, action: &str) -> T {
+        result.unwrap_or_else(|e| {
+            tracing::error!("Workspace failed to {action}: {e}");
+            T::default()
+        })
+    }
+
+    /// Solutions matching a query, in insertion order
+    pub async fn query(&self, query: &SolutionQuery) -> Vec<Solution> {
+        Self::logged(self.store.query(query).await, "query solutions")
+    }
+
+    /// Add a solution to the workspace, returning the ID it is stored under
+    ///
+    /// With deduplication enabled, a duplicate of an existing solution is
+    /// merged into it (see [`Solution::merge_duplicate`]) and the existing
+    /// solution's ID is returned. The duplicate's ID keeps resolving to it in
+    /// [`Workspace::get_solution`] and in later solutions' `parent_ids`.
+    pub async fn add_solution(&self, mut solution: Solution) -> String {
+        self.resolve_parents(&mut solution).await;
+
+        if let Some(existing) = self.find_duplicate(&solution).await {
+            tracing::debug!(
+                "Merging duplicate solution {} into {}",
+                solution.id,
+                existing.id
+            );
+            let id = existing.id;
+            Self::logged(
+                self.modify_solution(&id, |kept| {
+                    kept.merge_duplicate(&solution, self.consensus_threshold)
+                })
+                .await
+                .map(|_| ()),
+                "merge duplicate",
+            );
+            self.record(WorkspaceChange::Merged {
+                duplicate_id: solution.id.clone(),
+                into_id: id.clone(),
+            })
+            .await;
+            self.merged_ids
+                .write()
+                .await
+                .insert(solution.id, id.clone());
+            return id;
+        }
+
+        let id = solution.id.clone();
+        self.store_solution(solution).await;
+        self.touch(&id).await;
+        self.evict_over_limit(&id).await;
+        id
+    }
+
+    /// Mark a solution as just used, for least-recently-used eviction
+    async fn touch(&self, id: &str) {
+        if self.max_solutions.is_none() {
+            return;
+        }
+        let mut state = self.eviction_state.write().await;
+        state.clock += 1;
+        let now = state.clock;
+        state.last_used.insert(id.to_string(), now);
+    }
+
+    /// Evict solutions until the workspace is within its limit, sparing `keep`
+    async fn evict_over_limit(&self, keep: &str) {
+        let Some(max) = self.max_solutions else {
+            return;
+        };
+        let count = self.count_solutions().await;
+        if count <= max {
+            return;
+        }
+
+        let solutions = self.get_all_solutions().await;
+        let mut victims: Vec<&Solution> = solutions.iter().filter(|s| s.id != keep).collect();
+        let mut state = self.eviction_state.write().await;
+        // Solutions never used through this workspace (e.g. loaded from disk) count as oldest
+        let recency = |s: &Solution| state.last_used.get(&s.id).copied().unwrap_or(0);
+        match self.eviction {
+            EvictionPolicy::LowestScoreUnverified => victims.sort_by(|a, b| {
+                a.is_verified
+                    .cmp(&b.is_verified)
+                    .then_with(|| {
+                        if a.is_verified {
+                            std::cmp::Ordering::Equal
+                        } else {
+                            a.verification_score.total_cmp(&b.verification_score)
+                        }
+                    })
+                    .then_with(|| recency(a).cmp(&recency(b)))
+            }),
+            EvictionPolicy::LeastRecentlyUsed => victims.sort_by_key(|s| recency(s)),
+        }
+        let victim_ids: Vec<String> = victims
+            .into_iter()
+            .take(count - max)
+            .map(|s| s.id.clone())
+            .collect();
+
+        for id in victim_ids {
+            let removed = self.store.remove(&id).await;
+            if removed.is_ok() {
+                self.record(WorkspaceChange::Evicted {
+                    solution_id: id.clone(),
+                })
+                .await;
+            }
+            Self::logged(removed, "evict solution");
+            state.last_used.remove(&id);
+            state.evicted.push(id);
+        }
+    }
+
+    /// IDs of solutions evicted since the last call, in eviction order
+    pub async fn take_evicted(&self) -> Vec<String> {
+        std::mem::take(&mut self.eviction_state.write().await.evicted)
+    }
+
+    /// Point parent IDs of merged duplicates at the solutions they merged into
+    async fn resolve_parents(&self, solution: &mut Solution) {
+        let merged_ids = self.merged_ids.read().await;
+        if merged_ids.is_empty() {
+            return;
+        }
+        let mut parent_ids: Vec<String> = Vec::with_capacity(solution.parent_ids.len());
+        for parent_id in &solution.parent_ids {
+            let parent_id = merged_ids.get(parent_id).unwrap_or(parent_id);
+            if !parent_ids.contains(parent_id) {
+                parent_ids.push(parent_id.clone());
+            }
+        }
+        solution.parent_ids = parent_ids;
+    }
+
+    /// Existing solution that `solution` duplicates under the dedup setting
+    async fn find_duplicate(&self, solution: &Solution) -> Option<Solution> {
+        let threshold = match self.dedup {
+            SolutionDedup::Disabled => return None,
+            SolutionDedup::ContentHash => None,
+            SolutionDedup::Fuzzy(threshold) => Some(threshold),
+        };
+
+        let existing = self.get_all_solutions().await;
+        let hash = solution.content_hash();
+        if let Some(pos) = existing.iter().position(|s| s.content_hash() == hash) {
+            return existing.into_iter().nth(pos);
+        }
+
+        let threshold = threshold?;
+        let answer = solution.answer.trim().to_lowercase();
+        existing.into_iter().find(|s| {
+            s.answer.trim().to_lowercase() == answer
+                && Aggregator::lexical_similarity(s, solution) >= threshold
+        })
     }
 
     /// Get all solutions in the workspace
     pub async fn get_all_solutions(&self) -> Vec<Solution> {
-        let solutions = self.solutions.read().await;
-        solutions.clone()
+        Self::logged(self.store.all().await, "list solutions")
+    }
+
+    /// Get all solutions that propose an answer, leaving out plans
+    pub async fn get_candidate_solutions(&self) -> Vec<Solution> {
+        self.query(&SolutionQuery::new().candidates()).await
     }
 
     /// Get a specific solution by ID
+    ///
+    /// The ID of a merged duplicate resolves to the solution it merged into.
     pub async fn get_solution(&self, id: &str) -> Option<Solution> {
-        let solutions = self.solutions.read().await;
-        solutions.iter().find(|s| s.id == id).cloned()
+        let id = match self.merged_ids.read().await.get(id) {
+            Some(kept) => kept.clone(),
+            None => id.to_string(),
+        };
+        let solution = Self::logged(self.store.get(&id).await, "get solution");
+        if solution.is_some() {
+            self.touch(&id).await;
+        }
+        solution
     }
 
     /// Update a solution in the workspace
+    ///
+    /// Fails with [`MarsError::VersionConflict`](crate::MarsError::VersionConflict)
+    /// if the solution changed since `updated_solution` was read; prefer
+    /// [`Workspace::modify_solution`], which retries.
     pub async fn update_solution(&self, updated_solution: Solution) -> crate::Result<()> {
-        let mut solutions = self.solutions.write().await;
-        if let Some(pos) = solutions.iter().position(|s| s.id == updated_solution.id) {
-            solutions[pos] = updated_solution;
-            Ok(())
-        } else {
-            Err(crate::MarsError::CoordinatorError(format!(
-                "Solution {} not found",
-                updated_solution.id
-            )))
+        let id = updated_solution.id.clone();
+        let for_log = self.change_log.is_some().then(|| updated_solution.clone());
+        self.store.update(updated_solution).await?;
+        if let Some(mut solution) = for_log {
+            // The store advanced the version
+            solution.version += 1;
+            self.record(WorkspaceChange::Updated { solution }).await;
         }
+        self.touch(&id).await;
+        Ok(())
+    }
+
+    /// Apply `change` to the latest stored copy of a solution and save it
+    ///
+    /// When another writer updates the solution in between, `change` is
+    /// re-applied to the newer copy, so concurrent changes are never lost.
+    /// Returns the solution as stored.
+    pub async fn modify_solution<F>(&self, id: &str, change: F) -> crate::Result<Solution>
+    where
+        F: Fn(&mut Solution),
+    {
+        for _attempt in 0..MAX_UPDATE_ATTEMPTS {
+            let Some(mut solution) = self.get_solution(id).await else {
+                return Err(crate::MarsError::CoordinatorError(format!(
+                    "Solution {id} not found"
+                )));
+            };
+            change(&mut solution);
+            match self.update_solution(solution.clone()).await {
+                Ok(()) => {
+                    solution.version += 1;
+                    return Ok(solution);
+                }
+                Err(crate::MarsError::VersionConflict { .. }) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(crate::MarsError::StorageError(format!(
+            "Solution {id} kept changing; gave up after {MAX_UPDATE_ATTEMPTS} attempts"
+        )))
+    }
+
+    /// Record verifier feedback for a solution (empty feedback is ignored)
+    pub async fn add_feedback(&self, solution_id: &str, feedback: String) {
+        if feedback.trim().is_empty() {
+            return;
+        }
+        let result = self.store.add_feedback(solution_id, feedback.clone()).await;
+        if result.is_ok() {
+            self.record(WorkspaceChange::FeedbackAdded {
+                solution_id: solution_id.to_string(),
+                feedback,
+            })
+            .await;
+        }
+        Self::logged(result, "add feedback");
+    }
+
+    /// Get all verifier feedback recorded for a solution
+    pub async fn get_feedback(&self, solution_id: &str) -> Vec<String> {
+        Self::logged(self.store.feedback(solution_id).await, "get feedback")
     }
 
     /// Get all verified solutions
     pub async fn get_verified_solutions(&self) -> Vec<Solution> {
-        let solutions = self.solutions.read().await;
-        solutions
-            .iter()
-            .filter(|s| s.is_verified)
-            .cloned()
-            .collect()
+        self.query(&SolutionQuery::new().verified(true)).await
     }
 
     /// Get solutions sorted by verification score (descending)
     pub async fn get_solutions_by_score(&self) -> Vec<Solution> {
-        let solutions = self.solutions.read().await;
-        let mut sorted = solutions.clone();
-        sorted.sort_by(|a, b| {
-            b.verification_score
-                .partial_cmp(&a.verification_score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        sorted
+        self.query(&SolutionQuery::new().order_by(SolutionOrder::ScoreDescending))
+            .await
     }
 
     /// Get solutions from a specific agent
     pub async fn get_solutions_by_agent(&self, agent_id: &str) -> Vec<Solution> {
-        let solutions = self.solutions.read().await;
-        solutions
-            .iter()
-            .filter(|s| s.agent_id == agent_id)
-            .cloned()
-            .collect()
+        self.query(&SolutionQuery::new().by_agent(agent_id)).await
     }
 
     /// Count total solutions
     pub async fn count_solutions(&self) -> usize {
-        let solutions = self.solutions.read().await;
-        solutions.len()
+        Self::logged(self.store.count().await, "count solutions")
     }
 
     /// Clear all solutions (useful for testing)
     pub async fn clear(&self) {
-        let mut solutions = self.solutions.write().await;
-        solutions.clear();
+        let result = self.store.clear().await;
+        if result.is_ok() {
+            self.record(WorkspaceChange::Cleared).await;
+        }
+        Self::logged(result, "clear");
+        self.merged_ids.write().await.clear();
+        *self.eviction_state.write().await = EvictionState::default();
     }
 
     /// Get the best unverified solution by answer length (simpler answers are often better)
     pub async fn get_best_unverified(&self) -> Option<Solution> {
-        let solutions = self.solutions.read().await;
-        solutions
-            .iter()
-            .filter(|s| !s.is_verified && s.verification_failures == 0)
+        self.query(&SolutionQuery::new().verified(false))
+            .await
+            .into_iter()
+            .filter(|s| s.verification_failures == 0)
             .min_by_key(|s| s.answer.len())
-            .cloned()
     }
 
     /// Get top N solutions by verification score
     pub async fn get_top_n_verified(&self, n: usize) -> Vec<Solution> {
-        let mut solutions = self.get_solutions_by_score().await;
-        solutions.truncate(n);
-        solutions
+        self.query(
+            &SolutionQuery::new()
+                .order_by(SolutionOrder::ScoreDescending)
+                .limit(n),
+        )
+        .await
+    }
+
+    /// Get the solutions a solution was directly derived from
+    pub async fn get_parents(&self, id: &str) -> Vec<Solution> {
+        let Some(solution) = self.get_solution(id).await else {
+            return Vec::new();
+        };
+        let mut parents = Vec::with_capacity(solution.parent_ids.len());
+        for parent_id in &solution.parent_ids {
+            if let Some(parent) = self.get_solution(parent_id).await {
+                parents.push(parent);
+            }
+        }
+        parents
+    }
+
+    /// Get the solutions directly derived from a solution
+    pub async fn get_children(&self, id: &str) -> Vec<Solution> {
+        self.get_all_solutions()
+            .await
+            .into_iter()
+            .filter(|s| s.parent_ids.iter().any(|parent_id| parent_id == id))
+            .collect()
+    }
+
+    /// Get every solution a solution was derived from, nearest first
+    pub async fn get_ancestors(&self, id: &str) -> Vec<Solution> {
+        let mut ancestors = self
+            .get_derivation_tree(id)
+            .await
+            .map(|tree| tree.solutions().into_iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        if !ancestors.is_empty() {
+            ancestors.remove(0);
+        }
+        ancestors
+    }
+
+    /// Reconstruct the full derivation tree of a solution
+    pub async fn get_derivation_tree(&self, id: &str) -> Option<DerivationTree> {
+        DerivationTree::build(id, &self.get_all_solutions().await)
+    }
+
+    /// Save the current solutions and their feedback for a later rollback
+    ///
+    /// Fails instead of saving a partial state if the store cannot be read.
+    pub async fn snapshot(&self) -> crate::Result<SnapshotId> {
+        let snapshot = self.capture().await?;
+        let mut snapshots = self.snapshots.write().await;
+        snapshots.next_id += 1;
+        let id = SnapshotId(snapshots.next_id);
+        snapshots.saved.insert(id, snapshot);
+        Ok(id)
+    }
+
+    /// Read the current workspace state
+    async fn capture(&self) -> crate::Result<WorkspaceSnapshot> {
+        let solutions = self.store.all().await?;
+        let mut feedback = BTreeMap::new();
+        for solution in &solutions {
+            let entries = self.store.feedback(&solution.id).await?;
+            if !entries.is_empty() {
+                feedback.insert(solution.id.clone(), entries);
+            }
+        }
+        Ok(WorkspaceSnapshot {
+            solutions,
+            feedback,
+            merged_ids: self.merged_ids.read().await.clone(),
+            last_used: self.eviction_state.read().await.last_used.clone(),
+        })
+    }
+
+    /// Saved state for a snapshot ID
+    async fn saved_snapshot(&self, id: SnapshotId) -> crate::Result<WorkspaceSnapshot> {
+        self.snapshots
+            .read()
+            .await
+            .saved
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| {
+                crate::MarsError::CoordinatorError(format!("Workspace snapshot {id} not found"))
+            })
+    }
+
+    /// Restore the workspace to a snapshot, discarding everything since
+    ///
+    /// The snapshot is kept, so the workspace can be rolled back to it again.
+    /// Evictions already reported are not reported again.
+    pub async fn rollback(&self, id: SnapshotId) -> crate::Result<()> {
+        let snapshot = self.saved_snapshot(id).await?;
+        self.replace_contents(snapshot.solutions, snapshot.feedback, snapshot.merged_ids)
+            .await?;
+        self.eviction_state.write().await.last_used = snapshot.last_used;
+        Ok(())
+    }
+
+    /// Replace everything in the workspace with the given contents
+    async fn replace_contents(
+        &self,
+        solutions: Vec<Solution>,
+        feedback: BTreeMap<String, Vec<String>>,
+        merged_ids: HashMap<String, String>,
+    ) -> crate::Result<()> {
+        self.store.clear().await?;
+        self.record(WorkspaceChange::Cleared).await;
+        for solution in solutions {
+            let for_log = self.change_log.is_some().then(|| solution.clone());
+            self.store.add(solution).await?;
+            if let Some(solution) = for_log {
+                self.record(WorkspaceChange::Added { solution }).await;
+            }
+        }
+        for (solution_id, entries) in feedback {
+            for entry in entries {
+                self.store.add_feedback(&solution_id, entry.clone()).await?;
+                self.record(WorkspaceChange::FeedbackAdded {
+                    solution_id: solution_id.clone(),
+                    feedback: entry,
+                })
+                .await;
+            }
+        }
+        for (duplicate_id, into_id) in &merged_ids {
+            self.record(WorkspaceChange::Merged {
+                duplicate_id: duplicate_id.clone(),
+                into_id: into_id.clone(),
+            })
+            .await;
+        }
+        *self.merged_ids.write().await = merged_ids;
+        Ok(())
+    }
+
+    /// Take the workspace's full contents for archiving or analysis
+    pub async fn export(&self) -> crate::Result<WorkspaceExport> {
+        let contents = self.capture().await?;
+        Ok(WorkspaceExport {
+            format_version: EXPORT_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            solutions: contents.solutions,
+            feedback: contents.feedback,
+            merged_ids: contents.merged_ids.into_iter().collect(),
+        })
+    }
+
+    /// Export the workspace as pretty-printed JSON
+    pub async fn export_json(&self) -> crate::Result<String> {
+        serde_json::to_string_pretty(&self.export().await?)
+            .map_err(|e| crate::MarsError::ParsingError(format!("Workspace export failed: {e}")))
+    }
+
+    /// Replace the workspace's contents with an export
+    ///
+    /// Imported solutions keep their IDs, versions and lineage. The size
+    /// limit applies, and anything evicted is reported by
+    /// [`Workspace::take_evicted`]. Returns the number of solutions imported.
+    pub async fn import(&self, export: WorkspaceExport) -> crate::Result<usize> {
+        if export.format_version > EXPORT_FORMAT_VERSION {
+            return Err(crate::MarsError::ParsingError(format!(
+                "Workspace export format {} is newer than supported ({EXPORT_FORMAT_VERSION})",
+                export.format_version
+            )));
+        }
+
+        let count = export.solutions.len();
+        self.replace_contents(
+            export.solutions,
+            export.feedback,
+            export.merged_ids.into_iter().collect(),
+        )
+        .await?;
+        self.eviction_state.write().await.last_used.clear();
+        self.evict_over_limit("").await;
+        Ok(count)
+    }
+
+    /// Replace the workspace's contents with JSON from [`Workspace::export_json`]
+    pub async fn import_json(&self, json: &str) -> crate::Result<usize> {
+        let export = serde_json::from_str(json).map_err(|e| {
+            crate::MarsError::ParsingError(format!("Invalid workspace export: {e}"))
+        })?;
+        self.import(export).await
+    }
+
+    /// Store a new solution, logging the change
+    async fn store_solution(&self, solution: Solution) {
+        let for_log = self.change_log.is_some().then(|| solution.clone());
+        let result = self.store.add(solution).await;
+        if result.is_ok()
+            && let Some(solution) = for_log
+        {
+            self.record(WorkspaceChange::Added { solution }).await;
+        }
+        Self::logged(result, "add solution");
+    }
+
+    /// Append a change to the log, if it is enabled
+    async fn record(&self, change: WorkspaceChange) {
+        if let Some(change_log) = &self.change_log {
+            change_log.write().await.record(change);
+        }
+    }
+
+    /// Changes recorded after sequence number `seq` (0 for all), in order
+    ///
+    /// Empty unless the log was enabled with [`Workspace::with_change_log`].
+    pub async fn changes_since(&self, seq: u64) -> Vec<WorkspaceLogEntry> {
+        match &self.change_log {
+            Some(change_log) => change_log.read().await.since(seq),
+            None => Vec::new(),
+        }
+    }
+
+    /// Rebuild the workspace as it was after change `seq`, as a new in-memory workspace
+    pub async fn state_at(&self, seq: u64) -> crate::Result<Workspace> {
+        let Some(change_log) = &self.change_log else {
+            return Err(crate::MarsError::CoordinatorError(
+                "Workspace change log is not enabled".to_string(),
+            ));
+        };
+        let changes: Vec<WorkspaceLogEntry> = change_log
+            .read()
+            .await
+            .since(0)
+            .into_iter()
+            .take_while(|entry| entry.seq <= seq)
+            .collect();
+        let workspace = Workspace::new();
+        workspace
+            .import(crate::workspace_log::replay(&changes))
+            .await?;
+        Ok(workspace)
+    }
+
+    /// Forget a snapshot, returning whether it existed
+    pub async fn discard_snapshot(&self, id: SnapshotId) -> bool {
+        self.snapshots.write().await.saved.remove(&id).is_some()
+    }
+
+    /// Compare two snapshots, `from` being the earlier one
+    pub async fn diff(&self, from: SnapshotId, to: SnapshotId) -> crate::Result<WorkspaceDiff> {
+        let before = self.saved_snapshot(from).await?;
+        let after = self.saved_snapshot(to).await?;
+        Ok(WorkspaceDiff::between(&before.solutions, &after.solutions))
+    }
+
+    /// Compare a snapshot with the current workspace
+    pub async fn diff_since(&self, id: SnapshotId) -> crate::Result<WorkspaceDiff> {
+        let before = self.saved_snapshot(id).await?;
+        let current = self.store.all().await?;
+        Ok(WorkspaceDiff::between(&before.solutions, &current))
     }
 }
 
@@ -202,4 +871,457 @@ mod tests {
         let agent1_sols = workspace.get_solutions_by_agent("agent1").await;
         assert_eq!(agent1_sols.len(), 2);
     }
+
+    #[tokio::test]
+    async fn test_candidate_solutions_exclude_plans() {
+        let workspace = Workspace::new();
+
+        let answer = Solution::new(
+            "agent1".to_string(),
+            "r1".to_string(),
+            "a1".to_string(),
+            0.5,
+            100,
+        );
+        let mut plan = Solution::new(
+            "planner".to_string(),
+            "Step 1: plan".to_string(),
+            String::new(),
+            0.5,
+            100,
+        );
+        plan.phase = crate::types::GenerationPhase::Planned;
+
+        workspace.add_solution(answer.clone()).await;
+        workspace.add_solution(plan).await;
+
+        let candidates = workspace.get_candidate_solutions().await;
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].id, answer.id);
+        assert_eq!(workspace.count_solutions().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_feedback_per_solution() {
+        let workspace = Workspace::new();
+
+        workspace
+            .add_feedback("sol1", "Step 2 is wrong".to_string())
+            .await;
+        workspace.add_feedback("sol1", "  ".to_string()).await;
+        workspace
+            .add_feedback("sol2", "Missing edge case".to_string())
+            .await;
+
+        assert_eq!(
+            workspace.get_feedback("sol1").await,
+            vec!["Step 2 is wrong"]
+        );
+        assert_eq!(workspace.get_feedback("sol2").await.len(), 1);
+        assert!(workspace.get_feedback("sol3").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_lineage_queries() {
+        let workspace = Workspace::new();
+
+        let root = Solution::new(
+            "agent1".to_string(),
+            "r1".to_string(),
+            "a1".to_string(),
+            0.5,
+            100,
+        );
+        let mut improved = Solution::new(
+            "agent1".to_string(),
+            "r2".to_string(),
+            "a2".to_string(),
+            0.5,
+            100,
+        );
+        improved.parent_ids = vec![root.id.clone()];
+        let mut aggregated = Solution::new(
+            "aggregator".to_string(),
+            "r3".to_string(),
+            "a3".to_string(),
+            0.5,
+            100,
+        );
+        aggregated.parent_ids = vec![improved.id.clone(), root.id.clone()];
+
+        workspace.add_solution(root.clone()).await;
+        workspace.add_solution(improved.clone()).await;
+        workspace.add_solution(aggregated.clone()).await;
+
+        assert_eq!(workspace.get_parents(&aggregated.id).await.len(), 2);
+        assert_eq!(workspace.get_children(&root.id).await.len(), 2);
+
+        let ancestors: Vec<_> = workspace
+            .get_ancestors(&aggregated.id)
+            .await
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ancestors, vec![improved.id.clone(), root.id.clone()]);
+
+        let tree = workspace.get_derivation_tree(&aggregated.id).await.unwrap();
+        assert_eq!(tree.depth(), 2);
+        assert!(workspace.get_ancestors(&root.id).await.is_empty());
+        assert!(workspace.get_derivation_tree("unknown").await.is_none());
+    }
+
+    fn answer(reasoning: &str, answer: &str) -> Solution {
+        Solution::new(
+            "agent1".to_string(),
+            reasoning.to_string(),
+            answer.to_string(),
+            0.5,
+            100,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_dedup_merges_identical_content() {
+        let workspace = Workspace::new().with_dedup(SolutionDedup::ContentHash);
+
+        let mut original = answer("2 + 2 = 4", "4");
+        original.add_verification_pass(0.9);
+        let mut duplicate = answer("2 +  2 =\n4", "4");
+        duplicate.add_verification_pass(0.7);
+        let mut child = answer("Double-checked", "4");
+        child.parent_ids = vec![duplicate.id.clone()];
+
+        assert_eq!(workspace.add_solution(original.clone()).await, original.id);
+        assert_eq!(workspace.add_solution(duplicate.clone()).await, original.id);
+        workspace.add_solution(child.clone()).await;
+        workspace.add_solution(answer("2 + 2 = 5", "5")).await;
+
+        assert_eq!(workspace.count_solutions().await, 3);
+        let merged = workspace.get_solution(&duplicate.id).await.unwrap();
+        assert_eq!(merged.id, original.id);
+        assert_eq!(merged.verification_passes, 2);
+        assert!(merged.is_verified);
+        assert_eq!(merged.token_count, 200);
+        let child = workspace.get_solution(&child.id).await.unwrap();
+        assert_eq!(child.parent_ids, vec![original.id.clone()]);
+
+        // Two merged passes fall short of a higher consensus threshold
+        let strict = Workspace::new()
+            .with_dedup(SolutionDedup::ContentHash)
+            .with_consensus_threshold(3);
+        strict.add_solution(original.clone()).await;
+        strict.add_solution(duplicate).await;
+        let merged = strict.get_solution(&original.id).await.unwrap();
+        assert_eq!(merged.verification_passes, 2);
+        assert!(!merged.is_verified);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_fuzzy_requires_same_answer() {
+        let workspace = Workspace::new().with_dedup(SolutionDedup::Fuzzy(0.6));
+
+        workspace
+            .add_solution(answer("Add two and two to get four", "4"))
+            .await;
+        workspace
+            .add_solution(answer("Add two and two to get four.", "4"))
+            .await;
+        workspace
+            .add_solution(answer("Add two and two to get four", "5"))
+            .await;
+        workspace
+            .add_solution(answer("Count on fingers", "4"))
+            .await;
+
+        assert_eq!(workspace.count_solutions().await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_disabled_by_default() {
+        let workspace = Workspace::new();
+        workspace.add_solution(answer("same", "4")).await;
+        workspace.add_solution(answer("same", "4")).await;
+        assert_eq!(workspace.count_solutions().await, 2);
+    }
+
+    fn scored(score: f32, verified: bool) -> Solution {
+        let mut solution = answer("r", "4");
+        solution.verification_score = score;
+        solution.is_verified = verified;
+        solution
+    }
+
+    #[tokio::test]
+    async fn test_eviction_prefers_low_scoring_unverified() {
+        let workspace =
+            Workspace::new().with_max_solutions(3, EvictionPolicy::LowestScoreUnverified);
+
+        let verified = scored(0.1, true);
+        let weak = scored(0.2, false);
+        let strong = scored(0.5, false);
+        let newest = scored(0.0, false);
+        for solution in [&verified, &weak, &strong, &newest] {
+            workspace.add_solution(solution.clone()).await;
+        }
+        assert_eq!(workspace.count_solutions().await, 3);
+        assert_eq!(workspace.take_evicted().await, vec![weak.id.clone()]);
+        assert!(workspace.take_evicted().await.is_empty());
+
+        // The newest solution is spared when added, but not afterwards
+        workspace.add_solution(scored(0.3, false)).await;
+        assert_eq!(workspace.take_evicted().await, vec![newest.id.clone()]);
+        assert!(workspace.get_solution(&verified.id).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_eviction_falls_back_to_least_recently_used() {
+        let workspace =
+            Workspace::new().with_max_solutions(2, EvictionPolicy::LowestScoreUnverified);
+
+        let first = scored(0.9, true);
+        let second = scored(0.4, true);
+        workspace.add_solution(first.clone()).await;
+        workspace.add_solution(second.clone()).await;
+        workspace.get_solution(&first.id).await;
+        workspace.add_solution(scored(0.8, true)).await;
+
+        assert_eq!(workspace.take_evicted().await, vec![second.id.clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_eviction_least_recently_used() {
+        let workspace = Workspace::new().with_max_solutions(2, EvictionPolicy::LeastRecentlyUsed);
+
+        let first = scored(0.0, false);
+        let second = scored(0.9, true);
+        workspace.add_solution(first.clone()).await;
+        workspace.add_solution(second.clone()).await;
+        workspace.update_solution(first.clone()).await.unwrap();
+        workspace.add_solution(scored(0.0, false)).await;
+
+        assert_eq!(workspace.take_evicted().await, vec![second.id.clone()]);
+        assert_eq!(workspace.count_solutions().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stale_update_conflicts() {
+        let workspace = Workspace::new();
+        let solution = answer("r", "4");
+        workspace.add_solution(solution.clone()).await;
+
+        let mut first = solution.clone();
+        first.add_verification_pass(0.9);
+        workspace.update_solution(first).await.unwrap();
+
+        let mut stale = solution;
+        stale.add_verification_failure();
+        assert!(matches!(
+            workspace.update_solution(stale.clone()).await,
+            Err(crate::MarsError::VersionConflict { .. })
+        ));
+
+        let stored = workspace.get_solution(&stale.id).await.unwrap();
+        assert_eq!(stored.verification_passes, 1);
+        assert_eq!(stored.version, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_modifications_are_not_lost() {
+        let workspace = Workspace::new();
+        let solution = answer("r", "4");
+        workspace.add_solution(solution.clone()).await;
+
+        let mut tasks = Vec::new();
+        for _ in 0..8 {
+            let workspace = workspace.clone();
+            let id = solution.id.clone();
+            tasks.push(tokio::spawn(async move {
+                workspace
+                    .modify_solution(&id, |stored| stored.add_verification_pass(0.9))
+                    .await
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let stored = workspace.get_solution(&solution.id).await.unwrap();
+        assert_eq!(stored.verification_passes, 8);
+        assert_eq!(stored.version, 8);
+    }
+
+    #[tokio::test]
+    async fn test_rollback_restores_snapshot() {
+        let workspace = Workspace::new().with_dedup(SolutionDedup::ContentHash);
+        let original = answer("2 + 2 = 4", "4");
+        let duplicate = answer("2 + 2 = 4", "4");
+        workspace.add_solution(original.clone()).await;
+        workspace.add_solution(duplicate.clone()).await;
+        workspace
+            .add_feedback(&original.id, "looks right".to_string())
+            .await;
+
+        let snapshot = workspace.snapshot().await.unwrap();
+        workspace.add_solution(answer("2 + 2 = 5", "5")).await;
+        workspace
+            .modify_solution(&original.id, |s| s.add_verification_failure())
+            .await
+            .unwrap();
+        workspace
+            .add_feedback(&original.id, "wrong".to_string())
+            .await;
+
+        workspace.rollback(snapshot).await.unwrap();
+        assert_eq!(workspace.count_solutions().await, 1);
+        let restored = workspace.get_solution(&duplicate.id).await.unwrap();
+        assert_eq!(restored.id, original.id);
+        assert_eq!(restored.verification_failures, 0);
+        assert_eq!(
+            workspace.get_feedback(&original.id).await,
+            vec!["looks right".to_string()]
+        );
+
+        assert!(workspace.discard_snapshot(snapshot).await);
+        assert!(workspace.rollback(snapshot).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_diff() {
+        let workspace = Workspace::new();
+        let kept = answer("kept", "1");
+        let changed = answer("changed", "2");
+        let removed = answer("removed", "3");
+        for solution in [&kept, &changed, &removed] {
+            workspace.add_solution(solution.clone()).await;
+        }
+        let before = workspace.snapshot().await.unwrap();
+
+        let added = answer("added", "4");
+        workspace.add_solution(added.clone()).await;
+        workspace
+            .modify_solution(&changed.id, |s| s.add_verification_pass(0.9))
+            .await
+            .unwrap();
+        workspace.store().remove(&removed.id).await.unwrap();
+        let after = workspace.snapshot().await.unwrap();
+
+        let diff = workspace.diff(before, after).await.unwrap();
+        let ids = |solutions: &[Solution]| -> Vec<String> {
+            solutions.iter().map(|s| s.id.clone()).collect()
+        };
+        assert_eq!(ids(&diff.added), vec![added.id.clone()]);
+        assert_eq!(ids(&diff.updated), vec![changed.id.clone()]);
+        assert_eq!(ids(&diff.removed), vec![removed.id.clone()]);
+        assert_eq!(diff.updated[0].verification_passes, 1);
+
+        assert!(workspace.diff_since(after).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let source = Workspace::new().with_dedup(SolutionDedup::ContentHash);
+        let mut root = answer("2 + 2 = 4", "4");
+        root.add_verification_pass(0.9);
+        let duplicate = answer("2 + 2 = 4", "4");
+        let mut improved = answer("Checked: 2 + 2 = 4", "4");
+        improved.parent_ids = vec![root.id.clone()];
+        for solution in [&root, &duplicate, &improved] {
+            source.add_solution(solution.clone()).await;
+        }
+        source
+            .add_feedback(&root.id, "Correct arithmetic".to_string())
+            .await;
+
+        let json = source.export_json().await.unwrap();
+        let target = Workspace::new();
+        target.add_solution(answer("stale", "5")).await;
+        assert_eq!(target.import_json(&json).await.unwrap(), 2);
+
+        assert_eq!(target.count_solutions().await, 2);
+        let restored = target.get_solution(&duplicate.id).await.unwrap();
+        assert_eq!(restored.id, root.id);
+        assert_eq!(restored.verification_passes, 1);
+        assert_eq!(restored.verification_scores, vec![0.9]);
+        assert_eq!(
+            target.get_feedback(&root.id).await,
+            vec!["Correct arithmetic".to_string()]
+        );
+        let parents = target.get_parents(&improved.id).await;
+        assert_eq!(parents[0].id, root.id);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_bad_exports() {
+        let workspace = Workspace::new();
+        assert!(workspace.import_json("not json").await.is_err());
+
+        let mut export = workspace.export().await.unwrap();
+        export.format_version += 1;
+        assert!(workspace.import(export).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_change_log_replays_any_point() {
+        let workspace = Workspace::new()
+            .with_dedup(SolutionDedup::ContentHash)
+            .with_max_solutions(2, EvictionPolicy::LeastRecentlyUsed)
+            .with_change_log();
+
+        let first = answer("2 + 2 = 4", "4");
+        let second = answer("2 + 2 = 5", "5");
+        workspace.add_solution(first.clone()).await;
+        workspace.add_solution(second.clone()).await;
+        workspace
+            .add_feedback(&second.id, "off by one".to_string())
+            .await;
+        let before_changes = workspace.changes_since(0).await.len() as u64;
+
+        workspace.add_solution(answer("2 + 2 = 4", "4")).await;
+        workspace
+            .modify_solution(&first.id, |s| s.add_verification_pass(0.9))
+            .await
+            .unwrap();
+        workspace.add_solution(answer("2 + 2 = 3", "3")).await;
+
+        let changes = workspace.changes_since(before_changes).await;
+        assert!(matches!(changes[0].change, WorkspaceChange::Updated { .. }));
+        assert!(matches!(changes[1].change, WorkspaceChange::Merged { .. }));
+        let evicted: Vec<&str> = changes
+            .iter()
+            .filter_map(|entry| match &entry.change {
+                WorkspaceChange::Evicted { solution_id } => Some(solution_id.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(evicted, vec![second.id.as_str()]);
+
+        let earlier = workspace.state_at(before_changes).await.unwrap();
+        assert_eq!(earlier.count_solutions().await, 2);
+        assert_eq!(earlier.get_feedback(&second.id).await.len(), 1);
+
+        let last = workspace.changes_since(0).await.last().unwrap().seq;
+        let now = workspace.state_at(last).await.unwrap();
+        let ids = |solutions: Vec<Solution>| -> Vec<(String, u64)> {
+            solutions.into_iter().map(|s| (s.id, s.version)).collect()
+        };
+        assert_eq!(
+            ids(now.get_all_solutions().await),
+            ids(workspace.get_all_solutions().await)
+        );
+        assert_eq!(
+            now.get_solution(&first.id)
+                .await
+                .unwrap()
+                .verification_passes,
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_change_log_disabled_by_default() {
+        let workspace = Workspace::new();
+        workspace.add_solution(answer("r", "4")).await;
+        assert!(workspace.changes_since(0).await.is_empty());
+        assert!(workspace.state_at(1).await.is_err());
+    }
 }
@@ -0,0 +1,216 @@
+//! Ordered log of changes to a [`Workspace`](crate::workspace::Workspace).
+//!
+//! With the log enabled (see
+//! [`Workspace::with_change_log`](crate::workspace::Workspace::with_change_log)),
+//! every mutation is recorded as it happens: solutions added, updated and
+//! evicted, feedback, deduplication aliases and clears. Replaying a prefix of
+//! the log rebuilds the workspace as it was at that point, which helps when
+//! debugging a run and makes replays in tests deterministic.
+
+use crate::types::Solution;
+use crate::workspace::WorkspaceExport;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A single mutation of the workspace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkspaceChange {
+    /// A solution was stored
+    Added {
+        /// The solution as stored
+        solution: Solution,
+    },
+    /// A stored solution was replaced
+    Updated {
+        /// The solution as stored, with its new version
+        solution: Solution,
+    },
+    /// A solution was evicted to respect the size limit
+    Evicted {
+        /// ID of the evicted solution
+        solution_id: String,
+    },
+    /// Verifier feedback was recorded for a solution
+    FeedbackAdded {
+        /// ID of the solution
+        solution_id: String,
+        /// The feedback
+        feedback: String,
+    },
+    /// A duplicate was merged into an existing solution
+    Merged {
+        /// ID of the duplicate, which now resolves to `into_id`
+        duplicate_id: String,
+        /// ID of the solution kept
+        into_id: String,
+    },
+    /// Every solution, its feedback and all aliases were removed
+    Cleared,
+}
+
+/// A recorded change and when it happened
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceLogEntry {
+    /// Position in the log, starting at 1
+    pub seq: u64,
+    /// When the change was made
+    pub at: DateTime<Utc>,
+    /// The change
+    pub change: WorkspaceChange,
+}
+
+/// Append-only list of changes
+#[derive(Debug, Default)]
+pub(crate) struct ChangeLog {
+    entries: Vec<WorkspaceLogEntry>,
+}
+
+impl ChangeLog {
+    /// Append a change, returning its sequence number
+    pub(crate) fn record(&mut self, change: WorkspaceChange) -> u64 {
+        let seq = self.entries.len() as u64 + 1;
+        self.entries.push(WorkspaceLogEntry {
+            seq,
+            at: Utc::now(),
+            change,
+        });
+        seq
+    }
+
+    /// Entries with a sequence number above `seq`, in order
+    pub(crate) fn since(&self, seq: u64) -> Vec<WorkspaceLogEntry> {
+        let start = usize::try_from(seq)
+            .unwrap_or(usize::MAX)
+            .min(self.entries.len());
+        self.entries[start..].to_vec()
+    }
+}
+
+/// Rebuild workspace contents by applying changes in order
+pub fn replay(changes: &[WorkspaceLogEntry]) -> WorkspaceExport {
+    let mut solutions: Vec<Solution> = Vec::new();
+    let mut feedback: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut merged_ids: BTreeMap<String, String> = BTreeMap::new();
+
+    for entry in changes {
+        match &entry.change {
+            WorkspaceChange::Added { solution } => solutions.push(solution.clone()),
+            WorkspaceChange::Updated { solution } => {
+                match solutions.iter_mut().find(|s| s.id == solution.id) {
+                    Some(stored) => *stored = solution.clone(),
+                    None => solutions.push(solution.clone()),
+                }
+            }
+            WorkspaceChange::Evicted { solution_id } => {
+                solutions.retain(|s| &s.id != solution_id);
+                feedback.remove(solution_id);
+            }
+            WorkspaceChange::FeedbackAdded {
+                solution_id,
+                feedback: entry,
+            } => feedback
+                .entry(solution_id.clone())
+                .or_default()
+                .push(entry.clone()),
+            WorkspaceChange::Merged {
+                duplicate_id,
+                into_id,
+            } => {
+                merged_ids.insert(duplicate_id.clone(), into_id.clone());
+            }
+            WorkspaceChange::Cleared => {
+                solutions.clear();
+                feedback.clear();
+                merged_ids.clear();
+            }
+        }
+    }
+
+    WorkspaceExport {
+        format_version: crate::workspace::EXPORT_FORMAT_VERSION,
+        exported_at: changes.last().map_or_else(Utc::now, |entry| entry.at),
+        solutions,
+        feedback,
+        merged_ids,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solution(answer: &str) -> Solution {
+        Solution::new(
+            "agent1".to_string(),
+            "reasoning".to_string(),
+            answer.to_string(),
+            0.5,
+            10,
+        )
+    }
+
+    #[test]
+    fn test_replay_applies_changes_in_order() {
+        let first = solution("4");
+        let second = solution("5");
+        let mut updated = first.clone();
+        updated.version = 1;
+        updated.add_verification_pass(0.9);
+
+        let mut log = ChangeLog::default();
+        log.record(WorkspaceChange::Added {
+            solution: first.clone(),
+        });
+        log.record(WorkspaceChange::Added {
+            solution: second.clone(),
+        });
+        log.record(WorkspaceChange::FeedbackAdded {
+            solution_id: second.id.clone(),
+            feedback: "wrong".to_string(),
+        });
+        let evicted_at = log.record(WorkspaceChange::Evicted {
+            solution_id: second.id.clone(),
+        });
+        log.record(WorkspaceChange::Updated {
+            solution: updated.clone(),
+        });
+
+        let entries = log.since(0);
+        assert_eq!(entries.len(), 5);
+        assert_eq!(log.since(evicted_at).len(), 1);
+
+        let before_eviction = replay(&entries[..3]);
+        assert_eq!(before_eviction.solutions.len(), 2);
+        assert_eq!(before_eviction.feedback[&second.id], vec!["wrong"]);
+
+        let end = replay(&entries);
+        assert_eq!(end.solutions.len(), 1);
+        assert_eq!(end.solutions[0].version, 1);
+        assert_eq!(end.solutions[0].verification_passes, 1);
+        assert!(end.feedback.is_empty());
+    }
+
+    #[test]
+    fn test_cleared_resets_everything() {
+        let kept = solution("4");
+        let mut log = ChangeLog::default();
+        log.record(WorkspaceChange::Added {
+            solution: solution("3"),
+        });
+        log.record(WorkspaceChange::Merged {
+            duplicate_id: "dup".to_string(),
+            into_id: "other".to_string(),
+        });
+        log.record(WorkspaceChange::Cleared);
+        log.record(WorkspaceChange::Added {
+            solution: kept.clone(),
+        });
+
+        let state = replay(&log.since(0));
+        assert_eq!(state.solutions.len(), 1);
+        assert_eq!(state.solutions[0].id, kept.id);
+        assert!(state.merged_ids.is_empty());
+    }
+}
@@ -0,0 +1,987 @@
+//! Storage backends for the shared [`Workspace`](crate::workspace::Workspace).
+//!
+//! The workspace delegates to a [`WorkspaceStore`]. The default
+//! [`MemoryWorkspaceStore`] keeps everything in RAM; the SQLite (`sqlite`
+//! feature) and sled (`sled` feature) backends keep solutions and feedback on
+//! disk, so large batch evaluations neither exhaust memory nor lose their
+//! work when the process dies. Pick a backend with
+//! [`MarsConfig::with_workspace_backend`](crate::MarsConfig::with_workspace_backend)
+//! or plug in your own with
+//! [`MarsCoordinator::with_workspace_store`](crate::MarsCoordinator::with_workspace_store).
+
+use crate::Result;
+use crate::types::{GenerationPhase, Solution};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+/// Order of [`WorkspaceStore::query`] results
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SolutionOrder {
+    /// The order solutions were added in
+    #[default]
+    Inserted,
+    /// Highest verification score first
+    ScoreDescending,
+    /// Most recently created first
+    NewestFirst,
+}
+
+/// Filter for [`WorkspaceStore::query`]; unset fields match every solution
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SolutionQuery {
+    /// Only solutions from this agent
+    pub agent_id: Option<String>,
+    /// Only verified (`true`) or unverified (`false`) solutions
+    pub verified: Option<bool>,
+    /// Only solutions generated in this phase
+    pub phase: Option<GenerationPhase>,
+    /// Leave out plans, which propose no answer
+    pub exclude_plans: bool,
+    /// Only solutions with at least this verification score
+    pub min_score: Option<f32>,
+    /// Only solutions created at or after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only solutions created before this time
+    pub created_before: Option<DateTime<Utc>>,
+    /// Order of the results
+    pub order: SolutionOrder,
+    /// Return at most this many solutions (applied after ordering)
+    pub limit: Option<usize>,
+}
+
+impl SolutionQuery {
+    /// Query matching every solution
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only solutions from `agent_id`
+    pub fn by_agent(mut self, agent_id: impl Into<String>) -> Self {
+        self.agent_id = Some(agent_id.into());
+        self
+    }
+
+    /// Only verified (`true`) or unverified (`false`) solutions
+    pub fn verified(mut self, verified: bool) -> Self {
+        self.verified = Some(verified);
+        self
+    }
+
+    /// Only solutions generated in `phase`
+    pub fn in_phase(mut self, phase: GenerationPhase) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    /// Leave out plans
+    pub fn candidates(mut self) -> Self {
+        self.exclude_plans = true;
+        self
+    }
+
+    /// Only solutions scoring at least `score`
+    pub fn min_score(mut self, score: f32) -> Self {
+        self.min_score = Some(score);
+        self
+    }
+
+    /// Only solutions created at or after `time`
+    pub fn created_after(mut self, time: DateTime<Utc>) -> Self {
+        self.created_after = Some(time);
+        self
+    }
+
+    /// Only solutions created before `time`
+    pub fn created_before(mut self, time: DateTime<Utc>) -> Self {
+        self.created_before = Some(time);
+        self
+    }
+
+    /// Order the results
+    pub fn order_by(mut self, order: SolutionOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Return at most `limit` solutions
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Whether a solution passes every filter (`limit` aside)
+    pub fn matches(&self, solution: &Solution) -> bool {
+        self.agent_id
+            .as_ref()
+            .is_none_or(|agent_id| &solution.agent_id == agent_id)
+            && self
+                .verified
+                .is_none_or(|verified| solution.is_verified == verified)
+            && self
+                .phase
+                .as_ref()
+                .is_none_or(|phase| &solution.phase == phase)
+            && !(self.exclude_plans && solution.phase == GenerationPhase::Planned)
+            && self
+                .min_score
+                .is_none_or(|score| solution.verification_score >= score)
+            && self
+                .created_after
+                .is_none_or(|time| solution.created_at >= time)
+            && self
+                .created_before
+                .is_none_or(|time| solution.created_at < time)
+    }
+
+    /// Apply `order` and `limit` to matching solutions given in insertion order
+    pub fn order_and_limit(&self, mut solutions: Vec<Solution>) -> Vec<Solution> {
+        match self.order {
+            SolutionOrder::Inserted => {}
+            SolutionOrder::ScoreDescending => {
+                solutions.sort_by(|a, b| b.verification_score.total_cmp(&a.verification_score));
+            }
+            SolutionOrder::NewestFirst => solutions.sort_by(|a, b| b.created_at.cmp(&a.created_at)),
+        }
+        if let Some(limit) = self.limit {
+            solutions.truncate(limit);
+        }
+        solutions
+    }
+}
+
+/// Storage for workspace solutions and verifier feedback
+///
+/// Solutions are returned in the order they were added. Solution IDs are
+/// unique; persistent backends reject a second solution with the same ID.
+#[async_trait]
+pub trait WorkspaceStore: Send + Sync {
+    /// Add a solution
+    async fn add(&self, solution: Solution) -> Result<()>;
+
+    /// Get a solution by ID
+    async fn get(&self, id: &str) -> Result<Option<Solution>>;
+
+    /// Replace the stored solution with the same ID
+    ///
+    /// This is a compare-and-swap: `solution.version` must equal the stored
+    /// version, otherwise [`MarsError::VersionConflict`](crate::MarsError::VersionConflict)
+    /// is returned. The solution is stored with its version incremented.
+    async fn update(&self, solution: Solution) -> Result<()>;
+
+    /// Remove a solution and its feedback (a missing ID is not an error)
+    async fn remove(&self, id: &str) -> Result<()>;
+
+    /// Every solution, in insertion order
+    async fn all(&self) -> Result<Vec<Solution>>;
+
+    /// Solutions matching `query`, in the order it asks for
+    async fn query(&self, query: &SolutionQuery) -> Result<Vec<Solution>> {
+        let mut solutions = self.all().await?;
+        solutions.retain(|solution| query.matches(solution));
+        Ok(query.order_and_limit(solutions))
+    }
+
+    /// Number of stored solutions
+    async fn count(&self) -> Result<usize> {
+        Ok(self.all().await?.len())
+    }
+
+    /// Record verifier feedback for a solution
+    async fn add_feedback(&self, solution_id: &str, feedback: String) -> Result<()>;
+
+    /// All feedback recorded for a solution, oldest first
+    async fn feedback(&self, solution_id: &str) -> Result<Vec<String>>;
+
+    /// Remove every solution and all feedback
+    async fn clear(&self) -> Result<()>;
+}
+
+/// In-memory store; contents are lost when the process exits
+#[derive(Default)]
+pub struct MemoryWorkspaceStore {
+    solutions: RwLock<Vec<Solution>>,
+    feedback: RwLock<HashMap<String, Vec<String>>>,
+}
+
+impl MemoryWorkspaceStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl WorkspaceStore for MemoryWorkspaceStore {
+    async fn add(&self, solution: Solution) -> Result<()> {
+        self.solutions.write().await.push(solution);
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Solution>> {
+        let solutions = self.solutions.read().await;
+        Ok(solutions.iter().find(|s| s.id == id).cloned())
+    }
+
+    async fn update(&self, solution: Solution) -> Result<()> {
+        let mut solutions = self.solutions.write().await;
+        let Some(pos) = solutions.iter().position(|s| s.id == solution.id) else {
+            return Err(not_found(&solution.id));
+        };
+        solutions[pos] = next_version(&solutions[pos], solution)?;
+        Ok(())
+    }
+
+    async fn remove(&self, id: &str) -> Result<()> {
+        self.solutions.write().await.retain(|s| s.id != id);
+        self.feedback.write().await.remove(id);
+        Ok(())
+    }
+
+    async fn all(&self) -> Result<Vec<Solution>> {
+        Ok(self.solutions.read().await.clone())
+    }
+
+    async fn query(&self, query: &SolutionQuery) -> Result<Vec<Solution>> {
+        let solutions = self.solutions.read().await;
+        let matching = solutions
+            .iter()
+            .filter(|solution| query.matches(solution))
+            .cloned()
+            .collect();
+        Ok(query.order_and_limit(matching))
+    }
+
+    async fn count(&self) -> Result<usize> {
+        Ok(self.solutions.read().await.len())
+    }
+
+    async fn add_feedback(&self, solution_id: &str, feedback: String) -> Result<()> {
+        self.feedback
+            .write()
+            .await
+            .entry(solution_id.to_string())
+            .or_default()
+            .push(feedback);
+        Ok(())
+    }
+
+    async fn feedback(&self, solution_id: &str) -> Result<Vec<String>> {
+        let feedback = self.feedback.read().await;
+        Ok(feedback.get(solution_id).cloned().unwrap_or_default())
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.solutions.write().await.clear();
+        self.feedback.write().await.clear();
+        Ok(())
+    }
+}
+
+/// Error for an update to a solution that is not stored
+fn not_found(id: &str) -> crate::MarsError {
+    crate::MarsError::CoordinatorError(format!("Solution {id} not found"))
+}
+
+/// Check an update against the stored copy and advance its version
+fn next_version(stored: &Solution, mut update: Solution) -> Result<Solution> {
+    if stored.version != update.version {
+        return Err(crate::MarsError::VersionConflict {
+            id: update.id,
+            expected: update.version,
+            found: stored.version,
+        });
+    }
+    update.version += 1;
+    Ok(update)
+}
+
+/// Serialize a solution for a persistent backend
+#[cfg(any(feature = "sqlite", feature = "sled"))]
+fn encode(solution: &Solution) -> Result<String> {
+    serde_json::to_string(solution)
+        .map_err(|e| crate::MarsError::StorageError(format!("Failed to serialize solution: {e}")))
+}
+
+/// Deserialize a solution stored by a persistent backend
+#[cfg(any(feature = "sqlite", feature = "sled"))]
+fn decode(data: &[u8]) -> Result<Solution> {
+    serde_json::from_slice(data)
+        .map_err(|e| crate::MarsError::StorageError(format!("Failed to deserialize solution: {e}")))
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteWorkspaceStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::{SolutionQuery, WorkspaceStore, decode, encode, not_found};
+    use crate::types::Solution;
+    use crate::{MarsError, Result};
+    use async_trait::async_trait;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+    use sqlx::{QueryBuilder, Sqlite};
+    use std::path::Path;
+    use tokio::sync::OnceCell;
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS solutions (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            id TEXT NOT NULL UNIQUE,
+            agent_id TEXT NOT NULL,
+            is_verified INTEGER NOT NULL,
+            verification_score REAL NOT NULL,
+            data TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS feedback (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            solution_id TEXT NOT NULL,
+            feedback TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS feedback_solution ON feedback (solution_id);
+    ";
+
+    fn storage_error(action: &str, e: sqlx::Error) -> MarsError {
+        MarsError::StorageError(format!("Failed to {action}: {e}"))
+    }
+
+    /// Store backed by a SQLite database file
+    ///
+    /// Each solution is a row holding its JSON, with the fields that
+    /// [`SolutionQuery`] filters on as indexed columns.
+    pub struct SqliteWorkspaceStore {
+        pool: SqlitePool,
+        schema: OnceCell<()>,
+    }
+
+    impl SqliteWorkspaceStore {
+        /// Use the database at `path`, creating it on first use
+        ///
+        /// The connection opens lazily, so this does not touch the file.
+        pub fn open(path: impl AsRef<Path>) -> Self {
+            let options = SqliteConnectOptions::new()
+                .filename(path)
+                .create_if_missing(true);
+            // No idle or lifetime limits: they would need a runtime to reap
+            // connections, and a single long-lived connection suits SQLite
+            let pool = SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+                .connect_lazy_with(options);
+            Self {
+                pool,
+                schema: OnceCell::new(),
+            }
+        }
+
+        /// Pool with the schema created
+        async fn pool(&self) -> Result<&SqlitePool> {
+            self.schema
+                .get_or_try_init(|| async {
+                    sqlx::raw_sql(SCHEMA)
+                        .execute(&self.pool)
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| storage_error("create workspace schema", e))
+                })
+                .await?;
+            Ok(&self.pool)
+        }
+    }
+
+    #[async_trait]
+    impl WorkspaceStore for SqliteWorkspaceStore {
+        async fn add(&self, solution: Solution) -> Result<()> {
+            sqlx::query(
+                "INSERT INTO solutions (id, agent_id, is_verified, verification_score, data) \
+                 VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(&solution.id)
+            .bind(&solution.agent_id)
+            .bind(solution.is_verified)
+            .bind(solution.verification_score)
+            .bind(encode(&solution)?)
+            .execute(self.pool().await?)
+            .await
+            .map_err(|e| storage_error("add solution", e))?;
+            Ok(())
+        }
+
+        async fn get(&self, id: &str) -> Result<Option<Solution>> {
+            let data: Option<String> =
+                sqlx::query_scalar("SELECT data FROM solutions WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(self.pool().await?)
+                    .await
+                    .map_err(|e| storage_error("get solution", e))?;
+            data.map(|data| decode(data.as_bytes())).transpose()
+        }
+
+        async fn update(&self, mut solution: Solution) -> Result<()> {
+            let expected = solution.version;
+            solution.version += 1;
+            // The version check and the write happen in one statement
+            let result = sqlx::query(
+                "UPDATE solutions SET agent_id = ?, is_verified = ?, verification_score = ?, \
+                 data = ? WHERE id = ? AND COALESCE(json_extract(data, '$.version'), 0) = ?",
+            )
+            .bind(&solution.agent_id)
+            .bind(solution.is_verified)
+            .bind(solution.verification_score)
+            .bind(encode(&solution)?)
+            .bind(&solution.id)
+            .bind(i64::try_from(expected).unwrap_or(i64::MAX))
+            .execute(self.pool().await?)
+            .await
+            .map_err(|e| storage_error("update solution", e))?;
+            if result.rows_affected() == 0 {
+                return Err(match self.get(&solution.id).await? {
+                    Some(stored) => MarsError::VersionConflict {
+                        id: solution.id,
+                        expected,
+                        found: stored.version,
+                    },
+                    None => not_found(&solution.id),
+                });
+            }
+            Ok(())
+        }
+
+        async fn remove(&self, id: &str) -> Result<()> {
+            let pool = self.pool().await?;
+            for statement in [
+                "DELETE FROM solutions WHERE id = ?",
+                "DELETE FROM feedback WHERE solution_id = ?",
+            ] {
+                sqlx::query(statement)
+                    .bind(id)
+                    .execute(pool)
+                    .await
+                    .map_err(|e| storage_error("remove solution", e))?;
+            }
+            Ok(())
+        }
+
+        async fn all(&self) -> Result<Vec<Solution>> {
+            self.query(&SolutionQuery::new()).await
+        }
+
+        async fn query(&self, query: &SolutionQuery) -> Result<Vec<Solution>> {
+            // Filter on the indexed columns in SQL and on the rest in Rust
+            let mut sql = QueryBuilder::<Sqlite>::new("SELECT data FROM solutions WHERE 1 = 1");
+            if let Some(agent_id) = &query.agent_id {
+                sql.push(" AND agent_id = ").push_bind(agent_id);
+            }
+            if let Some(verified) = query.verified {
+                sql.push(" AND is_verified = ").push_bind(verified);
+            }
+            if let Some(score) = query.min_score {
+                sql.push(" AND verification_score >= ").push_bind(score);
+            }
+            sql.push(" ORDER BY seq");
+
+            let rows: Vec<String> = sql
+                .build_query_scalar()
+                .fetch_all(self.pool().await?)
+                .await
+                .map_err(|e| storage_error("query solutions", e))?;
+            let mut solutions = Vec::new();
+            for row in rows {
+                let solution = decode(row.as_bytes())?;
+                if query.matches(&solution) {
+                    solutions.push(solution);
+                }
+            }
+            Ok(query.order_and_limit(solutions))
+        }
+
+        async fn count(&self) -> Result<usize> {
+            let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM solutions")
+                .fetch_one(self.pool().await?)
+                .await
+                .map_err(|e| storage_error("count solutions", e))?;
+            Ok(usize::try_from(count).unwrap_or_default())
+        }
+
+        async fn add_feedback(&self, solution_id: &str, feedback: String) -> Result<()> {
+            sqlx::query("INSERT INTO feedback (solution_id, feedback) VALUES (?, ?)")
+                .bind(solution_id)
+                .bind(feedback)
+                .execute(self.pool().await?)
+                .await
+                .map_err(|e| storage_error("add feedback", e))?;
+            Ok(())
+        }
+
+        async fn feedback(&self, solution_id: &str) -> Result<Vec<String>> {
+            sqlx::query_scalar("SELECT feedback FROM feedback WHERE solution_id = ? ORDER BY seq")
+                .bind(solution_id)
+                .fetch_all(self.pool().await?)
+                .await
+                .map_err(|e| storage_error("get feedback", e))
+        }
+
+        async fn clear(&self) -> Result<()> {
+            sqlx::raw_sql("DELETE FROM solutions; DELETE FROM feedback;")
+                .execute(self.pool().await?)
+                .await
+                .map_err(|e| storage_error("clear workspace", e))?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "sled")]
+pub use sled_store::SledWorkspaceStore;
+
+#[cfg(feature = "sled")]
+mod sled_store {
+    use super::{WorkspaceStore, decode, encode, next_version, not_found};
+    use crate::types::Solution;
+    use crate::{MarsError, Result};
+    use async_trait::async_trait;
+    use std::path::Path;
+
+    fn storage_error(action: &str, e: sled::Error) -> MarsError {
+        MarsError::StorageError(format!("Failed to {action}: {e}"))
+    }
+
+    /// Feedback key: the solution ID, a 0 separator, then a sequence number
+    fn feedback_key(solution_id: &str, seq: u64) -> Vec<u8> {
+        let mut key = feedback_prefix(solution_id);
+        key.extend_from_slice(&seq.to_be_bytes());
+        key
+    }
+
+    fn feedback_prefix(solution_id: &str) -> Vec<u8> {
+        let mut prefix = solution_id.as_bytes().to_vec();
+        prefix.push(0);
+        prefix
+    }
+
+    /// Store backed by a sled embedded database directory
+    ///
+    /// Solutions are JSON keyed by ID; a second tree keyed by a
+    /// monotonically increasing sequence number keeps insertion order.
+    pub struct SledWorkspaceStore {
+        db: sled::Db,
+        solutions: sled::Tree,
+        order: sled::Tree,
+        feedback: sled::Tree,
+    }
+
+    impl SledWorkspaceStore {
+        /// Open (or create) the database at `path`
+        ///
+        /// Every write is flushed before it returns, so sled's periodic
+        /// flusher thread is not started; with no thread holding the
+        /// database, dropping the store releases its lock on `path`.
+        pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+            let db = sled::Config::new()
+                .path(path)
+                .flush_every_ms(None)
+                .open()
+                .map_err(|e| storage_error("open sled workspace", e))?;
+            let tree = |name: &str| {
+                db.open_tree(name)
+                    .map_err(|e| storage_error("open sled workspace", e))
+            };
+            Ok(Self {
+                solutions: tree("solutions")?,
+                order: tree("order")?,
+                feedback: tree("feedback")?,
+                db,
+            })
+        }
+
+        /// Flush outstanding writes and release the database
+        pub async fn close(self) -> Result<()> {
+            self.flush().await
+        }
+
+        /// Flush writes to disk so they survive a crash
+        async fn flush(&self) -> Result<()> {
+            self.db
+                .flush_async()
+                .await
+                .map(|_| ())
+                .map_err(|e| storage_error("flush sled workspace", e))
+        }
+    }
+
+    #[async_trait]
+    impl WorkspaceStore for SledWorkspaceStore {
+        async fn add(&self, solution: Solution) -> Result<()> {
+            let data = encode(&solution)?;
+            let inserted = self
+                .solutions
+                .compare_and_swap(
+                    solution.id.as_bytes(),
+                    None as Option<&[u8]>,
+                    Some(data.as_bytes()),
+                )
+                .map_err(|e| storage_error("add solution", e))?;
+            if inserted.is_err() {
+                return Err(MarsError::StorageError(format!(
+                    "Solution {} already exists",
+                    solution.id
+                )));
+            }
+
+            let seq = self
+                .db
+                .generate_id()
+                .map_err(|e| storage_error("add solution", e))?;
+            self.order
+                .insert(seq.to_be_bytes(), solution.id.as_bytes())
+                .map_err(|e| storage_error("add solution", e))?;
+            self.flush().await
+        }
+
+        async fn get(&self, id: &str) -> Result<Option<Solution>> {
+            self.solutions
+                .get(id.as_bytes())
+                .map_err(|e| storage_error("get solution", e))?
+                .map(|data| decode(&data))
+                .transpose()
+        }
+
+        async fn update(&self, solution: Solution) -> Result<()> {
+            let id = solution.id.clone();
+            let expected = solution.version;
+            let Some(current) = self
+                .solutions
+                .get(id.as_bytes())
+                .map_err(|e| storage_error("update solution", e))?
+            else {
+                return Err(not_found(&id));
+            };
+            let next = next_version(&decode(&current)?, solution)?;
+
+            let swapped = self
+                .solutions
+                .compare_and_swap(
+                    id.as_bytes(),
+                    Some(current),
+                    Some(encode(&next)?.as_bytes()),
+                )
+                .map_err(|e| storage_error("update solution", e))?;
+            if swapped.is_err() {
+                // Another writer got in between the read and the swap
+                return Err(match self.get(&id).await? {
+                    Some(stored) => MarsError::VersionConflict {
+                        id,
+                        expected,
+                        found: stored.version,
+                    },
+                    None => not_found(&id),
+                });
+            }
+            self.flush().await
+        }
+
+        async fn remove(&self, id: &str) -> Result<()> {
+            self.solutions
+                .remove(id.as_bytes())
+                .map_err(|e| storage_error("remove solution", e))?;
+            for entry in self.order.iter() {
+                let (seq, stored_id) = entry.map_err(|e| storage_error("remove solution", e))?;
+                if stored_id == id.as_bytes() {
+                    self.order
+                        .remove(seq)
+                        .map_err(|e| storage_error("remove solution", e))?;
+                    break;
+                }
+            }
+            for key in self.feedback.scan_prefix(feedback_prefix(id)).keys() {
+                let key = key.map_err(|e| storage_error("remove solution", e))?;
+                self.feedback
+                    .remove(key)
+                    .map_err(|e| storage_error("remove solution", e))?;
+            }
+            self.flush().await
+        }
+
+        async fn all(&self) -> Result<Vec<Solution>> {
+            let mut solutions = Vec::new();
+            for entry in self.order.iter() {
+                let (_seq, id) = entry.map_err(|e| storage_error("list solutions", e))?;
+                if let Some(data) = self
+                    .solutions
+                    .get(&id)
+                    .map_err(|e| storage_error("list solutions", e))?
+                {
+                    solutions.push(decode(&data)?);
+                }
+            }
+            Ok(solutions)
+        }
+
+        async fn count(&self) -> Result<usize> {
+            Ok(self.solutions.len())
+        }
+
+        async fn add_feedback(&self, solution_id: &str, feedback: String) -> Result<()> {
+            let seq = self
+                .db
+                .generate_id()
+                .map_err(|e| storage_error("add feedback", e))?;
+            self.feedback
+                .insert(feedback_key(solution_id, seq), feedback.as_bytes())
+                .map_err(|e| storage_error("add feedback", e))?;
+            self.flush().await
+        }
+
+        async fn feedback(&self, solution_id: &str) -> Result<Vec<String>> {
+            self.feedback
+                .scan_prefix(feedback_prefix(solution_id))
+                .values()
+                .map(|value| {
+                    let value = value.map_err(|e| storage_error("get feedback", e))?;
+                    Ok(String::from_utf8_lossy(&value).into_owned())
+                })
+                .collect()
+        }
+
+        async fn clear(&self) -> Result<()> {
+            for tree in [&self.solutions, &self.order, &self.feedback] {
+                tree.clear()
+                    .map_err(|e| storage_error("clear workspace", e))?;
+            }
+            self.flush().await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solution(agent_id: &str, answer: &str) -> Solution {
+        Solution::new(
+            agent_id.to_string(),
+            "reasoning".to_string(),
+            answer.to_string(),
+            0.5,
+            100,
+        )
+    }
+
+    /// Exercise the full store contract against any backend
+    async fn check_store(store: &dyn WorkspaceStore) {
+        let first = solution("agent1", "4");
+        let mut second = solution("agent2", "5");
+        second.created_at = first.created_at + chrono::Duration::seconds(1);
+        let mut plan = solution("planner", "");
+        plan.phase = GenerationPhase::Planned;
+        plan.created_at = first.created_at + chrono::Duration::seconds(2);
+        store.add(first.clone()).await.unwrap();
+        store.add(second.clone()).await.unwrap();
+        store.add(plan.clone()).await.unwrap();
+
+        assert_eq!(store.count().await.unwrap(), 3);
+        let ids: Vec<String> = store
+            .all()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(
+            ids,
+            vec![first.id.clone(), second.id.clone(), plan.id.clone()]
+        );
+        assert_eq!(
+            store.get(&first.id).await.unwrap().unwrap().answer,
+            "4".to_string()
+        );
+        assert!(store.get("missing").await.unwrap().is_none());
+
+        second.add_verification_pass(0.9);
+        second.add_verification_pass(0.9);
+        store.update(second.clone()).await.unwrap();
+        assert!(store.update(solution("agent3", "6")).await.is_err());
+        // A second update from the same, now outdated, copy is a conflict
+        assert!(matches!(
+            store.update(second.clone()).await,
+            Err(crate::MarsError::VersionConflict {
+                expected: 0,
+                found: 1,
+                ..
+            })
+        ));
+        assert_eq!(store.get(&second.id).await.unwrap().unwrap().version, 1);
+
+        let verified = store
+            .query(&SolutionQuery::new().verified(true))
+            .await
+            .unwrap();
+        assert_eq!(verified.len(), 1);
+        assert_eq!(verified[0].id, second.id);
+        let candidates = store
+            .query(&SolutionQuery::new().candidates())
+            .await
+            .unwrap();
+        assert_eq!(candidates.len(), 2);
+        let by_agent = store
+            .query(&SolutionQuery::new().by_agent("agent1").limit(1))
+            .await
+            .unwrap();
+        assert_eq!(by_agent.len(), 1);
+        assert_eq!(by_agent[0].id, first.id);
+        assert_eq!(
+            store
+                .query(&SolutionQuery::new().limit(2))
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        let best = store
+            .query(
+                &SolutionQuery::new()
+                    .order_by(SolutionOrder::ScoreDescending)
+                    .limit(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(best[0].id, second.id);
+        let newest = store
+            .query(&SolutionQuery::new().order_by(SolutionOrder::NewestFirst))
+            .await
+            .unwrap();
+        assert_eq!(newest[0].id, plan.id);
+        let recent = store
+            .query(&SolutionQuery::new().created_after(second.created_at))
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 2);
+
+        store
+            .add_feedback(&first.id, "Step 2 is wrong".to_string())
+            .await
+            .unwrap();
+        store
+            .add_feedback(&first.id, "Check the sign".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            store.feedback(&first.id).await.unwrap(),
+            vec!["Step 2 is wrong", "Check the sign"]
+        );
+        assert!(store.feedback(&second.id).await.unwrap().is_empty());
+
+        store.remove(&first.id).await.unwrap();
+        store.remove("missing").await.unwrap();
+        assert!(store.get(&first.id).await.unwrap().is_none());
+        assert!(store.feedback(&first.id).await.unwrap().is_empty());
+        assert_eq!(store.count().await.unwrap(), 2);
+        assert_eq!(store.all().await.unwrap()[0].id, second.id);
+
+        store.clear().await.unwrap();
+        assert_eq!(store.count().await.unwrap(), 0);
+        assert!(store.feedback(&first.id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        check_store(&MemoryWorkspaceStore::new()).await;
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let mut solution = solution("agent1", "4");
+        solution.verification_score = 0.7;
+        assert!(SolutionQuery::new().matches(&solution));
+        assert!(SolutionQuery::new().min_score(0.5).matches(&solution));
+        assert!(!SolutionQuery::new().min_score(0.8).matches(&solution));
+        assert!(!SolutionQuery::new().by_agent("agent2").matches(&solution));
+        assert!(
+            SolutionQuery::new()
+                .in_phase(GenerationPhase::Initial)
+                .matches(&solution)
+        );
+
+        let created = solution.created_at;
+        let second = chrono::Duration::seconds(1);
+        assert!(
+            SolutionQuery::new()
+                .created_after(created)
+                .created_before(created + second)
+                .matches(&solution)
+        );
+        assert!(
+            !SolutionQuery::new()
+                .created_before(created)
+                .matches(&solution)
+        );
+        assert!(
+            !SolutionQuery::new()
+                .created_after(created + second)
+                .matches(&solution)
+        );
+    }
+
+    #[test]
+    fn test_order_and_limit() {
+        let scores = [0.2, 0.9, 0.5];
+        let solutions: Vec<_> = scores
+            .iter()
+            .map(|&score| {
+                let mut solution = solution("agent1", "4");
+                solution.verification_score = score;
+                solution
+            })
+            .collect();
+
+        let inserted = SolutionQuery::new()
+            .limit(2)
+            .order_and_limit(solutions.clone());
+        assert_eq!(inserted[1].verification_score, 0.9);
+        let best = SolutionQuery::new()
+            .order_by(SolutionOrder::ScoreDescending)
+            .limit(2)
+            .order_and_limit(solutions);
+        let best_scores: Vec<_> = best.iter().map(|s| s.verification_score).collect();
+        assert_eq!(best_scores, vec![0.9, 0.5]);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_sqlite_store_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("workspace.db");
+        check_store(&SqliteWorkspaceStore::open(&path)).await;
+
+        let added = solution("agent1", "4");
+        SqliteWorkspaceStore::open(&path)
+            .add(added.clone())
+            .await
+            .unwrap();
+        let reopened = SqliteWorkspaceStore::open(&path);
+        assert_eq!(reopened.all().await.unwrap()[0].id, added.id);
+        assert!(reopened.add(added).await.is_err());
+    }
+
+    #[cfg(feature = "sled")]
+    #[tokio::test]
+    async fn test_sled_store_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("workspace.sled");
+        let store = SledWorkspaceStore::open(&path).unwrap();
+        check_store(&store).await;
+        store.close().await.unwrap();
+
+        let added = solution("agent1", "4");
+        {
+            let store = SledWorkspaceStore::open(&path).unwrap();
+            store.add(added.clone()).await.unwrap();
+        }
+        let reopened = SledWorkspaceStore::open(&path).unwrap();
+        assert_eq!(reopened.all().await.unwrap()[0].id, added.id);
+        assert!(reopened.add(added).await.is_err());
+        assert!(SledWorkspaceStore::open(&path).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 //! Integration tests for MCTS implementation
 
 use code_mars::{
-    config::MarsConfig, mcts::*, Result, LLMProvider,
+    config::MarsConfig, mcts::*, CompletionResponse, Result, LLMProvider,
 };
 
 /// Mock LLM provider for MCTS testing
@@ -13,9 +13,9 @@ impl LLMProvider for MockMCTSProvider {
         &self,
         _prompt: &str,
         _system: Option<&str>,
-    ) -> Result<String> {
+    ) -> Result<CompletionResponse> {
         // Return a mock response
-        Ok("This is a mock MCTS response".to_string())
+        Ok("This is a mock MCTS response".into())
     }
 
     async fn stream(
@@ -4,7 +4,7 @@
 
 use code_mars::{
     config::MarsConfig, provider_config::ProviderSpec,
-    LLMProvider, Result,
+    CompletionResponse, LLMProvider, Result,
 };
 
 /// Mock LLM provider for testing
@@ -18,14 +18,14 @@ impl LLMProvider for MockProvider {
         &self,
         prompt: &str,
         system_prompt: Option<&str>,
-    ) -> Result<String> {
+    ) -> Result<CompletionResponse> {
         let mut response = format!("Mock response to prompt: {}", &prompt[..50.min(prompt.len())]);
 
         if let Some(system) = system_prompt {
             response = format!("{}System: {}\n{}", response, system, response);
         }
 
-        Ok(response)
+        Ok(response.into())
     }
 
     async fn stream(
@@ -33,7 +33,7 @@ impl LLMProvider for MockProvider {
         prompt: &str,
         system_prompt: Option<&str>,
     ) -> Result<code_mars::model_router::ModelStream> {
-        let content = self.complete(prompt, system_prompt).await?;
+        let content = self.complete(prompt, system_prompt).await?.text;
         Ok(code_mars::model_router::ModelStream::new(content))
     }
 
@@ -80,7 +80,7 @@ async fn test_mock_provider_complete() {
     let response = mock.complete("What is 2+2?", None).await;
     assert!(response.is_ok());
 
-    let content = response.unwrap();
+    let content = response.unwrap().text;
     assert!(content.contains("Mock response"));
 }
 
@@ -95,7 +95,7 @@ async fn test_mock_provider_with_system_prompt() {
         .await;
     assert!(response.is_ok());
 
-    let content = response.unwrap();
+    let content = response.unwrap().text;
     assert!(content.contains("System:"));
 }
 
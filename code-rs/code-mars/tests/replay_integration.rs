@@ -0,0 +1,64 @@
+//! Coordinator runs replayed from recorded provider calls
+//!
+//! Requires the `test-utils` feature.
+
+use code_mars::{
+    CompletionResponse, LLMProvider, MarsConfig, MarsCoordinator, RecordingProvider,
+    ReplayProvider, Result, model_router::ModelStream, prompts,
+};
+use std::sync::Arc;
+
+/// Stand-in for a live model: approves solutions and answers 4
+struct ScriptedModel;
+
+#[async_trait::async_trait]
+impl LLMProvider for ScriptedModel {
+    async fn complete(
+        &self,
+        _prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<CompletionResponse> {
+        let text = if system_prompt == Some(prompts::VERIFICATION_SYSTEM_PROMPT) {
+            "RESULT: CORRECT\nSCORE: 0.9\nFEEDBACK: Looks right."
+        } else {
+            "<think>2 + 2 = 4</think>\n4"
+        };
+        Ok(CompletionResponse::new(text).with_usage(20, 10))
+    }
+
+    async fn stream(&self, prompt: &str, system_prompt: Option<&str>) -> Result<ModelStream> {
+        let content = self.complete(prompt, system_prompt).await?.text;
+        Ok(ModelStream::new(content))
+    }
+
+    fn provider_name(&self) -> &str {
+        "scripted"
+    }
+
+    fn model_name(&self) -> &str {
+        "scripted-model"
+    }
+}
+
+#[tokio::test]
+async fn test_coordinator_run_replays_from_cassette() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("coordinator_run.json");
+
+    let recorder = Arc::new(RecordingProvider::new(Arc::new(ScriptedModel), &path));
+    let recorded = MarsCoordinator::with_provider(MarsConfig::default(), recorder.clone())
+        .run("What is 2+2?")
+        .await
+        .unwrap();
+    assert!(!recorder.cassette().interactions.is_empty());
+
+    let replay = Arc::new(ReplayProvider::open(&path).unwrap());
+    let replayed = MarsCoordinator::with_provider(MarsConfig::default(), replay)
+        .run("What is 2+2?")
+        .await
+        .unwrap();
+
+    assert_eq!(replayed.answer, recorded.answer);
+    assert_eq!(replayed.all_solutions.len(), recorded.all_solutions.len());
+    assert_eq!(replayed.total_tokens, recorded.total_tokens);
+}